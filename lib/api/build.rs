@@ -1,9 +1,9 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::configure()
         .build_server(true)
-        .build_client(false)
+        .build_client(true)
         .compile(
-            &["proto/vectx.proto"],
+            &["proto/vectx.proto", "proto/health.proto"],
             &["proto"],
         )?;
     Ok(())
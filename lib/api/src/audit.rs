@@ -0,0 +1,180 @@
+//! Recording mutating requests in the audit log
+//!
+//! [`audit_trail`] records each REST request that needs write or admin
+//! access once access control has let it through, with the point ids its
+//! body or path names. gRPC handlers that change anything hold a
+//! [`GrpcAudit`] while they run. Nothing is recorded unless an
+//! [`AuditLog`](vectx_storage::AuditLog) is set on the storage manager.
+//! Entries are written on the blocking pool, off the async workers.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::CONTENT_ENCODING;
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::web;
+use serde_json::Value;
+use std::sync::Arc;
+use vectx_storage::{AuditEntry, AuditLog, Permission, StorageManager};
+use crate::auth;
+use crate::content::has_msgpack_body;
+
+/// Middleware appending an entry for each REST request that may change
+/// something to the audit log
+pub(crate) async fn audit_trail(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let storage = req.app_data::<web::Data<Arc<StorageManager>>>().cloned();
+    let log = storage.as_ref().and_then(|storage| storage.audit_log());
+    let required = auth::required_access(req.method(), req.path())
+        .filter(|required| req.method() != Method::GET && required.permission != Permission::Read);
+    let (Some(storage), Some(log), Some(required)) = (storage, log, required) else {
+        return next.call(req).await;
+    };
+
+    let mut entry = AuditEntry {
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        key_id: storage.access_control().identify(auth::header_key(req.headers())),
+        api: "rest".to_string(),
+        operation: format!("{} {}", req.method(), req.path()),
+        collection: required.collection.map(|name| storage.resolve_collection_name(&name)),
+        points: Vec::new(),
+        success: false,
+        status: None,
+    };
+
+    let segments: Vec<&str> = req.path().trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["collections", _, "points", id] if req.method() == Method::DELETE => {
+            entry.points.push(point_id_value(id));
+        }
+        ["collections", _, "points", ..] => {
+            // Read the body here to take its ids, then hand it on decoded
+            match req.extract::<web::Bytes>().await {
+                Ok(bytes) => {
+                    let body = if has_msgpack_body(req.request()) {
+                        rmp_serde::from_slice::<Value>(&bytes).ok()
+                    } else {
+                        serde_json::from_slice::<Value>(&bytes).ok()
+                    };
+                    entry.points = body.as_ref().map(point_ids).unwrap_or_default();
+                    req.headers_mut().remove(CONTENT_ENCODING);
+                    req.set_payload(Payload::from(bytes));
+                }
+                Err(e) => {
+                    entry.status = Some(e.as_response_error().status_code().as_u16());
+                    record(log, entry).await;
+                    return Err(e);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    entry.status = Some(status.as_u16());
+    entry.success = status.is_success();
+    record(log, entry).await;
+    result
+}
+
+/// Append `entry` to `log` on the blocking pool
+async fn record(log: Arc<AuditLog>, entry: AuditEntry) {
+    if let Err(e) = tokio::task::spawn_blocking(move || log.record(&entry)).await {
+        tracing::warn!("Failed to write audit entry: {}", e);
+    }
+}
+
+/// Ids in the `points` of a request body, given as ids or as points with
+/// an `id`, including those of the operations of a batch update
+fn point_ids(body: &Value) -> Vec<Value> {
+    let mut ids: Vec<Value> = body.get("points")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|point| match point {
+            Value::Object(point) => point.get("id").cloned(),
+            id => Some(id.clone()),
+        })
+        .collect();
+    let operations = body.get("operations").and_then(Value::as_array).into_iter().flatten();
+    for operation in operations.filter_map(Value::as_object) {
+        ids.extend(operation.values().flat_map(point_ids));
+    }
+    ids
+}
+
+/// A point id from a path or a gRPC message as it would be written in JSON
+fn point_id_value(id: &str) -> Value {
+    match id.parse::<u64>() {
+        Ok(number) => Value::from(number),
+        Err(_) => Value::from(id),
+    }
+}
+
+/// Audit entry of a gRPC request, recorded when the handler returns. An
+/// entry dropped before [`GrpcAudit::finish`], e.g. by `?`, is recorded as
+/// failed.
+pub(crate) struct GrpcAudit {
+    log: Option<Arc<AuditLog>>,
+    entry: AuditEntry,
+}
+
+impl GrpcAudit {
+    /// Start the entry of the `operation` (`Service/Method`) `request`
+    /// makes on `collection`
+    pub(crate) fn start<T>(
+        storage: &StorageManager,
+        request: &tonic::Request<T>,
+        operation: &str,
+        collection: Option<&str>,
+    ) -> Self {
+        let log = storage.audit_log();
+        let entry = AuditEntry {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            key_id: log.as_ref().and_then(|_| storage.access_control().identify(auth::grpc_key(request))),
+            api: "grpc".to_string(),
+            operation: operation.to_string(),
+            collection: collection.map(|name| storage.resolve_collection_name(name)),
+            points: Vec::new(),
+            success: false,
+            status: None,
+        };
+        Self { log, entry }
+    }
+
+    /// Note the ids of the points the request names
+    pub(crate) fn points(&mut self, ids: impl IntoIterator<Item = String>) {
+        if self.log.is_some() {
+            self.entry.points.extend(ids.into_iter().map(|id| point_id_value(&id)));
+        }
+    }
+
+    /// Record the outcome of the handler and pass it on
+    #[allow(clippy::result_large_err)]
+    pub(crate) async fn finish<R>(mut self, result: Result<R, tonic::Status>) -> Result<R, tonic::Status> {
+        self.entry.success = result.is_ok();
+        if let Some(log) = self.log.take() {
+            record(log, self.entry.clone()).await;
+        }
+        result
+    }
+}
+
+impl Drop for GrpcAudit {
+    /// Record a handler that returned early as failed, without waiting for
+    /// the write
+    fn drop(&mut self) {
+        let Some(log) = self.log.take() else { return };
+        let entry = self.entry.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(move || log.record(&entry))),
+            Err(_) => log.record(&entry),
+        }
+    }
+}
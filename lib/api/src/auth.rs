@@ -0,0 +1,253 @@
+//! API key enforcement for the REST and gRPC APIs
+//!
+//! Keys are read from the `api-key` header (as Qdrant clients send it) or an
+//! `Authorization: Bearer` header. REST requests are classified by route into
+//! the collection they touch and the [`Permission`] they need; gRPC handlers
+//! call [`authorize`] with the collection from the request message.
+//!
+//! A JWT may carry a forced payload filter. It is handed to handlers as a
+//! [`ClaimFilter`] (a request extension in REST), and only the operations
+//! that apply it — search, query (with their batch and group forms),
+//! discover, scroll, count, retrieve, delete, aggregate and the distance
+//! matrix — accept such tokens; everything else is refused.
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::HeaderMap;
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use std::sync::Arc;
+use vectx_core::{Filter, FilterCondition, PayloadFilter, Point};
+use vectx_storage::{AccessError, AccessGrant, Permission, StorageManager};
+
+const API_KEY_HEADER: &str = "api-key";
+
+/// Access a REST request needs: the collection it is scoped to (None for
+/// instance-wide endpoints) and the permission level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequiredAccess {
+    pub collection: Option<String>,
+    pub permission: Permission,
+}
+
+impl RequiredAccess {
+    fn global(permission: Permission) -> Option<Self> {
+        Some(Self { collection: None, permission })
+    }
+
+    fn collection(name: &str, permission: Permission) -> Option<Self> {
+        Some(Self { collection: Some(name.to_string()), permission })
+    }
+}
+
+/// Classify a REST route. Returns None for public endpoints.
+pub fn required_access(method: &Method, path: &str) -> Option<RequiredAccess> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let read_or = |permission| if method == Method::GET { Permission::Read } else { permission };
+
+    match segments.as_slice() {
+        [""] | ["healthz"] | ["livez"] | ["readyz"] | ["openapi.json"] => None,
+        ["dashboard", ..] => None,
+        // Collections come in the body; the handler calls authorize_collection
+        ["search"] if method == Method::POST => None,
+        ["admin", ..] | ["snapshots", ..] => RequiredAccess::global(Permission::Admin),
+        ["issues"] => RequiredAccess::global(read_or(Permission::Admin)),
+        ["collections", "aliases"] => RequiredAccess::global(Permission::Admin),
+        ["collections", name] => RequiredAccess::collection(name, read_or(Permission::Admin)),
+        ["collections", name, "snapshots", ..] => RequiredAccess::collection(name, read_or(Permission::Admin)),
+        ["collections", name, "shards", _, "snapshots", ..] => {
+            RequiredAccess::collection(name, read_or(Permission::Admin))
+        }
+        ["collections", name, "jobs", ..] => RequiredAccess::collection(name, Permission::Admin),
+        ["collections", name, "unload" | "load" | "reindex" | "verify"] => RequiredAccess::collection(name, Permission::Admin),
+        ["collections", name, "hnsw", "graph"] => RequiredAccess::collection(name, Permission::Admin),
+        ["collections", name, "index", "text", "rebuild"] => RequiredAccess::collection(name, Permission::Admin),
+        ["collections", name, "ef_tuning"] => RequiredAccess::collection(name, read_or(Permission::Admin)),
+        ["collections", name, "points", rest @ ..] => {
+            let read_only = method == Method::GET
+                || (method == Method::POST
+                    && matches!(
+                        rest.first(),
+                        None | Some(&"scroll") | Some(&"search") | Some(&"query") | Some(&"count")
+                            | Some(&"recommend") | Some(&"discover") | Some(&"hybrid")
+                    ));
+            let permission = if read_only { Permission::Read } else { Permission::Write };
+            RequiredAccess::collection(name, permission)
+        }
+        ["collections", name, "facet" | "aggregate"] => RequiredAccess::collection(name, Permission::Read),
+        ["collections", name, ..] => RequiredAccess::collection(name, read_or(Permission::Write)),
+        _ => RequiredAccess::global(read_or(Permission::Admin)),
+    }
+}
+
+/// Payload filter forced by a JWT claim
+pub struct ClaimFilter {
+    condition: FilterCondition,
+    filter: PayloadFilter,
+}
+
+impl ClaimFilter {
+    fn from_grant(grant: AccessGrant) -> Option<Self> {
+        let conditions: Vec<FilterCondition> = grant.payload?
+            .into_iter()
+            .map(|(field, value)| FilterCondition::Equals { field, value })
+            .collect();
+        let condition = FilterCondition::And(conditions);
+        Some(Self { filter: PayloadFilter::new(condition.clone()), condition })
+    }
+
+    /// Whether `point` is visible to the token
+    pub fn matches(&self, point: &Point) -> bool {
+        self.filter.matches(point)
+    }
+
+    /// Combine a request's own filter condition with the claim
+    pub fn restrict(&self, condition: Option<FilterCondition>) -> FilterCondition {
+        match condition {
+            Some(condition) => FilterCondition::And(vec![condition, self.condition.clone()]),
+            None => self.condition.clone(),
+        }
+    }
+}
+
+/// REST routes whose handlers apply a [`ClaimFilter`]
+fn supports_claim_filter(method: &Method, path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["collections", _] | ["collections", _, "exists" | "export"] => method == Method::GET,
+        ["collections", _, "points"] => method == Method::POST,
+        ["collections", _, "aggregate"] => method == Method::POST,
+        ["collections", _, "points", "search" | "query" | "hybrid" | "recommend" | "discover" | "scroll" | "count" | "delete"] => method == Method::POST,
+        ["collections", _, "points", "search" | "query" | "discover", "batch"] => method == Method::POST,
+        ["collections", _, "points", "search" | "query", "groups"] => method == Method::POST,
+        ["collections", _, "points", "search", "matrix", "pairs" | "offsets"] => method == Method::POST,
+        ["collections", _, "points", _] => method == Method::GET || method == Method::DELETE,
+        _ => false,
+    }
+}
+
+/// Middleware rejecting REST requests whose key does not grant the access the
+/// route needs
+pub async fn rest_access_control(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let storage = req.app_data::<web::Data<Arc<StorageManager>>>().cloned();
+    let required = required_access(req.method(), req.path());
+
+    if let (Some(storage), Some(required)) = (storage, required) {
+        let access = storage.access_control();
+        if access.is_enabled() {
+            let key = header_key(req.headers());
+            let collection = required.collection.map(|name| storage.resolve_collection_name(&name));
+
+            match access.check(key, collection.as_deref(), required.permission) {
+                Ok(grant) => {
+                    if let Some(claim) = ClaimFilter::from_grant(grant) {
+                        if !supports_claim_filter(req.method(), req.path()) {
+                            let response = access_error_response(&AccessError::Forbidden);
+                            return Ok(req.into_response(response).map_into_right_body());
+                        }
+                        req.extensions_mut().insert(Arc::new(claim));
+                    }
+                }
+                Err(e) => {
+                    let response = access_error_response(&e);
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+            }
+        }
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+pub(crate) fn access_error_response(error: &AccessError) -> HttpResponse {
+    let body = serde_json::json!({
+        "status": {
+            "error": error.to_string()
+        },
+        "time": 0.0
+    });
+    match error {
+        AccessError::Unauthenticated => HttpResponse::Unauthorized().json(body),
+        AccessError::Forbidden => HttpResponse::Forbidden().json(body),
+    }
+}
+
+/// Check a REST request's key against `required` on `collection`, for
+/// handlers whose collections are not in the path. Returns the forced payload
+/// filter of the token, if any.
+pub fn authorize_collection(
+    storage: &StorageManager,
+    req: &HttpRequest,
+    collection: &str,
+    required: Permission,
+) -> Result<Option<ClaimFilter>, AccessError> {
+    let access = storage.access_control();
+    if !access.is_enabled() {
+        return Ok(None);
+    }
+    let collection = storage.resolve_collection_name(collection);
+    let grant = access.check(header_key(req.headers()), Some(&collection), required)?;
+    Ok(ClaimFilter::from_grant(grant))
+}
+
+/// Check a gRPC request's key against `required` on `collection`. Tokens
+/// with a forced payload filter are refused; handlers that apply one use
+/// [`authorize_filtered`].
+#[allow(clippy::result_large_err)]
+pub fn authorize<T>(
+    storage: &StorageManager,
+    request: &tonic::Request<T>,
+    collection: Option<&str>,
+    required: Permission,
+) -> Result<(), tonic::Status> {
+    match authorize_filtered(storage, request, collection, required)? {
+        Some(_) => Err(tonic::Status::permission_denied(AccessError::Forbidden.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Like [`authorize`], but accepts tokens with a forced payload filter and
+/// returns it for the handler to apply
+#[allow(clippy::result_large_err)]
+pub fn authorize_filtered<T>(
+    storage: &StorageManager,
+    request: &tonic::Request<T>,
+    collection: Option<&str>,
+    required: Permission,
+) -> Result<Option<ClaimFilter>, tonic::Status> {
+    let access = storage.access_control();
+    if !access.is_enabled() {
+        return Ok(None);
+    }
+
+    let collection = collection.map(|name| storage.resolve_collection_name(name));
+
+    match access.check(grpc_key(request), collection.as_deref(), required) {
+        Ok(grant) => Ok(ClaimFilter::from_grant(grant)),
+        Err(e @ AccessError::Unauthenticated) => Err(tonic::Status::unauthenticated(e.to_string())),
+        Err(e @ AccessError::Forbidden) => Err(tonic::Status::permission_denied(e.to_string())),
+    }
+}
+
+pub(crate) fn grpc_key<T>(request: &tonic::Request<T>) -> Option<&str> {
+    let metadata = request.metadata();
+    metadata.get(API_KEY_HEADER)
+        .or_else(|| metadata.get("authorization"))
+        .and_then(|v| v.to_str().ok())
+        .map(strip_bearer)
+}
+
+pub(crate) fn header_key(headers: &HeaderMap) -> Option<&str> {
+    headers.get(API_KEY_HEADER)
+        .or_else(|| headers.get(actix_web::http::header::AUTHORIZATION))
+        .and_then(|v| v.to_str().ok())
+        .map(strip_bearer)
+}
+
+fn strip_bearer(value: &str) -> &str {
+    value.strip_prefix("Bearer ").unwrap_or(value).trim()
+}
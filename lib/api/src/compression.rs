@@ -0,0 +1,183 @@
+//! Compression of REST responses and gRPC messages
+//!
+//! Responses are compressed with an encoding the client asks for through
+//! `Accept-Encoding` (REST) or `grpc-accept-encoding` (gRPC), among those
+//! enabled with [`configure`]. Compressed request bodies are always
+//! accepted: actix decodes `Content-Encoding` before the JSON extractor, and
+//! the gRPC services take gzip and zstd messages. Body size limits apply to
+//! the decoded body, so a small compressed upload cannot expand without
+//! bound.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, ACCEPT_ENCODING};
+use actix_web::middleware::Next;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tonic::codec::CompressionEncoding;
+
+/// A response encoding that can be turned on or off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    Zstd,
+}
+
+impl Encoding {
+    /// Name in `Accept-Encoding` and `grpc-accept-encoding`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    pub(crate) fn grpc(self) -> CompressionEncoding {
+        match self {
+            Self::Gzip => CompressionEncoding::Gzip,
+            Self::Zstd => CompressionEncoding::Zstd,
+        }
+    }
+}
+
+/// Default of [`CompressionConfig::max_body_bytes`], as in Qdrant
+pub const DEFAULT_MAX_BODY_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressionConfig {
+    /// Encodings responses may be compressed with; none turns response
+    /// compression off
+    pub encodings: Vec<Encoding>,
+    /// Largest REST JSON body or gRPC message accepted, once decompressed
+    pub max_body_bytes: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self { encodings: vec![Encoding::Zstd, Encoding::Gzip], max_body_bytes: DEFAULT_MAX_BODY_BYTES }
+    }
+}
+
+impl CompressionConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.encodings.is_empty()
+    }
+
+    /// Whether responses may use `coding`, a name from `Accept-Encoding`
+    fn allows(&self, coding: &str) -> bool {
+        self.encodings.iter().any(|e| coding.eq_ignore_ascii_case(e.as_str()))
+            || coding.eq_ignore_ascii_case("x-gzip") && self.encodings.contains(&Encoding::Gzip)
+    }
+
+    /// `Accept-Encoding` narrowed to the enabled encodings. A wildcard
+    /// stands for each of them, keeping its quality; `identity` is kept.
+    /// `None` when nothing but identity is left.
+    pub fn restrict_accept_encoding(&self, header: &str) -> Option<String> {
+        let mut kept = Vec::new();
+        for item in header.split(',') {
+            let item = item.trim();
+            let (coding, params) = match item.split_once(';') {
+                Some((coding, params)) => (coding.trim(), Some(params.trim())),
+                None => (item, None),
+            };
+            let with_params = |coding: &str| match params {
+                Some(params) => format!("{};{}", coding, params),
+                None => coding.to_string(),
+            };
+            if coding == "*" {
+                kept.extend(self.encodings.iter().map(|e| with_params(e.as_str())));
+            } else if coding.eq_ignore_ascii_case("identity") || self.allows(coding) {
+                kept.push(with_params(coding));
+            }
+        }
+        if kept.iter().all(|item| item.to_ascii_lowercase().starts_with("identity")) {
+            return None;
+        }
+        Some(kept.join(", "))
+    }
+}
+
+/// Parses the encodings from a comma-separated list such as `gzip,zstd`,
+/// or `none`
+impl FromStr for CompressionConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut encodings = Vec::new();
+        for name in s.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let encoding = match name.to_ascii_lowercase().as_str() {
+                "none" | "off" => continue,
+                "gzip" => Encoding::Gzip,
+                "zstd" => Encoding::Zstd,
+                other => return Err(format!("Unknown compression '{}', expected gzip, zstd or none", other)),
+            };
+            if !encodings.contains(&encoding) {
+                encodings.push(encoding);
+            }
+        }
+        Ok(Self { encodings, ..Self::default() })
+    }
+}
+
+/// The encodings, as [`FromStr`] takes them
+impl fmt::Display for CompressionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.encodings.is_empty() {
+            return f.write_str("none");
+        }
+        let names: Vec<&str> = self.encodings.iter().map(|e| e.as_str()).collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+static CONFIG: OnceLock<CompressionConfig> = OnceLock::new();
+
+/// Set the configuration before the servers start; the first call wins.
+/// Without it the defaults apply.
+pub fn configure(config: CompressionConfig) {
+    let _ = CONFIG.set(config);
+}
+
+pub fn config() -> &'static CompressionConfig {
+    CONFIG.get_or_init(CompressionConfig::default)
+}
+
+/// Narrows `Accept-Encoding` to the enabled encodings before actix's
+/// `Compress` middleware picks one, so it never answers with brotli or
+/// deflate, or with anything when compression is off
+pub(crate) async fn restrict_accept_encoding(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let restricted = req.headers()
+        .get(ACCEPT_ENCODING)
+        .map(|value| value.to_str().ok().and_then(|value| config().restrict_accept_encoding(value)));
+    match restricted {
+        Some(Some(value)) => match HeaderValue::from_str(&value) {
+            Ok(value) => { req.headers_mut().insert(ACCEPT_ENCODING, value); }
+            Err(_) => { req.headers_mut().remove(ACCEPT_ENCODING); }
+        },
+        Some(None) => { req.headers_mut().remove(ACCEPT_ENCODING); }
+        None => {}
+    }
+    next.call(req).await
+}
+
+/// Let a gRPC service take compressed messages and compress its replies
+/// with the enabled encodings the client accepts. A macro because the
+/// generated servers share no trait for these builders.
+macro_rules! grpc_compression {
+    ($service:expr) => {{
+        let config = $crate::compression::config();
+        let mut service = $service
+            .accept_compressed(tonic::codec::CompressionEncoding::Gzip)
+            .accept_compressed(tonic::codec::CompressionEncoding::Zstd)
+            .max_decoding_message_size(config.max_body_bytes);
+        for encoding in config.encodings.iter() {
+            service = service.send_compressed(encoding.grpc());
+        }
+        service
+    }};
+}
+pub(crate) use grpc_compression;
@@ -0,0 +1,172 @@
+//! Write ordering and read consistency parameters
+//!
+//! Qdrant clients attach `wait` and `ordering` to update requests and
+//! `consistency` to reads as query parameters. vectX runs as a single node
+//! with one replica per collection, so every ordering and consistency level
+//! is already satisfied by applying the operation locally; the parameters are
+//! parsed and validated so clients get a clear error for bad values, and
+//! `wait` is reflected in the update status.
+//!
+//! Once collections have replicas, [`WriteOrdering`] selects which replica
+//! must accept a write first and [`ReadConsistency::required_replicas`] how
+//! many replicas a read has to consult.
+
+use actix_web::{error, HttpRequest, HttpResponse};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer};
+
+/// Which replica an update is routed through
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WriteOrdering {
+    /// Any replica may apply the write (may be reordered)
+    #[default]
+    Weak,
+    /// Routed through a dynamically elected leader
+    Medium,
+    /// Routed through the permanent leader
+    Strong,
+}
+
+/// How many replicas a read consults
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadConsistency {
+    /// Exactly this many replicas (at least 1)
+    Factor(u32),
+    /// More than half of the replicas
+    Majority,
+    /// More than half of the replicas, including the local one
+    Quorum,
+    /// Every replica
+    All,
+}
+
+impl Default for ReadConsistency {
+    fn default() -> Self {
+        Self::Factor(1)
+    }
+}
+
+impl ReadConsistency {
+    /// Replicas that have to answer, given how many the collection has
+    pub fn required_replicas(&self, replicas: usize) -> usize {
+        let replicas = replicas.max(1);
+        match self {
+            Self::Factor(n) => (*n as usize).min(replicas),
+            Self::Majority | Self::Quorum => replicas / 2 + 1,
+            Self::All => replicas,
+        }
+    }
+}
+
+impl std::str::FromStr for ReadConsistency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "majority" => Ok(Self::Majority),
+            "quorum" => Ok(Self::Quorum),
+            "all" => Ok(Self::All),
+            other => match other.parse::<u32>() {
+                Ok(0) => Err("consistency factor must be at least 1".to_string()),
+                Ok(n) => Ok(Self::Factor(n)),
+                Err(_) => Err(format!(
+                    "invalid consistency '{}': expected a number, 'majority', 'quorum' or 'all'",
+                    other
+                )),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ReadConsistency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Query parameters accepted by update endpoints
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct UpdateParams {
+    #[serde(default)]
+    pub wait: Option<bool>,
+    #[serde(default)]
+    pub ordering: Option<WriteOrdering>,
+}
+
+impl UpdateParams {
+    /// Update status to report. Writes are applied before the response is
+    /// sent, so a waiting client sees `completed`.
+    pub fn status(&self) -> &'static str {
+        if self.wait.unwrap_or(false) {
+            "completed"
+        } else {
+            "acknowledged"
+        }
+    }
+}
+
+/// Query parameters accepted by read endpoints
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ReadParams {
+    /// A replica count, `majority`, `quorum` or `all`
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub consistency: Option<ReadConsistency>,
+    /// Seconds a search may run before it is aborted
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// On timeout, answer with the results found so far instead of 408
+    #[serde(default)]
+    pub partial: Option<bool>,
+}
+
+/// Replication settings of a collection create or update request
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ReplicationParams {
+    #[serde(default)]
+    pub shard_number: Option<u32>,
+    #[serde(default)]
+    pub replication_factor: Option<u32>,
+    #[serde(default)]
+    pub write_consistency_factor: Option<u32>,
+}
+
+impl ReplicationParams {
+    /// Check the factors are positive and a write never needs more replicas
+    /// than the collection has
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("shard_number", self.shard_number),
+            ("replication_factor", self.replication_factor),
+            ("write_consistency_factor", self.write_consistency_factor),
+        ] {
+            if value == Some(0) {
+                return Err(format!("{} must be at least 1", name));
+            }
+        }
+        let replication = self.replication_factor.unwrap_or(1);
+        if let Some(write) = self.write_consistency_factor {
+            if write > replication {
+                return Err(format!(
+                    "write_consistency_factor ({}) cannot exceed replication_factor ({})",
+                    write, replication
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reject malformed query parameters with a Qdrant-style JSON error
+pub fn query_error_handler(err: error::QueryPayloadError, _req: &HttpRequest) -> error::Error {
+    let body = serde_json::json!({
+        "status": {
+            "error": format!("Bad request: {}", err)
+        },
+        "time": 0.0
+    });
+    error::InternalError::from_response(err, HttpResponse::BadRequest().json(body)).into()
+}
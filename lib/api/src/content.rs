@@ -0,0 +1,83 @@
+//! MessagePack bodies on the points endpoints
+//!
+//! Parsing and printing floats dominates the cost of JSON vector payloads.
+//! Requests under `/collections/{name}/points` may instead send
+//! `Content-Type: application/msgpack` with the same schema, and ask for
+//! `Accept: application/msgpack` to get successful responses the same way.
+//! Errors stay JSON, so clients should look at the response's
+//! `Content-Type`.
+//!
+//! The handlers build their responses through [`respond`], which reads the
+//! format chosen by [`negotiate_points_format`] for the request being
+//! handled, instead of each handler taking the `Accept` header.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, Header};
+use actix_web::middleware::Next;
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder};
+use serde::Serialize;
+
+/// Media type of MessagePack bodies
+pub const MSGPACK: &str = "application/msgpack";
+
+/// Media types taken as MessagePack; the last two predate the registered one
+const MSGPACK_TYPES: [&str; 3] = [MSGPACK, "application/x-msgpack", "application/vnd.msgpack"];
+
+/// Whether a `Content-Type` or `Accept` media type means MessagePack
+pub fn is_msgpack(media_type: &str) -> bool {
+    let essence = media_type.split(';').next().unwrap_or("").trim();
+    MSGPACK_TYPES.iter().any(|t| essence.eq_ignore_ascii_case(t))
+}
+
+/// Whether the request body is MessagePack
+pub(crate) fn has_msgpack_body(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(is_msgpack)
+}
+
+/// Whether an `Accept` header ranks MessagePack above JSON. Wildcards count
+/// as JSON.
+pub fn prefers_msgpack(accept: &header::Accept) -> bool {
+    accept.ranked()
+        .iter()
+        .map(|mime| mime.essence_str())
+        .find(|essence| is_msgpack(essence) || matches!(*essence, "application/json" | "application/*" | "*/*"))
+        .is_some_and(is_msgpack)
+}
+
+tokio::task_local! {
+    static MSGPACK_RESPONSE: bool;
+}
+
+/// Middleware choosing the response format of points requests from their
+/// `Accept` header
+pub(crate) async fn negotiate_points_format(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let segments: Vec<&str> = req.path().trim_matches('/').split('/').collect();
+    let msgpack = matches!(segments.as_slice(), ["collections", _, "points", ..])
+        && header::Accept::parse(&req).is_ok_and(|accept| prefers_msgpack(&accept));
+    MSGPACK_RESPONSE.scope(msgpack, next.call(req)).await
+}
+
+/// Run `handler` with its responses in JSON whatever the request asked for,
+/// so a batch can read them back
+pub(crate) async fn as_json<F: std::future::Future>(handler: F) -> F::Output {
+    MSGPACK_RESPONSE.scope(false, handler).await
+}
+
+/// Response with `body` as JSON, or as MessagePack if the request asked for
+/// it. Maps keep their field names, so the schema is the same either way.
+pub(crate) fn respond<T: Serialize>(mut builder: HttpResponseBuilder, body: &T) -> HttpResponse {
+    if MSGPACK_RESPONSE.try_with(|msgpack| *msgpack).unwrap_or(false) {
+        match rmp_serde::to_vec_named(body) {
+            Ok(bytes) => return builder.content_type(MSGPACK).body(bytes),
+            Err(e) => tracing::warn!("Failed to encode response as MessagePack: {}", e),
+        }
+    }
+    builder.json(body)
+}
@@ -0,0 +1,691 @@
+//! Transport-neutral requests shared by REST and gRPC
+//!
+//! Each transport decodes its wire format into the models here and runs
+//! them through the same functions, so an upsert, search or recommendation
+//! behaves the same whichever API it came through: search defaults, offsets,
+//! score thresholds, payload filters and JWT claim filters all apply in one
+//! place. A new transport only needs conversions into these models.
+
+use std::fmt;
+use std::sync::Arc;
+use vectx_core::{Collection, Distance, Filter, FilterCondition, GeoPoint, MultiVector, PayloadFilter, Point, PointId, RecencyOverride, SparseVector, UpsertStatus, Vector, VectorFormula};
+use vectx_core::recency::RECENCY_OVERSAMPLE;
+use crate::auth::ClaimFilter;
+use crate::deadline;
+use crate::parsing::{ParseError, ParseMode};
+
+/// Batches of at least this many points prewarm the HNSW graph
+const PREWARM_THRESHOLD: usize = 1000;
+
+/// Why a request failed, for the transport to map onto its own errors
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestError {
+    /// The collection or a referenced point does not exist
+    NotFound(String),
+    /// The request itself is wrong
+    Invalid(String),
+    /// The request was fine but could not be carried out
+    Internal(String),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(message) | Self::Invalid(message) | Self::Internal(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+// ============================================================================
+// Points
+// ============================================================================
+
+/// A point to upsert, as a transport received it
+#[derive(Debug, Clone)]
+pub struct PointInput {
+    pub id: PointId,
+    /// Dense vector; in Hamming collections the byte values of a binary vector
+    pub vector: Vec<f32>,
+    /// Bytes of a binary vector, for Hamming collections
+    pub binary: Option<Vec<u8>>,
+    /// Multivector stored as the point's unnamed multivector
+    pub multivector: Option<Vec<Vec<f32>>>,
+    /// Multivectors by name, each of which the collection must declare
+    pub named_multivectors: Vec<(String, Vec<Vec<f32>>)>,
+    pub sparse_vectors: Vec<(String, SparseVector)>,
+    pub payload: Option<serde_json::Value>,
+}
+
+impl PointInput {
+    pub fn new(id: PointId, vector: Vec<f32>, payload: Option<serde_json::Value>) -> Self {
+        Self {
+            id,
+            vector,
+            binary: None,
+            multivector: None,
+            named_multivectors: Vec::new(),
+            sparse_vectors: Vec::new(),
+            payload,
+        }
+    }
+
+    /// The point to store in `collection`
+    pub fn into_point(self, collection: &Collection) -> Result<Point, String> {
+        let mut named_multivectors = Vec::new();
+        for (name, data) in self.named_multivectors {
+            if collection.named_multivector(&name).is_none() {
+                return Err(format!("multivector '{}' is not declared by the collection", name));
+            }
+            let mv = MultiVector::new(data).map_err(|e| format!("multivector '{}': {}", name, e))?;
+            named_multivectors.push((name, mv));
+        }
+
+        let multivector = self.multivector
+            .map(|data| MultiVector::new(data).map_err(|e| format!("multivector: {}", e)))
+            .transpose()?;
+        let mut point = match multivector {
+            Some(mv) => Point::new_multi(self.id, mv, self.payload),
+            // Payload-only and sparse-only points have no dense vector
+            None if self.vector.is_empty() && self.binary.is_none() => Point::new(self.id, Vector::new(vec![]), self.payload),
+            None => {
+                let vector = dense_vector(collection, &self.vector, self.binary.as_deref())?;
+                Point::new(self.id, vector, self.payload)
+            }
+        };
+        for (name, sparse) in self.sparse_vectors {
+            point.add_sparse_vector(name, sparse);
+        }
+        for (name, mv) in named_multivectors {
+            point.add_named_multivector(name, mv);
+        }
+        Ok(point)
+    }
+}
+
+/// A dense vector from a request as `collection` stores it. Hamming
+/// collections take the bytes of a binary vector, as numbers or raw bytes,
+/// and pack them; other collections take numbers as they are.
+pub fn dense_vector(collection: &Collection, values: &[f32], binary: Option<&[u8]>) -> Result<Vector, String> {
+    let bits = collection.vector_dim();
+    match (collection.distance(), binary) {
+        (Distance::Hamming, Some(bytes)) if bytes.len() * 8 == bits => Ok(vectx_core::binary::pack_bytes(bytes)),
+        (Distance::Hamming, Some(bytes)) => Err(format!("Invalid binary vector: {} bits, expected {}", bytes.len() * 8, bits)),
+        (Distance::Hamming, None) => vectx_core::binary::pack_byte_values(values, bits).map_err(|e| e.to_string()),
+        (_, Some(_)) => Err("binary vectors are only accepted by Hamming collections".to_string()),
+        (_, None) => Ok(Vector::new(values.to_vec())),
+    }
+}
+
+/// A stored dense vector as clients receive it: the bytes of binary
+/// vectors, the numbers of others
+pub fn vector_json(collection: &Collection, vector: &Vector) -> serde_json::Value {
+    match collection.distance() {
+        Distance::Hamming => serde_json::json!(vectx_core::binary::unpack_bytes(vector, collection.vector_dim())),
+        _ => serde_json::json!(vector.as_slice()),
+    }
+}
+
+/// Convert `points` and write them all, or none if any is invalid. Errors
+/// name the offending point by its index in the request.
+pub fn upsert_points(
+    collection: &Collection,
+    points: Vec<PointInput>,
+    max_payload_bytes: Option<usize>,
+) -> Result<Vec<UpsertStatus>, RequestError> {
+    let points = points.into_iter()
+        .enumerate()
+        .map(|(index, point)| {
+            point.into_point(collection)
+                .map_err(|e| RequestError::Invalid(format!("Point at index {}: {}", index, e)))
+        })
+        .collect::<Result<Vec<Point>, RequestError>>()?;
+    collection.validate_points(&points, max_payload_bytes)
+        .map_err(|e| RequestError::Invalid(e.to_string()))?;
+    let written = match points.len() {
+        0 => Ok(Vec::new()),
+        1 => collection.upsert_with_status(points.into_iter().next().expect("one point")).map(|s| vec![s]),
+        n if n >= PREWARM_THRESHOLD => collection.batch_upsert_with_prewarm(points, true),
+        _ => collection.batch_upsert(points),
+    };
+    written.map_err(|e| RequestError::Internal(e.to_string()))
+}
+
+// ============================================================================
+// Filters
+// ============================================================================
+
+/// Parse a Qdrant-style JSON filter (`must` / `should` / `must_not`, a
+/// single field condition, or the legacy `{field, value, operator}` form).
+/// Parts that cannot be parsed are logged and skipped; see
+/// [`try_parse_filter`] to reject them instead.
+pub fn parse_filter(filter_json: &serde_json::Value) -> Option<FilterCondition> {
+    try_parse_filter(filter_json, ParseMode::Lenient).ok().flatten()
+}
+
+/// Parse a JSON filter as [`parse_filter`] does, failing on the first part
+/// that cannot be parsed in strict `mode`. Error paths start at `filter`.
+pub fn try_parse_filter(filter_json: &serde_json::Value, mode: ParseMode) -> Result<Option<FilterCondition>, ParseError> {
+    FilterParser { mode }.filter(filter_json, "filter")
+}
+
+const CLAUSES: [&str; 3] = ["must", "should", "must_not"];
+
+struct FilterParser {
+    mode: ParseMode,
+}
+
+impl FilterParser {
+    /// The error of the part at `path`; nothing in lenient mode
+    fn reject(&self, path: &str, message: impl Into<String>) -> Result<Option<FilterCondition>, ParseError> {
+        self.mode.tolerate(ParseError::new(path, message)).map(|()| None)
+    }
+
+    fn filter(&self, value: &serde_json::Value, path: &str) -> Result<Option<FilterCondition>, ParseError> {
+        let Some(obj) = value.as_object() else {
+            return self.reject(path, "expected an object");
+        };
+        if CLAUSES.iter().any(|clause| obj.contains_key(*clause)) {
+            let mut clauses = Vec::with_capacity(CLAUSES.len());
+            for clause in CLAUSES {
+                clauses.push(self.clause(obj.get(clause), &format!("{}.{}", path, clause))?);
+            }
+            for key in obj.keys().filter(|key| !CLAUSES.contains(&key.as_str())) {
+                self.reject(&format!("{}.{}", path, key), "unsupported filter field")?;
+            }
+            let [must, should, must_not]: [Vec<FilterCondition>; 3] = clauses.try_into().expect("three clauses");
+            return Ok(combine_clauses(must, should, must_not));
+        }
+        if obj.contains_key("operator") {
+            return self.legacy_condition(obj, path);
+        }
+        if obj.contains_key("key") {
+            return self.field_condition(obj, path);
+        }
+        match obj.keys().next() {
+            Some(key) => self.reject(&format!("{}.{}", path, key), "unsupported filter field"),
+            None => Ok(None),
+        }
+    }
+
+    /// The conditions of a `must`, `should` or `must_not` clause
+    fn clause(&self, value: Option<&serde_json::Value>, path: &str) -> Result<Vec<FilterCondition>, ParseError> {
+        let mut conditions = Vec::new();
+        match value {
+            None | Some(serde_json::Value::Null) => {}
+            Some(serde_json::Value::Array(entries)) => {
+                for (idx, entry) in entries.iter().enumerate() {
+                    conditions.extend(self.condition(entry, &format!("{}[{}]", path, idx))?);
+                }
+            }
+            // A single condition, as Qdrant accepts too
+            Some(entry @ serde_json::Value::Object(_)) => conditions.extend(self.condition(entry, path)?),
+            Some(_) => {
+                self.reject(path, "expected an array of conditions")?;
+            }
+        }
+        Ok(conditions)
+    }
+
+    /// One clause entry: a field condition or a nested filter
+    fn condition(&self, value: &serde_json::Value, path: &str) -> Result<Option<FilterCondition>, ParseError> {
+        let Some(obj) = value.as_object() else {
+            return self.reject(path, "expected an object");
+        };
+        if obj.contains_key("key") {
+            self.field_condition(obj, path)
+        } else if CLAUSES.iter().any(|clause| obj.contains_key(*clause)) {
+            self.filter(value, path)
+        } else {
+            match obj.keys().next() {
+                Some(kind) => self.reject(path, format!("unsupported condition \"{}\"", kind)),
+                None => self.reject(path, "empty condition"),
+            }
+        }
+    }
+
+    /// The legacy simple format: `{field, value, operator}`
+    fn legacy_condition(&self, obj: &serde_json::Map<String, serde_json::Value>, path: &str) -> Result<Option<FilterCondition>, ParseError> {
+        let Some(field) = obj.get("field").and_then(|f| f.as_str()) else {
+            return self.reject(&format!("{}.field", path), "expected a field name");
+        };
+        let Some(value) = obj.get("value") else {
+            return self.reject(&format!("{}.value", path), "missing");
+        };
+        let field = field.to_string();
+        let op = obj.get("operator").and_then(|o| o.as_str()).unwrap_or_default();
+        let bound = |value: &serde_json::Value| value.as_f64();
+        let condition = match (op, bound(value)) {
+            ("eq", _) => FilterCondition::Equals { field, value: value.clone() },
+            ("ne", _) => FilterCondition::NotEquals { field, value: value.clone() },
+            ("gt", Some(value)) => FilterCondition::GreaterThan { field, value },
+            ("lt", Some(value)) => FilterCondition::LessThan { field, value },
+            ("gte", Some(value)) => FilterCondition::GreaterEqual { field, value },
+            ("lte", Some(value)) => FilterCondition::LessEqual { field, value },
+            ("gt" | "lt" | "gte" | "lte", None) => return self.reject(&format!("{}.value", path), "expected a number"),
+            _ => return self.reject(&format!("{}.operator", path), "expected eq, ne, gt, lt, gte or lte"),
+        };
+        Ok(Some(condition))
+    }
+
+    /// A single Qdrant field condition: `{"key": "field", "match": {"value": x}}`
+    fn field_condition(&self, obj: &serde_json::Map<String, serde_json::Value>, path: &str) -> Result<Option<FilterCondition>, ParseError> {
+        let Some(key) = obj.get("key").and_then(|k| k.as_str()) else {
+            return self.reject(&format!("{}.key", path), "expected a field name");
+        };
+        if let Some(match_value) = obj.get("match") {
+            return self.match_condition(key, match_value, &format!("{}.match", path));
+        }
+        if let Some(range) = obj.get("range") {
+            return self.range_condition(key, range, &format!("{}.range", path));
+        }
+        if let Some(geo_radius) = obj.get("geo_radius") {
+            return self.geo_radius_condition(key, geo_radius, &format!("{}.geo_radius", path));
+        }
+        match obj.keys().find(|k| *k != "key") {
+            Some(kind) => self.reject(&format!("{}.{}", path, kind), "unsupported condition"),
+            None => self.reject(path, "expected a match, a range or a geo_radius"),
+        }
+    }
+
+    /// Geo radius: `{"geo_radius": {"center": {"lat": y, "lon": x}, "radius": metres}}`.
+    /// No earlier version skipped these, so a bad one is an error in either
+    /// mode.
+    fn geo_radius_condition(&self, key: &str, value: &serde_json::Value, path: &str) -> Result<Option<FilterCondition>, ParseError> {
+        let Some(center) = value.get("center") else {
+            return Err(ParseError::new(format!("{}.center", path), "missing"));
+        };
+        let Some(center) = GeoPoint::from_value(center) else {
+            return Err(ParseError::new(format!("{}.center", path), "expected {\"lat\": number, \"lon\": number}"));
+        };
+        if let Err(message) = center.validate() {
+            return Err(ParseError::new(format!("{}.center", path), message));
+        }
+        match value.get("radius").and_then(|r| r.as_f64()) {
+            Some(radius) if radius >= 0.0 => Ok(Some(FilterCondition::GeoRadius { field: key.to_string(), center, radius })),
+            _ => Err(ParseError::new(format!("{}.radius", path), "expected a non-negative number of metres")),
+        }
+    }
+
+    fn match_condition(&self, key: &str, value: &serde_json::Value, path: &str) -> Result<Option<FilterCondition>, ParseError> {
+        let Some(match_obj) = value.as_object() else {
+            return self.reject(path, "expected an object");
+        };
+        let values = |name: &str| -> Result<Option<Vec<serde_json::Value>>, ParseError> {
+            match match_obj.get(name) {
+                Some(serde_json::Value::Array(values)) => Ok(Some(values.clone())),
+                Some(_) => self.reject(&format!("{}.{}", path, name), "expected an array").map(|_| None),
+                None => Ok(None),
+            }
+        };
+        if let Some(value) = match_obj.get("value") {
+            return Ok(Some(FilterCondition::Equals { field: key.to_string(), value: value.clone() }));
+        }
+        // Match any: { "match": { "any": [x, y, z] } }
+        if match_obj.contains_key("any") {
+            return match values("any")? {
+                Some(any) if any.is_empty() => self.reject(&format!("{}.any", path), "expected at least one value"),
+                any => Ok(any.and_then(|any| match_any(key, any))),
+            };
+        }
+        // Match all: { "match": { "all": [x, y] } }. With none to hold it
+        // would match every point, so an empty list is refused like `any`
+        if match_obj.contains_key("all") {
+            return match values("all")? {
+                Some(all) if all.is_empty() => self.reject(&format!("{}.all", path), "expected at least one value"),
+                all => Ok(all.and_then(|all| match_all(key, all))),
+            };
+        }
+        // Match except: { "match": { "except": [x, y] } }
+        if match_obj.contains_key("except") {
+            return Ok(values("except")?.map(|except| match_except(key, except)));
+        }
+        // Match text: { "match": { "text": "value" } }
+        if let Some(text) = match_obj.get("text") {
+            return match text.as_str() {
+                Some(text) => Ok(Some(FilterCondition::Text { field: key.to_string(), text: text.to_string() })),
+                None => self.reject(&format!("{}.text", path), "expected a string"),
+            };
+        }
+        // Match fuzzy: { "match": { "fuzzy": { "value": "x", "max_edits": 1 } } }
+        if let Some(fuzzy) = match_obj.get("fuzzy") {
+            return match parse_fuzzy(fuzzy) {
+                Some((value, max_edits)) => Ok(Some(FilterCondition::Fuzzy { field: key.to_string(), value, max_edits })),
+                None => self.reject(&format!("{}.fuzzy", path), "expected {\"value\": string, \"max_edits\": number}"),
+            };
+        }
+        match match_obj.keys().next() {
+            Some(kind) => self.reject(&format!("{}.{}", path, kind), "unsupported match"),
+            None => self.reject(path, "expected value, any, all, except, text or fuzzy"),
+        }
+    }
+
+    /// Range condition: `{"range": {"gt": x, "lt": y}}`; null bounds are absent
+    fn range_condition(&self, key: &str, value: &serde_json::Value, path: &str) -> Result<Option<FilterCondition>, ParseError> {
+        let Some(range) = value.as_object() else {
+            return self.reject(path, "expected an object");
+        };
+        let mut bounds: [Option<f64>; 4] = [None; 4];
+        for (name, bound) in range {
+            let slot = match name.as_str() {
+                "gt" => 0,
+                "gte" => 1,
+                "lt" => 2,
+                "lte" => 3,
+                _ => {
+                    self.reject(&format!("{}.{}", path, name), "unknown bound, expected gt, gte, lt or lte")?;
+                    continue;
+                }
+            };
+            match bound {
+                serde_json::Value::Null => {}
+                bound => match bound.as_f64() {
+                    Some(bound) => bounds[slot] = Some(bound),
+                    None => {
+                        self.reject(&format!("{}.{}", path, name), "expected a number")?;
+                    }
+                },
+            }
+        }
+        let [gt, gte, lt, lte] = bounds;
+        match range_condition(key, gt, gte, lt, lte) {
+            Some(condition) => Ok(Some(condition)),
+            None => self.reject(path, "expected at least one bound"),
+        }
+    }
+}
+
+/// Combine the clauses of a filter: all of `must`, at least one of
+/// `should` and none of `must_not`. None if every clause is empty.
+pub fn combine_clauses(
+    must: Vec<FilterCondition>,
+    should: Vec<FilterCondition>,
+    must_not: Vec<FilterCondition>,
+) -> Option<FilterCondition> {
+    let mut all = Vec::new();
+    match must.len() {
+        0 => {}
+        1 => all.extend(must),
+        _ => all.push(FilterCondition::And(must)),
+    }
+    match should.len() {
+        0 => {}
+        1 => all.extend(should),
+        _ => all.push(FilterCondition::Or(should)),
+    }
+    all.extend(must_not.into_iter().map(|c| FilterCondition::Not(Box::new(c))));
+    match all.len() {
+        0 => None,
+        1 => all.pop(),
+        _ => Some(FilterCondition::And(all)),
+    }
+}
+
+/// The field equals one of `values`
+pub fn match_any(field: &str, values: impl IntoIterator<Item = serde_json::Value>) -> Option<FilterCondition> {
+    let mut any: Vec<FilterCondition> = values.into_iter()
+        .map(|value| FilterCondition::Equals { field: field.to_string(), value })
+        .collect();
+    match any.len() {
+        0 => None,
+        1 => any.pop(),
+        _ => Some(FilterCondition::Or(any)),
+    }
+}
+
+/// The field holds every one of `values`
+pub fn match_all(field: &str, values: impl IntoIterator<Item = serde_json::Value>) -> Option<FilterCondition> {
+    let values: Vec<serde_json::Value> = values.into_iter().collect();
+    if values.is_empty() {
+        return None;
+    }
+    Some(FilterCondition::All { field: field.to_string(), values })
+}
+
+/// The field has a value other than `values`
+pub fn match_except(field: &str, values: impl IntoIterator<Item = serde_json::Value>) -> FilterCondition {
+    FilterCondition::Except { field: field.to_string(), values: values.into_iter().collect() }
+}
+
+/// The field lies within every bound given
+pub fn range_condition(
+    field: &str,
+    gt: Option<f64>,
+    gte: Option<f64>,
+    lt: Option<f64>,
+    lte: Option<f64>,
+) -> Option<FilterCondition> {
+    let field = || field.to_string();
+    let mut bounds: Vec<FilterCondition> = [
+        gt.map(|value| FilterCondition::GreaterThan { field: field(), value }),
+        gte.map(|value| FilterCondition::GreaterEqual { field: field(), value }),
+        lt.map(|value| FilterCondition::LessThan { field: field(), value }),
+        lte.map(|value| FilterCondition::LessEqual { field: field(), value }),
+    ].into_iter().flatten().collect();
+    match bounds.len() {
+        0 => None,
+        1 => bounds.pop(),
+        _ => Some(FilterCondition::And(bounds)),
+    }
+}
+
+/// Value and edit budget of a `match.fuzzy` object (one edit by default)
+pub fn parse_fuzzy(fuzzy: &serde_json::Value) -> Option<(String, usize)> {
+    let value = fuzzy.get("value")?.as_str()?.to_string();
+    let max_edits = fuzzy.get("max_edits").and_then(|m| m.as_u64()).unwrap_or(1) as usize;
+    Some((value, max_edits))
+}
+
+/// Refuse a filter selecting the points a write changes if it holds an
+/// empty `all` list anywhere. Dropped in lenient parsing, or negated under
+/// `must_not`, it would select every point, so writes refuse it in every
+/// parse mode.
+pub fn check_write_selector(filter: &serde_json::Value) -> Result<(), ParseError> {
+    fn check(value: &serde_json::Value, path: &str) -> Result<(), ParseError> {
+        match value {
+            serde_json::Value::Object(obj) => {
+                for (key, child) in obj {
+                    let path = format!("{}.{}", path, key);
+                    if key == "match" && child.get("all").and_then(|all| all.as_array()).is_some_and(Vec::is_empty) {
+                        return Err(ParseError::new(format!("{}.all", path), "expected at least one value"));
+                    }
+                    check(child, &path)?;
+                }
+            }
+            serde_json::Value::Array(entries) => {
+                for (idx, entry) in entries.iter().enumerate() {
+                    check(entry, &format!("{}[{}]", path, idx))?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    check(filter, "filter")
+}
+
+/// A request's filter condition, restricted to what the caller's claim
+/// allows. With a collection, its text dictionaries and keyword index
+/// options apply and fuzzy matches on keyword-indexed fields are resolved
+/// through its trigram index.
+pub fn build_filter(
+    condition: Option<FilterCondition>,
+    claim: Option<&ClaimFilter>,
+    collection: Option<&Collection>,
+) -> Option<Box<dyn Filter>> {
+    let condition = match claim {
+        Some(claim) => Some(claim.restrict(condition)),
+        None => condition,
+    };
+    condition.map(|cond| {
+        let filter = match collection {
+            Some(collection) => PayloadFilter::new(collection.resolve_fuzzy(cond))
+                .with_dictionaries(collection.text_dictionaries())
+                .with_keyword_params(collection.keyword_params()),
+            None => PayloadFilter::new(cond),
+        };
+        Box::new(filter) as Box<dyn Filter>
+    })
+}
+
+// ============================================================================
+// Search
+// ============================================================================
+
+/// A nearest-neighbour search by vector
+#[derive(Debug, Clone)]
+pub struct SearchRequest {
+    /// The query as the collection stores vectors (see [`dense_vector`])
+    pub vector: Vector,
+    /// Results to return; the collection's search defaults when None
+    pub limit: Option<usize>,
+    /// Best results to skip
+    pub offset: usize,
+    pub score_threshold: Option<f32>,
+    pub filter: Option<FilterCondition>,
+    /// Seconds the search may run
+    pub timeout: Option<u64>,
+    /// Replaces or turns off the collection's recency boost
+    pub recency: Option<RecencyOverride>,
+}
+
+/// Points found by a search, best first
+#[derive(Debug, Clone)]
+pub struct SearchResults {
+    pub points: Vec<(Point, f32)>,
+    /// The timeout passed first; `points` holds what was found until then
+    pub timed_out: bool,
+}
+
+/// Run a search on the blocking pool, applying the collection's search
+/// defaults. Dropping the future cancels the scan.
+pub async fn search(
+    collection: Arc<Collection>,
+    request: SearchRequest,
+    claim: Option<Arc<ClaimFilter>>,
+) -> Result<SearchResults, RequestError> {
+    let defaults = collection.search_defaults();
+    let limit = defaults.limit(request.limit, 10);
+    let score_threshold = defaults.score_threshold(request.score_threshold);
+    if let Some(Err(e)) = request.recency.as_ref().map(RecencyOverride::validate) {
+        return Err(RequestError::Invalid(e));
+    }
+    let recency = defaults.recency(request.recency.as_ref()).cloned();
+    let offset = request.offset;
+    let SearchRequest { vector, filter, timeout, .. } = request;
+    let candidates = match recency {
+        Some(_) => (limit + offset) * RECENCY_OVERSAMPLE,
+        None => limit + offset,
+    };
+
+    let scan = deadline::run_cancellable(timeout, move |cancel| {
+        let filter = build_filter(filter, claim.as_deref(), Some(&collection));
+        collection.search_cancellable(&vector, candidates, filter.as_deref(), cancel)
+    }).await.map_err(RequestError::Internal)?;
+
+    let results = match &recency {
+        Some(boost) => boost.apply(scan.results),
+        None => scan.results,
+    };
+    let points = results
+        .into_iter()
+        .skip(offset)
+        .filter(|(_, score)| score_threshold.map_or(true, |t| *score >= t))
+        .take(limit)
+        .collect();
+    Ok(SearchResults { points, timed_out: scan.timed_out })
+}
+
+/// Points like the `positive` examples and unlike the `negative` ones
+#[derive(Debug, Clone, Default)]
+pub struct RecommendRequest {
+    /// Ids of the examples; those that do not exist are ignored
+    pub positive: Vec<String>,
+    pub negative: Vec<String>,
+    /// Results to return; the collection's search defaults when None
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub score_threshold: Option<f32>,
+    pub filter: Option<FilterCondition>,
+    /// Replaces or turns off the collection's recency boost
+    pub recency: Option<RecencyOverride>,
+}
+
+/// Search with `2 * avg(positive) - avg(negative)`, leaving out the examples
+pub fn recommend(
+    collection: &Collection,
+    request: &RecommendRequest,
+    claim: Option<&ClaimFilter>,
+) -> Result<Vec<(Point, f32)>, RequestError> {
+    let defaults = collection.search_defaults();
+    let limit = defaults.limit(request.limit, 10);
+    let score_threshold = defaults.score_threshold(request.score_threshold);
+    if let Some(Err(e)) = request.recency.as_ref().map(RecencyOverride::validate) {
+        return Err(RequestError::Invalid(e));
+    }
+    let recency = defaults.recency(request.recency.as_ref());
+
+    let examples: std::collections::HashSet<&str> = request.positive.iter()
+        .chain(&request.negative)
+        .map(String::as_str)
+        .collect();
+    let existing = |ids: &[String]| -> Vec<String> {
+        ids.iter().filter(|id| collection.get(id).is_some()).cloned().collect()
+    };
+    let positive = existing(&request.positive);
+    if positive.is_empty() {
+        return Err(RequestError::Invalid("At least one valid positive example is required".to_string()));
+    }
+    let negative = existing(&request.negative);
+
+    let query = VectorFormula::recommend(&positive, &negative)
+        .evaluate(collection)
+        .map_err(|e| RequestError::Invalid(e.to_string()))?;
+    let filter = build_filter(request.filter.clone(), claim, Some(collection));
+    // Ask for more results to make up for the examples left out
+    let mut candidates = limit + request.offset;
+    if recency.is_some() {
+        candidates *= RECENCY_OVERSAMPLE;
+    }
+    let mut found = collection.search(&query, candidates + examples.len(), filter.as_deref());
+    if let Some(boost) = recency {
+        found = boost.apply(found);
+    }
+
+    Ok(found.into_iter()
+        .filter(|(point, _)| !examples.contains(point.id.to_string().as_str()))
+        .skip(request.offset)
+        .filter(|(_, score)| score_threshold.map_or(true, |t| *score >= t))
+        .take(limit)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_parse_filter() {
+        let strict = |filter: serde_json::Value| try_parse_filter(&filter, ParseMode::Strict).map(|c| c.is_some());
+        let lenient = |filter: serde_json::Value| try_parse_filter(&filter, ParseMode::Lenient).map(|c| c.is_some());
+        let has_id = serde_json::json!({"must": [
+            {"key": "city", "match": {"value": "Rome"}},
+            {"has_id": [1]},
+        ]});
+        assert_eq!(strict(has_id.clone()), Err(ParseError::new("filter.must[1]", "unsupported condition \"has_id\"")));
+        assert_eq!(lenient(has_id), Ok(true));
+        let range = serde_json::json!({"should": [{"key": "price", "range": {"gt": "5"}}]});
+        assert_eq!(strict(range.clone()).unwrap_err().path, "filter.should[0].range.gt");
+        // Lenient parsing skips the bound, then the condition it leaves empty
+        assert_eq!(lenient(range), Ok(false));
+        assert_eq!(strict(serde_json::json!({"must": "city"})).unwrap_err().path, "filter.must");
+        assert_eq!(strict(serde_json::json!({"key": "tags", "match": {"any": []}})).unwrap_err().path, "filter.match.any");
+        assert_eq!(strict(serde_json::json!({"key": "city", "match": {"like": "R%"}})).unwrap_err().path, "filter.match.like");
+        assert_eq!(strict(serde_json::json!({"must": [{"key": "price", "range": {"gt": 1, "lt": null}}]})), Ok(true));
+        assert_eq!(strict(serde_json::json!({})), Ok(false));
+        // Geo radius conditions are checked in either mode
+        let far = serde_json::json!({"key": "location", "geo_radius": {"center": {"lat": 95.0, "lon": 0.0}, "radius": 1.0}});
+        assert_eq!(lenient(far).unwrap_err().path, "filter.geo_radius.center");
+    }
+}
@@ -0,0 +1,49 @@
+//! Searches that stop when their client goes away or their timeout passes
+//!
+//! Scans run on the blocking thread pool with a [`CancellationToken`]. actix
+//! and tonic drop a handler's future when the client disconnects; the guard
+//! held by that future then cancels the token, so the scan stops at its next
+//! check instead of running to the end. A `timeout` gives the token a
+//! deadline; the handler answers with a timeout error or, if the client
+//! asked for it, with the results found before the deadline.
+
+use std::time::Duration;
+use vectx_core::CancellationToken;
+
+/// Output of a cancellable search
+pub(crate) struct Scan<T> {
+    pub results: T,
+    /// The deadline passed before the scan finished; `results` is partial
+    pub timed_out: bool,
+}
+
+/// Cancels its token when dropped
+struct CancelOnDrop(CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// Run `search` on the blocking pool with a token that expires after
+/// `timeout_secs` and is cancelled if the returned future is dropped
+pub(crate) async fn run_cancellable<T, F>(timeout_secs: Option<u64>, search: F) -> Result<Scan<T>, String>
+where
+    F: FnOnce(&CancellationToken) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let token = match timeout_secs {
+        Some(secs) => CancellationToken::with_timeout(Duration::from_secs(secs)),
+        None => CancellationToken::new(),
+    };
+    let _guard = CancelOnDrop(token.clone());
+    let worker_token = token.clone();
+    let results = tokio::task::spawn_blocking(move || search(&worker_token))
+        .await
+        .map_err(|e| format!("Search failed: {}", e))?;
+    Ok(Scan {
+        results,
+        timed_out: token.interrupted() && token.timed_out(),
+    })
+}
@@ -0,0 +1,112 @@
+//! Export of a collection as NDJSON in Qdrant's point schema
+//!
+//! Each line is one point the way Qdrant's upsert API takes it: `id`,
+//! `vector` and `payload`. Sending the lines in batches to
+//! `PUT /collections/{name}/points` loads the dump into Qdrant, or back into
+//! vectX, so it doubles as a vendor-neutral backup. Qdrant's own snapshots
+//! hold RocksDB segments that vectX cannot write.
+//!
+//! The HNSW graph of a collection can be exported too, as [`graphml`] for
+//! graph tools such as Gephi or NetworkX.
+
+use actix_web::web::Bytes;
+use futures_util::stream::{self, Stream, StreamExt};
+use std::sync::Arc;
+use vectx_core::{Collection, Filter, GraphNode, Point, PointId, Projection};
+use crate::core_requests::vector_json;
+
+/// Points serialized into each chunk of the response
+const EXPORT_CHUNK: usize = 256;
+
+/// A point as Qdrant's upsert API takes it. A point with only a dense vector
+/// (or only a multivector) exports it as `vector`; one that also has sparse
+/// vectors or named multivectors exports a map of named vectors, with the
+/// dense vector under Qdrant's default name `""`.
+pub fn qdrant_point(collection: &Collection, point: &Point) -> serde_json::Value {
+    let default = match &point.multivector {
+        Some(mv) => Some(serde_json::json!(mv.vectors())),
+        None if point.vector.dim() > 0 => Some(vector_json(collection, &point.vector)),
+        None => None,
+    };
+    let vector = if point.sparse_vectors.is_empty() && point.named_multivectors.is_empty() {
+        default.unwrap_or_else(|| serde_json::json!({}))
+    } else {
+        let mut named = serde_json::Map::new();
+        if let Some(default) = default {
+            named.insert(String::new(), default);
+        }
+        for (name, sparse) in &point.sparse_vectors {
+            named.insert(name.clone(), serde_json::json!(sparse));
+        }
+        for (name, mv) in &point.named_multivectors {
+            named.insert(name.clone(), serde_json::json!(mv.vectors()));
+        }
+        named.into()
+    };
+    let id = match &point.id {
+        PointId::Integer(i) => serde_json::json!(i),
+        PointId::String(s) => serde_json::json!(s),
+        PointId::Uuid(u) => serde_json::json!(u.to_string()),
+    };
+    serde_json::json!({
+        "id": id,
+        "vector": vector,
+        "payload": point.payload.clone().unwrap_or_else(|| serde_json::json!({})),
+    })
+}
+
+/// Every point of `collection` matching `filter`, one JSON line each. The
+/// matching points are picked when this is called; points deleted while
+/// the stream runs, or changed so they no longer match, are skipped.
+pub fn export_ndjson(collection: Arc<Collection>, filter: Option<Box<dyn Filter>>) -> impl Stream<Item = Bytes> + 'static {
+    let points = collection.iter_filtered_owned(filter, Projection::ALL);
+    stream::iter(points)
+        .chunks(EXPORT_CHUNK)
+        .map(move |chunk| {
+            let mut body = Vec::new();
+            for point in &chunk {
+                // Serializing a JSON value cannot fail
+                let _ = serde_json::to_writer(&mut body, &qdrant_point(&collection, point));
+                body.push(b'\n');
+            }
+            Bytes::from(body)
+        })
+}
+
+/// `nodes` as a directed GraphML graph. Nodes carry their `level`; each
+/// link is an edge with the `layer` it is on.
+pub fn graphml(nodes: &[GraphNode]) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"level\" for=\"node\" attr.name=\"level\" attr.type=\"int\"/>\n",
+        "  <key id=\"layer\" for=\"edge\" attr.name=\"layer\" attr.type=\"int\"/>\n",
+        "  <graph id=\"hnsw\" edgedefault=\"directed\">\n",
+    ));
+    for node in nodes {
+        xml.push_str(&format!(
+            "    <node id=\"{}\"><data key=\"level\">{}</data></node>\n",
+            xml_escape(&node.id), node.level
+        ));
+    }
+    for node in nodes {
+        for (layer, links) in node.layers.iter().enumerate() {
+            for link in links {
+                xml.push_str(&format!(
+                    "    <edge source=\"{}\" target=\"{}\"><data key=\"layer\">{}</data></edge>\n",
+                    xml_escape(&node.id), xml_escape(link), layer
+                ));
+            }
+        }
+    }
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
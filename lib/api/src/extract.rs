@@ -0,0 +1,133 @@
+//! JSON request bodies with errors that name the offending field
+//!
+//! `web::Json` rejects a body that does not fit the request type with
+//! serde's message alone, which rarely says where the problem is in a large
+//! batch. [`TypedJson`] deserializes through `serde_path_to_error` and
+//! answers `422 Unprocessable Entity` with the path of the field, such as
+//! `operations[2].upsert.points[0].id`. Bodies that are not JSON at all are
+//! still rejected by `web::Json`.
+//!
+//! [`Body`] deserializes the same way but answers `400 Bad Request` in the
+//! shape of the other parse errors, with the path in `status.path`.
+//!
+//! Both also take MessagePack bodies with the same schema (see
+//! [`crate::content`]). Both deserialize in the server's [`ParseMode`].
+
+use actix_web::dev::Payload;
+use actix_web::{error, web, FromRequest, HttpRequest, HttpResponse};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use std::fmt::Display;
+use std::ops::Deref;
+use std::time::Instant;
+use crate::content::has_msgpack_body;
+use crate::parsing::{self, ParseError, ParseMode};
+use crate::rest::qdrant_parse_error;
+
+/// The parse mode of the server handling `req`
+pub(crate) fn parse_mode(req: &HttpRequest) -> ParseMode {
+    req.app_data::<web::Data<ParseMode>>().map_or_else(ParseMode::default, |mode| *mode.get_ref())
+}
+
+/// A JSON body deserialized into `T`
+pub(crate) struct TypedJson<T>(pub T);
+
+impl<T> Deref for TypedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for TypedJson<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let mode = parse_mode(req);
+        if has_msgpack_body(req) {
+            let bytes = web::Bytes::from_request(req, payload);
+            return Box::pin(async move {
+                let bytes = bytes.await?;
+                let mut deserializer = rmp_serde::Deserializer::new(&bytes[..]);
+                parsing::with_mode(mode, || serde_path_to_error::deserialize(&mut deserializer))
+                    .map(TypedJson)
+                    .map_err(unprocessable)
+            });
+        }
+        let json = web::Json::<serde_json::Value>::from_request(req, payload);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            parsing::with_mode(mode, || serde_path_to_error::deserialize(value))
+                .map(TypedJson)
+                .map_err(unprocessable)
+        })
+    }
+}
+
+/// A JSON or MessagePack body deserialized into `T`, refused with the path
+/// of the field that does not fit
+pub(crate) struct Body<T>(pub T);
+
+impl<T> Body<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Body<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Body<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let mode = parse_mode(req);
+        if has_msgpack_body(req) {
+            let bytes = web::Bytes::from_request(req, payload);
+            return Box::pin(async move {
+                let bytes = bytes.await?;
+                let mut deserializer = rmp_serde::Deserializer::new(&bytes[..]);
+                parsing::with_mode(mode, || serde_path_to_error::deserialize(&mut deserializer))
+                    .map(Body)
+                    .map_err(bad_request)
+            });
+        }
+        let json = web::Json::<serde_json::Value>::from_request(req, payload);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            parsing::with_mode(mode, || serde_path_to_error::deserialize(value))
+                .map(Body)
+                .map_err(bad_request)
+        })
+    }
+}
+
+fn bad_request<E: Display>(err: serde_path_to_error::Error<E>) -> actix_web::Error {
+    let error = ParseError::new(err.path().to_string(), err.inner().to_string());
+    let response = qdrant_parse_error(&error, Instant::now());
+    error::InternalError::from_response(error.to_string(), response).into()
+}
+
+fn unprocessable<E: Display>(err: serde_path_to_error::Error<E>) -> actix_web::Error {
+    let path = err.path().to_string();
+    let message = if path == "." {
+        format!("Unprocessable request: {}", err.inner())
+    } else {
+        format!("Unprocessable request: {}: {}", path, err.inner())
+    };
+    let body = serde_json::json!({
+        "status": {
+            "error": message
+        },
+        "time": 0.0
+    });
+    error::InternalError::from_response(message, HttpResponse::UnprocessableEntity().json(body)).into()
+}
@@ -0,0 +1,142 @@
+//! Entry points for fuzzing the hand-rolled request parsers
+//!
+//! Each [`Target`] takes arbitrary bytes, as a fuzzer produces them, and
+//! runs them through one parser the way a request would, then through the
+//! collection code the parsed request reaches. Inputs that are not JSON or
+//! that the parser rejects are fine; a target panics only on a bug, such as
+//! a parser or the collection panicking, or strict and lenient filter
+//! parsing disagreeing on an input both accept.
+//!
+//! The cargo-fuzz crate in `fuzz/` calls the targets, and its seed corpus
+//! lives in `fuzz/corpus/<target>/`. [`replay_corpus`] runs a corpus
+//! through the targets, so an input that once crashed keeps being checked
+//! by `cargo test` once it is added there. Lenient parsing logs what it
+//! skips on stderr; pass `-close_fd_mask=2` to libFuzzer to silence it.
+
+use crate::core_requests::{self, try_parse_filter};
+use crate::parsing::ParseMode;
+use std::io;
+use std::path::Path;
+use vectx_core::{Collection, CollectionConfig, Distance, KeywordParams, Point, PointId, Projection, Vector};
+
+/// A parser to fuzz
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// A JSON filter, as in searches
+    Filter,
+    /// A point with its vector, as in `PUT /collections/{name}/points`
+    PointVector,
+    /// A batch update, as in `POST /collections/{name}/points/batch`
+    BatchUpdate,
+}
+
+impl Target {
+    pub const ALL: [Target; 3] = [Target::Filter, Target::PointVector, Target::BatchUpdate];
+
+    /// Name of the target's fuzz target and corpus directory
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Filter => "filter",
+            Self::PointVector => "point_vector",
+            Self::BatchUpdate => "batch_update",
+        }
+    }
+
+    pub fn run(self, data: &[u8]) {
+        match self {
+            Self::Filter => filter(data),
+            Self::PointVector => point_vector(data),
+            Self::BatchUpdate => batch_update(data),
+        }
+    }
+}
+
+/// Parse a filter in both modes and evaluate it on a small collection
+pub fn filter(data: &[u8]) {
+    let Ok(json) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return;
+    };
+    let lenient = try_parse_filter(&json, ParseMode::Lenient)
+        .expect("lenient filter parsing never fails");
+    if let Ok(strict) = try_parse_filter(&json, ParseMode::Strict) {
+        // Nothing is skipped when strict parsing succeeds
+        assert_eq!(format!("{:?}", strict), format!("{:?}", lenient), "strict and lenient filters differ");
+    }
+
+    let collection = sample_collection();
+    if let Some(filter) = core_requests::build_filter(lenient, None, Some(&collection)) {
+        let _ = collection.iter_filtered(Some(filter.as_ref()), Projection::IDS).count();
+        let _ = collection.search(&Vector::new(vec![1.0, 0.0, 0.0]), 10, Some(filter.as_ref()));
+    }
+}
+
+/// Parse a point as an upsert does and store it
+pub fn point_vector(data: &[u8]) {
+    let Ok(input) = crate::rest::parse_point(data) else {
+        return;
+    };
+    let collection = sample_collection();
+    let _ = core_requests::upsert_points(&collection, vec![input], None);
+}
+
+/// Parse a batch update and apply it atomically
+pub fn batch_update(data: &[u8]) {
+    let collection = sample_collection();
+    if let Ok(operations) = crate::rest::parse_batch_update(data, &collection) {
+        let _ = collection.apply_atomic(operations);
+    }
+}
+
+/// A collection of 3-dimensional vectors with a few points and a keyword
+/// index that ignores case, for the parsed requests to run against
+fn sample_collection() -> Collection {
+    let collection = Collection::new(CollectionConfig {
+        name: "fuzz".to_string(),
+        vector_dim: 3,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    let _ = collection.create_keyword_index("city", KeywordParams { lowercase: true, normalize: true });
+    let payloads = [
+        Some(serde_json::json!({"city": "Berlin", "count": 3, "price": 1.5, "tags": ["a", "b"], "meta": {"x": 1}})),
+        Some(serde_json::json!({"city": "bern", "count": null, "tags": []})),
+        None,
+    ];
+    for (id, payload) in payloads.into_iter().enumerate() {
+        let vector = Vector::new(vec![1.0, id as f32, 0.5]);
+        let _ = collection.upsert(Point::new(PointId::Integer(id as u64), vector, payload));
+    }
+    collection
+}
+
+/// Run every file in `dir` through `target` and return how many ran; a
+/// missing directory has none. A panicking input is named on stderr before
+/// the panic goes on.
+pub fn replay(target: Target, dir: &Path) -> io::Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<_>>()?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+    for path in &paths {
+        let data = std::fs::read(path)?;
+        if let Err(panic) = std::panic::catch_unwind(|| target.run(&data)) {
+            eprintln!("Fuzz target {} panicked on {}", target.name(), path.display());
+            std::panic::resume_unwind(panic);
+        }
+    }
+    Ok(paths.len())
+}
+
+/// [`replay`] each target on its directory under `root`, e.g. `fuzz/corpus`
+pub fn replay_corpus(root: &Path) -> io::Result<usize> {
+    let mut count = 0;
+    for target in Target::ALL {
+        count += replay(target, &root.join(target.name()))?;
+    }
+    Ok(count)
+}
@@ -1,15 +1,88 @@
+// tonic::Status is large, but it is the error type every service method returns
+#![allow(clippy::result_large_err)]
+
 use std::sync::Arc;
 use std::time::Instant;
+use futures_util::StreamExt;
 use tonic::{Request, Response, Status};
-use vectx_storage::StorageManager;
-use vectx_core::{Point, PointId, Vector, Distance as CoreDistance};
+use vectx_storage::{Permission, RecoverOptions, SnapshotPriority, StorageManager};
+use crate::compression::grpc_compression;
+use crate::audit::GrpcAudit;
+use crate::auth::{authorize, authorize_filtered};
+use crate::core_requests::{self, PointInput, RecommendRequest as CoreRecommendRequest, RequestError, SearchRequest as CoreSearchRequest};
+use vectx_core::{Collection, FilterCondition, HnswParams, HnswParamsDiff, KeywordParams, PayloadIndexType, Point, PointId, Projection, Vector, Distance as CoreDistance};
 
 pub mod vectx {
     tonic::include_proto!("vectx");
 }
 
+pub mod health {
+    tonic::include_proto!("grpc.health.v1");
+}
+
 use vectx::*;
 
+/// Refuse a write while `/locks` forbids writes
+fn check_write_lock(storage: &StorageManager) -> Result<(), Status> {
+    match storage.write_lock_error() {
+        Some(message) => Err(Status::permission_denied(message)),
+        None => Ok(()),
+    }
+}
+
+/// Refuse a vector write while the collection has as many pending points as
+/// the storage admits, as the REST API does with 429
+fn check_admission(storage: &StorageManager, collection: &Collection) -> Result<(), Status> {
+    match storage.admission_error(collection) {
+        Some(message) => Err(Status::unavailable(message)),
+        None => Ok(()),
+    }
+}
+
+impl From<RequestError> for Status {
+    fn from(error: RequestError) -> Self {
+        match error {
+            RequestError::NotFound(message) => Status::not_found(message),
+            RequestError::Invalid(message) => Status::invalid_argument(message),
+            RequestError::Internal(message) => Status::internal(message),
+        }
+    }
+}
+
+/// Apply a change of HNSW parameters, rejecting invalid results
+fn update_hnsw_params(collection: &Collection, diff: &HnswParamsDiff) -> Result<bool, Status> {
+    let params = collection.hnsw_params().apply(diff);
+    params.validate().map_err(Status::invalid_argument)?;
+    collection.set_hnsw_params(params).map_err(|e| match e {
+        vectx_core::Error::Rebuilding => Status::failed_precondition(e.to_string()),
+        e => Status::internal(e.to_string()),
+    })
+}
+
+/// Index type of a payload schema type; keyword for types without an index
+/// of their own, as over REST
+fn payload_index_type(schema_type: PayloadSchemaType) -> PayloadIndexType {
+    match schema_type {
+        PayloadSchemaType::Integer => PayloadIndexType::Integer,
+        PayloadSchemaType::Float => PayloadIndexType::Float,
+        PayloadSchemaType::Geo => PayloadIndexType::Geo,
+        PayloadSchemaType::Text => PayloadIndexType::Text,
+        PayloadSchemaType::Bool => PayloadIndexType::Bool,
+        _ => PayloadIndexType::Keyword,
+    }
+}
+
+fn payload_schema_type(index_type: &PayloadIndexType) -> PayloadSchemaType {
+    match index_type {
+        PayloadIndexType::Keyword => PayloadSchemaType::Keyword,
+        PayloadIndexType::Integer => PayloadSchemaType::Integer,
+        PayloadIndexType::Float => PayloadSchemaType::Float,
+        PayloadIndexType::Bool => PayloadSchemaType::Bool,
+        PayloadIndexType::Geo => PayloadSchemaType::Geo,
+        PayloadIndexType::Text => PayloadSchemaType::Text,
+    }
+}
+
 // ============================================================================
 // Qdrant Service (Health Check)
 // ============================================================================
@@ -30,6 +103,77 @@ impl vectx::qdrant_server::Qdrant for QdrantService {
     }
 }
 
+// ============================================================================
+// Health Service (grpc.health.v1)
+// ============================================================================
+
+/// Services a health check may name; "" stands for the whole server
+const HEALTH_SERVICES: &[&str] = &["", "vectx.Qdrant", "vectx.Collections", "vectx.Points", "vectx.Snapshots"];
+
+/// How often `Watch` checks whether the status changed
+const HEALTH_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Standard gRPC health checks: NOT_SERVING whenever `/readyz` would fail,
+/// including while the node is past its load limits
+pub struct HealthService {
+    storage: Arc<StorageManager>,
+}
+
+impl HealthService {
+    pub fn new(storage: Arc<StorageManager>) -> Self {
+        Self { storage }
+    }
+
+    fn status(storage: &StorageManager) -> health::health_check_response::ServingStatus {
+        use health::health_check_response::ServingStatus;
+        if storage.readiness_issues().is_empty() {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl health::health_server::Health for HealthService {
+    async fn check(
+        &self,
+        request: Request<health::HealthCheckRequest>,
+    ) -> Result<Response<health::HealthCheckResponse>, Status> {
+        if !HEALTH_SERVICES.contains(&request.get_ref().service.as_str()) {
+            return Err(Status::not_found("unknown service"));
+        }
+        Ok(Response::new(health::HealthCheckResponse { status: Self::status(&self.storage) as i32 }))
+    }
+
+    type WatchStream = std::pin::Pin<Box<dyn futures_util::Stream<Item = Result<health::HealthCheckResponse, Status>> + Send>>;
+
+    /// Sends the status now and then each time it changes
+    async fn watch(
+        &self,
+        request: Request<health::HealthCheckRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        use health::health_check_response::ServingStatus;
+        let known = HEALTH_SERVICES.contains(&request.get_ref().service.as_str());
+        let storage = self.storage.clone();
+        let updates = futures_util::stream::unfold(None, move |last: Option<ServingStatus>| {
+            let storage = storage.clone();
+            async move {
+                loop {
+                    if last.is_some() {
+                        tokio::time::sleep(HEALTH_WATCH_INTERVAL).await;
+                    }
+                    let status = if known { Self::status(&storage) } else { ServingStatus::ServiceUnknown };
+                    if last != Some(status) {
+                        return Some((Ok(health::HealthCheckResponse { status: status as i32 }), Some(status)));
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(updates)))
+    }
+}
+
 // ============================================================================
 // Collections Service
 // ============================================================================
@@ -51,6 +195,7 @@ impl vectx::collections_server::Collections for CollectionsService {
         request: Request<GetCollectionInfoRequest>,
     ) -> Result<Response<GetCollectionInfoResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
         let req = request.into_inner();
         
         let collection = self.storage.get_collection(&req.collection_name)
@@ -58,10 +203,30 @@ impl vectx::collections_server::Collections for CollectionsService {
         
         let points_count = collection.count() as u64;
         let vector_dim = collection.vector_dim() as u64;
+        let hnsw_params = collection.hnsw_params();
+        let index_points = collection.payload_index_points();
+        let keyword_params = collection.keyword_params();
+        let payload_schema = collection.get_payload_indexes().into_iter()
+            .map(|(field, index_type)| {
+                let params = keyword_params.get(&field).map(|params| PayloadSchemaParams {
+                    params: Some(payload_schema_params::Params::KeywordIndexParams(KeywordIndexParams {
+                        lowercase: Some(params.lowercase),
+                        normalize: Some(params.normalize),
+                    })),
+                });
+                let info = PayloadIndexInfo {
+                    data_type: payload_schema_type(&index_type) as i32,
+                    params,
+                    points: index_points.get(&field).map(|&n| n as u64),
+                };
+                (field, info)
+            })
+            .collect();
         let distance = match collection.distance() {
             CoreDistance::Cosine => Distance::Cosine,
             CoreDistance::Euclidean => Distance::Euclid,
             CoreDistance::Dot => Distance::Dot,
+            CoreDistance::Hamming => Distance::Hamming,
         };
 
         let result = CollectionInfo {
@@ -85,9 +250,9 @@ impl vectx::collections_server::Collections for CollectionsService {
                 shard_number: 1,
                 replication_factor: 1,
                 hnsw_config: Some(HnswConfig {
-                    m: 16,
-                    ef_construct: 100,
-                    full_scan_threshold: 10000,
+                    m: hnsw_params.m as u64,
+                    ef_construct: hnsw_params.ef_construct as u64,
+                    full_scan_threshold: hnsw_params.full_scan_threshold as u64,
                     max_indexing_threads: None,
                     on_disk: Some(false),
                 }),
@@ -105,7 +270,7 @@ impl vectx::collections_server::Collections for CollectionsService {
                     flush_interval_sec: 5,
                 }),
             }),
-            payload_schema: Default::default(),
+            payload_schema,
         };
 
         Ok(Response::new(GetCollectionInfoResponse {
@@ -116,8 +281,9 @@ impl vectx::collections_server::Collections for CollectionsService {
 
     async fn list(
         &self,
-        _request: Request<ListCollectionsRequest>,
+        request: Request<ListCollectionsRequest>,
     ) -> Result<Response<ListCollectionsResponse>, Status> {
+        authorize(&self.storage, &request, None, Permission::Read)?;
         let start_time = Instant::now();
         let collections = self.storage.list_collections();
         
@@ -137,6 +303,9 @@ impl vectx::collections_server::Collections for CollectionsService {
         request: Request<CreateCollection>,
     ) -> Result<Response<CollectionOperationResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Admin)?;
+        let audit = GrpcAudit::start(&self.storage, &request, "Collections/Create", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
         
         let (vector_dim, distance) = if let Some(vectors_config) = req.vectors_config {
@@ -146,6 +315,7 @@ impl vectx::collections_server::Collections for CollectionsService {
                         Ok(Distance::Cosine) => CoreDistance::Cosine,
                         Ok(Distance::Euclid) => CoreDistance::Euclidean,
                         Ok(Distance::Dot) => CoreDistance::Dot,
+                        Ok(Distance::Hamming) => CoreDistance::Hamming,
                         _ => CoreDistance::Cosine,
                     };
                     (params.size as usize, dist)
@@ -157,6 +327,7 @@ impl vectx::collections_server::Collections for CollectionsService {
                             Ok(Distance::Cosine) => CoreDistance::Cosine,
                             Ok(Distance::Euclid) => CoreDistance::Euclidean,
                             Ok(Distance::Dot) => CoreDistance::Dot,
+                            Ok(Distance::Hamming) => CoreDistance::Hamming,
                             _ => CoreDistance::Cosine,
                         };
                         (params.size as usize, dist)
@@ -169,6 +340,12 @@ impl vectx::collections_server::Collections for CollectionsService {
         } else {
             return Err(Status::invalid_argument("Vector configuration required"));
         };
+        if vector_dim == 0 {
+            return Err(Status::invalid_argument("Vector size must be positive"));
+        }
+        if distance == CoreDistance::Hamming && vector_dim % 8 != 0 {
+            return Err(Status::invalid_argument("Hamming vector size must be a multiple of 8 bits"));
+        }
 
         let config = vectx_core::CollectionConfig {
             name: req.collection_name,
@@ -178,13 +355,29 @@ impl vectx::collections_server::Collections for CollectionsService {
             enable_bm25: false,
         };
 
-        self.storage.create_collection(config)
+        // The fields of HnswConfig are not optional; zero leaves a parameter unset
+        let hnsw_diff = req.hnsw_config.map(|hnsw| HnswParamsDiff {
+            m: (hnsw.m > 0).then_some(hnsw.m as usize),
+            ef_construct: (hnsw.ef_construct > 0).then_some(hnsw.ef_construct as usize),
+            full_scan_threshold: (hnsw.full_scan_threshold > 0).then_some(hnsw.full_scan_threshold as usize),
+        });
+        if let Some(diff) = &hnsw_diff {
+            HnswParams::default().apply(diff).validate().map_err(Status::invalid_argument)?;
+        }
+
+        let collection = self.storage.create_collection(config)
             .map_err(|e| Status::internal(e.to_string()))?;
+        if let Some(diff) = &hnsw_diff {
+            if update_hnsw_params(&collection, diff)? {
+                self.storage.save_collection_config(&collection)
+                    .map_err(|e| Status::internal(e.to_string()))?;
+            }
+        }
 
-        Ok(Response::new(CollectionOperationResponse {
+        audit.finish(Ok(Response::new(CollectionOperationResponse {
             result: true,
             time: start_time.elapsed().as_secs_f64(),
-        }))
+        }))).await
     }
 
     async fn update(
@@ -192,17 +385,114 @@ impl vectx::collections_server::Collections for CollectionsService {
         request: Request<UpdateCollection>,
     ) -> Result<Response<CollectionOperationResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Admin)?;
+        let audit = GrpcAudit::start(&self.storage, &request, "Collections/Update", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
         
-        // Verify collection exists
-        if self.storage.get_collection(&req.collection_name).is_none() {
-            return Err(Status::not_found("Collection not found"));
+        let collection = self.storage.get_collection(&req.collection_name)
+            .ok_or_else(|| Status::not_found("Collection not found"))?;
+
+        if let Some(params) = req.params {
+            let replication = crate::consistency::ReplicationParams {
+                shard_number: None,
+                replication_factor: params.replication_factor,
+                write_consistency_factor: params.write_consistency_factor,
+            };
+            replication.validate().map_err(Status::invalid_argument)?;
+        }
+
+        // Optimizer settings are accepted as-is: there are no segments to optimize
+        if let Some(hnsw) = req.hnsw_config {
+            let diff = HnswParamsDiff {
+                m: hnsw.m.map(|m| m as usize),
+                ef_construct: hnsw.ef_construct.map(|ef| ef as usize),
+                full_scan_threshold: hnsw.full_scan_threshold.map(|t| t as usize),
+            };
+            if update_hnsw_params(&collection, &diff)? {
+                self.storage.save_collection_config(&collection)
+                    .map_err(|e| Status::internal(e.to_string()))?;
+            }
+        }
+
+        audit.finish(Ok(Response::new(CollectionOperationResponse {
+            result: true,
+            time: start_time.elapsed().as_secs_f64(),
+        }))).await
+    }
+
+    async fn update_aliases(
+        &self,
+        request: Request<ChangeAliases>,
+    ) -> Result<Response<CollectionOperationResponse>, Status> {
+        let start_time = Instant::now();
+        authorize(&self.storage, &request, None, Permission::Admin)?;
+        let audit = GrpcAudit::start(&self.storage, &request, "Collections/UpdateAliases", None);
+        check_write_lock(&self.storage)?;
+        let req = request.into_inner();
+
+        // Applied in order, stopping at the first that fails
+        for (idx, operation) in req.actions.into_iter().enumerate() {
+            let (applied, alias) = match operation.action {
+                Some(alias_operations::Action::CreateAlias(create)) => {
+                    (self.storage.create_alias(&create.alias_name, &create.collection_name), create.alias_name)
+                }
+                Some(alias_operations::Action::RenameAlias(rename)) => {
+                    (self.storage.rename_alias(&rename.old_alias_name, &rename.new_alias_name), rename.old_alias_name)
+                }
+                Some(alias_operations::Action::DeleteAlias(delete)) => {
+                    (self.storage.delete_alias(&delete.alias_name), delete.alias_name)
+                }
+                None => return Err(Status::invalid_argument(format!("action {}: no alias operation", idx))),
+            };
+            match applied {
+                Ok(true) => {}
+                Ok(false) => return Err(Status::not_found(format!("action {}: alias '{}' not found", idx, alias))),
+                Err(e @ vectx_core::Error::CollectionNotFound(_)) => {
+                    return Err(Status::not_found(format!("action {}: {}", idx, e)));
+                }
+                Err(e) => return Err(Status::already_exists(format!("action {}: {}", idx, e))),
+            }
         }
 
-        // Update not fully implemented - just return success
-        Ok(Response::new(CollectionOperationResponse {
+        audit.finish(Ok(Response::new(CollectionOperationResponse {
             result: true,
             time: start_time.elapsed().as_secs_f64(),
+        }))).await
+    }
+
+    async fn list_collection_aliases(
+        &self,
+        request: Request<ListCollectionAliasesRequest>,
+    ) -> Result<Response<ListAliasesResponse>, Status> {
+        let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
+        let collection_name = request.into_inner().collection_name;
+        let aliases = self.storage.list_collection_aliases(&collection_name)
+            .into_iter()
+            .map(|alias_name| AliasDescription { alias_name, collection_name: collection_name.clone() })
+            .collect();
+
+        Ok(Response::new(ListAliasesResponse {
+            aliases,
+            time: start_time.elapsed().as_secs_f64(),
+        }))
+    }
+
+    async fn list_aliases(
+        &self,
+        request: Request<ListAliasesRequest>,
+    ) -> Result<Response<ListAliasesResponse>, Status> {
+        let start_time = Instant::now();
+        authorize(&self.storage, &request, None, Permission::Read)?;
+        let aliases = self.storage.list_aliases()
+            .into_iter()
+            .map(|(alias_name, collection_name)| AliasDescription { alias_name, collection_name })
+            .collect();
+
+        Ok(Response::new(ListAliasesResponse {
+            aliases,
+            time: start_time.elapsed().as_secs_f64(),
         }))
     }
 
@@ -211,16 +501,19 @@ impl vectx::collections_server::Collections for CollectionsService {
         request: Request<DeleteCollection>,
     ) -> Result<Response<CollectionOperationResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Admin)?;
+        let audit = GrpcAudit::start(&self.storage, &request, "Collections/Delete", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
         
-        match self.storage.delete_collection(&req.collection_name) {
+        audit.finish(match self.storage.delete_collection(&req.collection_name) {
             Ok(true) => Ok(Response::new(CollectionOperationResponse {
                 result: true,
                 time: start_time.elapsed().as_secs_f64(),
             })),
             Ok(false) => Err(Status::not_found("Collection not found")),
             Err(e) => Err(Status::internal(e.to_string())),
-        }
+        }).await
     }
 
     async fn collection_exists(
@@ -228,6 +521,7 @@ impl vectx::collections_server::Collections for CollectionsService {
         request: Request<CollectionExistsRequest>,
     ) -> Result<Response<CollectionExistsResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
         let req = request.into_inner();
         
         let exists = self.storage.collection_exists(&req.collection_name);
@@ -252,6 +546,146 @@ impl PointsService {
         Self { storage }
     }
 
+    /// Validate the write ordering and pick the status to report: the update
+    /// has been applied by the time we respond, so waiting clients see
+    /// `Completed`
+    fn update_status(wait: Option<bool>, ordering: Option<&WriteOrdering>) -> Result<UpdateStatus, Status> {
+        if let Some(ordering) = ordering {
+            WriteOrderingType::try_from(ordering.r#type)
+                .map_err(|_| Status::invalid_argument(format!("Unknown write ordering {}", ordering.r#type)))?;
+        }
+        Ok(if wait.unwrap_or(false) { UpdateStatus::Completed } else { UpdateStatus::Acknowledged })
+    }
+
+    /// The vector to search with: the `binary` bytes or the byte values in
+    /// `data` in Hamming collections, packed; `data` otherwise
+    fn input_vector(collection: &vectx_core::Collection, vector: &vectx::Vector) -> Result<Vector, Status> {
+        let binary = (!vector.binary.is_empty()).then_some(vector.binary.as_slice());
+        core_requests::dense_vector(collection, &vector.data, binary).map_err(Status::invalid_argument)
+    }
+
+    /// The only vector of a `VectorInput`. vectX keeps one dense vector per
+    /// point, so several named vectors are refused.
+    fn single_vector(input: Option<VectorInput>) -> Result<Option<vectx::Vector>, Status> {
+        match input.and_then(|input| input.variant) {
+            Some(vector_input::Variant::Dense(vector)) => Ok(Some(vector)),
+            Some(vector_input::Variant::Named(named)) if named.vectors.len() > 1 => {
+                Err(Status::invalid_argument("Only one named vector per point is supported"))
+            }
+            Some(vector_input::Variant::Named(named)) => Ok(named.vectors.into_values().next()),
+            None => Ok(None),
+        }
+    }
+
+    /// The shared model of an upserted point
+    fn point_input(point: PointStruct) -> Result<PointInput, Status> {
+        let id = point.id.as_ref()
+            .and_then(Self::parse_point_id)
+            .ok_or_else(|| Status::invalid_argument("ID required"))?;
+        let id = match id.parse::<u64>() {
+            Ok(num) => PointId::Integer(num),
+            Err(_) => PointId::String(id),
+        };
+        let vector = Self::single_vector(point.vectors)?
+            .ok_or_else(|| Status::invalid_argument("vector required"))?;
+        let payload = (!point.payload.is_empty()).then(|| {
+            serde_json::Value::Object(point.payload.iter()
+                .map(|(k, v)| (k.clone(), Self::proto_value_to_json(v)))
+                .collect())
+        });
+        let mut input = PointInput::new(id, vector.data, payload);
+        input.binary = (!vector.binary.is_empty()).then_some(vector.binary);
+        Ok(input)
+    }
+
+    /// A request filter as a condition; None without a filter or for one
+    /// without clauses. A condition that does not convert is an error, as
+    /// leaving it out would widen the filter.
+    fn filter_condition(filter: Option<&vectx::Filter>) -> Result<Option<FilterCondition>, Status> {
+        let Some(filter) = filter else { return Ok(None) };
+        let conditions = |conditions: &[Condition]| -> Result<Vec<FilterCondition>, Status> {
+            conditions.iter().map(Self::condition).collect()
+        };
+        Ok(core_requests::combine_clauses(
+            conditions(&filter.must)?,
+            conditions(&filter.should)?,
+            conditions(&filter.must_not)?,
+        ))
+    }
+
+    fn condition(condition: &Condition) -> Result<FilterCondition, Status> {
+        match &condition.condition_one_of {
+            Some(condition::ConditionOneOf::Field(field)) => Self::field_condition(field),
+            Some(condition::ConditionOneOf::Filter(filter)) => Self::filter_condition(Some(filter))?
+                .ok_or_else(|| Status::invalid_argument("A nested filter needs at least one condition")),
+            Some(condition::ConditionOneOf::HasId(_)) => Err(Status::invalid_argument("has_id conditions are not supported")),
+            None => Err(Status::invalid_argument("A condition needs a field or a nested filter")),
+        }
+    }
+
+    fn field_condition(field: &FieldCondition) -> Result<FilterCondition, Status> {
+        let key = field.key.as_str();
+        let no_values = || Status::invalid_argument(format!("The match on '{}' lists no values", key));
+        let matched = match field.r#match.as_ref().map(|m| m.match_value.as_ref()) {
+            None => None,
+            Some(None) => return Err(Status::invalid_argument(format!("The match on '{}' has no value", key))),
+            Some(Some(value)) => Some(match value {
+                r#match::MatchValue::Keyword(keyword) => core_requests::match_any(key, [serde_json::json!(keyword)]).ok_or_else(no_values)?,
+                r#match::MatchValue::Integer(integer) => core_requests::match_any(key, [serde_json::json!(integer)]).ok_or_else(no_values)?,
+                r#match::MatchValue::Boolean(boolean) => core_requests::match_any(key, [serde_json::json!(boolean)]).ok_or_else(no_values)?,
+                r#match::MatchValue::Keywords(keywords) => {
+                    core_requests::match_any(key, keywords.strings.iter().map(|k| serde_json::json!(k))).ok_or_else(no_values)?
+                }
+                r#match::MatchValue::Integers(integers) => {
+                    core_requests::match_any(key, integers.integers.iter().map(|i| serde_json::json!(i))).ok_or_else(no_values)?
+                }
+                r#match::MatchValue::ExceptKeywords(keywords) => {
+                    core_requests::match_except(key, keywords.strings.iter().map(|k| serde_json::json!(k)))
+                }
+                r#match::MatchValue::ExceptIntegers(integers) => {
+                    core_requests::match_except(key, integers.integers.iter().map(|i| serde_json::json!(i)))
+                }
+                r#match::MatchValue::AllKeywords(keywords) => {
+                    core_requests::match_all(key, keywords.strings.iter().map(|k| serde_json::json!(k))).ok_or_else(no_values)?
+                }
+                r#match::MatchValue::AllIntegers(integers) => {
+                    core_requests::match_all(key, integers.integers.iter().map(|i| serde_json::json!(i))).ok_or_else(no_values)?
+                }
+            }),
+        };
+        let range = match field.range.as_ref() {
+            None => None,
+            Some(r) => Some(core_requests::range_condition(key, r.gt, r.gte, r.lt, r.lte)
+                .ok_or_else(|| Status::invalid_argument(format!("The range on '{}' has no bound", key)))?),
+        };
+        match (matched, range) {
+            (Some(matched), Some(range)) => Ok(FilterCondition::And(vec![matched, range])),
+            (Some(condition), None) | (None, Some(condition)) => Ok(condition),
+            (None, None) => Err(Status::invalid_argument(format!("The condition on '{}' has neither a match nor a range", key))),
+        }
+    }
+
+    fn scored_point(point: Point, score: f32) -> ScoredPoint {
+        ScoredPoint {
+            id: Some(Self::to_proto_point_id(&point.id)),
+            payload: Self::payload_to_proto(point.payload.as_ref()),
+            score,
+            vectors: None,
+            version: Some(point.version),
+        }
+    }
+
+    /// A stored vector as clients receive it
+    fn output_vector(collection: &vectx_core::Collection, vector: &Vector) -> vectx::Vector {
+        match collection.distance() {
+            CoreDistance::Hamming => vectx::Vector {
+                data: Vec::new(),
+                binary: vectx_core::binary::unpack_bytes(vector, collection.vector_dim()),
+            },
+            _ => vectx::Vector { data: vector.as_slice().to_vec(), binary: Vec::new() },
+        }
+    }
+
     fn parse_point_id(id: &vectx::PointId) -> Option<String> {
         match &id.point_id_options {
             Some(point_id::PointIdOptions::Num(n)) => Some(n.to_string()),
@@ -260,6 +694,16 @@ impl PointsService {
         }
     }
 
+    /// Ids a selector lists; none for a filter
+    fn selected_ids(selector: Option<&PointsSelector>) -> Vec<String> {
+        match selector.and_then(|selector| selector.points_selector_one_of.as_ref()) {
+            Some(points_selector::PointsSelectorOneOf::Points(list)) => {
+                list.ids.iter().filter_map(Self::parse_point_id).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
     fn to_proto_point_id(id: &PointId) -> vectx::PointId {
         match id {
             PointId::String(s) => vectx::PointId {
@@ -296,6 +740,17 @@ impl PointsService {
         }
     }
 
+    fn payload_to_proto(payload: Option<&serde_json::Value>) -> std::collections::HashMap<String, vectx::Value> {
+        payload
+            .and_then(|p| p.as_object())
+            .map(|obj| {
+                obj.iter()
+                    .map(|(k, v)| (k.clone(), Self::json_to_proto_value(v)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     fn json_to_proto_value(value: &serde_json::Value) -> vectx::Value {
         let kind = match value {
             serde_json::Value::Null => Some(value::Kind::NullValue(0)),
@@ -303,10 +758,8 @@ impl PointsService {
             serde_json::Value::Number(n) => {
                 if let Some(i) = n.as_i64() {
                     Some(value::Kind::IntegerValue(i))
-                } else if let Some(f) = n.as_f64() {
-                    Some(value::Kind::DoubleValue(f))
                 } else {
-                    None
+                    n.as_f64().map(value::Kind::DoubleValue)
                 }
             }
             serde_json::Value::String(s) => Some(value::Kind::StringValue(s.clone())),
@@ -327,68 +780,40 @@ impl PointsService {
 
 #[tonic::async_trait]
 impl vectx::points_server::Points for PointsService {
+    type WatchStream = std::pin::Pin<Box<dyn futures_util::Stream<Item = Result<WatchEvent, Status>> + Send>>;
+
     async fn upsert(
         &self,
         request: Request<UpsertPoints>,
     ) -> Result<Response<PointsOperationResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Write)?;
+        let mut audit = GrpcAudit::start(&self.storage, &request, "Points/Upsert", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
+        audit.points(req.points.iter().filter_map(|p| p.id.as_ref()).filter_map(Self::parse_point_id));
+        let status = Self::update_status(req.wait, req.ordering.as_ref())?;
         
         let collection = self.storage.get_collection(&req.collection_name)
             .ok_or_else(|| Status::not_found("Collection not found"))?;
+        check_admission(&self.storage, &collection)?;
 
-        let points: Result<Vec<Point>, Status> = req.points.into_iter().map(|p| {
-            let id = p.id.as_ref()
-                .and_then(Self::parse_point_id)
-                .ok_or_else(|| Status::invalid_argument("Point ID required"))?;
-            
-            let point_id = if let Ok(num) = id.parse::<u64>() {
-                PointId::Integer(num)
-            } else {
-                PointId::String(id)
-            };
-            
-            let vector_data = p.vectors.as_ref()
-                .and_then(|vi| match &vi.variant {
-                    Some(vector_input::Variant::Dense(v)) => Some(v.data.clone()),
-                    Some(vector_input::Variant::Named(nv)) => {
-                        nv.vectors.values().next().map(|v| v.data.clone())
-                    }
-                    None => None,
-                })
-                .ok_or_else(|| Status::invalid_argument("Vector required"))?;
-            
-            let payload = if p.payload.is_empty() {
-                None
-            } else {
-                let json_map: serde_json::Map<String, serde_json::Value> = p.payload.iter()
-                    .map(|(k, v)| (k.clone(), Self::proto_value_to_json(v)))
-                    .collect();
-                Some(serde_json::Value::Object(json_map))
-            };
-            
-            let vector = Vector::new(vector_data);
-            Ok(Point::new(point_id, vector, payload))
-        }).collect();
-
-        let points = points?;
-        let count = points.len();
-        
-        if count > 1 {
-            collection.batch_upsert(points)
-                .map_err(|e| Status::internal(e.to_string()))?;
-        } else if let Some(point) = points.into_iter().next() {
-            collection.upsert(point)
-                .map_err(|e| Status::internal(e.to_string()))?;
-        }
+        let points = req.points.into_iter()
+            .enumerate()
+            .map(|(index, p)| {
+                Self::point_input(p)
+                    .map_err(|e| Status::invalid_argument(format!("Point at index {}: {}", index, e.message())))
+            })
+            .collect::<Result<Vec<PointInput>, Status>>()?;
+        core_requests::upsert_points(&collection, points, self.storage.max_payload_bytes())?;
 
-        Ok(Response::new(PointsOperationResponse {
+        audit.finish(Ok(Response::new(PointsOperationResponse {
             result: Some(UpdateResult {
                 operation_id: 0,
-                status: UpdateStatus::Acknowledged as i32,
+                status: status as i32,
             }),
             time: start_time.elapsed().as_secs_f64(),
-        }))
+        }))).await
     }
 
     async fn delete(
@@ -396,28 +821,44 @@ impl vectx::points_server::Points for PointsService {
         request: Request<DeletePoints>,
     ) -> Result<Response<PointsOperationResponse>, Status> {
         let start_time = Instant::now();
+        let claim = authorize_filtered(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Write)?;
+        let mut audit = GrpcAudit::start(&self.storage, &request, "Points/Delete", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
+        audit.points(Self::selected_ids(req.points.as_ref()));
+        let status = Self::update_status(req.wait, req.ordering.as_ref())?;
         
         let collection = self.storage.get_collection(&req.collection_name)
             .ok_or_else(|| Status::not_found("Collection not found"))?;
 
-        if let Some(points_selector) = req.points {
-            if let Some(points_selector::PointsSelectorOneOf::Points(list)) = points_selector.points_selector_one_of {
-                for point_id in list.ids {
-                    if let Some(id_str) = Self::parse_point_id(&point_id) {
-                        let _ = collection.delete(&id_str);
-                    }
-                }
+        let ids: Vec<String> = match req.points.and_then(|selector| selector.points_selector_one_of) {
+            Some(points_selector::PointsSelectorOneOf::Points(list)) => list.ids.iter()
+                .filter_map(Self::parse_point_id)
+                .filter(|id| match &claim {
+                    Some(claim) => collection.get(id).is_some_and(|p| claim.matches(&p)),
+                    None => true,
+                })
+                .collect(),
+            Some(points_selector::PointsSelectorOneOf::Filter(filter)) => {
+                // A filter without conditions would select every point
+                let condition = Self::filter_condition(Some(&filter))?
+                    .ok_or_else(|| Status::invalid_argument("A delete filter needs at least one condition"))?;
+                let filter = core_requests::build_filter(Some(condition), claim.as_ref(), Some(&collection));
+                collection.iter_filtered(filter.as_deref(), Projection::IDS)
+                    .map(|point| point.id.to_string())
+                    .collect()
             }
-        }
+            None => Vec::new(),
+        };
+        collection.delete_batch(&ids).map_err(|e| Status::internal(e.to_string()))?;
 
-        Ok(Response::new(PointsOperationResponse {
+        audit.finish(Ok(Response::new(PointsOperationResponse {
             result: Some(UpdateResult {
                 operation_id: 0,
-                status: UpdateStatus::Acknowledged as i32,
+                status: status as i32,
             }),
             time: start_time.elapsed().as_secs_f64(),
-        }))
+        }))).await
     }
 
     async fn get(
@@ -425,6 +866,7 @@ impl vectx::points_server::Points for PointsService {
         request: Request<GetPoints>,
     ) -> Result<Response<GetResponse>, Status> {
         let start_time = Instant::now();
+        let claim = authorize_filtered(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
         let req = request.into_inner();
         
         let collection = self.storage.get_collection(&req.collection_name)
@@ -433,7 +875,7 @@ impl vectx::points_server::Points for PointsService {
         let mut results = Vec::new();
         for point_id in req.ids {
             if let Some(id_str) = Self::parse_point_id(&point_id) {
-                if let Some(point) = collection.get(&id_str) {
+                if let Some(point) = collection.get(&id_str).filter(|p| claim.as_ref().map_or(true, |c| c.matches(p))) {
                     let payload: std::collections::HashMap<String, vectx::Value> = point.payload
                         .as_ref()
                         .and_then(|p| p.as_object())
@@ -448,10 +890,9 @@ impl vectx::points_server::Points for PointsService {
                         id: Some(Self::to_proto_point_id(&point.id)),
                         payload,
                         vectors: Some(VectorInput {
-                            variant: Some(vector_input::Variant::Dense(vectx::Vector {
-                                data: point.vector.as_slice().to_vec(),
-                            })),
+                            variant: Some(vector_input::Variant::Dense(Self::output_vector(&collection, &point.vector))),
                         }),
+                        version: Some(point.version),
                     });
                 }
             }
@@ -468,20 +909,24 @@ impl vectx::points_server::Points for PointsService {
         request: Request<SetPayloadPoints>,
     ) -> Result<Response<PointsOperationResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Write)?;
+        let mut audit = GrpcAudit::start(&self.storage, &request, "Points/SetPayload", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
+        audit.points(Self::selected_ids(req.points_selector.as_ref()));
         
         if self.storage.get_collection(&req.collection_name).is_none() {
             return Err(Status::not_found("Collection not found"));
         }
 
         // Payload update stub - not fully implemented
-        Ok(Response::new(PointsOperationResponse {
+        audit.finish(Ok(Response::new(PointsOperationResponse {
             result: Some(UpdateResult {
                 operation_id: 0,
                 status: UpdateStatus::Acknowledged as i32,
             }),
             time: start_time.elapsed().as_secs_f64(),
-        }))
+        }))).await
     }
 
     async fn delete_payload(
@@ -489,19 +934,23 @@ impl vectx::points_server::Points for PointsService {
         request: Request<DeletePayloadPoints>,
     ) -> Result<Response<PointsOperationResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Write)?;
+        let mut audit = GrpcAudit::start(&self.storage, &request, "Points/DeletePayload", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
+        audit.points(Self::selected_ids(req.points_selector.as_ref()));
         
         if self.storage.get_collection(&req.collection_name).is_none() {
             return Err(Status::not_found("Collection not found"));
         }
 
-        Ok(Response::new(PointsOperationResponse {
+        audit.finish(Ok(Response::new(PointsOperationResponse {
             result: Some(UpdateResult {
                 operation_id: 0,
                 status: UpdateStatus::Acknowledged as i32,
             }),
             time: start_time.elapsed().as_secs_f64(),
-        }))
+        }))).await
     }
 
     async fn clear_payload(
@@ -509,19 +958,23 @@ impl vectx::points_server::Points for PointsService {
         request: Request<ClearPayloadPoints>,
     ) -> Result<Response<PointsOperationResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Write)?;
+        let mut audit = GrpcAudit::start(&self.storage, &request, "Points/ClearPayload", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
+        audit.points(Self::selected_ids(req.points.as_ref()));
         
         if self.storage.get_collection(&req.collection_name).is_none() {
             return Err(Status::not_found("Collection not found"));
         }
 
-        Ok(Response::new(PointsOperationResponse {
+        audit.finish(Ok(Response::new(PointsOperationResponse {
             result: Some(UpdateResult {
                 operation_id: 0,
                 status: UpdateStatus::Acknowledged as i32,
             }),
             time: start_time.elapsed().as_secs_f64(),
-        }))
+        }))).await
     }
 
     async fn create_field_index(
@@ -529,19 +982,36 @@ impl vectx::points_server::Points for PointsService {
         request: Request<CreateFieldIndexCollection>,
     ) -> Result<Response<PointsOperationResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Write)?;
+        let audit = GrpcAudit::start(&self.storage, &request, "Points/CreateFieldIndex", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
         
-        if self.storage.get_collection(&req.collection_name).is_none() {
-            return Err(Status::not_found("Collection not found"));
-        }
+        let collection = self.storage.get_collection(&req.collection_name)
+            .ok_or_else(|| Status::not_found("Collection not found"))?;
+        let index_type = payload_index_type(req.field_type());
+        let keyword_params = match req.field_index_params.and_then(|p| p.params) {
+            Some(payload_schema_params::Params::KeywordIndexParams(params)) => KeywordParams {
+                lowercase: params.lowercase.unwrap_or(false),
+                normalize: params.normalize.unwrap_or(false),
+            },
+            _ => KeywordParams::default(),
+        };
+        let created = match index_type {
+            PayloadIndexType::Keyword => collection.create_keyword_index(&req.field_name, keyword_params),
+            index_type => collection.create_payload_index(&req.field_name, index_type),
+        };
+        created.map_err(|e| Status::internal(e.to_string()))?;
+        self.storage.save_collection_config(&collection)
+            .map_err(|e| Status::internal(e.to_string()))?;
 
-        Ok(Response::new(PointsOperationResponse {
+        audit.finish(Ok(Response::new(PointsOperationResponse {
             result: Some(UpdateResult {
-                operation_id: 0,
+                operation_id: collection.last_operation_id(),
                 status: UpdateStatus::Acknowledged as i32,
             }),
             time: start_time.elapsed().as_secs_f64(),
-        }))
+        }))).await
     }
 
     async fn delete_field_index(
@@ -549,19 +1019,27 @@ impl vectx::points_server::Points for PointsService {
         request: Request<DeleteFieldIndexCollection>,
     ) -> Result<Response<PointsOperationResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Write)?;
+        let audit = GrpcAudit::start(&self.storage, &request, "Points/DeleteFieldIndex", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
         
-        if self.storage.get_collection(&req.collection_name).is_none() {
-            return Err(Status::not_found("Collection not found"));
+        let collection = self.storage.get_collection(&req.collection_name)
+            .ok_or_else(|| Status::not_found("Collection not found"))?;
+        let removed = collection.delete_payload_index(&req.field_name)
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if removed {
+            self.storage.save_collection_config(&collection)
+                .map_err(|e| Status::internal(e.to_string()))?;
         }
 
-        Ok(Response::new(PointsOperationResponse {
+        audit.finish(Ok(Response::new(PointsOperationResponse {
             result: Some(UpdateResult {
-                operation_id: 0,
+                operation_id: collection.last_operation_id(),
                 status: UpdateStatus::Acknowledged as i32,
             }),
             time: start_time.elapsed().as_secs_f64(),
-        }))
+        }))).await
     }
 
     async fn search(
@@ -569,38 +1047,33 @@ impl vectx::points_server::Points for PointsService {
         request: Request<SearchPoints>,
     ) -> Result<Response<SearchResponse>, Status> {
         let start_time = Instant::now();
+        let claim = authorize_filtered(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
         let req = request.into_inner();
         
         let collection = self.storage.get_collection(&req.collection_name)
             .ok_or_else(|| Status::not_found("Collection not found"))?;
 
-        let query = Vector::new(req.vector);
-        let limit = req.limit as usize;
-        
-        let results = collection.search(&query, limit, None);
-        
-        let scored_points: Vec<ScoredPoint> = results.into_iter().map(|(point, score)| {
-            let payload: std::collections::HashMap<String, vectx::Value> = point.payload
-                .as_ref()
-                .and_then(|p| p.as_object())
-                .map(|obj| {
-                    obj.iter()
-                        .map(|(k, v)| (k.clone(), Self::json_to_proto_value(v)))
-                        .collect()
-                })
-                .unwrap_or_default();
-
-            ScoredPoint {
-                id: Some(Self::to_proto_point_id(&point.id)),
-                payload,
-                score,
-                vectors: None,
-                version: Some(0),
-            }
-        }).collect();
+        let query = match &req.binary_vector {
+            Some(binary) => Self::input_vector(&collection, &vectx::Vector { data: Vec::new(), binary: binary.clone() })?,
+            None => Self::input_vector(&collection, &vectx::Vector { data: req.vector, binary: Vec::new() })?,
+        };
+        let request = CoreSearchRequest {
+            vector: query,
+            limit: (req.limit > 0).then_some(req.limit as usize),
+            offset: req.offset.unwrap_or(0) as usize,
+            score_threshold: req.score_threshold,
+            filter: Self::filter_condition(req.filter.as_ref())?,
+            timeout: req.timeout,
+            recency: None,
+        };
+        let scan = core_requests::search(collection.clone(), request, claim.map(Arc::new)).await?;
+        if scan.timed_out {
+            return Err(Status::deadline_exceeded("Search did not finish within the requested time"));
+        }
+        collection.record_query(start_time.elapsed());
 
         Ok(Response::new(SearchResponse {
-            result: scored_points,
+            result: scan.points.into_iter().map(|(point, score)| Self::scored_point(point, score)).collect(),
             time: start_time.elapsed().as_secs_f64(),
         }))
     }
@@ -610,13 +1083,15 @@ impl vectx::points_server::Points for PointsService {
         request: Request<ScrollPoints>,
     ) -> Result<Response<ScrollResponse>, Status> {
         let start_time = Instant::now();
+        let claim = authorize_filtered(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
         let req = request.into_inner();
         
         let collection = self.storage.get_collection(&req.collection_name)
             .ok_or_else(|| Status::not_found("Collection not found"))?;
 
         let limit = req.limit.unwrap_or(10) as usize;
-        let all_points = collection.get_all_points();
+        let filter = core_requests::build_filter(Self::filter_condition(req.filter.as_ref())?, claim.as_ref(), Some(&collection));
+        let all_points: Vec<Point> = collection.iter_filtered(filter.as_deref(), Projection::ALL).collect();
         
         // Get offset
         let offset_id: Option<String> = req.offset.as_ref()
@@ -626,7 +1101,7 @@ impl vectx::points_server::Points for PointsService {
         
         // Skip to offset
         if let Some(ref offset) = offset_id {
-            while let Some(p) = points_iter.next() {
+            for p in points_iter.by_ref() {
                 if p.id.to_string() == *offset {
                     break;
                 }
@@ -653,10 +1128,9 @@ impl vectx::points_server::Points for PointsService {
                 id: Some(Self::to_proto_point_id(&point.id)),
                 payload,
                 vectors: Some(VectorInput {
-                    variant: Some(vector_input::Variant::Dense(vectx::Vector {
-                        data: point.vector.as_slice().to_vec(),
-                    })),
+                    variant: Some(vector_input::Variant::Dense(Self::output_vector(&collection, &point.vector))),
                 }),
+                version: Some(point.version),
             });
         }
 
@@ -679,101 +1153,26 @@ impl vectx::points_server::Points for PointsService {
         request: Request<RecommendPoints>,
     ) -> Result<Response<RecommendResponse>, Status> {
         let start_time = Instant::now();
+        let claim = authorize_filtered(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
         let req = request.into_inner();
         
         let collection = self.storage.get_collection(&req.collection_name)
             .ok_or_else(|| Status::not_found("Collection not found"))?;
 
-        let limit = req.limit as usize;
-        
-        // Collect positive vectors
-        let mut positive_vectors: Vec<Vec<f32>> = Vec::new();
-        let mut exclude_ids: std::collections::HashSet<String> = std::collections::HashSet::new();
-        
-        for pos_id in &req.positive {
-            if let Some(id_str) = Self::parse_point_id(pos_id) {
-                exclude_ids.insert(id_str.clone());
-                if let Some(point) = collection.get(&id_str) {
-                    positive_vectors.push(point.vector.as_slice().to_vec());
-                }
-            }
-        }
-        
-        if positive_vectors.is_empty() {
-            return Err(Status::invalid_argument("At least one valid positive example required"));
-        }
-        
-        // Collect negative vectors
-        let mut negative_vectors: Vec<Vec<f32>> = Vec::new();
-        for neg_id in &req.negative {
-            if let Some(id_str) = Self::parse_point_id(neg_id) {
-                exclude_ids.insert(id_str.clone());
-                if let Some(point) = collection.get(&id_str) {
-                    negative_vectors.push(point.vector.as_slice().to_vec());
-                }
-            }
-        }
-        
-        // Compute average positive
-        let dim = positive_vectors[0].len();
-        let mut avg_positive = vec![0.0f32; dim];
-        for vec in &positive_vectors {
-            for (i, &val) in vec.iter().enumerate() {
-                if i < dim { avg_positive[i] += val; }
-            }
-        }
-        let pos_count = positive_vectors.len() as f32;
-        for val in &mut avg_positive { *val /= pos_count; }
-        
-        // Create query vector
-        let query_data = if !negative_vectors.is_empty() {
-            let mut avg_negative = vec![0.0f32; dim];
-            for vec in &negative_vectors {
-                for (i, &val) in vec.iter().enumerate() {
-                    if i < dim { avg_negative[i] += val; }
-                }
-            }
-            let neg_count = negative_vectors.len() as f32;
-            for val in &mut avg_negative { *val /= neg_count; }
-            
-            avg_positive.iter()
-                .zip(avg_negative.iter())
-                .map(|(p, n)| 2.0 * p - n)
-                .collect()
-        } else {
-            avg_positive
+        let request = CoreRecommendRequest {
+            positive: req.positive.iter().filter_map(Self::parse_point_id).collect(),
+            negative: req.negative.iter().filter_map(Self::parse_point_id).collect(),
+            limit: (req.limit > 0).then_some(req.limit as usize),
+            offset: req.offset.unwrap_or(0) as usize,
+            score_threshold: req.score_threshold,
+            filter: Self::filter_condition(req.filter.as_ref())?,
+            recency: None,
         };
-        
-        let query = Vector::new(query_data);
-        let search_results = collection.search(&query, limit + exclude_ids.len(), None);
-        
-        let scored_points: Vec<ScoredPoint> = search_results
-            .into_iter()
-            .filter(|(point, _)| !exclude_ids.contains(&point.id.to_string()))
-            .take(limit)
-            .map(|(point, score)| {
-                let payload: std::collections::HashMap<String, vectx::Value> = point.payload
-                    .as_ref()
-                    .and_then(|p| p.as_object())
-                    .map(|obj| {
-                        obj.iter()
-                            .map(|(k, v)| (k.clone(), Self::json_to_proto_value(v)))
-                            .collect()
-                    })
-                    .unwrap_or_default();
-
-                ScoredPoint {
-                    id: Some(Self::to_proto_point_id(&point.id)),
-                    payload,
-                    score,
-                    vectors: None,
-                    version: Some(0),
-                }
-            })
-            .collect();
+        let found = core_requests::recommend(&collection, &request, claim.as_ref())?;
+        collection.record_query(start_time.elapsed());
 
         Ok(Response::new(RecommendResponse {
-            result: scored_points,
+            result: found.into_iter().map(|(point, score)| Self::scored_point(point, score)).collect(),
             time: start_time.elapsed().as_secs_f64(),
         }))
     }
@@ -783,14 +1182,19 @@ impl vectx::points_server::Points for PointsService {
         request: Request<CountPoints>,
     ) -> Result<Response<CountResponse>, Status> {
         let start_time = Instant::now();
+        let claim = authorize_filtered(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
         let req = request.into_inner();
         
         let collection = self.storage.get_collection(&req.collection_name)
             .ok_or_else(|| Status::not_found("Collection not found"))?;
 
+        let filter = core_requests::build_filter(Self::filter_condition(req.filter.as_ref())?, claim.as_ref(), Some(&collection));
         Ok(Response::new(CountResponse {
             result: Some(CountResult {
-                count: collection.count() as u64,
+                count: match filter {
+                    Some(filter) => collection.iter_filtered(Some(filter.as_ref()), Projection::IDS).remaining() as u64,
+                    None => collection.count() as u64,
+                },
             }),
             time: start_time.elapsed().as_secs_f64(),
         }))
@@ -801,51 +1205,82 @@ impl vectx::points_server::Points for PointsService {
         request: Request<QueryPoints>,
     ) -> Result<Response<QueryResponse>, Status> {
         let start_time = Instant::now();
+        let claim = authorize_filtered(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
         let req = request.into_inner();
         
         let collection = self.storage.get_collection(&req.collection_name)
             .ok_or_else(|| Status::not_found("Collection not found"))?;
 
-        let limit = req.limit as usize;
-        
-        let query_data = req.query
-            .and_then(|vi| match vi.variant {
-                Some(vector_input::Variant::Dense(v)) => Some(v.data),
-                Some(vector_input::Variant::Named(nv)) => {
-                    nv.vectors.values().next().map(|v| v.data.clone())
-                }
-                None => None,
-            })
+        let query_data = Self::single_vector(req.query)?
             .ok_or_else(|| Status::invalid_argument("Query vector required"))?;
-        
-        let query = Vector::new(query_data);
-        let results = collection.search(&query, limit, None);
-        
-        let scored_points: Vec<ScoredPoint> = results.into_iter().map(|(point, score)| {
-            let payload: std::collections::HashMap<String, vectx::Value> = point.payload
-                .as_ref()
-                .and_then(|p| p.as_object())
-                .map(|obj| {
-                    obj.iter()
-                        .map(|(k, v)| (k.clone(), Self::json_to_proto_value(v)))
-                        .collect()
-                })
-                .unwrap_or_default();
-
-            ScoredPoint {
-                id: Some(Self::to_proto_point_id(&point.id)),
-                payload,
-                score,
-                vectors: None,
-                version: Some(0),
-            }
-        }).collect();
+        let request = CoreSearchRequest {
+            vector: Self::input_vector(&collection, &query_data)?,
+            limit: (req.limit > 0).then_some(req.limit as usize),
+            offset: req.offset.unwrap_or(0) as usize,
+            score_threshold: req.score_threshold,
+            filter: Self::filter_condition(req.filter.as_ref())?,
+            timeout: req.timeout,
+            recency: None,
+        };
+        let scan = core_requests::search(collection.clone(), request, claim.map(Arc::new)).await?;
+        if scan.timed_out {
+            return Err(Status::deadline_exceeded("Search did not finish within the requested time"));
+        }
+        collection.record_query(start_time.elapsed());
 
         Ok(Response::new(QueryResponse {
-            result: scored_points,
+            result: scan.points.into_iter().map(|(point, score)| Self::scored_point(point, score)).collect(),
             time: start_time.elapsed().as_secs_f64(),
         }))
     }
+    async fn watch(
+        &self,
+        request: Request<WatchCollection>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
+        let req = request.into_inner();
+
+        let collection = self.storage.get_collection(&req.collection_name)
+            .ok_or_else(|| Status::not_found("Collection not found"))?;
+        let with_points = req.with_points.unwrap_or(false);
+
+        let events = crate::watch::watch_collection(&collection, req.resume_token).map(move |entry| {
+            let points = if with_points {
+                entry.point_ids.iter()
+                    .filter_map(|id| collection.get(id))
+                    .map(|point| RetrievedPoint {
+                        id: Some(Self::to_proto_point_id(&point.id)),
+                        payload: Self::payload_to_proto(point.payload.as_ref()),
+                        vectors: Some(VectorInput {
+                            variant: Some(vector_input::Variant::Dense(Self::output_vector(&collection, &point.vector))),
+                        }),
+                        version: Some(point.version),
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            Ok(WatchEvent {
+                op_id: entry.op_id,
+                op_type: serde_json::to_value(entry.op_type)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default(),
+                ids: entry.point_ids.iter()
+                    .map(|id| match id.parse::<u64>() {
+                        Ok(num) => vectx::PointId { point_id_options: Some(point_id::PointIdOptions::Num(num)) },
+                        Err(_) => vectx::PointId { point_id_options: Some(point_id::PointIdOptions::Uuid(id.clone())) },
+                    })
+                    .collect(),
+                timestamp: entry.timestamp,
+                resume_token: crate::watch::resume_token(&entry),
+                points,
+            })
+        });
+
+        Ok(Response::new(Box::pin(events)))
+    }
 }
 
 // ============================================================================
@@ -869,9 +1304,11 @@ impl vectx::snapshots_server::Snapshots for SnapshotsService {
         request: Request<CreateSnapshotRequest>,
     ) -> Result<Response<CreateSnapshotResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Admin)?;
+        let audit = GrpcAudit::start(&self.storage, &request, "Snapshots/Create", Some(&request.get_ref().collection_name));
         let req = request.into_inner();
         
-        match self.storage.create_collection_snapshot(&req.collection_name) {
+        audit.finish(match self.storage.create_collection_snapshot(&req.collection_name) {
             Ok(snapshot) => Ok(Response::new(CreateSnapshotResponse {
                 result: Some(SnapshotDescription {
                     name: snapshot.name,
@@ -882,7 +1319,7 @@ impl vectx::snapshots_server::Snapshots for SnapshotsService {
                 time: start_time.elapsed().as_secs_f64(),
             })),
             Err(e) => Err(Status::internal(e.to_string())),
-        }
+        }).await
     }
 
     async fn list(
@@ -890,6 +1327,7 @@ impl vectx::snapshots_server::Snapshots for SnapshotsService {
         request: Request<ListSnapshotsRequest>,
     ) -> Result<Response<ListSnapshotsResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Read)?;
         let req = request.into_inner();
         
         match self.storage.list_collection_snapshots(&req.collection_name) {
@@ -918,16 +1356,18 @@ impl vectx::snapshots_server::Snapshots for SnapshotsService {
         request: Request<DeleteSnapshotRequest>,
     ) -> Result<Response<DeleteSnapshotResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Admin)?;
+        let audit = GrpcAudit::start(&self.storage, &request, "Snapshots/Delete", Some(&request.get_ref().collection_name));
         let req = request.into_inner();
         
-        match self.storage.delete_collection_snapshot(&req.collection_name, &req.snapshot_name) {
+        audit.finish(match self.storage.delete_collection_snapshot(&req.collection_name, &req.snapshot_name) {
             Ok(true) => Ok(Response::new(DeleteSnapshotResponse {
                 result: true,
                 time: start_time.elapsed().as_secs_f64(),
             })),
             Ok(false) => Err(Status::not_found("Snapshot not found")),
             Err(e) => Err(Status::internal(e.to_string())),
-        }
+        }).await
     }
 
     async fn recover(
@@ -935,15 +1375,29 @@ impl vectx::snapshots_server::Snapshots for SnapshotsService {
         request: Request<RecoverSnapshotRequest>,
     ) -> Result<Response<RecoverSnapshotResponse>, Status> {
         let start_time = Instant::now();
+        authorize(&self.storage, &request, Some(&request.get_ref().collection_name), Permission::Admin)?;
+        let audit = GrpcAudit::start(&self.storage, &request, "Snapshots/Recover", Some(&request.get_ref().collection_name));
+        check_write_lock(&self.storage)?;
         let req = request.into_inner();
-        
-        match self.storage.recover_from_snapshot(&req.collection_name, &req.location) {
-            Ok(_) => Ok(Response::new(RecoverSnapshotResponse {
-                result: true,
-                time: start_time.elapsed().as_secs_f64(),
-            })),
-            Err(e) => Err(Status::internal(e.to_string())),
-        }
+        let priority = match req.priority.as_deref() {
+            None | Some("snapshot") => SnapshotPriority::Snapshot,
+            Some("replica") => SnapshotPriority::Replica,
+            Some("no_sync") => SnapshotPriority::NoSync,
+            Some(other) => return Err(Status::invalid_argument(format!("Unknown snapshot priority: {}", other))),
+        };
+        // The options hold a filter, which is not Send, so they are gone
+        // before the audit entry is written
+        let result = {
+            let options = RecoverOptions { priority, ..Default::default() };
+            match self.storage.recover_from_snapshot(&req.collection_name, &req.location, &options) {
+                Ok(_) => Ok(Response::new(RecoverSnapshotResponse {
+                    result: true,
+                    time: start_time.elapsed().as_secs_f64(),
+                })),
+                Err(e) => Err(Status::internal(e.to_string())),
+            }
+        };
+        audit.finish(result).await
     }
 }
 
@@ -960,27 +1414,84 @@ impl GrpcApi {
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let addr = format!("0.0.0.0:{}", port).parse()?;
         
-        let qdrant_service = vectx::qdrant_server::QdrantServer::new(QdrantService);
-        let collections_service = vectx::collections_server::CollectionsServer::new(
+        println!("gRPC server listening on {}", addr);
+        
+        Self::router(storage).serve(addr).await?;
+        
+        Ok(())
+    }
+
+    /// Serve on `listener`, e.g. one bound to a random port, until
+    /// `shutdown` completes
+    pub async fn serve(
+        storage: Arc<StorageManager>,
+        listener: tokio::net::TcpListener,
+        shutdown: impl std::future::Future<Output = ()>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let incoming = tonic::transport::server::TcpIncoming::from_listener(listener, true, None)?;
+        Self::router(storage).serve_with_incoming_shutdown(incoming, shutdown).await?;
+        Ok(())
+    }
+
+    fn router(storage: Arc<StorageManager>) -> tonic::transport::server::Router {
+        let qdrant_service = grpc_compression!(vectx::qdrant_server::QdrantServer::new(QdrantService));
+        let health_service = grpc_compression!(health::health_server::HealthServer::new(HealthService::new(storage.clone())));
+        let collections_service = grpc_compression!(vectx::collections_server::CollectionsServer::new(
             CollectionsService::new(storage.clone())
-        );
-        let points_service = vectx::points_server::PointsServer::new(
+        ));
+        let points_service = grpc_compression!(vectx::points_server::PointsServer::new(
             PointsService::new(storage.clone())
-        );
-        let snapshots_service = vectx::snapshots_server::SnapshotsServer::new(
+        ));
+        let snapshots_service = grpc_compression!(vectx::snapshots_server::SnapshotsServer::new(
             SnapshotsService::new(storage)
-        );
-        
-        println!("gRPC server listening on {}", addr);
+        ));
         
         tonic::transport::Server::builder()
             .add_service(qdrant_service)
+            .add_service(health_service)
             .add_service(collections_service)
             .add_service(points_service)
             .add_service(snapshots_service)
-            .serve(addr)
-            .await?;
-        
-        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use health::health_check_response::ServingStatus;
+    use health::health_server::Health;
+    use vectx_core::CollectionConfig;
+    use vectx_storage::{LoadLimits, MIN_P99_QUERIES};
+
+    fn check(service: &HealthService, name: &str) -> Result<i32, Status> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let request = Request::new(health::HealthCheckRequest { service: name.to_string() });
+        runtime.block_on(service.check(request)).map(|response| response.into_inner().status)
+    }
+
+    #[test]
+    fn test_health_follows_load_limits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(StorageManager::new(temp_dir.path()).unwrap());
+        let collection = storage.create_collection(CollectionConfig {
+            name: "busy".to_string(),
+            vector_dim: 2,
+            distance: CoreDistance::Euclidean,
+            use_hnsw: false,
+            enable_bm25: false,
+        }).unwrap();
+        let service = HealthService::new(storage.clone());
+        assert_eq!(check(&service, "").unwrap(), ServingStatus::Serving as i32);
+        assert_eq!(check(&service, "vectx.Points").unwrap(), ServingStatus::Serving as i32);
+        assert_eq!(check(&service, "nope").unwrap_err().code(), tonic::Code::NotFound);
+
+        storage.set_load_limits(LoadLimits { max_queued_jobs: None, max_p99_ms: Some(100.0) });
+        for _ in 0..MIN_P99_QUERIES {
+            collection.record_query(std::time::Duration::from_millis(250));
+        }
+        assert_eq!(check(&service, "").unwrap(), ServingStatus::NotServing as i32);
+
+        collection.sample_stats(0);
+        assert_eq!(check(&service, "").unwrap(), ServingStatus::Serving as i32);
     }
 }
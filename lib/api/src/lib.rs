@@ -1,5 +1,22 @@
 pub mod rest;
 pub mod grpc;
+pub mod watch;
+pub mod export;
+pub mod telemetry;
+pub mod compression;
+pub mod content;
+pub mod rerank;
+pub mod pagination;
+pub mod auth;
+pub mod consistency;
+pub mod core_requests;
+pub mod parsing;
+pub mod logging;
+pub mod fuzz;
+mod audit;
+mod deadline;
+mod extract;
+mod openapi;
 
 pub use rest::RestApi;
 pub use grpc::GrpcApi;
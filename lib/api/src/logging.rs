@@ -0,0 +1,245 @@
+//! Server logging
+//!
+//! [`init`] installs the global tracing subscriber. Its filter sits behind
+//! a reload layer, so `POST /admin/log_level` can change what is logged
+//! without a restart, e.g. to `info,vectx_core=debug` while chasing a
+//! problem. Events are written as text or as JSON lines, to stdout or to a
+//! file that is rotated by time, keeping a bounded number of old files.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::{fmt as tracing_fmt, reload, EnvFilter, Layer, Registry};
+
+/// How events are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One human-readable line per event
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!("unknown log format \"{}\", expected \"text\" or \"json\"", other)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Text => "text",
+            Self::Json => "json",
+        })
+    }
+}
+
+/// How often a log file is started afresh
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    /// Write to one file forever
+    Never,
+}
+
+impl LogRotation {
+    fn rotation(self) -> Rotation {
+        match self {
+            Self::Minutely => Rotation::MINUTELY,
+            Self::Hourly => Rotation::HOURLY,
+            Self::Daily => Rotation::DAILY,
+            Self::Never => Rotation::NEVER,
+        }
+    }
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "minutely" => Ok(Self::Minutely),
+            "hourly" => Ok(Self::Hourly),
+            "daily" => Ok(Self::Daily),
+            "never" => Ok(Self::Never),
+            other => Err(format!(
+                "unknown log rotation \"{}\", expected \"minutely\", \"hourly\", \"daily\" or \"never\"",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LogRotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Minutely => "minutely",
+            Self::Hourly => "hourly",
+            Self::Daily => "daily",
+            Self::Never => "never",
+        })
+    }
+}
+
+/// Where and how the server logs
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// Filter directives, as in `RUST_LOG`: a level such as `debug`, or
+    /// levels per module such as `info,vectx_core=debug`
+    pub level: String,
+    pub format: LogFormat,
+    /// File to write to instead of stdout; rotated files get the date and
+    /// time appended to its name
+    pub file: Option<PathBuf>,
+    pub rotation: LogRotation,
+    /// Rotated files kept besides the current one (0 keeps all)
+    pub max_files: usize,
+}
+
+/// The filter of the installed subscriber and the directives it was built from
+struct LevelControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    directives: Mutex<String>,
+}
+
+impl LevelControl {
+    fn set(&self, directives: &str) -> Result<(), String> {
+        let filter = parse_directives(directives)?;
+        let mut current = self.directives.lock().unwrap_or_else(|e| e.into_inner());
+        self.handle.reload(filter).map_err(|e| e.to_string())?;
+        *current = directives.to_string();
+        Ok(())
+    }
+}
+
+static CONTROL: OnceLock<LevelControl> = OnceLock::new();
+
+/// Parse filter directives. A bare word that is not a level, which would
+/// enable everything of a target by that name, is refused as a typo.
+fn parse_directives(directives: &str) -> Result<EnvFilter, String> {
+    if directives.trim().is_empty() {
+        return Err("log level must not be empty".to_string());
+    }
+    let bare = directives.split(',').map(str::trim).filter(|d| !d.contains(['=', '[']));
+    for directive in bare {
+        if directive.parse::<LevelFilter>().is_err() {
+            return Err(format!(
+                "invalid log level \"{}\": expected trace, debug, info, warn, error, off or target=level",
+                directive
+            ));
+        }
+    }
+    EnvFilter::builder()
+        .parse(directives)
+        .map_err(|e| format!("invalid log level \"{}\": {}", directives, e))
+}
+
+/// Install the global subscriber. Keep the returned guard alive while the
+/// server runs: events written to a file are flushed when it is dropped.
+pub fn init(options: &LogOptions) -> anyhow::Result<Option<WorkerGuard>> {
+    let filter = parse_directives(&options.level).map_err(anyhow::Error::msg)?;
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let (output, guard) = match &options.file {
+        Some(path) => {
+            let (writer, guard) = tracing_appender::non_blocking(file_appender(options, path)?);
+            let layer = tracing_fmt::layer().with_ansi(false).with_writer(writer);
+            let layer = match options.format {
+                LogFormat::Text => layer.boxed(),
+                LogFormat::Json => layer.json().boxed(),
+            };
+            (layer, Some(guard))
+        }
+        None => {
+            let layer = tracing_fmt::layer();
+            let layer = match options.format {
+                LogFormat::Text => layer.boxed(),
+                LogFormat::Json => layer.json().boxed(),
+            };
+            (layer, None)
+        }
+    };
+    tracing::subscriber::set_global_default(Registry::default().with(filter).with(output))?;
+    let _ = CONTROL.set(LevelControl { handle, directives: Mutex::new(options.level.clone()) });
+    Ok(guard)
+}
+
+fn file_appender(options: &LogOptions, path: &std::path::Path) -> anyhow::Result<RollingFileAppender> {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        anyhow::bail!("Log file {:?} has no file name", path);
+    };
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    };
+    std::fs::create_dir_all(directory)?;
+    let mut builder = RollingFileAppender::builder()
+        .rotation(options.rotation.rotation())
+        .filename_prefix(name);
+    if options.max_files > 0 {
+        // The current file counts among those the appender keeps
+        builder = builder.max_log_files(options.max_files + 1);
+    }
+    Ok(builder.build(directory)?)
+}
+
+/// The filter directives in effect, if [`init`] installed the subscriber
+pub fn level() -> Option<String> {
+    CONTROL.get().map(|control| control.directives.lock().unwrap_or_else(|e| e.into_inner()).clone())
+}
+
+/// Replace the filter directives of the subscriber [`init`] installed
+pub fn set_level(directives: &str) -> Result<(), String> {
+    match CONTROL.get() {
+        Some(control) => control.set(directives),
+        None => {
+            parse_directives(directives)?;
+            Err("logging is not set up by this process, so its level can't be changed".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::Level;
+
+    #[test]
+    fn test_reload_level() {
+        let (filter, handle) = reload::Layer::new(parse_directives("info").unwrap());
+        let control = LevelControl { handle, directives: Mutex::new("info".to_string()) };
+        let subscriber = Registry::default().with(filter);
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(!tracing::enabled!(Level::DEBUG));
+            control.set("warn,vectx_api=debug").unwrap();
+            assert!(tracing::enabled!(Level::DEBUG));
+            assert!(!tracing::enabled!(target: "actix_web", Level::INFO));
+
+            // A bad level leaves the filter as it was
+            assert!(control.set("loud").is_err());
+            assert!(control.set("vectx_api=loud").is_err());
+            assert!(control.set(" ").is_err());
+            assert!(tracing::enabled!(Level::DEBUG));
+        });
+        assert_eq!(*control.directives.lock().unwrap(), "warn,vectx_api=debug");
+        assert_eq!("JSON".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("hourly".parse::<LogRotation>().unwrap().to_string(), "hourly");
+        assert!("weekly".parse::<LogRotation>().is_err());
+    }
+}
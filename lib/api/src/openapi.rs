@@ -0,0 +1,177 @@
+//! OpenAPI description of the REST API
+//!
+//! `GET /openapi.json` serves an OpenAPI 3.0 document whose request bodies
+//! and query parameters are JSON Schemas derived from the same types the
+//! handlers deserialize, so the spec follows the code, vectX extensions
+//! included. Responses use Qdrant's `{"result", "status", "time"}`
+//! envelope and are described by that envelope only.
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+/// Builder of the OpenAPI document
+pub(crate) struct OpenApi {
+    gen: SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+/// One method of one path, being described
+pub(crate) struct Operation<'a> {
+    gen: &'a mut SchemaGenerator,
+    operation: &'a mut Map<String, Value>,
+}
+
+impl OpenApi {
+    pub fn new() -> Self {
+        Self {
+            gen: SchemaSettings::openapi3().into_generator(),
+            paths: Map::new(),
+        }
+    }
+
+    /// Describe `method` on `path`; `{param}` segments become path parameters
+    pub fn operation(&mut self, method: &str, path: &str, summary: &str) -> Operation<'_> {
+        let parameters: Vec<Value> = path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+            .map(|name| json!({"name": name, "in": "path", "required": true, "schema": {"type": "string"}}))
+            .collect();
+        let operation = json!({
+            "summary": summary,
+            "parameters": parameters,
+            "responses": {
+                "200": {
+                    "description": "Success",
+                    "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Response"}}}
+                },
+                "default": {
+                    "description": "Error",
+                    "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Response"}}}
+                }
+            }
+        });
+
+        let item = self.paths.entry(path.to_string()).or_insert_with(|| json!({}));
+        let item = item.as_object_mut().expect("path items are objects");
+        item.insert(method.to_string(), operation);
+        Operation {
+            gen: &mut self.gen,
+            operation: item.get_mut(method).and_then(Value::as_object_mut).expect("operation was just inserted"),
+        }
+    }
+
+    /// The finished OpenAPI document
+    pub fn finish(self) -> Value {
+        let mut schemas: Map<String, Value> = self.gen.definitions()
+            .iter()
+            .map(|(name, schema)| (name.clone(), schema_to_json(schema)))
+            .collect();
+        schemas.insert("Response".to_string(), json!({
+            "type": "object",
+            "properties": {
+                "result": {"description": "Operation result; its shape depends on the endpoint"},
+                "status": {
+                    "description": "`ok`, or an object with an `error` message",
+                    "oneOf": [
+                        {"type": "string"},
+                        {"type": "object", "properties": {"error": {"type": "string"}}}
+                    ]
+                },
+                "time": {"type": "number", "description": "Seconds spent on the request"}
+            }
+        }));
+
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "vectX",
+                "description": "Qdrant-compatible REST API of the vectX vector database",
+                "version": env!("CARGO_PKG_VERSION")
+            },
+            "paths": self.paths,
+            "components": {
+                "schemas": schemas,
+                "securitySchemes": {
+                    "api-key": {"type": "apiKey", "in": "header", "name": "api-key"},
+                    "bearer": {"type": "http", "scheme": "bearer"}
+                }
+            },
+            "security": [{"api-key": []}, {"bearer": []}]
+        })
+    }
+}
+
+impl Operation<'_> {
+    /// JSON request body of type `T`
+    pub fn body<T: JsonSchema>(self) -> Self {
+        let schema = schema_to_json(&self.gen.subschema_for::<T>());
+        self.operation.insert("requestBody".to_string(), json!({
+            "required": true,
+            "content": {"application/json": {"schema": schema}}
+        }));
+        self
+    }
+
+    /// Query parameters from the fields of `T`
+    pub fn query<T: JsonSchema>(self) -> Self {
+        let root = self.gen.root_schema_for::<T>();
+        let Some(object) = root.schema.object else { return self };
+        let parameters: Vec<Value> = object.properties
+            .iter()
+            .map(|(name, schema)| {
+                let mut parameter = json!({
+                    "name": name,
+                    "in": "query",
+                    "required": object.required.contains(name),
+                    "schema": schema_to_json(schema),
+                });
+                if let Schema::Object(schema) = schema {
+                    if let Some(description) = schema.metadata.as_ref().and_then(|m| m.description.as_ref()) {
+                        parameter["description"] = json!(description);
+                    }
+                }
+                parameter
+            })
+            .collect();
+        if let Some(Value::Array(existing)) = self.operation.get_mut("parameters") {
+            existing.extend(parameters);
+        }
+        self
+    }
+
+    /// Request body uploaded as a multipart form with a `snapshot` file
+    pub fn multipart(self) -> Self {
+        self.operation.insert("requestBody".to_string(), json!({
+            "required": true,
+            "content": {"multipart/form-data": {"schema": {
+                "type": "object",
+                "properties": {"snapshot": {"type": "string", "format": "binary"}}
+            }}}
+        }));
+        self
+    }
+
+    /// Successful responses are `content_type` instead of the JSON envelope
+    pub fn produces(self, content_type: &str) -> Self {
+        self.operation.insert("responses".to_string(), json!({
+            "200": {"description": "Success", "content": {content_type: {}}},
+            "default": {
+                "description": "Error",
+                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Response"}}}
+            }
+        }));
+        self
+    }
+
+    /// Served without authentication
+    pub fn public(self) -> Self {
+        self.operation.insert("security".to_string(), json!([]));
+        self
+    }
+}
+
+fn schema_to_json(schema: &Schema) -> Value {
+    serde_json::to_value(schema).unwrap_or(Value::Null)
+}
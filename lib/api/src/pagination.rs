@@ -0,0 +1,169 @@
+//! Continuation tokens for deep result pages
+//!
+//! Paging with `offset` runs the whole search again for every page. A
+//! search or query with `"paginate": true` instead keeps its scored
+//! candidates, up to [`PAGE_DEPTH`], and answers with a `next_page_token`.
+//! Sending the token back returns the following page from the kept list
+//! without searching again. Lists expire [`TOKEN_TTL`] after they were last
+//! read.
+//!
+//! Only ids, scores and explanations are kept. Points are read again for
+//! every page, so deleted points drop out and payloads are current, while
+//! scores and order are those of the original search.
+//!
+//! A list belongs to a [`PageOwner`]: the API key of the search and its
+//! filter. Its tokens are only followed with the same key and filter, so a
+//! token gives away nothing another key, or another JWT claim, may not see.
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Most results a paginated search keeps
+pub const PAGE_DEPTH: usize = 1000;
+
+/// How long a result list is kept after its last page was read
+pub const TOKEN_TTL: Duration = Duration::from_secs(300);
+
+/// Result lists kept at once; the least recently read goes first
+const MAX_LISTS: usize = 1024;
+
+/// A scored result of a kept list
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub id: String,
+    pub score: f32,
+    /// Explanation of the score, null if none was asked for
+    pub explanation: Value,
+}
+
+/// Digest of the API key and filter of the search a list was kept for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageOwner([u8; 32]);
+
+impl PageOwner {
+    /// The owner of a search by `key` (None without one) with `filter`
+    pub fn new(key: Option<&str>, filter: Option<&Value>) -> Self {
+        let search = serde_json::json!([key, filter]);
+        Self(Sha256::digest(search.to_string().as_bytes()).into())
+    }
+}
+
+struct ResultList {
+    collection: String,
+    owner: PageOwner,
+    hits: Arc<Vec<Hit>>,
+    page_size: usize,
+    last_read: Instant,
+}
+
+/// Result lists of paginated searches, by token
+pub struct PageCache {
+    lists: Mutex<HashMap<String, ResultList>>,
+    ttl: Duration,
+}
+
+impl PageCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self { lists: Mutex::new(HashMap::new()), ttl }
+    }
+
+    /// Keep `hits`, of which the first `page_size` were returned, and give
+    /// the token of the next page. None when there is no next page, or
+    /// when pages are empty and following the token would never get past
+    /// the first hit.
+    pub fn store(&self, collection: &str, owner: &PageOwner, hits: Vec<Hit>, page_size: usize) -> Option<String> {
+        if page_size == 0 || hits.len() <= page_size {
+            return None;
+        }
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let now = Instant::now();
+        let mut lists = self.lists.lock().unwrap_or_else(|e| e.into_inner());
+        lists.retain(|_, list| now.duration_since(list.last_read) < self.ttl);
+        if lists.len() >= MAX_LISTS {
+            let oldest = lists.iter().min_by_key(|(_, list)| list.last_read).map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                lists.remove(&oldest);
+            }
+        }
+        lists.insert(id.clone(), ResultList {
+            collection: collection.to_string(),
+            owner: owner.clone(),
+            hits: Arc::new(hits),
+            page_size,
+            last_read: now,
+        });
+        Some(token(&id, page_size))
+    }
+
+    /// The hits of the page at `token`, `page_size` of them or as many as
+    /// the first page had, and the token of the page after it. A page size
+    /// of 0 is refused, its next token would point at the same offset, as
+    /// is a token of another collection or owner.
+    pub fn page(&self, collection: &str, owner: &PageOwner, token: &str, page_size: Option<usize>) -> Result<(Vec<Hit>, Option<String>), String> {
+        let expired = || "Page token is invalid or has expired".to_string();
+        if page_size == Some(0) {
+            return Err("Page size must be at least 1".to_string());
+        }
+        let (id, offset) = token.rsplit_once('.').ok_or_else(expired)?;
+        let offset: usize = offset.parse().map_err(|_| expired())?;
+
+        let mut lists = self.lists.lock().unwrap_or_else(|e| e.into_inner());
+        let list = lists.get_mut(id)
+            .filter(|list| list.last_read.elapsed() < self.ttl)
+            .ok_or_else(expired)?;
+        if list.collection != collection {
+            return Err("Page token belongs to another collection".to_string());
+        }
+        if list.owner != *owner {
+            return Err("Page token belongs to another API key or filter".to_string());
+        }
+        // Tokens this cache hands out point inside their list
+        if offset > list.hits.len() {
+            return Err(expired());
+        }
+        list.last_read = Instant::now();
+        let page_size = page_size.unwrap_or(list.page_size);
+        let end = offset.saturating_add(page_size).min(list.hits.len());
+        let hits = list.hits[offset..end].to_vec();
+        let next = (end < list.hits.len()).then(|| self::token(id, end));
+        Ok((hits, next))
+    }
+}
+
+fn token(id: &str, offset: usize) -> String {
+    format!("{}.{}", id, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hits(n: usize) -> Vec<Hit> {
+        (0..n).map(|i| Hit { id: i.to_string(), score: 1.0, explanation: Value::Null }).collect()
+    }
+
+    #[test]
+    fn test_page_size_zero() {
+        let cache = PageCache::new(TOKEN_TTL);
+        let owner = PageOwner::new(None, None);
+        // Empty pages give no token to follow
+        assert_eq!(cache.store("c", &owner, hits(5), 0), None);
+
+        let token = cache.store("c", &owner, hits(5), 2).unwrap();
+        assert!(cache.page("c", &owner, &token, Some(0)).is_err());
+
+        // Every page moves past the one before it
+        let mut next = Some(token);
+        let mut seen = Vec::new();
+        while let Some(token) = next {
+            let (page, after) = cache.page("c", &owner, &token, Some(1)).unwrap();
+            assert_eq!(page.len(), 1);
+            seen.extend(page.into_iter().map(|hit| hit.id));
+            next = after;
+        }
+        assert_eq!(seen, ["2", "3", "4"]);
+    }
+}
@@ -0,0 +1,101 @@
+//! Strict and lenient parsing of hand-parsed request JSON
+//!
+//! Filters and point vectors are parsed by hand rather than through serde
+//! models, so nothing rejects their unknown parts for us. In
+//! [`ParseMode::Lenient`], the default, a part that cannot be understood
+//! is skipped and logged, as earlier versions skipped it silently, so
+//! existing clients keep working. In [`ParseMode::Strict`] it fails the
+//! request with a [`ParseError`] saying where it is, e.g.
+//! `filter.must[1].match`. Each REST server has its own mode, passed to
+//! `RestApi::serve_with_parse_mode`.
+
+use std::cell::Cell;
+use std::fmt;
+use std::str::FromStr;
+
+/// What to do with a part of a request that cannot be parsed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Reject the request
+    Strict,
+    /// Log the part and go on without it
+    #[default]
+    Lenient,
+}
+
+impl ParseMode {
+    /// `error` in strict mode; in lenient mode it is logged and the caller
+    /// skips the part it is about
+    pub fn tolerate(self, error: ParseError) -> Result<(), ParseError> {
+        match self {
+            Self::Strict => Err(error),
+            Self::Lenient => {
+                tracing::warn!("Ignoring {}", error);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for ParseMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strict" => Ok(Self::Strict),
+            "lenient" => Ok(Self::Lenient),
+            other => Err(format!("unknown parse mode \"{}\", expected \"strict\" or \"lenient\"", other)),
+        }
+    }
+}
+
+impl fmt::Display for ParseMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Strict => "strict",
+            Self::Lenient => "lenient",
+        })
+    }
+}
+
+/// A part of a request that cannot be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Where the part is, e.g. `filter.must[1].match`
+    pub path: String,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { path: path.into(), message: message.into() }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+thread_local! {
+    static DESERIALIZE_MODE: Cell<ParseMode> = const { Cell::new(ParseMode::Lenient) };
+}
+
+/// Run `deserialize` with [`mode`] reporting `mode`. Request bodies are
+/// deserialized this way, so the deserializers of the parts parsed by hand
+/// follow the mode of the server that received them.
+pub fn with_mode<R>(mode: ParseMode, deserialize: impl FnOnce() -> R) -> R {
+    let previous = DESERIALIZE_MODE.with(|current| current.replace(mode));
+    let result = deserialize();
+    DESERIALIZE_MODE.with(|current| current.set(previous));
+    result
+}
+
+/// The mode of the deserialization running on this thread (see
+/// [`with_mode`]); lenient outside one
+pub fn mode() -> ParseMode {
+    DESERIALIZE_MODE.with(Cell::get)
+}
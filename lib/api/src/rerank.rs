@@ -0,0 +1,244 @@
+//! Reranking of query results with cross-encoder models
+//!
+//! A `rerank` stage in `/points/query` hands the best `top_k` candidates of
+//! the search to a cross-encoder, which scores each (query text, payload
+//! text) pair jointly. That is slower than comparing vectors but usually
+//! orders the few results that matter better, so the candidates come back
+//! ordered and scored by the model.
+//!
+//! Models are registered by name at startup. With the `inference` feature,
+//! [`load_onnx`] loads an exported cross-encoder (`model.onnx` and
+//! `tokenizer.json` from one directory) and runs it in-process with ONNX
+//! Runtime; anything implementing [`CrossEncoder`] can be [`register`]ed.
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
+use vectx_core::Point;
+
+/// Candidates reranked when the request does not say
+pub const DEFAULT_TOP_K: usize = 50;
+
+/// Most candidates a request may rerank; a collection's `max_limit` caps
+/// them further
+pub const MAX_TOP_K: usize = 1000;
+
+/// Scores how well texts answer a query; higher is better
+pub trait CrossEncoder: Send + Sync {
+    /// One score per text, in order
+    fn score(&self, query: &str, texts: &[&str]) -> Result<Vec<f32>, String>;
+}
+
+type Registry = RwLock<HashMap<String, Arc<dyn CrossEncoder>>>;
+
+static MODELS: OnceLock<Registry> = OnceLock::new();
+
+fn models() -> &'static Registry {
+    MODELS.get_or_init(Default::default)
+}
+
+/// Make `model` available to queries as `name`, replacing any model
+/// registered under that name
+pub fn register(name: &str, model: Arc<dyn CrossEncoder>) {
+    models().write().unwrap_or_else(|e| e.into_inner()).insert(name.to_string(), model);
+}
+
+fn model(name: &str) -> Result<Arc<dyn CrossEncoder>, String> {
+    let models = models().read().unwrap_or_else(|e| e.into_inner());
+    match models.get(name) {
+        Some(model) => Ok(model.clone()),
+        None if !cfg!(feature = "inference") && models.is_empty() => Err(format!(
+            "Unknown rerank model '{}': this build has no ONNX runtime (enable the 'inference' feature)",
+            name
+        )),
+        None => Err(format!("Unknown rerank model '{}'", name)),
+    }
+}
+
+/// A model to load at startup, given as `name=directory`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelSpec {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl FromStr for ModelSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, dir)) if !name.trim().is_empty() && !dir.trim().is_empty() => Ok(Self {
+                name: name.trim().to_string(),
+                dir: PathBuf::from(dir.trim()),
+            }),
+            _ => Err(format!("Expected NAME=DIRECTORY, got '{}'", s)),
+        }
+    }
+}
+
+/// Load the ONNX cross-encoder in `dir` and register it as `name`
+#[cfg(feature = "inference")]
+pub fn load_onnx(name: &str, dir: &Path) -> Result<(), String> {
+    let model = onnx::OnnxCrossEncoder::load(dir)?;
+    register(name, Arc::new(model));
+    Ok(())
+}
+
+#[cfg(not(feature = "inference"))]
+pub fn load_onnx(name: &str, _dir: &Path) -> Result<(), String> {
+    Err(format!(
+        "Rerank model '{}': this build has no ONNX runtime (enable the 'inference' feature)",
+        name
+    ))
+}
+
+/// The rerank stage of a query
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RerankRequest {
+    /// Name the model was registered under
+    pub model: String,
+    /// Text the candidates are scored against
+    pub query: String,
+    /// Number of best candidates reranked, at most [`MAX_TOP_K`]; the
+    /// others are dropped
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+    /// Top-level payload field with the candidate text
+    #[serde(default = "default_field")]
+    pub field: String,
+}
+
+fn default_top_k() -> usize {
+    DEFAULT_TOP_K
+}
+
+fn default_field() -> String {
+    "text".to_string()
+}
+
+impl RerankRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.top_k == 0 {
+            return Err("rerank top_k must be greater than 0".to_string());
+        }
+        if self.top_k > MAX_TOP_K {
+            return Err(format!("rerank top_k must be at most {}", MAX_TOP_K));
+        }
+        if self.query.trim().is_empty() {
+            return Err("rerank query must not be empty".to_string());
+        }
+        model(&self.model).map(|_| ())
+    }
+
+    /// Candidates to rerank, capped by the collection's `max_limit`
+    pub fn top_k(&self, max_limit: Option<usize>) -> usize {
+        max_limit.map_or(self.top_k, |max| self.top_k.min(max))
+    }
+
+    /// Model scores of `points`, in order. Points without text in `field`
+    /// are scored as empty text.
+    pub fn score(&self, points: &[&Point]) -> Result<Vec<f32>, String> {
+        let model = model(&self.model)?;
+        let texts: Vec<&str> = points.iter()
+            .map(|point| {
+                point.payload.as_ref()
+                    .and_then(|payload| payload.get(&self.field))
+                    .and_then(|text| text.as_str())
+                    .unwrap_or("")
+            })
+            .collect();
+        let scores = model.score(&self.query, &texts)?;
+        if scores.len() != texts.len() {
+            return Err(format!(
+                "Rerank model '{}' returned {} scores for {} texts",
+                self.model, scores.len(), texts.len()
+            ));
+        }
+        Ok(scores)
+    }
+}
+
+#[cfg(feature = "inference")]
+mod onnx {
+    use super::CrossEncoder;
+    use ort::session::Session;
+    use ort::value::Tensor;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+    /// Pairs run through the model at once
+    const BATCH_SIZE: usize = 32;
+
+    /// Token limit of a pair when the tokenizer sets none, that of BERT
+    const MAX_TOKENS: usize = 512;
+
+    /// A cross-encoder exported to ONNX with a Hugging Face tokenizer
+    pub struct OnnxCrossEncoder {
+        session: Mutex<Session>,
+        tokenizer: Tokenizer,
+        input_names: Vec<String>,
+    }
+
+    impl OnnxCrossEncoder {
+        pub fn load(dir: &Path) -> Result<Self, String> {
+            let model_path = dir.join("model.onnx");
+            let session = Session::builder()
+                .and_then(|builder| builder.commit_from_file(&model_path))
+                .map_err(|e| format!("Failed to load {:?}: {}", model_path, e))?;
+            let tokenizer_path = dir.join("tokenizer.json");
+            let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
+                .map_err(|e| format!("Failed to load {:?}: {}", tokenizer_path, e))?;
+            if tokenizer.get_padding().is_none() {
+                tokenizer.with_padding(Some(PaddingParams::default()));
+            }
+            if tokenizer.get_truncation().is_none() {
+                tokenizer
+                    .with_truncation(Some(TruncationParams { max_length: MAX_TOKENS, ..Default::default() }))
+                    .map_err(|e| e.to_string())?;
+            }
+            let input_names = session.inputs.iter().map(|input| input.name.clone()).collect();
+            Ok(Self { session: Mutex::new(session), tokenizer, input_names })
+        }
+
+        fn score_batch(&self, query: &str, texts: &[&str]) -> Result<Vec<f32>, String> {
+            let pairs: Vec<(&str, &str)> = texts.iter().map(|text| (query, *text)).collect();
+            let encodings = self.tokenizer.encode_batch(pairs, true).map_err(|e| e.to_string())?;
+            let rows = encodings.len();
+            let columns = encodings.first().map_or(0, |e| e.get_ids().len());
+
+            let mut inputs = Vec::new();
+            for name in &self.input_names {
+                let values: Vec<i64> = match name.as_str() {
+                    "input_ids" => encodings.iter().flat_map(|e| e.get_ids()).map(|&v| v as i64).collect(),
+                    "attention_mask" => encodings.iter().flat_map(|e| e.get_attention_mask()).map(|&v| v as i64).collect(),
+                    "token_type_ids" => encodings.iter().flat_map(|e| e.get_type_ids()).map(|&v| v as i64).collect(),
+                    other => return Err(format!("Unsupported cross-encoder input '{}'", other)),
+                };
+                let tensor = Tensor::from_array(([rows, columns], values)).map_err(|e| e.to_string())?;
+                inputs.push((name.clone(), tensor));
+            }
+
+            let mut session = self.session.lock().unwrap_or_else(|e| e.into_inner());
+            let outputs = session.run(inputs).map_err(|e| e.to_string())?;
+            let (shape, logits) = outputs[0].try_extract_tensor::<f32>().map_err(|e| e.to_string())?;
+            // One logit per pair, or one per class with relevance the last
+            let classes = shape.last().copied().unwrap_or(1).max(1) as usize;
+            Ok(logits.chunks(classes).map(|row| row[classes - 1]).collect())
+        }
+    }
+
+    impl CrossEncoder for OnnxCrossEncoder {
+        fn score(&self, query: &str, texts: &[&str]) -> Result<Vec<f32>, String> {
+            let mut scores = Vec::with_capacity(texts.len());
+            for batch in texts.chunks(BATCH_SIZE) {
+                scores.extend(self.score_batch(query, batch)?);
+            }
+            Ok(scores)
+        }
+    }
+}
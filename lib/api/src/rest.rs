@@ -1,27 +1,85 @@
-use actix_web::{web, App, HttpServer, HttpResponse, Result as ActixResult};
+use actix_web::{web, App, HttpMessage, HttpRequest, HttpServer, HttpResponse, Result as ActixResult};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
 use actix_cors::Cors;
 use actix_files::Files;
 use actix_multipart::Multipart;
-use chrono::Utc;
-use vectx_core::{CollectionConfig, Collection, Distance, Point, PointId, Vector, PayloadFilter, FilterCondition, Filter, MultiVector};
-use vectx_storage::StorageManager;
+use vectx_core::{CancellationToken, CollectionConfig, Collection, Distance, NormalizationPolicy, DuplicatePolicy, UpsertStatus, Point, PointId, Vector, PayloadFilter, Filter, FilterCondition, MultiVector, VectorFormula, TextDictionaries, HighlightOptions, SearchDefaults, RecencyBoost, RecencyOverride, QueryTemplate, EfTuning, Projection, VectorUsing, ComputedFields, GeoOrigin, HnswParams, HnswParamsDiff};
+use vectx_core::background::get_background_system;
+use vectx_core::recency::{self, RECENCY_OVERSAMPLE};
+use vectx_storage::{ApiKey, AuditQuery, Locks, Permission, RecoverOptions, RestoreScope, SnapshotPriority, StorageManager, VectorEncoding};
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize};
+use crate::audit;
+use crate::auth::{self, ClaimFilter};
+use crate::consistency::{self, ReadParams, UpdateParams};
+use crate::core_requests::{self, dense_vector, parse_fuzzy, vector_json, PointInput, RecommendRequest as CoreRecommendRequest, SearchRequest as CoreSearchRequest};
+use crate::compression;
+use crate::content;
+use crate::deadline;
+use crate::extract::{parse_mode, Body, TypedJson};
+use crate::logging;
+use crate::openapi::OpenApi;
+use crate::pagination::{Hit, PageCache, PageOwner, PAGE_DEPTH, TOKEN_TTL};
+use crate::parsing::{self, ParseError, ParseMode};
+use crate::rerank::RerankRequest;
 use std::sync::Arc;
 use std::path::Path;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::time::Instant;
 use futures_util::StreamExt;
 
-/// Create Qdrant-compatible JSON response with status and time
+/// Create Qdrant-compatible JSON response with status and time, or
+/// MessagePack when the request asked for it (see [`content`])
 fn qdrant_response<T: Serialize>(result: T, start_time: Instant) -> HttpResponse {
     let elapsed = start_time.elapsed().as_secs_f64();
-    HttpResponse::Ok().json(serde_json::json!({
+    content::respond(HttpResponse::Ok(), &serde_json::json!({
         "result": result,
         "status": "ok",
         "time": elapsed
     }))
 }
 
+/// Response for a search cut short by its timeout, with the results found
+/// before the deadline
+fn qdrant_partial_response<T: Serialize>(result: T, start_time: Instant) -> HttpResponse {
+    let elapsed = start_time.elapsed().as_secs_f64();
+    content::respond(HttpResponse::Ok(), &serde_json::json!({
+        "result": result,
+        "status": "ok",
+        "partial": true,
+        "time": elapsed
+    }))
+}
+
+/// Response with the token of the next page of results, if there is one
+/// (see [`pagination`])
+fn qdrant_paged_response<T: Serialize>(result: T, next_page_token: Option<String>, start_time: Instant) -> HttpResponse {
+    let Some(token) = next_page_token else {
+        return qdrant_response(result, start_time);
+    };
+    let elapsed = start_time.elapsed().as_secs_f64();
+    content::respond(HttpResponse::Ok(), &serde_json::json!({
+        "result": result,
+        "status": "ok",
+        "next_page_token": token,
+        "time": elapsed
+    }))
+}
+
+/// 408 for a search that did not finish within its timeout
+fn qdrant_timeout(start_time: Instant) -> HttpResponse {
+    let elapsed = start_time.elapsed().as_secs_f64();
+    HttpResponse::RequestTimeout().json(serde_json::json!({
+        "status": {
+            "error": "Timeout: search did not finish within the requested time"
+        },
+        "time": elapsed
+    }))
+}
+
 /// Create Qdrant-compatible error response
 fn qdrant_error(error: &str, start_time: Instant) -> HttpResponse {
     let elapsed = start_time.elapsed().as_secs_f64();
@@ -33,6 +91,19 @@ fn qdrant_error(error: &str, start_time: Instant) -> HttpResponse {
     }))
 }
 
+/// 400 for a part of the request that cannot be parsed, with the path to
+/// it in `status.path`
+pub(crate) fn qdrant_parse_error(error: &ParseError, start_time: Instant) -> HttpResponse {
+    let elapsed = start_time.elapsed().as_secs_f64();
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "status": {
+            "error": format!("Invalid request: {}", error),
+            "path": error.path
+        },
+        "time": elapsed
+    }))
+}
+
 /// Create Qdrant-compatible not found response
 fn qdrant_not_found(error: &str, start_time: Instant) -> HttpResponse {
     let elapsed = start_time.elapsed().as_secs_f64();
@@ -44,43 +115,304 @@ fn qdrant_not_found(error: &str, start_time: Instant) -> HttpResponse {
     }))
 }
 
+/// Seconds a client is asked to wait before retrying a request for a
+/// collection that is being unloaded
+const UNLOADING_RETRY_AFTER_SECS: u64 = 1;
+
+/// Middleware answering 503 with `Retry-After` to requests that touch a
+/// collection while it is being unloaded, instead of loading it back
+async fn unloading_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let segments: Vec<&str> = req.path().trim_matches('/').split('/').collect();
+    if let ["collections", name, ..] = segments.as_slice() {
+        let storage = req.app_data::<web::Data<Arc<StorageManager>>>();
+        if storage.is_some_and(|storage| storage.is_unloading(name)) {
+            let response = HttpResponse::ServiceUnavailable()
+                .insert_header((actix_web::http::header::RETRY_AFTER, UNLOADING_RETRY_AFTER_SECS.to_string()))
+                .json(serde_json::json!({
+                    "status": {
+                        "error": format!("Collection {} is being unloaded, retry later", name)
+                    },
+                    "time": 0.0
+                }));
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+/// Whether a REST request changes stored data: point and payload writes,
+/// field indexes, and creating, updating, deleting, recovering or running
+/// jobs over collections
+fn changes_data(method: &Method, path: &str) -> bool {
+    if method == Method::GET {
+        return false;
+    }
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["collections", _] | ["collections", _, "jobs", ..] => true,
+        ["collections", _, "snapshots", "upload" | "recover"] => true,
+        ["collections", _, "shards", _, "snapshots", "upload" | "recover"] => true,
+        // Admin routes that rebuild indexes or change search settings;
+        // unload, load and verify leave the data as it is
+        ["collections", _, "reindex" | "ef_tuning"] | ["collections", _, "index", "text", "rebuild"] => true,
+        _ => auth::required_access(method, path).is_some_and(|required| required.permission == Permission::Write),
+    }
+}
+
+/// Middleware answering 403 to writes while `/locks` forbids them; reads go
+/// on
+async fn write_lock_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    if changes_data(req.method(), req.path()) {
+        let storage = req.app_data::<web::Data<Arc<StorageManager>>>();
+        if let Some(message) = storage.and_then(|storage| storage.write_lock_error()) {
+            let response = HttpResponse::Forbidden().json(serde_json::json!({
+                "status": {
+                    "error": message
+                },
+                "time": 0.0
+            }));
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+/// Seconds a client is asked to wait before retrying a point write refused
+/// while the collection catches up with its pending points
+const ADMISSION_RETRY_AFTER_SECS: u64 = 1;
+
+/// Whether a REST request writes vectors the HNSW graph has to link:
+/// upserts, vector updates and batch updates
+fn writes_vectors(method: &Method, path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    matches!(
+        (method.as_str(), segments.as_slice()),
+        ("PUT", ["collections", _, "points"])
+            | ("PUT", ["collections", _, "points", "vectors"])
+            | ("POST", ["collections", _, "points", "batch"])
+    )
+}
+
+/// Middleware answering 429 with `Retry-After` to vector writes while the
+/// collection has as many pending points as the storage admits (see
+/// [`StorageManager::admission_error`]), e.g. during a long HNSW rebuild
+async fn admission_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    if writes_vectors(req.method(), req.path()) {
+        let segments: Vec<&str> = req.path().trim_matches('/').split('/').collect();
+        let storage = req.app_data::<web::Data<Arc<StorageManager>>>();
+        // A collection that is not loaded has nothing pending
+        let message = storage.and_then(|storage| {
+            let collection = storage.loaded_collection(segments[1])?;
+            storage.admission_error(&collection)
+        });
+        if let Some(message) = message {
+            let response = HttpResponse::TooManyRequests()
+                .insert_header((actix_web::http::header::RETRY_AFTER, ADMISSION_RETRY_AFTER_SECS.to_string()))
+                .json(serde_json::json!({
+                    "status": {
+                        "error": message
+                    },
+                    "time": 0.0
+                }));
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+/// Middleware counting successful searches and queries, with their
+/// latency, towards the collection's statistics history. A batch request
+/// counts once.
+async fn query_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let segments: Vec<&str> = req.path().trim_matches('/').split('/').collect();
+    let collection = match segments.as_slice() {
+        ["collections", name, "points", "search" | "query" | "recommend" | "discover" | "hybrid", ..]
+            if req.method() == Method::POST => Some(name.to_string()),
+        _ => None,
+    };
+    let started = Instant::now();
+    let response = next.call(req).await?;
+    if let Some(name) = collection.filter(|_| response.status().is_success()) {
+        let storage = response.request().app_data::<web::Data<Arc<StorageManager>>>();
+        if let Some(collection) = storage.and_then(|storage| storage.loaded_collection(&name)) {
+            collection.record_query(started.elapsed());
+        }
+    }
+    Ok(response)
+}
+
+/// Payload filter forced by the caller's JWT, if any
+fn claim_filter(http_req: &HttpRequest) -> Option<Arc<ClaimFilter>> {
+    http_req.extensions().get::<Arc<ClaimFilter>>().cloned()
+}
+
+/// The result lists of paginated searches kept by the server handling
+/// `http_req`
+fn page_cache(http_req: &HttpRequest) -> &PageCache {
+    http_req.app_data::<web::Data<PageCache>>().expect("the REST server keeps a page cache")
+}
+
+/// Owner of the result list of `http_req`'s search with `filter`: the
+/// caller's API key, which also carries any JWT claim, and the filter
+fn page_owner(http_req: &HttpRequest, filter: Option<&serde_json::Value>) -> PageOwner {
+    PageOwner::new(auth::header_key(http_req.headers()), filter)
+}
+
+/// Parse a request filter in the server's parse mode (see [`parse_mode`])
+fn parse_request_filter(filter: Option<&serde_json::Value>, mode: ParseMode) -> Result<Option<FilterCondition>, ParseError> {
+    filter.map_or(Ok(None), |filter| core_requests::try_parse_filter(filter, mode))
+}
+
+/// Parse a request filter, restricted to what the caller's claim allows
+/// (see [`core_requests::build_filter`])
+fn build_filter(
+    filter: Option<&serde_json::Value>,
+    mode: ParseMode,
+    claim: Option<&ClaimFilter>,
+    collection: Option<&Collection>,
+) -> Result<Option<Box<dyn Filter>>, ParseError> {
+    Ok(core_requests::build_filter(parse_request_filter(filter, mode)?, claim, collection))
+}
+
 // Dashboard configuration
 const STATIC_DIR: &str = "./static";
 const DASHBOARD_PATH: &str = "/dashboard";
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct CreateCollectionRequest {
     /// Dense vectors configuration (optional - can be omitted for sparse-only collections)
     #[serde(default, deserialize_with = "deserialize_vectors_optional")]
-    vectors: Option<VectorConfig>,
+    vectors: Option<VectorsConfig>,
     #[serde(default)]
     use_hnsw: bool,
     #[serde(default)]
     enable_bm25: bool,
-    // Qdrant compatibility - sparse vectors (stored but not fully implemented)
+    /// Detect the language of each point's text and stem it accordingly
+    #[serde(default)]
+    detect_language: bool,
+    /// Named sparse vectors, e.g. `{"text": {"modifier": "idf"}}`
     #[serde(default)]
     sparse_vectors: Option<serde_json::Value>,
+    /// Parameters applied to searches that leave them out
+    #[serde(default)]
+    search_defaults: Option<SearchDefaults>,
+    /// Whether cosine vectors are stored normalized (default `on_write`)
+    #[serde(default)]
+    normalization: Option<NormalizationPolicy>,
+    /// What upserts do with a vector another point already has (default `allow`)
+    #[serde(default)]
+    duplicate_policy: Option<DuplicatePolicy>,
+    /// HNSW graph parameters; unset ones take their defaults
+    #[serde(default)]
+    hnsw_config: Option<HnswParamsDiff>,
+    /// Without a dense vector size, take it from the first point written.
+    /// Otherwise such a collection rejects dense vectors.
+    #[serde(default)]
+    infer_vector_dim: bool,
+    /// Qdrant compatibility - validated; a single node always keeps one replica
+    #[serde(flatten)]
+    replication: consistency::ReplicationParams,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Clone, JsonSchema)]
 struct VectorConfig {
     size: usize,
     distance: Option<String>,
-    // Qdrant compatibility - ignored fields
-    #[serde(default)]
-    on_disk: Option<bool>,
-    #[serde(default)]
-    hnsw_config: Option<serde_json::Value>,
-    #[serde(default)]
-    quantization_config: Option<serde_json::Value>,
     #[serde(default)]
     multivector_config: Option<serde_json::Value>,
-    #[serde(default)]
-    datatype: Option<String>,
+}
+
+/// `vectors` of a new collection: one config, or configs by vector name
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum VectorsConfig {
+    Single(VectorConfig),
+    Named(BTreeMap<String, VectorConfig>),
+}
+
+impl VectorsConfig {
+    /// Config of the collection's dense vectors: the single config, or the
+    /// first named one without a `multivector_config`
+    fn dense(&self) -> Option<&VectorConfig> {
+        match self {
+            Self::Single(config) => Some(config),
+            Self::Named(named) => named.values().find(|config| config.multivector_config.is_none()),
+        }
+    }
+
+    /// Name of the dense vector in the named form
+    fn dense_name(&self) -> Option<&str> {
+        match self {
+            Self::Single(_) => None,
+            Self::Named(named) => named.iter()
+                .find(|(_, config)| config.multivector_config.is_none())
+                .map(|(name, _)| name.as_str()),
+        }
+    }
+
+    /// Named configs with a `multivector_config`, declared as named
+    /// multivectors. A single config with one stays the unnamed multivector.
+    fn named_multivectors(&self) -> Result<Vec<(String, vectx_core::MultiVectorParams)>, String> {
+        let Self::Named(named) = self else { return Ok(Vec::new()) };
+        named.iter()
+            .filter_map(|(name, config)| config.multivector_config.as_ref().map(|mv| (name, config, mv)))
+            .map(|(name, config, multivector_config)| {
+                let distance = config.distance.as_deref().and_then(parse_distance).unwrap_or(Distance::Cosine);
+                if distance == Distance::Hamming {
+                    return Err(format!("multivector '{}': Hamming distance is not supported for multivectors", name));
+                }
+                if config.size == 0 {
+                    return Err(format!("multivector '{}': size must be greater than 0", name));
+                }
+                let multivector_config = serde_json::from_value(multivector_config.clone())
+                    .map_err(|e| format!("multivector '{}': {}", name, e))?;
+                Ok((name.clone(), vectx_core::MultiVectorParams { size: config.size, distance, multivector_config }))
+            })
+            .collect()
+    }
+}
+
+fn parse_distance(name: &str) -> Option<Distance> {
+    match name {
+        "Cosine" | "cosine" => Some(Distance::Cosine),
+        "Euclid" | "Euclidean" | "euclidean" => Some(Distance::Euclidean),
+        "Dot" | "dot" => Some(Distance::Dot),
+        "Hamming" | "hamming" => Some(Distance::Hamming),
+        _ => None,
+    }
+}
+
+/// Distance set by a collection update's `vectors`, given as
+/// `{"distance": ...}` or per vector as `{"": {"distance": ...}}`
+fn update_distance(vectors: &serde_json::Value) -> Result<Option<Distance>, String> {
+    let name = match vectors.get("distance") {
+        Some(name) => Some(name),
+        None => vectors.as_object()
+            .and_then(|named| named.values().find_map(|config| config.get("distance"))),
+    };
+    match name {
+        None => Ok(None),
+        Some(name) => name.as_str()
+            .and_then(parse_distance)
+            .map(Some)
+            .ok_or_else(|| format!("Unknown distance {}", name)),
+    }
 }
 
 // Custom deserializer to handle both simple and named vector formats
-fn deserialize_vectors_optional<'de, D>(deserializer: D) -> Result<Option<VectorConfig>, D::Error>
+fn deserialize_vectors_optional<'de, D>(deserializer: D) -> Result<Option<VectorsConfig>, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -92,14 +424,13 @@ where
     
     // Try simple format first: {"size": 1536, "distance": "Cosine"}
     if let Ok(config) = serde_json::from_value::<VectorConfig>(value.clone()) {
-        return Ok(Some(config));
+        return Ok(Some(VectorsConfig::Single(config)));
     }
     
     // Try named vectors format: {"": {"size": 1536, ...}} or {"vector_name": {"size": 1536, ...}}
-    if let Ok(named) = serde_json::from_value::<HashMap<String, VectorConfig>>(value.clone()) {
-        // Use the first vector config (default or named)
-        if let Some(config) = named.into_values().next() {
-            return Ok(Some(config));
+    if let Ok(named) = serde_json::from_value::<BTreeMap<String, VectorConfig>>(value.clone()) {
+        if !named.is_empty() {
+            return Ok(Some(VectorsConfig::Named(named)));
         }
     }
     
@@ -122,19 +453,56 @@ struct VectorConfigResponse {
     distance: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct UpsertPointsRequest {
     points: Vec<PointRequest>,
+    /// Reject the whole request at the first invalid point. When false,
+    /// valid points are written and invalid ones reported as failed.
+    #[serde(default = "default_abort_on_error")]
+    abort_on_error: bool,
+    /// Report the status of each point; implied when `abort_on_error` is false
+    #[serde(default)]
+    report_statuses: bool,
 }
 
-/// Parsed sparse vector data
-struct ParsedSparseVector {
-    /// Name of the sparse vector (e.g., "keywords")
-    name: String,
-    /// Indices of non-zero elements
-    indices: Vec<u32>,
-    /// Values at those indices
-    values: Vec<f32>,
+fn default_abort_on_error() -> bool {
+    true
+}
+
+fn upsert_status_json(id: &serde_json::Value, status: &UpsertStatus) -> serde_json::Value {
+    match status {
+        UpsertStatus::Inserted => serde_json::json!({"id": id, "status": "inserted"}),
+        UpsertStatus::Updated => serde_json::json!({"id": id, "status": "updated"}),
+        UpsertStatus::Merged(duplicate_of) => {
+            serde_json::json!({"id": id, "status": "merged", "duplicate_of": duplicate_of})
+        }
+        UpsertStatus::Failed(error) => serde_json::json!({"id": id, "status": "failed", "error": error}),
+    }
+}
+
+/// Named sparse vector configs from a `sparse_vectors` config object. For
+/// vectors `current` already declares, fields left out keep their value.
+fn parse_sparse_vectors(
+    config: &serde_json::Value,
+    current: Option<&Collection>,
+) -> Result<Vec<(String, vectx_core::SparseVectorConfig)>, String> {
+    let obj = config.as_object().ok_or("sparse_vectors must be an object")?;
+    obj.iter()
+        .map(|(name, params)| {
+            let params = if params.is_null() { serde_json::json!({}) } else { params.clone() };
+            let mut parsed: vectx_core::SparseVectorConfig = serde_json::from_value(params.clone())
+                .map_err(|e| format!("sparse vector '{}': {}", name, e))?;
+            if let Some(existing) = current.and_then(|c| c.sparse_vector(name)) {
+                if params.get("index").is_none() {
+                    parsed.index = existing.index;
+                }
+                if params.get("modifier").is_none() {
+                    parsed.modifier = existing.modifier;
+                }
+            }
+            Ok((name.clone(), parsed))
+        })
+        .collect()
 }
 
 /// Parsed vector data - can be single, multi, or sparse
@@ -143,127 +511,151 @@ struct ParsedVector {
     primary: Vec<f32>,
     /// Full multivector data if this was a multivector input
     multivector: Option<Vec<Vec<f32>>>,
-    /// Sparse vectors (named)
-    sparse_vectors: Vec<ParsedSparseVector>,
+    /// Multivectors given by name; those the collection declares are stored
+    /// as named multivectors, any other becomes the unnamed multivector
+    named_multivectors: Vec<(String, Vec<Vec<f32>>)>,
+    /// Sparse vectors, keyed by vector name (e.g., "keywords")
+    sparse_vectors: Vec<(String, vectx_core::SparseVector)>,
+    /// Bytes of a binary vector sent as a base64 string
+    binary: Option<Vec<u8>>,
+}
+
+/// Parse and validate a `{"indices": [...], "values": [...]}` object
+fn parse_sparse_vector(obj: &serde_json::Map<String, serde_json::Value>) -> Result<vectx_core::SparseVector, String> {
+    let indices = obj.get("indices")
+        .and_then(|i| i.as_array())
+        .ok_or("Invalid sparse vector: missing indices")?
+        .iter()
+        .map(|i| i.as_u64().and_then(|n| u32::try_from(n).ok()))
+        .collect::<Option<Vec<u32>>>()
+        .ok_or("Invalid sparse vector: indices must be unsigned 32-bit integers")?;
+    let values = obj.get("values")
+        .and_then(|v| v.as_array())
+        .ok_or("Invalid sparse vector: missing values")?
+        .iter()
+        .map(|v| v.as_f64().map(|f| f as f32))
+        .collect::<Option<Vec<f32>>>()
+        .ok_or("Invalid sparse vector: values must be numbers")?;
+
+    let sparse = vectx_core::SparseVector::new(indices, values);
+    sparse.validate().map_err(|e| e.to_string())?;
+    Ok(sparse)
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct PointRequest {
+    /// May be omitted on `PUT /collections/{name}/points`, where the server
+    /// then assigns a random UUID
+    #[serde(default)]
     id: serde_json::Value,
     /// Vector is optional when using similarity schema (auto-embedding mode)
     #[serde(default, deserialize_with = "deserialize_vector_optional")]
+    #[schemars(with = "Option<serde_json::Value>")]
     vector: Option<ParsedVector>,
     payload: Option<serde_json::Value>,
 }
 
-// Custom deserializer to handle multiple vector formats (Qdrant compatibility)
-// Simple: [0.1, 0.2, 0.3]
-// Multivector: [[0.1, 0.2], [0.3, 0.4]] -> stores full multivector for MaxSim search
-// Named vectors: {"vector_name": [0.1, 0.2]} -> extracts the vector
-fn deserialize_vector<'de, D>(deserializer: D) -> Result<ParsedVector, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let value = serde_json::Value::deserialize(deserializer)?;
-    
-    fn parse_simple_vector(arr: &[serde_json::Value]) -> Result<Vec<f32>, String> {
-        arr.iter()
-            .map(|v| v.as_f64().map(|f| f as f32).ok_or_else(|| "expected f32".to_string()))
-            .collect()
-    }
-    
-    fn parse_multivector(arr: &[serde_json::Value]) -> Result<Vec<Vec<f32>>, String> {
-        arr.iter()
-            .map(|sub| {
-                if let serde_json::Value::Array(sub_arr) = sub {
-                    parse_simple_vector(sub_arr)
-                } else {
-                    Err("expected array of arrays for multivector".to_string())
-                }
-            })
-            .collect()
-    }
-    
-    match &value {
-        // Array: could be simple vector or multivector
-        serde_json::Value::Array(arr) if !arr.is_empty() => {
-            match arr.first() {
-                // Simple vector: [0.1, 0.2, 0.3]
-                Some(serde_json::Value::Number(_)) => {
-                    let primary = parse_simple_vector(arr).map_err(serde::de::Error::custom)?;
-                    Ok(ParsedVector { primary, multivector: None, sparse_vectors: Vec::new() })
-                }
-                // Multivector: [[0.1, 0.2], [0.3, 0.4]] - store full multivector
-                Some(serde_json::Value::Array(_)) => {
-                    let multivec = parse_multivector(arr).map_err(serde::de::Error::custom)?;
-                    let primary = multivec.first().cloned().unwrap_or_default();
-                    Ok(ParsedVector { primary, multivector: Some(multivec), sparse_vectors: Vec::new() })
-                }
-                _ => Err(serde::de::Error::custom("invalid vector format: expected number or array"))
-            }
+/// Point id as clients send it: an unsigned integer or a string (UUID)
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(untagged)]
+enum ExtendedPointId {
+    Integer(u64),
+    String(String),
+}
+
+impl ExtendedPointId {
+    /// The key the collection stores the point under
+    fn to_id_string(&self) -> String {
+        match self {
+            Self::Integer(n) => n.to_string(),
+            Self::String(s) => s.clone(),
         }
-        // Empty array
-        serde_json::Value::Array(_) => {
-            Err(serde::de::Error::custom("vector cannot be empty"))
+    }
+}
+
+/// A query vector, or the id of a point whose vector is used
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum VectorInput {
+    Vector(Vec<f32>),
+    Id(ExtendedPointId),
+}
+
+impl VectorInput {
+    /// The vector, looking up the point for an id
+    fn resolve(&self, collection: &Collection) -> Option<Vector> {
+        match self {
+            Self::Vector(v) => Some(Vector::new(v.clone())),
+            Self::Id(id) => collection.get(&id.to_id_string()).map(|p| p.vector),
         }
-        // Named vector: {"vector_name": [0.1, 0.2]} or {"": [...]}
-        // Or sparse vector: {"keywords": {"indices": [...], "values": [...]}}
-        serde_json::Value::Object(obj) => {
-            let mut sparse_vectors = Vec::new();
-            let mut primary = vec![0.0];
-            let mut multivector = None;
-            
-            for (name, vec_value) in obj.iter() {
-                match vec_value {
-                    // Dense named vector: [0.1, 0.2, 0.3]
-                    serde_json::Value::Array(arr) if !arr.is_empty() => {
-                        match arr.first() {
-                            Some(serde_json::Value::Number(_)) => {
-                                primary = parse_simple_vector(arr).map_err(serde::de::Error::custom)?;
-                            }
-                            Some(serde_json::Value::Array(_)) => {
-                                // Named multivector
-                                let multivec = parse_multivector(arr).map_err(serde::de::Error::custom)?;
-                                primary = multivec.first().cloned().unwrap_or_default();
-                                multivector = Some(multivec);
-                            }
-                            _ => {}
-                        }
-                    }
-                    // Sparse vector: {"indices": [...], "values": [...]}
-                    serde_json::Value::Object(sparse_obj) => {
-                        if let (Some(indices_arr), Some(values_arr)) = (
-                            sparse_obj.get("indices").and_then(|i| i.as_array()),
-                            sparse_obj.get("values").and_then(|v| v.as_array())
-                        ) {
-                            let indices: Vec<u32> = indices_arr.iter()
-                                .filter_map(|i| i.as_u64().map(|n| n as u32))
-                                .collect();
-                            let values: Vec<f32> = values_arr.iter()
-                                .filter_map(|v| v.as_f64().map(|f| f as f32))
-                                .collect();
-                            
-                            if !indices.is_empty() && !values.is_empty() {
-                                sparse_vectors.push(ParsedSparseVector {
-                                    name: name.clone(),
-                                    indices,
-                                    values,
-                                });
-                            }
-                        }
-                    }
-                    // Empty array - ignore
-                    serde_json::Value::Array(_) => {}
-                    _ => {}
-                }
-            }
-            
-            Ok(ParsedVector { primary, multivector, sparse_vectors })
+    }
+}
+
+/// Named multivectors of a point as `{name: [[...], ...]}`
+fn named_multivectors_json(point: &Point) -> serde_json::Value {
+    point.named_multivectors.iter()
+        .map(|(name, mv)| (name.clone(), serde_json::json!(mv.vectors())))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD
+        .decode(text)
+        .map_err(|e| format!("invalid base64 vector: {}", e))
+}
+
+/// A dense query vector: numbers, or the bytes of a binary vector as a
+/// base64 string
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(untagged)]
+enum DenseInput {
+    Values(Vec<f32>),
+    Base64(String),
+}
+
+impl DenseInput {
+    fn to_vector(&self, collection: &Collection) -> Result<Vector, String> {
+        match self {
+            Self::Values(values) => dense_vector(collection, values, None),
+            Self::Base64(text) => dense_vector(collection, &[], Some(&decode_base64(text)?)),
         }
-        _ => Err(serde::de::Error::custom("vector must be an array or object")),
     }
 }
 
+/// The shared model of an upserted point
+fn point_input(point_req: &PointRequest) -> Result<PointInput, String> {
+    let id = match &point_req.id {
+        serde_json::Value::String(s) => PointId::String(s.clone()),
+        serde_json::Value::Number(n) => match n.as_u64() {
+            Some(u) => PointId::Integer(u),
+            None => return Err("Invalid point ID".to_string()),
+        },
+        _ => return Err("Invalid point ID".to_string()),
+    };
+    let mut input = PointInput::new(id, Vec::new(), point_req.payload.clone());
+    if let Some(parsed) = &point_req.vector {
+        input.vector = parsed.primary.clone();
+        input.binary = parsed.binary.clone();
+        input.multivector = parsed.multivector.clone();
+        input.named_multivectors = parsed.named_multivectors.clone();
+        input.sparse_vectors = parsed.sparse_vectors.clone();
+    }
+    Ok(input)
+}
+
+/// Parse an upserted point as `PUT /points` does (see [`crate::fuzz`])
+pub(crate) fn parse_point(body: &[u8]) -> Result<PointInput, String> {
+    let point: PointRequest = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+    point_input(&point)
+}
+
+/// Build the point to store from an upserted point
+fn point_from_request(point_req: &PointRequest, collection: &Collection) -> Result<Point, String> {
+    point_input(point_req)?.into_point(collection)
+}
+
 // Custom deserializer for optional vector (for similarity schema auto-embedding)
 fn deserialize_vector_optional<'de, D>(deserializer: D) -> Result<Option<ParsedVector>, D::Error>
 where
@@ -278,9 +670,21 @@ where
     // Reuse the same parsing logic
     fn parse_simple_vector(arr: &[serde_json::Value]) -> Result<Vec<f32>, String> {
         arr.iter()
-            .map(|v| v.as_f64().map(|f| f as f32).ok_or_else(|| "expected f32".to_string()))
+            .map(|v| match v.as_f64().map(|f| f as f32) {
+                Some(f) if f.is_finite() => Ok(f),
+                Some(_) => Err(format!("{} is out of range for f32", v)),
+                None => Err("expected f32".to_string()),
+            })
             .collect()
     }
+
+    // A named vector that cannot be parsed: an error in strict mode, left
+    // out of the point in lenient mode
+    fn skip_named<E: serde::de::Error>(name: &str, message: &str) -> Result<(), E> {
+        parsing::mode()
+            .tolerate(ParseError::new(format!("vector '{}'", name), message))
+            .map_err(|e| E::custom(e.to_string()))
+    }
     
     fn parse_multivector(arr: &[serde_json::Value]) -> Result<Vec<Vec<f32>>, String> {
         arr.iter()
@@ -299,21 +703,26 @@ where
             match arr.first() {
                 Some(serde_json::Value::Number(_)) => {
                     let primary = parse_simple_vector(arr).map_err(serde::de::Error::custom)?;
-                    Ok(Some(ParsedVector { primary, multivector: None, sparse_vectors: Vec::new() }))
+                    Ok(Some(ParsedVector { primary, multivector: None, named_multivectors: Vec::new(), sparse_vectors: Vec::new(), binary: None }))
                 }
                 Some(serde_json::Value::Array(_)) => {
                     let multivec = parse_multivector(arr).map_err(serde::de::Error::custom)?;
                     let primary = multivec.first().cloned().unwrap_or_default();
-                    Ok(Some(ParsedVector { primary, multivector: Some(multivec), sparse_vectors: Vec::new() }))
+                    Ok(Some(ParsedVector { primary, multivector: Some(multivec), named_multivectors: Vec::new(), sparse_vectors: Vec::new(), binary: None }))
                 }
                 _ => Err(serde::de::Error::custom("invalid vector format"))
             }
         }
         serde_json::Value::Array(_) => Ok(None), // Empty array treated as no vector
+        serde_json::Value::Object(obj) if obj.contains_key("indices") && obj.contains_key("values") => {
+            Err(serde::de::Error::custom(
+                "sparse vectors must be named, e.g. {\"text\": {\"indices\": [...], \"values\": [...]}}"
+            ))
+        }
         serde_json::Value::Object(obj) => {
             let mut sparse_vectors = Vec::new();
-            let mut primary = vec![0.0];
-            let mut multivector = None;
+            let mut primary = Vec::new();
+            let mut named_multivectors = Vec::new();
             
             for (name, vec_value) in obj.iter() {
                 match vec_value {
@@ -324,47 +733,37 @@ where
                             }
                             Some(serde_json::Value::Array(_)) => {
                                 let multivec = parse_multivector(arr).map_err(serde::de::Error::custom)?;
-                                primary = multivec.first().cloned().unwrap_or_default();
-                                multivector = Some(multivec);
+                                named_multivectors.push((name.clone(), multivec));
                             }
-                            _ => {}
+                            _ => skip_named(name, "expected numbers or arrays of numbers")?,
                         }
                     }
                     serde_json::Value::Object(sparse_obj) => {
-                        if let (Some(indices_arr), Some(values_arr)) = (
-                            sparse_obj.get("indices").and_then(|i| i.as_array()),
-                            sparse_obj.get("values").and_then(|v| v.as_array())
-                        ) {
-                            let indices: Vec<u32> = indices_arr.iter()
-                                .filter_map(|i| i.as_u64().map(|n| n as u32))
-                                .collect();
-                            let values: Vec<f32> = values_arr.iter()
-                                .filter_map(|v| v.as_f64().map(|f| f as f32))
-                                .collect();
-                            
-                            if !indices.is_empty() && !values.is_empty() {
-                                sparse_vectors.push(ParsedSparseVector {
-                                    name: name.clone(),
-                                    indices,
-                                    values,
-                                });
-                            }
+                        let sparse = parse_sparse_vector(sparse_obj)
+                            .map_err(|e| serde::de::Error::custom(format!("vector '{}': {}", name, e)))?;
+                        if !sparse.is_empty() {
+                            sparse_vectors.push((name.clone(), sparse));
                         }
                     }
-                    _ => {}
+                    serde_json::Value::Array(_) | serde_json::Value::Null => {}
+                    _ => skip_named(name, "expected a vector, a multivector or a sparse vector")?,
                 }
             }
             
-            Ok(Some(ParsedVector { primary, multivector, sparse_vectors }))
+            Ok(Some(ParsedVector { primary, multivector: None, named_multivectors, sparse_vectors, binary: None }))
+        }
+        serde_json::Value::String(text) => {
+            let binary = decode_base64(text).map_err(serde::de::Error::custom)?;
+            Ok(Some(ParsedVector { primary: Vec::new(), multivector: None, named_multivectors: Vec::new(), sparse_vectors: Vec::new(), binary: Some(binary) }))
         }
         serde_json::Value::Null => Ok(None),
-        _ => Err(serde::de::Error::custom("vector must be an array, object, or null")),
+        _ => Err(serde::de::Error::custom("vector must be an array, object, base64 string or null")),
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct SearchRequest {
-    vector: Option<Vec<f32>>,
+    vector: Option<DenseInput>,
     text: Option<String>,
     #[serde(alias = "top")]
     limit: Option<usize>,
@@ -377,6 +776,71 @@ struct SearchRequest {
     score_threshold: Option<f32>,
     #[serde(default)]
     offset: Option<usize>,
+    /// Highlight matched terms of a text search: `true` or options
+    #[serde(default)]
+    highlight: Option<HighlightRequest>,
+    /// Restrict a text search to documents in this language (ISO 639-3)
+    #[serde(default)]
+    language: Option<String>,
+    /// Payload fields computed from expressions over the payload, such as
+    /// `"price * 1.22"`, added to each result by name
+    #[serde(default)]
+    computed: std::collections::BTreeMap<String, String>,
+    /// Recency boost replacing the collection's, or `false` to turn it off
+    #[serde(default)]
+    recency: Option<RecencyOverride>,
+    /// Keep the results after this page and answer with a
+    /// `next_page_token` (vector searches)
+    #[serde(default)]
+    paginate: bool,
+    /// Token of the page to return from an earlier paginated search; the
+    /// search itself is not run
+    #[serde(default)]
+    page_token: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum HighlightRequest {
+    Enabled(bool),
+    Options(HighlightOptions),
+}
+
+/// The recency boost of a request on a collection with `defaults`, or the
+/// error of an invalid one
+fn recency_boost<'a>(defaults: &'a SearchDefaults, requested: Option<&'a RecencyOverride>) -> Result<Option<&'a RecencyBoost>, String> {
+    requested.map_or(Ok(()), RecencyOverride::validate)?;
+    Ok(defaults.recency(requested))
+}
+
+/// The points of kept results that still exist and that `claim` lets the
+/// caller see
+fn cached_hits(collection: &Collection, hits: Vec<Hit>, claim: Option<&ClaimFilter>) -> Vec<(Point, f32, serde_json::Value)> {
+    hits.into_iter()
+        .filter_map(|hit| {
+            collection.get(&hit.id)
+                .filter(|point| claim.map_or(true, |c| c.matches(point)))
+                .map(|point| (point, hit.score, hit.explanation))
+        })
+        .collect()
+}
+
+/// Candidates to fetch for `n` results, more when a boost may reorder them
+fn recency_candidates(recency: Option<&RecencyBoost>, n: usize) -> usize {
+    match recency {
+        Some(_) => n * RECENCY_OVERSAMPLE,
+        None => n,
+    }
+}
+
+impl HighlightRequest {
+    fn options(&self) -> Option<HighlightOptions> {
+        match self {
+            Self::Enabled(true) => Some(HighlightOptions::default()),
+            Self::Enabled(false) => None,
+            Self::Options(options) => Some(options.clone()),
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -389,12 +853,27 @@ struct SearchResult {
 
 pub struct RestApi;
 
+/// Where the REST server accepts connections
+enum Listen {
+    Port(u16),
+    Listener(std::net::TcpListener),
+}
+
 impl RestApi {
     pub async fn start(
         storage: Arc<StorageManager>,
         port: u16,
     ) -> std::io::Result<()> {
-        Self::start_with_static_dir(storage, port, STATIC_DIR).await
+        Self::start_with_parse_mode(storage, port, ParseMode::default()).await
+    }
+
+    /// [`RestApi::start`], parsing requests in `parse_mode`
+    pub async fn start_with_parse_mode(
+        storage: Arc<StorageManager>,
+        port: u16,
+        parse_mode: ParseMode,
+    ) -> std::io::Result<()> {
+        Self::server(storage, STATIC_DIR, Listen::Port(port), parse_mode)?.await
     }
     
     pub async fn start_with_static_dir(
@@ -402,9 +881,39 @@ impl RestApi {
         port: u16,
         static_dir: &str,
     ) -> std::io::Result<()> {
+        Self::server(storage, static_dir, Listen::Port(port), ParseMode::default())?.await
+    }
+
+    /// The server accepting connections on `listener`, e.g. one bound to a
+    /// random port. It runs once awaited on an actix system, and its
+    /// handle stops it. Call from within that system.
+    pub fn serve(
+        storage: Arc<StorageManager>,
+        listener: std::net::TcpListener,
+    ) -> std::io::Result<actix_web::dev::Server> {
+        Self::serve_with_parse_mode(storage, listener, ParseMode::default())
+    }
+
+    /// [`RestApi::serve`], parsing requests in `parse_mode`
+    pub fn serve_with_parse_mode(
+        storage: Arc<StorageManager>,
+        listener: std::net::TcpListener,
+        parse_mode: ParseMode,
+    ) -> std::io::Result<actix_web::dev::Server> {
+        Self::server(storage, STATIC_DIR, Listen::Listener(listener), parse_mode)
+    }
+
+    fn server(
+        storage: Arc<StorageManager>,
+        static_dir: &str,
+        listen: Listen,
+        parse_mode: ParseMode,
+    ) -> std::io::Result<actix_web::dev::Server> {
         let static_folder = static_dir.to_string();
+        // Shared by the workers, so a token works whichever one reads it
+        let pages = web::Data::new(PageCache::new(TOKEN_TTL));
         
-        HttpServer::new(move || {
+        let server = HttpServer::new(move || {
             let cors = Cors::default()
                 .allow_any_origin()
                 .allow_any_method()
@@ -412,14 +921,29 @@ impl RestApi {
                 .max_age(3600);
 
             let mut app = App::new()
+                .wrap(actix_web::middleware::from_fn(content::negotiate_points_format))
+                .wrap(actix_web::middleware::from_fn(query_metrics))
+                .wrap(actix_web::middleware::from_fn(admission_guard))
+                .wrap(actix_web::middleware::from_fn(write_lock_guard))
+                .wrap(actix_web::middleware::from_fn(unloading_guard))
+                .wrap(actix_web::middleware::from_fn(audit::audit_trail))
+                .wrap(actix_web::middleware::from_fn(auth::rest_access_control))
                 .wrap(cors)
+                .wrap(actix_web::middleware::Compress::default())
+                .wrap(actix_web::middleware::from_fn(compression::restrict_accept_encoding))
                 .app_data(web::Data::new(storage.clone()))
+                .app_data(web::Data::new(parse_mode))
+                .app_data(pages.clone())
+                .app_data(web::QueryConfig::default().error_handler(consistency::query_error_handler))
+                .app_data(web::JsonConfig::default().limit(compression::config().max_body_bytes))
+                .app_data(web::PayloadConfig::default().limit(compression::config().max_body_bytes))
                 // Service endpoints (Qdrant-compatible)
                 .route("/", web::get().to(root_info))
                 .route("/healthz", web::get().to(health_check))
                 .route("/livez", web::get().to(livez_check))
                 .route("/readyz", web::get().to(readyz_check))
                 .route("/metrics", web::get().to(metrics_endpoint))
+                .route("/openapi.json", web::get().to(openapi_document))
                 // Collection endpoints
                 .route("/collections", web::get().to(list_collections))
                 .route("/collections/{name}", web::get().to(get_collection))
@@ -433,6 +957,16 @@ impl RestApi {
                 .route("/collections/{name}/points/{id}", web::get().to(get_point))
                 .route("/collections/{name}/points/{id}", web::delete().to(delete_point))
                 .route("/collections/{name}/exists", web::get().to(collection_exists))
+                .route("/collections/{name}/unload", web::post().to(unload_collection))
+                .route("/collections/{name}/load", web::post().to(load_collection))
+                .route("/collections/{name}/reindex", web::post().to(reindex_collection))
+                .route("/collections/{name}/index/text/rebuild", web::post().to(rebuild_text_index))
+                .route("/collections/{name}/verify", web::post().to(verify_consistency))
+                .route("/collections/{name}/operations", web::get().to(list_operations))
+                .route("/collections/{name}/stats/history", web::get().to(stats_history))
+                .route("/collections/{name}/watch", web::get().to(watch_collection))
+                .route("/collections/{name}/export", web::get().to(export_collection))
+                .route("/collections/{name}/hnsw/graph", web::get().to(export_hnsw_graph))
                 // Qdrant compatibility - additional endpoints
                 .route("/aliases", web::get().to(list_aliases))
                 .route("/collections/aliases", web::post().to(update_aliases))
@@ -452,14 +986,34 @@ impl RestApi {
                 .route("/collections/{name}/points/batch", web::post().to(batch_update))
                 .route("/collections/{name}/points/search/batch", web::post().to(batch_search))
                 .route("/collections/{name}/points/search/groups", web::post().to(search_groups))
+                .route("/collections/{name}/points/search/matrix/pairs", web::post().to(search_matrix_pairs))
+                .route("/collections/{name}/points/search/matrix/offsets", web::post().to(search_matrix_offsets))
+                .route("/collections/{name}/points/hybrid", web::post().to(hybrid_search))
+                .route("/search", web::post().to(multi_search))
                 .route("/collections/{name}/points/query/batch", web::post().to(batch_query))
                 .route("/collections/{name}/points/query/groups", web::post().to(query_groups))
+                .route("/collections/{name}/points/query/templates/{template}", web::post().to(query_template))
                 .route("/collections/{name}/points/discover", web::post().to(discover_points))
                 .route("/collections/{name}/points/discover/batch", web::post().to(discover_batch))
                 .route("/collections/{name}/facet", web::post().to(facet_counts))
+                .route("/collections/{name}/aggregate", web::post().to(aggregate_points))
+                .route("/collections/{name}/payload/stats", web::get().to(payload_stats))
                 // Index endpoints
                 .route("/collections/{name}/index", web::put().to(create_field_index))
                 .route("/collections/{name}/index/{field_name}", web::delete().to(delete_field_index))
+                // Text analysis dictionaries
+                .route("/collections/{name}/synonyms", web::get().to(get_synonyms))
+                .route("/collections/{name}/synonyms", web::put().to(set_synonyms))
+                .route("/collections/{name}/stopwords", web::get().to(get_stopwords))
+                .route("/collections/{name}/stopwords", web::put().to(set_stopwords))
+                // Query templates
+                .route("/collections/{name}/templates", web::get().to(list_query_templates))
+                .route("/collections/{name}/templates/{template}", web::get().to(get_query_template))
+                .route("/collections/{name}/templates/{template}", web::put().to(set_query_template))
+                .route("/collections/{name}/templates/{template}", web::delete().to(delete_query_template))
+                .route("/collections/{name}/ef_tuning", web::get().to(get_ef_tuning))
+                .route("/collections/{name}/ef_tuning", web::put().to(set_ef_tuning))
+                .route("/collections/{name}/ef_tuning", web::delete().to(delete_ef_tuning))
                 // Recommend endpoint
                 .route("/collections/{name}/points/recommend", web::post().to(recommend_points))
                 // Snapshot endpoints (stubs for UI compatibility)
@@ -469,6 +1023,13 @@ impl RestApi {
                 .route("/collections/{name}/snapshots/recover", web::put().to(recover_snapshot))
                 .route("/collections/{name}/snapshots/{snapshot_name}", web::get().to(get_snapshot))
                 .route("/collections/{name}/snapshots/{snapshot_name}", web::delete().to(delete_snapshot))
+                // Shard snapshot endpoints (shard 0 is the whole collection)
+                .route("/collections/{name}/shards/{shard_id}/snapshots", web::get().to(list_shard_snapshots))
+                .route("/collections/{name}/shards/{shard_id}/snapshots", web::post().to(create_shard_snapshot))
+                .route("/collections/{name}/shards/{shard_id}/snapshots/upload", web::post().to(upload_shard_snapshot))
+                .route("/collections/{name}/shards/{shard_id}/snapshots/recover", web::put().to(recover_shard_snapshot))
+                .route("/collections/{name}/shards/{shard_id}/snapshots/{snapshot_name}", web::get().to(get_shard_snapshot))
+                .route("/collections/{name}/shards/{shard_id}/snapshots/{snapshot_name}", web::delete().to(delete_shard_snapshot))
                 // Full storage snapshots
                 .route("/snapshots", web::get().to(list_all_snapshots))
                 .route("/snapshots", web::post().to(create_full_snapshot))
@@ -477,8 +1038,27 @@ impl RestApi {
                 // Collection update endpoint
                 .route("/collections/{name}", web::patch().to(update_collection))
                 // Issues endpoints
+                .route("/locks", web::get().to(get_locks))
+                .route("/locks", web::post().to(set_locks))
+                .route("/locks", web::put().to(set_locks))
                 .route("/issues", web::get().to(get_issues))
-                .route("/issues", web::delete().to(clear_issues));
+                .route("/issues", web::delete().to(clear_issues))
+                // API key administration
+                .route("/admin/keys", web::get().to(list_api_keys))
+                .route("/admin/keys", web::post().to(create_api_key))
+                .route("/admin/keys/{key_id}", web::get().to(get_api_key))
+                .route("/admin/keys/{key_id}", web::delete().to(delete_api_key))
+                // Admin jobs
+                .route("/admin/jobs", web::get().to(list_jobs))
+                .route("/admin/jobs/workers", web::put().to(update_workers))
+                .route("/admin/jobs/{job_id}", web::get().to(get_job))
+                .route("/admin/jobs/{job_id}/cancel", web::post().to(cancel_job))
+                // Logging
+                .route("/admin/log_level", web::get().to(get_log_level))
+                .route("/admin/log_level", web::post().to(set_log_level))
+                .route("/admin/audit", web::get().to(list_audit_entries))
+                .route("/collections/{name}/jobs/kmeans", web::post().to(start_kmeans_job))
+                .route("/collections/{name}/jobs/dedup", web::post().to(start_dedup_job));
             
             // Serve web UI dashboard if static folder exists
             let static_path = Path::new(&static_folder);
@@ -491,13 +1071,134 @@ impl RestApi {
             }
             
             app
-        })
-        .bind(("0.0.0.0", port))?
-        .run()
-        .await
+        });
+        let server = match listen {
+            Listen::Port(port) => server.bind(("0.0.0.0", port))?,
+            Listen::Listener(listener) => server.listen(listener)?,
+        };
+        Ok(server.run())
     }
 }
 
+/// OpenAPI document of the routes registered in [`RestApi::server`]. An
+/// integration test reads the route table and fails on any route missing
+/// here.
+fn openapi_spec() -> serde_json::Value {
+    let mut spec = OpenApi::new();
+    spec.operation("get", "/", "Server version").public();
+    spec.operation("get", "/healthz", "Health check").public();
+    spec.operation("get", "/livez", "Liveness probe").produces("text/plain").public();
+    spec.operation("get", "/readyz", "Readiness probe").produces("text/plain").public();
+    spec.operation("get", "/metrics", "Prometheus metrics").produces("text/plain");
+    spec.operation("get", "/collections", "List collections");
+    spec.operation("get", "/collections/{name}", "Collection info").query::<CollectionInfoQuery>();
+    spec.operation("put", "/collections/{name}", "Create a collection").body::<CreateCollectionRequest>();
+    spec.operation("delete", "/collections/{name}", "Delete a collection");
+    spec.operation("put", "/collections/{name}/points", "Upsert points").body::<UpsertPointsRequest>().query::<UpdateParams>();
+    spec.operation("post", "/collections/{name}/points/scroll", "Scroll through points").body::<ScrollRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/delete", "Delete points by id or filter").body::<DeletePointsRequest>().query::<UpdateParams>();
+    spec.operation("post", "/collections/{name}/points/search", "Search by vector or text").body::<SearchRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/query", "Universal query with prefetch and fusion").body::<QueryRequest>().query::<ReadParams>();
+    spec.operation("get", "/collections/{name}/points/{id}", "Get a point").query::<ReadParams>();
+    spec.operation("delete", "/collections/{name}/points/{id}", "Delete a point").query::<UpdateParams>();
+    spec.operation("get", "/collections/{name}/exists", "Check that a collection exists");
+    spec.operation("post", "/collections/{name}/unload", "Drop a collection from memory");
+    spec.operation("post", "/collections/{name}/load", "Load a collection into memory");
+    spec.operation("post", "/collections/{name}/reindex", "Rebuild the HNSW index in the background");
+    spec.operation("post", "/collections/{name}/index/text/rebuild", "Rebuild the BM25 index from the payloads as a job");
+    spec.operation("post", "/collections/{name}/verify", "Compare the scores of brute force, HNSW and quantized search").body::<VerifyConsistencyRequest>();
+    spec.operation("get", "/collections/{name}/operations", "Operation journal").query::<OperationsQuery>();
+    spec.operation("get", "/collections/{name}/stats/history", "Statistics over time").query::<StatsHistoryQuery>();
+    spec.operation("get", "/collections/{name}/watch", "Stream changes as server-sent events").query::<WatchQuery>().produces("text/event-stream");
+    spec.operation("get", "/collections/{name}/export", "Export points as NDJSON in Qdrant's point schema").produces("application/x-ndjson");
+    spec.operation("get", "/collections/{name}/hnsw/graph", "Export the HNSW graph as JSON or GraphML").query::<HnswGraphQuery>();
+    spec.operation("get", "/aliases", "List aliases");
+    spec.operation("post", "/collections/aliases", "Create, rename or delete aliases").body::<UpdateAliasesRequest>();
+    spec.operation("get", "/collections/{name}/aliases", "Aliases of a collection");
+    spec.operation("get", "/cluster", "Cluster status");
+    spec.operation("get", "/collections/{name}/cluster", "Collection cluster status");
+    spec.operation("get", "/telemetry", "Telemetry").query::<TelemetryQuery>();
+    spec.operation("post", "/collections/{name}/points", "Get points by id").body::<GetPointsRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/count", "Count points").body::<CountRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/payload", "Set payload keys").body::<SetPayloadRequest>().query::<UpdateParams>();
+    spec.operation("put", "/collections/{name}/points/payload", "Overwrite payloads").body::<SetPayloadRequest>().query::<UpdateParams>();
+    spec.operation("post", "/collections/{name}/points/payload/delete", "Delete payload keys").body::<DeletePayloadRequest>().query::<UpdateParams>();
+    spec.operation("post", "/collections/{name}/points/payload/clear", "Clear payloads").body::<ClearPayloadRequest>().query::<UpdateParams>();
+    spec.operation("put", "/collections/{name}/points/vectors", "Update vectors").body::<UpdateVectorsRequest>().query::<UpdateParams>();
+    spec.operation("post", "/collections/{name}/points/vectors/delete", "Delete named vectors").body::<DeleteVectorsRequest>().query::<UpdateParams>();
+    spec.operation("post", "/collections/{name}/points/batch", "Apply a batch of operations atomically").body::<BatchUpdateRequest>().query::<UpdateParams>();
+    spec.operation("post", "/collections/{name}/points/search/batch", "Run several searches").body::<BatchSearchRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/search/groups", "Search grouped by a payload field").body::<SearchGroupsRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/search/matrix/pairs", "Distance matrix as pairs").body::<SearchMatrixRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/search/matrix/offsets", "Distance matrix as offsets").body::<SearchMatrixRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/hybrid", "Dense and BM25 search in one request").body::<HybridSearchRequest>().query::<ReadParams>();
+    spec.operation("post", "/search", "Search several collections and merge the results").body::<MultiSearchRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/query/batch", "Run several queries").body::<BatchQueryRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/query/groups", "Query grouped by a payload field").body::<QueryGroupsRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/query/templates/{template}", "Run a query template").body::<TemplateQueryRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/discover", "Discovery search").body::<DiscoverRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/points/discover/batch", "Run several discovery searches").body::<DiscoverBatchRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/facet", "Count points per payload value").body::<FacetRequest>().query::<ReadParams>();
+    spec.operation("post", "/collections/{name}/aggregate", "Aggregate payload fields, optionally by group").body::<AggregateRequest>().query::<ReadParams>();
+    spec.operation("get", "/collections/{name}/payload/stats", "Payload statistics per key").query::<PayloadStatsParams>();
+    spec.operation("put", "/collections/{name}/index", "Create a payload index").body::<CreateIndexRequest>().query::<UpdateParams>();
+    spec.operation("delete", "/collections/{name}/index/{field_name}", "Delete a payload index").query::<UpdateParams>();
+    spec.operation("get", "/collections/{name}/synonyms", "Synonym groups");
+    spec.operation("put", "/collections/{name}/synonyms", "Replace the synonym groups").body::<SynonymsRequest>();
+    spec.operation("get", "/collections/{name}/templates", "Query templates by name");
+    spec.operation("get", "/collections/{name}/templates/{template}", "A query template");
+    spec.operation("put", "/collections/{name}/templates/{template}", "Add or replace a query template").body::<QueryTemplate>();
+    spec.operation("delete", "/collections/{name}/templates/{template}", "Delete a query template");
+    spec.operation("get", "/collections/{name}/ef_tuning", "Recall target of the ef search default and the last tuning");
+    spec.operation("put", "/collections/{name}/ef_tuning", "Tune the ef search default to a recall target").body::<EfTuning>();
+    spec.operation("delete", "/collections/{name}/ef_tuning", "Stop tuning the ef search default");
+    spec.operation("get", "/collections/{name}/stopwords", "Stopwords");
+    spec.operation("put", "/collections/{name}/stopwords", "Replace the stopwords").body::<StopwordsRequest>();
+    spec.operation("post", "/collections/{name}/points/recommend", "Recommend points from examples").body::<RecommendRequest>().query::<ReadParams>();
+    spec.operation("get", "/collections/{name}/snapshots", "List collection snapshots");
+    spec.operation("post", "/collections/{name}/snapshots", "Create a collection snapshot").query::<CreateSnapshotParams>();
+    spec.operation("post", "/collections/{name}/snapshots/upload", "Upload and recover a collection snapshot").multipart().query::<UploadSnapshotParams>();
+    spec.operation("put", "/collections/{name}/snapshots/recover", "Recover a collection from a snapshot").body::<RecoverSnapshotRequest>();
+    spec.operation("get", "/collections/{name}/snapshots/{snapshot_name}", "Download a collection snapshot").produces("application/octet-stream");
+    spec.operation("delete", "/collections/{name}/snapshots/{snapshot_name}", "Delete a collection snapshot");
+    spec.operation("get", "/collections/{name}/shards/{shard_id}/snapshots", "List shard snapshots");
+    spec.operation("post", "/collections/{name}/shards/{shard_id}/snapshots", "Create a shard snapshot").query::<CreateSnapshotParams>();
+    spec.operation("post", "/collections/{name}/shards/{shard_id}/snapshots/upload", "Upload and recover a shard snapshot").multipart().query::<UploadSnapshotParams>();
+    spec.operation("put", "/collections/{name}/shards/{shard_id}/snapshots/recover", "Recover a shard from a snapshot").body::<RecoverSnapshotRequest>();
+    spec.operation("get", "/collections/{name}/shards/{shard_id}/snapshots/{snapshot_name}", "Download a shard snapshot").produces("application/octet-stream");
+    spec.operation("delete", "/collections/{name}/shards/{shard_id}/snapshots/{snapshot_name}", "Delete a shard snapshot");
+    spec.operation("get", "/snapshots", "List full storage snapshots");
+    spec.operation("post", "/snapshots", "Snapshot all collections");
+    spec.operation("get", "/snapshots/{snapshot_name}", "Download a full storage snapshot").produces("application/octet-stream");
+    spec.operation("delete", "/snapshots/{snapshot_name}", "Delete a full storage snapshot");
+    spec.operation("patch", "/collections/{name}", "Update collection parameters").body::<UpdateCollectionRequest>();
+    spec.operation("get", "/locks", "Current write lock");
+    spec.operation("post", "/locks", "Lock or unlock writes").body::<Locks>();
+    spec.operation("put", "/locks", "Lock or unlock writes").body::<Locks>();
+    spec.operation("get", "/issues", "Reported issues");
+    spec.operation("delete", "/issues", "Clear reported issues");
+    spec.operation("get", "/admin/keys", "List API keys");
+    spec.operation("post", "/admin/keys", "Create an API key").body::<CreateApiKeyRequest>();
+    spec.operation("get", "/admin/keys/{key_id}", "Get an API key");
+    spec.operation("delete", "/admin/keys/{key_id}", "Revoke an API key");
+    spec.operation("get", "/admin/jobs", "List admin and background jobs");
+    spec.operation("put", "/admin/jobs/workers", "Resize the background worker pool").body::<UpdateWorkersRequest>();
+    spec.operation("get", "/admin/jobs/{job_id}", "Admin job status");
+    spec.operation("post", "/admin/jobs/{job_id}/cancel", "Cancel an admin or background job");
+    spec.operation("get", "/admin/log_level", "Current log filter");
+    spec.operation("post", "/admin/log_level", "Change the log filter").body::<LogLevelRequest>();
+    spec.operation("get", "/admin/audit", "Recent audit log entries").query::<AuditParams>();
+    spec.operation("post", "/collections/{name}/jobs/kmeans", "Start a k-means clustering job").body::<KMeansJobRequest>();
+    spec.operation("post", "/collections/{name}/jobs/dedup", "Start a near-duplicate detection job").body::<DedupJobRequest>();
+    spec.operation("get", "/openapi.json", "This OpenAPI document").produces("application/json").public();
+    spec.finish()
+}
+
+/// OpenAPI 3.0 description of the REST API
+async fn openapi_document() -> ActixResult<HttpResponse> {
+    Ok(HttpResponse::Ok().json(openapi_spec()))
+}
+
 async fn root_info() -> ActixResult<HttpResponse> {
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "title": "vectx - vector search engine",
@@ -520,8 +1221,15 @@ async fn livez_check() -> ActixResult<HttpResponse> {
         .body("healthz check passed"))
 }
 
-/// Kubernetes readiness probe  
-async fn readyz_check() -> ActixResult<HttpResponse> {
+/// Kubernetes readiness probe: not ready while the node is warming up,
+/// restoring a snapshot or building a graph it cannot search without
+async fn readyz_check(storage: web::Data<Arc<StorageManager>>) -> ActixResult<HttpResponse> {
+    let issues = storage.readiness_issues();
+    if !issues.is_empty() {
+        return Ok(HttpResponse::ServiceUnavailable()
+            .content_type("text/plain")
+            .body(issues.join("\n")));
+    }
     Ok(HttpResponse::Ok()
         .content_type("text/plain")
         .body("healthz check passed"))
@@ -534,15 +1242,11 @@ async fn metrics_endpoint(
     let collections = storage.list_collections();
     let collections_count = collections.len();
     
-    // Count total points across all collections
-    let mut total_points = 0u64;
-    for name in &collections {
-        if let Some(collection) = storage.get_collection(name) {
-            total_points += collection.count() as u64;
-        }
-    }
+    // Count points of loaded collections; counting the others would load them
+    let loaded = storage.loaded_collections();
+    let total_points: u64 = loaded.iter().map(|collection| collection.count() as u64).sum();
     
-    let metrics = format!(
+    let mut metrics = format!(
         "# HELP app_info information about vectx server\n\
          # TYPE app_info gauge\n\
          app_info{{name=\"vectx\",version=\"{}\"}} 1\n\
@@ -552,13 +1256,39 @@ async fn metrics_endpoint(
          # HELP collections_total number of collections\n\
          # TYPE collections_total gauge\n\
          collections_total {}\n\
-         # HELP points_total total number of points across all collections\n\
+         # HELP collections_loaded number of collections with their points in memory\n\
+         # TYPE collections_loaded gauge\n\
+         collections_loaded {}\n\
+         # HELP points_total total number of points across loaded collections\n\
          # TYPE points_total gauge\n\
-         points_total {}\n",
+         points_total {}\n\
+         # HELP hnsw_rebuilds_running number of loaded collections whose HNSW graph is being rebuilt\n\
+         # TYPE hnsw_rebuilds_running gauge\n\
+         hnsw_rebuilds_running {}\n\
+         # HELP rejected_writes_total point writes refused for too many pending points\n\
+         # TYPE rejected_writes_total counter\n\
+         rejected_writes_total {}\n",
         env!("CARGO_PKG_VERSION"),
         collections_count,
-        total_points
+        loaded.len(),
+        total_points,
+        loaded.iter().filter(|collection| collection.hnsw_rebuilding()).count(),
+        storage.rejected_writes()
     );
+
+    // Per collection, sorted for stable output
+    let mut pending: Vec<(String, usize, usize)> = loaded.iter()
+        .map(|collection| (collection.name().to_string(), collection.pending_points(), collection.rebuild_backlog()))
+        .collect();
+    pending.sort();
+    metrics.push_str("# HELP pending_points points stored but not yet linked into the HNSW graph\n# TYPE pending_points gauge\n");
+    for (name, points, _) in &pending {
+        metrics.push_str(&format!("pending_points{{collection=\"{}\"}} {}\n", name, points));
+    }
+    metrics.push_str("# HELP rebuild_backlog_points points written during a running HNSW rebuild\n# TYPE rebuild_backlog_points gauge\n");
+    for (name, _, backlog) in &pending {
+        metrics.push_str(&format!("rebuild_backlog_points{{collection=\"{}\"}} {}\n", name, backlog));
+    }
     
     Ok(HttpResponse::Ok()
         .content_type("text/plain")
@@ -581,9 +1311,19 @@ async fn list_collections(
     }), start_time))
 }
 
-async fn get_collection(
-    storage: web::Data<Arc<StorageManager>>,
+#[derive(Deserialize, JsonSchema)]
+struct CollectionInfoQuery {
+    /// Include `stats`, `memory_total_bytes`, `hnsw_graph` and the
+    /// `payload_schema` point counts, which walk every point and the whole
+    /// HNSW graph
+    #[serde(default)]
+    with_stats: bool,
+}
+
+async fn get_collection(
+    storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
+    query: web::Query<CollectionInfoQuery>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -592,13 +1332,38 @@ async fn get_collection(
         let distance_str = format!("{:?}", collection.distance());
         let vector_dim = collection.vector_dim();
         let points_count = collection.count();
+        let stats = query.with_stats.then(|| collection.stats());
+        let sparse_vectors: BTreeMap<String, vectx_core::SparseVectorConfig> =
+            collection.sparse_vectors().into_iter().collect();
+        let named_multivectors: BTreeMap<String, vectx_core::MultiVectorParams> =
+            collection.named_multivectors().into_iter().collect();
+        let hnsw_params = collection.hnsw_params();
+        let index_points = query.with_stats.then(|| collection.payload_index_points());
+        let keyword_params = collection.keyword_params();
+        let payload_schema: BTreeMap<String, serde_json::Value> = collection.get_payload_indexes()
+            .into_iter()
+            .map(|(field, index_type)| {
+                let mut info = serde_json::json!({ "data_type": index_type });
+                if let Some(index_points) = &index_points {
+                    info["points"] = serde_json::json!(index_points.get(&field).copied().unwrap_or(0));
+                }
+                if let Some(params) = keyword_params.get(&field) {
+                    let mut params = serde_json::to_value(params).unwrap_or_default();
+                    params["type"] = serde_json::json!("keyword");
+                    info["params"] = params;
+                }
+                (field, info)
+            })
+            .collect();
         
         // Format to match Qdrant's full response structure
-        Ok(qdrant_response(serde_json::json!({
-            "status": "green",
+        let mut info = serde_json::json!({
+            // Yellow while the index is being rebuilt or the collection
+            // restored, as for Qdrant's optimizers
+            "status": if collection.reindex_progress().is_some() || collection.awaiting_index() || storage.is_restoring(&name) { "yellow" } else { "green" },
             "optimizer_status": "ok",
-            "vectors_count": points_count,
-            "indexed_vectors_count": points_count,
+            "vectors_count": stats.as_ref().map_or(points_count, |stats| stats.vectors_count),
+            "indexed_vectors_count": collection.indexed_vectors_count(),
             "points_count": points_count,
             "segments_count": 1,
             "config": {
@@ -607,15 +1372,24 @@ async fn get_collection(
                         "size": vector_dim,
                         "distance": distance_str
                     },
+                    "sparse_vectors": sparse_vectors,
+                    "named_multivectors": named_multivectors,
+                    "dense_vector_name": collection.dense_vector_name(),
+                    "detect_language": collection.detect_language(),
+                    "infer_vector_dim": collection.infer_vector_dim(),
+                    "search_defaults": collection.search_defaults(),
+                    "ef_tuning": collection.ef_tuning(),
+                    "normalization": collection.normalization(),
+                    "duplicate_policy": collection.duplicate_policy(),
                     "shard_number": 1,
                     "replication_factor": 1,
                     "write_consistency_factor": 1,
                     "on_disk_payload": true
                 },
                 "hnsw_config": {
-                    "m": 16,
-                    "ef_construct": 100,
-                    "full_scan_threshold": 10000,
+                    "m": hnsw_params.m,
+                    "ef_construct": hnsw_params.ef_construct,
+                    "full_scan_threshold": hnsw_params.full_scan_threshold,
                     "max_indexing_threads": 0,
                     "on_disk": false
                 },
@@ -636,8 +1410,14 @@ async fn get_collection(
                 },
                 "quantization_config": null
             },
-            "payload_schema": {}
-        }), start_time))
+            "payload_schema": payload_schema
+        });
+        if let Some(stats) = stats {
+            info["memory_total_bytes"] = serde_json::json!(stats.memory.total_bytes());
+            info["stats"] = serde_json::json!(stats);
+            info["hnsw_graph"] = serde_json::json!(collection.hnsw_graph_stats());
+        }
+        Ok(qdrant_response(info, start_time))
     } else {
         Ok(qdrant_not_found("Collection not found", start_time))
     }
@@ -653,21 +1433,46 @@ async fn create_collection(
     
     // Handle sparse-only collections (Qdrant compatibility)
     // For sparse-only collections, we create with a default vector dimension
-    let (vector_dim, distance) = if let Some(ref vectors) = req.vectors {
-        let dist = match vectors.distance.as_deref() {
-            Some("Cosine") | Some("cosine") => Distance::Cosine,
-            Some("Euclidean") | Some("euclidean") => Distance::Euclidean,
-            Some("Dot") | Some("dot") => Distance::Dot,
-            _ => Distance::Cosine,
-        };
+    let dense_vector_name = req.vectors.as_ref().and_then(VectorsConfig::dense_name).filter(|name| !name.is_empty()).map(str::to_string);
+    let named_multivectors = match req.vectors.as_ref().map(VectorsConfig::named_multivectors).transpose() {
+        Ok(named) => named.unwrap_or_default(),
+        Err(e) => return Ok(qdrant_error(&e, start_time)),
+    };
+    let (vector_dim, distance) = if let Some(vectors) = req.vectors.as_ref().and_then(VectorsConfig::dense) {
+        let dist = vectors.distance.as_deref().and_then(parse_distance).unwrap_or(Distance::Cosine);
+        if dist == Distance::Hamming && vectors.size % 8 != 0 {
+            return Ok(qdrant_error("Hamming vector size must be a multiple of 8 bits", start_time));
+        }
+        if req.infer_vector_dim && (vectors.size > 0 || dist == Distance::Hamming) {
+            return Ok(qdrant_error("infer_vector_dim needs a vector size of 0 and a distance other than Hamming", start_time));
+        }
+        if vectors.size == 0 && !req.infer_vector_dim {
+            return Ok(qdrant_error("Vector size must be positive, or set infer_vector_dim to take it from the first point", start_time));
+        }
         (vectors.size, dist)
-    } else if req.sparse_vectors.is_some() {
-        // Sparse-only collection - use BM25 with default text dimension
+    } else if req.sparse_vectors.is_some() || !named_multivectors.is_empty() || req.infer_vector_dim {
+        // Sparse-only or multivector-only collection - no dense vectors
+        // unless they are inferred
         (0, Distance::Cosine)
     } else {
         return Ok(qdrant_error("'vectors' configuration is required. Clients must provide embedding vectors.", start_time));
     };
 
+    if let Err(e) = req.replication.validate() {
+        return Ok(qdrant_error(&e, start_time));
+    }
+    let sparse_vectors = match req.sparse_vectors.as_ref().map(|config| parse_sparse_vectors(config, None)).transpose() {
+        Ok(sparse_vectors) => sparse_vectors.unwrap_or_default(),
+        Err(e) => return Ok(qdrant_error(&e, start_time)),
+    };
+    if let Some(Err(e)) = req.search_defaults.as_ref().map(SearchDefaults::validate) {
+        return Ok(qdrant_error(&e, start_time));
+    }
+    let hnsw_params = HnswParams::default().apply(&req.hnsw_config.unwrap_or_default());
+    if let Err(e) = hnsw_params.validate() {
+        return Ok(qdrant_error(&e, start_time));
+    }
+
     let config = CollectionConfig {
         name: name.clone(),
         vector_dim,
@@ -678,7 +1483,30 @@ async fn create_collection(
     };
 
     match storage.create_collection(config) {
-        Ok(_) => Ok(qdrant_response(true, start_time)),
+        Ok(collection) => {
+            if !sparse_vectors.is_empty() || !named_multivectors.is_empty() || dense_vector_name.is_some() || req.detect_language || req.search_defaults.is_some() || req.normalization.is_some() || req.duplicate_policy.is_some() || req.hnsw_config.is_some() || req.infer_vector_dim {
+                for (vector_name, sparse_config) in sparse_vectors {
+                    collection.set_sparse_vector(&vector_name, sparse_config);
+                }
+                for (vector_name, params) in named_multivectors {
+                    collection.set_named_multivector(&vector_name, params);
+                }
+                collection.set_dense_vector_name(dense_vector_name);
+                collection.set_detect_language(req.detect_language);
+                collection.set_search_defaults(req.search_defaults.clone().unwrap_or_default());
+                if let Err(e) = collection.set_normalization(req.normalization.unwrap_or_default()) {
+                    return Ok(qdrant_error(&e.to_string(), start_time));
+                }
+                collection.set_duplicate_policy(req.duplicate_policy.unwrap_or_default());
+                // An empty collection has no graph to rebuild, so this can't be refused
+                let _ = collection.set_hnsw_params(hnsw_params);
+                collection.set_infer_vector_dim(req.infer_vector_dim);
+                if let Err(e) = storage.save_collection_config(&collection) {
+                    return Ok(qdrant_error(&e.to_string(), start_time));
+                }
+            }
+            Ok(qdrant_response(true, start_time))
+        }
         Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
     }
 }
@@ -700,7 +1528,8 @@ async fn delete_collection(
 async fn upsert_points(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<UpsertPointsRequest>,
+    req: Body<UpsertPointsRequest>,
+    params: web::Query<UpdateParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -711,134 +1540,189 @@ async fn upsert_points(
             return Ok(qdrant_not_found("Collection not found", start_time));
         }
     };
-    
-    let points: Result<Vec<Point>, &str> = req.points.iter().map(|point_req| {
-        let id = match &point_req.id {
-            serde_json::Value::String(s) => PointId::String(s.clone()),
-            serde_json::Value::Number(n) => {
-                if let Some(u) = n.as_u64() {
-                    PointId::Integer(u)
-                } else {
-                    return Err("Invalid point ID");
-                }
-            }
-            _ => return Err("Invalid point ID"),
-        };
 
-        // Vector is required - clients must provide embeddings
-        let mut point = match &point_req.vector {
-            Some(parsed_vector) => {
-                if let Some(ref multivec_data) = parsed_vector.multivector {
-                    // Create MultiVector and Point with multivector
-                    match MultiVector::new(multivec_data.clone()) {
-                        Ok(mv) => Point::new_multi(id, mv, point_req.payload.clone()),
-                        Err(_) => {
-                            let vector = Vector::new(parsed_vector.primary.clone());
-                            Point::new(id, vector, point_req.payload.clone())
-                        }
-                    }
-                } else {
-                    // Simple dense vector
-                    let vector = Vector::new(parsed_vector.primary.clone());
-                    Point::new(id, vector, point_req.payload.clone())
-                }
-            }
-            None => {
-                // No vector provided - use empty vector (for payload-only points)
-                let vector = Vector::new(vec![]);
-                Point::new(id, vector, point_req.payload.clone())
-            }
+    let mut req = req.into_inner();
+    let generated_ids = assign_missing_ids(&mut req.points);
+    
+    let statuses = if req.abort_on_error {
+        let points: Result<Vec<PointInput>, String> = req.points.iter()
+            .enumerate()
+            .map(|(index, point)| point_input(point).map_err(|e| format!("Point at index {}: {}", index, e)))
+            .collect();
+        let points = match points {
+            Ok(points) => points,
+            Err(e) => return Ok(qdrant_error(&e, start_time)),
         };
-        
-        // Add sparse vectors if present
-        if let Some(ref parsed_vector) = point_req.vector {
-            for sparse in &parsed_vector.sparse_vectors {
-                let sparse_vec = vectx_core::SparseVector::new(
-                    sparse.indices.clone(),
-                    sparse.values.clone()
-                );
-                point.add_sparse_vector(sparse.name.clone(), sparse_vec);
-            }
+        match core_requests::upsert_points(&collection, points, storage.max_payload_bytes()) {
+            Ok(statuses) => statuses,
+            Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
         }
-        
-        Ok(point)
-    }).collect();
-
-    match points {
-        Ok(points_vec) => {
-            if points_vec.len() > 1 {
-                const PREWARM_THRESHOLD: usize = 1000;
-                let should_prewarm = points_vec.len() >= PREWARM_THRESHOLD;
-                
-                let result = if should_prewarm {
-                    collection.batch_upsert_with_prewarm(points_vec, true)
-                } else {
-                    collection.batch_upsert(points_vec)
-                };
-                
-                if let Err(e) = result {
-                    return Ok(qdrant_error(&e.to_string(), start_time));
-                }
-            } else if let Some(point) = points_vec.first() {
-                if let Err(e) = collection.upsert(point.clone()) {
-                    return Ok(qdrant_error(&e.to_string(), start_time));
+    } else {
+        let parsed = req.points.iter().map(|p| point_from_request(p, &collection));
+        // Points that do not parse keep their place among the statuses
+        let mut statuses: Vec<Option<UpsertStatus>> = Vec::with_capacity(parsed.len());
+        let mut points = Vec::new();
+        for point in parsed {
+            match point {
+                Ok(point) => {
+                    statuses.push(None);
+                    points.push(point);
                 }
+                Err(e) => statuses.push(Some(UpsertStatus::Failed(e))),
             }
         }
-        Err(e) => {
-            return Ok(qdrant_error(e, start_time));
-        }
+        let mut written = match collection.batch_upsert_lenient(points, storage.max_payload_bytes()) {
+            Ok(written) => written.into_iter(),
+            Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
+        };
+        statuses.into_iter()
+            .map(|status| status.or_else(|| written.next()).expect("a status per written point"))
+            .collect()
+    };
+
+    let operation_id = collection.last_operation_id();
+    let mut result = if !req.report_statuses && req.abort_on_error {
+        serde_json::json!({
+            "operation_id": operation_id,
+            "status": params.status()
+        })
+    } else {
+        let failed = statuses.iter().filter(|s| matches!(s, UpsertStatus::Failed(_))).count();
+        let points: Vec<serde_json::Value> = req.points.iter()
+            .zip(&statuses)
+            .map(|(point, status)| upsert_status_json(&point.id, status))
+            .collect();
+        serde_json::json!({
+            "operation_id": operation_id,
+            "status": params.status(),
+            "failed": failed,
+            "points": points
+        })
+    };
+    // Clients that send every id, as Qdrant clients do, get Qdrant's response
+    if generated_ids {
+        let ids: Vec<serde_json::Value> = req.points.iter().map(|p| p.id.clone()).collect();
+        result["ids"] = serde_json::json!(ids);
     }
+    Ok(qdrant_response(result, start_time))
+}
 
-    let operation_id = collection.next_operation_id();
-    Ok(qdrant_response(serde_json::json!({
-        "operation_id": operation_id,
-        "status": "acknowledged"
-    }), start_time))
+/// Give every point without an id a random UUID; true if any was assigned
+fn assign_missing_ids(points: &mut [PointRequest]) -> bool {
+    let mut assigned = false;
+    for point in points.iter_mut().filter(|p| p.id.is_null()) {
+        point.id = serde_json::json!(uuid::Uuid::new_v4().to_string());
+        assigned = true;
+    }
+    assigned
 }
 
 async fn search_points(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<SearchRequest>,
+    req: Body<SearchRequest>,
+    http_req: HttpRequest,
+    params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
-    let name = path.into_inner();
-    
-    let collection = match storage.get_collection(&name) {
+    let collection = match storage.get_collection(&path.into_inner()) {
         Some(c) => c,
         None => {
             return Ok(qdrant_not_found("Collection not found", start_time));
         }
     };
+    run_search(collection, req.into_inner(), &http_req, &params, start_time).await
+}
 
-    let limit = req.limit.unwrap_or(10);
-    let with_payload = req.with_payload.unwrap_or(true);
+/// Run a search on `collection`, from `/points/search` or a batch of them
+async fn run_search(
+    collection: Arc<Collection>,
+    req: SearchRequest,
+    http_req: &HttpRequest,
+    params: &ReadParams,
+    start_time: Instant,
+) -> ActixResult<HttpResponse> {
+    let claim = claim_filter(http_req);
+
+    let defaults = collection.search_defaults();
+    let limit = defaults.limit(req.limit, 10);
+    let with_payload = defaults.with_payload(req.with_payload);
+    let payload_fields = defaults.payload_fields(req.with_payload);
     let with_vector = req.with_vector.unwrap_or(false);
-    let score_threshold = req.score_threshold;
+    let score_threshold = defaults.score_threshold(req.score_threshold);
     let offset = req.offset.unwrap_or(0);
+    let computed = match ComputedFields::parse(&req.computed) {
+        Ok(computed) => computed,
+        Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
+    };
+    let filter = match parse_request_filter(req.filter.as_ref(), parse_mode(http_req)) {
+        Ok(filter) => filter,
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+    };
+    let geo = filter.as_ref().and_then(FilterCondition::geo_origin);
+
+    let format_point = |point: &Point, score: f32| {
+        let mut result = serde_json::json!({
+            "id": point_id_to_json(&point.id),
+            "version": point.version,
+            "score": score,
+        });
+        if with_payload {
+            result["payload"] = result_payload(point, payload_fields);
+        }
+        add_computed_fields(&mut result, &computed, point, Some(score));
+        add_geo_distance(&mut result, geo.as_ref(), point);
+        if with_vector {
+            result["vector"] = vector_json(&collection, &point.vector);
+        }
+        result
+    };
+
+    if let Some(token) = &req.page_token {
+        let page_size = req.limit.map(|_| limit);
+        let owner = page_owner(http_req, req.filter.as_ref());
+        let (hits, next) = match page_cache(http_req).page(collection.name(), &owner, token, page_size) {
+            Ok(page) => page,
+            Err(e) => return Ok(qdrant_error(&e, start_time)),
+        };
+        let search_results: Vec<serde_json::Value> = cached_hits(&collection, hits, claim.as_deref())
+            .iter()
+            .map(|(point, score, _)| format_point(point, *score))
+            .collect();
+        return Ok(qdrant_paged_response(search_results, next, start_time));
+    }
 
     if let Some(text) = &req.text {
-        let results = collection.search_text(text, limit + offset);
-        let search_results: Vec<serde_json::Value> = results
+        let highlight = req.highlight.as_ref().and_then(|h| h.options());
+        let recency = match recency_boost(&defaults, req.recency.as_ref()) {
+            Ok(recency) => recency,
+            Err(e) => return Ok(qdrant_error(&e, start_time)),
+        };
+        let results = collection.search_text_in(text, req.language.as_deref(), recency_candidates(recency, limit + offset));
+        let mut found: Vec<(Point, f32)> = results
+            .into_iter()
+            .filter_map(|(doc_id, score)| {
+                collection.get(&doc_id)
+                    .filter(|point| claim.as_ref().map_or(true, |c| c.matches(point)))
+                    .map(|point| (point, score))
+            })
+            .collect();
+        if let Some(boost) = recency {
+            found = boost.apply(found);
+        }
+        let search_results: Vec<serde_json::Value> = found
             .into_iter()
             .skip(offset)
             .filter(|(_, score)| score_threshold.map(|t| *score >= t).unwrap_or(true))
-            .filter_map(|(doc_id, score)| {
-                collection.get(&doc_id).map(|point| {
-                    let mut result = serde_json::json!({
-                        "id": point_id_to_json(&point.id),
-                        "version": point.version,
-                        "score": score,
-                    });
-                    if with_payload {
-                        result["payload"] = point.payload.clone().unwrap_or(serde_json::Value::Null);
-                    }
-                    if with_vector {
-                        result["vector"] = serde_json::json!(point.vector.as_slice());
+            .take(limit)
+            .map(|(point, score)| {
+                let mut result = format_point(&point, score);
+                if let Some(options) = &highlight {
+                    if let Some(h) = collection.highlight(&point.id.to_string(), text, options) {
+                        result["highlight"] = serde_json::json!(h);
                     }
-                    result
-                })
+                }
+                result
             })
             .collect();
 
@@ -846,212 +1730,714 @@ async fn search_points(
     }
 
     if let Some(vector_data) = &req.vector {
-        let query_vector = Vector::new(vector_data.clone());
-        
-        let filter: Option<Box<dyn Filter>> = req.filter.as_ref().and_then(|f| {
-            parse_filter(f).map(|cond| Box::new(PayloadFilter::new(cond)) as Box<dyn Filter>)
-        });
+        let query_vector = match vector_data.to_vector(&collection) {
+            Ok(vector) => vector,
+            Err(e) => return Ok(qdrant_error(&e, start_time)),
+        };
+        let depth = if req.paginate { defaults.limit(Some(PAGE_DEPTH), 10).max(limit) } else { limit };
+        let request = CoreSearchRequest {
+            vector: query_vector,
+            limit: Some(depth),
+            offset,
+            score_threshold,
+            filter,
+            timeout: params.timeout,
+            recency: req.recency.clone(),
+        };
+        let scan = match core_requests::search(collection.clone(), request, claim).await {
+            Ok(scan) => scan,
+            Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
+        };
+        if scan.timed_out && !params.partial.unwrap_or(false) {
+            return Ok(qdrant_timeout(start_time));
+        }
 
-        let results = if let Some(f) = filter.as_deref() {
-            collection.search(&query_vector, limit + offset, Some(f))
+        let next_page_token = if req.paginate && !scan.timed_out {
+            let hits = scan.points.iter()
+                .map(|(point, score)| Hit { id: point.id.to_string(), score: *score, explanation: serde_json::Value::Null })
+                .collect();
+            page_cache(http_req).store(collection.name(), &page_owner(http_req, req.filter.as_ref()), hits, limit)
         } else {
-            collection.search(&query_vector, limit + offset, None)
+            None
         };
-
-        let search_results: Vec<serde_json::Value> = results
-            .into_iter()
-            .skip(offset)
-            .filter(|(_, score)| score_threshold.map(|t| *score >= t).unwrap_or(true))
-            .map(|(point, score)| {
-                let mut result = serde_json::json!({
-                    "id": point_id_to_json(&point.id),
-                    "version": point.version,
-                    "score": score,
-                });
-                if with_payload {
-                    result["payload"] = point.payload.clone().unwrap_or(serde_json::Value::Null);
-                }
-                if with_vector {
-                    result["vector"] = serde_json::json!(point.vector.as_slice());
-                }
-                result
-            })
+        let search_results: Vec<serde_json::Value> = scan.points
+            .iter()
+            .take(limit)
+            .map(|(point, score)| format_point(point, *score))
             .collect();
 
-        return Ok(qdrant_response(search_results, start_time));
+        if scan.timed_out {
+            return Ok(qdrant_partial_response(search_results, start_time));
+        }
+        return Ok(qdrant_paged_response(search_results, next_page_token, start_time));
     }
 
     Ok(qdrant_error("Either 'vector' or 'text' must be provided", start_time))
 }
 
-/// Convert PointId to JSON value
-fn point_id_to_json(id: &vectx_core::PointId) -> serde_json::Value {
-    match id {
-        vectx_core::PointId::String(s) => serde_json::Value::String(s.clone()),
-        vectx_core::PointId::Integer(i) => serde_json::Value::Number((*i).into()),
-        vectx_core::PointId::Uuid(u) => serde_json::Value::String(u.to_string()),
-    }
-}
-
-/// Prefetch query for hybrid search
-#[derive(Deserialize, Clone)]
-struct PrefetchQuery {
-    /// Query vector or sparse vector
-    query: serde_json::Value,
-    #[serde(default)]
-    using: Option<String>,
+/// Dense + BM25 search in one request, without prefetch/fusion queries
+#[derive(Deserialize, JsonSchema)]
+struct HybridSearchRequest {
+    text: String,
+    vector: Vec<f32>,
+    /// Weight of the dense scores; BM25 scores get `1 - alpha`
+    #[serde(default = "default_hybrid_alpha")]
+    alpha: f32,
     #[serde(default)]
     limit: Option<usize>,
     #[serde(default)]
     filter: Option<serde_json::Value>,
-}
-
-/// Query request for Qdrant's universal query API
-/// Supports both single vectors and multivectors (ColBERT-style MaxSim)
-#[derive(Deserialize)]
-struct QueryRequest {
-    /// Query vector - can be single [f32], multi [[f32]], or fusion object {"fusion": "rrf"}
-    query: serde_json::Value,
-    #[serde(default)]
-    limit: Option<usize>,
     #[serde(default)]
     with_payload: Option<bool>,
     #[serde(default)]
     with_vector: Option<bool>,
     #[serde(default)]
-    filter: Option<serde_json::Value>,
-    /// Prefetch queries for hybrid search
-    #[serde(default)]
-    prefetch: Option<Vec<PrefetchQuery>>,
-    /// Which named vector to use
+    score_threshold: Option<f32>,
+    /// Recency boost replacing the collection's, or `false` to turn it off
     #[serde(default)]
-    using: Option<String>,
+    recency: Option<RecencyOverride>,
 }
 
-/// Query points using Qdrant's universal query API
-/// Supports multivector queries with MaxSim scoring, prefetch, and fusion
-async fn query_points(
+fn default_hybrid_alpha() -> f32 {
+    0.5
+}
+
+async fn hybrid_search(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<QueryRequest>,
+    req: Body<HybridSearchRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
-    
+    let claim = claim_filter(&http_req);
+
     let collection = match storage.get_collection(&name) {
         Some(c) => c,
         None => {
             return Ok(qdrant_not_found("Collection not found", start_time));
         }
     };
+    if !collection.enable_bm25() {
+        return Ok(qdrant_error("Hybrid search requires a collection with BM25 enabled", start_time));
+    }
 
-    let limit = req.limit.unwrap_or(10);
-    let with_payload = req.with_payload.unwrap_or(true);
+    let defaults = collection.search_defaults();
+    let limit = defaults.limit(req.limit, 10);
+    let with_payload = defaults.with_payload(req.with_payload);
+    let payload_fields = defaults.payload_fields(req.with_payload);
     let with_vector = req.with_vector.unwrap_or(false);
-    
-    // Check if this is a fusion query with prefetch
-    let is_fusion = req.query.as_object()
-        .and_then(|o| o.get("fusion"))
-        .is_some();
-    
-    let results = if is_fusion && req.prefetch.is_some() {
-        // Handle hybrid search with prefetch and fusion
-        match execute_fusion_query(&collection, &req, limit) {
-            Ok(r) => r,
-            Err(e) => return Ok(qdrant_error(&e, start_time)),
-        }
-    } else {
-        // Parse filter if provided
-        let filter: Option<Box<dyn Filter>> = req.filter.as_ref().and_then(|f| {
-            parse_filter(f).map(|cond| Box::new(PayloadFilter::new(cond)) as Box<dyn Filter>)
-        });
-        
-        // Get the "using" parameter for named/sparse vector queries
-        let using = req.using.as_deref();
-        
-        // Determine query type: point ID, single vector, sparse, or multivector
-        match execute_simple_query(&collection, &req.query, limit, filter.as_deref(), using) {
-            Ok(r) => r,
-            Err(e) => return Ok(qdrant_error(&e, start_time)),
-        }
+    let score_threshold = defaults.score_threshold(req.score_threshold);
+    let condition = match parse_request_filter(req.filter.as_ref(), parse_mode(&http_req)) {
+        Ok(condition) => condition,
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
     };
-    
-    // Format results
+    let geo = condition.as_ref().and_then(FilterCondition::geo_origin);
+    let filter = core_requests::build_filter(condition, claim.as_deref(), Some(&collection));
+    let query_vector = Vector::new(req.vector.clone());
+    let recency = match recency_boost(&defaults, req.recency.as_ref()) {
+        Ok(recency) => recency,
+        Err(e) => return Ok(qdrant_error(&e, start_time)),
+    };
+
+    let mut results = match collection.search_hybrid(&req.text, &query_vector, req.alpha, recency_candidates(recency, limit), filter.as_deref()) {
+        Ok(r) => r,
+        Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
+    };
+    if let Some(boost) = recency {
+        results = boost.apply(results);
+    }
+
     let search_results: Vec<serde_json::Value> = results
         .into_iter()
+        .filter(|(_, score)| score_threshold.map(|t| *score >= t).unwrap_or(true))
+        .take(limit)
         .map(|(point, score)| {
             let mut result = serde_json::json!({
                 "id": point_id_to_json(&point.id),
                 "version": point.version,
                 "score": score,
             });
-            
             if with_payload {
-                result["payload"] = point.payload.clone().unwrap_or(serde_json::Value::Null);
+                result["payload"] = result_payload(&point, payload_fields);
             }
-            
+            add_geo_distance(&mut result, geo.as_ref(), &point);
             if with_vector {
-                result["vector"] = serde_json::json!(point.vector.as_slice());
-                if let Some(mv) = &point.multivector {
-                    result["multivector"] = serde_json::json!(mv.vectors());
-                }
+                result["vector"] = vector_json(&collection, &point.vector);
             }
-            
             result
         })
         .collect();
 
-    Ok(qdrant_response(serde_json::json!({
-        "points": search_results
-    }), start_time))
+    Ok(qdrant_response(search_results, start_time))
 }
 
-/// Execute a fusion query with prefetch (RRF - Reciprocal Rank Fusion)
-fn execute_fusion_query(
-    collection: &Arc<Collection>,
-    req: &QueryRequest,
-    limit: usize,
-) -> Result<Vec<(Point, f32)>, String> {
-    use std::collections::HashMap;
-    
-    let prefetch = req.prefetch.as_ref().ok_or("Fusion requires prefetch")?;
-    
-    // Execute each prefetch query and collect ranked results
-    let mut all_results: Vec<Vec<(Point, f32)>> = Vec::new();
-    
-    for pf in prefetch {
-        let pf_limit = pf.limit.unwrap_or(20);
-        let filter: Option<Box<dyn Filter>> = pf.filter.as_ref().and_then(|f| {
-            parse_filter(f).map(|cond| Box::new(PayloadFilter::new(cond)) as Box<dyn Filter>)
-        });
-        
-        // Parse the prefetch query, using the "using" parameter for named/sparse vectors
-        let using = pf.using.as_deref();
-        let pf_results = parse_and_search(collection, &pf.query, pf_limit, filter.as_deref(), using)?;
-        all_results.push(pf_results);
+/// Search several collections with one vector and merge the results
+#[derive(Deserialize, JsonSchema)]
+struct MultiSearchRequest {
+    collections: Vec<MultiSearchTarget>,
+    vector: Vec<f32>,
+    #[serde(default)]
+    limit: Option<usize>,
+    /// Applied in every collection
+    #[serde(default)]
+    filter: Option<serde_json::Value>,
+    #[serde(default)]
+    with_payload: Option<bool>,
+    #[serde(default)]
+    with_vector: Option<bool>,
+    /// Compared with the weighted scores
+    #[serde(default)]
+    score_threshold: Option<f32>,
+    #[serde(default)]
+    merge: MultiSearchMerge,
+}
+
+/// A collection name, or a name with a weight
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum MultiSearchTarget {
+    Name(String),
+    Weighted {
+        name: String,
+        #[serde(default = "default_target_weight")]
+        weight: f32,
+    },
+}
+
+fn default_target_weight() -> f32 {
+    1.0
+}
+
+impl MultiSearchTarget {
+    fn name(&self) -> &str {
+        match self {
+            Self::Name(name) | Self::Weighted { name, .. } => name,
+        }
     }
-    
-    // Apply RRF (Reciprocal Rank Fusion)
-    // RRF score = sum(1 / (k + rank_i)) for each result set
-    // Qdrant uses k=1 for consistent scoring
-    const K: f32 = 1.0;
-    
-    let mut rrf_scores: HashMap<String, (Point, f32)> = HashMap::new();
-    
-    for result_set in &all_results {
-        for (rank, (point, _original_score)) in result_set.iter().enumerate() {
-            let rrf_contribution = 1.0 / (K + rank as f32 + 1.0);
-            let point_id = point.id.to_string();
-            
-            rrf_scores
-                .entry(point_id)
-                .and_modify(|(_, score)| *score += rrf_contribution)
-                .or_insert_with(|| (point.clone(), rrf_contribution));
+
+    fn weight(&self) -> f32 {
+        match self {
+            Self::Name(_) => 1.0,
+            Self::Weighted { weight, .. } => *weight,
+        }
+    }
+}
+
+/// How the result lists of the collections are combined
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum MultiSearchMerge {
+    /// By weighted similarity score; all collections must share a distance
+    #[default]
+    Score,
+    /// By weighted reciprocal rank, for collections whose scores differ in scale
+    Rrf,
+}
+
+impl MultiSearchMerge {
+    fn weighted(self, score: f32, rank: usize, weight: f32) -> f32 {
+        match self {
+            // Euclidean scores are negated distances: dividing keeps a weight
+            // above 1 in favour of the collection
+            Self::Score if score < 0.0 => score / weight,
+            Self::Score => score * weight,
+            Self::Rrf => weight / (RRF_K + rank as f32 + 1.0),
+        }
+    }
+}
+
+/// Rank offset of reciprocal rank fusion
+const RRF_K: f32 = 1.0;
+
+async fn multi_search(
+    storage: web::Data<Arc<StorageManager>>,
+    req: web::Json<MultiSearchRequest>,
+    http_req: HttpRequest,
+    params: web::Query<ReadParams>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let req = req.into_inner();
+    if req.collections.is_empty() {
+        return Ok(qdrant_error("'collections' must name at least one collection", start_time));
+    }
+
+    let mut targets = Vec::with_capacity(req.collections.len());
+    for target in &req.collections {
+        let name = target.name();
+        if !target.weight().is_finite() || target.weight() <= 0.0 {
+            return Ok(qdrant_error(&format!("Weight of collection '{}' must be positive", name), start_time));
+        }
+        let claim = match auth::authorize_collection(&storage, &http_req, name, Permission::Read) {
+            Ok(claim) => claim,
+            Err(e) => return Ok(auth::access_error_response(&e)),
+        };
+        let Some(collection) = storage.get_collection(name) else {
+            return Ok(qdrant_not_found(&format!("Collection '{}' not found", name), start_time));
+        };
+        if collection.vector_dim() != req.vector.len() {
+            return Ok(qdrant_error(&format!(
+                "Vector dimension {} does not match collection '{}' ({})",
+                req.vector.len(), name, collection.vector_dim()
+            ), start_time));
+        }
+        targets.push((name.to_string(), target.weight(), collection, claim));
+    }
+    if req.merge == MultiSearchMerge::Score
+        && targets.iter().any(|(_, _, c, _)| c.distance() != targets[0].2.distance())
+    {
+        return Ok(qdrant_error("Collections use different distances; merge them with \"rrf\"", start_time));
+    }
+
+    let condition = match parse_request_filter(req.filter.as_ref(), parse_mode(&http_req)) {
+        Ok(condition) => condition,
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+    };
+    let geo = condition.as_ref().and_then(FilterCondition::geo_origin);
+
+    let limit = req.limit.unwrap_or(10);
+    let search = {
+        let query_vector = Vector::new(req.vector.clone());
+        let merge = req.merge;
+        move |cancel: &CancellationToken| {
+            let mut merged = Vec::new();
+            for (name, weight, collection, claim) in targets {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                let defaults = collection.search_defaults();
+                let filter = core_requests::build_filter(condition.clone(), claim.as_ref(), Some(&collection));
+                let results = collection.search_cancellable(&query_vector, defaults.limit(Some(limit), limit), filter.as_deref(), cancel);
+                merged.extend(results.into_iter().enumerate().map(|(rank, (point, score))| {
+                    (merge.weighted(score, rank, weight), score, name.clone(), point, defaults.clone())
+                }));
+            }
+            merged
+        }
+    };
+    let scan = match deadline::run_cancellable(params.timeout, search).await {
+        Ok(scan) => scan,
+        Err(e) => return Ok(qdrant_error(&e, start_time)),
+    };
+    if scan.timed_out && !params.partial.unwrap_or(false) {
+        return Ok(qdrant_timeout(start_time));
+    }
+
+    let mut merged = scan.results;
+    merged.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let with_vector = req.with_vector.unwrap_or(false);
+    let search_results: Vec<serde_json::Value> = merged
+        .into_iter()
+        .filter(|(weighted, ..)| req.score_threshold.map_or(true, |t| *weighted >= t))
+        .take(limit)
+        .map(|(weighted, score, name, point, defaults)| {
+            let mut result = serde_json::json!({
+                "collection": name,
+                "id": point_id_to_json(&point.id),
+                "version": point.version,
+                "score": weighted,
+                "collection_score": score,
+            });
+            if defaults.with_payload(req.with_payload) {
+                result["payload"] = result_payload(&point, defaults.payload_fields(req.with_payload));
+            }
+            add_geo_distance(&mut result, geo.as_ref(), &point);
+            if with_vector {
+                result["vector"] = serde_json::json!(point.vector.as_slice());
+            }
+            result
+        })
+        .collect();
+
+    if scan.timed_out {
+        return Ok(qdrant_partial_response(search_results, start_time));
+    }
+    Ok(qdrant_response(search_results, start_time))
+}
+
+/// Payload of a search result, cut down to `fields` when given
+fn result_payload(point: &Point, fields: Option<&[String]>) -> serde_json::Value {
+    match (&point.payload, fields) {
+        (Some(payload), Some(fields)) => vectx_core::search_defaults::project_payload(payload, fields),
+        (Some(payload), None) => payload.clone(),
+        (None, _) => serde_json::Value::Null,
+    }
+}
+
+/// Add the computed fields of `point` to the payload of `result`
+fn add_computed_fields(result: &mut serde_json::Value, computed: &ComputedFields, point: &Point, score: Option<f32>) {
+    if !computed.is_empty() {
+        computed.apply(point, score, &mut result["payload"]);
+    }
+}
+
+/// Add the distance in metres of `point` from the geo radius of the
+/// request's filter to `result`, as `geo_distance`
+fn add_geo_distance(result: &mut serde_json::Value, origin: Option<&GeoOrigin>, point: &Point) {
+    if let Some(distance) = origin.and_then(|origin| origin.distance(point)) {
+        result["geo_distance"] = serde_json::json!(distance);
+    }
+}
+
+/// Convert PointId to JSON value
+fn point_id_to_json(id: &vectx_core::PointId) -> serde_json::Value {
+    match id {
+        vectx_core::PointId::String(s) => serde_json::Value::String(s.clone()),
+        vectx_core::PointId::Integer(i) => serde_json::Value::Number((*i).into()),
+        vectx_core::PointId::Uuid(u) => serde_json::Value::String(u.to_string()),
+    }
+}
+
+/// Prefetch query for hybrid search
+#[derive(Deserialize, Clone, JsonSchema)]
+struct PrefetchQuery {
+    /// Query vector or sparse vector
+    query: serde_json::Value,
+    #[serde(default)]
+    using: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    filter: Option<serde_json::Value>,
+}
+
+/// Query request for Qdrant's universal query API
+/// Supports both single vectors and multivectors (ColBERT-style MaxSim)
+#[derive(Deserialize, JsonSchema)]
+struct QueryRequest {
+    /// Query vector - can be single [f32], multi [[f32]], or fusion object {"fusion": "rrf"}
+    #[serde(default)]
+    query: serde_json::Value,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    with_payload: Option<bool>,
+    #[serde(default)]
+    with_vector: Option<bool>,
+    #[serde(default)]
+    filter: Option<serde_json::Value>,
+    #[serde(default)]
+    score_threshold: Option<f32>,
+    /// Prefetch queries for hybrid search
+    #[serde(default)]
+    prefetch: Option<Vec<PrefetchQuery>>,
+    /// Which named vector to use
+    #[serde(default)]
+    using: Option<String>,
+    /// Attach an `explanation` of each score to the results
+    #[serde(default)]
+    explain: bool,
+    /// Payload fields computed from expressions over the payload, such as
+    /// `"price * 1.22"`, added to each result by name
+    #[serde(default)]
+    computed: std::collections::BTreeMap<String, String>,
+    /// Recency boost replacing the collection's, or `false` to turn it off
+    #[serde(default)]
+    recency: Option<RecencyOverride>,
+    /// Reorder the best candidates with a cross-encoder model
+    #[serde(default)]
+    rerank: Option<RerankRequest>,
+    /// Keep the results after this page and answer with a `next_page_token`
+    #[serde(default)]
+    paginate: bool,
+    /// Token of the page to return from an earlier paginated query; the
+    /// query itself is not run
+    #[serde(default)]
+    page_token: Option<String>,
+}
+
+/// Part of a result's score coming from one search
+#[derive(Serialize, Clone, Debug)]
+struct ScoreContribution {
+    /// Index of the prefetch in the request (fusion queries only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prefetch: Option<usize>,
+    /// Named or sparse vector searched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    using: Option<String>,
+    /// 1-based position of the point in that search's results
+    rank: usize,
+    /// Score the point got in that search
+    score: f32,
+    /// Amount added to the final score
+    contribution: f32,
+}
+
+/// Query points using Qdrant's universal query API
+/// Supports multivector queries with MaxSim scoring, prefetch, and fusion
+async fn query_points(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    req: Body<QueryRequest>,
+    http_req: HttpRequest,
+    params: web::Query<ReadParams>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let collection = match storage.get_collection(&path.into_inner()) {
+        Some(c) => c,
+        None => {
+            return Ok(qdrant_not_found("Collection not found", start_time));
+        }
+    };
+    run_query(collection, req.into_inner(), &http_req, &params, start_time).await
+}
+
+/// Run a universal query on `collection`, from `/points/query` or a template
+async fn run_query(
+    collection: Arc<Collection>,
+    req: QueryRequest,
+    http_req: &HttpRequest,
+    params: &ReadParams,
+    start_time: Instant,
+) -> ActixResult<HttpResponse> {
+    let claim = claim_filter(http_req);
+    let mode = parse_mode(http_req);
+
+    let defaults = collection.search_defaults();
+    let limit = defaults.limit(req.limit, 10);
+    let with_payload = defaults.with_payload(req.with_payload);
+    let payload_fields = defaults.payload_fields(req.with_payload);
+    let with_vector = req.with_vector.unwrap_or(false);
+    let score_threshold = defaults.score_threshold(req.score_threshold);
+    let explain = req.explain;
+    let computed = match ComputedFields::parse(&req.computed) {
+        Ok(computed) => computed,
+        Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
+    };
+    // Filters are parsed again as the search runs, so reject bad ones here
+    let geo = match parse_request_filter(req.filter.as_ref(), mode) {
+        Ok(filter) => filter.as_ref().and_then(FilterCondition::geo_origin),
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+    };
+
+    let format_result = |(point, score, explanation): (Point, f32, serde_json::Value)| {
+        let mut result = serde_json::json!({
+            "id": point_id_to_json(&point.id),
+            "version": point.version,
+            "score": score,
+        });
+        if explain {
+            result["explanation"] = explanation;
+        }
+        
+        if with_payload {
+            result["payload"] = result_payload(&point, payload_fields);
+        }
+        add_computed_fields(&mut result, &computed, &point, Some(score));
+        add_geo_distance(&mut result, geo.as_ref(), &point);
+        
+        if with_vector {
+            result["vector"] = vector_json(&collection, &point.vector);
+            if let Some(mv) = &point.multivector {
+                result["multivector"] = serde_json::json!(mv.vectors());
+            }
+            if !point.named_multivectors.is_empty() {
+                result["named_multivectors"] = named_multivectors_json(&point);
+            }
+            if !point.sparse_vectors.is_empty() {
+                result["sparse_vectors"] = serde_json::json!(point.sparse_vectors);
+            }
+        }
+        
+        result
+    };
+
+    if let Some(token) = &req.page_token {
+        let page_size = req.limit.map(|_| limit);
+        let owner = page_owner(http_req, req.filter.as_ref());
+        let (hits, next) = match page_cache(http_req).page(collection.name(), &owner, token, page_size) {
+            Ok(page) => page,
+            Err(e) => return Ok(qdrant_error(&e, start_time)),
+        };
+        let points: Vec<serde_json::Value> = cached_hits(&collection, hits, claim.as_deref())
+            .into_iter()
+            .map(format_result)
+            .collect();
+        return Ok(qdrant_paged_response(serde_json::json!({"points": points}), next, start_time));
+    }
+    // A paginated query keeps the results of the pages after the first
+    let paginate = req.paginate;
+    let owner = page_owner(http_req, req.filter.as_ref());
+    let depth = if paginate { defaults.limit(Some(PAGE_DEPTH), 10).max(limit) } else { limit };
+    
+    // Check if this is a fusion query with prefetch
+    let is_fusion = req.query.as_object()
+        .and_then(|o| o.get("fusion"))
+        .is_some();
+    
+    let recency = match recency_boost(&defaults, req.recency.as_ref()) {
+        Ok(recency) => recency.cloned(),
+        Err(e) => return Ok(qdrant_error(&e, start_time)),
+    };
+    let mut candidates = recency_candidates(recency.as_ref(), depth);
+    let rerank_top_k = req.rerank.as_ref().map_or(0, |rerank| rerank.top_k(defaults.max_limit));
+    if let Some(rerank) = &req.rerank {
+        if let Err(e) = rerank.validate() {
+            return Ok(qdrant_error(&e, start_time));
+        }
+        candidates = candidates.max(rerank_top_k);
+    }
+    for (idx, pf) in req.prefetch.iter().flatten().enumerate() {
+        if let Err(mut e) = parse_request_filter(pf.filter.as_ref(), mode) {
+            e.path = format!("prefetch[{}].{}", idx, e.path);
+            return Ok(qdrant_parse_error(&e, start_time));
+        }
+    }
+
+    let search = {
+        let collection = collection.clone();
+        move |cancel: &CancellationToken| -> Result<Vec<(Point, f32, serde_json::Value)>, String> {
+            let mut results: Vec<(Point, f32, serde_json::Value)> = if is_fusion && req.prefetch.is_some() {
+                // Handle hybrid search with prefetch and fusion
+                let fused = execute_fusion_query(&collection, &req, candidates, mode, claim.as_deref(), cancel)?;
+                fused.into_iter()
+                    .map(|(point, score, contributions)| {
+                        let explanation = serde_json::json!({"method": "rrf", "contributions": contributions});
+                        (point, score, explanation)
+                    })
+                    .collect()
+            } else {
+                // Parse filter if provided
+                let filter = build_filter(req.filter.as_ref(), mode, claim.as_deref(), Some(&collection))
+                    .map_err(|e| e.to_string())?;
+
+                // Get the "using" parameter for named/sparse vector queries
+                let using = req.using.as_deref();
+
+                // Determine query type: point ID, single vector, sparse, or multivector
+                let results = execute_simple_query(&collection, &req.query, candidates, filter.as_deref(), using, cancel)?;
+                results.into_iter()
+                    .enumerate()
+                    .map(|(rank, (point, score))| {
+                        let contribution = ScoreContribution {
+                            prefetch: None,
+                            using: req.using.clone(),
+                            rank: rank + 1,
+                            score,
+                            contribution: score,
+                        };
+                        let explanation = serde_json::json!({"method": "single", "contributions": [contribution]});
+                        (point, score, explanation)
+                    })
+                    .collect()
+            };
+            if let Some(boost) = &recency {
+                let now = recency::now_secs();
+                for (point, score, explanation) in results.iter_mut() {
+                    let added = boost.boost(point, now);
+                    *score += added;
+                    explanation["recency"] = serde_json::json!(added);
+                }
+                results.sort_by(|a, b| b.1.total_cmp(&a.1));
+            }
+            if let Some(rerank) = &req.rerank {
+                results.truncate(rerank_top_k);
+                let points: Vec<&Point> = results.iter().map(|(point, _, _)| point).collect();
+                let scores = rerank.score(&points)?;
+                for ((_, score, explanation), reranked) in results.iter_mut().zip(scores) {
+                    *score = reranked;
+                    explanation["rerank"] = serde_json::json!({"model": rerank.model, "score": reranked});
+                }
+                results.sort_by(|a, b| b.1.total_cmp(&a.1));
+            }
+            results.truncate(depth);
+            Ok(results)
+        }
+    };
+    let scan = match deadline::run_cancellable(params.timeout, search).await {
+        Ok(scan) => scan,
+        Err(e) => return Ok(qdrant_error(&e, start_time)),
+    };
+    if scan.timed_out && !params.partial.unwrap_or(false) {
+        return Ok(qdrant_timeout(start_time));
+    }
+    let results = match scan.results {
+        Ok(r) => r,
+        Err(e) => return Ok(qdrant_error(&e, start_time)),
+    };
+    
+    let mut results: Vec<(Point, f32, serde_json::Value)> = results
+        .into_iter()
+        .filter(|(_, score, _)| score_threshold.map_or(true, |t| *score >= t))
+        .collect();
+    let next_page_token = if paginate && !scan.timed_out {
+        let hits = results.iter()
+            .map(|(point, score, explanation)| Hit { id: point.id.to_string(), score: *score, explanation: explanation.clone() })
+            .collect();
+        page_cache(http_req).store(collection.name(), &owner, hits, limit)
+    } else {
+        None
+    };
+    results.truncate(limit);
+    let search_results: Vec<serde_json::Value> = results.into_iter().map(format_result).collect();
+
+    let result = serde_json::json!({
+        "points": search_results
+    });
+    if scan.timed_out {
+        return Ok(qdrant_partial_response(result, start_time));
+    }
+    Ok(qdrant_paged_response(result, next_page_token, start_time))
+}
+
+/// Execute a fusion query with prefetch (RRF - Reciprocal Rank Fusion).
+/// Each result comes with the contribution of every prefetch that found it.
+fn execute_fusion_query(
+    collection: &Arc<Collection>,
+    req: &QueryRequest,
+    limit: usize,
+    mode: ParseMode,
+    claim: Option<&ClaimFilter>,
+    cancel: &CancellationToken,
+) -> Result<Vec<(Point, f32, Vec<ScoreContribution>)>, String> {
+    use std::collections::HashMap;
+    
+    let prefetch = req.prefetch.as_ref().ok_or("Fusion requires prefetch")?;
+    
+    // Execute each prefetch query and collect ranked results
+    let mut all_results: Vec<Vec<(Point, f32)>> = Vec::new();
+    
+    for pf in prefetch {
+        let pf_limit = pf.limit.unwrap_or(20);
+        let filter = build_filter(pf.filter.as_ref(), mode, claim, Some(collection))
+            .map_err(|e| e.to_string())?;
+        
+        // Parse the prefetch query, using the "using" parameter for named/sparse vectors
+        let using = pf.using.as_deref();
+        let pf_results = parse_and_search(collection, &pf.query, pf_limit, filter.as_deref(), using, cancel)?;
+        all_results.push(pf_results);
+    }
+    
+    // Apply RRF (Reciprocal Rank Fusion)
+    // RRF score = sum(1 / (k + rank_i)) for each result set
+    // Qdrant uses k=1 for consistent scoring
+    const K: f32 = 1.0;
+    
+    let mut rrf_scores: HashMap<String, (Point, f32, Vec<ScoreContribution>)> = HashMap::new();
+    
+    for (prefetch_idx, result_set) in all_results.iter().enumerate() {
+        for (rank, (point, original_score)) in result_set.iter().enumerate() {
+            let rrf_contribution = 1.0 / (K + rank as f32 + 1.0);
+            let point_id = point.id.to_string();
+            let contribution = ScoreContribution {
+                prefetch: Some(prefetch_idx),
+                using: prefetch[prefetch_idx].using.clone(),
+                rank: rank + 1,
+                score: *original_score,
+                contribution: rrf_contribution,
+            };
+            
+            let entry = rrf_scores
+                .entry(point_id)
+                .or_insert_with(|| (point.clone(), 0.0, Vec::new()));
+            entry.1 += rrf_contribution;
+            entry.2.push(contribution);
         }
     }
     
     // Sort by RRF score descending
-    let mut fused: Vec<(Point, f32)> = rrf_scores.into_values().collect();
+    let mut fused: Vec<(Point, f32, Vec<ScoreContribution>)> = rrf_scores.into_values().collect();
     fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
     fused.truncate(limit);
     
@@ -1065,34 +2451,52 @@ fn parse_and_search(
     limit: usize,
     filter: Option<&dyn Filter>,
     using: Option<&str>,
+    cancel: &CancellationToken,
 ) -> Result<Vec<(Point, f32)>, String> {
+    let target = collection.resolve_using(using).map_err(|e| e.to_string())?;
+    // Dense, binary and formula queries can only search the dense vector
+    let dense_only = || match &target {
+        VectorUsing::Dense => Ok(()),
+        VectorUsing::Sparse(name) => Err(format!("'{}' is a sparse vector; query it with {{\"indices\", \"values\"}}", name)),
+        VectorUsing::Multivector(name) => Err(format!("'{}' is a multivector; query it with a list of vectors", name)),
+    };
     match query {
+        // Vector arithmetic over stored points: {"formula": {"add": [...], "sub": [...]}}
+        serde_json::Value::Object(obj) if obj.contains_key("formula") => {
+            dense_only()?;
+            let formula = parse_formula(&obj["formula"])?;
+            let query_vector = formula.evaluate(collection).map_err(|e| e.to_string())?;
+            let exclude: std::collections::HashSet<&str> = formula.point_ids().collect();
+            let mut results = collection.search_cancellable(&query_vector, limit + exclude.len(), filter, cancel);
+            results.retain(|(p, _)| !exclude.contains(p.id.to_string().as_str()));
+            results.truncate(limit);
+            Ok(results)
+        }
+        // Binary vector as base64: {"binary": "..."}; a bare string is a point id
+        serde_json::Value::Object(obj) if obj.contains_key("binary") => {
+            dense_only()?;
+            let text = obj["binary"].as_str().ok_or("binary must be a base64 string")?;
+            let query_vector = dense_vector(collection, &[], Some(&decode_base64(text)?))?;
+            Ok(collection.search_cancellable(&query_vector, limit, filter, cancel))
+        }
         // Sparse vector format: {"indices": [...], "values": [...]}
         serde_json::Value::Object(obj) if obj.contains_key("indices") && obj.contains_key("values") => {
-            let indices = obj.get("indices")
-                .and_then(|i| i.as_array())
-                .ok_or("Invalid sparse vector: missing indices")?;
-            let values = obj.get("values")
-                .and_then(|v| v.as_array())
-                .ok_or("Invalid sparse vector: missing values")?;
-            
-            let indices_vec: Vec<u32> = indices.iter()
-                .filter_map(|i| i.as_u64().map(|n| n as u32))
-                .collect();
-            let values_vec: Vec<f32> = values.iter()
-                .filter_map(|v| v.as_f64().map(|f| f as f32))
-                .collect();
-            
-            if indices_vec.is_empty() || values_vec.is_empty() {
+            let query_sparse = parse_sparse_vector(obj)?;
+            if query_sparse.is_empty() {
                 return Ok(Vec::new());
             }
             
-            // Use the "using" parameter as the sparse vector name, default to "default"
-            let vector_name = using.unwrap_or("default");
-            let query_sparse = vectx_core::SparseVector::new(indices_vec, values_vec);
+            // The sparse vector named by "using", "default" when it is left out
+            let vector_name = match &target {
+                VectorUsing::Sparse(name) => name.as_str(),
+                VectorUsing::Dense => "default",
+                VectorUsing::Multivector(name) => {
+                    return Err(format!("'{}' is a multivector; query it with a list of vectors", name));
+                }
+            };
             
             // Perform sparse dot product search
-            Ok(collection.search_sparse(&query_sparse, vector_name, limit, filter))
+            Ok(collection.search_sparse_cancellable(&query_sparse, vector_name, limit, filter, cancel))
         }
         // Array: single vector or multivector
         serde_json::Value::Array(arr) if !arr.is_empty() => {
@@ -1113,7 +2517,24 @@ fn parse_and_search(
                     
                     let data = multivec_data.map_err(|e| format!("Invalid multivector: {}", e))?;
                     let query_mv = MultiVector::new(data).map_err(|e| format!("Invalid multivector: {}", e))?;
-                    Ok(collection.search_multivector(&query_mv, limit, filter))
+                    // A declared named multivector, or the only one when `using` is left out
+                    let named = match &target {
+                        VectorUsing::Multivector(name) => Some(name.clone()),
+                        VectorUsing::Sparse(name) => {
+                            return Err(format!("'{}' is a sparse vector; query it with {{\"indices\", \"values\"}}", name));
+                        }
+                        VectorUsing::Dense if using.is_none() => {
+                            let declared = collection.named_multivectors();
+                            if declared.len() == 1 { declared.into_keys().next() } else { None }
+                        }
+                        VectorUsing::Dense => None,
+                    };
+                    match named {
+                        Some(name) => collection
+                            .search_named_multivector_cancellable(&name, &query_mv, limit, filter, cancel)
+                            .map_err(|e| e.to_string()),
+                        None => Ok(collection.search_multivector_cancellable(&query_mv, limit, filter, cancel)),
+                    }
                 }
                 // Single vector: [0.1, 0.2, 0.3]
                 Some(serde_json::Value::Number(_)) => {
@@ -1122,8 +2543,9 @@ fn parse_and_search(
                         .collect();
                     
                     let data = vector_data.map_err(|e| format!("Invalid vector: {}", e))?;
-                    let query_vector = Vector::new(data);
-                    Ok(collection.search(&query_vector, limit, filter))
+                    dense_only()?;
+                    let query_vector = dense_vector(collection, &data, None)?;
+                    Ok(collection.search_cancellable(&query_vector, limit, filter, cancel))
                 }
                 _ => Err("Invalid query format".to_string())
             }
@@ -1132,191 +2554,108 @@ fn parse_and_search(
     }
 }
 
-/// Execute a simple (non-fusion) query
-fn execute_simple_query(
+/// Parse `{"add": [ids], "sub": [ids], "average": bool}`. Terms are summed
+/// unless `average` is set, in which case each side is averaged.
+fn parse_formula(value: &serde_json::Value) -> Result<VectorFormula, String> {
+    let ids = |key: &str| -> Result<Vec<String>, String> {
+        match value.get(key) {
+            None => Ok(Vec::new()),
+            Some(serde_json::Value::Array(arr)) => arr.iter()
+                .map(|id| match id {
+                    serde_json::Value::String(s) => Ok(s.clone()),
+                    serde_json::Value::Number(n) => Ok(n.to_string()),
+                    other => Err(format!("Invalid point ID in formula: {}", other)),
+                })
+                .collect(),
+            Some(_) => Err(format!("formula '{}' must be an array of point IDs", key)),
+        }
+    };
+    let add = ids("add")?;
+    let sub = ids("sub")?;
+    if add.is_empty() && sub.is_empty() {
+        return Err("formula requires at least one point in 'add' or 'sub'".to_string());
+    }
+    let average = value.get("average").and_then(|v| v.as_bool()).unwrap_or(false);
+    Ok(if average { VectorFormula::average(&add, &sub) } else { VectorFormula::sum(&add, &sub) })
+}
+
+/// Search with the vector `using` selects of an existing point
+fn search_by_point_id(
     collection: &Arc<Collection>,
-    query: &serde_json::Value,
+    point_id: &str,
     limit: usize,
     filter: Option<&dyn Filter>,
     using: Option<&str>,
+    cancel: &CancellationToken,
 ) -> Result<Vec<(Point, f32)>, String> {
-    match query {
-        // Query by point ID (nearest to existing point)
-        serde_json::Value::Number(n) => {
-            let point_id_str = if let Some(id) = n.as_u64() {
-                id.to_string()
+    let source_point = collection.get(point_id)
+        .ok_or_else(|| format!("Point with ID '{}' not found", point_id))?;
+
+    let mut search_results = match collection.resolve_using(using).map_err(|e| e.to_string())? {
+        VectorUsing::Dense => collection.search_cancellable(&source_point.vector, limit + 1, filter, cancel),
+        VectorUsing::Sparse(name) => {
+            let query_sparse = source_point.sparse_vectors.get(&name)
+                .ok_or_else(|| format!("Point with ID '{}' has no sparse vector '{}'", point_id, name))?;
+            collection.search_sparse_cancellable(query_sparse, &name, limit + 1, filter, cancel)
+        }
+        VectorUsing::Multivector(name) => {
+            let query_mv = source_point.named_multivectors.get(&name)
+                .ok_or_else(|| format!("Point with ID '{}' has no multivector '{}'", point_id, name))?;
+            collection.search_named_multivector_cancellable(&name, query_mv, limit + 1, filter, cancel)
+                .map_err(|e| e.to_string())?
+        }
+    };
+    // Remove the source point from results
+    search_results.retain(|(p, _)| p.id.to_string() != point_id);
+    search_results.truncate(limit);
+    Ok(search_results)
+}
+
+/// Execute a simple (non-fusion) query
+fn execute_simple_query(
+    collection: &Arc<Collection>,
+    query: &serde_json::Value,
+    limit: usize,
+    filter: Option<&dyn Filter>,
+    using: Option<&str>,
+    cancel: &CancellationToken,
+) -> Result<Vec<(Point, f32)>, String> {
+    match query {
+        // Query by point ID (nearest to existing point)
+        serde_json::Value::Number(n) => {
+            let point_id_str = if let Some(id) = n.as_u64() {
+                id.to_string()
             } else if let Some(id) = n.as_i64() {
                 id.to_string()
             } else {
                 return Err("Invalid point ID format".to_string());
             };
             
-            // Get the point by ID and use its vector for search
-            if let Some(source_point) = collection.get(&point_id_str) {
-                let query_vector = source_point.vector.clone();
-                let mut search_results = collection.search(&query_vector, limit + 1, filter);
-                // Remove the source point from results
-                search_results.retain(|(p, _)| p.id.to_string() != point_id_str);
-                search_results.truncate(limit);
-                Ok(search_results)
-            } else {
-                Err(format!("Point with ID '{}' not found", point_id_str))
-            }
+            search_by_point_id(collection, &point_id_str, limit, filter, using, cancel)
         }
         // Query by string point ID
-        serde_json::Value::String(s) => {
-            if let Some(source_point) = collection.get(s) {
-                let query_vector = source_point.vector.clone();
-                let mut search_results = collection.search(&query_vector, limit + 1, filter);
-                // Remove the source point from results
-                search_results.retain(|(p, _)| p.id.to_string() != *s);
-                search_results.truncate(limit);
-                Ok(search_results)
-            } else {
-                Err(format!("Point with ID '{}' not found", s))
-            }
-        }
+        serde_json::Value::String(s) => search_by_point_id(collection, s, limit, filter, using, cancel),
         // Arrays and sparse vectors
-        _ => parse_and_search(collection, query, limit, filter, using)
-    }
-}
-
-/// Parse Qdrant-style filter format (must/should/must_not)
-fn parse_filter(filter_json: &serde_json::Value) -> Option<FilterCondition> {
-    if let Some(obj) = filter_json.as_object() {
-        let mut all_conditions: Vec<FilterCondition> = Vec::new();
-        
-        // Qdrant-style filter with must (AND conditions)
-        if let Some(must) = obj.get("must") {
-            if let Some(arr) = must.as_array() {
-                let must_conditions: Vec<FilterCondition> = arr.iter()
-                    .filter_map(parse_field_condition)
-                    .collect();
-                if !must_conditions.is_empty() {
-                    // Multiple must conditions are AND'd together
-                    if must_conditions.len() == 1 {
-                        all_conditions.push(must_conditions.into_iter().next().unwrap());
-                    } else {
-                        all_conditions.push(FilterCondition::And(must_conditions));
-                    }
-                }
-            }
-        }
-        
-        // Qdrant-style filter with should (OR conditions)
-        if let Some(should) = obj.get("should") {
-            if let Some(arr) = should.as_array() {
-                let should_conditions: Vec<FilterCondition> = arr.iter()
-                    .filter_map(parse_field_condition)
-                    .collect();
-                if !should_conditions.is_empty() {
-                    // Multiple should conditions are OR'd together
-                    if should_conditions.len() == 1 {
-                        all_conditions.push(should_conditions.into_iter().next().unwrap());
-                    } else {
-                        all_conditions.push(FilterCondition::Or(should_conditions));
-                    }
-                }
-            }
-        }
-        
-        // Qdrant-style filter with must_not (negated conditions)
-        if let Some(must_not) = obj.get("must_not") {
-            if let Some(arr) = must_not.as_array() {
-                for cond in arr {
-                    if let Some(fc) = parse_field_condition(cond) {
-                        all_conditions.push(FilterCondition::Not(Box::new(fc)));
-                    }
-                }
-            }
-        }
-        
-        // If we collected any conditions, combine them
-        if !all_conditions.is_empty() {
-            return if all_conditions.len() == 1 {
-                Some(all_conditions.into_iter().next().unwrap())
-            } else {
-                Some(FilterCondition::And(all_conditions))
-            };
-        }
-        
-        // Legacy simple format: { field, value, operator }
-        if let Some(field) = obj.get("field").and_then(|v| v.as_str()) {
-            if let Some(value) = obj.get("value") {
-                if let Some(op) = obj.get("operator").and_then(|v| v.as_str()) {
-                    match op {
-                        "eq" => return Some(FilterCondition::Equals { field: field.to_string(), value: value.clone() }),
-                        "ne" => return Some(FilterCondition::NotEquals { field: field.to_string(), value: value.clone() }),
-                        "gt" => return value.as_f64().map(|v| FilterCondition::GreaterThan { field: field.to_string(), value: v }),
-                        "lt" => return value.as_f64().map(|v| FilterCondition::LessThan { field: field.to_string(), value: v }),
-                        "gte" => return value.as_f64().map(|v| FilterCondition::GreaterEqual { field: field.to_string(), value: v }),
-                        "lte" => return value.as_f64().map(|v| FilterCondition::LessEqual { field: field.to_string(), value: v }),
-                        _ => {}
-                    }
-                }
-            }
-        }
-        
-        // Direct field condition (Qdrant format): { "key": "field", "match": { "value": x } }
-        if let Some(fc) = parse_field_condition(filter_json) {
-            return Some(fc);
-        }
+        _ => parse_and_search(collection, query, limit, filter, using, cancel)
     }
-    None
 }
 
-/// Parse a single Qdrant field condition: { "key": "field", "match": { "value": x } }
-fn parse_field_condition(cond: &serde_json::Value) -> Option<FilterCondition> {
-    let obj = cond.as_object()?;
-    let key = obj.get("key")?.as_str()?;
-    
-    // Match condition: { "match": { "value": x } }
-    if let Some(match_obj) = obj.get("match").and_then(|m| m.as_object()) {
-        if let Some(value) = match_obj.get("value") {
-            return Some(FilterCondition::Equals { 
-                field: key.to_string(), 
-                value: value.clone() 
-            });
-        }
-        // Match any: { "match": { "any": [x, y, z] } }
-        if let Some(any_arr) = match_obj.get("any").and_then(|a| a.as_array()) {
-            if let Some(first) = any_arr.first() {
-                return Some(FilterCondition::Equals { 
-                    field: key.to_string(), 
-                    value: first.clone() 
-                });
-            }
-        }
-        // Match text: { "match": { "text": "value" } }
-        if let Some(text) = match_obj.get("text") {
-            return Some(FilterCondition::Equals { 
-                field: key.to_string(), 
-                value: text.clone() 
-            });
-        }
-    }
-    
-    // Range condition: { "range": { "gt": x, "lt": y } }
-    if let Some(range_obj) = obj.get("range").and_then(|r| r.as_object()) {
-        if let Some(gt) = range_obj.get("gt").and_then(|v| v.as_f64()) {
-            return Some(FilterCondition::GreaterThan { field: key.to_string(), value: gt });
-        }
-        if let Some(gte) = range_obj.get("gte").and_then(|v| v.as_f64()) {
-            return Some(FilterCondition::GreaterEqual { field: key.to_string(), value: gte });
-        }
-        if let Some(lt) = range_obj.get("lt").and_then(|v| v.as_f64()) {
-            return Some(FilterCondition::LessThan { field: key.to_string(), value: lt });
-        }
-        if let Some(lte) = range_obj.get("lte").and_then(|v| v.as_f64()) {
-            return Some(FilterCondition::LessEqual { field: key.to_string(), value: lte });
-        }
+/// Check if a point matches a Qdrant-style filter
+/// A scroll-style JSON filter, restricted to what the caller's claim allows,
+/// as a predicate for `Collection::iter_filtered`
+fn json_filter<'a>(
+    filter: Option<&'a serde_json::Value>,
+    claim: Option<&'a ClaimFilter>,
+    collection: &Collection,
+) -> impl Fn(&Point) -> bool + 'a {
+    let dictionaries = collection.text_dictionaries();
+    move |point: &Point| {
+        filter.map_or(true, |f| matches_filter(point, f, &dictionaries))
+            && claim.map_or(true, |c| c.matches(point))
     }
-    
-    None
 }
 
-/// Check if a point matches a Qdrant-style filter
-fn matches_filter(point: &Point, filter: &serde_json::Value) -> bool {
+fn matches_filter(point: &Point, filter: &serde_json::Value, dictionaries: &TextDictionaries) -> bool {
     let obj = match filter.as_object() {
         Some(o) => o,
         None => return true, // No valid filter, match all
@@ -1325,7 +2664,7 @@ fn matches_filter(point: &Point, filter: &serde_json::Value) -> bool {
     // Handle "must" conditions (AND logic)
     if let Some(must) = obj.get("must").and_then(|m| m.as_array()) {
         for cond in must {
-            if !matches_condition(point, cond) {
+            if !matches_condition(point, cond, dictionaries) {
                 return false; // All must conditions must match
             }
         }
@@ -1334,7 +2673,7 @@ fn matches_filter(point: &Point, filter: &serde_json::Value) -> bool {
     // Handle "should" conditions (OR logic)
     if let Some(should) = obj.get("should").and_then(|s| s.as_array()) {
         if !should.is_empty() {
-            let any_match = should.iter().any(|cond| matches_condition(point, cond));
+            let any_match = should.iter().any(|cond| matches_condition(point, cond, dictionaries));
             if !any_match {
                 return false; // At least one should condition must match
             }
@@ -1344,7 +2683,7 @@ fn matches_filter(point: &Point, filter: &serde_json::Value) -> bool {
     // Handle "must_not" conditions (NOT logic)
     if let Some(must_not) = obj.get("must_not").and_then(|m| m.as_array()) {
         for cond in must_not {
-            if matches_condition(point, cond) {
+            if matches_condition(point, cond, dictionaries) {
                 return false; // No must_not condition should match
             }
         }
@@ -1354,7 +2693,7 @@ fn matches_filter(point: &Point, filter: &serde_json::Value) -> bool {
 }
 
 /// Check if a point matches a single condition
-fn matches_condition(point: &Point, cond: &serde_json::Value) -> bool {
+fn matches_condition(point: &Point, cond: &serde_json::Value, dictionaries: &TextDictionaries) -> bool {
     let obj = match cond.as_object() {
         Some(o) => o,
         None => return false,
@@ -1404,7 +2743,7 @@ fn matches_condition(point: &Point, cond: &serde_json::Value) -> bool {
         None => {
             // Handle nested filter (recursive)
             if obj.contains_key("must") || obj.contains_key("should") || obj.contains_key("must_not") {
-                return matches_filter(point, cond);
+                return matches_filter(point, cond, dictionaries);
             }
             return false;
         }
@@ -1418,7 +2757,7 @@ fn matches_condition(point: &Point, cond: &serde_json::Value) -> bool {
         // Match exact value
         if let Some(expected) = match_obj.get("value") {
             return match &payload_value {
-                Some(actual) => values_equal(actual, expected),
+                Some(actual) => value_matches(actual, expected),
                 None => false,
             };
         }
@@ -1426,20 +2765,41 @@ fn matches_condition(point: &Point, cond: &serde_json::Value) -> bool {
         // Match any of values
         if let Some(any_arr) = match_obj.get("any").and_then(|a| a.as_array()) {
             return match &payload_value {
-                Some(actual) => any_arr.iter().any(|expected| values_equal(actual, expected)),
+                Some(actual) => any_arr.iter().any(|expected| value_matches(actual, expected)),
+                None => false,
+            };
+        }
+
+        // Match all of values
+        if let Some(all_arr) = match_obj.get("all").and_then(|a| a.as_array()) {
+            return match &payload_value {
+                Some(actual) => !all_arr.is_empty() && all_arr.iter().all(|expected| value_matches(actual, expected)),
                 None => false,
             };
         }
+
+        // Match a value other than these
+        if let Some(except_arr) = match_obj.get("except").and_then(|e| e.as_array()) {
+            let excluded = |item: &serde_json::Value| except_arr.iter().any(|expected| values_equal(item, expected));
+            return match &payload_value {
+                Some(serde_json::Value::Array(items)) => items.iter().any(|item| !excluded(item)),
+                Some(serde_json::Value::Null) | None => false,
+                Some(actual) => !excluded(actual),
+            };
+        }
         
-        // Match text (words OR - any word in query matches)
+        // Match text (words OR - any word in query or a synonym matches)
         if let Some(text) = match_obj.get("text").and_then(|t| t.as_str()) {
             return match &payload_value {
-                Some(serde_json::Value::String(s)) => {
-                    let s_lower = s.to_lowercase();
-                    // Split query into words and check if any word matches
-                    text.split_whitespace()
-                        .any(|word| s_lower.contains(&word.to_lowercase()))
-                }
+                Some(serde_json::Value::String(s)) => dictionaries.matches_text(s, text),
+                _ => false,
+            };
+        }
+
+        // Match within a few edits of a string
+        if let Some((value, max_edits)) = match_obj.get("fuzzy").and_then(parse_fuzzy) {
+            return match &payload_value {
+                Some(serde_json::Value::String(s)) => vectx_core::trigram::within_edits(s, &value, max_edits),
                 _ => false,
             };
         }
@@ -1572,7 +2932,7 @@ fn matches_element_condition(element: &serde_json::Value, cond: &serde_json::Val
     if let Some(match_obj) = obj.get("match").and_then(|m| m.as_object()) {
         if let Some(expected) = match_obj.get("value") {
             return match element_value {
-                Some(actual) => values_equal(actual, expected),
+                Some(actual) => value_matches(actual, expected),
                 None => false,
             };
         }
@@ -1581,23 +2941,25 @@ fn matches_element_condition(element: &serde_json::Value, cond: &serde_json::Val
     false
 }
 
+/// Whether a payload value is `expected`, or an array holding it
+fn value_matches(actual: &serde_json::Value, expected: &serde_json::Value) -> bool {
+    match actual {
+        serde_json::Value::Array(items) => items.iter().any(|item| values_equal(item, expected)),
+        actual => values_equal(actual, expected),
+    }
+}
+
 /// Compare two JSON values for equality
 fn values_equal(a: &serde_json::Value, b: &serde_json::Value) -> bool {
     match (a, b) {
-        (serde_json::Value::String(s1), serde_json::Value::String(s2)) => s1 == s2,
         (serde_json::Value::Number(n1), serde_json::Value::Number(n2)) => {
             n1.as_f64() == n2.as_f64()
         }
-        (serde_json::Value::Bool(b1), serde_json::Value::Bool(b2)) => b1 == b2,
-        (serde_json::Value::Array(arr), val) | (val, serde_json::Value::Array(arr)) => {
-            // Check if val is in array
-            arr.iter().any(|item| values_equal(item, val))
-        }
         _ => a == b,
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct ScrollRequest {
     limit: Option<usize>,
     offset: Option<serde_json::Value>,
@@ -1605,15 +2967,22 @@ struct ScrollRequest {
     with_vector: Option<bool>,
     #[serde(default)]
     filter: Option<serde_json::Value>,
+    /// Payload fields computed from expressions over the payload, such as
+    /// `"price * 1.22"`, added to each result by name
+    #[serde(default)]
+    computed: std::collections::BTreeMap<String, String>,
 }
 
 async fn scroll_points(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<ScrollRequest>,
+    req: Body<ScrollRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let collection_name = path.into_inner();
+    let claim = claim_filter(&http_req);
     
     let collection = match storage.get_collection(&collection_name) {
         Some(c) => c,
@@ -1625,6 +2994,10 @@ async fn scroll_points(
     let limit = req.limit.unwrap_or(10);
     let with_payload = req.with_payload.unwrap_or(true);
     let with_vector = req.with_vector.unwrap_or(false);
+    let computed = match ComputedFields::parse(&req.computed) {
+        Ok(computed) => computed,
+        Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
+    };
     
     // Get offset as integer if provided
     let offset_id: Option<i64> = req.offset.as_ref().and_then(|v| {
@@ -1635,17 +3008,11 @@ async fn scroll_points(
         }
     });
     
-    // Get all points and sort by ID for consistent pagination
-    let all_points = collection.get_all_points();
-    
-    // Apply filter if provided
-    let filtered_points: Vec<_> = if let Some(filter_json) = &req.filter {
-        all_points.iter()
-            .filter(|p| matches_filter(p, filter_json))
-            .collect()
-    } else {
-        all_points.iter().collect()
-    };
+    // Matching points, sorted by ID for consistent pagination
+    let filter = json_filter(req.filter.as_ref(), claim.as_deref(), &collection);
+    let filtered_points: Vec<Point> = collection
+        .iter_filtered(Some(&filter), Projection::new(with_payload || !computed.is_empty(), with_vector))
+        .collect();
     
     let mut points_with_ids: Vec<_> = filtered_points.iter()
         .map(|p| {
@@ -1654,7 +3021,7 @@ async fn scroll_points(
                 vectx_core::PointId::String(s) => s.parse::<i64>().unwrap_or(0),
                 vectx_core::PointId::Uuid(_) => 0,
             };
-            (id_num, *p)
+            (id_num, p)
         })
         .collect();
     
@@ -1690,8 +3057,12 @@ async fn scroll_points(
         if with_payload {
             obj["payload"] = point.payload.clone().unwrap_or(serde_json::json!({}));
         }
+        add_computed_fields(&mut obj, &computed, point, None);
         if with_vector {
-            obj["vector"] = serde_json::json!(point.vector.as_slice());
+            obj["vector"] = vector_json(&collection, &point.vector);
+            if !point.sparse_vectors.is_empty() {
+                obj["sparse_vectors"] = serde_json::json!(point.sparse_vectors);
+            }
         }
         
         obj
@@ -1703,336 +3074,1438 @@ async fn scroll_points(
     }), start_time))
 }
 
-async fn get_point(
+async fn get_point(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<(String, String)>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let (collection_name, point_id) = path.into_inner();
+    let claim = claim_filter(&http_req);
+    
+    let collection = match storage.get_collection(&collection_name) {
+        Some(c) => c,
+        None => {
+            return Ok(qdrant_not_found("Collection not found", start_time));
+        }
+    };
+
+    match collection.get(&point_id).filter(|p| claim.as_ref().map_or(true, |c| c.matches(p))) {
+        Some(point) => {
+            // Build response with optional multivector
+            let mut result = serde_json::json!({
+                "id": point_id_to_json(&point.id),
+                "version": point.version,
+                "vector": vector_json(&collection, &point.vector),
+                "payload": point.payload.clone().unwrap_or(serde_json::Value::Null),
+            });
+            
+            // Add multivector if present
+            if let Some(mv) = &point.multivector {
+                result["multivector"] = serde_json::json!(mv.vectors());
+            }
+            if !point.named_multivectors.is_empty() {
+                result["named_multivectors"] = named_multivectors_json(&point);
+            }
+            
+            Ok(qdrant_response(result, start_time))
+        }
+        None => Ok(qdrant_not_found("Point not found", start_time)),
+    }
+}
+
+async fn delete_point(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<(String, String)>,
+    http_req: HttpRequest,
+    params: web::Query<UpdateParams>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let (collection_name, point_id) = path.into_inner();
+    
+    let collection = match storage.get_collection(&collection_name) {
+        Some(c) => c,
+        None => {
+            return Ok(qdrant_not_found("Collection not found", start_time));
+        }
+    };
+
+    if let Some(claim) = claim_filter(&http_req) {
+        if !collection.get(&point_id).is_some_and(|p| claim.matches(&p)) {
+            return Ok(qdrant_not_found("Point not found", start_time));
+        }
+    }
+
+    match collection.delete(&point_id) {
+        Ok(true) => {
+            let operation_id = collection.last_operation_id();
+            Ok(qdrant_response(serde_json::json!({
+                "operation_id": operation_id,
+                "status": params.status()
+            }), start_time))
+        }
+        Ok(false) => Ok(qdrant_not_found("Point not found", start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct DeletePointsRequest {
+    filter: Option<serde_json::Value>,
+    points: Option<Vec<serde_json::Value>>,
+    /// Add the number of points actually removed to the result, as
+    /// `deleted` (a vectX extension)
+    #[serde(default)]
+    report_count: bool,
+}
+
+async fn delete_points_by_filter(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    req: Body<DeletePointsRequest>,
+    http_req: HttpRequest,
+    params: web::Query<UpdateParams>,
+) -> ActixResult<HttpResponse> {
+    let collection_name = path.into_inner();
+    let claim = claim_filter(&http_req);
+    
+    let start_time = Instant::now();
+    let collection = match storage.get_collection(&collection_name) {
+        Some(c) => c,
+        None => {
+            return Ok(qdrant_not_found("Collection not found", start_time));
+        }
+    };
+    
+    let mut ids = Vec::new();
+
+    // Handle filter-based deletion. Conditions are parsed strictly whatever
+    // the server's mode, as one dropped would widen what is deleted.
+    if let Some(filter) = &req.filter {
+        let condition = match core_requests::check_write_selector(filter)
+            .and_then(|()| parse_request_filter(Some(filter), ParseMode::Strict))
+        {
+            Ok(Some(condition)) => condition,
+            // A filter without conditions would select every point
+            Ok(None) => return Ok(qdrant_error("A delete filter needs at least one condition", start_time)),
+            Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+        };
+        let filter = core_requests::build_filter(Some(condition), claim.as_deref(), Some(&collection));
+        ids.extend(collection.iter_filtered(filter.as_deref(), Projection::IDS).map(|p| p.id.to_string()));
+    }
+
+    // Handle point ID-based deletion
+    if let Some(point_ids) = &req.points {
+        for point_id in point_ids {
+            let id_str = match point_id {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Number(n) => n.to_string(),
+                _ => continue,
+            };
+            if let Some(claim) = &claim {
+                if !collection.get(&id_str).is_some_and(|p| claim.matches(&p)) {
+                    continue;
+                }
+            }
+            ids.push(id_str);
+        }
+    }
+
+    let deleted = match collection.delete_batch(&ids) {
+        Ok(deleted) => deleted,
+        Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
+    };
+    let operation_id = collection.last_operation_id();
+    let mut result = serde_json::json!({
+        "operation_id": operation_id,
+        "status": params.status()
+    });
+    if req.report_count {
+        result["deleted"] = serde_json::json!(deleted);
+    }
+    Ok(qdrant_response(result, start_time))
+}
+
+async fn collection_exists(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = path.into_inner();
+    let exists = storage.collection_exists(&name);
+    
+    Ok(qdrant_response(serde_json::json!({
+        "exists": exists
+    }), start_time))
+}
+
+/// Flush a collection to disk and drop its points from memory. It stays
+/// listed, and the next request that uses it loads it again.
+async fn unload_collection(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = storage.resolve_collection_name(&path.into_inner());
+    if !storage.collection_exists(&name) {
+        return Ok(qdrant_not_found("Collection not found", start_time));
+    }
+
+    match storage.unload_collection(&name) {
+        Ok(unloaded) => Ok(qdrant_response(serde_json::json!({ "unloaded": unloaded }), start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+/// Rebuild a collection's HNSW graph in the background
+async fn reindex_collection(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let Some(collection) = storage.get_collection(&path.into_inner()) else {
+        return Ok(qdrant_not_found("Collection not found", start_time));
+    };
+    if !collection.use_hnsw() {
+        return Ok(qdrant_error("Collection has no HNSW index", start_time));
+    }
+    let started = collection.reindex();
+    Ok(qdrant_response(serde_json::json!({
+        "started": started,
+        "progress": collection.reindex_progress()
+    }), start_time))
+}
+
+/// Re-tokenize every point's text into a new BM25 index as an admin job.
+/// Poll `/admin/jobs/{id}` for its progress.
+async fn rebuild_text_index(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let Some(collection) = storage.get_collection(&path.into_inner()) else {
+        return Ok(qdrant_not_found("Collection not found", start_time));
+    };
+    if !collection.enable_bm25() {
+        return Ok(qdrant_error("Collection has no BM25 index", start_time));
+    }
+    let collection_name = collection.name().to_string();
+    let job = storage.jobs().spawn("text_index_rebuild", Some(&collection_name), move |ctx| {
+        match collection.rebuild_text_index(ctx).map_err(|e| e.to_string())? {
+            Some(documents) => Ok(serde_json::json!({ "documents": documents })),
+            None => Err("cancelled".to_string()),
+        }
+    });
+    match job {
+        Ok(job) => Ok(qdrant_response(job, start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct VerifyConsistencyRequest {
+    vector: Vec<f32>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+/// Run one query by brute force, HNSW and the quantized replica and compare
+/// the results
+async fn verify_consistency(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    req: web::Json<VerifyConsistencyRequest>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let Some(collection) = storage.get_collection(&path.into_inner()) else {
+        return Ok(qdrant_not_found("Collection not found", start_time));
+    };
+    let limit = req.limit.unwrap_or(10);
+    match collection.verify_consistency(&Vector::new(req.vector.clone()), limit) {
+        Ok(report) => Ok(qdrant_response(report, start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+/// Load a collection's points into memory ahead of its first request
+async fn load_collection(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = path.into_inner();
+
+    match storage.load_collection(&name) {
+        Ok(collection) => Ok(qdrant_response(serde_json::json!({
+            "points_count": collection.count()
+        }), start_time)),
+        Err(vectx_core::Error::CollectionNotFound(_)) => Ok(qdrant_not_found("Collection not found", start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct OperationsQuery {
+    /// Return operations with op_id >= since
+    #[serde(default)]
+    since: Option<u64>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+/// Recent write operations from the collection's journal (for audit/CDC)
+async fn list_operations(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    query: web::Query<OperationsQuery>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = path.into_inner();
+
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+
+    let entries = collection.journal().since(query.since, query.limit.unwrap_or(1000));
+    let next_since = entries.last().map(|e| e.op_id + 1);
+
+    Ok(qdrant_response(serde_json::json!({
+        "operations": entries,
+        "next_since": next_since
+    }), start_time))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct StatsHistoryQuery {
+    /// Return samples taken after this time, ms since the Unix epoch
+    #[serde(default)]
+    since: Option<u64>,
+    /// Return at most this many of the most recent samples
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+/// Points, memory, query rate and p99 latency of a collection over time,
+/// oldest sample first
+async fn stats_history(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    query: web::Query<StatsHistoryQuery>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let Some(collection) = storage.get_collection(&path.into_inner()) else {
+        return Ok(qdrant_not_found("Collection not found", start_time));
+    };
+
+    let mut samples = collection.stats_history(query.since);
+    if let Some(limit) = query.limit {
+        samples.drain(..samples.len().saturating_sub(limit));
+    }
+    Ok(qdrant_response(serde_json::json!({
+        "interval_secs": storage.stats_interval().map(|interval| interval.as_secs()),
+        "samples": samples
+    }), start_time))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct WatchQuery {
+    /// Resume token from a previous event (replays retained operations from there)
+    #[serde(default)]
+    since: Option<u64>,
+    /// Include the current state of the affected points in each event
+    #[serde(default)]
+    with_points: Option<bool>,
+}
+
+/// Server-sent events stream of collection changes
+async fn watch_collection(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    query: web::Query<WatchQuery>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = path.into_inner();
+
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+
+    // EventSource reconnects send back the last event id, which is the resume token
+    let since = query.since.or_else(|| {
+        req.headers()
+            .get("Last-Event-ID")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+    });
+    let with_points = query.with_points.unwrap_or(false);
+
+    let events = crate::watch::watch_collection(&collection, since).map(move |entry| {
+        let token = crate::watch::resume_token(&entry);
+        let mut event = serde_json::json!(entry);
+        event["resume_token"] = serde_json::json!(token);
+
+        if with_points {
+            let points: Vec<serde_json::Value> = entry.point_ids.iter()
+                .filter_map(|id| collection.get(id))
+                .map(|p| serde_json::json!({
+                    "id": point_id_to_json(&p.id),
+                    "version": p.version,
+                    "payload": p.payload,
+                    "vector": vector_json(&collection, &p.vector)
+                }))
+                .collect();
+            event["points"] = serde_json::json!(points);
+        }
+
+        let op_type = event["op_type"].as_str().unwrap_or("change").to_string();
+        Ok::<_, actix_web::Error>(web::Bytes::from(format!(
+            "id: {}\nevent: {}\ndata: {}\n\n",
+            token, op_type, event
+        )))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        // Compression would hold events back until a block fills up
+        .insert_header(actix_web::http::header::ContentEncoding::Identity)
+        .streaming(events))
+}
+
+/// Every point as NDJSON in Qdrant's point schema (see [`crate::export`])
+async fn export_collection(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    http_req: HttpRequest,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = path.into_inner();
+
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+    let claim = claim_filter(&http_req);
+    let filter = core_requests::build_filter(None, claim.as_deref(), Some(&collection));
+    let lines = crate::export::export_ndjson(collection.clone(), filter)
+        .map(Ok::<_, actix_web::Error>);
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}.ndjson\"", collection.name())))
+        .streaming(lines))
+}
+
+/// Format of an exported HNSW graph
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum GraphFormat {
+    #[default]
+    Json,
+    Graphml,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct HnswGraphQuery {
+    #[serde(default)]
+    format: GraphFormat,
+}
+
+/// Nodes and links of the HNSW graph, with per-layer statistics in JSON
+async fn export_hnsw_graph(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    query: web::Query<HnswGraphQuery>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let Some(collection) = storage.get_collection(&path.into_inner()) else {
+        return Ok(qdrant_not_found("Collection not found", start_time));
+    };
+    let (Some(nodes), Some(stats)) = (collection.hnsw_graph(), collection.hnsw_graph_stats()) else {
+        return Ok(qdrant_error("Collection has no HNSW graph built", start_time));
+    };
+
+    match query.format {
+        GraphFormat::Json => Ok(qdrant_response(serde_json::json!({
+            "stats": stats,
+            "nodes": nodes
+        }), start_time)),
+        GraphFormat::Graphml => Ok(HttpResponse::Ok()
+            .content_type("application/graphml+xml")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"{}-hnsw.graphml\"", collection.name())))
+            .body(crate::export::graphml(&nodes))),
+    }
+}
+
+// Qdrant compatibility endpoints
+
+async fn list_aliases(
+    storage: web::Data<Arc<StorageManager>>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let aliases: Vec<serde_json::Value> = storage.list_aliases()
+        .into_iter()
+        .map(|(alias, collection)| serde_json::json!({
+            "alias_name": alias,
+            "collection_name": collection
+        }))
+        .collect();
+    Ok(qdrant_response(serde_json::json!({
+        "aliases": aliases
+    }), start_time))
+}
+
+async fn cluster_info() -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    // vectX runs as single node - return disabled status like Qdrant single-node
+    Ok(qdrant_response(serde_json::json!({
+        "status": "disabled"
+    }), start_time))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct TelemetryQuery {
+    /// Hash collection names and round counts down to a power of ten
+    #[serde(default)]
+    anonymize: bool,
+    /// 0 for totals only; 1 and up lists each collection
+    #[serde(default)]
+    details_level: Option<usize>,
+}
+
+/// Runtime information in Qdrant's telemetry shape (see [`crate::telemetry`])
+async fn telemetry_info(
+    storage: web::Data<Arc<StorageManager>>,
+    query: web::Query<TelemetryQuery>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let options = crate::telemetry::TelemetryOptions {
+        anonymize: query.anonymize,
+        details_level: query.details_level.unwrap_or(crate::telemetry::TelemetryOptions::default().details_level),
+    };
+    let reported = storage.get_ref().clone();
+    let mut report = match tokio::task::spawn_blocking(move || crate::telemetry::telemetry_report(&reported, options)).await {
+        Ok(report) => report,
+        Err(e) => return Ok(qdrant_error(&format!("Failed to gather telemetry: {}", e), start_time)),
+    };
+    report["background"] = background_jobs_json();
+    Ok(qdrant_response(report, start_time))
+}
+
+// Snapshot endpoints
+
+async fn list_snapshots(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let collection_name = path.into_inner();
+    
+    match storage.list_collection_snapshots(&collection_name) {
+        Ok(snapshots) => Ok(qdrant_response(snapshots, start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+async fn list_all_snapshots(
+    storage: web::Data<Arc<StorageManager>>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    match storage.list_full_snapshots() {
+        Ok(snapshots) => Ok(qdrant_response(snapshots, start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+/// Create full storage snapshot (a consistent copy of the LMDB environment)
+async fn create_full_snapshot(
+    storage: web::Data<Arc<StorageManager>>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    match storage.create_full_snapshot() {
+        Ok(snapshot) => Ok(qdrant_response(snapshot, start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+/// Get (download) full snapshot
+async fn get_full_snapshot(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let snapshot_name = path.into_inner();
+
+    let Some(snapshot_path) = storage.get_full_snapshot_path(&snapshot_name) else {
+        return Ok(qdrant_not_found(&format!("Snapshot '{}' not found", snapshot_name), start_time));
+    };
+    match std::fs::read(&snapshot_path) {
+        Ok(data) => {
+            Ok(HttpResponse::Ok()
+                .content_type("application/octet-stream")
+                .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", snapshot_name)))
+                .body(data))
+        }
+        Err(e) => Ok(qdrant_error(&format!("Failed to read snapshot file: {}", e), start_time)),
+    }
+}
+
+/// Delete full snapshot
+async fn delete_full_snapshot(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let snapshot_name = path.into_inner();
+
+    match storage.delete_full_snapshot(&snapshot_name) {
+        Ok(true) => Ok(qdrant_response(true, start_time)),
+        Ok(false) => Ok(qdrant_not_found(&format!("Snapshot '{}' not found", snapshot_name), start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+/// Update collection parameters
+#[derive(Deserialize, JsonSchema)]
+struct UpdateCollectionRequest {
+    #[serde(default)]
+    params: Option<serde_json::Value>,
+    /// HNSW parameters to change; a new `m` or `ef_construct` rebuilds the graph
+    #[serde(default)]
+    hnsw_config: Option<HnswParamsDiff>,
+    #[serde(default)]
+    vectors: Option<serde_json::Value>,
+    #[serde(default)]
+    sparse_vectors: Option<serde_json::Value>,
+    #[serde(default)]
+    detect_language: Option<bool>,
+    /// Turning BM25 on indexes the stored points' text in an admin job;
+    /// turning it off drops the index
+    #[serde(default)]
+    enable_bm25: Option<bool>,
+    /// Replaces the collection's search defaults
+    #[serde(default)]
+    search_defaults: Option<SearchDefaults>,
+    /// Switching to `on_write` normalizes the stored cosine vectors
+    #[serde(default)]
+    normalization: Option<NormalizationPolicy>,
+    /// Applies to later upserts; points already stored are left as they are
+    #[serde(default)]
+    duplicate_policy: Option<DuplicatePolicy>,
+}
+
+async fn update_collection(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    req: web::Json<UpdateCollectionRequest>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = path.into_inner();
+    
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+
+    // Every section is checked before any is applied, so a refused request
+    // changes nothing
+    if let Some(params) = &req.params {
+        let replication = match consistency::ReplicationParams::deserialize(params) {
+            Ok(replication) => replication,
+            Err(e) => return Ok(qdrant_error(&format!("Invalid params: {}", e), start_time)),
+        };
+        if let Err(e) = replication.validate() {
+            return Ok(qdrant_error(&e, start_time));
+        }
+    }
+
+    let sparse_vectors = match req.sparse_vectors.as_ref().map(|config| parse_sparse_vectors(config, Some(&collection))) {
+        Some(Ok(configs)) => configs,
+        Some(Err(e)) => return Ok(qdrant_error(&e, start_time)),
+        None => Vec::new(),
+    };
+
+    let distance = match req.vectors.as_ref().map(update_distance) {
+        Some(Ok(Some(distance))) if distance != collection.distance()
+            && (distance == Distance::Hamming || collection.distance() == Distance::Hamming) => {
+            return Ok(qdrant_error("Binary vectors are stored packed; a collection cannot switch to or from Hamming distance", start_time));
+        }
+        Some(Ok(distance)) => distance,
+        Some(Err(e)) => return Ok(qdrant_error(&e, start_time)),
+        None => None,
+    };
+
+    if let Some(defaults) = &req.search_defaults {
+        if let Err(e) = defaults.validate() {
+            return Ok(qdrant_error(&e, start_time));
+        }
+    }
+
+    let hnsw_params = req.hnsw_config.as_ref().map(|diff| collection.hnsw_params().apply(diff));
+    if let Some(Err(e)) = hnsw_params.map(|params| params.validate()) {
+        return Ok(qdrant_error(&e, start_time));
+    }
+
+    // Refused while the graph is rebuilt, so applied first
+    if distance.is_some() || hnsw_params.is_some() {
+        let changed = collection
+            .set_graph_config(
+                distance.unwrap_or_else(|| collection.distance()),
+                hnsw_params.unwrap_or_else(|| collection.hnsw_params()),
+            )
+            .and_then(|changed| if changed { storage.save_collection_config(&collection) } else { Ok(()) });
+        if let Err(e) = changed {
+            return Ok(qdrant_error(&e.to_string(), start_time));
+        }
+    }
+
+    if !sparse_vectors.is_empty() {
+        for (vector_name, sparse_config) in sparse_vectors {
+            collection.set_sparse_vector(&vector_name, sparse_config);
+        }
+        if let Err(e) = storage.save_collection_config(&collection) {
+            return Ok(qdrant_error(&e.to_string(), start_time));
+        }
+    }
+
+    if let Some(detect_language) = req.detect_language {
+        collection.set_detect_language(detect_language);
+        if let Err(e) = storage.save_collection_config(&collection) {
+            return Ok(qdrant_error(&e.to_string(), start_time));
+        }
+    }
+
+    match req.enable_bm25 {
+        Some(true) if !collection.enable_bm25() => {
+            let indexed = collection.clone();
+            let saved = storage.get_ref().clone();
+            let job = storage.jobs().spawn("text_index_build", Some(&name), move |ctx| {
+                match indexed.enable_text_index(ctx).map_err(|e| e.to_string())? {
+                    Some(documents) => {
+                        saved.save_collection_config(&indexed).map_err(|e| e.to_string())?;
+                        Ok(serde_json::json!({ "documents": documents }))
+                    }
+                    None => Err("cancelled".to_string()),
+                }
+            });
+            if let Err(e) = job {
+                return Ok(qdrant_error(&e.to_string(), start_time));
+            }
+        }
+        Some(false) if collection.enable_bm25() => {
+            let disabled = collection.disable_text_index()
+                .and_then(|()| storage.save_collection_config(&collection));
+            if let Err(e) = disabled {
+                return Ok(qdrant_error(&e.to_string(), start_time));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(defaults) = &req.search_defaults {
+        collection.set_search_defaults(defaults.clone());
+        if let Err(e) = storage.save_collection_config(&collection) {
+            return Ok(qdrant_error(&e.to_string(), start_time));
+        }
+    }
+
+    if let Some(policy) = req.normalization {
+        let changed = collection.set_normalization(policy)
+            .and_then(|changed| if changed { storage.save_collection_config(&collection) } else { Ok(()) });
+        if let Err(e) = changed {
+            return Ok(qdrant_error(&e.to_string(), start_time));
+        }
+    }
+
+    if let Some(policy) = req.duplicate_policy {
+        if policy != collection.duplicate_policy() {
+            collection.set_duplicate_policy(policy);
+            if let Err(e) = storage.save_collection_config(&collection) {
+                return Ok(qdrant_error(&e.to_string(), start_time));
+            }
+        }
+    }
+
+    // Optimizer settings are accepted as-is: there are no segments to optimize
+    Ok(qdrant_response(true, start_time))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SynonymsRequest {
+    /// Groups of interchangeable words
+    synonyms: Vec<Vec<String>>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct StopwordsRequest {
+    stopwords: Vec<String>,
+}
+
+async fn get_synonyms(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    match storage.get_collection(&path.into_inner()) {
+        Some(collection) => Ok(qdrant_response(serde_json::json!({
+            "synonyms": collection.text_dictionaries().synonyms
+        }), start_time)),
+        None => Ok(qdrant_not_found("Collection not found", start_time)),
+    }
+}
+
+async fn get_stopwords(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    match storage.get_collection(&path.into_inner()) {
+        Some(collection) => Ok(qdrant_response(serde_json::json!({
+            "stopwords": collection.text_dictionaries().stopwords
+        }), start_time)),
+        None => Ok(qdrant_not_found("Collection not found", start_time)),
+    }
+}
+
+/// Replace a collection's synonym groups, keeping its stopwords
+async fn set_synonyms(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    req: web::Json<SynonymsRequest>,
+) -> ActixResult<HttpResponse> {
+    let stopwords = |collection: &Collection| collection.text_dictionaries().stopwords.iter().cloned().collect::<Vec<_>>();
+    update_text_dictionaries(&storage, &path.into_inner(), |collection| {
+        TextDictionaries::new(req.into_inner().synonyms, stopwords(collection))
+    })
+}
+
+/// Replace a collection's stopwords, keeping its synonym groups
+async fn set_stopwords(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    req: web::Json<StopwordsRequest>,
+) -> ActixResult<HttpResponse> {
+    update_text_dictionaries(&storage, &path.into_inner(), |collection| {
+        TextDictionaries::new(collection.text_dictionaries().synonyms.clone(), req.into_inner().stopwords)
+    })
+}
+
+/// Swap in new dictionaries and persist them with the collection's config.
+/// Queries pick them up immediately; nothing is reindexed.
+fn update_text_dictionaries(
+    storage: &StorageManager,
+    name: &str,
+    build: impl FnOnce(&Collection) -> TextDictionaries,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let collection = match storage.get_collection(name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+    let dictionaries = build(&collection);
+    let result = serde_json::json!({
+        "synonyms": dictionaries.synonyms.len(),
+        "stopwords": dictionaries.stopwords.len(),
+    });
+    collection.set_text_dictionaries(dictionaries);
+    if let Err(e) = storage.save_collection_config(&collection) {
+        return Ok(qdrant_error(&e.to_string(), start_time));
+    }
+    Ok(qdrant_response(result, start_time))
+}
+
+async fn list_query_templates(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    match storage.get_collection(&path.into_inner()) {
+        Some(collection) => Ok(qdrant_response(serde_json::json!({
+            "templates": collection.query_templates()
+        }), start_time)),
+        None => Ok(qdrant_not_found("Collection not found", start_time)),
+    }
+}
+
+async fn get_query_template(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<(String, String)>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
-    let (collection_name, point_id) = path.into_inner();
-    
-    let collection = match storage.get_collection(&collection_name) {
+    let (name, template) = path.into_inner();
+    let collection = match storage.get_collection(&name) {
         Some(c) => c,
-        None => {
-            return Ok(qdrant_not_found("Collection not found", start_time));
-        }
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
     };
-
-    match collection.get(&point_id) {
-        Some(point) => {
-            // Build response with optional multivector
-            let mut result = serde_json::json!({
-                "id": point_id_to_json(&point.id),
-                "version": point.version,
-                "vector": point.vector.as_slice(),
-                "payload": point.payload.clone().unwrap_or(serde_json::Value::Null),
-            });
-            
-            // Add multivector if present
-            if let Some(mv) = &point.multivector {
-                result["multivector"] = serde_json::json!(mv.vectors());
-            }
-            
-            Ok(qdrant_response(result, start_time))
-        }
-        None => Ok(qdrant_not_found("Point not found", start_time)),
+    match collection.query_template(&template) {
+        Some(template) => Ok(qdrant_response(template, start_time)),
+        None => Ok(qdrant_not_found("Template not found", start_time)),
     }
 }
 
-async fn delete_point(
+/// Add or replace a template after checking that its body, with defaults
+/// filled in, is a query request, and persist it with the collection's
+/// config
+async fn set_query_template(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<(String, String)>,
+    req: web::Json<QueryTemplate>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
-    let (collection_name, point_id) = path.into_inner();
-    
-    let collection = match storage.get_collection(&collection_name) {
+    let (name, template_name) = path.into_inner();
+    let collection = match storage.get_collection(&name) {
         Some(c) => c,
-        None => {
-            return Ok(qdrant_not_found("Collection not found", start_time));
-        }
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
     };
-
-    match collection.delete(&point_id) {
-        Ok(true) => {
-            let operation_id = collection.next_operation_id();
-            Ok(qdrant_response(serde_json::json!({
-                "operation_id": operation_id,
-                "status": "acknowledged"
-            }), start_time))
-        }
-        Ok(false) => Ok(qdrant_not_found("Point not found", start_time)),
-        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    if !vectx_core::templates::is_valid_name(&template_name) {
+        return Ok(qdrant_error("Template names may only contain letters, digits, '_' and '-'", start_time));
     }
+    let template = req.into_inner();
+    if let Err(e) = template.validate() {
+        return Ok(qdrant_error(&e, start_time));
+    }
+    if let Err(e) = serde_json::from_value::<QueryRequest>(template.preview()) {
+        return Ok(qdrant_error(&format!("Invalid template query: {}", e), start_time));
+    }
+    let variables = template.variables();
+    collection.set_query_template(&template_name, template);
+    if let Err(e) = storage.save_collection_config(&collection) {
+        return Ok(qdrant_error(&e.to_string(), start_time));
+    }
+    Ok(qdrant_response(serde_json::json!({"name": template_name, "variables": variables}), start_time))
 }
 
-#[derive(Deserialize)]
-struct DeletePointsRequest {
-    filter: Option<DeleteFilter>,
-    points: Option<Vec<serde_json::Value>>,
-}
-
-#[derive(Deserialize)]
-struct DeleteFilter {
-    must: Option<Vec<FilterMust>>,
-}
-
-#[derive(Deserialize)]
-struct FilterMust {
-    key: String,
-    #[serde(rename = "match")]
-    match_value: MatchValue,
+async fn delete_query_template(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<(String, String)>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let (name, template) = path.into_inner();
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+    if !collection.remove_query_template(&template) {
+        return Ok(qdrant_not_found("Template not found", start_time));
+    }
+    if let Err(e) = storage.save_collection_config(&collection) {
+        return Ok(qdrant_error(&e.to_string(), start_time));
+    }
+    Ok(qdrant_response(true, start_time))
 }
 
-#[derive(Deserialize)]
-struct MatchValue {
-    value: serde_json::Value,
+async fn get_ef_tuning(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    match storage.get_collection(&path.into_inner()) {
+        Some(collection) => Ok(qdrant_response(serde_json::json!({
+            "tuning": collection.ef_tuning(),
+            "last_run": collection.ef_tuning_report(),
+        }), start_time)),
+        None => Ok(qdrant_not_found("Collection not found", start_time)),
+    }
 }
 
-async fn delete_points_by_filter(
+/// Set the recall target, tune `ef` to it right away and persist both
+async fn set_ef_tuning(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<DeletePointsRequest>,
+    req: web::Json<EfTuning>,
 ) -> ActixResult<HttpResponse> {
-    let collection_name = path.into_inner();
-    
     let start_time = Instant::now();
-    let collection = match storage.get_collection(&collection_name) {
+    let collection = match storage.get_collection(&path.into_inner()) {
         Some(c) => c,
-        None => {
-            return Ok(qdrant_not_found("Collection not found", start_time));
-        }
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
     };
-    
-    let mut _deleted_count = 0;
-    
-    // Handle filter-based deletion
-    if let Some(filter) = &req.filter {
-        if let Some(must_conditions) = &filter.must {
-            // Get the field and value to match
-            if let Some(condition) = must_conditions.first() {
-                let field_key = &condition.key;
-                let match_value = &condition.match_value.value;
-                
-                // Get all points and filter by payload
-                let all_points = collection.get_all_points();
-                let mut points_to_delete = Vec::new();
-                
-                for point in all_points {
-                    if let Some(payload) = &point.payload {
-                        if let Some(field_value) = payload.get(field_key) {
-                            if field_value == match_value {
-                                points_to_delete.push(point.id.clone());
-                            }
-                        }
-                    }
-                }
-                
-                // Delete matching points
-                for point_id in points_to_delete {
-                    let id_str = match &point_id {
-                        vectx_core::PointId::String(s) => s.clone(),
-                        vectx_core::PointId::Integer(i) => i.to_string(),
-                        vectx_core::PointId::Uuid(u) => u.to_string(),
-                    };
-                    if collection.delete(&id_str).is_ok() {
-                        _deleted_count += 1;
-                    }
-                }
-            }
-        }
+    let tuning = req.into_inner();
+    if let Err(e) = tuning.validate() {
+        return Ok(qdrant_error(&e, start_time));
     }
-    
-    // Handle point ID-based deletion
-    if let Some(point_ids) = &req.points {
-        for point_id in point_ids {
-            let id_str = match point_id {
-                serde_json::Value::String(s) => s.clone(),
-                serde_json::Value::Number(n) => n.to_string(),
-                _ => continue,
-            };
-            if collection.delete(&id_str).is_ok() {
-                _deleted_count += 1;
+    if !collection.use_hnsw() {
+        return Ok(qdrant_error("Collection has no HNSW index", start_time));
+    }
+    collection.set_ef_tuning(Some(tuning));
+    // An empty collection is tuned by the background tuner once it has points.
+    // Tuning runs many searches, so it runs on the blocking pool.
+    let report = if collection.is_empty() {
+        None
+    } else {
+        let tuned = collection.clone();
+        let outcome = tokio::task::spawn_blocking(move || tuned.tune_ef())
+            .await
+            .map_err(|e| format!("Tuning failed: {}", e))
+            .and_then(|tuned| tuned.map_err(|e| e.to_string()));
+        match outcome {
+            Ok(report) => Some(report),
+            Err(e) => {
+                collection.set_ef_tuning(None);
+                return Ok(qdrant_error(&e, start_time));
             }
         }
+    };
+    if let Err(e) = storage.save_collection_config(&collection) {
+        return Ok(qdrant_error(&e.to_string(), start_time));
     }
-    
-    let operation_id = collection.next_operation_id();
     Ok(qdrant_response(serde_json::json!({
-        "operation_id": operation_id,
-        "status": "acknowledged"
+        "tuning": collection.ef_tuning(),
+        "last_run": report,
     }), start_time))
 }
 
-async fn collection_exists(
+/// Stop tuning; the `ef` default keeps its last tuned value
+async fn delete_ef_tuning(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
-    let name = path.into_inner();
-    let exists = storage.collection_exists(&name);
-    
+    let collection = match storage.get_collection(&path.into_inner()) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+    collection.set_ef_tuning(None);
+    if let Err(e) = storage.save_collection_config(&collection) {
+        return Ok(qdrant_error(&e.to_string(), start_time));
+    }
+    Ok(qdrant_response(true, start_time))
+}
+
+/// Values for the placeholders of a query template
+#[derive(Deserialize, JsonSchema)]
+struct TemplateQueryRequest {
+    #[serde(default)]
+    variables: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Run a stored template with the caller's variables, as `/points/query`
+async fn query_template(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<(String, String)>,
+    req: Body<TemplateQueryRequest>,
+    http_req: HttpRequest,
+    params: web::Query<ReadParams>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let (name, template_name) = path.into_inner();
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+    let template = match collection.query_template(&template_name) {
+        Some(template) => template,
+        None => return Ok(qdrant_not_found("Template not found", start_time)),
+    };
+    let rendered = match template.render(&req.variables) {
+        Ok(rendered) => rendered,
+        Err(e) => return Ok(qdrant_error(&e, start_time)),
+    };
+    let query = match serde_json::from_value::<QueryRequest>(rendered) {
+        Ok(query) => query,
+        Err(e) => return Ok(qdrant_error(&format!("Invalid query from template '{}': {}", template_name, e), start_time)),
+    };
+    run_query(collection, query, &http_req, &params, start_time).await
+}
+
+async fn get_locks(
+    storage: web::Data<Arc<StorageManager>>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    Ok(qdrant_response(storage.locks(), start_time))
+}
+
+/// Set the write lock; the result is the previous locks, as in Qdrant
+async fn set_locks(
+    storage: web::Data<Arc<StorageManager>>,
+    req: web::Json<Locks>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    Ok(qdrant_response(storage.set_locks(req.into_inner()), start_time))
+}
+
+/// Get issues/performance suggestions
+async fn get_issues() -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
     Ok(qdrant_response(serde_json::json!({
-        "exists": exists
+        "issues": []
     }), start_time))
 }
 
-// Qdrant compatibility endpoints
+/// Clear all reported issues
+async fn clear_issues() -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    Ok(qdrant_response(true, start_time))
+}
 
-async fn list_aliases(
+// ==================== API Key Administration ====================
+
+#[derive(Deserialize, JsonSchema)]
+struct CreateApiKeyRequest {
+    name: String,
+    /// Collections the key is scoped to (empty or omitted = all collections)
+    #[serde(default)]
+    collections: Vec<String>,
+    permission: Permission,
+}
+
+fn api_key_json(key: &ApiKey) -> serde_json::Value {
+    serde_json::json!({
+        "id": key.id,
+        "name": key.name,
+        "collections": key.collections,
+        "permission": key.permission,
+        "created_at": key.created_at
+    })
+}
+
+async fn list_api_keys(
     storage: web::Data<Arc<StorageManager>>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
-    let aliases: Vec<serde_json::Value> = storage.list_aliases()
-        .into_iter()
-        .map(|(alias, collection)| serde_json::json!({
-            "alias_name": alias,
-            "collection_name": collection
-        }))
+    let keys: Vec<serde_json::Value> = storage.access_control()
+        .list_keys()
+        .iter()
+        .map(api_key_json)
         .collect();
-    Ok(qdrant_response(serde_json::json!({
-        "aliases": aliases
-    }), start_time))
+    Ok(qdrant_response(serde_json::json!({ "keys": keys }), start_time))
 }
 
-async fn cluster_info() -> ActixResult<HttpResponse> {
+async fn create_api_key(
+    storage: web::Data<Arc<StorageManager>>,
+    req: web::Json<CreateApiKeyRequest>,
+) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
-    // vectX runs as single node - return disabled status like Qdrant single-node
-    Ok(qdrant_response(serde_json::json!({
-        "status": "disabled"
-    }), start_time))
+    let req = req.into_inner();
+
+    if req.name.trim().is_empty() {
+        return Ok(qdrant_error("Key name must not be empty", start_time));
+    }
+
+    match storage.access_control().create_key(&req.name, req.collections, req.permission) {
+        Ok((key, secret)) => {
+            let mut result = api_key_json(&key);
+            // The secret is only ever returned here
+            result["key"] = serde_json::json!(secret);
+            Ok(qdrant_response(result, start_time))
+        }
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+async fn get_api_key(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let key_id = path.into_inner();
+
+    match storage.access_control().get_key(&key_id) {
+        Some(key) => Ok(qdrant_response(api_key_json(&key), start_time)),
+        None => Ok(qdrant_not_found("API key not found", start_time)),
+    }
+}
+
+async fn delete_api_key(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let key_id = path.into_inner();
+
+    match storage.access_control().delete_key(&key_id) {
+        Ok(true) => Ok(qdrant_response(true, start_time)),
+        Ok(false) => Ok(qdrant_not_found("API key not found", start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
 }
 
-async fn telemetry_info() -> ActixResult<HttpResponse> {
+/// Workers and queued or running jobs of the background job system
+fn background_jobs_json() -> serde_json::Value {
+    let system = get_background_system();
+    serde_json::json!({
+        "max_threads": system.budget().max_threads,
+        "running": system.running_jobs(),
+        "jobs": system.jobs()
+    })
+}
+
+async fn list_jobs(
+    storage: web::Data<Arc<StorageManager>>,
+) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     Ok(qdrant_response(serde_json::json!({
-        "id": "vectx-single-node",
-        "app": {
-            "name": "vectx",
-            "version": "0.2.1"
-        }
+        "jobs": storage.jobs().list(),
+        "background": background_jobs_json()
     }), start_time))
 }
 
-// Snapshot endpoints
+/// Cancel an admin job by its id, or a background job by its numeric id
+async fn cancel_job(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let job_id = path.into_inner();
+    if let Some(job) = storage.jobs().cancel(&job_id) {
+        return Ok(qdrant_response(job, start_time));
+    }
+    match job_id.parse::<u64>() {
+        Ok(id) if get_background_system().cancel(id) => Ok(qdrant_response(true, start_time)),
+        _ => Ok(qdrant_not_found("Job not found", start_time)),
+    }
+}
 
-async fn list_snapshots(
+#[derive(Deserialize, JsonSchema)]
+struct UpdateWorkersRequest {
+    /// Background jobs run at the same time (at least 1)
+    max_threads: usize,
+}
+
+/// Resize the worker pool of the background job system
+async fn update_workers(
+    req: web::Json<UpdateWorkersRequest>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    if req.max_threads == 0 {
+        return Ok(qdrant_error("max_threads must be at least 1", start_time));
+    }
+    get_background_system().set_max_threads(req.max_threads);
+    Ok(qdrant_response(background_jobs_json(), start_time))
+}
+
+async fn get_job(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
-    let collection_name = path.into_inner();
-    
-    match storage.list_collection_snapshots(&collection_name) {
-        Ok(snapshots) => Ok(qdrant_response(snapshots, start_time)),
-        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    match storage.jobs().get(&path.into_inner()) {
+        Some(job) => Ok(qdrant_response(job, start_time)),
+        None => Ok(qdrant_not_found("Job not found", start_time)),
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct LogLevelRequest {
+    /// A level (`debug`) or filter directives (`info,vectx_core=debug`)
+    level: String,
+}
+
+async fn get_log_level() -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    Ok(qdrant_response(serde_json::json!({"level": logging::level()}), start_time))
+}
+
+/// Change what the server logs until the next change or restart
+async fn set_log_level(req: web::Json<LogLevelRequest>) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    match logging::set_level(&req.level) {
+        Ok(()) => {
+            tracing::info!("Log level set to {}", req.level);
+            Ok(qdrant_response(serde_json::json!({"level": req.level}), start_time))
+        }
+        Err(e) => Ok(qdrant_error(&e, start_time)),
     }
 }
 
-async fn list_all_snapshots(
-    storage: web::Data<Arc<StorageManager>>,
-) -> ActixResult<HttpResponse> {
-    let start_time = Instant::now();
-    match storage.list_all_snapshots() {
-        Ok(snapshots) => Ok(qdrant_response(snapshots, start_time)),
-        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
-    }
+#[derive(Deserialize, JsonSchema)]
+struct AuditParams {
+    #[serde(default)]
+    collection: Option<String>,
+    /// Id of an API key, `master` or `jwt`
+    #[serde(default)]
+    key_id: Option<String>,
+    /// Part of the operation, e.g. `points` or `DELETE`
+    #[serde(default)]
+    operation: Option<String>,
+    /// Return entries at or after this time, ms since the Unix epoch
+    #[serde(default)]
+    since: Option<i64>,
+    #[serde(default)]
+    success: Option<bool>,
+    #[serde(default)]
+    limit: Option<usize>,
 }
 
-/// Create full storage snapshot
-async fn create_full_snapshot(
+/// Most recent audit log entries first
+async fn list_audit_entries(
     storage: web::Data<Arc<StorageManager>>,
+    params: web::Query<AuditParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
-    
-    // Create snapshots for all collections
-    let collections = storage.list_collections();
-    let mut created_snapshots = Vec::new();
-    
-    for collection_name in collections {
-        match storage.create_collection_snapshot(&collection_name) {
-            Ok(snapshot) => created_snapshots.push(snapshot),
-            Err(e) => {
-                return Ok(qdrant_error(&format!("Failed to snapshot {}: {}", collection_name, e), start_time));
-            }
-        }
+    let Some(log) = storage.audit_log() else {
+        return Ok(qdrant_error("Audit log is not enabled", start_time));
+    };
+    let params = params.into_inner();
+    let query = AuditQuery {
+        collection: params.collection,
+        key_id: params.key_id,
+        operation: params.operation,
+        since: params.since,
+        success: params.success,
+    };
+    let limit = params.limit.unwrap_or(100);
+    let entries = tokio::task::spawn_blocking(move || log.recent(&query, limit))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|entries| entries);
+    match entries {
+        Ok(entries) => Ok(qdrant_response(serde_json::json!({"entries": entries}), start_time)),
+        Err(e) => Ok(qdrant_error(&format!("Failed to read audit log: {}", e), start_time)),
     }
-    
-    // Return metadata about full snapshot
-    let snapshot_name = format!("full-snapshot-{}.snapshot", Utc::now().format("%Y-%m-%d-%H-%M-%S"));
-    
-    Ok(qdrant_response(serde_json::json!({
-        "name": snapshot_name,
-        "creation_time": Utc::now().to_rfc3339(),
-        "size": 0,
-        "collections": created_snapshots.len()
-    }), start_time))
 }
 
-/// Get (download) full snapshot
-async fn get_full_snapshot(
-    _path: web::Path<String>,
-) -> ActixResult<HttpResponse> {
-    let start_time = Instant::now();
-    Ok(qdrant_error("Full storage snapshot download not yet implemented", start_time))
+#[derive(Deserialize, JsonSchema)]
+struct KMeansJobRequest {
+    #[serde(flatten)]
+    config: vectx_core::KMeansConfig,
+    #[serde(default)]
+    filter: Option<serde_json::Value>,
+    /// Payload key receiving each point's cluster index; null to skip
+    #[serde(default = "default_cluster_payload_key")]
+    payload_key: Option<String>,
 }
 
-/// Delete full snapshot
-async fn delete_full_snapshot(
-    _path: web::Path<String>,
+fn default_cluster_payload_key() -> Option<String> {
+    Some("cluster_id".to_string())
+}
+
+/// Start a k-means job over a collection. Poll `/admin/jobs/{id}` for the
+/// centroids.
+async fn start_kmeans_job(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    req: web::Json<KMeansJobRequest>,
+    http_req: HttpRequest,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
-    // For now, just acknowledge
-    Ok(qdrant_response(true, start_time))
+    let name = path.into_inner();
+    let req = req.into_inner();
+
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+    if req.config.k == 0 {
+        return Ok(qdrant_error("k must be at least 1", start_time));
+    }
+
+    let condition = match parse_request_filter(req.filter.as_ref(), parse_mode(&http_req)) {
+        Ok(condition) => condition,
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+    };
+    let collection_name = collection.name().to_string();
+    let job = storage.jobs().spawn("kmeans", Some(&collection_name), move |_| {
+        let filter = condition.map(PayloadFilter::new);
+        let result = collection
+            .kmeans(&req.config, filter.as_ref().map(|f| f as &dyn Filter), req.payload_key.as_deref())
+            .map_err(|e| e.to_string())?;
+        Ok(serde_json::json!({
+            "points": result.assignments.len(),
+            "payload_key": req.payload_key,
+            "centroids": result.centroids,
+            "sizes": result.sizes,
+            "inertia": result.inertia,
+            "iterations": result.iterations
+        }))
+    });
+
+    match job {
+        Ok(job) => Ok(qdrant_response(job, start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
 }
 
-/// Update collection parameters
-#[derive(Deserialize)]
-struct UpdateCollectionRequest {
-    #[serde(default)]
-    optimizers_config: Option<serde_json::Value>,
-    #[serde(default)]
-    params: Option<serde_json::Value>,
+#[derive(Deserialize, JsonSchema)]
+struct DedupJobRequest {
+    /// Minimum similarity score for two points to count as duplicates
+    threshold: f32,
+    /// Nearest neighbours compared per point
+    #[serde(default = "default_dedup_neighbors")]
+    neighbors: usize,
     #[serde(default)]
-    hnsw_config: Option<serde_json::Value>,
-    #[serde(default)]
-    vectors: Option<serde_json::Value>,
+    filter: Option<serde_json::Value>,
+    /// Delete every group member except the highest-versioned one
     #[serde(default)]
-    quantization_config: Option<serde_json::Value>,
+    delete: bool,
 }
 
-async fn update_collection(
+fn default_dedup_neighbors() -> usize { 10 }
+
+/// Start a near-duplicate scan over a collection
+async fn start_dedup_job(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    _req: web::Json<UpdateCollectionRequest>,
+    req: web::Json<DedupJobRequest>,
+    http_req: HttpRequest,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
-    
-    if !storage.collection_exists(&name) {
-        return Ok(qdrant_not_found("Collection not found", start_time));
+    let req = req.into_inner();
+
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+    if req.neighbors == 0 {
+        return Ok(qdrant_error("neighbors must be at least 1", start_time));
     }
-    
-    // Collection update acknowledged (parameters update not yet fully implemented)
-    Ok(qdrant_response(true, start_time))
-}
 
-/// Get issues/performance suggestions
-async fn get_issues() -> ActixResult<HttpResponse> {
-    let start_time = Instant::now();
-    Ok(qdrant_response(serde_json::json!({
-        "issues": []
-    }), start_time))
+    let condition = match parse_request_filter(req.filter.as_ref(), parse_mode(&http_req)) {
+        Ok(condition) => condition,
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+    };
+    let collection_name = collection.name().to_string();
+    let job = storage.jobs().spawn("dedup", Some(&collection_name), move |ctx| {
+        let filter = condition.map(PayloadFilter::new);
+        let groups = collection.find_duplicates(
+            req.threshold,
+            req.neighbors,
+            filter.as_ref().map(|f| f as &dyn Filter),
+        );
+
+        let mut deleted = 0;
+        if req.delete && !ctx.is_cancelled() {
+            let ids: Vec<String> = groups.iter().flat_map(|g| &g.duplicates).map(|id| id.to_string()).collect();
+            deleted = collection.delete_batch(&ids).map_err(|e| e.to_string())?;
+        }
+
+        let groups: Vec<serde_json::Value> = groups.iter()
+            .map(|g| serde_json::json!({
+                "keep": point_id_to_json(&g.keep),
+                "duplicates": g.duplicates.iter().map(point_id_to_json).collect::<Vec<_>>(),
+                "min_score": g.min_score
+            }))
+            .collect();
+        Ok(serde_json::json!({
+            "groups": groups,
+            "deleted": deleted
+        }))
+    });
+
+    match job {
+        Ok(job) => Ok(qdrant_response(job, start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
 }
 
-/// Clear all reported issues
-async fn clear_issues() -> ActixResult<HttpResponse> {
-    let start_time = Instant::now();
-    Ok(qdrant_response(true, start_time))
+#[derive(Deserialize, JsonSchema)]
+struct CreateSnapshotParams {
+    /// `f32` (default, exact) or `f16` (half the vector bytes)
+    #[serde(default)]
+    vector_encoding: VectorEncoding,
+    /// Take a differential snapshot relative to this snapshot
+    #[serde(default)]
+    base: Option<String>,
 }
 
 async fn create_snapshot(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
+    params: web::Query<CreateSnapshotParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let collection_name = path.into_inner();
@@ -2042,29 +4515,49 @@ async fn create_snapshot(
         return Ok(qdrant_not_found(&format!("Collection '{}' not found", collection_name), start_time));
     }
     
-    match storage.create_collection_snapshot(&collection_name) {
+    let result = match &params.base {
+        Some(base) => storage.create_differential_snapshot(&collection_name, base, params.vector_encoding),
+        None => storage.create_collection_snapshot_encoded(&collection_name, params.vector_encoding),
+    };
+    match result {
         Ok(snapshot) => Ok(qdrant_response(snapshot, start_time)),
         Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct RecoverSnapshotRequest {
     location: String,
     #[serde(default)]
-    priority: Option<String>,
+    priority: SnapshotPriority,
     #[serde(default)]
     checksum: Option<String>,
+    /// Restore only the configuration or only payloads
+    #[serde(default)]
+    restore: RestoreScope,
+    /// Restore only snapshot points matching this filter
+    #[serde(default)]
+    filter: Option<serde_json::Value>,
 }
 
 async fn recover_snapshot(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
     req: web::Json<RecoverSnapshotRequest>,
+    http_req: HttpRequest,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let collection_name = path.into_inner();
     let location = &req.location;
+    let filter = match build_filter(req.filter.as_ref(), parse_mode(&http_req), None, None) {
+        Ok(filter) => filter,
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+    };
+    let options = RecoverOptions {
+        priority: req.priority,
+        scope: req.restore,
+        filter,
+    };
     
     // Helper to build response with collection info
     fn build_recovery_result(collection: &vectx_core::Collection) -> serde_json::Value {
@@ -2096,6 +4589,7 @@ async fn recover_snapshot(
             &collection_name,
             location,
             req.checksum.as_deref(),
+            &options,
         ).await {
             Ok(collection) => Ok(qdrant_response(build_recovery_result(&collection), start_time)),
             Err(e) => Ok(qdrant_error(&format!("Failed to recover from URL: {}", e), start_time)),
@@ -2108,13 +4602,13 @@ async fn recover_snapshot(
             .next()
             .unwrap_or(location);
         
-        match storage.recover_from_snapshot(&collection_name, snapshot_name) {
+        match storage.recover_from_snapshot(&collection_name, snapshot_name, &options) {
             Ok(collection) => Ok(qdrant_response(build_recovery_result(&collection), start_time)),
             Err(e) => Ok(qdrant_error(&format!("Failed to recover from snapshot: {}", e), start_time)),
         }
     } else {
         // Assume it's a snapshot name directly
-        match storage.recover_from_snapshot(&collection_name, location) {
+        match storage.recover_from_snapshot(&collection_name, location, &options) {
             Ok(collection) => Ok(qdrant_response(build_recovery_result(&collection), start_time)),
             Err(e) => Ok(qdrant_error(&format!("Failed to recover from snapshot: {}", e), start_time)),
         }
@@ -2158,9 +4652,16 @@ async fn delete_snapshot(
     }
 }
 
+#[derive(Deserialize, JsonSchema)]
+struct UploadSnapshotParams {
+    #[serde(default)]
+    priority: SnapshotPriority,
+}
+
 async fn upload_snapshot(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
+    params: web::Query<UploadSnapshotParams>,
     mut payload: Multipart,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
@@ -2211,7 +4712,12 @@ async fn upload_snapshot(
     };
     
     // Save and restore the snapshot
-    match storage.upload_and_restore_snapshot(&collection_name, &data, filename.as_deref()) {
+    match storage.upload_and_restore_snapshot(
+        &collection_name,
+        &data,
+        filename.as_deref(),
+        &RecoverOptions { priority: params.priority, ..Default::default() },
+    ) {
         Ok(collection) => Ok(qdrant_response(serde_json::json!({
             "collection": collection_name,
             "points_count": collection.count()
@@ -2220,49 +4726,137 @@ async fn upload_snapshot(
     }
 }
 
+// Shard snapshot endpoints
+//
+// Qdrant cluster tooling addresses snapshots per shard. A vectX collection is
+// a single local shard, so shard 0 maps onto the collection snapshot
+// endpoints and any other shard id is reported as missing.
+
+/// Not-found response for a shard id other than 0
+fn check_shard(collection_name: &str, shard_id: u32, start_time: Instant) -> Option<HttpResponse> {
+    (shard_id != 0).then(|| qdrant_not_found(
+        &format!("Shard {} not found in collection '{}'", shard_id, collection_name),
+        start_time,
+    ))
+}
+
+async fn list_shard_snapshots(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<(String, u32)>,
+) -> ActixResult<HttpResponse> {
+    let (collection_name, shard_id) = path.into_inner();
+    if let Some(response) = check_shard(&collection_name, shard_id, Instant::now()) {
+        return Ok(response);
+    }
+    list_snapshots(storage, web::Path::from(collection_name)).await
+}
+
+async fn create_shard_snapshot(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<(String, u32)>,
+    params: web::Query<CreateSnapshotParams>,
+) -> ActixResult<HttpResponse> {
+    let (collection_name, shard_id) = path.into_inner();
+    if let Some(response) = check_shard(&collection_name, shard_id, Instant::now()) {
+        return Ok(response);
+    }
+    create_snapshot(storage, web::Path::from(collection_name), params).await
+}
+
+async fn recover_shard_snapshot(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<(String, u32)>,
+    req: web::Json<RecoverSnapshotRequest>,
+    http_req: HttpRequest,
+) -> ActixResult<HttpResponse> {
+    let (collection_name, shard_id) = path.into_inner();
+    if let Some(response) = check_shard(&collection_name, shard_id, Instant::now()) {
+        return Ok(response);
+    }
+    recover_snapshot(storage, web::Path::from(collection_name), req, http_req).await
+}
+
+async fn upload_shard_snapshot(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<(String, u32)>,
+    params: web::Query<UploadSnapshotParams>,
+    payload: Multipart,
+) -> ActixResult<HttpResponse> {
+    let (collection_name, shard_id) = path.into_inner();
+    if let Some(response) = check_shard(&collection_name, shard_id, Instant::now()) {
+        return Ok(response);
+    }
+    upload_snapshot(storage, web::Path::from(collection_name), params, payload).await
+}
+
+async fn get_shard_snapshot(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<(String, u32, String)>,
+) -> ActixResult<HttpResponse> {
+    let (collection_name, shard_id, snapshot_name) = path.into_inner();
+    if let Some(response) = check_shard(&collection_name, shard_id, Instant::now()) {
+        return Ok(response);
+    }
+    get_snapshot(storage, web::Path::from((collection_name, snapshot_name))).await
+}
+
+async fn delete_shard_snapshot(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<(String, u32, String)>,
+) -> ActixResult<HttpResponse> {
+    let (collection_name, shard_id, snapshot_name) = path.into_inner();
+    if let Some(response) = check_shard(&collection_name, shard_id, Instant::now()) {
+        return Ok(response);
+    }
+    delete_snapshot(storage, web::Path::from((collection_name, snapshot_name))).await
+}
+
 // ============ Additional Qdrant-compatible endpoints ============
 
 /// Update aliases (stub - aliases not yet implemented)
 /// Update aliases request
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct UpdateAliasesRequest {
-    actions: Vec<serde_json::Value>,
+    actions: Vec<AliasAction>,
+}
+
+/// One alias change, e.g. `{"create_alias": {"alias_name": "x", "collection_name": "y"}}`
+#[derive(Deserialize, JsonSchema)]
+enum AliasAction {
+    #[serde(rename = "create_alias")]
+    Create { alias_name: String, collection_name: String },
+    #[serde(rename = "delete_alias")]
+    Delete { alias_name: String },
+    #[serde(rename = "rename_alias")]
+    Rename { old_alias_name: String, new_alias_name: String },
 }
 
+/// Apply alias actions in order, stopping at the first that fails
 async fn update_aliases(
     storage: web::Data<Arc<StorageManager>>,
-    req: web::Json<UpdateAliasesRequest>,
+    req: TypedJson<UpdateAliasesRequest>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     
-    for action in &req.actions {
-        if let Some(obj) = action.as_object() {
-            // Create alias: { "create_alias": { "alias_name": "x", "collection_name": "y" } }
-            if let Some(create) = obj.get("create_alias").and_then(|c| c.as_object()) {
-                if let (Some(alias), Some(collection)) = (
-                    create.get("alias_name").and_then(|v| v.as_str()),
-                    create.get("collection_name").and_then(|v| v.as_str())
-                ) {
-                    let _ = storage.create_alias(alias, collection);
-                }
-            }
-            
-            // Delete alias: { "delete_alias": { "alias_name": "x" } }
-            if let Some(delete) = obj.get("delete_alias").and_then(|d| d.as_object()) {
-                if let Some(alias) = delete.get("alias_name").and_then(|v| v.as_str()) {
-                    let _ = storage.delete_alias(alias);
-                }
-            }
-            
-            // Rename alias: { "rename_alias": { "old_alias_name": "x", "new_alias_name": "y" } }
-            if let Some(rename) = obj.get("rename_alias").and_then(|r| r.as_object()) {
-                if let (Some(old_alias), Some(new_alias)) = (
-                    rename.get("old_alias_name").and_then(|v| v.as_str()),
-                    rename.get("new_alias_name").and_then(|v| v.as_str())
-                ) {
-                    let _ = storage.rename_alias(old_alias, new_alias);
-                }
+    for (idx, action) in req.actions.iter().enumerate() {
+        let applied = match action {
+            AliasAction::Create { alias_name, collection_name } => {
+                storage.create_alias(alias_name, collection_name)
+                    .map_err(|e| format!("action {}: {}", idx, e))
             }
+            AliasAction::Delete { alias_name } => match storage.delete_alias(alias_name) {
+                Ok(true) => Ok(true),
+                Ok(false) => return Ok(qdrant_not_found(&format!("action {}: alias '{}' not found", idx, alias_name), start_time)),
+                Err(e) => Err(format!("action {}: {}", idx, e)),
+            },
+            AliasAction::Rename { old_alias_name, new_alias_name } => match storage.rename_alias(old_alias_name, new_alias_name) {
+                Ok(true) => Ok(true),
+                Ok(false) => return Ok(qdrant_not_found(&format!("action {}: alias '{}' not found", idx, old_alias_name), start_time)),
+                Err(e) => Err(format!("action {}: {}", idx, e)),
+            },
+        };
+        if let Err(e) = applied {
+            return Ok(qdrant_error(&e, start_time));
         }
     }
     
@@ -2309,7 +4903,7 @@ async fn collection_cluster_info(
 }
 
 /// Get multiple points by IDs
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct GetPointsRequest {
     ids: Vec<serde_json::Value>,
     #[serde(default)]
@@ -2321,10 +4915,13 @@ struct GetPointsRequest {
 async fn get_points_by_ids(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<GetPointsRequest>,
+    req: Body<GetPointsRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
+    let claim = claim_filter(&http_req);
     
     let collection = match storage.get_collection(&name) {
         Some(c) => c,
@@ -2344,7 +4941,7 @@ async fn get_points_by_ids(
             _ => continue,
         };
         
-        if let Some(point) = collection.get(&id_str) {
+        if let Some(point) = collection.get(&id_str).filter(|p| claim.as_ref().map_or(true, |c| c.matches(p))) {
             let mut result = serde_json::json!({
                 "id": id_value,
                 "version": point.version
@@ -2353,7 +4950,10 @@ async fn get_points_by_ids(
                 result["payload"] = point.payload.clone().unwrap_or(serde_json::Value::Null);
             }
             if with_vector {
-                result["vector"] = serde_json::json!(point.vector.as_slice());
+                result["vector"] = vector_json(&collection, &point.vector);
+                if !point.sparse_vectors.is_empty() {
+                    result["sparse_vectors"] = serde_json::json!(point.sparse_vectors);
+                }
             }
             points.push(result);
         }
@@ -2363,21 +4963,22 @@ async fn get_points_by_ids(
 }
 
 /// Count points in collection
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct CountRequest {
     #[serde(default)]
     filter: Option<serde_json::Value>,
-    #[serde(default)]
-    exact: Option<bool>,
 }
 
 async fn count_points(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    _req: web::Json<CountRequest>,
+    req: Body<CountRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
+    let claim = claim_filter(&http_req);
     
     let collection = match storage.get_collection(&name) {
         Some(c) => c,
@@ -2386,13 +4987,21 @@ async fn count_points(
         }
     };
 
+    let count = if req.filter.is_none() && claim.is_none() {
+        collection.count()
+    } else {
+        let filter = json_filter(req.filter.as_ref(), claim.as_deref(), &collection);
+        let matching = collection.iter_filtered(Some(&filter), Projection::IDS).remaining();
+        matching
+    };
+
     Ok(qdrant_response(serde_json::json!({
-        "count": collection.count()
+        "count": count
     }), start_time))
 }
 
 /// Set payload on points
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct SetPayloadRequest {
     payload: serde_json::Value,
     #[serde(default)]
@@ -2404,7 +5013,8 @@ struct SetPayloadRequest {
 async fn set_payload(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<SetPayloadRequest>,
+    req: Body<SetPayloadRequest>,
+    params: web::Query<UpdateParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -2414,7 +5024,7 @@ async fn set_payload(
         None => return Ok(qdrant_not_found("Collection not found", start_time)),
     };
 
-    let mut updated_count = 0;
+    let mut versions = serde_json::Map::new();
 
     // If specific points are provided, update only those
     if let Some(point_ids) = &req.points {
@@ -2424,24 +5034,35 @@ async fn set_payload(
                 serde_json::Value::Number(n) => n.to_string(),
                 _ => continue,
             };
-            if collection.set_payload(&id_str, req.payload.clone()).unwrap_or(false) {
-                updated_count += 1;
+            match collection.set_payload(&id_str, req.payload.clone()) {
+                Ok(Some(version)) => {
+                    versions.insert(id_str, version.into());
+                }
+                Ok(None) => {}
+                Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
             }
         }
     } else {
         // Update all points (or filtered points)
-        let all_points = collection.get_all_points();
-        for point in all_points {
+        if let Some(Err(e)) = req.filter.as_ref().map(core_requests::check_write_selector) {
+            return Ok(qdrant_parse_error(&e, start_time));
+        }
+        let filter = json_filter(req.filter.as_ref(), None, &collection);
+        for point in collection.iter_filtered(Some(&filter), Projection::IDS) {
             let id_str = point.id.to_string();
-            if collection.set_payload(&id_str, req.payload.clone()).unwrap_or(false) {
-                updated_count += 1;
+            match collection.set_payload(&id_str, req.payload.clone()) {
+                Ok(Some(version)) => {
+                    versions.insert(id_str, version.into());
+                }
+                Ok(None) => {}
+                Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
             }
         }
     }
 
     Ok(qdrant_response(serde_json::json!({
-        "operation_id": updated_count,
-        "status": "acknowledged"
+        "status": params.status(),
+        "versions": versions
     }), start_time))
 }
 
@@ -2449,7 +5070,8 @@ async fn set_payload(
 async fn overwrite_payload(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<SetPayloadRequest>,
+    req: Body<SetPayloadRequest>,
+    params: web::Query<UpdateParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -2459,7 +5081,7 @@ async fn overwrite_payload(
         None => return Ok(qdrant_not_found("Collection not found", start_time)),
     };
 
-    let mut updated_count = 0;
+    let mut versions = serde_json::Map::new();
 
     if let Some(point_ids) = &req.points {
         for id_value in point_ids {
@@ -2468,28 +5090,39 @@ async fn overwrite_payload(
                 serde_json::Value::Number(n) => n.to_string(),
                 _ => continue,
             };
-            if collection.overwrite_payload(&id_str, req.payload.clone()).unwrap_or(false) {
-                updated_count += 1;
+            match collection.overwrite_payload(&id_str, req.payload.clone()) {
+                Ok(Some(version)) => {
+                    versions.insert(id_str, version.into());
+                }
+                Ok(None) => {}
+                Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
             }
         }
     } else {
-        let all_points = collection.get_all_points();
-        for point in all_points {
+        if let Some(Err(e)) = req.filter.as_ref().map(core_requests::check_write_selector) {
+            return Ok(qdrant_parse_error(&e, start_time));
+        }
+        let filter = json_filter(req.filter.as_ref(), None, &collection);
+        for point in collection.iter_filtered(Some(&filter), Projection::IDS) {
             let id_str = point.id.to_string();
-            if collection.overwrite_payload(&id_str, req.payload.clone()).unwrap_or(false) {
-                updated_count += 1;
+            match collection.overwrite_payload(&id_str, req.payload.clone()) {
+                Ok(Some(version)) => {
+                    versions.insert(id_str, version.into());
+                }
+                Ok(None) => {}
+                Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
             }
         }
     }
 
     Ok(qdrant_response(serde_json::json!({
-        "operation_id": updated_count,
-        "status": "acknowledged"
+        "status": params.status(),
+        "versions": versions
     }), start_time))
 }
 
 /// Delete payload fields from points
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct DeletePayloadRequest {
     keys: Vec<String>,
     #[serde(default)]
@@ -2501,7 +5134,8 @@ struct DeletePayloadRequest {
 async fn delete_payload(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<DeletePayloadRequest>,
+    req: Body<DeletePayloadRequest>,
+    params: web::Query<UpdateParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -2511,7 +5145,7 @@ async fn delete_payload(
         None => return Ok(qdrant_not_found("Collection not found", start_time)),
     };
 
-    let mut updated_count = 0;
+    let mut versions = serde_json::Map::new();
 
     if let Some(point_ids) = &req.points {
         for id_value in point_ids {
@@ -2520,28 +5154,39 @@ async fn delete_payload(
                 serde_json::Value::Number(n) => n.to_string(),
                 _ => continue,
             };
-            if collection.delete_payload_keys(&id_str, &req.keys).unwrap_or(false) {
-                updated_count += 1;
+            match collection.delete_payload_keys(&id_str, &req.keys) {
+                Ok(Some(version)) => {
+                    versions.insert(id_str, version.into());
+                }
+                Ok(None) => {}
+                Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
             }
         }
     } else {
-        let all_points = collection.get_all_points();
-        for point in all_points {
+        if let Some(Err(e)) = req.filter.as_ref().map(core_requests::check_write_selector) {
+            return Ok(qdrant_parse_error(&e, start_time));
+        }
+        let filter = json_filter(req.filter.as_ref(), None, &collection);
+        for point in collection.iter_filtered(Some(&filter), Projection::IDS) {
             let id_str = point.id.to_string();
-            if collection.delete_payload_keys(&id_str, &req.keys).unwrap_or(false) {
-                updated_count += 1;
+            match collection.delete_payload_keys(&id_str, &req.keys) {
+                Ok(Some(version)) => {
+                    versions.insert(id_str, version.into());
+                }
+                Ok(None) => {}
+                Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
             }
         }
     }
 
     Ok(qdrant_response(serde_json::json!({
-        "operation_id": updated_count,
-        "status": "acknowledged"
+        "status": params.status(),
+        "versions": versions
     }), start_time))
 }
 
 /// Clear all payload from points
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct ClearPayloadRequest {
     #[serde(default)]
     points: Option<Vec<serde_json::Value>>,
@@ -2552,7 +5197,8 @@ struct ClearPayloadRequest {
 async fn clear_payload(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<ClearPayloadRequest>,
+    req: Body<ClearPayloadRequest>,
+    params: web::Query<UpdateParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -2562,7 +5208,7 @@ async fn clear_payload(
         None => return Ok(qdrant_not_found("Collection not found", start_time)),
     };
 
-    let mut updated_count = 0;
+    let mut versions = serde_json::Map::new();
 
     if let Some(point_ids) = &req.points {
         for id_value in point_ids {
@@ -2571,34 +5217,49 @@ async fn clear_payload(
                 serde_json::Value::Number(n) => n.to_string(),
                 _ => continue,
             };
-            if collection.clear_payload(&id_str).unwrap_or(false) {
-                updated_count += 1;
+            match collection.clear_payload(&id_str) {
+                Ok(Some(version)) => {
+                    versions.insert(id_str, version.into());
+                }
+                Ok(None) => {}
+                Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
             }
         }
     } else {
-        let all_points = collection.get_all_points();
-        for point in all_points {
+        if let Some(Err(e)) = req.filter.as_ref().map(core_requests::check_write_selector) {
+            return Ok(qdrant_parse_error(&e, start_time));
+        }
+        let filter = json_filter(req.filter.as_ref(), None, &collection);
+        for point in collection.iter_filtered(Some(&filter), Projection::IDS) {
             let id_str = point.id.to_string();
-            if collection.clear_payload(&id_str).unwrap_or(false) {
-                updated_count += 1;
+            match collection.clear_payload(&id_str) {
+                Ok(Some(version)) => {
+                    versions.insert(id_str, version.into());
+                }
+                Ok(None) => {}
+                Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
             }
         }
     }
 
     Ok(qdrant_response(serde_json::json!({
-        "operation_id": updated_count,
-        "status": "acknowledged"
+        "status": params.status(),
+        "versions": versions
     }), start_time))
 }
 
 /// Update vectors on existing points
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct UpdateVectorsRequest {
     /// List of point updates with id and vector
     points: Vec<UpdateVectorPoint>,
+    /// Rebuild the HNSW graph in the background instead of re-inserting the
+    /// updated points (better for refreshing a large share of the collection)
+    #[serde(default)]
+    relink: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct UpdateVectorPoint {
     id: serde_json::Value,
     vector: serde_json::Value,
@@ -2607,7 +5268,8 @@ struct UpdateVectorPoint {
 async fn update_vectors(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<UpdateVectorsRequest>,
+    req: Body<UpdateVectorsRequest>,
+    params: web::Query<UpdateParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -2617,7 +5279,8 @@ async fn update_vectors(
         None => return Ok(qdrant_not_found("Collection not found", start_time)),
     };
 
-    let mut updated_count = 0;
+    let mut updates = Vec::with_capacity(req.points.len());
+    let mut multivector_updates = Vec::new();
 
     for point_update in &req.points {
         let id_str = match &point_update.id {
@@ -2626,6 +5289,19 @@ async fn update_vectors(
             _ => continue,
         };
 
+        // Declared named multivectors are set by name
+        if let serde_json::Value::Object(obj) = &point_update.vector {
+            for (vector_name, value) in obj.iter().filter(|(n, _)| collection.named_multivector(n).is_some()) {
+                let mv = serde_json::from_value::<Vec<Vec<f32>>>(value.clone())
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| MultiVector::new(data).map_err(str::to_string));
+                match mv {
+                    Ok(mv) => multivector_updates.push((id_str.clone(), vector_name.clone(), mv)),
+                    Err(e) => return Ok(qdrant_error(&format!("multivector '{}': {}", vector_name, e), start_time)),
+                }
+            }
+        }
+
         // Parse vector - can be array or named vectors object
         let vector_data = match &point_update.vector {
             serde_json::Value::Array(arr) => {
@@ -2635,8 +5311,8 @@ async fn update_vectors(
                 vec.ok()
             }
             serde_json::Value::Object(obj) => {
-                // Named vectors - get first one
-                if let Some((_, vec_val)) = obj.iter().next() {
+                // Named vectors - get the first that isn't a named multivector
+                if let Some((_, vec_val)) = obj.iter().find(|(n, _)| collection.named_multivector(n).is_none()) {
                     if let Some(arr) = vec_val.as_array() {
                         let vec: Result<Vec<f32>, _> = arr.iter()
                             .map(|v| v.as_f64().map(|f| f as f32).ok_or("expected f32"))
@@ -2652,22 +5328,42 @@ async fn update_vectors(
             _ => None,
         };
 
-        if let Some(vec) = vector_data {
-            let vector = Vector::new(vec);
-            if collection.update_vector(&id_str, vector).unwrap_or(false) {
-                updated_count += 1;
+        // Binary vectors may also come as a base64 string
+        let vector = match (&point_update.vector, vector_data) {
+            (serde_json::Value::String(text), _) => {
+                decode_base64(text).and_then(|bytes| dense_vector(&collection, &[], Some(&bytes)))
+            }
+            (_, Some(vec)) => dense_vector(&collection, &vec, None),
+            (_, None) => continue,
+        };
+        match vector {
+            Ok(vector) => updates.push((id_str, vector)),
+            Err(e) => return Ok(qdrant_error(&e, start_time)),
+        }
+    }
+
+    let mut versions: serde_json::Map<String, serde_json::Value> = match collection.update_vectors(updates, req.relink) {
+        Ok(updated) => updated.into_iter().map(|(id, version)| (id, version.into())).collect(),
+        Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
+    };
+    for (id, vector_name, mv) in multivector_updates {
+        match collection.update_named_multivector(&id, &vector_name, Some(mv)) {
+            Ok(Some(version)) => {
+                versions.insert(id, version.into());
             }
+            Ok(None) => {}
+            Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
         }
     }
 
     Ok(qdrant_response(serde_json::json!({
-        "operation_id": updated_count,
-        "status": "acknowledged"
+        "status": params.status(),
+        "versions": versions
     }), start_time))
 }
 
 /// Delete vectors from points
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct DeleteVectorsRequest {
     #[serde(default)]
     points: Option<Vec<serde_json::Value>>,
@@ -2680,7 +5376,8 @@ struct DeleteVectorsRequest {
 async fn delete_vectors(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<DeleteVectorsRequest>,
+    req: Body<DeleteVectorsRequest>,
+    params: web::Query<UpdateParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -2690,42 +5387,86 @@ async fn delete_vectors(
         None => return Ok(qdrant_not_found("Collection not found", start_time)),
     };
 
-    let mut deleted_count = 0;
+    let mut versions = serde_json::Map::new();
 
-    // Note: In a full named-vectors implementation, this would delete specific named vectors
-    // For now, if points are specified, we clear their vectors (effectively delete the point)
-    if let Some(point_ids) = &req.points {
-        for id_value in point_ids {
-            let id_str = match id_value {
-                serde_json::Value::String(s) => s.clone(),
-                serde_json::Value::Number(n) => n.to_string(),
-                _ => continue,
-            };
-            // Delete multivector if it was the target
-            if req.vectors.iter().any(|v| v == "multivector" || v.is_empty()) {
-                if collection.update_multivector(&id_str, None).unwrap_or(false) {
-                    deleted_count += 1;
+    // Points given by id, or else those matching the filter
+    let ids: Vec<String> = if let Some(point_ids) = &req.points {
+        point_ids.iter()
+            .filter_map(|id_value| match id_value {
+                serde_json::Value::String(s) => Some(s.clone()),
+                serde_json::Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect()
+    } else if let Some(filter) = &req.filter {
+        if let Err(e) = core_requests::check_write_selector(filter) {
+            return Ok(qdrant_parse_error(&e, start_time));
+        }
+        let filter = json_filter(Some(filter), None, &collection);
+        collection.iter_filtered(Some(&filter), Projection::IDS)
+            .map(|point| point.id.to_string())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    for id_str in ids {
+        for vector_name in req.vectors.iter().filter(|v| collection.named_multivector(v).is_some()) {
+            match collection.update_named_multivector(&id_str, vector_name, None) {
+                Ok(Some(version)) => {
+                    versions.insert(id_str.clone(), version.into());
                 }
+                Ok(None) => {}
+                Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
+            }
+        }
+        // Delete multivector if it was the target
+        if !req.vectors.iter().any(|v| v == "multivector" || v.is_empty()) {
+            continue;
+        }
+        match collection.update_multivector(&id_str, None) {
+            Ok(Some(version)) => {
+                versions.insert(id_str, version.into());
             }
+            Ok(None) => {}
+            Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
         }
     }
 
     Ok(qdrant_response(serde_json::json!({
-        "operation_id": deleted_count,
-        "status": "acknowledged"
+        "status": params.status(),
+        "versions": versions
     }), start_time))
 }
 
 /// Batch update operations
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct BatchUpdateRequest {
-    operations: Vec<serde_json::Value>,
+    operations: Vec<UpdateOperation>,
+    /// Apply every operation or none: an invalid point rejects the whole
+    /// request
+    #[serde(default)]
+    atomic: bool,
+}
+
+/// One operation of a batch update, keyed by its kind as in Qdrant, e.g.
+/// `{"delete": {"points": [1, 2]}}`
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum UpdateOperation {
+    Upsert { points: Vec<PointRequest> },
+    Delete { points: Vec<ExtendedPointId> },
+    SetPayload { payload: serde_json::Value, points: Vec<ExtendedPointId> },
+    OverwritePayload { payload: serde_json::Value, points: Vec<ExtendedPointId> },
+    DeletePayload { keys: Vec<String>, points: Vec<ExtendedPointId> },
+    ClearPayload { points: Vec<ExtendedPointId> },
 }
 
 async fn batch_update(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<BatchUpdateRequest>,
+    req: TypedJson<BatchUpdateRequest>,
+    params: web::Query<UpdateParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -2735,176 +5476,168 @@ async fn batch_update(
         None => return Ok(qdrant_not_found("Collection not found", start_time)),
     };
 
-    let mut results = Vec::new();
-
-    for (idx, operation) in req.operations.iter().enumerate() {
-        let op_result = process_batch_operation(&collection, operation);
-        results.push(serde_json::json!({
-            "operation_id": idx,
-            "status": if op_result { "acknowledged" } else { "failed" }
-        }));
-    }
-
-    Ok(qdrant_response(results, start_time))
-}
-
-/// Process a single batch operation
-fn process_batch_operation(collection: &std::sync::Arc<vectx_core::Collection>, operation: &serde_json::Value) -> bool {
-    let obj = match operation.as_object() {
-        Some(o) => o,
-        None => return false,
-    };
-
-    // Upsert operation
-    if let Some(upsert) = obj.get("upsert") {
-        if let Some(points) = upsert.get("points").and_then(|p| p.as_array()) {
-            for point_json in points {
-                if let Some(point) = parse_point_from_json(point_json) {
-                    let _ = collection.upsert(point);
-                }
-            }
-            return true;
-        }
-    }
-
-    // Delete operation
-    if let Some(delete) = obj.get("delete") {
-        if let Some(points) = delete.get("points").and_then(|p| p.as_array()) {
-            for id_val in points {
-                let id_str = match id_val {
-                    serde_json::Value::String(s) => s.clone(),
-                    serde_json::Value::Number(n) => n.to_string(),
-                    _ => continue,
-                };
-                let _ = collection.delete(&id_str);
+    if req.atomic {
+        let mut operations = Vec::with_capacity(req.operations.len());
+        for (idx, operation) in req.operations.iter().enumerate() {
+            match to_batch_operation(operation, &collection, storage.max_payload_bytes()) {
+                Ok(op) => operations.push(op),
+                Err(e) => return Ok(qdrant_error(&format!("operation {}: {}", idx, e), start_time)),
             }
-            return true;
         }
-    }
-
-    // Set payload operation
-    if let Some(set_payload) = obj.get("set_payload") {
-        if let Some(payload) = set_payload.get("payload") {
-            if let Some(points) = set_payload.get("points").and_then(|p| p.as_array()) {
-                for id_val in points {
-                    let id_str = match id_val {
-                        serde_json::Value::String(s) => s.clone(),
-                        serde_json::Value::Number(n) => n.to_string(),
-                        _ => continue,
-                    };
-                    let _ = collection.set_payload(&id_str, payload.clone());
-                }
-                return true;
-            }
+        if let Err(e) = collection.apply_atomic(operations) {
+            return Ok(qdrant_error(&e.to_string(), start_time));
         }
+        let results: Vec<serde_json::Value> = (0..req.operations.len())
+            .map(|idx| serde_json::json!({ "operation_id": idx, "status": params.status() }))
+            .collect();
+        return Ok(qdrant_response(results, start_time));
     }
 
-    // Overwrite payload operation
-    if let Some(overwrite_payload) = obj.get("overwrite_payload") {
-        if let Some(payload) = overwrite_payload.get("payload") {
-            if let Some(points) = overwrite_payload.get("points").and_then(|p| p.as_array()) {
-                for id_val in points {
-                    let id_str = match id_val {
-                        serde_json::Value::String(s) => s.clone(),
-                        serde_json::Value::Number(n) => n.to_string(),
-                        _ => continue,
-                    };
-                    let _ = collection.overwrite_payload(&id_str, payload.clone());
-                }
-                return true;
-            }
-        }
-    }
+    let mut results = Vec::new();
 
-    // Delete payload operation
-    if let Some(delete_payload) = obj.get("delete_payload") {
-        if let Some(keys) = delete_payload.get("keys").and_then(|k| k.as_array()) {
-            let key_strings: Vec<String> = keys.iter()
-                .filter_map(|k| k.as_str().map(String::from))
-                .collect();
-            if let Some(points) = delete_payload.get("points").and_then(|p| p.as_array()) {
-                for id_val in points {
-                    let id_str = match id_val {
-                        serde_json::Value::String(s) => s.clone(),
-                        serde_json::Value::Number(n) => n.to_string(),
-                        _ => continue,
-                    };
-                    let _ = collection.delete_payload_keys(&id_str, &key_strings);
-                }
-                return true;
-            }
-        }
+    for (idx, operation) in req.operations.iter().enumerate() {
+        let result = match to_batch_operation(operation, &collection, storage.max_payload_bytes())
+            .and_then(|op| apply_batch_operation(&collection, op).map_err(|e| e.to_string()))
+        {
+            Ok(()) => serde_json::json!({ "operation_id": idx, "status": params.status() }),
+            Err(e) => serde_json::json!({ "operation_id": idx, "status": "failed", "error": e }),
+        };
+        results.push(result);
     }
 
-    // Clear payload operation
-    if let Some(clear_payload) = obj.get("clear_payload") {
-        if let Some(points) = clear_payload.get("points").and_then(|p| p.as_array()) {
-            for id_val in points {
-                let id_str = match id_val {
-                    serde_json::Value::String(s) => s.clone(),
-                    serde_json::Value::Number(n) => n.to_string(),
-                    _ => continue,
-                };
-                let _ = collection.clear_payload(&id_str);
-            }
-            return true;
-        }
-    }
+    Ok(qdrant_response(results, start_time))
+}
 
-    false
+/// Parse a batch update body and check its operations against
+/// `collection`, as `POST /points/batch` does (see [`crate::fuzz`])
+pub(crate) fn parse_batch_update(body: &[u8], collection: &Collection) -> Result<Vec<vectx_core::BatchOperation>, String> {
+    let request: BatchUpdateRequest = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+    request.operations.iter()
+        .enumerate()
+        .map(|(idx, operation)| {
+            to_batch_operation(operation, collection, None).map_err(|e| format!("operation {}: {}", idx, e))
+        })
+        .collect()
 }
 
-/// Parse a point from JSON
-fn parse_point_from_json(json: &serde_json::Value) -> Option<Point> {
-    let obj = json.as_object()?;
-    
-    let id = match obj.get("id")? {
-        serde_json::Value::String(s) => vectx_core::PointId::String(s.clone()),
-        serde_json::Value::Number(n) => {
-            vectx_core::PointId::Integer(n.as_u64().unwrap_or(0))
+/// The collection operation of one batch update operation, with its points
+/// checked against `collection`
+fn to_batch_operation(
+    operation: &UpdateOperation,
+    collection: &Collection,
+    max_payload_bytes: Option<usize>,
+) -> Result<vectx_core::BatchOperation, String> {
+    use vectx_core::BatchOperation;
+
+    let ids = |points: &[ExtendedPointId]| points.iter().map(ExtendedPointId::to_id_string).collect();
+    Ok(match operation {
+        UpdateOperation::Upsert { points } => {
+            let mut parsed = Vec::with_capacity(points.len());
+            for (idx, point) in points.iter().enumerate() {
+                parsed.push(point_from_request(point, collection).map_err(|e| format!("point {}: {}", idx, e))?);
+            }
+            collection.validate_points(&parsed, max_payload_bytes).map_err(|e| e.to_string())?;
+            BatchOperation::Upsert(parsed)
         }
-        _ => return None,
-    };
-
-    let vector_data = obj.get("vector")?;
-    let vector = match vector_data {
-        serde_json::Value::Array(arr) => {
-            let vec: Result<Vec<f32>, _> = arr.iter()
-                .map(|v| v.as_f64().map(|f| f as f32).ok_or("expected f32"))
-                .collect();
-            Vector::new(vec.ok()?)
+        UpdateOperation::Delete { points } => BatchOperation::Delete(ids(points)),
+        UpdateOperation::SetPayload { payload, points } => {
+            BatchOperation::SetPayload { ids: ids(points), payload: payload.clone() }
         }
-        _ => return None,
-    };
+        UpdateOperation::OverwritePayload { payload, points } => {
+            BatchOperation::OverwritePayload { ids: ids(points), payload: payload.clone() }
+        }
+        UpdateOperation::DeletePayload { keys, points } => {
+            BatchOperation::DeletePayload { ids: ids(points), keys: keys.clone() }
+        }
+        UpdateOperation::ClearPayload { points } => BatchOperation::ClearPayload(ids(points)),
+    })
+}
 
-    let payload = obj.get("payload").cloned();
+/// Apply one batch operation point by point, skipping missing points.
+/// Stops at the first write that fails, such as one the store refuses.
+fn apply_batch_operation(collection: &Collection, operation: vectx_core::BatchOperation) -> vectx_core::Result<()> {
+    use vectx_core::BatchOperation;
 
-    Some(Point::new(id, vector, payload))
+    match operation {
+        BatchOperation::Upsert(points) => {
+            for point in points {
+                collection.upsert(point)?;
+            }
+        }
+        BatchOperation::Delete(ids) => {
+            collection.delete_batch(&ids)?;
+        }
+        BatchOperation::SetPayload { ids, payload } => {
+            for id in ids {
+                collection.set_payload(&id, payload.clone())?;
+            }
+        }
+        BatchOperation::OverwritePayload { ids, payload } => {
+            for id in ids {
+                collection.overwrite_payload(&id, payload.clone())?;
+            }
+        }
+        BatchOperation::DeletePayload { ids, keys } => {
+            for id in ids {
+                collection.delete_payload_keys(&id, &keys)?;
+            }
+        }
+        BatchOperation::ClearPayload(ids) => {
+            for id in ids {
+                collection.clear_payload(&id)?;
+            }
+        }
+    }
+    Ok(())
 }
 
 /// Batch search
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct BatchSearchRequest {
-    searches: Vec<serde_json::Value>,
+    searches: Vec<SearchRequest>,
 }
 
 async fn batch_search(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    _req: web::Json<BatchSearchRequest>,
+    req: Body<BatchSearchRequest>,
+    http_req: HttpRequest,
+    params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
     
-    if storage.get_collection(&name).is_none() {
-        return Ok(qdrant_not_found("Collection not found", start_time));
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
+
+    let mut results = Vec::new();
+    for search in req.into_inner().searches {
+        let response = content::as_json(run_search(collection.clone(), search, &http_req, &params, start_time)).await?;
+        match batch_result(response).await {
+            Ok(result) => results.push(result),
+            Err(response) => return Ok(response),
+        }
     }
+    Ok(qdrant_response(results, start_time))
+}
 
-    Ok(qdrant_response(Vec::<serde_json::Value>::new(), start_time))
+/// The `result` of one search of a batch, or its response if it failed
+async fn batch_result(response: HttpResponse) -> Result<serde_json::Value, HttpResponse> {
+    if !response.status().is_success() {
+        return Err(response);
+    }
+    let start_time = Instant::now();
+    let body = actix_web::body::to_bytes(response.into_body()).await
+        .map_err(|e| qdrant_error(&e.to_string(), start_time))?;
+    let mut body: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|e| qdrant_error(&e.to_string(), start_time))?;
+    Ok(body["result"].take())
 }
 
 /// Search points grouped by a payload field
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct SearchGroupsRequest {
     vector: Vec<f32>,
     group_by: String,
@@ -2923,7 +5656,9 @@ struct SearchGroupsRequest {
 async fn search_groups(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<SearchGroupsRequest>,
+    req: Body<SearchGroupsRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -2940,9 +5675,14 @@ async fn search_groups(
     let with_payload = req.with_payload.unwrap_or(true);
     let with_vector = req.with_vector.unwrap_or(false);
     let group_by = &req.group_by;
+    let claim = claim_filter(&http_req);
+    let filter = match build_filter(req.filter.as_ref(), parse_mode(&http_req), claim.as_deref(), Some(&collection)) {
+        Ok(filter) => filter,
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+    };
     
     let query_vector = Vector::new(req.vector.clone());
-    let search_results = collection.search(&query_vector, limit * group_size * 2, None);
+    let search_results = collection.search(&query_vector, limit * group_size * 2, filter.as_deref());
     
     // Group results by the group_by field
     let mut groups: std::collections::HashMap<String, Vec<serde_json::Value>> = std::collections::HashMap::new();
@@ -2970,7 +5710,7 @@ async fn search_groups(
                 hit["payload"] = point.payload.clone().unwrap_or(serde_json::Value::Null);
             }
             if with_vector {
-                hit["vector"] = serde_json::json!(point.vector.as_slice());
+                hit["vector"] = vector_json(&collection, &point.vector);
             }
             
             group.push(hit);
@@ -2992,13 +5732,12 @@ async fn search_groups(
     }), start_time))
 }
 
-/// Discover points using context pairs
-#[derive(Deserialize)]
+/// Discover points near a target. Context pairs are not supported: the
+/// search runs from the target alone.
+#[derive(Deserialize, JsonSchema)]
 struct DiscoverRequest {
     #[serde(default)]
-    target: Option<serde_json::Value>,
-    #[serde(default)]
-    context: Option<Vec<ContextPair>>,
+    target: Option<VectorInput>,
     #[serde(default)]
     limit: Option<usize>,
     #[serde(default)]
@@ -3009,16 +5748,12 @@ struct DiscoverRequest {
     filter: Option<serde_json::Value>,
 }
 
-#[derive(Deserialize)]
-struct ContextPair {
-    positive: serde_json::Value,
-    negative: serde_json::Value,
-}
-
 async fn discover_points(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<DiscoverRequest>,
+    req: TypedJson<DiscoverRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -3030,42 +5765,29 @@ async fn discover_points(
         }
     };
 
+    match discover(&collection, &req, &http_req) {
+        Ok(scored_points) => Ok(qdrant_response(scored_points, start_time)),
+        Err(e) => Ok(qdrant_error(&e, start_time)),
+    }
+}
+
+/// Search from a discovery request's target
+fn discover(collection: &Collection, req: &DiscoverRequest, http_req: &HttpRequest) -> Result<Vec<serde_json::Value>, String> {
     let limit = req.limit.unwrap_or(10);
     let with_payload = req.with_payload.unwrap_or(true);
-    let _with_vector = req.with_vector.unwrap_or(false);
-    
-    // Parse target vector or point ID
-    let target_vector = if let Some(target) = &req.target {
-        match target {
-            serde_json::Value::Array(arr) => {
-                let vec: Result<Vec<f32>, _> = arr.iter()
-                    .map(|v| v.as_f64().map(|f| f as f32).ok_or("expected f32"))
-                    .collect();
-                vec.ok().map(Vector::new)
-            }
-            serde_json::Value::Number(n) => {
-                let id = n.to_string();
-                collection.get(&id).map(|p| p.vector.clone())
-            }
-            serde_json::Value::String(s) => {
-                collection.get(s).map(|p| p.vector.clone())
-            }
-            _ => None,
-        }
-    } else {
-        None
-    };
+    let with_vector = req.with_vector.unwrap_or(false);
+    let claim = claim_filter(http_req);
+    let filter = build_filter(req.filter.as_ref(), parse_mode(http_req), claim.as_deref(), Some(collection))
+        .map_err(|e| e.to_string())?;
 
-    let query = match target_vector {
-        Some(v) => v,
-        None => {
-            return Ok(qdrant_error("Target vector or point ID required", start_time));
-        }
-    };
+    // Target vector, or the vector of the target point
+    let query = req.target.as_ref()
+        .and_then(|target| target.resolve(collection))
+        .ok_or("Target vector or point ID required")?;
     
-    let results = collection.search(&query, limit, None);
+    let results = collection.search(&query, limit, filter.as_deref());
     
-    let scored_points: Vec<serde_json::Value> = results.into_iter().map(|(point, score)| {
+    Ok(results.into_iter().map(|(point, score)| {
         let mut result = serde_json::json!({
             "id": point_id_to_json(&point.id),
             "version": point.version,
@@ -3074,49 +5796,62 @@ async fn discover_points(
         if with_payload {
             result["payload"] = point.payload.clone().unwrap_or(serde_json::Value::Null);
         }
+        if with_vector {
+            result["vector"] = vector_json(collection, &point.vector);
+        }
         result
-    }).collect();
-
-    Ok(qdrant_response(scored_points, start_time))
+    }).collect())
 }
 
 /// Batch discover points
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct DiscoverBatchRequest {
-    searches: Vec<serde_json::Value>,
+    searches: Vec<DiscoverRequest>,
 }
 
 async fn discover_batch(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    _req: web::Json<DiscoverBatchRequest>,
+    req: TypedJson<DiscoverBatchRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
     
-    if storage.get_collection(&name).is_none() {
-        return Ok(qdrant_not_found("Collection not found", start_time));
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => {
+            return Ok(qdrant_not_found("Collection not found", start_time));
+        }
+    };
+
+    let mut results = Vec::with_capacity(req.searches.len());
+    for (idx, search) in req.searches.iter().enumerate() {
+        match discover(&collection, search, &http_req) {
+            Ok(scored_points) => results.push(scored_points),
+            Err(e) => return Ok(qdrant_error(&format!("search {}: {}", idx, e), start_time)),
+        }
     }
 
-    Ok(qdrant_response(Vec::<Vec<serde_json::Value>>::new(), start_time))
+    Ok(qdrant_response(results, start_time))
 }
 
 /// Facet counts - count points by unique payload values
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct FacetRequest {
     key: String,
     #[serde(default)]
     limit: Option<usize>,
     #[serde(default)]
     filter: Option<serde_json::Value>,
-    #[serde(default)]
-    exact: Option<bool>,
 }
 
 async fn facet_counts(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
     req: web::Json<FacetRequest>,
+    _params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -3132,10 +5867,10 @@ async fn facet_counts(
     let key = &req.key;
     
     // Count occurrences of each value for the given key
-    let all_points = collection.get_all_points();
+    let filter = json_filter(req.filter.as_ref(), None, &collection);
     let mut value_counts: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
     
-    for point in all_points {
+    for point in collection.iter_filtered(Some(&filter), Projection::PAYLOAD) {
         if let Some(payload) = &point.payload {
             if let Some(value) = payload.get(key) {
                 let value_str = match value {
@@ -3151,7 +5886,7 @@ async fn facet_counts(
     
     // Sort by count and take top limit
     let mut counts: Vec<_> = value_counts.into_iter().collect();
-    counts.sort_by(|a, b| b.1.cmp(&a.1));
+    counts.sort_by_key(|c| std::cmp::Reverse(c.1));
     
     let hits: Vec<serde_json::Value> = counts.into_iter()
         .take(limit)
@@ -3166,29 +5901,198 @@ async fn facet_counts(
     }), start_time))
 }
 
+#[derive(Deserialize, JsonSchema)]
+struct AggregateRequest {
+    #[serde(flatten)]
+    aggregation: vectx_core::AggregationRequest,
+    /// Only aggregate points matching this filter
+    #[serde(default)]
+    filter: Option<serde_json::Value>,
+}
+
+/// Counts, sums, averages, extremes and histograms of indexed payload
+/// fields, per value of a keyword field
+async fn aggregate_points(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    req: web::Json<AggregateRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = path.into_inner();
+    let claim = claim_filter(&http_req);
+
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => {
+            return Ok(qdrant_not_found("Collection not found", start_time));
+        }
+    };
+
+    let filter = json_filter(req.filter.as_ref(), claim.as_deref(), &collection);
+    match collection.aggregate(&req.aggregation, Some(&filter)) {
+        Ok(result) => Ok(qdrant_response(result, start_time)),
+        Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct PayloadStatsParams {
+    /// Most frequent values listed per key
+    #[serde(default = "default_top_values")]
+    top: usize,
+}
+
+fn default_top_values() -> usize { 10 }
+
+/// Per payload key: value types, distinct count, numeric range and most
+/// frequent values
+async fn payload_stats(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    params: web::Query<PayloadStatsParams>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = path.into_inner();
+
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => {
+            return Ok(qdrant_not_found("Collection not found", start_time));
+        }
+    };
+
+    Ok(qdrant_response(serde_json::json!({
+        "points_count": collection.count(),
+        "fields": collection.payload_stats(params.top),
+    }), start_time))
+}
+
+/// Distance matrix over a sample of points
+#[derive(Deserialize, JsonSchema)]
+struct SearchMatrixRequest {
+    /// Number of points to sample
+    #[serde(default = "default_matrix_sample")]
+    sample: usize,
+    /// Neighbours kept per sampled point
+    #[serde(default = "default_matrix_limit")]
+    limit: usize,
+    #[serde(default)]
+    filter: Option<serde_json::Value>,
+}
+
+fn default_matrix_sample() -> usize { 10 }
+fn default_matrix_limit() -> usize { 3 }
+
+/// Shared part of the matrix endpoints: sample and score
+fn compute_search_matrix(
+    storage: &StorageManager,
+    name: &str,
+    req: &SearchMatrixRequest,
+    http_req: &HttpRequest,
+) -> Result<Option<vectx_core::DistanceMatrix>, ParseError> {
+    let Some(collection) = storage.get_collection(name) else {
+        return Ok(None);
+    };
+    let claim = claim_filter(http_req);
+    let filter = build_filter(req.filter.as_ref(), parse_mode(http_req), claim.as_deref(), Some(&collection))?;
+    Ok(Some(collection.distance_matrix(req.sample, req.limit, filter.as_deref())))
+}
+
+/// Sparse distance matrix as a list of scored pairs
+async fn search_matrix_pairs(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    req: Body<SearchMatrixRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = path.into_inner();
+
+    let matrix = match compute_search_matrix(&storage, &name, &req, &http_req) {
+        Ok(Some(matrix)) => matrix,
+        Ok(None) => return Ok(qdrant_not_found("Collection not found", start_time)),
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+    };
+
+    let pairs: Vec<serde_json::Value> = matrix.pairs()
+        .iter()
+        .map(|pair| serde_json::json!({
+            "a": point_id_to_json(&pair.a),
+            "b": point_id_to_json(&pair.b),
+            "score": pair.score
+        }))
+        .collect();
+
+    Ok(qdrant_response(serde_json::json!({
+        "pairs": pairs
+    }), start_time))
+}
+
+/// Sparse distance matrix in coordinate form, indexing into `ids`
+async fn search_matrix_offsets(
+    storage: web::Data<Arc<StorageManager>>,
+    path: web::Path<String>,
+    req: Body<SearchMatrixRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
+) -> ActixResult<HttpResponse> {
+    let start_time = Instant::now();
+    let name = path.into_inner();
+
+    let matrix = match compute_search_matrix(&storage, &name, &req, &http_req) {
+        Ok(Some(matrix)) => matrix,
+        Ok(None) => return Ok(qdrant_not_found("Collection not found", start_time)),
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+    };
+
+    let (offsets_row, offsets_col, scores) = matrix.offsets();
+    let ids: Vec<serde_json::Value> = matrix.ids.iter().map(point_id_to_json).collect();
+
+    Ok(qdrant_response(serde_json::json!({
+        "offsets_row": offsets_row,
+        "offsets_col": offsets_col,
+        "scores": scores,
+        "ids": ids
+    }), start_time))
+}
+
 /// Batch query
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct BatchQueryRequest {
-    searches: Vec<serde_json::Value>,
+    searches: Vec<QueryRequest>,
 }
 
 async fn batch_query(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    _req: web::Json<BatchQueryRequest>,
+    req: Body<BatchQueryRequest>,
+    http_req: HttpRequest,
+    params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
     
-    if storage.get_collection(&name).is_none() {
-        return Ok(qdrant_not_found("Collection not found", start_time));
-    }
+    let collection = match storage.get_collection(&name) {
+        Some(c) => c,
+        None => return Ok(qdrant_not_found("Collection not found", start_time)),
+    };
 
-    Ok(qdrant_response(Vec::<serde_json::Value>::new(), start_time))
+    let mut results = Vec::new();
+    for search in req.into_inner().searches {
+        let response = content::as_json(run_query(collection.clone(), search, &http_req, &params, start_time)).await?;
+        match batch_result(response).await {
+            Ok(result) => results.push(result),
+            Err(response) => return Ok(response),
+        }
+    }
+    Ok(qdrant_response(results, start_time))
 }
 
 /// Query points with grouping
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct QueryGroupsRequest {
     query: serde_json::Value,
     group_by: String,
@@ -3207,7 +6111,9 @@ struct QueryGroupsRequest {
 async fn query_groups(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<QueryGroupsRequest>,
+    req: Body<QueryGroupsRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -3224,6 +6130,11 @@ async fn query_groups(
     let with_payload = req.with_payload.unwrap_or(true);
     let with_vector = req.with_vector.unwrap_or(false);
     let group_by = &req.group_by;
+    let claim = claim_filter(&http_req);
+    let filter = match build_filter(req.filter.as_ref(), parse_mode(&http_req), claim.as_deref(), Some(&collection)) {
+        Ok(filter) => filter,
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
+    };
     
     // Parse query vector
     let query_vector = match &req.query {
@@ -3244,7 +6155,7 @@ async fn query_groups(
     };
     
     // Search for points
-    let search_results = collection.search(&query_vector, limit * group_size * 2, None);
+    let search_results = collection.search(&query_vector, limit * group_size * 2, filter.as_deref());
     
     // Group results by the group_by field
     let mut groups: std::collections::HashMap<String, Vec<serde_json::Value>> = std::collections::HashMap::new();
@@ -3274,7 +6185,7 @@ async fn query_groups(
                 hit["payload"] = point.payload.clone().unwrap_or(serde_json::Value::Null);
             }
             if with_vector {
-                hit["vector"] = serde_json::json!(point.vector.as_slice());
+                hit["vector"] = vector_json(&collection, &point.vector);
             }
             
             group.push(hit);
@@ -3304,7 +6215,7 @@ async fn query_groups(
 }
 
 /// Create field index
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct CreateIndexRequest {
     field_name: String,
     #[serde(default)]
@@ -3315,6 +6226,7 @@ async fn create_field_index(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
     req: web::Json<CreateIndexRequest>,
+    params: web::Query<UpdateParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
@@ -3357,12 +6269,26 @@ async fn create_field_index(
         vectx_core::PayloadIndexType::Keyword
     };
 
-    match collection.create_payload_index(&req.field_name, index_type) {
+    // Comparison options of a keyword index, e.g. {"type": "keyword", "lowercase": true}
+    let created = match (&req.field_schema, &index_type) {
+        (Some(schema @ serde_json::Value::Object(_)), vectx_core::PayloadIndexType::Keyword) => {
+            match serde_json::from_value::<vectx_core::KeywordParams>(schema.clone()) {
+                Ok(keyword_params) => collection.create_keyword_index(&req.field_name, keyword_params),
+                Err(e) => return Ok(qdrant_error(&format!("Invalid field_schema: {}", e), start_time)),
+            }
+        }
+        _ => collection.create_payload_index(&req.field_name, index_type),
+    };
+
+    match created {
         Ok(_) => {
-            let operation_id = collection.next_operation_id();
+            if let Err(e) = storage.save_collection_config(&collection) {
+                return Ok(qdrant_error(&e.to_string(), start_time));
+            }
+            let operation_id = collection.last_operation_id();
             Ok(qdrant_response(serde_json::json!({
                 "operation_id": operation_id,
-                "status": "acknowledged"
+                "status": params.status()
             }), start_time))
         }
         Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
@@ -3373,6 +6299,7 @@ async fn create_field_index(
 async fn delete_field_index(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<(String, String)>,
+    params: web::Query<UpdateParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let (name, field_name) = path.into_inner();
@@ -3383,11 +6310,16 @@ async fn delete_field_index(
     };
 
     match collection.delete_payload_index(&field_name) {
-        Ok(_) => {
-            let operation_id = collection.next_operation_id();
+        Ok(removed) => {
+            if removed {
+                if let Err(e) = storage.save_collection_config(&collection) {
+                    return Ok(qdrant_error(&e.to_string(), start_time));
+                }
+            }
+            let operation_id = collection.last_operation_id();
             Ok(qdrant_response(serde_json::json!({
                 "operation_id": operation_id,
-                "status": "acknowledged"
+                "status": params.status()
             }), start_time))
         }
         Err(e) => Ok(qdrant_error(&e.to_string(), start_time)),
@@ -3398,7 +6330,7 @@ async fn delete_field_index(
 /// Uses average vector strategy: query = 2*avg(positive) - avg(negative)
 /// This creates a single query vector that moves toward positive examples
 /// and away from negative examples, then performs one efficient search.
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct RecommendRequest {
     #[serde(default)]
     positive: Vec<serde_json::Value>,
@@ -3407,20 +6339,30 @@ struct RecommendRequest {
     #[serde(default)]
     limit: Option<usize>,
     #[serde(default)]
+    offset: Option<usize>,
+    #[serde(default)]
+    filter: Option<serde_json::Value>,
+    #[serde(default)]
     with_payload: Option<bool>,
     #[serde(default)]
     with_vector: Option<bool>,
     #[serde(default)]
     score_threshold: Option<f32>,
+    /// Recency boost replacing the collection's, or `false` to turn it off
+    #[serde(default)]
+    recency: Option<RecencyOverride>,
 }
 
 async fn recommend_points(
     storage: web::Data<Arc<StorageManager>>,
     path: web::Path<String>,
-    req: web::Json<RecommendRequest>,
+    req: Body<RecommendRequest>,
+    http_req: HttpRequest,
+    _params: web::Query<ReadParams>,
 ) -> ActixResult<HttpResponse> {
     let start_time = Instant::now();
     let name = path.into_inner();
+    let claim = claim_filter(&http_req);
     
     let collection = match storage.get_collection(&name) {
         Some(c) => c,
@@ -3429,131 +6371,54 @@ async fn recommend_points(
         }
     };
 
-    let limit = req.limit.unwrap_or(10);
     let with_payload = req.with_payload.unwrap_or(true);
     let with_vector = req.with_vector.unwrap_or(false);
-    let score_threshold = req.score_threshold;
-    
-    // Collect point IDs to exclude from results
-    let mut exclude_ids: std::collections::HashSet<String> = std::collections::HashSet::new();
-    
-    // Helper to parse point ID from JSON
-    let parse_id = |id: &serde_json::Value| -> Option<String> {
-        match id {
-            serde_json::Value::String(s) => Some(s.clone()),
-            serde_json::Value::Number(n) => Some(n.to_string()),
-            _ => None,
-        }
+    let filter = match parse_request_filter(req.filter.as_ref(), parse_mode(&http_req)) {
+        Ok(filter) => filter,
+        Err(e) => return Ok(qdrant_parse_error(&e, start_time)),
     };
+    let geo = filter.as_ref().and_then(FilterCondition::geo_origin);
     
-    // Collect positive vectors and compute average
-    let mut positive_vectors: Vec<Vec<f32>> = Vec::new();
-    for pos_id in &req.positive {
-        if let Some(id_str) = parse_id(pos_id) {
-            exclude_ids.insert(id_str.clone());
-            if let Some(point) = collection.get(&id_str) {
-                positive_vectors.push(point.vector.as_slice().to_vec());
-            }
-        }
-    }
-    
-    if positive_vectors.is_empty() {
-        return Ok(qdrant_error("At least one valid positive example is required", start_time));
-    }
-    
-    // Collect negative vectors and compute average
-    let mut negative_vectors: Vec<Vec<f32>> = Vec::new();
-    for neg_id in &req.negative {
-        if let Some(id_str) = parse_id(neg_id) {
-            exclude_ids.insert(id_str.clone());
-            if let Some(point) = collection.get(&id_str) {
-                negative_vectors.push(point.vector.as_slice().to_vec());
-            }
-        }
-    }
-    
-    // Compute average of positive vectors
-    let dim = positive_vectors[0].len();
-    let mut avg_positive = vec![0.0f32; dim];
-    for vec in &positive_vectors {
-        for (i, &val) in vec.iter().enumerate() {
-            if i < dim {
-                avg_positive[i] += val;
-            }
-        }
-    }
-    let pos_count = positive_vectors.len() as f32;
-    for val in &mut avg_positive {
-        *val /= pos_count;
-    }
-    
-    // Create query vector: if negatives exist, use formula 2*avg_pos - avg_neg
-    // This moves the query toward positives and away from negatives
-    let query_vector = if !negative_vectors.is_empty() {
-        let mut avg_negative = vec![0.0f32; dim];
-        for vec in &negative_vectors {
-            for (i, &val) in vec.iter().enumerate() {
-                if i < dim {
-                    avg_negative[i] += val;
-                }
-            }
-        }
-        let neg_count = negative_vectors.len() as f32;
-        for val in &mut avg_negative {
-            *val /= neg_count;
-        }
-        
-        // Combined query: 2*avg_pos - avg_neg
-        avg_positive.iter()
-            .zip(avg_negative.iter())
-            .map(|(&pos, &neg)| 2.0 * pos - neg)
-            .collect::<Vec<f32>>()
-    } else {
-        avg_positive
+    let example_ids = |ids: &[serde_json::Value]| -> Vec<String> {
+        ids.iter()
+            .filter_map(|id| match id {
+                serde_json::Value::String(s) => Some(s.clone()),
+                serde_json::Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect()
     };
-    
-    // Perform single search with combined query vector
-    let query = Vector::new(query_vector);
-    
-    // Request more results to account for excluded IDs
-    let search_limit = limit + exclude_ids.len();
-    let search_results = collection.search(&query, search_limit, None);
-    
-    // Build results, excluding input point IDs
-    let mut results = Vec::with_capacity(limit);
-    for (point, score) in search_results {
-        // Skip excluded points (the positive/negative examples)
-        let point_id_str = point.id.to_string();
-        if exclude_ids.contains(&point_id_str) {
-            continue;
-        }
-        
-        // Apply score threshold if provided
-        if let Some(threshold) = score_threshold {
-            if score < threshold {
-                continue;
+    let request = CoreRecommendRequest {
+        positive: example_ids(&req.positive),
+        negative: example_ids(&req.negative),
+        limit: req.limit,
+        offset: req.offset.unwrap_or(0),
+        score_threshold: req.score_threshold,
+        filter,
+        recency: req.recency.clone(),
+    };
+    let found = match core_requests::recommend(&collection, &request, claim.as_deref()) {
+        Ok(found) => found,
+        Err(e) => return Ok(qdrant_error(&e.to_string(), start_time)),
+    };
+
+    let results: Vec<serde_json::Value> = found.into_iter()
+        .map(|(point, score)| {
+            let mut result = serde_json::json!({
+                "id": point_id_to_json(&point.id),
+                "version": point.version,
+                "score": score
+            });
+            if with_payload {
+                result["payload"] = point.payload.clone().unwrap_or(serde_json::Value::Null);
             }
-        }
-        
-        let mut result = serde_json::json!({
-            "id": point_id_to_json(&point.id),
-            "version": point.version,
-            "score": score
-        });
-        
-        if with_payload {
-            result["payload"] = point.payload.clone().unwrap_or(serde_json::Value::Null);
-        }
-        if with_vector {
-            result["vector"] = serde_json::json!(point.vector.as_slice());
-        }
-        
-        results.push(result);
-        
-        if results.len() >= limit {
-            break;
-        }
-    }
+            add_geo_distance(&mut result, geo.as_ref(), &point);
+            if with_vector {
+                result["vector"] = vector_json(&collection, &point.vector);
+            }
+            result
+        })
+        .collect();
 
     Ok(qdrant_response(results, start_time))
 }
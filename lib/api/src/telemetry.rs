@@ -0,0 +1,248 @@
+//! Usage telemetry: the local report served at `GET /telemetry` and an
+//! optional phone-home
+//!
+//! The report follows the shape of Qdrant's telemetry (`id`, `app`,
+//! `collections`, `cluster`) so the bundled dashboard can show it. With
+//! `anonymize`, collection names are replaced by salted SHA-256 hashes and
+//! counts and memory sizes are rounded down to a power of ten. The salt is
+//! random per install and never reported, so names cannot be recovered by
+//! hashing guesses. Nothing leaves the node unless the server
+//! is started with a report URL; [`report_periodically`] then posts the
+//! anonymized report to it.
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use vectx_storage::StorageManager;
+
+/// File in the data directory holding the instance id
+const INSTANCE_ID_FILE: &str = "telemetry_id";
+
+/// File in the data directory holding the salt of anonymized names
+const NAME_SALT_FILE: &str = "telemetry_salt";
+
+/// Environment variable that turns reporting off whatever the command line
+/// says, e.g. for a whole fleet
+pub const DISABLE_ENV: &str = "VECTX_TELEMETRY_DISABLED";
+
+/// What the running binary was built and started with
+#[derive(Debug, Clone)]
+pub struct AppInfo {
+    /// Cargo features and runtime options, by name
+    pub features: BTreeMap<String, bool>,
+    pub started_at: DateTime<Utc>,
+}
+
+static APP_INFO: OnceLock<AppInfo> = OnceLock::new();
+static INSTANCE_ID: OnceLock<String> = OnceLock::new();
+static NAME_SALT: OnceLock<String> = OnceLock::new();
+
+/// Record the binary's features and start time; the first call wins. If
+/// it is never called, the report lists no features and dates the start
+/// from its first use.
+pub fn init(features: BTreeMap<String, bool>) {
+    let _ = APP_INFO.set(AppInfo { features, started_at: Utc::now() });
+}
+
+fn app_info() -> &'static AppInfo {
+    APP_INFO.get_or_init(|| AppInfo { features: BTreeMap::new(), started_at: Utc::now() })
+}
+
+/// Whether reporting was turned off through [`DISABLE_ENV`]
+pub fn disabled_by_env() -> bool {
+    std::env::var(DISABLE_ENV).is_ok_and(|v| !v.is_empty() && v != "0" && v != "false")
+}
+
+/// A random value kept in `path`, created on first use
+fn persisted_random(path: &Path) -> String {
+    if let Ok(value) = std::fs::read_to_string(path) {
+        if !value.trim().is_empty() {
+            return value.trim().to_string();
+        }
+    }
+    let value = uuid::Uuid::new_v4().to_string();
+    if let Err(e) = std::fs::write(path, &value) {
+        tracing::warn!("Failed to save telemetry state to {:?}: {}", path, e);
+    }
+    value
+}
+
+/// A random id kept in the data directory, so reports from one instance can
+/// be told apart across restarts without identifying it
+fn instance_id(storage: &StorageManager) -> &'static str {
+    INSTANCE_ID.get_or_init(|| persisted_random(&storage.data_dir().join(INSTANCE_ID_FILE)))
+}
+
+/// A random salt kept in the data directory, so a collection keeps its
+/// anonymized name across restarts
+fn name_salt(storage: &StorageManager) -> &'static str {
+    NAME_SALT.get_or_init(|| persisted_random(&storage.data_dir().join(NAME_SALT_FILE)))
+}
+
+/// How much the report tells
+#[derive(Debug, Clone, Copy)]
+pub struct TelemetryOptions {
+    /// Hash collection names and round counts
+    pub anonymize: bool,
+    /// 0 reports totals only; 1 and up lists each collection
+    pub details_level: usize,
+}
+
+impl Default for TelemetryOptions {
+    fn default() -> Self {
+        Self { anonymize: false, details_level: 1 }
+    }
+}
+
+fn anonymize_name(salt: &str, name: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(name.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// `count` rounded down to a power of ten
+fn anonymize_count(count: usize) -> usize {
+    if count < 10 { count } else { 10usize.pow(count.ilog10()) }
+}
+
+fn anonymize_memory(memory: vectx_core::MemoryStats) -> vectx_core::MemoryStats {
+    vectx_core::MemoryStats {
+        vectors_bytes: anonymize_count(memory.vectors_bytes),
+        payload_bytes: anonymize_count(memory.payload_bytes),
+        hnsw_bytes: anonymize_count(memory.hnsw_bytes),
+        bm25_bytes: anonymize_count(memory.bm25_bytes),
+    }
+}
+
+/// The telemetry report of the node. Configurations come from collection
+/// manifests, so unloaded collections are reported without being loaded;
+/// counts and memory are only known for loaded ones. Gathering them walks
+/// every loaded collection, so call this off the async runtime.
+pub fn telemetry_report(storage: &StorageManager, options: TelemetryOptions) -> serde_json::Value {
+    let app = app_info();
+    let count = |n: usize| if options.anonymize { anonymize_count(n) } else { n };
+    let memory = |m: vectx_core::MemoryStats| if options.anonymize { anonymize_memory(m) } else { m };
+
+    let mut names = storage.list_collections();
+    names.sort();
+    let mut total = vectx_core::MemoryStats::default();
+    let mut points_total = 0;
+    let mut collections = Vec::new();
+    for name in &names {
+        let Some(manifest) = storage.collection_manifest(name) else { continue };
+        let config = &manifest.config;
+        let mut sparse: Vec<&String> = config.sparse_vectors.keys().collect();
+        sparse.sort();
+        let mut multivectors: Vec<&String> = config.named_multivectors.keys().collect();
+        multivectors.sort();
+        let mut entry = serde_json::json!({
+            "id": if options.anonymize { anonymize_name(name_salt(storage), name) } else { name.clone() },
+            "loaded": false,
+            "config": {
+                "params": {
+                    "vectors": {"size": config.vector_dim, "distance": config.distance},
+                    "sparse_vectors": sparse.len(),
+                    "named_multivectors": multivectors.len(),
+                    "enable_bm25": config.enable_bm25,
+                    "detect_language": config.detect_language,
+                    "normalization": config.normalization
+                },
+                "hnsw_config": {
+                    "enabled": config.use_hnsw,
+                    "m": config.hnsw_config.m,
+                    "ef_construct": config.hnsw_config.ef_construct,
+                    "full_scan_threshold": config.hnsw_config.full_scan_threshold
+                }
+            }
+        });
+        if !options.anonymize {
+            entry["config"]["params"]["sparse_vectors"] = serde_json::json!(sparse);
+            entry["config"]["params"]["named_multivectors"] = serde_json::json!(multivectors);
+        }
+        if let Some(collection) = storage.loaded_collection(name) {
+            let stats = collection.stats();
+            total.vectors_bytes += stats.memory.vectors_bytes;
+            total.payload_bytes += stats.memory.payload_bytes;
+            total.hnsw_bytes += stats.memory.hnsw_bytes;
+            total.bm25_bytes += stats.memory.bm25_bytes;
+            points_total += stats.points_count;
+            entry["loaded"] = serde_json::json!(true);
+            entry["points_count"] = serde_json::json!(count(stats.points_count));
+            entry["vectors_count"] = serde_json::json!(count(stats.vectors_count));
+            entry["indexed_vectors_count"] = serde_json::json!(count(stats.indexed_vectors_count));
+            entry["hnsw_built_at"] = serde_json::json!(stats.hnsw_built_at);
+            entry["memory"] = serde_json::json!(memory(stats.memory));
+            entry["memory_total_bytes"] = serde_json::json!(count(stats.memory.total_bytes()));
+        }
+        collections.push(entry);
+    }
+    let loaded = collections.iter().filter(|c| c["loaded"] == true).count();
+
+    let mut features = app.features.clone();
+    features.insert("api_key_auth".to_string(), storage.access_control().is_enabled());
+    features.insert("check_invariants".to_string(), storage.check_invariants());
+    features.insert("stats_history".to_string(), storage.stats_interval().is_some());
+
+    let mut collections_json = serde_json::json!({
+        "number_of_collections": names.len(),
+        "loaded_collections": loaded,
+        "points_count": count(points_total)
+    });
+    if options.details_level > 0 {
+        collections_json["collections"] = serde_json::json!(collections);
+    }
+
+    serde_json::json!({
+        "id": instance_id(storage),
+        "app": {
+            "name": "vectx",
+            "version": env!("CARGO_PKG_VERSION"),
+            "features": features,
+            "system": {
+                "os": std::env::consts::OS,
+                "arch": std::env::consts::ARCH,
+                "cores": std::thread::available_parallelism().map_or(1, |n| n.get())
+            },
+            "startup": app.started_at.to_rfc3339(),
+            "jwt_rbac": storage.access_control().is_enabled()
+        },
+        "collections": collections_json,
+        "cluster": {"enabled": false},
+        "memory": memory(total),
+        "memory_total_bytes": count(total.total_bytes())
+    })
+}
+
+/// Post the anonymized report to `url` now and then every `interval`,
+/// until the runtime shuts down. Failures are logged and retried at the
+/// next interval.
+pub async fn report_periodically(storage: Arc<StorageManager>, url: String, interval: Duration) {
+    let client = reqwest::Client::new();
+    loop {
+        let reported = storage.clone();
+        let report = match tokio::task::spawn_blocking(move || {
+            telemetry_report(&reported, TelemetryOptions { anonymize: true, details_level: 1 })
+        }).await {
+            Ok(report) => report,
+            Err(e) => {
+                tracing::warn!("Failed to gather telemetry: {}", e);
+                tokio::time::sleep(interval).await;
+                continue;
+            }
+        };
+        let sent = client.post(&url)
+            .json(&report)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = sent {
+            tracing::warn!("Failed to send telemetry to {}: {}", url, e);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
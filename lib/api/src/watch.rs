@@ -0,0 +1,64 @@
+//! Change streams over a collection's operation journal
+//!
+//! Shared by the REST (SSE) and gRPC (server-streaming) watch endpoints.
+//! Each event carries a resume token; passing it back when reconnecting
+//! replays the retained journal entries from that point before going live.
+
+use futures_util::stream::{self, Stream, StreamExt};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use vectx_core::{Collection, JournalEntry, OperationJournal};
+
+/// Events buffered per subscriber. A subscriber that falls further behind is
+/// disconnected and has to resume from its last token.
+const WATCH_BUFFER: usize = 1024;
+
+/// Token that resumes a stream right after `entry`
+#[inline]
+pub fn resume_token(entry: &JournalEntry) -> u64 {
+    entry.op_id + 1
+}
+
+/// Stream the collection's changes.
+///
+/// With `resume_token`, retained entries from that operation id onward are
+/// replayed first; live entries follow without duplicates. The stream ends if
+/// the subscriber lags more than [`WATCH_BUFFER`] events behind.
+pub fn watch_collection(
+    collection: &Arc<Collection>,
+    resume_token: Option<u64>,
+) -> impl Stream<Item = JournalEntry> + Send + 'static {
+    let journal = collection.journal().clone();
+
+    // Subscribe before reading the backlog so nothing recorded in between is lost
+    let (tx, rx) = mpsc::channel(WATCH_BUFFER);
+    let id = journal.subscribe(Box::new(move |entry| tx.try_send(entry.clone()).is_ok()));
+
+    let backlog = match resume_token {
+        Some(token) => journal.since(Some(token), usize::MAX),
+        None => Vec::new(),
+    };
+    let replayed_up_to = backlog.last().map(|e| e.op_id);
+
+    let subscription = Subscription { journal, id };
+    let live = stream::unfold((rx, subscription), |(mut rx, subscription)| async move {
+        rx.recv().await.map(|entry| (entry, (rx, subscription)))
+    })
+    .filter(move |entry| {
+        futures_util::future::ready(replayed_up_to.map_or(true, |last| entry.op_id > last))
+    });
+
+    stream::iter(backlog).chain(live)
+}
+
+/// Unsubscribes from the journal when the stream is dropped (client went away)
+struct Subscription {
+    journal: Arc<OperationJournal>,
+    id: u64,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.journal.unsubscribe(self.id);
+    }
+}
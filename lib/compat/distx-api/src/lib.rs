@@ -0,0 +1,15 @@
+//! Deprecated: `distx-api` was renamed to
+//! [`vectx-api`](https://docs.rs/vectx-api).
+//!
+//! This crate re-exports the vectX REST and gRPC APIs so code written
+//! against `distx-api` keeps compiling. It gets no changes of its own;
+//! depend on `vectx-api` and replace `distx_api::` paths with
+//! `vectx_api::`. rustc does not warn about items used through a re-export,
+//! so the deprecation is only noted here and on crates.io.
+//!
+//! ```
+//! use distx_api::{GrpcApi, RestApi};
+//! ```
+
+#[deprecated(since = "0.2.7", note = "`distx-api` was renamed to `vectx-api`; use `vectx_api` instead")]
+pub use vectx_api::*;
@@ -0,0 +1,15 @@
+//! Deprecated: `distx-core` was renamed to
+//! [`vectx-core`](https://docs.rs/vectx-core).
+//!
+//! This crate re-exports the vectX core library so code written against
+//! `distx-core` keeps compiling. It gets no changes of its own; depend on
+//! `vectx-core` and replace `distx_core::` paths with `vectx_core::`. rustc
+//! does not warn about items used through a re-export, so the deprecation
+//! is only noted here and on crates.io.
+//!
+//! ```
+//! use distx_core::{Collection, CollectionConfig, Distance};
+//! ```
+
+#[deprecated(since = "0.2.7", note = "`distx-core` was renamed to `vectx-core`; use `vectx_core` instead")]
+pub use vectx_core::*;
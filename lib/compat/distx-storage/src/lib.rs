@@ -0,0 +1,15 @@
+//! Deprecated: `distx-storage` was renamed to
+//! [`vectx-storage`](https://docs.rs/vectx-storage).
+//!
+//! This crate re-exports the vectX storage layer so code written against
+//! `distx-storage` keeps compiling. It gets no changes of its own; depend
+//! on `vectx-storage` and replace `distx_storage::` paths with
+//! `vectx_storage::`. rustc does not warn about items used through a
+//! re-export, so the deprecation is only noted here and on crates.io.
+//!
+//! ```
+//! use distx_storage::StorageManager;
+//! ```
+
+#[deprecated(since = "0.2.7", note = "`distx-storage` was renamed to `vectx-storage`; use `vectx_storage` instead")]
+pub use vectx_storage::*;
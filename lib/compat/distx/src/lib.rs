@@ -0,0 +1,14 @@
+//! Deprecated: `distx` was renamed to [`vectx`](https://docs.rs/vectx).
+//!
+//! This crate re-exports the vectX library so code written against `distx`
+//! keeps compiling. It gets no changes of its own; depend on `vectx` and
+//! replace `distx::` paths with `vectx::`. rustc does not warn about items
+//! used through a re-export, so the deprecation is only noted here and on
+//! crates.io.
+//!
+//! ```
+//! use distx::prelude::*;
+//! ```
+
+#[deprecated(since = "0.2.7", note = "`distx` was renamed to `vectx`; use `vectx` instead")]
+pub use vectx::*;
@@ -0,0 +1,345 @@
+//! Aggregations over payload fields
+//!
+//! Counts, sums, averages, extremes and numeric histograms of payload
+//! fields, optionally split into groups by the values of a keyword field,
+//! computed in one pass over the matching points rather than by scrolling
+//! them out. Fields are read the way their payload index declares them:
+//! [`Collection::aggregate`](crate::Collection::aggregate) takes the group
+//! key only from a keyword, integer or bool index and metrics only from
+//! integer or float indexes. The elements of an array count as values of
+//! its key, so a point tagged `["a", "b"]` falls into both groups.
+//!
+//! Groups are tracked exactly up to [`MAX_GROUPS`] keys. Values that would
+//! open a further group are left out and the result is marked approximate.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{Point, Vector};
+
+/// Distinct group keys tracked per aggregation
+pub const MAX_GROUPS: usize = 10_000;
+
+/// Histogram bucket indexes are clamped to this magnitude, so the bounds of
+/// every bucket stay exact however extreme the values or small the interval
+const MAX_BUCKET: f64 = (1u64 << 52) as f64;
+
+/// One value computed per group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Metric {
+    /// Number of points in the group
+    Count,
+    Sum { field: String },
+    Avg { field: String },
+    Min { field: String },
+    Max { field: String },
+    /// Number of values per bucket of width `interval`; buckets start at
+    /// multiples of the interval and empty ones are left out
+    Histogram { field: String, interval: f64 },
+}
+
+impl Metric {
+    /// Payload field the metric reads, if any
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            Metric::Count => None,
+            Metric::Sum { field }
+            | Metric::Avg { field }
+            | Metric::Min { field }
+            | Metric::Max { field }
+            | Metric::Histogram { field, .. } => Some(field),
+        }
+    }
+}
+
+/// What to aggregate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AggregationRequest {
+    /// Field whose values split the points into groups; one group of all
+    /// points without it
+    #[serde(default)]
+    pub group_by: Option<String>,
+    /// Metrics by the name they are reported under
+    pub metrics: BTreeMap<String, Metric>,
+    /// Largest groups reported
+    #[serde(default = "default_group_limit")]
+    pub limit: usize,
+}
+
+fn default_group_limit() -> usize { 10 }
+
+/// Points whose values fall in `[from, to)`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramBucket {
+    pub from: f64,
+    pub to: f64,
+    pub count: usize,
+}
+
+/// Outcome of one metric in one group
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum MetricValue {
+    Count(usize),
+    /// None when the group has no value of the field
+    Number(Option<f64>),
+    Histogram(Vec<HistogramBucket>),
+}
+
+/// Metrics of the points sharing one group key
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AggregationGroup {
+    /// Value of the `group_by` field; absent without grouping
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<Value>,
+    /// Number of points in the group
+    pub count: usize,
+    pub metrics: BTreeMap<String, MetricValue>,
+}
+
+/// Groups of an aggregation, largest first
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AggregationResult {
+    pub groups: Vec<AggregationGroup>,
+    /// Number of groups before `limit` applied
+    pub total_groups: usize,
+    /// Whether some values were left out because of [`MAX_GROUPS`]
+    pub approximate: bool,
+}
+
+/// Aggregate `points` as `request` says. Fields are not checked against
+/// the payload indexes here.
+pub fn aggregate<'a>(points: impl IntoIterator<Item = &'a Point>, request: &AggregationRequest) -> AggregationResult {
+    let metrics: Vec<(&String, &Metric)> = request.metrics.iter().collect();
+    let mut groups: HashMap<String, GroupAccumulator> = HashMap::new();
+    let mut overflowed = false;
+
+    for point in points {
+        let keys = match &request.group_by {
+            Some(field) => {
+                let mut keys = Vec::new();
+                for_each_value(point, field, |value| {
+                    let scalar = matches!(value, Value::String(_) | Value::Number(_) | Value::Bool(_));
+                    if scalar && !keys.contains(value) {
+                        keys.push(value.clone());
+                    }
+                });
+                keys.into_iter().map(Some).collect()
+            }
+            None => vec![None],
+        };
+        for key in keys {
+            let id = key.as_ref().map_or_else(String::new, Value::to_string);
+            let tracked = groups.len();
+            let group = match groups.get_mut(&id) {
+                Some(group) => group,
+                None if tracked < MAX_GROUPS => groups.entry(id).or_insert_with(|| GroupAccumulator::new(key, metrics.len())),
+                None => {
+                    overflowed = true;
+                    continue;
+                }
+            };
+            group.add(point, &metrics);
+        }
+    }
+
+    let mut groups: Vec<GroupAccumulator> = groups.into_values().collect();
+    groups.sort_by(|a, b| {
+        b.count.cmp(&a.count).then_with(|| {
+            let key = |g: &GroupAccumulator| g.key.as_ref().map(Value::to_string);
+            key(a).cmp(&key(b))
+        })
+    });
+    let total_groups = groups.len();
+    groups.truncate(request.limit);
+
+    AggregationResult {
+        groups: groups.into_iter().map(|g| g.finish(&metrics)).collect(),
+        total_groups,
+        approximate: overflowed,
+    }
+}
+
+/// A copy of `point` holding only the payload fields `request` reads, so
+/// the values can be collected while the points are locked and aggregated
+/// after
+pub fn project(point: &Point, request: &AggregationRequest) -> Point {
+    let fields = request.group_by.iter()
+        .map(String::as_str)
+        .chain(request.metrics.values().filter_map(Metric::field))
+        .map(top_level);
+    let payload: serde_json::Map<String, Value> = fields
+        .filter_map(|field| Some((field.to_string(), point.payload.as_ref()?.get(field)?.clone())))
+        .collect();
+    Point::new(point.id.clone(), Vector::new(Vec::new()), Some(Value::Object(payload)))
+}
+
+fn top_level(field: &str) -> &str {
+    field.strip_prefix('.').unwrap_or(field)
+}
+
+/// Call `f` with each value of the top-level `field` of the point's
+/// payload, or with each element if it is an array
+fn for_each_value(point: &Point, field: &str, mut f: impl FnMut(&Value)) {
+    match point.payload.as_ref().and_then(|p| p.get(top_level(field))) {
+        Some(Value::Array(items)) => items.iter().for_each(f),
+        Some(value) => f(value),
+        None => {}
+    }
+}
+
+struct GroupAccumulator {
+    key: Option<Value>,
+    count: usize,
+    /// One per metric, in the order of the request's metrics
+    values: Vec<NumberAccumulator>,
+}
+
+#[derive(Default)]
+struct NumberAccumulator {
+    sum: f64,
+    n: usize,
+    min: Option<f64>,
+    max: Option<f64>,
+    /// Values per bucket index, for histograms
+    buckets: BTreeMap<i64, usize>,
+}
+
+impl GroupAccumulator {
+    fn new(key: Option<Value>, metrics: usize) -> Self {
+        Self {
+            key,
+            count: 0,
+            values: (0..metrics).map(|_| NumberAccumulator::default()).collect(),
+        }
+    }
+
+    fn add(&mut self, point: &Point, metrics: &[(&String, &Metric)]) {
+        self.count += 1;
+        for ((_, metric), acc) in metrics.iter().zip(self.values.iter_mut()) {
+            let Some(field) = metric.field() else { continue };
+            let interval = match metric {
+                Metric::Histogram { interval, .. } => Some(*interval),
+                _ => None,
+            };
+            for_each_value(point, field, |value| {
+                let Some(n) = value.as_f64() else { return };
+                acc.sum += n;
+                acc.n += 1;
+                acc.min = Some(acc.min.map_or(n, |m| m.min(n)));
+                acc.max = Some(acc.max.map_or(n, |m| m.max(n)));
+                if let Some(interval) = interval {
+                    let bucket = (n / interval).floor().clamp(-MAX_BUCKET, MAX_BUCKET);
+                    *acc.buckets.entry(bucket as i64).or_insert(0) += 1;
+                }
+            });
+        }
+    }
+
+    fn finish(self, metrics: &[(&String, &Metric)]) -> AggregationGroup {
+        let count = self.count;
+        let metrics = metrics.iter()
+            .zip(self.values)
+            .map(|((name, metric), acc)| {
+                let value = match metric {
+                    Metric::Count => MetricValue::Count(count),
+                    Metric::Sum { .. } => MetricValue::Number(Some(acc.sum)),
+                    Metric::Avg { .. } => MetricValue::Number((acc.n > 0).then(|| acc.sum / acc.n as f64)),
+                    Metric::Min { .. } => MetricValue::Number(acc.min),
+                    Metric::Max { .. } => MetricValue::Number(acc.max),
+                    Metric::Histogram { interval, .. } => MetricValue::Histogram(acc.buckets.into_iter()
+                        .map(|(bucket, count)| HistogramBucket {
+                            from: bucket as f64 * interval,
+                            to: (bucket + 1) as f64 * interval,
+                            count,
+                        })
+                        .collect()),
+                };
+                ((*name).clone(), value)
+            })
+            .collect();
+        AggregationGroup { key: self.key, count, metrics }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PointId, Vector};
+
+    fn point(id: u64, payload: Value) -> Point {
+        Point::new(PointId::Integer(id), Vector::new(vec![0.0]), Some(payload))
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let points = vec![
+            point(1, serde_json::json!({"city": "Rome", "price": 3})),
+            point(2, serde_json::json!({"city": "Rome", "price": 12.5})),
+            point(3, serde_json::json!({"city": ["Rome", "Oslo"], "price": [1, 7]})),
+            point(4, serde_json::json!({"city": "Oslo"})),
+            point(5, serde_json::json!({"price": 4})),
+        ];
+        let request: AggregationRequest = serde_json::from_value(serde_json::json!({
+            "group_by": "city",
+            "metrics": {
+                "points": {"type": "count"},
+                "total": {"type": "sum", "field": "price"},
+                "mean": {"type": "avg", "field": "price"},
+                "cheapest": {"type": "min", "field": "price"},
+                "prices": {"type": "histogram", "field": "price", "interval": 5.0}
+            }
+        })).unwrap();
+
+        let result = aggregate(&points, &request);
+        assert!(!result.approximate);
+        assert_eq!(result.total_groups, 2);
+        let rome = &result.groups[0];
+        assert_eq!(rome.key, Some(serde_json::json!("Rome")));
+        assert_eq!(rome.count, 3);
+        assert_eq!(rome.metrics["points"], MetricValue::Count(3));
+        assert_eq!(rome.metrics["total"], MetricValue::Number(Some(23.5)));
+        assert_eq!(rome.metrics["mean"], MetricValue::Number(Some(23.5 / 4.0)));
+        assert_eq!(rome.metrics["cheapest"], MetricValue::Number(Some(1.0)));
+        assert_eq!(rome.metrics["prices"], MetricValue::Histogram(vec![
+            HistogramBucket { from: 0.0, to: 5.0, count: 2 },
+            HistogramBucket { from: 5.0, to: 10.0, count: 1 },
+            HistogramBucket { from: 10.0, to: 15.0, count: 1 },
+        ]));
+        let oslo = &result.groups[1];
+        assert_eq!(oslo.count, 2);
+        assert_eq!(oslo.metrics["mean"], MetricValue::Number(Some(4.0)));
+
+        let ungrouped = AggregationRequest { group_by: None, limit: 1, ..request };
+        let result = aggregate(&points, &ungrouped);
+        assert_eq!(result.groups.len(), 1);
+        assert_eq!(result.groups[0].key, None);
+        assert_eq!(result.groups[0].count, 5);
+        assert_eq!(result.groups[0].metrics["total"], MetricValue::Number(Some(27.5)));
+
+        // Projected points aggregate the same
+        let projected: Vec<Point> = points.iter().map(|p| project(p, &ungrouped)).collect();
+        assert_eq!(aggregate(&projected, &ungrouped), result);
+    }
+
+    #[test]
+    fn test_histogram_extremes() {
+        let points = vec![
+            point(1, serde_json::json!({"x": f64::MAX})),
+            point(2, serde_json::json!({"x": -f64::MAX})),
+            point(3, serde_json::json!({"x": 1.0})),
+        ];
+        let request: AggregationRequest = serde_json::from_value(serde_json::json!({
+            "metrics": {"xs": {"type": "histogram", "field": "x", "interval": 1e-10}}
+        })).unwrap();
+        let result = aggregate(&points, &request);
+        let MetricValue::Histogram(buckets) = &result.groups[0].metrics["xs"] else { panic!() };
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[0].from, -MAX_BUCKET * 1e-10);
+        assert!(buckets.iter().all(|b| b.from < b.to && b.count == 1));
+    }
+}
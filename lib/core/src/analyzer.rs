@@ -0,0 +1,113 @@
+//! Query-time synonym and stopword dictionaries
+//!
+//! [`TextDictionaries`] are applied to BM25 queries and `match.text`
+//! filters when they run, not when documents are indexed, so replacing them
+//! takes effect immediately without reindexing. Stopwords are dropped from
+//! the query; every remaining word also matches the other members of its
+//! synonym groups. All entries are compared lowercase.
+
+use crate::BM25Index;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Synonym groups and stopwords of one collection
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextDictionaries {
+    /// Groups of interchangeable words, e.g. `["tv", "television"]`
+    #[serde(default)]
+    pub synonyms: Vec<Vec<String>>,
+    /// Words ignored in queries
+    #[serde(default)]
+    pub stopwords: BTreeSet<String>,
+}
+
+impl TextDictionaries {
+    /// Dictionaries with entries lowercased and single-word groups dropped
+    pub fn new(synonyms: Vec<Vec<String>>, stopwords: impl IntoIterator<Item = String>) -> Self {
+        let synonyms = synonyms.into_iter()
+            .map(|group| {
+                let mut words: Vec<String> = Vec::new();
+                for word in group.iter().map(|w| w.trim().to_lowercase()) {
+                    if !word.is_empty() && !words.contains(&word) {
+                        words.push(word);
+                    }
+                }
+                words
+            })
+            .filter(|group| group.len() > 1)
+            .collect();
+        let stopwords = stopwords.into_iter().map(|w| w.trim().to_lowercase()).filter(|w| !w.is_empty()).collect();
+        Self { synonyms, stopwords }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.synonyms.is_empty() && self.stopwords.is_empty()
+    }
+
+    /// A word followed by its synonyms, or nothing for a stopword
+    pub fn expand(&self, word: &str) -> Vec<String> {
+        let word = word.to_lowercase();
+        if self.stopwords.contains(&word) {
+            return Vec::new();
+        }
+        let mut alternatives = vec![word.clone()];
+        for group in self.synonyms.iter().filter(|g| g.contains(&word)) {
+            for synonym in group {
+                if !alternatives.contains(synonym) {
+                    alternatives.push(synonym.clone());
+                }
+            }
+        }
+        alternatives
+    }
+
+    /// BM25 terms of a query: its tokens without stopwords, plus synonyms
+    pub fn query_terms(&self, query: &str) -> Vec<String> {
+        let mut terms = Vec::new();
+        for token in BM25Index::tokenize(query) {
+            for alternative in self.expand(&token) {
+                // Multi-word synonyms contribute each of their tokens
+                for term in BM25Index::tokenize(&alternative) {
+                    if !terms.contains(&term) {
+                        terms.push(term);
+                    }
+                }
+            }
+        }
+        terms
+    }
+
+    /// Whether `text` contains any word of `query` or one of its synonyms,
+    /// ignoring case and stopwords
+    pub fn matches_text(&self, text: &str, query: &str) -> bool {
+        let text = text.to_lowercase();
+        query.split_whitespace()
+            .flat_map(|word| self.expand(word))
+            .any(|word| text.contains(&word))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_query_expansion() {
+        let dictionaries = TextDictionaries::new(
+            vec![words(&["TV", "television", "tv"]), words(&["laptop", "notebook computer"]), words(&["alone"])],
+            words(&["the", "a"]),
+        );
+        assert_eq!(dictionaries.synonyms.len(), 2);
+        assert_eq!(dictionaries.expand("Tv"), words(&["tv", "television"]));
+        assert!(dictionaries.expand("The").is_empty());
+
+        assert_eq!(dictionaries.query_terms("the laptop"), words(&["laptop", "notebook", "computer"]));
+        assert!(dictionaries.matches_text("A Television set", "the tv"));
+        assert!(!dictionaries.matches_text("the radio", "the tv"));
+        assert!(TextDictionaries::default().matches_text("the radio", "the tv"));
+    }
+}
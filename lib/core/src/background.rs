@@ -1,137 +1,437 @@
 // Background I/O system inspired by Redis's BIO (Background I/O)
-// Implements job queues with worker threads for async operations
+// Implements a job queue with worker threads for async operations
+//
+// Background work runs under a CPU budget so it never starves queries:
+// at most `max_threads` jobs run at once, their threads run at a lower OS
+// priority (on Linux), and queued jobs start by priority class, oldest
+// first within a class.
 
-use std::collections::VecDeque;
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BinaryHeap, HashMap};
 use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use serde::Serialize;
+
+use crate::cancel::CancellationToken;
 
 /// Background job types (inspired by Redis BIO)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BackgroundJobType {
     HnswRebuild = 0,  // HNSW index rebuild
     LazyFree = 1,     // Lazy memory freeing
+    Admin = 2,        // Admin jobs (clustering, deduplication, ...)
+}
+
+const JOB_TYPES: usize = 3;
+
+/// Order in which queued jobs start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    Low,
+    Normal,
+    High,
+}
+
+/// Share of the machine background jobs may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuBudget {
+    /// Jobs running at the same time
+    pub max_threads: usize,
+    /// Nice value of the worker threads (0 = same as queries, 19 = lowest);
+    /// only applied on Linux
+    pub nice: i32,
+}
+
+impl Default for CpuBudget {
+    /// Half of the cores at nice 10
+    fn default() -> Self {
+        let cores = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        Self { max_threads: (cores / 2).max(1), nice: 10 }
+    }
+}
+
+/// Work done so far by a running job, in job-specific units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct JobProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Handle a running job uses to report progress and notice cancellation
+#[derive(Debug, Clone, Default)]
+pub struct JobContext {
+    cancel: CancellationToken,
+    progress: Arc<Mutex<Option<JobProgress>>>,
+}
+
+impl JobContext {
+    /// Whether the job was asked to stop. Jobs check this between steps and
+    /// return early, leaving things as they were.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Token cancelled along with the job, for scans that take one
+    pub fn cancel_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    pub fn set_progress(&self, done: usize, total: usize) {
+        *self.progress.lock().unwrap() = Some(JobProgress { done, total });
+    }
+
+    fn progress(&self) -> Option<JobProgress> {
+        *self.progress.lock().unwrap()
+    }
 }
 
 /// Background job trait
 pub trait BackgroundJob: Send + 'static {
-    fn execute(self: Box<Self>);
+    fn execute(self: Box<Self>, ctx: &JobContext);
     fn job_type(&self) -> BackgroundJobType;
+
+    fn priority(&self) -> JobPriority {
+        JobPriority::Normal
+    }
+
+    /// Collection the job works on, if any
+    fn collection(&self) -> Option<&str> {
+        None
+    }
+
+    /// Called instead of `execute` when the job is cancelled before it
+    /// starts
+    fn cancelled(self: Box<Self>) {}
 }
 
-/// Background worker thread
-struct BackgroundWorker {
-    #[allow(dead_code)]
-    worker_id: usize,
-    #[allow(dead_code)]
-    job_type: BackgroundJobType,
-    jobs: Arc<Mutex<VecDeque<Box<dyn BackgroundJob>>>>,
-    condvar: Arc<Condvar>,
-    running: Arc<AtomicBool>,
+/// Where a tracked job is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    /// Running, and asked to stop at its next check
+    Cancelling,
 }
 
-impl BackgroundWorker {
-    fn new(worker_id: usize, job_type: BackgroundJobType) -> Self {
-        Self {
-            worker_id,
-            job_type,
-            jobs: Arc::new(Mutex::new(VecDeque::new())),
-            condvar: Arc::new(Condvar::new()),
-            running: Arc::new(AtomicBool::new(true)),
-        }
+/// A queued or running job, as listed by [`BackgroundJobSystem::jobs`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JobSnapshot {
+    pub id: u64,
+    pub job_type: BackgroundJobType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    pub priority: JobPriority,
+    pub state: JobState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<JobProgress>,
+    /// Milliseconds since the Unix epoch
+    pub submitted_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+}
+
+/// Bookkeeping of a job between submission and completion
+struct TrackedJob {
+    snapshot: JobSnapshot,
+    context: JobContext,
+}
+
+/// A job waiting in the queue
+struct QueuedJob {
+    priority: JobPriority,
+    /// Submission order, to keep FIFO order within a priority class; also
+    /// the job's id
+    seq: u64,
+    job: Box<dyn BackgroundJob>,
+}
+
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
     }
+}
 
-    fn submit(&self, job: Box<dyn BackgroundJob>) {
-        let mut jobs = self.jobs.lock().unwrap();
-        jobs.push_back(job);
-        self.condvar.notify_one();
+impl Eq for QueuedJob {}
+
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
     }
+}
 
-    fn pending_jobs(&self) -> usize {
-        self.jobs.lock().unwrap().len()
+impl Ord for QueuedJob {
+    /// Highest priority first, then oldest first
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.priority.cmp(&other.priority).then_with(|| other.seq.cmp(&self.seq))
     }
+}
+
+/// Queue shared by the worker threads. Lock order: `jobs`, then `tracked`.
+struct JobQueue {
+    jobs: Mutex<BinaryHeap<QueuedJob>>,
+    condvar: Condvar,
+    running: AtomicBool,
+    next_seq: AtomicU64,
+    pending: [AtomicUsize; JOB_TYPES],
+    tracked: Mutex<HashMap<u64, TrackedJob>>,
+    /// Live worker threads
+    workers: AtomicUsize,
+    /// Worker threads wanted; idle workers above it exit
+    max_threads: AtomicUsize,
+    next_worker_id: AtomicUsize,
+    nice: i32,
+}
 
-    fn shutdown(&self) {
-        self.running.store(false, Ordering::Release);
-        self.condvar.notify_all();
+impl JobQueue {
+    /// Let the calling worker exit if there are more workers than wanted
+    fn retire_worker(&self) -> bool {
+        self.workers
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |workers| {
+                (workers > self.max_threads.load(Ordering::Acquire)).then(|| workers - 1)
+            })
+            .is_ok()
+    }
+
+    /// Mark a job just taken off the queue as running. Called with `jobs`
+    /// locked, so a cancel cannot drop it in between; None if it is no
+    /// longer tracked.
+    fn start(&self, id: u64) -> Option<JobContext> {
+        let mut tracked = self.tracked.lock().unwrap();
+        let job = tracked.get_mut(&id)?;
+        job.snapshot.state = JobState::Running;
+        job.snapshot.started_at = Some(crate::journal::now_millis());
+        Some(job.context.clone())
     }
 }
 
+/// Runs its closure if dropped while the thread panics, so a job that
+/// panics still resets the flags it set
+struct OnPanic<F: FnMut()>(F);
+
+impl<F: FnMut()> Drop for OnPanic<F> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            (self.0)();
+        }
+    }
+}
+
+fn spawn_worker(queue: Arc<JobQueue>) {
+    let worker_id = queue.next_worker_id.fetch_add(1, Ordering::Relaxed);
+    // Workers exit on shutdown or when the pool shrinks; their handles are
+    // not kept
+    thread::Builder::new()
+        .name(format!("bg-worker-{}", worker_id))
+        .spawn(move || {
+            lower_thread_priority(queue.nice);
+            loop {
+                let mut jobs = queue.jobs.lock().unwrap();
+
+                // Wait for jobs, a smaller pool or the shutdown signal
+                loop {
+                    if queue.retire_worker() {
+                        return;
+                    }
+                    if !jobs.is_empty() || !queue.running.load(Ordering::Acquire) {
+                        break;
+                    }
+                    jobs = queue.condvar.wait(jobs).unwrap();
+                }
+
+                // Drain remaining jobs before shutting down
+                let Some(queued) = jobs.pop() else { break };
+                let ctx = queue.start(queued.seq);
+                drop(jobs); // Release lock before executing
+                queue.pending[queued.job.job_type() as usize].fetch_sub(1, Ordering::Relaxed);
+                let Some(ctx) = ctx else {
+                    queued.job.cancelled();
+                    continue;
+                };
+                // A panicking job must not take the worker, or its entry in
+                // the job list, with it
+                let job = queued.job;
+                if std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| job.execute(&ctx))).is_err() {
+                    tracing::error!("Background job {} panicked", queued.seq);
+                }
+                queue.tracked.lock().unwrap().remove(&queued.seq);
+            }
+            queue.workers.fetch_sub(1, Ordering::AcqRel);
+        })
+        .expect("Failed to spawn background worker thread");
+}
+
 /// Background job system (Redis-style BIO)
 pub struct BackgroundJobSystem {
-    workers: Vec<Arc<BackgroundWorker>>,
-    job_counters: Arc<[AtomicU64; 2]>, // One counter per job type
+    queue: Arc<JobQueue>,
+    job_counters: Arc<[AtomicU64; JOB_TYPES]>, // One counter per job type
 }
 
 impl BackgroundJobSystem {
-    /// Create a new background job system
+    /// Create a new background job system with the default budget
     pub fn new() -> Self {
-        let mut workers = Vec::new();
-        let mut handles = Vec::new();
-
-        // Create workers for each job type
-        for job_type in [BackgroundJobType::HnswRebuild, BackgroundJobType::LazyFree] {
-            let worker = BackgroundWorker::new(0, job_type);
-            let worker_arc = Arc::new(worker);
-            let worker_for_thread = worker_arc.clone();
-            let handle = thread::Builder::new()
-                .name(format!("bg-worker-{:?}-0", job_type))
-                .spawn(move || {
-                    let jobs = worker_for_thread.jobs.clone();
-                    let condvar = worker_for_thread.condvar.clone();
-                    let running = worker_for_thread.running.clone();
-                    
-                    loop {
-                        let mut jobs_guard = jobs.lock().unwrap();
-                        
-                        // Wait for jobs or shutdown signal
-                        while jobs_guard.is_empty() && running.load(Ordering::Acquire) {
-                            jobs_guard = condvar.wait(jobs_guard).unwrap();
-                        }
-
-                        // Check if we should shutdown
-                        if !running.load(Ordering::Acquire) && jobs_guard.is_empty() {
-                            break;
-                        }
-
-                        // Process jobs in FIFO order
-                        while let Some(job) = jobs_guard.pop_front() {
-                            drop(jobs_guard); // Release lock before executing
-                            job.execute();
-                            jobs_guard = jobs.lock().unwrap();
-                        }
-                    }
-                })
-                .expect("Failed to spawn background worker thread");
-            
-            handles.push(handle);
-            workers.push(worker_arc);
-        }
+        Self::with_budget(CpuBudget::default())
+    }
 
-        // Don't wait for handles - let them run in background
-        std::mem::forget(handles);
+    /// Create a background job system running at most
+    /// `budget.max_threads` jobs at once
+    pub fn with_budget(budget: CpuBudget) -> Self {
+        let max_threads = budget.max_threads.max(1);
+        let queue = Arc::new(JobQueue {
+            jobs: Mutex::new(BinaryHeap::new()),
+            condvar: Condvar::new(),
+            running: AtomicBool::new(true),
+            next_seq: AtomicU64::new(0),
+            pending: Default::default(),
+            tracked: Mutex::new(HashMap::new()),
+            workers: AtomicUsize::new(max_threads),
+            max_threads: AtomicUsize::new(max_threads),
+            next_worker_id: AtomicUsize::new(0),
+            nice: budget.nice,
+        });
+
+        for _ in 0..max_threads {
+            spawn_worker(queue.clone());
+        }
 
         Self {
-            workers,
-            job_counters: Arc::new([
-                AtomicU64::new(0), // HnswRebuild
-                AtomicU64::new(0), // LazyFree
-            ]),
+            queue,
+            job_counters: Arc::new(Default::default()),
         }
     }
 
-    /// Submit a background job
-    pub fn submit(&self, job: Box<dyn BackgroundJob>) {
+    /// Submit a background job. Returns its id.
+    pub fn submit(&self, job: Box<dyn BackgroundJob>) -> u64 {
         let job_type = job.job_type();
-        let worker = &self.workers[job_type as usize];
-        
         self.job_counters[job_type as usize].fetch_add(1, Ordering::Relaxed);
-        worker.submit(job);
+        self.queue.pending[job_type as usize].fetch_add(1, Ordering::Relaxed);
+
+        let seq = self.queue.next_seq.fetch_add(1, Ordering::Relaxed);
+        let snapshot = JobSnapshot {
+            id: seq,
+            job_type,
+            collection: job.collection().map(str::to_string),
+            priority: job.priority(),
+            state: JobState::Queued,
+            progress: None,
+            submitted_at: crate::journal::now_millis(),
+            started_at: None,
+        };
+        // Tracked before a worker can pick it up
+        self.queue.tracked.lock().unwrap().insert(seq, TrackedJob { snapshot, context: JobContext::default() });
+
+        let queued = QueuedJob {
+            priority: job.priority(),
+            seq,
+            job,
+        };
+        self.queue.jobs.lock().unwrap().push(queued);
+        self.queue.condvar.notify_one();
+        seq
+    }
+
+    /// Run a closure as a job of the given type and priority
+    pub fn spawn<F>(&self, job_type: BackgroundJobType, priority: JobPriority, work: F) -> u64
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.spawn_job(job_type, priority, None, move |_| work())
+    }
+
+    /// Run a closure on `collection` as a job; it gets the job's context to
+    /// report progress and check for cancellation
+    pub fn spawn_job<F>(&self, job_type: BackgroundJobType, priority: JobPriority, collection: Option<&str>, work: F) -> u64
+    where
+        F: FnOnce(&JobContext) + Send + 'static,
+    {
+        self.submit(Box::new(TaskJob {
+            work: Box::new(work),
+            job_type,
+            priority,
+            collection: collection.map(str::to_string),
+        }))
+    }
+
+    /// Queued and running jobs, running ones first, each group in
+    /// submission order
+    pub fn jobs(&self) -> Vec<JobSnapshot> {
+        let mut jobs: Vec<JobSnapshot> = self.queue.tracked.lock().unwrap()
+            .values()
+            .map(|job| JobSnapshot { progress: job.context.progress(), ..job.snapshot.clone() })
+            .collect();
+        jobs.sort_by_key(|job| (job.state == JobState::Queued, job.id));
+        jobs
+    }
+
+    pub fn job(&self, id: u64) -> Option<JobSnapshot> {
+        self.jobs().into_iter().find(|job| job.id == id)
+    }
+
+    /// Cancel a job. A queued job is dropped; a running one is asked to stop
+    /// and does so at its next check. Returns false if no such job is queued
+    /// or running.
+    pub fn cancel(&self, id: u64) -> bool {
+        let mut jobs = self.queue.jobs.lock().unwrap();
+        let mut tracked = self.queue.tracked.lock().unwrap();
+        let Some(job) = tracked.get_mut(&id) else { return false };
+        if job.snapshot.state != JobState::Queued {
+            job.snapshot.state = JobState::Cancelling;
+            job.context.cancel.cancel();
+            return true;
+        }
+
+        tracked.remove(&id);
+        drop(tracked);
+        let (removed, kept): (Vec<QueuedJob>, Vec<QueuedJob>) =
+            std::mem::take(&mut *jobs).into_vec().into_iter().partition(|queued| queued.seq == id);
+        *jobs = kept.into();
+        drop(jobs);
+        for queued in removed {
+            self.queue.pending[queued.job.job_type() as usize].fetch_sub(1, Ordering::Relaxed);
+            queued.job.cancelled();
+        }
+        true
+    }
+
+    /// The budget of the workers; `max_threads` follows
+    /// [`BackgroundJobSystem::set_max_threads`]
+    pub fn budget(&self) -> CpuBudget {
+        CpuBudget {
+            max_threads: self.queue.max_threads.load(Ordering::Acquire),
+            nice: self.queue.nice,
+        }
+    }
+
+    /// Run at most `max_threads` jobs at once (at least 1). Extra workers
+    /// start right away; surplus ones exit once their current job is done.
+    pub fn set_max_threads(&self, max_threads: usize) {
+        let max_threads = max_threads.max(1);
+        let _jobs = self.queue.jobs.lock().unwrap();
+        self.queue.max_threads.store(max_threads, Ordering::Release);
+        let workers = self.queue.workers.load(Ordering::Acquire);
+        for _ in workers..max_threads {
+            self.queue.workers.fetch_add(1, Ordering::AcqRel);
+            spawn_worker(self.queue.clone());
+        }
+        // Wake idle workers so surplus ones exit
+        self.queue.condvar.notify_all();
+    }
+
+    /// Jobs running right now
+    pub fn running_jobs(&self) -> usize {
+        self.queue.tracked.lock().unwrap()
+            .values()
+            .filter(|job| job.snapshot.state != JobState::Queued)
+            .count()
     }
 
     /// Get pending jobs count for a job type
     pub fn pending_jobs(&self, job_type: BackgroundJobType) -> usize {
-        self.workers[job_type as usize].pending_jobs()
+        self.queue.pending[job_type as usize].load(Ordering::Relaxed)
     }
 
     /// Get total jobs processed for a job type
@@ -139,11 +439,10 @@ impl BackgroundJobSystem {
         self.job_counters[job_type as usize].load(Ordering::Relaxed)
     }
 
-    /// Shutdown all workers
+    /// Shutdown all workers once the queue is drained
     pub fn shutdown(&self) {
-        for worker in &self.workers {
-            worker.shutdown();
-        }
+        self.queue.running.store(false, Ordering::Release);
+        self.queue.condvar.notify_all();
     }
 }
 
@@ -153,52 +452,176 @@ impl Default for BackgroundJobSystem {
     }
 }
 
-/// HNSW rebuild job
-pub struct HnswRebuildJob {
+/// Make the calling thread yield to normal-priority threads. On Linux
+/// `setpriority` with `who = 0` applies to the calling thread only.
+#[cfg(target_os = "linux")]
+fn lower_thread_priority(nice: i32) {
+    if nice > 0 {
+        // Best effort: a failure leaves the thread at normal priority
+        unsafe {
+            libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lower_thread_priority(_nice: i32) {}
+
+/// A closure run as a background job
+struct TaskJob {
+    work: Box<dyn FnOnce(&JobContext) + Send>,
+    job_type: BackgroundJobType,
+    priority: JobPriority,
+    collection: Option<String>,
+}
+
+impl BackgroundJob for TaskJob {
+    fn execute(self: Box<Self>, ctx: &JobContext) {
+        (self.work)(ctx)
+    }
+
+    fn job_type(&self) -> BackgroundJobType {
+        self.job_type
+    }
+
+    fn priority(&self) -> JobPriority {
+        self.priority
+    }
+
+    fn collection(&self) -> Option<&str> {
+        self.collection.as_deref()
+    }
+}
+
+/// Points linked between progress updates and cancellation checks
+const REBUILD_CHUNK: usize = 1_000;
+
+/// Link `points` into the empty graph `new_index` chunk by chunk, reporting
+/// progress to `ctx` and `on_progress`. None if the job is cancelled before
+/// the end.
+fn build_graph(
+    mut new_index: crate::HnswIndex,
     points: Vec<crate::Point>,
-    hnsw: Arc<parking_lot::RwLock<crate::HnswIndex>>,
-    built_flag: Arc<parking_lot::RwLock<bool>>,
-    rebuilding_flag: Arc<AtomicBool>,
+    ctx: &JobContext,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Option<crate::HnswIndex> {
+    let total = points.len();
+    let mut points = points.into_iter().peekable();
+    let mut indexed = 0;
+    while points.peek().is_some() {
+        if ctx.is_cancelled() {
+            return None;
+        }
+        let chunk: Vec<crate::Point> = points.by_ref().take(REBUILD_CHUNK).collect();
+        indexed += chunk.len();
+        // Points of another dimension cannot be linked and are left out
+        let _ = new_index.insert_batch(chunk);
+        ctx.set_progress(indexed, total);
+        on_progress(indexed, total);
+    }
+    Some(new_index)
+}
+
+/// Why the HNSW graph is rebuilt, which decides what a rebuild that stops
+/// early leaves behind
+pub enum RebuildReason {
+    /// Vectors the graph links have changed since (a relinking vector
+    /// update). Stopping has the next search build the graph again.
+    Relink,
+    /// Vectors are compared differently, by a new distance or HNSW
+    /// parameters. Progress is reported in the given slot, and stopping
+    /// keeps the old graph.
+    Reindex(Arc<parking_lot::RwLock<Option<crate::ReindexProgress>>>),
+}
+
+/// Rebuild of the HNSW graph from a copy of the points. Points are linked
+/// in chunks, and the writes made meanwhile are linked before the new graph
+/// is swapped in.
+pub struct HnswRebuildJob {
+    pub(crate) collection: String,
+    /// Metric the new graph links by
+    pub(crate) distance: crate::Distance,
+    pub(crate) points: Vec<crate::Point>,
+    pub(crate) hnsw: Arc<parking_lot::RwLock<crate::HnswIndex>>,
+    pub(crate) built_flag: Arc<parking_lot::RwLock<bool>>,
+    /// Set to the completion time (ms since the Unix epoch)
+    pub(crate) built_at: Arc<AtomicU64>,
+    pub(crate) rebuilding_flag: Arc<AtomicBool>,
+    /// Points written since `points` were copied
+    pub(crate) backlog: Arc<crate::RebuildBacklog>,
+    pub(crate) reason: RebuildReason,
 }
 
 impl HnswRebuildJob {
-    pub fn new(
-        points: Vec<crate::Point>,
-        hnsw: Arc<parking_lot::RwLock<crate::HnswIndex>>,
-        built_flag: Arc<parking_lot::RwLock<bool>>,
-        rebuilding_flag: Arc<AtomicBool>,
-    ) -> Self {
-        Self {
-            points,
-            hnsw,
-            built_flag,
-            rebuilding_flag,
+    fn done(&self) {
+        if let RebuildReason::Reindex(progress) = &self.reason {
+            *progress.write() = None;
         }
+        self.rebuilding_flag.store(false, Ordering::Release);
+    }
+
+    /// Give up the rebuild, without the writes made meanwhile
+    fn stop(&self) {
+        self.backlog.discard(|| {
+            if let RebuildReason::Relink = self.reason {
+                // The current graph misses the changes this job was to
+                // link, so have the next search build it again
+                *self.built_flag.write() = false;
+            }
+            self.done();
+        });
     }
 }
 
 impl BackgroundJob for HnswRebuildJob {
-    fn execute(self: Box<Self>) {
-        // Rebuild HNSW index from all points
-        let mut new_index = crate::HnswIndex::new(16, 3);
-        for point in self.points {
-            new_index.insert(point);
-        }
+    fn execute(mut self: Box<Self>, ctx: &JobContext) {
+        let points = std::mem::take(&mut self.points);
+        let _reset = OnPanic(|| self.stop());
+        // Not read-locked while it is built, so writes go on
+        let empty = self.hnsw.read().empty_like().with_distance(self.distance);
+        let new_index = build_graph(empty, points, ctx, |indexed, total| {
+            if let RebuildReason::Reindex(progress) = &self.reason {
+                *progress.write() = Some(crate::ReindexProgress { indexed, total });
+            }
+        });
 
-        // Swap in the new index
-        *self.hnsw.write() = new_index;
-        *self.built_flag.write() = true;
-        self.rebuilding_flag.store(false, Ordering::Release);
+        match new_index {
+            Some(new_index) => self.backlog.swap_in(new_index, &self.hnsw, || {
+                *self.built_flag.write() = true;
+                self.built_at.store(crate::journal::now_millis(), Ordering::Release);
+                self.done();
+            }),
+            None => self.stop(),
+        }
     }
 
     fn job_type(&self) -> BackgroundJobType {
         BackgroundJobType::HnswRebuild
     }
+
+    fn collection(&self) -> Option<&str> {
+        Some(&self.collection)
+    }
+
+    fn cancelled(self: Box<Self>) {
+        self.stop();
+    }
 }
 
 /// Global background job system (initialized on first use)
 static BACKGROUND_SYSTEM: std::sync::OnceLock<Arc<BackgroundJobSystem>> = std::sync::OnceLock::new();
 
+/// Start the global background job system with `budget`. Returns false if
+/// it is already running, in which case its budget is unchanged.
+pub fn configure_background_system(budget: CpuBudget) -> bool {
+    let mut configured = false;
+    BACKGROUND_SYSTEM.get_or_init(|| {
+        configured = true;
+        Arc::new(BackgroundJobSystem::with_budget(budget))
+    });
+    configured
+}
+
 /// Get the global background job system
 pub fn get_background_system() -> Arc<BackgroundJobSystem> {
     BACKGROUND_SYSTEM.get_or_init(|| {
@@ -206,3 +629,102 @@ pub fn get_background_system() -> Arc<BackgroundJobSystem> {
     }).clone()
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_priority_order() {
+        let system = BackgroundJobSystem::with_budget(CpuBudget { max_threads: 1, nice: 0 });
+        let (release, blocked) = mpsc::channel::<()>();
+        let (started, running) = mpsc::channel::<()>();
+        // Occupy the only worker while the other jobs queue up
+        system.spawn(BackgroundJobType::Admin, JobPriority::Normal, move || {
+            started.send(()).unwrap();
+            blocked.recv().unwrap();
+        });
+        running.recv().unwrap();
+
+        let (done, order) = mpsc::channel();
+        for (name, priority) in [("low", JobPriority::Low), ("high", JobPriority::High), ("normal", JobPriority::Normal), ("high2", JobPriority::High)] {
+            let done = done.clone();
+            system.spawn(BackgroundJobType::Admin, priority, move || done.send(name).unwrap());
+        }
+        assert_eq!(system.pending_jobs(BackgroundJobType::Admin), 4);
+        release.send(()).unwrap();
+
+        let order: Vec<&str> = order.iter().take(4).collect();
+        assert_eq!(order, vec!["high", "high2", "normal", "low"]);
+        assert_eq!(system.pending_jobs(BackgroundJobType::Admin), 0);
+        assert_eq!(system.jobs_processed(BackgroundJobType::Admin), 5);
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_cancel_and_resize() {
+        let system = BackgroundJobSystem::with_budget(CpuBudget { max_threads: 1, nice: 0 });
+        let (started, running) = mpsc::channel::<()>();
+        let (stopped, stop) = mpsc::channel::<()>();
+        let blocker = system.spawn_job(BackgroundJobType::Admin, JobPriority::Normal, Some("docs"), move |ctx| {
+            ctx.set_progress(1, 2);
+            started.send(()).unwrap();
+            while !ctx.is_cancelled() {
+                thread::sleep(std::time::Duration::from_millis(1));
+            }
+            stopped.send(()).unwrap();
+        });
+        running.recv().unwrap();
+        let (done, finished) = mpsc::channel::<()>();
+        let queued = system.spawn(BackgroundJobType::Admin, JobPriority::Low, move || done.send(()).unwrap());
+
+        let jobs = system.jobs();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!((jobs[0].id, jobs[0].state), (blocker, JobState::Running));
+        assert_eq!(jobs[0].collection.as_deref(), Some("docs"));
+        assert_eq!(jobs[0].progress, Some(JobProgress { done: 1, total: 2 }));
+        assert!(jobs[0].started_at.is_some());
+        assert_eq!((jobs[1].id, jobs[1].state), (queued, JobState::Queued));
+
+        // A queued job is dropped, a running one stops at its next check
+        assert!(system.cancel(queued));
+        assert_eq!(system.pending_jobs(BackgroundJobType::Admin), 0);
+        assert!(system.cancel(blocker));
+        stop.recv().unwrap();
+        assert!(finished.try_recv().is_err());
+        assert!(!system.cancel(queued));
+
+        // Two jobs waiting on each other only finish with two workers
+        system.set_max_threads(2);
+        assert_eq!(system.budget().max_threads, 2);
+        let barrier = Arc::new(std::sync::Barrier::new(3));
+        for _ in 0..2 {
+            let barrier = barrier.clone();
+            system.spawn(BackgroundJobType::Admin, JobPriority::Normal, move || {
+                barrier.wait();
+            });
+        }
+        barrier.wait();
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_panicking_job() {
+        let system = BackgroundJobSystem::with_budget(CpuBudget { max_threads: 1, nice: 0 });
+        let flag = Arc::new(AtomicBool::new(true));
+        let job_flag = flag.clone();
+        let failing = system.spawn(BackgroundJobType::Admin, JobPriority::Normal, move || {
+            let _reset = OnPanic(|| job_flag.store(false, Ordering::Release));
+            panic!("job failed");
+        });
+
+        // The worker survives to run the next job, and the first is gone
+        let (done, finished) = mpsc::channel::<()>();
+        system.spawn(BackgroundJobType::Admin, JobPriority::Normal, move || done.send(()).unwrap());
+        finished.recv().unwrap();
+        assert!(!flag.load(Ordering::Acquire));
+        assert!(system.job(failing).is_none());
+        system.shutdown();
+    }
+}
@@ -0,0 +1,124 @@
+//! Bit-packed binary vectors
+//!
+//! Collections with [`Distance::Hamming`](crate::Distance::Hamming) hold
+//! binary embeddings such as 1024-bit hashes. Their `vector_dim` counts
+//! bits, and each stored [`Vector`] packs [`BITS_PER_COMPONENT`] of them
+//! into the bit pattern of every component, so the point store, the WAL
+//! and snapshots carry them as they are, at 1/32 of the size of a float
+//! per bit. The components are not meaningful as numbers: they are only
+//! compared with [`hamming_distance`], which counts differing bits with
+//! the popcount kernels in [`simd`](crate::simd).
+//!
+//! Clients send and receive binary vectors as bytes, first bit in the
+//! lowest bit of the first byte.
+
+use crate::{Error, Result, Vector};
+
+/// Bits packed into each component of a stored vector
+pub const BITS_PER_COMPONENT: usize = 32;
+
+/// Components of a stored vector of `bits` bits
+#[inline]
+#[must_use]
+pub fn packed_dim(bits: usize) -> usize {
+    bits.div_ceil(BITS_PER_COMPONENT)
+}
+
+/// Pack bytes into a stored vector; the last component is zero-padded
+pub fn pack_bytes(bytes: &[u8]) -> Vector {
+    let components = bytes
+        .chunks(BITS_PER_COMPONENT / 8)
+        .map(|chunk| {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            f32::from_bits(u32::from_le_bytes(word))
+        })
+        .collect();
+    Vector::new(components)
+}
+
+/// Pack byte values given as numbers, as JSON arrays arrive. Fails unless
+/// there are `bits / 8` of them and each is a whole number from 0 to 255.
+pub fn pack_byte_values(values: &[f32], bits: usize) -> Result<Vector> {
+    if values.len() * 8 != bits {
+        return Err(Error::InvalidBinaryVector(format!("{} bits, expected {}", values.len() * 8, bits)));
+    }
+    let bytes = values
+        .iter()
+        .map(|&v| {
+            if v.fract() == 0.0 && (0.0..=255.0).contains(&v) {
+                Ok(v as u8)
+            } else {
+                Err(Error::InvalidBinaryVector(format!("bytes must be integers from 0 to 255, got {}", v)))
+            }
+        })
+        .collect::<Result<Vec<u8>>>()?;
+    Ok(pack_bytes(&bytes))
+}
+
+/// The first `bits / 8` bytes of a stored vector
+pub fn unpack_bytes(vector: &Vector, bits: usize) -> Vec<u8> {
+    let mut bytes: Vec<u8> = words(vector.as_slice())
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    bytes.truncate(bits.div_ceil(8));
+    bytes
+}
+
+/// The packed bits of stored components
+#[inline]
+pub fn words(components: &[f32]) -> &[u32] {
+    // Safety: f32 and u32 have the same size and alignment, and every bit
+    // pattern is a valid u32
+    unsafe { std::slice::from_raw_parts(components.as_ptr().cast::<u32>(), components.len()) }
+}
+
+/// Number of bits in which two stored vectors differ
+#[inline]
+pub fn hamming_distance(a: &[f32], b: &[f32]) -> u32 {
+    crate::simd::hamming_distance_simd(words(a), words(b))
+}
+
+/// Search score of two stored vectors: the negated Hamming distance, so
+/// higher is more similar and identical vectors score 0
+#[inline]
+pub fn hamming_score(a: &[f32], b: &[f32]) -> f32 {
+    0.0 - hamming_distance(a, b) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_roundtrip() {
+        let bytes: Vec<u8> = (0..=255u8).step_by(7).collect();
+        let bits = bytes.len() * 8;
+        let vector = pack_bytes(&bytes);
+        assert_eq!(vector.dim(), packed_dim(bits));
+        assert_eq!(unpack_bytes(&vector, bits), bytes);
+
+        let values: Vec<f32> = bytes.iter().map(|&b| f32::from(b)).collect();
+        assert_eq!(unpack_bytes(&pack_byte_values(&values, bits).unwrap(), bits), bytes);
+        assert!(pack_byte_values(&values, bits + 8).is_err());
+        assert!(pack_byte_values(&[1.5], 8).is_err());
+        assert!(pack_byte_values(&[256.0], 8).is_err());
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        // Long enough for the vector kernels, with a scalar tail
+        let a: Vec<u8> = (0..132u32).map(|i| (i * 37 % 251) as u8).collect();
+        let b: Vec<u8> = (0..132u32).map(|i| (i * 91 % 241) as u8).collect();
+        let expected: u32 = a.iter().zip(&b).map(|(x, y)| (x ^ y).count_ones()).sum();
+
+        let (pa, pb) = (pack_bytes(&a), pack_bytes(&b));
+        assert_eq!(hamming_distance(pa.as_slice(), pb.as_slice()), expected);
+        assert_eq!(hamming_distance(pa.as_slice(), pa.as_slice()), 0);
+        for len in [1, 3, 9] {
+            let expected: u32 = a[..len * 4].iter().zip(&b[..len * 4]).map(|(x, y)| (x ^ y).count_ones()).sum();
+            assert_eq!(hamming_distance(&pa.as_slice()[..len], &pb.as_slice()[..len]), expected);
+        }
+    }
+}
@@ -10,6 +10,12 @@ pub struct BM25Index {
     doc_lengths: HashMap<String, u32>,
     // term -> document frequency
     term_dfs: HashMap<String, u32>,
+    // doc_id -> (term, byte start, byte end) of each token, for highlighting
+    #[serde(default)]
+    offsets: HashMap<String, Vec<(String, u32, u32)>>,
+    // doc_id -> language the document was analyzed in (ISO 639-3)
+    #[serde(default)]
+    doc_languages: HashMap<String, String>,
     total_docs: u64,
     k1: f32, // term frequency saturation parameter
     b: f32,  // length normalization parameter
@@ -23,6 +29,8 @@ impl BM25Index {
             inverted_index: HashMap::new(),
             doc_lengths: HashMap::new(),
             term_dfs: HashMap::new(),
+            offsets: HashMap::new(),
+            doc_languages: HashMap::new(),
             total_docs: 0,
             k1: 1.5,
             b: 0.75,
@@ -33,25 +41,58 @@ impl BM25Index {
     /// Uses lowercase normalization and removes punctuation
     #[inline]
     pub fn tokenize(text: &str) -> Vec<String> {
-        text.to_lowercase()
-            .split(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
-            .map(|s| s.trim_matches(|c: char| !c.is_alphanumeric()).to_string())
-            .filter(|s| !s.is_empty() && s.len() > 1)  // Filter single chars
+        Self::tokenize_with_offsets(text)
+            .into_iter()
+            .map(|(token, _, _)| token)
             .collect()
     }
 
+    /// Tokens with the byte range each one covers in `text`
+    pub fn tokenize_with_offsets(text: &str) -> Vec<(String, usize, usize)> {
+        let mut tokens = Vec::new();
+        let mut start = 0;
+        for (end, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+            if !(c.is_whitespace() || c.is_ascii_punctuation()) {
+                continue;
+            }
+            let segment = &text[start..end];
+            let trimmed = segment.trim_start_matches(|c: char| !c.is_alphanumeric());
+            let token_start = start + segment.len() - trimmed.len();
+            let trimmed = trimmed.trim_end_matches(|c: char| !c.is_alphanumeric());
+            let token = trimmed.to_lowercase();
+            if token.len() > 1 {  // Filter single chars
+                tokens.push((token, token_start, token_start + trimmed.len()));
+            }
+            start = end + c.len_utf8();
+        }
+        tokens
+    }
+
     pub fn insert_doc(&mut self, doc_id: &str, text: &str) {
+        self.insert_doc_with_language(doc_id, text, None);
+    }
+
+    /// Index a document with the stemmer of `language` (ISO 639-3), if any
+    pub fn insert_doc_with_language(&mut self, doc_id: &str, text: &str, language: Option<&str>) {
         // Remove old document if exists
         self.delete_doc(doc_id);
 
-        let tokens = Self::tokenize(text);
+        let mut tokens = Self::tokenize_with_offsets(text);
+        if let Some(language) = language {
+            crate::language::stem_all(language, tokens.iter_mut().map(|(token, _, _)| token));
+            self.doc_languages.insert(doc_id.to_string(), language.to_string());
+        }
         let doc_len = tokens.len() as u32;
 
         // Count term frequencies
         let mut term_freqs: HashMap<String, u32> = HashMap::new();
-        for token in &tokens {
+        for (token, _, _) in &tokens {
             *term_freqs.entry(token.clone()).or_insert(0) += 1;
         }
+        self.offsets.insert(
+            doc_id.to_string(),
+            tokens.into_iter().map(|(token, start, end)| (token, start as u32, end as u32)).collect(),
+        );
 
         // Update inverted index
         for (term, tf) in &term_freqs {
@@ -73,6 +114,8 @@ impl BM25Index {
     }
 
     pub fn delete_doc(&mut self, doc_id: &str) {
+        self.offsets.remove(doc_id);
+        self.doc_languages.remove(doc_id);
         if self.doc_lengths.remove(doc_id).is_some() {
             // Remove from inverted index and update DFs
             let mut terms_to_update = Vec::new();
@@ -94,11 +137,21 @@ impl BM25Index {
     }
 
     pub fn search(&self, query: &str, limit: usize) -> Vec<(String, f32)> {
+        self.search_terms(&Self::tokenize(query), limit)
+    }
+
+    /// Search with already analyzed query terms
+    pub fn search_terms(&self, query_terms: &[String], limit: usize) -> Vec<(String, f32)> {
+        self.search_terms_in(query_terms, None, limit)
+    }
+
+    /// Search with analyzed query terms, only among documents indexed in
+    /// `language` when one is given
+    pub fn search_terms_in(&self, query_terms: &[String], language: Option<&str>, limit: usize) -> Vec<(String, f32)> {
         if self.total_docs == 0 {
             return Vec::new();
         }
 
-        let query_terms = Self::tokenize(query);
         if query_terms.is_empty() {
             return Vec::new();
         }
@@ -113,7 +166,7 @@ impl BM25Index {
         // Score each document
         let mut doc_scores: HashMap<String, f32> = HashMap::new();
 
-        for term in &query_terms {
+        for term in query_terms {
             if let Some(docs) = self.inverted_index.get(term) {
                 let df = self.term_dfs.get(term).copied().unwrap_or(0) as f32;
                 let idf = if df > 0.0 {
@@ -123,6 +176,9 @@ impl BM25Index {
                 };
 
                 for (doc_id, &tf) in docs {
+                    if language.is_some() && self.doc_languages.get(doc_id).map(String::as_str) != language {
+                        continue;
+                    }
                     if let Some(&doc_len) = self.doc_lengths.get(doc_id) {
                         let score = self.calculate_bm25_score(tf, doc_len, df as u32, self.total_docs, avgdl, idf);
                         *doc_scores.entry(doc_id.clone()).or_insert(0.0) += score;
@@ -138,6 +194,27 @@ impl BM25Index {
         results
     }
 
+    /// Languages documents have been indexed in
+    pub fn languages(&self) -> Vec<String> {
+        let mut languages: Vec<String> = self.doc_languages.values().cloned().collect();
+        languages.sort();
+        languages.dedup();
+        languages
+    }
+
+    /// Byte ranges of the occurrences of `terms` in an indexed document,
+    /// in text order
+    pub fn term_offsets(&self, doc_id: &str, terms: &[String]) -> Vec<(usize, usize)> {
+        self.offsets.get(doc_id)
+            .map(|tokens| {
+                tokens.iter()
+                    .filter(|(token, _, _)| terms.contains(token))
+                    .map(|&(_, start, end)| (start as usize, end as usize))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     fn calculate_bm25_score(
         &self,
         tf: u32,
@@ -168,6 +245,51 @@ impl BM25Index {
     pub fn is_empty(&self) -> bool {
         self.doc_lengths.is_empty()
     }
+
+    /// Whether a document is indexed under `doc_id`
+    pub fn contains_doc(&self, doc_id: &str) -> bool {
+        self.doc_lengths.contains_key(doc_id)
+    }
+
+    /// Ids of the indexed documents
+    pub fn doc_ids(&self) -> impl Iterator<Item = &str> {
+        self.doc_lengths.keys().map(String::as_str)
+    }
+
+    /// Number of distinct indexed terms
+    #[inline]
+    #[must_use]
+    pub fn term_count(&self) -> usize {
+        self.term_dfs.len()
+    }
+
+    /// Estimated bytes held by posting lists and document statistics
+    pub fn memory_usage(&self) -> usize {
+        use crate::stats::MAP_ENTRY_OVERHEAD;
+
+        let postings: usize = self.inverted_index.iter()
+            .map(|(term, docs)| {
+                term.len() + MAP_ENTRY_OVERHEAD
+                    + docs.keys().map(|doc| doc.len() + 4 + MAP_ENTRY_OVERHEAD).sum::<usize>()
+            })
+            .sum();
+        let doc_lengths: usize = self.doc_lengths.keys()
+            .map(|doc| doc.len() + 4 + MAP_ENTRY_OVERHEAD)
+            .sum();
+        let dfs: usize = self.term_dfs.keys()
+            .map(|term| term.len() + 4 + MAP_ENTRY_OVERHEAD)
+            .sum();
+        let offsets: usize = self.offsets.iter()
+            .map(|(doc, tokens)| {
+                doc.len() + MAP_ENTRY_OVERHEAD
+                    + tokens.iter().map(|(token, _, _)| token.len() + 8 + std::mem::size_of::<String>()).sum::<usize>()
+            })
+            .sum();
+        let languages: usize = self.doc_languages.keys()
+            .map(|doc| doc.len() + 3 + std::mem::size_of::<String>() + MAP_ENTRY_OVERHEAD)
+            .sum();
+        postings + doc_lengths + dfs + offsets + languages
+    }
 }
 
 impl Default for BM25Index {
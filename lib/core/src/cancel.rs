@@ -0,0 +1,111 @@
+//! Cooperative cancellation of long scans
+//!
+//! Brute-force, multivector and sparse searches visit every point. Their
+//! `_cancellable` variants check a [`CancellationToken`] every
+//! [`CHECK_INTERVAL`] points and stop once it is cancelled or its deadline
+//! has passed, returning the best results among the points scored so far.
+//! [`CancellationToken::interrupted`] then tells the caller the results are
+//! partial.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Points scored between two checks of the token
+pub const CHECK_INTERVAL: usize = 1024;
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    interrupted: AtomicBool,
+    deadline: Option<Instant>,
+}
+
+/// Shared flag telling a running search to stop. Clones share the state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<TokenState>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that expires `timeout` from now; one too long for the
+    /// clock to represent never expires
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            state: Arc::new(TokenState {
+                deadline: Instant::now().checked_add(timeout),
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Whether the token was cancelled or its deadline has passed
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Acquire) || self.timed_out()
+    }
+
+    /// Whether the deadline has passed
+    pub fn timed_out(&self) -> bool {
+        self.state.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Whether a search stopped early because of this token
+    pub fn interrupted(&self) -> bool {
+        self.state.interrupted.load(Ordering::Acquire)
+    }
+
+    /// Check the token from a scan, recording that the scan stops if it is
+    /// cancelled
+    pub(crate) fn should_stop(&self) -> bool {
+        let stop = self.is_cancelled();
+        if stop {
+            self.state.interrupted.store(true, Ordering::Release);
+        }
+        stop
+    }
+}
+
+/// Check an optional token every [`CHECK_INTERVAL`] points
+#[inline]
+pub(crate) fn should_stop(cancel: Option<&CancellationToken>, visited: usize) -> bool {
+    visited % CHECK_INTERVAL == 0 && cancel.is_some_and(|c| c.should_stop())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!should_stop(Some(&token), 0));
+        clone.cancel();
+        // Only checked on interval boundaries
+        assert!(!should_stop(Some(&token), 1));
+        assert!(!token.interrupted());
+        assert!(should_stop(Some(&token), CHECK_INTERVAL));
+        assert!(token.interrupted());
+        assert!(!token.timed_out());
+        assert!(!should_stop(None, 0));
+
+        let expired = CancellationToken::with_timeout(Duration::ZERO);
+        assert!(expired.timed_out() && expired.is_cancelled());
+    }
+
+    #[test]
+    fn test_unrepresentable_timeout() {
+        let token = CancellationToken::with_timeout(Duration::from_secs(u64::MAX));
+        assert!(!token.timed_out() && !token.is_cancelled());
+        token.cancel();
+        assert!(token.is_cancelled());
+    }
+}
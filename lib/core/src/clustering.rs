@@ -0,0 +1,195 @@
+//! Mini-batch k-means over a collection's dense vectors
+//!
+//! Centroids are seeded with k-means++ and refined with mini-batch updates
+//! (Sculley, 2010): each iteration samples `batch_size` vectors, assigns them
+//! to their nearest centroid and moves that centroid towards them with a
+//! per-centroid learning rate of `1 / count`. A final full pass assigns every
+//! vector and computes the inertia.
+//!
+//! Distances are squared Euclidean; for cosine collections the stored vectors
+//! are normalized, so this is equivalent to clustering by angle.
+
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Parameters of a k-means run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct KMeansConfig {
+    /// Number of clusters
+    pub k: usize,
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: usize,
+    /// Vectors sampled per iteration
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Stop once no centroid moves further than this (Euclidean)
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f32,
+    /// Seed for reproducible runs
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+fn default_max_iterations() -> usize { 100 }
+fn default_batch_size() -> usize { 1024 }
+fn default_tolerance() -> f32 { 1e-4 }
+
+impl KMeansConfig {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            max_iterations: default_max_iterations(),
+            batch_size: default_batch_size(),
+            tolerance: default_tolerance(),
+            seed: None,
+        }
+    }
+}
+
+/// Outcome of a k-means run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KMeansResult {
+    pub centroids: Vec<Vec<f32>>,
+    /// Number of vectors assigned to each centroid
+    pub sizes: Vec<usize>,
+    /// Cluster index of each input vector, in input order
+    #[serde(skip)]
+    pub assignments: Vec<usize>,
+    /// Sum of squared distances to the assigned centroid
+    pub inertia: f32,
+    pub iterations: usize,
+}
+
+/// Cluster `vectors` (all of the same dimension). Returns None if there are
+/// fewer vectors than clusters or `k` is zero.
+pub fn kmeans(vectors: &[&[f32]], config: &KMeansConfig) -> Option<KMeansResult> {
+    if config.k == 0 || vectors.len() < config.k {
+        return None;
+    }
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(&mut rand::rng()),
+    };
+
+    let mut centroids = init_plus_plus(vectors, config.k, &mut rng);
+    let mut counts = vec![0usize; config.k];
+    let batch_size = config.batch_size.clamp(1, vectors.len());
+
+    let mut iterations = 0;
+    while iterations < config.max_iterations {
+        iterations += 1;
+        let batch: Vec<&[f32]> = vectors.choose_multiple(&mut rng, batch_size).copied().collect();
+        let previous = centroids.clone();
+
+        for vector in batch {
+            let (cluster, _) = nearest(&centroids, vector);
+            counts[cluster] += 1;
+            let rate = 1.0 / counts[cluster] as f32;
+            for (c, v) in centroids[cluster].iter_mut().zip(vector) {
+                *c += rate * (v - *c);
+            }
+        }
+
+        let shift = centroids.iter()
+            .zip(&previous)
+            .map(|(a, b)| squared_distance(a, b))
+            .fold(0.0f32, f32::max)
+            .sqrt();
+        if shift <= config.tolerance {
+            break;
+        }
+    }
+
+    let mut sizes = vec![0usize; config.k];
+    let mut inertia = 0.0;
+    let assignments = vectors.iter()
+        .map(|vector| {
+            let (cluster, distance) = nearest(&centroids, vector);
+            sizes[cluster] += 1;
+            inertia += distance;
+            cluster
+        })
+        .collect();
+
+    Some(KMeansResult { centroids, sizes, assignments, inertia, iterations })
+}
+
+/// k-means++ seeding: each next centroid is drawn with probability
+/// proportional to its squared distance from the closest chosen one
+fn init_plus_plus(vectors: &[&[f32]], k: usize, rng: &mut StdRng) -> Vec<Vec<f32>> {
+    let mut centroids = vec![vectors[rng.random_range(0..vectors.len())].to_vec()];
+    let mut distances: Vec<f32> = vectors.iter().map(|v| squared_distance(v, &centroids[0])).collect();
+
+    while centroids.len() < k {
+        let total: f32 = distances.iter().sum();
+        let next = if total > 0.0 {
+            let mut target = rng.random_range(0.0..total);
+            distances.iter()
+                .position(|&d| {
+                    target -= d;
+                    target <= 0.0
+                })
+                .unwrap_or(vectors.len() - 1)
+        } else {
+            // All remaining vectors coincide with a centroid
+            rng.random_range(0..vectors.len())
+        };
+        let centroid = vectors[next].to_vec();
+        for (d, v) in distances.iter_mut().zip(vectors) {
+            *d = d.min(squared_distance(v, &centroid));
+        }
+        centroids.push(centroid);
+    }
+    centroids
+}
+
+fn nearest(centroids: &[Vec<f32>], vector: &[f32]) -> (usize, f32) {
+    centroids.iter()
+        .enumerate()
+        .map(|(i, c)| (i, squared_distance(c, vector)))
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .unwrap_or((0, 0.0))
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_separates_blobs() {
+        let mut data = Vec::new();
+        for i in 0..50 {
+            let jitter = (i % 5) as f32 * 0.01;
+            data.push(vec![0.0 + jitter, 0.0]);
+            data.push(vec![10.0 + jitter, 10.0]);
+        }
+        let vectors: Vec<&[f32]> = data.iter().map(|v| v.as_slice()).collect();
+
+        let mut config = KMeansConfig::new(2);
+        config.seed = Some(7);
+        config.batch_size = 20;
+        let result = kmeans(&vectors, &config).unwrap();
+
+        assert_eq!(result.sizes, vec![50, 50]);
+        assert_eq!(result.assignments.len(), 100);
+        // Alternating input: neighbours belong to different clusters
+        assert_ne!(result.assignments[0], result.assignments[1]);
+        assert_eq!(result.assignments[0], result.assignments[2]);
+        assert!(result.inertia < 1.0);
+    }
+
+    #[test]
+    fn test_rejects_too_few_vectors() {
+        let data = [vec![1.0, 2.0]];
+        let vectors: Vec<&[f32]> = data.iter().map(|v| v.as_slice()).collect();
+        assert!(kmeans(&vectors, &KMeansConfig::new(2)).is_none());
+        assert!(kmeans(&vectors, &KMeansConfig::new(0)).is_none());
+    }
+}
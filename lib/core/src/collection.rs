@@ -1,8 +1,27 @@
-use crate::{Error, Point, Result, Vector, HnswIndex, BM25Index, Filter, MultiVector};
-use parking_lot::RwLock;
-use std::collections::HashMap;
+use crate::{Error, Point, Result, Vector, GraphNode, GraphStats, HnswIndex, HnswParams, BM25Index, Filter, MultiVector, MultiVectorParams, GpuReplica, ReindexProgress};
+use crate::consistency::{ConsistencyReport, IndexViolation, PathComparison, ScoredId, SearchPath};
+use crate::journal::{JournalEntry, OperationJournal, OperationType};
+use crate::sparse::{SparseDocFrequencies, SparseModifier, SparseVectorConfig};
+use crate::store::PointStore;
+use crate::background::{HnswRebuildJob, RebuildReason};
+use crate::transaction::BatchOperation;
+use crate::analyzer::TextDictionaries;
+use crate::search_defaults::SearchDefaults;
+use crate::templates::QueryTemplate;
+use crate::ef_tuning::{EfTuning, EfTuningReport};
+use crate::dedup::{self, DuplicatePolicy};
+use crate::history::{QueryLatencies, StatsHistory, StatsSample};
+use crate::cancel::{self, CancellationToken};
+use crate::filter::FilterCondition;
+use crate::trigram::TrigramIndex;
+use crate::keyword::KeywordParams;
+use crate::points_iter::{PointsIter, Projection};
+use crate::rebuild::RebuildBacklog;
+use crate::fresh::FreshPoints;
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
 /// Configuration for a collection
 #[derive(Debug, Clone)]
@@ -26,15 +45,66 @@ impl Default for CollectionConfig {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum Distance {
     Cosine,
     Euclidean,
     Dot,
+    /// Differing bits between binary vectors, stored bit-packed (see
+    /// [`crate::binary`]); `vector_dim` counts bits
+    Hamming,
+}
+
+/// Whether the dense vectors of a cosine collection are stored normalized.
+/// Other distances compare vectors as given under either policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum NormalizationPolicy {
+    /// Normalize vectors when they are written, so cosine scores are dot
+    /// products of unit vectors
+    #[default]
+    OnWrite,
+    /// Store vectors as given and divide by their norm when scoring
+    Never,
+}
+
+impl NormalizationPolicy {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Whether `point` has a dense vector of its own, rather than none or the
+/// first vector of its multivector
+fn has_dense_vector(point: &Point) -> bool {
+    point.vector.dim() > 0 && point.multivector.is_none()
+}
+
+/// Score of a stored `vector` against a query prepared by
+/// [`Collection::prepare_query`]. Higher is more similar for every distance.
+#[inline]
+fn score_vector(distance: Distance, stored_normalized: bool, query: &[f32], vector: &[f32]) -> f32 {
+    match distance {
+        Distance::Cosine if stored_normalized => crate::simd::dot_product_simd(query, vector),
+        Distance::Cosine => {
+            let norm = crate::simd::norm_squared_simd(vector).sqrt();
+            if norm > 0.0 {
+                crate::simd::dot_product_simd(query, vector) / norm
+            } else {
+                0.0
+            }
+        }
+        Distance::Euclidean => -crate::simd::l2_distance_simd(query, vector),
+        Distance::Dot => crate::simd::dot_product_simd(query, vector),
+        Distance::Hamming => crate::binary::hamming_score(query, vector),
+    }
 }
 
 /// Payload field index type
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
 pub enum PayloadIndexType {
     Keyword,
     Integer,
@@ -44,47 +114,210 @@ pub enum PayloadIndexType {
     Text,
 }
 
+/// The vector of the points a query's `using` selects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VectorUsing {
+    /// The unnamed dense vector
+    Dense,
+    /// A named sparse vector
+    Sparse(String),
+    /// A declared named multivector
+    Multivector(String),
+}
+
+/// Outcome of upserting one point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpsertStatus {
+    Inserted,
+    Updated,
+    /// The vector duplicated that of the point with this id, which took
+    /// the payload instead (see [`DuplicatePolicy::Merge`])
+    Merged(String),
+    Failed(String),
+}
+
+/// A point's change worked out against the HNSW graph and the BM25 index
+/// before anything is written. Staging makes every check that can fail, so
+/// committing it can't leave the stored points and the indexes disagreeing.
+struct StagedWrite {
+    id: String,
+    /// New state of the point; None when it is deleted
+    point: Option<Point>,
+    /// (Re)link the point's vector in the graph
+    link: bool,
+    /// New BM25 text and language of the point if they change; Some(None)
+    /// drops its document
+    document: Option<Option<(String, Option<String>)>>,
+}
+
+/// Store writes and journal entries of a batch in progress, held back
+/// until its points are stored together
+#[derive(Default)]
+struct DeferredWrites {
+    puts: Vec<Point>,
+    /// State of each point the batch changed, from before its first change
+    /// (None if it didn't exist), to put back if the store refuses the batch
+    previous: HashMap<String, Option<Point>>,
+    operations: Vec<(OperationType, Vec<String>)>,
+}
+
 /// A collection of vectors with metadata
 pub struct Collection {
     config: CollectionConfig,
+    /// Dimension of dense vectors: the configured one, or for a collection
+    /// created without one that infers it, the first one written (0 until
+    /// then)
+    vector_dim: AtomicUsize,
+    infer_vector_dim: AtomicBool,
     points: Arc<RwLock<HashMap<String, Point>>>,
     hnsw: Option<Arc<RwLock<HnswIndex>>>,
-    bm25: Option<Arc<RwLock<BM25Index>>>,
+    /// None until BM25 is enabled, at creation or later
+    bm25: RwLock<Option<BM25Index>>,
+    /// `bm25` holds an index; checked without taking its lock
+    bm25_enabled: AtomicBool,
+    /// A `rebuild_text_index` or `enable_text_index` is running
+    text_index_rebuilding: AtomicBool,
     hnsw_built: Arc<RwLock<bool>>,
+    /// When the HNSW graph was last built, ms since the Unix epoch (0 = never)
+    hnsw_built_at: Arc<AtomicU64>,
     hnsw_rebuilding: Arc<AtomicBool>,
+    /// Points written while the HNSW graph is rebuilt
+    rebuild_backlog: Arc<RebuildBacklog>,
+    /// Stored points the HNSW graph misses or links with an old vector
+    fresh_points: Arc<FreshPoints>,
+    /// Progress of a running reindex
+    reindex_progress: Arc<RwLock<Option<ReindexProgress>>>,
+    /// Distance metric; starts as the configured one and may be changed
+    distance: RwLock<Distance>,
+    normalization: RwLock<NormalizationPolicy>,
     batch_mode: Arc<RwLock<bool>>,
     pending_points: Arc<RwLock<Vec<Point>>>,
     /// Payload field indexes
     payload_indexes: Arc<RwLock<HashMap<String, PayloadIndexType>>>,
+    /// Comparison options of keyword indexes that have any
+    keyword_params: RwLock<Arc<HashMap<String, KeywordParams>>>,
+    /// Trigrams of the values of keyword-indexed fields, for fuzzy matches;
+    /// rebuilt on the first fuzzy match after a write
+    trigram_indexes: RwLock<HashMap<String, TrigramIndex>>,
+    trigram_stale: AtomicBool,
     /// Operation counter for tracking write operations
     operation_counter: Arc<std::sync::atomic::AtomicU64>,
+    /// Recent write operations, for auditing and change-data-capture
+    journal: Arc<OperationJournal>,
+    /// Quantized read replica for batched brute-force scoring (GPU when available)
+    gpu_replica: Arc<RwLock<Option<GpuReplica>>>,
+    gpu_replica_enabled: Arc<AtomicBool>,
+    gpu_replica_stale: Arc<AtomicBool>,
+    /// Declared named sparse vectors; any name is accepted while empty
+    sparse_vectors: Arc<RwLock<HashMap<String, SparseVectorConfig>>>,
+    /// Scoring modifiers of undeclared sparse vectors, from configs written
+    /// before sparse vectors were declared; they restrict no names
+    sparse_modifiers: RwLock<HashMap<String, SparseModifier>>,
+    /// Declared named multivectors
+    named_multivectors: Arc<RwLock<HashMap<String, MultiVectorParams>>>,
+    /// Name the dense vector was declared under, if any
+    dense_vector_name: RwLock<Option<String>>,
+    text_dictionaries: RwLock<Arc<TextDictionaries>>,
+    detect_language: AtomicBool,
+    /// Parameters applied to searches that leave them out
+    search_defaults: RwLock<SearchDefaults>,
+    /// Named query templates
+    query_templates: RwLock<BTreeMap<String, QueryTemplate>>,
+    /// What upserts do with the vector of another point
+    duplicate_policy: RwLock<DuplicatePolicy>,
+    /// Hash of each quantized stored vector to the points with it; kept
+    /// only while duplicates are not allowed. Entries go stale as points
+    /// change or go, so each is checked against its point on every hit.
+    vector_keys: RwLock<HashMap<u64, Vec<String>>>,
+    /// Recall target the `ef` search default is tuned to, if any
+    ef_tuning: RwLock<Option<EfTuning>>,
+    ef_tuning_report: RwLock<Option<EfTuningReport>>,
+    /// Parameters of the HNSW graph
+    hnsw_params: RwLock<HnswParams>,
+    /// Document frequencies of sparse dimensions, for IDF scoring
+    sparse_df: Arc<RwLock<SparseDocFrequencies>>,
+    /// Persistent copy of the points, updated on every write
+    point_store: Arc<RwLock<Option<Arc<dyn PointStore>>>>,
+    /// Points written by the batch in progress, stored together when it
+    /// ends instead of one store write each; None outside a batch
+    deferred: Mutex<Option<DeferredWrites>>,
+    /// Held by every write, so a point's change reaches the point store and
+    /// all indexes before the next write starts
+    write_lock: Arc<Mutex<()>>,
+    /// Set once the collection is unloaded; writes that still reach this
+    /// instance fail instead of changing a copy nothing persists
+    closed: AtomicBool,
+    /// Check the indexes against the stored points after every write
+    check_invariants: AtomicBool,
+    /// Latencies of the queries since the last statistics sample
+    query_latencies: QueryLatencies,
+    stats_history: RwLock<StatsHistory>,
 }
 
 impl Collection {
     pub fn new(config: CollectionConfig) -> Self {
         let hnsw = if config.use_hnsw {
-            Some(Arc::new(RwLock::new(HnswIndex::new(16, 3))))
+            let index = HnswIndex::new(HnswParams::default().m, 3).with_distance(config.distance);
+            Some(Arc::new(RwLock::new(index)))
         } else {
             None
         };
 
-        let bm25 = if config.enable_bm25 {
-            Some(Arc::new(RwLock::new(BM25Index::new())))
-        } else {
-            None
-        };
+        let bm25 = config.enable_bm25.then(BM25Index::new);
 
+        let distance = config.distance;
+        let points = Arc::new(RwLock::new(HashMap::new()));
+        let write_lock = Arc::new(Mutex::new(()));
+        let fresh_points = Arc::new(FreshPoints::default());
         Self {
+            vector_dim: AtomicUsize::new(config.vector_dim),
+            infer_vector_dim: AtomicBool::new(false),
             config,
-            points: Arc::new(RwLock::new(HashMap::new())),
+            rebuild_backlog: Arc::new(RebuildBacklog::new(write_lock.clone(), points.clone(), fresh_points.clone())),
+            fresh_points,
+            points,
             hnsw,
-            bm25,
+            bm25_enabled: AtomicBool::new(bm25.is_some()),
+            bm25: RwLock::new(bm25),
+            text_index_rebuilding: AtomicBool::new(false),
             hnsw_built: Arc::new(RwLock::new(false)),
+            hnsw_built_at: Arc::new(AtomicU64::new(0)),
             hnsw_rebuilding: Arc::new(AtomicBool::new(false)),
+            reindex_progress: Arc::new(RwLock::new(None)),
+            distance: RwLock::new(distance),
+            normalization: RwLock::new(NormalizationPolicy::default()),
             batch_mode: Arc::new(RwLock::new(false)),
             pending_points: Arc::new(RwLock::new(Vec::new())),
             payload_indexes: Arc::new(RwLock::new(HashMap::new())),
+            keyword_params: RwLock::new(Arc::new(HashMap::new())),
+            trigram_indexes: RwLock::new(HashMap::new()),
+            trigram_stale: AtomicBool::new(true),
             operation_counter: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            journal: Arc::new(OperationJournal::default()),
+            gpu_replica: Arc::new(RwLock::new(None)),
+            gpu_replica_enabled: Arc::new(AtomicBool::new(false)),
+            gpu_replica_stale: Arc::new(AtomicBool::new(false)),
+            sparse_vectors: Arc::new(RwLock::new(HashMap::new())),
+            sparse_modifiers: RwLock::new(HashMap::new()),
+            named_multivectors: Arc::new(RwLock::new(HashMap::new())),
+            dense_vector_name: RwLock::new(None),
+            text_dictionaries: RwLock::new(Arc::new(TextDictionaries::default())),
+            detect_language: AtomicBool::new(false),
+            search_defaults: RwLock::new(SearchDefaults::default()),
+            query_templates: RwLock::new(BTreeMap::new()),
+            duplicate_policy: RwLock::new(DuplicatePolicy::default()),
+            vector_keys: RwLock::new(HashMap::new()),
+            ef_tuning: RwLock::new(None),
+            ef_tuning_report: RwLock::new(None),
+            hnsw_params: RwLock::new(HnswParams::default()),
+            sparse_df: Arc::new(RwLock::new(SparseDocFrequencies::new())),
+            point_store: Arc::new(RwLock::new(None)),
+            deferred: Mutex::new(None),
+            write_lock,
+            closed: AtomicBool::new(false),
+            check_invariants: AtomicBool::new(false),
+            query_latencies: QueryLatencies::default(),
+            stats_history: RwLock::new(StatsHistory::default()),
         }
     }
     
@@ -94,6 +327,170 @@ impl Collection {
         self.operation_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
     }
 
+    /// Id of the most recent write operation
+    #[inline]
+    pub fn last_operation_id(&self) -> u64 {
+        self.operation_counter.load(std::sync::atomic::Ordering::SeqCst).saturating_sub(1)
+    }
+
+    /// Number of write operations applied so far (the id the next one gets)
+    #[inline]
+    pub fn operation_count(&self) -> u64 {
+        self.operation_counter.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Operation journal for this collection
+    #[inline]
+    pub fn journal(&self) -> &Arc<OperationJournal> {
+        &self.journal
+    }
+
+    /// Replace the journal with persisted entries and continue numbering after them
+    pub fn restore_journal(&self, entries: Vec<JournalEntry>) {
+        let next_id = entries.iter().map(|e| e.op_id + 1).max().unwrap_or(0);
+        self.journal.restore(entries);
+        self.operation_counter.store(next_id, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    fn record_operation(&self, op_type: OperationType, point_ids: Vec<String>) {
+        if let Some(deferred) = self.deferred.lock().as_mut() {
+            deferred.operations.push((op_type, point_ids));
+            return;
+        }
+        if matches!(
+            op_type,
+            OperationType::Upsert | OperationType::Delete | OperationType::UpdateVectors
+                | OperationType::DeleteVectors | OperationType::Transaction
+        ) {
+            self.note_rebuild_writes(&point_ids);
+        }
+        let op_id = self.next_operation_id();
+        self.journal.record(op_id, op_type, point_ids);
+    }
+
+    /// Note points written while the HNSW graph is rebuilt, for the
+    /// rebuild to link into its new graph. The caller holds the write lock.
+    fn note_rebuild_writes(&self, ids: &[String]) {
+        if self.hnsw_rebuilding.load(Ordering::Acquire) {
+            self.rebuild_backlog.note(ids);
+        }
+    }
+
+    /// The write lock, unless the collection was closed while waiting for it
+    fn writer(&self) -> Result<parking_lot::MutexGuard<'_, ()>> {
+        let writer = self.write_lock.lock();
+        if self.closed.load(Ordering::Acquire) {
+            return Err(Error::Storage(format!(
+                "collection {} was unloaded while the write waited; retry it", self.name()
+            )));
+        }
+        Ok(writer)
+    }
+
+    /// Wait for the write in progress, if any, then detach the point store
+    /// and refuse every later write to this instance. Called when the
+    /// collection is unloaded, so no write is applied to a copy that is
+    /// neither persisted nor seen once the collection is loaded again.
+    pub fn close(&self) {
+        let _writer = self.write_lock.lock();
+        self.closed.store(true, Ordering::Release);
+        *self.point_store.write() = None;
+    }
+
+    /// Attach (or detach) the store that keeps a persistent copy of the points
+    pub fn set_point_store(&self, store: Option<Arc<dyn PointStore>>) {
+        *self.point_store.write() = store;
+    }
+
+    /// Store the new state of a point, before the change is made in memory
+    /// so a refused write changes nothing. Within a batch it is kept for
+    /// [`Collection::persist_deferred`] instead, along with the point as it
+    /// was before the batch.
+    fn persist_point(&self, point: &Point) -> Result<()> {
+        if let Some(deferred) = self.deferred.lock().as_mut() {
+            let id = point.id.to_string();
+            deferred.previous.entry(id)
+                .or_insert_with_key(|id| self.points.read().get(id).cloned());
+            deferred.puts.push(point.clone());
+            return Ok(());
+        }
+        self.persist(|store| store.put(point))
+    }
+
+    fn persist_removals(&self, ids: &[String]) -> Result<()> {
+        self.persist(|store| store.apply(&[], ids))
+    }
+
+    /// Run `write` against the point store, if one is attached
+    fn persist(&self, write: impl FnOnce(&dyn PointStore) -> Result<()>) -> Result<()> {
+        match self.point_store.read().as_ref() {
+            Some(store) => write(store.as_ref()),
+            None => Ok(()),
+        }
+    }
+
+    /// Start deferring point store writes and journal entries for a batch.
+    /// The caller holds the write lock until [`Collection::persist_deferred`]
+    /// or [`Collection::discard_deferred`].
+    fn defer_persistence(&self) {
+        *self.deferred.lock() = Some(DeferredWrites::default());
+    }
+
+    /// Store the points deferred since [`Collection::defer_persistence`]
+    /// in one write, then journal the batch. If the store refuses it, the
+    /// batch's changes in memory are undone and it fails.
+    fn persist_deferred(&self) -> Result<()> {
+        let Some(deferred) = self.deferred.lock().take() else { return Ok(()) };
+        if !deferred.puts.is_empty() {
+            if let Err(e) = self.persist(|store| store.apply(&deferred.puts, &[])) {
+                self.roll_back(deferred.previous);
+                return Err(e);
+            }
+        }
+        for (op_type, point_ids) in deferred.operations {
+            self.record_operation(op_type, point_ids);
+        }
+        Ok(())
+    }
+
+    /// Drop the writes deferred since [`Collection::defer_persistence`]
+    /// and undo the batch's changes in memory
+    fn discard_deferred(&self) {
+        if let Some(deferred) = self.deferred.lock().take() {
+            self.roll_back(deferred.previous);
+        }
+    }
+
+    /// Put the points a batch changed back as they were before it. Batch
+    /// writes leave the graph to `end_batch`, so only the point map, the
+    /// BM25 index and the points pending a link are restored.
+    fn roll_back(&self, previous: HashMap<String, Option<Point>>) {
+        if previous.is_empty() {
+            return;
+        }
+        self.pending_points.write().retain(|point| !previous.contains_key(&point.id.to_string()));
+        self.invalidate_gpu_replica();
+        self.trigram_stale.store(true, Ordering::Release);
+        for (id, point) in previous {
+            let current = self.points.read().get(&id).cloned();
+            let document = point.as_ref().and_then(|p| self.bm25_document(p));
+            if current.as_ref().and_then(|p| self.bm25_document(p)) != document {
+                self.index_document(&id, document.as_ref());
+            }
+            match point {
+                Some(point) => self.store_point(id, point),
+                None => {
+                    let mut df = self.sparse_df.write();
+                    if let Some(current) = self.points.write().remove(&id) {
+                        df.remove(&current);
+                    }
+                    drop(df);
+                    self.fresh_points.remove([id.as_str()]);
+                }
+            }
+        }
+    }
+
     #[inline]
     #[must_use]
     pub fn name(&self) -> &str {
@@ -103,13 +500,36 @@ impl Collection {
     #[inline]
     #[must_use]
     pub fn vector_dim(&self) -> usize {
-        self.config.vector_dim
+        self.vector_dim.load(Ordering::Acquire)
+    }
+
+    /// Whether a collection created without a dense dimension takes it from
+    /// the first dense vector written while it is empty. Otherwise such a
+    /// collection only holds sparse vectors and named multivectors, and
+    /// rejects dense vectors.
+    pub fn set_infer_vector_dim(&self, enabled: bool) {
+        self.infer_vector_dim.store(enabled, Ordering::Release);
+    }
+
+    pub fn infer_vector_dim(&self) -> bool {
+        self.infer_vector_dim.load(Ordering::Acquire)
+    }
+
+    /// Components of each stored dense vector: `vector_dim`, or for
+    /// Hamming collections the bits packed 32 to a component
+    #[inline]
+    #[must_use]
+    pub fn stored_dim(&self) -> usize {
+        match self.distance() {
+            Distance::Hamming => crate::binary::packed_dim(self.vector_dim()),
+            _ => self.vector_dim(),
+        }
     }
 
     #[inline]
     #[must_use]
     pub fn distance(&self) -> Distance {
-        self.config.distance
+        *self.distance.read()
     }
 
     #[inline]
@@ -121,7 +541,7 @@ impl Collection {
     #[inline]
     #[must_use]
     pub fn enable_bm25(&self) -> bool {
-        self.config.enable_bm25
+        self.bm25_enabled.load(Ordering::Acquire)
     }
 
     #[inline]
@@ -141,343 +561,1643 @@ impl Collection {
         self.points.read().values().cloned().collect()
     }
 
-    /// Insert or update a point
-    pub fn upsert(&self, point: Point) -> Result<()> {
+    /// Points matching `filter` (all points without one), with only the
+    /// parts in `projection`. The filter runs now, and again on each point
+    /// as the iterator reaches it and copies it, without holding the
+    /// collection's lock.
+    pub fn iter_filtered<'f>(&self, filter: Option<&'f dyn Filter>, projection: Projection) -> PointsIter<'f> {
+        let filter = filter.map(|f| Box::new(move |point: &Point| f.matches(point)) as Box<dyn Filter + 'f>);
+        self.iter_filtered_owned(filter, projection)
+    }
+
+    /// [`Collection::iter_filtered`] with a filter the iterator keeps
+    pub fn iter_filtered_owned<'f>(&self, filter: Option<Box<dyn Filter + 'f>>, projection: Projection) -> PointsIter<'f> {
+        let ids: Vec<String> = self.points.read()
+            .iter()
+            .filter(|(_, point)| filter.as_ref().map_or(true, |f| f.matches(point)))
+            .map(|(id, _)| id.clone())
+            .collect();
+        PointsIter::new(Arc::clone(&self.points), ids, projection, filter)
+    }
+
+    /// Reject points whose dense or sparse vectors don't fit the collection,
+    /// the check every stored point passes when it is loaded. A collection
+    /// inferring its dimension takes it from the point if it is still
+    /// empty.
+    pub fn validate_point(&self, point: &Point) -> Result<()> {
+        if self.vector_dim() == 0 && self.infer_vector_dim() && has_dense_vector(point) && self.is_empty() {
+            // A concurrent first write may win; the point is then checked
+            // against its dimension
+            let _ = self.vector_dim.compare_exchange(0, point.vector.dim(), Ordering::AcqRel, Ordering::Acquire);
+        }
         // Skip dimension check for sparse-only collections (vector_dim == 0)
-        if self.config.vector_dim > 0 && point.vector.dim() != self.config.vector_dim {
+        if self.vector_dim() > 0 && point.vector.dim() != self.stored_dim() {
             return Err(Error::InvalidDimension {
-                expected: self.config.vector_dim,
+                expected: self.stored_dim(),
                 actual: point.vector.dim(),
             });
         }
+        for sparse in point.sparse_vectors.values() {
+            sparse.validate()?;
+        }
+        Ok(())
+    }
 
-        let id_str = point.id.to_string();
-        
-        // Check if point exists and get its current version
-        let new_version = {
-            let points = self.points.read();
-            if let Some(existing) = points.get(&id_str) {
-                existing.version + 1
-            } else {
-                0
+    /// [`Collection::validate_point`] for points being written, which must
+    /// also only hold declared sparse vectors and named multivectors of the
+    /// declared size. Stored points are loaded without this check, so
+    /// declaring names later doesn't lock them out.
+    fn validate_write(&self, point: &Point) -> Result<()> {
+        self.validate_point(point)?;
+        if self.vector_dim() == 0 && has_dense_vector(point) {
+            return Err(self.no_dense_vectors());
+        }
+        if !point.named_multivectors.is_empty() {
+            let declared = self.named_multivectors.read();
+            for (name, multivector) in &point.named_multivectors {
+                let params = declared.get(name).ok_or_else(|| {
+                    Error::UnknownVector(format!("multivector '{}' is not declared by the collection", name))
+                })?;
+                if multivector.dim() != params.size {
+                    return Err(Error::InvalidDimension { expected: params.size, actual: multivector.dim() });
+                }
             }
-        };
-        
-        // Create point with updated version
-        let mut versioned_point = point;
-        versioned_point.version = new_version;
-        
-        let in_batch = *self.batch_mode.read();
-        if in_batch {
-            self.points.write().insert(id_str.clone(), versioned_point.clone());
-            self.pending_points.write().push(versioned_point);
+        }
+        let declared = self.sparse_vectors.read();
+        if declared.is_empty() {
             return Ok(());
         }
-        
-        if let Some(hnsw) = &self.hnsw {
-            let built = *self.hnsw_built.read();
-            if built {
-                let mut normalized_point = versioned_point.clone();
-                normalized_point.vector.normalize();
-                
-                let mut index = hnsw.write();
-                index.insert(normalized_point);
+        match point.sparse_vectors.keys().find(|name| !declared.contains_key(*name)) {
+            Some(name) => {
+                let mut names: Vec<&str> = declared.keys().map(String::as_str).collect();
+                names.sort_unstable();
+                Err(Error::InvalidSparseVector(format!(
+                    "'{}' is not declared by the collection (declared: {})",
+                    name,
+                    names.join(", ")
+                )))
             }
+            None => Ok(()),
         }
+    }
+
+    /// The error for a dense vector written to a collection without a dense
+    /// dimension
+    fn no_dense_vectors(&self) -> Error {
+        let reason = if self.infer_vector_dim() {
+            "the collection has no dense vectors, and infers their dimension only from the first point written while it is empty"
+        } else {
+            "the collection was created without dense vectors; send sparse vectors or named multivectors, or create it with infer_vector_dim"
+        };
+        Error::UnknownVector(format!("dense vector ({})", reason))
+    }
 
-        if let Some(bm25) = &self.bm25 {
-            if let Some(payload) = &versioned_point.payload {
-                if let Some(text) = payload.get("text").and_then(|v| v.as_str()) {
-                    let mut index = bm25.write();
-                    index.insert_doc(&id_str, text);
+    /// Check a batch of points before any of it is applied. The error names
+    /// the first point that does not fit the collection, or whose payload is
+    /// larger than `max_payload_bytes`, and its index in the batch.
+    pub fn validate_points(&self, points: &[Point], max_payload_bytes: Option<usize>) -> Result<()> {
+        // With duplicates rejected, points of the batch also count
+        let reject = self.duplicate_policy() == DuplicatePolicy::Reject;
+        let mut batch_keys: HashMap<Vec<i64>, String> = HashMap::new();
+        for (index, point) in points.iter().enumerate() {
+            let mut checked = self.check_point(point, max_payload_bytes);
+            if reject && checked.is_ok() && has_dense_vector(point) {
+                let id = point.id.to_string();
+                let key = dedup::vector_key(self.stored_vector(&point.vector).as_slice());
+                let duplicate_of = batch_keys.get(&key).filter(|other| **other != id).cloned()
+                    .or_else(|| self.find_duplicate(&id, &key));
+                if let Some(duplicate_of) = duplicate_of {
+                    checked = Err(Error::DuplicateVector { id: id.clone(), duplicate_of });
                 }
+                batch_keys.entry(key).or_insert(id);
+            }
+            if let Err(e) = checked {
+                return Err(Error::InvalidPoint { index, id: point.id.to_string(), reason: e.to_string() });
             }
         }
-
-        self.points.write().insert(id_str, versioned_point);
         Ok(())
     }
 
-    /// Start batch insert mode
-    pub fn start_batch(&self) {
-        *self.batch_mode.write() = true;
-        self.pending_points.write().clear();
-    }
-
-    /// End batch insert mode
-    pub fn end_batch(&self) -> Result<()> {
-        *self.batch_mode.write() = false;
-        
-        if let Some(hnsw) = &self.hnsw {
-            let points = self.points.read();
-            let point_count = points.len();
-            
-            const HNSW_REBUILD_THRESHOLD: usize = 10_000;
-            
-            if point_count > HNSW_REBUILD_THRESHOLD && !self.hnsw_rebuilding.load(Ordering::Acquire) {
-                self.hnsw_rebuilding.store(true, Ordering::Release);
-                let points_clone: Vec<Point> = points.values().cloned().collect();
-                let hnsw_clone = hnsw.clone();
-                let built_flag = self.hnsw_built.clone();
-                let rebuilding_flag = self.hnsw_rebuilding.clone();
-                
-                let job = crate::background::HnswRebuildJob::new(
-                    points_clone,
-                    hnsw_clone,
-                    built_flag,
-                    rebuilding_flag,
-                );
-                crate::background::get_background_system().submit(Box::new(job));
+    fn check_point(&self, point: &Point, max_payload_bytes: Option<usize>) -> Result<()> {
+        self.validate_write(point)?;
+        match max_payload_bytes {
+            Some(limit) if point.payload_size() > limit => {
+                Err(Error::PayloadTooLarge { size: point.payload_size(), limit })
             }
+            _ => Ok(()),
         }
-        
-        self.pending_points.write().clear();
-        Ok(())
     }
 
-    /// Batch insert multiple points
-    pub fn batch_upsert(&self, points: Vec<Point>) -> Result<()> {
-        self.start_batch();
+    /// Insert points read back from durable storage, keeping their stored
+    /// versions. Nothing is journaled.
+    pub fn load_points(&self, points: impl IntoIterator<Item = Point>) -> Result<()> {
+        let _writer = self.write_lock.lock();
         for point in points {
-            self.upsert(point)?;
+            self.validate_point(&point)?;
+            let id = point.id.to_string();
+            self.insert_point(point, false)?;
+            self.note_rebuild_writes(&[id]);
         }
-        self.end_batch()?;
+        self.after_write();
         Ok(())
     }
 
-    /// Batch insert with optional pre-warming
-    pub fn batch_upsert_with_prewarm(&self, points: Vec<Point>, prewarm: bool) -> Result<()> {
-        self.batch_upsert(points)?;
-        if prewarm {
-            self.prewarm_index()?;
-        }
-        Ok(())
+    /// Insert or update a point
+    pub fn upsert(&self, point: Point) -> Result<()> {
+        self.upsert_with_status(point).map(|_| ())
     }
 
-    /// Get a point by ID
-    #[inline]
-    pub fn get(&self, id: &str) -> Option<Point> {
-        self.points.read().get(id).cloned()
+    /// Insert or update a point, telling which of the two it was
+    pub fn upsert_with_status(&self, point: Point) -> Result<UpsertStatus> {
+        self.validate_write(&point)?;
+        let _writer = self.writer()?;
+        self.upsert_locked(point)
     }
 
-    /// Delete a point by ID
-    pub fn delete(&self, id: &str) -> Result<bool> {
-        if let Some(hnsw) = &self.hnsw {
-            let mut index = hnsw.write();
-            index.remove(id);
+    /// Apply the duplicate policy to a validated point and store it. The
+    /// caller holds the write lock.
+    fn upsert_locked(&self, point: Point) -> Result<UpsertStatus> {
+        let mut versioned_point = point;
+        self.tag_language(&mut versioned_point);
+        if let Some(duplicate_of) = self.duplicate_of(&versioned_point) {
+            match self.duplicate_policy() {
+                DuplicatePolicy::Allow => {}
+                DuplicatePolicy::Reject => {
+                    return Err(Error::DuplicateVector { id: versioned_point.id.to_string(), duplicate_of });
+                }
+                DuplicatePolicy::Merge => {
+                    if let Some(payload) = versioned_point.payload {
+                        self.mutate_point_locked(&duplicate_of, OperationType::SetPayload, |point| {
+                            merge_payload(point, payload);
+                        })?;
+                    }
+                    return Ok(UpsertStatus::Merged(duplicate_of));
+                }
+                DuplicatePolicy::Tag => {
+                    let original = self.points.read().get(&duplicate_of).map(|point| point.id.clone());
+                    let payload = versioned_point.payload.get_or_insert_with(|| serde_json::json!({}));
+                    if let (Some(payload), Some(original)) = (payload.as_object_mut(), original) {
+                        payload.insert(dedup::DUPLICATE_OF_FIELD.to_string(), serde_json::json!(original));
+                    }
+                }
+            }
         }
+        let new_version = self.points.read()
+            .get(&versioned_point.id.to_string())
+            .map_or(0, |existing| existing.version + 1);
+        versioned_point.version = new_version;
+        self.insert_point(versioned_point, true)?;
+        self.after_write();
+        Ok(if new_version == 0 { UpsertStatus::Inserted } else { UpsertStatus::Updated })
+    }
 
-        if let Some(bm25) = &self.bm25 {
-            let mut index = bm25.write();
-            index.delete_doc(id);
-        }
+    /// Index and store a point whose version is already set, journaling it
+    /// if `record` is set. The caller holds the write lock. Nothing changes
+    /// if the point can't join the HNSW graph or the point store refuses it.
+    fn insert_point(&self, mut versioned_point: Point, record: bool) -> Result<()> {
+        self.normalize_on_write(&mut versioned_point.vector);
+        let id_str = versioned_point.id.to_string();
 
-        let mut points = self.points.write();
-        Ok(points.remove(id).is_some())
-    }
+        // Points written in a batch are linked into the graph by `end_batch`
+        let in_batch = record && *self.batch_mode.read();
+        let old = self.points.read().get(&id_str).cloned();
+        // Stored before the graph is write-locked, so searches go on while
+        // the store writes
+        let staged = {
+            let graph = if in_batch { None } else { self.built_graph_read() };
+            self.stage_write(id_str.clone(), old.as_ref(), Some(versioned_point), !in_batch, graph.as_deref())?
+        };
+        if let Some(point) = &staged.point {
+            self.persist_point(point)?;
+        }
+        let mut graph = if in_batch { None } else { self.built_graph() };
+        if let Err(e) = self.check_link(&staged, graph.as_deref()) {
+            drop(graph);
+            self.restore_stored(&[id_str], &old.into_iter().map(|p| (p.id.to_string(), p)).collect())?;
+            return Err(e);
+        }
+        self.invalidate_gpu_replica();
+        self.commit_indexes(&staged, graph.as_deref_mut());
+        drop(graph);
 
-    /// Set payload values for a point (merge with existing)
-    pub fn set_payload(&self, id: &str, payload: serde_json::Value) -> Result<bool> {
-        let mut points = self.points.write();
-        if let Some(point) = points.get_mut(id) {
-            if let Some(existing) = &mut point.payload {
-                if let (Some(existing_obj), Some(new_obj)) = (existing.as_object_mut(), payload.as_object()) {
-                    for (key, value) in new_obj {
-                        existing_obj.insert(key.clone(), value.clone());
-                    }
-                }
-            } else {
-                point.payload = Some(payload);
+        let Some(point) = staged.point else { return Ok(()) };
+        if in_batch {
+            if self.hnsw.is_some() {
+                self.fresh_points.add([id_str.as_str()]);
             }
-            Ok(true)
-        } else {
-            Ok(false)
+            self.pending_points.write().push(point.clone());
+            self.store_point(id_str, point);
+            return Ok(());
         }
+        self.store_point(id_str.clone(), point);
+        if record {
+            self.record_operation(OperationType::Upsert, vec![id_str]);
+        }
+        Ok(())
     }
 
-    /// Overwrite entire payload for a point
-    pub fn overwrite_payload(&self, id: &str, payload: serde_json::Value) -> Result<bool> {
-        let mut points = self.points.write();
-        if let Some(point) = points.get_mut(id) {
-            point.payload = Some(payload);
-            Ok(true)
-        } else {
-            Ok(false)
+    /// The HNSW graph, write-locked, if it is built
+    fn built_graph(&self) -> Option<RwLockWriteGuard<'_, HnswIndex>> {
+        let hnsw = self.hnsw.as_ref()?;
+        if !*self.hnsw_built.read() {
+            return None;
         }
+        Some(hnsw.write())
     }
 
-    /// Delete specific payload keys from a point
-    pub fn delete_payload_keys(&self, id: &str, keys: &[String]) -> Result<bool> {
-        let mut points = self.points.write();
-        if let Some(point) = points.get_mut(id) {
-            if let Some(payload) = &mut point.payload {
-                if let Some(obj) = payload.as_object_mut() {
-                    for key in keys {
-                        obj.remove(key);
-                    }
-                }
-            }
-            Ok(true)
-        } else {
-            Ok(false)
+    /// The HNSW graph, read-locked, if it is built
+    fn built_graph_read(&self) -> Option<RwLockReadGuard<'_, HnswIndex>> {
+        let hnsw = self.hnsw.as_ref()?;
+        if !*self.hnsw_built.read() {
+            return None;
         }
+        Some(hnsw.read())
     }
 
-    /// Clear all payload from a point
-    pub fn clear_payload(&self, id: &str) -> Result<bool> {
-        let mut points = self.points.write();
-        if let Some(point) = points.get_mut(id) {
-            point.payload = None;
-            Ok(true)
-        } else {
-            Ok(false)
+    /// Work out how writing `point` (None to delete) over `old` changes the
+    /// indexes, failing if `graph` can't take it. `link` (re)links the
+    /// point's vector in the graph, as upserts do.
+    fn stage_write(&self, id: String, old: Option<&Point>, point: Option<Point>, link: bool, graph: Option<&HnswIndex>) -> Result<StagedWrite> {
+        let link = link && point.is_some();
+        let document = point.as_ref().and_then(|p| self.bm25_document(p));
+        let document = (link || old.and_then(|p| self.bm25_document(p)) != document).then_some(document);
+        let staged = StagedWrite { id, point, link, document };
+        self.check_link(&staged, graph)?;
+        Ok(staged)
+    }
+
+    /// Fail if `graph` can't link the staged point. Checked again once the
+    /// graph is write-locked, as it may have been built since.
+    fn check_link(&self, staged: &StagedWrite, graph: Option<&HnswIndex>) -> Result<()> {
+        match (graph, staged.point.as_ref().filter(|_| staged.link)) {
+            (Some(graph), Some(point)) => graph.check_dim(point.vector.dim()),
+            _ => Ok(()),
         }
     }
 
-    /// Update vector for a point
-    pub fn update_vector(&self, id: &str, vector: Vector) -> Result<bool> {
-        let mut points = self.points.write();
-        if let Some(point) = points.get_mut(id) {
-            point.vector = vector.clone();
-            
-            // Update HNSW index if present
-            if let Some(hnsw) = &self.hnsw {
-                let mut index = hnsw.write();
-                index.remove(id);
-                // Insert the updated point
-                index.insert(point.clone());
+    /// Put the stored copies of `ids` back as they are in `old` (removed if
+    /// absent), undoing a store write whose change the graph then refused
+    fn restore_stored(&self, ids: &[String], old: &HashMap<String, Point>) -> Result<()> {
+        let puts: Vec<Point> = ids.iter().filter_map(|id| old.get(id).cloned()).collect();
+        let removes: Vec<String> = ids.iter().filter(|id| !old.contains_key(*id)).cloned().collect();
+        self.persist(|store| store.apply(&puts, &removes))
+    }
+
+    /// Apply a staged write to the graph and the BM25 index. It was checked
+    /// against `graph` when staged, so nothing here fails.
+    fn commit_indexes(&self, staged: &StagedWrite, graph: Option<&mut HnswIndex>) {
+        if let Some(graph) = graph {
+            if staged.point.is_none() || (staged.link && graph.contains(&staged.id)) {
+                graph.remove(&staged.id);
             }
-            Ok(true)
-        } else {
-            Ok(false)
+            if let Some(point) = staged.point.as_ref().filter(|_| staged.link) {
+                graph.insert(point.clone()).expect("dimension checked when staged");
+            }
+            if staged.point.is_none() || staged.link {
+                self.fresh_points.remove([staged.id.as_str()]);
+            }
+        }
+        if let Some(document) = &staged.document {
+            self.index_document(&staged.id, document.as_ref());
         }
     }
 
-    /// Update multivector for a point
-    pub fn update_multivector(&self, id: &str, multivector: Option<MultiVector>) -> Result<bool> {
-        let mut points = self.points.write();
-        if let Some(point) = points.get_mut(id) {
-            point.multivector = multivector;
-            Ok(true)
-        } else {
-            Ok(false)
+    /// The text BM25 indexes a point under and its language, if the
+    /// collection has BM25 and the point has text
+    fn bm25_document(&self, point: &Point) -> Option<(String, Option<String>)> {
+        if !self.enable_bm25() {
+            return None;
         }
+        self.text_document(point)
     }
 
-    /// Delete vector (set to empty) - for named vectors this would delete specific vector
-    pub fn delete_vector(&self, id: &str) -> Result<bool> {
-        // For now, deleting a vector means deleting the point
-        // In full implementation, named vectors would be individually deletable
-        self.delete(id)
+    /// The text and language a point would be indexed under with BM25
+    fn text_document(&self, point: &Point) -> Option<(String, Option<String>)> {
+        let text = point.payload.as_ref()?.get("text")?.as_str()?;
+        Some((text.to_string(), self.text_language(point).map(str::to_string)))
     }
 
-    /// Create a payload field index
-    pub fn create_payload_index(&self, field_name: &str, index_type: PayloadIndexType) -> Result<bool> {
-        let mut indexes = self.payload_indexes.write();
-        indexes.insert(field_name.to_string(), index_type);
-        Ok(true)
+    /// Index `document` as the BM25 document of `id`, or drop the point's
+    /// document if None
+    fn index_document(&self, id: &str, document: Option<&(String, Option<String>)>) {
+        let mut bm25 = self.bm25.write();
+        let Some(index) = bm25.as_mut() else { return };
+        match document {
+            Some((text, language)) => index.insert_doc_with_language(id, text, language.as_deref()),
+            None => index.delete_doc(id),
+        }
     }
 
-    /// Delete a payload field index
-    pub fn delete_payload_index(&self, field_name: &str) -> Result<bool> {
-        let mut indexes = self.payload_indexes.write();
-        Ok(indexes.remove(field_name).is_some())
+    /// Rebuild the BM25 index from the `text` payload field of every
+    /// point. Documents are tokenized into a new index without holding any
+    /// lock; under the write lock, points whose document changed meanwhile
+    /// are then re-indexed before the new index replaces the old one.
+    /// Reports progress in documents to `ctx`; once cancelled, it stops and
+    /// keeps the old index. Returns the documents indexed, or None if
+    /// cancelled.
+    pub fn rebuild_text_index(&self, ctx: &crate::JobContext) -> Result<Option<usize>> {
+        if !self.enable_bm25() {
+            return Err(Error::InvalidConfig("the collection has no BM25 index".to_string()));
+        }
+        self.build_bm25(ctx)
     }
 
-    /// Get all payload indexes
-    pub fn get_payload_indexes(&self) -> HashMap<String, PayloadIndexType> {
-        self.payload_indexes.read().clone()
+    /// Enable BM25 on a collection created without it, indexing the `text`
+    /// payload field of the points already stored as
+    /// [`Collection::rebuild_text_index`] does. Searches find no text until
+    /// the index is swapped in. Returns the documents indexed, or None if
+    /// cancelled, in which case BM25 stays off.
+    pub fn enable_text_index(&self, ctx: &crate::JobContext) -> Result<Option<usize>> {
+        if self.enable_bm25() {
+            return Err(Error::InvalidConfig("the collection already has a BM25 index".to_string()));
+        }
+        self.build_bm25(ctx)
     }
 
-    /// Check if a field is indexed
-    pub fn is_field_indexed(&self, field_name: &str) -> bool {
-        self.payload_indexes.read().contains_key(field_name)
+    /// Drop the BM25 index; text searches find nothing afterwards
+    pub fn disable_text_index(&self) -> Result<()> {
+        let _writer = self.writer()?;
+        self.bm25_enabled.store(false, Ordering::Release);
+        *self.bm25.write() = None;
+        Ok(())
     }
 
-    /// Pre-warm HNSW index
-    pub fn prewarm_index(&self) -> Result<()> {
-        if let Some(hnsw) = &self.hnsw {
-            let mut built = self.hnsw_built.write();
-            if !*built {
-                let points = self.points.read();
-                if !points.is_empty() {
-                    let mut index = hnsw.write();
-                    *index = HnswIndex::new(16, 3);
-                    for point in points.values() {
-                        index.insert(point.clone());
-                    }
-                    *built = true;
+    fn build_bm25(&self, ctx: &crate::JobContext) -> Result<Option<usize>> {
+        if self.text_index_rebuilding.swap(true, Ordering::AcqRel) {
+            return Err(Error::InvalidConfig("a text index rebuild is already running".to_string()));
+        }
+        let built = self.build_bm25_marked(ctx);
+        self.text_index_rebuilding.store(false, Ordering::Release);
+        built
+    }
+
+    fn build_bm25_marked(&self, ctx: &crate::JobContext) -> Result<Option<usize>> {
+        let documents: HashMap<String, Option<(String, Option<String>)>> = self.points.read()
+            .iter()
+            .map(|(id, point)| (id.clone(), self.text_document(point)))
+            .collect();
+        let total = documents.len();
+        let mut index = BM25Index::new();
+        for (done, (id, document)) in documents.iter().enumerate() {
+            if done % 1000 == 0 {
+                if ctx.is_cancelled() {
+                    return Ok(None);
                 }
+                ctx.set_progress(done, total);
+            }
+            if let Some((text, language)) = document {
+                index.insert_doc_with_language(id, text, language.as_deref());
             }
         }
-        Ok(())
-    }
+        ctx.set_progress(total, total);
 
-    /// Fast brute-force search using SIMD - optimal for small datasets
-    fn brute_force_search(&self, query: &Vector, limit: usize, filter: Option<&dyn Filter>) -> Vec<(Point, f32)> {
-        use rayon::prelude::*;
-        
+        let _writer = self.writer()?;
         let points = self.points.read();
-        let query_slice = query.as_slice();
-        let distance = self.config.distance.clone();
-        
-        // Collect points to a Vec for indexing
-        let point_vec: Vec<_> = points.values().collect();
-        
-        // Parallel scoring - compute scores without cloning points
-        // Only clone the final top-k results
-        // Use parallel only for 10K+ vectors (rayon has overhead)
-        let scored: Vec<(usize, f32)> = if point_vec.len() >= 10000 && filter.is_none() {
-            // Parallel path for larger datasets without filter
-            point_vec
-                .par_iter()
+        for (id, point) in points.iter() {
+            let document = self.text_document(point);
+            if documents.get(id).map_or(true, |indexed| *indexed != document) {
+                match document {
+                    Some((text, language)) => index.insert_doc_with_language(id, &text, language.as_deref()),
+                    None => index.delete_doc(id),
+                }
+            }
+        }
+        for id in documents.keys().filter(|id| !points.contains_key(*id)) {
+            index.delete_doc(id);
+        }
+        let indexed = index.len();
+        *self.bm25.write() = Some(index);
+        self.bm25_enabled.store(true, Ordering::Release);
+        Ok(Some(indexed))
+    }
+
+    /// Whether the indexes are checked against the stored points after
+    /// every write
+    pub fn check_invariants(&self) -> bool {
+        self.check_invariants.load(Ordering::Relaxed)
+    }
+
+    /// Check the HNSW graph, the BM25 index and the sparse document
+    /// frequencies against the stored points after every write, panicking
+    /// on the first disagreement. Each check reads every point, so this is
+    /// meant for tests and debugging.
+    pub fn set_check_invariants(&self, enabled: bool) {
+        self.check_invariants.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Where the HNSW graph, the BM25 index and the sparse document
+    /// frequencies disagree with the stored points. The graph isn't
+    /// checked while it is rebuilt, nor for points of an open batch.
+    pub fn check_index_consistency(&self) -> Vec<IndexViolation> {
+        let _writer = self.write_lock.lock();
+        self.index_violations()
+    }
+
+    /// [`Collection::check_index_consistency`] for a caller holding the
+    /// write lock
+    fn index_violations(&self) -> Vec<IndexViolation> {
+        let mut violations = Vec::new();
+        let graph = self.hnsw.as_ref()
+            .filter(|_| *self.hnsw_built.read() && !self.hnsw_rebuilding.load(Ordering::Acquire))
+            .map(|hnsw| hnsw.read());
+        let df = self.sparse_df.read();
+        let points = self.points.read();
+
+        if let Some(graph) = &graph {
+            let pending: HashSet<String> = self.pending_points.read().iter().map(|p| p.id.to_string()).collect();
+            let mut linked = HashSet::new();
+            for id in graph.node_ids() {
+                if !points.contains_key(&id) {
+                    violations.push(IndexViolation::GraphOrphan { id });
+                } else if !linked.insert(id.clone()) {
+                    violations.push(IndexViolation::GraphDuplicate { id });
+                }
+            }
+            // Points of another dimension can't be linked
+            for (id, point) in points.iter() {
+                let linkable = graph.is_empty() || point.vector.dim() == graph.dim();
+                if linkable && !linked.contains(id) && !pending.contains(id) {
+                    violations.push(IndexViolation::GraphMissing { id: id.clone() });
+                }
+            }
+        }
+
+        if let Some(index) = self.bm25.read().as_ref() {
+            for id in index.doc_ids() {
+                if points.get(id).and_then(|p| self.bm25_document(p)).is_none() {
+                    violations.push(IndexViolation::Bm25Orphan { id: id.to_string() });
+                }
+            }
+            for (id, point) in points.iter() {
+                if self.bm25_document(point).is_some() && !index.contains_doc(id) {
+                    violations.push(IndexViolation::Bm25Missing { id: id.clone() });
+                }
+            }
+        }
+
+        let mut expected = SparseDocFrequencies::new();
+        for point in points.values() {
+            expected.add(point);
+        }
+        if expected != *df {
+            violations.push(IndexViolation::SparseFrequencies);
+        }
+        violations
+    }
+
+    /// With invariant checks on, panic if the write just made left an
+    /// index disagreeing with the stored points. The caller holds the
+    /// write lock.
+    fn after_write(&self) {
+        if !self.check_invariants() {
+            return;
+        }
+        let violations = self.index_violations();
+        assert!(
+            violations.is_empty(),
+            "indexes of collection '{}' disagree with its points: {:?}",
+            self.name(),
+            violations
+        );
+    }
+
+    /// Insert a point, already persisted, into the map, keeping sparse
+    /// document frequencies in step
+    fn store_point(&self, id_str: String, point: Point) {
+        self.trigram_stale.store(true, Ordering::Release);
+        // Keyed once stored, so a rebuild of the keys includes it
+        let keyed = (!self.duplicate_policy().is_default()).then(|| point.clone());
+        {
+            let mut df = self.sparse_df.write();
+            df.add(&point);
+            if let Some(old) = self.points.write().insert(id_str.clone(), point) {
+                df.remove(&old);
+            }
+        }
+        if let Some(point) = keyed {
+            self.register_vector_key(&id_str, &point);
+        }
+    }
+
+    /// Start batch insert mode
+    pub fn start_batch(&self) {
+        *self.batch_mode.write() = true;
+        self.pending_points.write().clear();
+    }
+
+    /// End batch insert mode. The points written in the batch are linked
+    /// into a built HNSW graph, or the graph is rebuilt if the collection
+    /// is large.
+    pub fn end_batch(&self) -> Result<()> {
+        let _writer = self.writer()?;
+        self.end_batch_locked()
+    }
+
+    fn end_batch_locked(&self) -> Result<()> {
+        *self.batch_mode.write() = false;
+        let pending: Vec<Point> = self.pending_points.write().drain(..).collect();
+
+        if let Some(hnsw) = &self.hnsw {
+            const HNSW_REBUILD_THRESHOLD: usize = 10_000;
+            
+            if self.count() > HNSW_REBUILD_THRESHOLD {
+                self.schedule_hnsw_rebuild(hnsw);
+            } else if !pending.is_empty() {
+                // The points as stored now, once each, skipping any the batch deleted
+                let ids: HashSet<String> = pending.iter().map(|p| p.id.to_string()).collect();
+                let stored: Vec<Point> = {
+                    let points = self.points.read();
+                    ids.iter().filter_map(|id| points.get(id).cloned()).collect()
+                };
+                if let Some(mut graph) = self.built_graph() {
+                    let id_refs: Vec<&str> = ids.iter().map(String::as_str).collect();
+                    graph.remove_batch(&id_refs);
+                    let linkable = stored.into_iter()
+                        .filter(|p| graph.is_empty() || p.vector.dim() == graph.dim())
+                        .collect();
+                    graph.insert_batch(linkable)?;
+                    self.fresh_points.remove(id_refs);
+                }
+                // A rebuild would catch up with an overflow on its own
+                if self.fresh_points.overflowed() && !self.hnsw_rebuilding() {
+                    let points = self.points.read();
+                    if let Some(graph) = self.built_graph() {
+                        self.fresh_points.retain_unlinked(&graph, &points);
+                    }
+                }
+            }
+        }
+        
+        let pending_ids: Vec<String> = pending.iter().map(|p| p.id.to_string()).collect();
+        if !pending_ids.is_empty() {
+            self.record_operation(OperationType::Upsert, pending_ids);
+        }
+        self.after_write();
+        Ok(())
+    }
+
+    /// Batch insert multiple points; nothing is inserted if any point is
+    /// invalid. Returns the status of each point, in order.
+    pub fn batch_upsert(&self, points: Vec<Point>) -> Result<Vec<UpsertStatus>> {
+        // Checked and applied under one lock, so no write in between can
+        // make a checked point fail halfway through the batch
+        let _writer = self.writer()?;
+        self.validate_points(&points, None)?;
+        self.start_batch();
+        self.defer_persistence();
+        let mut statuses = Vec::with_capacity(points.len());
+        for point in points {
+            match self.upsert_locked(point) {
+                Ok(status) => statuses.push(status),
+                Err(e) => {
+                    self.discard_deferred();
+                    self.end_batch_locked()?;
+                    return Err(e);
+                }
+            }
+        }
+        let persisted = self.persist_deferred();
+        self.end_batch_locked()?;
+        persisted?;
+        Ok(statuses)
+    }
+
+    /// Batch insert that skips invalid points instead of rejecting the
+    /// batch, including payloads over `max_payload_bytes`. Returns the status
+    /// of each point, in order.
+    pub fn batch_upsert_lenient(&self, points: Vec<Point>, max_payload_bytes: Option<usize>) -> Result<Vec<UpsertStatus>> {
+        let _writer = self.writer()?;
+        self.start_batch();
+        self.defer_persistence();
+        let statuses = points.into_iter()
+            .map(|point| {
+                self.check_point(&point, max_payload_bytes)
+                    .and_then(|()| self.upsert_locked(point))
+                    .unwrap_or_else(|e| UpsertStatus::Failed(e.to_string()))
+            })
+            .collect();
+        let persisted = self.persist_deferred();
+        self.end_batch_locked()?;
+        persisted?;
+        Ok(statuses)
+    }
+
+    /// Batch insert with optional pre-warming
+    pub fn batch_upsert_with_prewarm(&self, points: Vec<Point>, prewarm: bool) -> Result<Vec<UpsertStatus>> {
+        let statuses = self.batch_upsert(points)?;
+        if prewarm {
+            self.prewarm_index()?;
+        }
+        Ok(statuses)
+    }
+
+    /// Apply `operations` all-or-nothing. Every upserted point is validated
+    /// and the change to each point is staged against the indexes before
+    /// anything changes; the resulting point states are then persisted
+    /// with a single store write, swapped in under one write lock of the
+    /// point map and journaled as one `Transaction` entry. Payload operations
+    /// and deletes skip points that don't exist, as they do outside a
+    /// batch. The duplicate policy is not applied to the batch's vectors.
+    /// Returns the ids of the changed points.
+    pub fn apply_atomic(&self, operations: Vec<BatchOperation>) -> Result<Vec<String>> {
+        for operation in &operations {
+            if let BatchOperation::Upsert(points) = operation {
+                for point in points {
+                    self.validate_write(point)?;
+                }
+            }
+        }
+        let _writer = self.writer()?;
+
+        // Final state of every touched point (None once deleted), in the
+        // order the batch first touched them, and its state before
+        let mut order: Vec<String> = Vec::new();
+        let mut staged: HashMap<String, Option<Point>> = HashMap::new();
+        let mut upserted: HashSet<String> = HashSet::new();
+        let mut old: HashMap<String, Point> = HashMap::new();
+        {
+            let points = self.points.read();
+            let current = |staged: &HashMap<String, Option<Point>>, id: &str| -> Option<Point> {
+                match staged.get(id) {
+                    Some(state) => state.clone(),
+                    None => points.get(id).cloned(),
+                }
+            };
+            let mut stage = |staged: &mut HashMap<String, Option<Point>>, id: String, state: Option<Point>| {
+                if !staged.contains_key(&id) {
+                    if let Some(point) = points.get(&id) {
+                        old.insert(id.clone(), point.clone());
+                    }
+                    order.push(id.clone());
+                }
+                staged.insert(id, state);
+            };
+
+            for operation in operations {
+                match operation {
+                    BatchOperation::Upsert(new_points) => {
+                        for mut point in new_points {
+                            let id = point.id.to_string();
+                            point.version = current(&staged, &id).map_or(0, |p| p.version + 1);
+                            self.normalize_on_write(&mut point.vector);
+                            self.tag_language(&mut point);
+                            upserted.insert(id.clone());
+                            stage(&mut staged, id, Some(point));
+                        }
+                    }
+                    BatchOperation::Delete(ids) => {
+                        for id in ids {
+                            if current(&staged, &id).is_some() {
+                                upserted.remove(&id);
+                                stage(&mut staged, id, None);
+                            }
+                        }
+                    }
+                    BatchOperation::SetPayload { ids, payload } => {
+                        for id in ids {
+                            let Some(mut point) = current(&staged, &id) else { continue };
+                            merge_payload(&mut point, payload.clone());
+                            point.version += 1;
+                            stage(&mut staged, id, Some(point));
+                        }
+                    }
+                    BatchOperation::OverwritePayload { ids, payload } => {
+                        for id in ids {
+                            let Some(mut point) = current(&staged, &id) else { continue };
+                            point.payload = Some(payload.clone());
+                            point.version += 1;
+                            stage(&mut staged, id, Some(point));
+                        }
+                    }
+                    BatchOperation::DeletePayload { ids, keys } => {
+                        for id in ids {
+                            let Some(mut point) = current(&staged, &id) else { continue };
+                            remove_payload_keys(&mut point, &keys);
+                            point.version += 1;
+                            stage(&mut staged, id, Some(point));
+                        }
+                    }
+                    BatchOperation::ClearPayload(ids) => {
+                        for id in ids {
+                            let Some(mut point) = current(&staged, &id) else { continue };
+                            point.payload = None;
+                            point.version += 1;
+                            stage(&mut staged, id, Some(point));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Nothing has changed yet; a point the graph can't take fails the
+        // batch. It is stored before the graph is write-locked, as by
+        // `insert_point`.
+        let writes: Vec<StagedWrite> = {
+            let graph = self.built_graph_read();
+            order.iter()
+                .map(|id| {
+                    let state = staged.remove(id).flatten();
+                    self.stage_write(id.clone(), old.get(id), state, upserted.contains(id), graph.as_deref())
+                })
+                .collect::<Result<_>>()?
+        };
+        let puts: Vec<Point> = writes.iter().filter_map(|write| write.point.clone()).collect();
+        let removes: Vec<String> = writes.iter()
+            .filter(|write| write.point.is_none() && old.contains_key(&write.id))
+            .map(|write| write.id.clone())
+            .collect();
+        self.persist(|store| store.apply(&puts, &removes))?;
+        let mut graph = self.built_graph();
+        if let Err(e) = writes.iter().try_for_each(|write| self.check_link(write, graph.as_deref())) {
+            drop(graph);
+            self.restore_stored(&order, &old)?;
+            return Err(e);
+        }
+        self.invalidate_gpu_replica();
+        for write in &writes {
+            self.commit_indexes(write, graph.as_deref_mut());
+        }
+        drop(graph);
+
+        // Same lock order as `store_point`: frequencies, then points
+        let mut df = self.sparse_df.write();
+        let mut points = self.points.write();
+        self.trigram_stale.store(true, Ordering::Release);
+        for write in writes {
+            match write.point {
+                Some(point) => {
+                    df.add(&point);
+                    if let Some(old) = points.insert(write.id, point) {
+                        df.remove(&old);
+                    }
+                }
+                None => {
+                    if let Some(old) = points.remove(&write.id) {
+                        df.remove(&old);
+                    }
+                }
+            }
+        }
+        drop(points);
+        drop(df);
+        for point in &puts {
+            self.register_vector_key(&point.id.to_string(), point);
+        }
+
+        if !order.is_empty() {
+            self.record_operation(OperationType::Transaction, order.clone());
+        }
+        self.after_write();
+        Ok(order)
+    }
+
+    /// Get a point by ID
+    #[inline]
+    pub fn get(&self, id: &str) -> Option<Point> {
+        self.points.read().get(id).cloned()
+    }
+
+    /// Delete a point by ID
+    pub fn delete(&self, id: &str) -> Result<bool> {
+        let _writer = self.writer()?;
+        if !self.points.read().contains_key(id) {
+            return Ok(false);
+        }
+        self.persist_removals(&[id.to_string()])?;
+        self.invalidate_gpu_replica();
+
+        if let Some(hnsw) = &self.hnsw {
+            let mut index = hnsw.write();
+            index.remove(id);
+        }
+        self.fresh_points.remove([id]);
+
+        if let Some(index) = self.bm25.write().as_mut() {
+            index.delete_doc(id);
+        }
+
+        let removed = self.points.write().remove(id);
+        if let Some(old) = &removed {
+            self.sparse_df.write().remove(old);
+            self.trigram_stale.store(true, Ordering::Release);
+        }
+        let removed = removed.is_some();
+        if removed {
+            self.record_operation(OperationType::Delete, vec![id.to_string()]);
+        }
+        self.after_write();
+        Ok(removed)
+    }
+
+    /// Delete several points in one pass: the points and each index are
+    /// locked once, and the removal is journaled as one operation. Ids that
+    /// do not exist are skipped. Returns the number of points removed.
+    pub fn delete_batch(&self, ids: &[String]) -> Result<usize> {
+        let _writer = self.writer()?;
+        let removed: Vec<String> = {
+            let points = self.points.read();
+            let mut seen = HashSet::new();
+            ids.iter()
+                .filter(|id| points.contains_key(id.as_str()) && seen.insert(id.as_str()))
+                .cloned()
+                .collect()
+        };
+        if removed.is_empty() {
+            return Ok(0);
+        }
+        self.persist_removals(&removed)?;
+        {
+            let mut sparse_df = self.sparse_df.write();
+            let mut points = self.points.write();
+            for id in &removed {
+                if let Some(old) = points.remove(id) {
+                    sparse_df.remove(&old);
+                }
+            }
+        }
+
+        self.invalidate_gpu_replica();
+        self.trigram_stale.store(true, Ordering::Release);
+        // Searches in between skip graph results whose point is gone
+        if let Some(hnsw) = &self.hnsw {
+            let id_refs: Vec<&str> = removed.iter().map(String::as_str).collect();
+            hnsw.write().remove_batch(&id_refs);
+        }
+        self.fresh_points.remove(removed.iter().map(String::as_str));
+        if let Some(index) = self.bm25.write().as_mut() {
+            for id in &removed {
+                index.delete_doc(id);
+            }
+        }
+        let count = removed.len();
+        self.record_operation(OperationType::Delete, removed);
+        self.after_write();
+        Ok(count)
+    }
+
+    /// Bump the version of a changed copy of a stored point and persist
+    /// it; the caller swaps it in once this succeeds. Every in-place
+    /// mutation goes through here, so any change to a point is visible to
+    /// clients comparing versions.
+    fn commit_change(&self, point: &mut Point) -> Result<()> {
+        point.version += 1;
+        self.persist_point(point)
+    }
+
+    /// Apply `change` to a stored point, then commit and journal it,
+    /// re-indexing its text if that changed. Returns the new version, or
+    /// None if the point does not exist.
+    fn mutate_point(&self, id: &str, operation: OperationType, change: impl FnOnce(&mut Point)) -> Result<Option<u64>> {
+        let _writer = self.writer()?;
+        self.mutate_point_locked(id, operation, change)
+    }
+
+    /// [`Collection::mutate_point`] for a caller holding the write lock
+    fn mutate_point_locked(&self, id: &str, operation: OperationType, change: impl FnOnce(&mut Point)) -> Result<Option<u64>> {
+        let Some(mut point) = self.get(id) else { return Ok(None) };
+        let document = self.bm25_document(&point);
+        change(&mut point);
+        let changed_document = Some(self.bm25_document(&point)).filter(|new| *new != document);
+        self.commit_change(&mut point)?;
+        let version = point.version;
+        self.trigram_stale.store(true, Ordering::Release);
+        self.points.write().insert(id.to_string(), point);
+
+        if let Some(document) = changed_document {
+            self.index_document(id, document.as_ref());
+        }
+        self.record_operation(operation, vec![id.to_string()]);
+        self.after_write();
+        Ok(Some(version))
+    }
+
+    /// Set payload values for a point (merge with existing). Returns the
+    /// point's new version, or None if it does not exist.
+    pub fn set_payload(&self, id: &str, payload: serde_json::Value) -> Result<Option<u64>> {
+        self.mutate_point(id, OperationType::SetPayload, |point| merge_payload(point, payload))
+    }
+
+    /// Overwrite entire payload for a point. Returns the point's new
+    /// version, or None if it does not exist.
+    pub fn overwrite_payload(&self, id: &str, payload: serde_json::Value) -> Result<Option<u64>> {
+        self.mutate_point(id, OperationType::OverwritePayload, |point| {
+            point.payload = Some(payload);
+        })
+    }
+
+    /// Delete specific payload keys from a point. Returns the point's new
+    /// version, or None if it does not exist.
+    pub fn delete_payload_keys(&self, id: &str, keys: &[String]) -> Result<Option<u64>> {
+        self.mutate_point(id, OperationType::DeletePayload, |point| remove_payload_keys(point, keys))
+    }
+
+    /// Clear all payload from a point. Returns the point's new version, or
+    /// None if it does not exist.
+    pub fn clear_payload(&self, id: &str) -> Result<Option<u64>> {
+        self.mutate_point(id, OperationType::ClearPayload, |point| {
+            point.payload = None;
+        })
+    }
+
+    /// Update vector for a point. Returns the point's new version, or None
+    /// if it does not exist.
+    pub fn update_vector(&self, id: &str, vector: Vector) -> Result<Option<u64>> {
+        Ok(self.update_vectors(vec![(id.to_string(), vector)], false)?
+            .pop()
+            .map(|(_, version)| version))
+    }
+
+    /// Replace the dense vectors of many points. The HNSW graph is updated
+    /// under a single write lock: all old nodes are removed in one pass, then
+    /// the new vectors are inserted with neighbour pruning deferred to the
+    /// end. With `relink`, the graph is instead rebuilt from scratch by a
+    /// background job (unless one is already running), which gives better
+    /// link quality after large refreshes.
+    /// Unknown ids are skipped; returns the id and new version of each
+    /// updated point.
+    pub fn update_vectors(&self, updates: Vec<(String, Vector)>, relink: bool) -> Result<Vec<(String, u64)>> {
+        if self.vector_dim() > 0 {
+            if let Some((_, vector)) = updates.iter().find(|(_, v)| v.dim() != self.stored_dim()) {
+                return Err(Error::InvalidDimension {
+                    expected: self.stored_dim(),
+                    actual: vector.dim(),
+                });
+            }
+        } else if updates.iter().any(|(_, v)| v.dim() > 0) {
+            return Err(self.no_dense_vectors());
+        }
+
+        let _writer = self.writer()?;
+        let hnsw_built = *self.hnsw_built.read();
+        if let Some(hnsw) = self.hnsw.as_ref().filter(|_| hnsw_built) {
+            let graph = hnsw.read();
+            for (_, vector) in &updates {
+                graph.check_dim(vector.dim())?;
+            }
+        }
+
+        // The changed copies are persisted in one store write before any
+        // is swapped in
+        let mut updated: Vec<Point> = Vec::with_capacity(updates.len());
+        let mut latest: HashMap<String, usize> = HashMap::new();
+        self.defer_persistence();
+        for (id, mut vector) in updates {
+            let current = match latest.get(&id) {
+                Some(&i) => Some(updated[i].clone()),
+                None => self.get(&id),
+            };
+            let Some(mut point) = current else { continue };
+            self.normalize_on_write(&mut vector);
+            point.vector = vector;
+            if let Err(e) = self.commit_change(&mut point) {
+                self.deferred.lock().take();
+                return Err(e);
+            }
+            latest.insert(id, updated.len());
+            updated.push(point);
+        }
+        self.persist_deferred()?;
+        if updated.is_empty() {
+            return Ok(Vec::new());
+        }
+        {
+            let mut points = self.points.write();
+            for point in &updated {
+                points.insert(point.id.to_string(), point.clone());
+            }
+        }
+        self.invalidate_gpu_replica();
+
+        if let Some(hnsw) = self.hnsw.as_ref().filter(|_| hnsw_built) {
+            let ids: Vec<String> = updated.iter().map(|p| p.id.to_string()).collect();
+            let id_refs: Vec<&str> = ids.iter().map(String::as_str).collect();
+            if relink && self.schedule_hnsw_rebuild(hnsw) {
+                // The graph links the old vectors until the rebuild is done
+                self.fresh_points.add(id_refs);
+            } else {
+                let mut index = hnsw.write();
+                index.remove_batch(&id_refs);
+                index.insert_batch(updated.clone())?;
+                self.fresh_points.remove(id_refs);
+            }
+        }
+
+        let versions: Vec<(String, u64)> = updated.iter()
+            .map(|p| (p.id.to_string(), p.version))
+            .collect();
+        self.record_operation(
+            OperationType::UpdateVectors,
+            versions.iter().map(|(id, _)| id.clone()).collect(),
+        );
+        self.after_write();
+        Ok(versions)
+    }
+
+    /// Rebuild the HNSW graph from the current points on the background
+    /// system. Returns false if a rebuild is already running.
+    fn schedule_hnsw_rebuild(&self, hnsw: &Arc<RwLock<HnswIndex>>) -> bool {
+        if !self.start_rebuild() {
+            return false;
+        }
+        let points: Vec<Point> = self.points.read().values().cloned().collect();
+        let job = self.rebuild_job(hnsw, points, RebuildReason::Relink);
+        crate::background::get_background_system().submit(Box::new(job));
+        true
+    }
+
+    /// Mark a rebuild of the HNSW graph started. False if one is already
+    /// running.
+    fn start_rebuild(&self) -> bool {
+        !self.hnsw_rebuilding.swap(true, Ordering::AcqRel)
+    }
+
+    /// The job linking `points` into a new graph for `hnsw` by the current
+    /// distance, once [`Collection::start_rebuild`] marked it started
+    fn rebuild_job(&self, hnsw: &Arc<RwLock<HnswIndex>>, points: Vec<Point>, reason: RebuildReason) -> HnswRebuildJob {
+        HnswRebuildJob {
+            collection: self.name().to_string(),
+            distance: self.distance(),
+            points,
+            hnsw: hnsw.clone(),
+            built_flag: self.hnsw_built.clone(),
+            built_at: self.hnsw_built_at.clone(),
+            rebuilding_flag: self.hnsw_rebuilding.clone(),
+            backlog: self.rebuild_backlog.clone(),
+            reason,
+        }
+    }
+
+    /// Whether the HNSW graph is being built in the background with no
+    /// graph to search meanwhile, so searches fall back to brute force
+    pub fn awaiting_index(&self) -> bool {
+        self.hnsw_rebuilding.load(Ordering::Acquire) && !*self.hnsw_built.read()
+    }
+
+    /// Whether the HNSW graph is being built or rebuilt in the background
+    pub fn hnsw_rebuilding(&self) -> bool {
+        self.hnsw_rebuilding.load(Ordering::Acquire)
+    }
+
+    /// Points written since a running HNSW rebuild copied the points; the
+    /// rebuild links them into its new graph before swapping it in
+    pub fn rebuild_backlog(&self) -> usize {
+        self.rebuild_backlog.len()
+    }
+
+    /// Points stored but not yet linked into the HNSW graph they will end
+    /// up in: those of an open batch and the rebuild backlog
+    pub fn pending_points(&self) -> usize {
+        self.pending_points.read().len() + self.rebuild_backlog()
+    }
+
+    /// The built HNSW graph, None without one
+    fn built_hnsw(&self) -> Option<&Arc<RwLock<HnswIndex>>> {
+        self.hnsw.as_ref().filter(|_| *self.hnsw_built.read())
+    }
+
+    /// Every node of the HNSW graph with its links, for analysis; None if
+    /// the collection has no built graph
+    pub fn hnsw_graph(&self) -> Option<Vec<GraphNode>> {
+        self.built_hnsw().map(|hnsw| hnsw.read().export_graph())
+    }
+
+    /// Degrees and connectivity of each layer of the HNSW graph; None if
+    /// the collection has no built graph
+    pub fn hnsw_graph_stats(&self) -> Option<GraphStats> {
+        self.built_hnsw().map(|hnsw| hnsw.read().graph_stats())
+    }
+
+    /// Change the distance metric. Returns false if it is unchanged, or if
+    /// either metric is Hamming, whose vectors are stored packed; otherwise
+    /// the HNSW graph is rebuilt as by [`Collection::set_graph_config`].
+    pub fn set_distance(&self, distance: Distance) -> Result<bool> {
+        let current = self.distance();
+        if current == distance || current == Distance::Hamming || distance == Distance::Hamming {
+            return Ok(false);
+        }
+        self.set_graph_config(distance, self.hnsw_params())
+    }
+
+    /// Change the distance metric and HNSW parameters together, relinking
+    /// the graph once. A new distance, or a new `m` or `ef_construct` of a
+    /// non-empty collection, rebuilds the graph in the background (see
+    /// [`Collection::reindex`]); the distance is kept if either is Hamming.
+    /// Nothing changes if the normalized vectors can't be persisted, or
+    /// while a rebuild is running, as it links by the old settings.
+    /// Returns false if nothing changed.
+    pub fn set_graph_config(&self, distance: Distance, params: HnswParams) -> Result<bool> {
+        let current = self.distance();
+        let distance = if current == Distance::Hamming || distance == Distance::Hamming { current } else { distance };
+        let old = self.hnsw_params();
+        if distance == current && params == old {
+            return Ok(false);
+        }
+        let relinked = (old.m, old.ef_construct) != (params.m, params.ef_construct);
+        let rebuild = self.hnsw.as_ref()
+            .filter(|_| distance != current || (relinked && self.count() > 0));
+        if rebuild.is_some() && !self.start_rebuild() {
+            return Err(Error::Rebuilding);
+        }
+        if distance != current {
+            *self.distance.write() = distance;
+            if let Err(e) = self.normalize_stored() {
+                *self.distance.write() = current;
+                if rebuild.is_some() {
+                    self.hnsw_rebuilding.store(false, Ordering::Release);
+                }
+                return Err(e);
+            }
+            self.invalidate_gpu_replica();
+        }
+        *self.hnsw_params.write() = params;
+        if let Some(hnsw) = self.hnsw.as_ref().filter(|_| relinked) {
+            hnsw.write().set_construction_params(params.m, params.ef_construct);
+        }
+        if let Some(hnsw) = rebuild {
+            let job = self.started_reindex_job(hnsw);
+            crate::background::get_background_system().submit(Box::new(job));
+        }
+        Ok(true)
+    }
+
+    /// Rebuild the HNSW graph from the stored vectors on a background thread,
+    /// linking them by the current distance. Searches use the old graph
+    /// until the new one is complete. Returns false if the collection has no graph
+    /// or a rebuild is already running.
+    pub fn reindex(&self) -> bool {
+        let Some(job) = self.reindex_job() else { return false };
+        crate::background::get_background_system().submit(Box::new(job));
+        true
+    }
+
+    /// The job [`Collection::reindex`] runs, marking the rebuild started;
+    /// None if the collection has no graph or a rebuild is already running
+    pub(crate) fn reindex_job(&self) -> Option<HnswRebuildJob> {
+        let hnsw = self.hnsw.as_ref()?;
+        if !self.start_rebuild() {
+            return None;
+        }
+        Some(self.started_reindex_job(hnsw))
+    }
+
+    /// The job of a reindex [`Collection::start_rebuild`] marked started
+    fn started_reindex_job(&self, hnsw: &Arc<RwLock<HnswIndex>>) -> HnswRebuildJob {
+        let points: Vec<Point> = self.points.read().values().cloned().collect();
+        *self.reindex_progress.write() = Some(ReindexProgress { indexed: 0, total: points.len() });
+        self.rebuild_job(hnsw, points, RebuildReason::Reindex(self.reindex_progress.clone()))
+    }
+
+    /// Change when cosine vectors are normalized. Switching to `OnWrite`
+    /// normalizes the stored vectors of a cosine collection in place; their
+    /// original lengths are lost. Returns false if the policy is unchanged.
+    /// Nothing changes if the normalized vectors can't be persisted.
+    pub fn set_normalization(&self, policy: NormalizationPolicy) -> Result<bool> {
+        let previous = std::mem::replace(&mut *self.normalization.write(), policy);
+        if previous == policy {
+            return Ok(false);
+        }
+        if let Err(e) = self.normalize_stored() {
+            *self.normalization.write() = previous;
+            return Err(e);
+        }
+        self.invalidate_gpu_replica();
+        self.rebuild_vector_keys();
+        Ok(true)
+    }
+
+    pub fn normalization(&self) -> NormalizationPolicy {
+        *self.normalization.read()
+    }
+
+    /// Change what upserts do with a vector another point already has.
+    /// Turning detection on keys every stored vector; turning it off drops
+    /// the keys.
+    pub fn set_duplicate_policy(&self, policy: DuplicatePolicy) {
+        *self.duplicate_policy.write() = policy;
+        self.rebuild_vector_keys();
+    }
+
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        *self.duplicate_policy.read()
+    }
+
+    fn rebuild_vector_keys(&self) {
+        let mut keys: HashMap<u64, Vec<String>> = HashMap::new();
+        if !self.duplicate_policy().is_default() {
+            let points = self.points.read();
+            for (id, point) in points.iter().filter(|(_, point)| has_dense_vector(point)) {
+                let hash = dedup::key_hash(&dedup::vector_key(point.vector.as_slice()));
+                keys.entry(hash).or_default().push(id.clone());
+            }
+        }
+        *self.vector_keys.write() = keys;
+    }
+
+    /// `vector` as it would be stored
+    fn stored_vector(&self, vector: &Vector) -> Vector {
+        let mut vector = vector.clone();
+        self.normalize_on_write(&mut vector);
+        vector
+    }
+
+    /// Id of another stored point whose vector has the quantized `key`
+    fn find_duplicate(&self, id: &str, key: &[i64]) -> Option<String> {
+        let keys = self.vector_keys.read();
+        let candidates = keys.get(&dedup::key_hash(key))?;
+        // A point may have been deleted or given another vector since
+        let points = self.points.read();
+        candidates.iter()
+            .filter(|candidate| candidate.as_str() != id)
+            .find(|candidate| points.get(*candidate).is_some_and(|p| dedup::vector_key(p.vector.as_slice()) == key))
+            .cloned()
+    }
+
+    /// Id of another stored point `point` would duplicate, while duplicates
+    /// are detected
+    fn duplicate_of(&self, point: &Point) -> Option<String> {
+        if self.duplicate_policy().is_default() || !has_dense_vector(point) {
+            return None;
+        }
+        let key = dedup::vector_key(self.stored_vector(&point.vector).as_slice());
+        self.find_duplicate(&point.id.to_string(), &key)
+    }
+
+    /// Add `point`, just stored as `id`, to the points its vector's key
+    /// leads to, dropping the stale entries next to it
+    fn register_vector_key(&self, id: &str, point: &Point) {
+        if self.duplicate_policy().is_default() || !has_dense_vector(point) {
+            return;
+        }
+        let hash = dedup::key_hash(&dedup::vector_key(point.vector.as_slice()));
+        let stale = {
+            let mut keys = self.vector_keys.write();
+            let points = self.points.read();
+            let ids = keys.entry(hash).or_default();
+            ids.retain(|other| {
+                other != id
+                    && points.get(other)
+                        .is_some_and(|p| dedup::key_hash(&dedup::vector_key(p.vector.as_slice())) == hash)
+            });
+            ids.push(id.to_string());
+            // Keys of points since deleted or changed are only dropped when
+            // a point lands next to them; start over once they pile up
+            keys.len() > 2 * points.len() + 1024
+        };
+        if stale {
+            self.rebuild_vector_keys();
+        }
+    }
+
+    /// Whether stored dense vectors are unit length, as cosine scoring with
+    /// `OnWrite` assumes
+    fn stores_normalized(&self) -> bool {
+        self.distance() == Distance::Cosine && self.normalization() == NormalizationPolicy::OnWrite
+    }
+
+    fn normalize_on_write(&self, vector: &mut Vector) {
+        if self.stores_normalized() {
+            vector.normalize();
+        }
+    }
+
+    /// Bring the stored vectors in line with the current policy, persisting
+    /// them in one store write before they change in memory
+    fn normalize_stored(&self) -> Result<()> {
+        if !self.stores_normalized() {
+            return Ok(());
+        }
+        let mut points = self.points.write();
+        let normalized: Vec<Point> = points.values()
+            .map(|point| {
+                let mut point = point.clone();
+                point.vector.normalize();
+                point
+            })
+            .collect();
+        self.persist(|store| store.apply(&normalized, &[]))?;
+        for point in normalized {
+            points.insert(point.id.to_string(), point);
+        }
+        Ok(())
+    }
+
+    /// The query as it is compared with stored vectors: normalized for
+    /// cosine, as given otherwise
+    fn prepare_query(&self, query: &Vector) -> Vector {
+        match self.distance() {
+            Distance::Cosine => query.normalized(),
+            _ => query.clone(),
+        }
+    }
+
+    /// Progress of the running reindex, if any
+    pub fn reindex_progress(&self) -> Option<ReindexProgress> {
+        *self.reindex_progress.read()
+    }
+
+    /// Update multivector for a point. Returns the point's new version, or
+    /// None if it does not exist.
+    pub fn update_multivector(&self, id: &str, multivector: Option<MultiVector>) -> Result<Option<u64>> {
+        self.mutate_point(id, OperationType::UpdateVectors, |point| {
+            point.multivector = multivector;
+        })
+    }
+
+    /// Set or remove the named multivector `name` of a point. Returns the
+    /// point's new version, or None if it does not exist.
+    pub fn update_named_multivector(&self, id: &str, name: &str, multivector: Option<MultiVector>) -> Result<Option<u64>> {
+        if let Some(multivector) = &multivector {
+            let params = self.named_multivector(name).ok_or_else(|| {
+                Error::UnknownVector(format!("multivector '{}' is not declared by the collection", name))
+            })?;
+            if multivector.dim() != params.size {
+                return Err(Error::InvalidDimension { expected: params.size, actual: multivector.dim() });
+            }
+        }
+        self.mutate_point(id, OperationType::UpdateVectors, |point| {
+            match multivector {
+                Some(multivector) => point.add_named_multivector(name.to_string(), multivector),
+                None => {
+                    point.named_multivectors.remove(name);
+                }
+            }
+        })
+    }
+
+    /// Delete vector (set to empty) - for named vectors this would delete specific vector
+    pub fn delete_vector(&self, id: &str) -> Result<bool> {
+        // For now, deleting a vector means deleting the point
+        // In full implementation, named vectors would be individually deletable
+        self.delete(id)
+    }
+
+    /// Create a payload field index
+    pub fn create_payload_index(&self, field_name: &str, index_type: PayloadIndexType) -> Result<bool> {
+        self.create_index_with(field_name, index_type, KeywordParams::default())
+    }
+
+    /// Create a keyword index whose values are compared as `params` say
+    pub fn create_keyword_index(&self, field_name: &str, params: KeywordParams) -> Result<bool> {
+        self.create_index_with(field_name, PayloadIndexType::Keyword, params)
+    }
+
+    fn create_index_with(&self, field_name: &str, index_type: PayloadIndexType, params: KeywordParams) -> Result<bool> {
+        {
+            let mut indexes = self.payload_indexes.write();
+            let mut keyword_params = self.keyword_params.write();
+            indexes.insert(field_name.to_string(), index_type);
+            if params.is_default() {
+                if keyword_params.contains_key(field_name) {
+                    Arc::make_mut(&mut keyword_params).remove(field_name);
+                }
+            } else {
+                Arc::make_mut(&mut keyword_params).insert(field_name.to_string(), params);
+            }
+        }
+        self.trigram_stale.store(true, Ordering::Release);
+        self.record_operation(OperationType::CreateIndex, Vec::new());
+        Ok(true)
+    }
+
+    /// Delete a payload field index
+    pub fn delete_payload_index(&self, field_name: &str) -> Result<bool> {
+        let removed = self.payload_indexes.write().remove(field_name).is_some();
+        if removed {
+            let mut keyword_params = self.keyword_params.write();
+            if keyword_params.contains_key(field_name) {
+                Arc::make_mut(&mut keyword_params).remove(field_name);
+            }
+            drop(keyword_params);
+            self.trigram_indexes.write().remove(field_name);
+            self.record_operation(OperationType::DeleteIndex, Vec::new());
+        }
+        Ok(removed)
+    }
+
+    /// Replace the payload index definitions and the options of keyword
+    /// indexes, e.g. with the ones restored from a manifest; the index
+    /// structures are rebuilt on first use
+    pub fn set_payload_indexes(
+        &self,
+        indexes: HashMap<String, PayloadIndexType>,
+        keyword_params: HashMap<String, KeywordParams>,
+    ) {
+        *self.payload_indexes.write() = indexes;
+        *self.keyword_params.write() = Arc::new(keyword_params);
+        self.trigram_indexes.write().clear();
+        self.trigram_stale.store(true, Ordering::Release);
+    }
+
+    /// Get all payload indexes
+    pub fn get_payload_indexes(&self) -> HashMap<String, PayloadIndexType> {
+        self.payload_indexes.read().clone()
+    }
+
+    /// Comparison options of the keyword indexes that have any
+    pub fn keyword_params(&self) -> Arc<HashMap<String, KeywordParams>> {
+        self.keyword_params.read().clone()
+    }
+
+    /// Number of points with a value for each indexed payload field
+    pub fn payload_index_points(&self) -> HashMap<String, usize> {
+        let mut counts: HashMap<String, usize> = self.payload_indexes.read().keys()
+            .map(|field| (field.clone(), 0))
+            .collect();
+        for point in self.points.read().values() {
+            let Some(payload) = &point.payload else { continue };
+            for (field, count) in counts.iter_mut() {
+                if payload.get(field).is_some_and(|v| !v.is_null()) {
+                    *count += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// Check if a field is indexed
+    pub fn is_field_indexed(&self, field_name: &str) -> bool {
+        self.payload_indexes.read().contains_key(field_name)
+    }
+
+    /// String values of `field` within `max_edits` edits of `value`, looked
+    /// up in the field's trigram index. None if the field has no keyword
+    /// index.
+    pub fn fuzzy_matches(&self, field: &str, value: &str, max_edits: usize) -> Option<Vec<String>> {
+        if self.payload_indexes.read().get(field) != Some(&PayloadIndexType::Keyword) {
+            return None;
+        }
+        self.refresh_trigram_indexes();
+        let params = self.keyword_params.read().get(field).copied().unwrap_or_default();
+        self.trigram_indexes.read().get(field).map(|index| index.matches(&params.apply(value), max_edits))
+    }
+
+    /// Rewrite `Fuzzy` conditions on keyword-indexed fields into exact
+    /// matches of the values found in the trigram index, so the filter
+    /// doesn't compute edit distances point by point
+    pub fn resolve_fuzzy(&self, condition: FilterCondition) -> FilterCondition {
+        match condition {
+            FilterCondition::Fuzzy { field, value, max_edits } => {
+                match self.fuzzy_matches(&field, &value, max_edits) {
+                    Some(values) => FilterCondition::Or(values.into_iter()
+                        .map(|v| FilterCondition::Equals { field: field.clone(), value: serde_json::Value::String(v) })
+                        .collect()),
+                    None => FilterCondition::Fuzzy { field, value, max_edits },
+                }
+            }
+            FilterCondition::And(conditions) => {
+                FilterCondition::And(conditions.into_iter().map(|c| self.resolve_fuzzy(c)).collect())
+            }
+            FilterCondition::Or(conditions) => {
+                FilterCondition::Or(conditions.into_iter().map(|c| self.resolve_fuzzy(c)).collect())
+            }
+            FilterCondition::Not(condition) => FilterCondition::Not(Box::new(self.resolve_fuzzy(*condition))),
+            other => other,
+        }
+    }
+
+    /// Rebuild the trigram indexes if points changed since the last build
+    fn refresh_trigram_indexes(&self) {
+        if !self.trigram_stale.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        let fields: Vec<String> = self.payload_indexes.read().iter()
+            .filter(|(_, index_type)| **index_type == PayloadIndexType::Keyword)
+            .map(|(field, _)| field.clone())
+            .collect();
+        let keyword_params = self.keyword_params();
+        let mut indexes: HashMap<String, TrigramIndex> = fields.iter()
+            .map(|field| (field.clone(), TrigramIndex::new()))
+            .collect();
+        for point in self.points.read().values() {
+            let Some(payload) = &point.payload else { continue };
+            for (field, index) in indexes.iter_mut() {
+                if let Some(value) = payload.get(field).and_then(|v| v.as_str()) {
+                    match keyword_params.get(field) {
+                        Some(params) => index.insert(&params.apply(value)),
+                        None => index.insert(value),
+                    }
+                }
+            }
+        }
+        *self.trigram_indexes.write() = indexes;
+    }
+
+    /// Pre-warm HNSW index
+    pub fn prewarm_index(&self) -> Result<()> {
+        if let Some(hnsw) = &self.hnsw {
+            let mut built = self.hnsw_built.write();
+            if !*built {
+                let points = self.points.read();
+                if !points.is_empty() {
+                    let mut index = hnsw.read().empty_like().with_distance(self.distance());
+                    for point in points.values() {
+                        // Points of another dimension cannot be linked
+                        let _ = index.insert(point.clone());
+                    }
+                    self.fresh_points.retain_unlinked(&index, &points);
+                    *hnsw.write() = index;
+                    *built = true;
+                    self.hnsw_built_at.store(crate::journal::now_millis(), Ordering::Release);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Prepare the collection for its first queries: read every stored
+    /// vector so its pages are resident, build the HNSW graph if it is
+    /// still pending and run up to `queries` searches with stored vectors
+    /// spread over the collection. Returns the number of searches run.
+    pub fn warm_up(&self, queries: usize) -> Result<usize> {
+        let sample: Vec<Vector> = {
+            let points = self.points.read();
+            let mut checksum = 0.0f32;
+            for point in points.values() {
+                checksum += point.vector.as_slice().iter().sum::<f32>();
+                for multivector in point.multivector.iter().chain(point.named_multivectors.values()) {
+                    checksum += multivector.vectors().iter().flatten().sum::<f32>();
+                }
+            }
+            std::hint::black_box(checksum);
+
+            let step = (points.len() / queries.max(1)).max(1);
+            points.values()
+                .filter(|p| !p.vector.as_slice().is_empty())
+                .step_by(step)
+                .take(queries)
+                .map(|p| p.vector.clone())
+                .collect()
+        };
+
+        self.prewarm_index()?;
+        self.refresh_trigram_indexes();
+        for query in &sample {
+            self.search(query, 10, None);
+        }
+        Ok(sample.len())
+    }
+
+    /// Fast brute-force search using SIMD - optimal for small datasets
+    fn brute_force_search(
+        &self,
+        query: &Vector,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+        cancel: Option<&CancellationToken>,
+    ) -> Vec<(Point, f32)> {
+        use rayon::prelude::*;
+        
+        let stored_normalized = self.stores_normalized();
+        let points = self.points.read();
+        let query_slice = query.as_slice();
+        let distance = self.distance();
+        
+        // Collect points to a Vec for indexing
+        let point_vec: Vec<_> = points.values().collect();
+        
+        // Parallel scoring - compute scores without cloning points
+        // Only clone the final top-k results
+        // Use parallel only for 10K+ vectors (rayon has overhead)
+        let scored: Vec<(usize, f32)> = if point_vec.len() >= 10000 && filter.is_none() {
+            // Parallel path for larger datasets without filter; each chunk
+            // checks the cancellation token before it is scored
+            point_vec
+                .par_chunks(cancel::CHECK_INTERVAL)
                 .enumerate()
-                .map(|(idx, point)| {
-                    let score = match distance {
-                        Distance::Cosine => {
-                            crate::simd::dot_product_simd(query_slice, point.vector.as_slice())
-                        }
-                        Distance::Euclidean => {
-                            -crate::simd::l2_distance_simd(query_slice, point.vector.as_slice())
-                        }
-                        Distance::Dot => {
-                            crate::simd::dot_product_simd(query_slice, point.vector.as_slice())
-                        }
-                    };
-                    (idx, score)
+                .flat_map_iter(|(chunk_idx, chunk)| {
+                    let scored = if cancel.is_some_and(|c| c.should_stop()) { 0 } else { chunk.len() };
+                    let base = chunk_idx * cancel::CHECK_INTERVAL;
+                    chunk[..scored].iter().enumerate().map(move |(i, point)| {
+                        (base + i, score_vector(distance, stored_normalized, query_slice, point.vector.as_slice()))
+                    })
                 })
                 .collect()
         } else {
             // Sequential path - optimized for common case (Cosine without filter)
             let mut results = Vec::with_capacity(point_vec.len());
             
-            if filter.is_none() && matches!(distance, Distance::Cosine) {
-                // Hot path: Cosine without filter - avoid branching
+            if filter.is_none() && stored_normalized {
+                // Hot path: normalized Cosine without filter - avoid branching
                 for (idx, point) in point_vec.iter().enumerate() {
+                    if cancel::should_stop(cancel, idx) {
+                        break;
+                    }
                     let score = crate::simd::dot_product_simd(query_slice, point.vector.as_slice());
                     results.push((idx, score));
                 }
             } else {
                 // General path with filter/distance checks
                 for (idx, point) in point_vec.iter().enumerate() {
+                    if cancel::should_stop(cancel, idx) {
+                        break;
+                    }
                     if let Some(f) = filter {
                         if !f.matches(point) {
                             continue;
                         }
                     }
                     
-                    let score = match distance {
-                        Distance::Cosine => {
-                            crate::simd::dot_product_simd(query_slice, point.vector.as_slice())
-                        }
-                        Distance::Euclidean => {
-                            -crate::simd::l2_distance_simd(query_slice, point.vector.as_slice())
-                        }
-                        Distance::Dot => {
-                            crate::simd::dot_product_simd(query_slice, point.vector.as_slice())
-                        }
-                    };
-                    
+                    let score = score_vector(distance, stored_normalized, query_slice, point.vector.as_slice());
                     results.push((idx, score));
                 }
             }
@@ -501,104 +2221,633 @@ impl Collection {
             .collect()
     }
 
-    /// Search for similar vectors
-    /// Uses brute-force for small datasets (<1000), HNSW for larger ones
-    pub fn search(
-        &self,
-        query: &Vector,
-        limit: usize,
-        filter: Option<&dyn Filter>,
-    ) -> Vec<(Point, f32)> {
-        let normalized_query = query.normalized();
-        let point_count = self.points.read().len();
-        
-        // Use brute-force for datasets up to 10K - SIMD is very fast and avoids HNSW overhead
-        const BRUTE_FORCE_THRESHOLD: usize = 10000;
-        if point_count < BRUTE_FORCE_THRESHOLD {
-            return self.brute_force_search(&normalized_query, limit, filter);
+    /// Search the HNSW graph, building it first if needed. Results are
+    /// rescored against the stored points, as brute force would score them.
+    fn hnsw_search(
+        &self,
+        hnsw: &RwLock<HnswIndex>,
+        query: &Vector,
+        prepared_query: &Vector,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+    ) -> Vec<(Point, f32)> {
+        let _ = self.prewarm_index();
+
+        // Use write lock for search (HNSW search is now mutable for performance)
+        let mut index = hnsw.write();
+        let mut results = index.search(query, limit, self.search_defaults.read().ef);
+        drop(index);
+
+        // The graph keeps copies made at insert time; payload updates only
+        // reach the stored points, so take the points from there and score
+        // them as brute force would
+        let distance = self.distance();
+        let stored_normalized = self.stores_normalized();
+        let points = self.points.read();
+        let score = |point: &Point| score_vector(distance, stored_normalized, prepared_query.as_slice(), point.vector.as_slice());
+        let matches = |point: &Point| filter.map_or(true, |f| f.matches(point));
+        results.retain_mut(|(point, point_score)| match points.get(&point.id.to_string()) {
+            Some(current) if matches(current) => {
+                *point_score = score(current);
+                *point = current.clone();
+                true
+            }
+            _ => false,
+        });
+        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        // Points the graph misses, e.g. while it is rebuilt, by brute force
+        self.fresh_points.merge_into(&mut results, &points, limit, matches, score);
+        results
+    }
+
+    /// Run `query` by brute force, through the HNSW graph and through the
+    /// quantized replica, where the collection has them, and compare the
+    /// results of each with the exact brute-force ones. The graph is built
+    /// if it was not yet, even below the size at which searches use it.
+    pub fn verify_consistency(&self, query: &Vector, limit: usize) -> Result<ConsistencyReport> {
+        if query.dim() != self.stored_dim() {
+            return Err(Error::InvalidDimension { expected: self.stored_dim(), actual: query.dim() });
+        }
+        let scored = |results: Vec<(Point, f32)>| -> Vec<ScoredId> {
+            results.into_iter().map(|(point, score)| ScoredId { id: point.id.to_string(), score }).collect()
+        };
+
+        let prepared_query = self.prepare_query(query);
+        let exact = scored(self.brute_force_search(&prepared_query, limit, None, None));
+        let mut paths = Vec::new();
+        if let Some(hnsw) = &self.hnsw {
+            let results = scored(self.hnsw_search(hnsw, query, &prepared_query, limit, None));
+            paths.push(PathComparison::new(SearchPath::Hnsw, &exact, results));
+        }
+        if self.gpu_replica_enabled.load(Ordering::Acquire) {
+            let results = self.replica_search_batch(std::slice::from_ref(query), limit).pop().unwrap_or_default();
+            paths.push(PathComparison::new(SearchPath::Quantized, &exact, scored(results)));
+        }
+        let index_violations = self.check_index_consistency();
+        Ok(ConsistencyReport { limit, exact, paths, index_violations })
+    }
+
+    /// Search for similar vectors
+    /// Uses brute-force for small datasets (<1000), HNSW for larger ones
+    pub fn search(
+        &self,
+        query: &Vector,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+    ) -> Vec<(Point, f32)> {
+        self.search_with(query, limit, filter, None)
+    }
+
+    /// [`Collection::search`] that stops scanning once `cancel` is
+    /// cancelled, returning the best results found so far
+    pub fn search_cancellable(
+        &self,
+        query: &Vector,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+        cancel: &CancellationToken,
+    ) -> Vec<(Point, f32)> {
+        self.search_with(query, limit, filter, Some(cancel))
+    }
+
+    fn search_with(
+        &self,
+        query: &Vector,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+        cancel: Option<&CancellationToken>,
+    ) -> Vec<(Point, f32)> {
+        if filter.is_none() && self.gpu_replica_enabled.load(Ordering::Acquire) {
+            return self
+                .search_batch(std::slice::from_ref(query), limit, None)
+                .pop()
+                .unwrap_or_default();
+        }
+
+        let prepared_query = self.prepare_query(query);
+        let point_count = self.points.read().len();
+        
+        // Use brute-force for small datasets - SIMD is very fast and avoids HNSW overhead
+        if point_count < self.hnsw_params().full_scan_threshold {
+            return self.brute_force_search(&prepared_query, limit, filter, cancel);
+        }
+        
+        // The graph misses points too many to merge in
+        if self.fresh_points.overflowed() {
+            return self.brute_force_search(&prepared_query, limit, filter, cancel);
+        }
+
+        if let Some(hnsw) = &self.hnsw {
+            self.hnsw_search(hnsw, query, &prepared_query, limit, filter)
+        } else {
+            let distance = self.distance();
+            let stored_normalized = self.stores_normalized();
+            let points = self.points.read();
+            let results: Vec<(Point, f32)> = points
+                .values()
+                .enumerate()
+                .take_while(|(visited, _)| !cancel::should_stop(cancel, *visited))
+                .map(|(_, point)| point)
+                .filter(|point| {
+                    filter.map(|f| f.matches(point)).unwrap_or(true)
+                })
+                .map(|point| {
+                    let score = score_vector(distance, stored_normalized, prepared_query.as_slice(), point.vector.as_slice());
+                    (point.clone(), score)
+                })
+                .collect();
+
+            let mut sorted = results;
+            sorted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            sorted.truncate(limit);
+            sorted
+        }
+    }
+
+    /// Enable the quantized read replica and build it from the current points.
+    ///
+    /// With the `cuda` feature the replica is kept in GPU memory; otherwise (or
+    /// if no device is available) scoring falls back to the CPU. Returns whether
+    /// the replica ended up on a GPU device. Hamming collections have none.
+    pub fn enable_gpu_replica(&self) -> bool {
+        // Packed bits cannot be quantized; popcounts are cheap anyway
+        if self.distance() == Distance::Hamming {
+            return false;
+        }
+        self.gpu_replica_enabled.store(true, Ordering::Release);
+        self.gpu_replica_stale.store(true, Ordering::Release);
+        self.refresh_gpu_replica();
+        self.gpu_replica_on_device()
+    }
+
+    /// Disable the read replica and release its memory
+    pub fn disable_gpu_replica(&self) {
+        self.gpu_replica_enabled.store(false, Ordering::Release);
+        *self.gpu_replica.write() = None;
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn has_gpu_replica(&self) -> bool {
+        self.gpu_replica_enabled.load(Ordering::Acquire)
+    }
+
+    /// Whether the replica is resident on a GPU device
+    #[must_use]
+    pub fn gpu_replica_on_device(&self) -> bool {
+        self.gpu_replica.read().as_ref().map(|r| r.is_gpu()).unwrap_or(false)
+    }
+
+    #[inline]
+    fn invalidate_gpu_replica(&self) {
+        if self.gpu_replica_enabled.load(Ordering::Relaxed) {
+            self.gpu_replica_stale.store(true, Ordering::Release);
+        }
+    }
+
+    /// Rebuild the replica if writes happened since it was last built
+    fn refresh_gpu_replica(&self) {
+        if !self.gpu_replica_stale.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        let points = self.points.read();
+        let dim = if self.vector_dim() > 0 {
+            self.vector_dim()
+        } else {
+            points.values().next().map(|p| p.vector.dim()).unwrap_or(0)
+        };
+        let replica = GpuReplica::build(points.values(), dim, self.distance());
+        *self.gpu_replica.write() = Some(replica);
+    }
+
+    /// Search several queries at once.
+    ///
+    /// When the read replica is enabled and no filter is given, candidates for
+    /// all queries are scored in one batched pass over the quantized replica and
+    /// then rescored with the full-precision vectors. Otherwise each query goes
+    /// through [`Collection::search`].
+    pub fn search_batch(
+        &self,
+        queries: &[Vector],
+        limit: usize,
+        filter: Option<&dyn Filter>,
+    ) -> Vec<Vec<(Point, f32)>> {
+        if filter.is_some() || !self.gpu_replica_enabled.load(Ordering::Acquire) {
+            return queries.iter().map(|q| self.search(q, limit, filter)).collect();
+        }
+        self.replica_search_batch(queries, limit)
+    }
+
+    /// Score `queries` against the quantized replica and rescore the
+    /// candidates with the full-precision vectors
+    fn replica_search_batch(&self, queries: &[Vector], limit: usize) -> Vec<Vec<(Point, f32)>> {
+        // Quantization error can reorder near-ties, so oversample before rescoring
+        const RESCORE_OVERSAMPLE: usize = 4;
+
+        self.refresh_gpu_replica();
+        let replica = self.gpu_replica.read();
+        let Some(replica) = replica.as_ref() else {
+            return vec![Vec::new(); queries.len()];
+        };
+
+        let slices: Vec<&[f32]> = queries.iter().map(|q| q.as_slice()).collect();
+        let candidates = replica.search_batch(&slices, limit.saturating_mul(RESCORE_OVERSAMPLE));
+
+        let distance = self.distance();
+        let stored_normalized = self.stores_normalized();
+        let points = self.points.read();
+        queries
+            .iter()
+            .zip(candidates)
+            .map(|(query, ids)| {
+                let query = self.prepare_query(query);
+                let mut rescored: Vec<(Point, f32)> = ids
+                    .into_iter()
+                    .filter_map(|(id, _)| points.get(&id))
+                    .map(|point| {
+                        let score = score_vector(distance, stored_normalized, query.as_slice(), point.vector.as_slice());
+                        (point.clone(), score)
+                    })
+                    .collect();
+                rescored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+                rescored.truncate(limit);
+                rescored
+            })
+            .collect()
+    }
+
+    /// BM25 text search, with the collection's stopwords removed from the
+    /// query and synonyms added
+    pub fn search_text(&self, query: &str, limit: usize) -> Vec<(String, f32)> {
+        self.search_text_in(query, None, limit)
+    }
+
+    /// BM25 text search restricted to documents detected as `language`
+    /// (ISO 639-3), or across all languages when None
+    pub fn search_text_in(&self, query: &str, language: Option<&str>, limit: usize) -> Vec<(String, f32)> {
+        if let Some(index) = self.bm25.read().as_ref() {
+            let terms = self.analyze_query(index, query, language);
+            index.search_terms_in(&terms, language, limit)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Query terms after stopwords and synonyms, plus their stems in
+    /// `language` (or every indexed language) when detection is enabled
+    fn analyze_query(&self, index: &BM25Index, query: &str, language: Option<&str>) -> Vec<String> {
+        let mut terms = self.text_dictionaries.read().query_terms(query);
+        if !self.detect_language() {
+            return terms;
+        }
+        let languages = match language {
+            Some(language) => vec![language.to_string()],
+            None => index.languages(),
+        };
+        let raw = terms.clone();
+        for language in languages {
+            let mut stems = raw.clone();
+            crate::language::stem_all(&language, stems.iter_mut());
+            for stem in stems {
+                if !terms.contains(&stem) {
+                    terms.push(stem);
+                }
+            }
+        }
+        terms
+    }
+
+    /// Detect the language of upserted text and index it with that
+    /// language's stemmer. Applies to points written from now on.
+    pub fn set_detect_language(&self, enabled: bool) {
+        self.detect_language.store(enabled, Ordering::Release);
+    }
+
+    pub fn detect_language(&self) -> bool {
+        self.detect_language.load(Ordering::Acquire)
+    }
+
+    /// Replace the defaults of searches that leave parameters out. The `ef`
+    /// default is used by every HNSW search of the collection.
+    pub fn set_search_defaults(&self, defaults: SearchDefaults) {
+        *self.search_defaults.write() = defaults;
+    }
+
+    pub fn search_defaults(&self) -> SearchDefaults {
+        self.search_defaults.read().clone()
+    }
+
+    /// Add or replace the query template `name`
+    pub fn set_query_template(&self, name: &str, template: QueryTemplate) {
+        self.query_templates.write().insert(name.to_string(), template);
+    }
+
+    /// Remove the query template `name`; false if there was none
+    pub fn remove_query_template(&self, name: &str) -> bool {
+        self.query_templates.write().remove(name).is_some()
+    }
+
+    pub fn query_template(&self, name: &str) -> Option<QueryTemplate> {
+        self.query_templates.read().get(name).cloned()
+    }
+
+    pub fn query_templates(&self) -> BTreeMap<String, QueryTemplate> {
+        self.query_templates.read().clone()
+    }
+
+    /// Replace all query templates
+    pub fn set_query_templates(&self, templates: BTreeMap<String, QueryTemplate>) {
+        *self.query_templates.write() = templates;
+    }
+
+    /// Tune the `ef` search default to `tuning` from now on, or stop with
+    /// None; the last report is dropped either way
+    pub fn set_ef_tuning(&self, tuning: Option<EfTuning>) {
+        *self.ef_tuning.write() = tuning;
+        *self.ef_tuning_report.write() = None;
+    }
+
+    pub fn ef_tuning(&self) -> Option<EfTuning> {
+        self.ef_tuning.read().clone()
+    }
+
+    /// Outcome of the last ef tuning, None if there was none since the
+    /// tuning was set
+    pub fn ef_tuning_report(&self) -> Option<EfTuningReport> {
+        self.ef_tuning_report.read().clone()
+    }
+
+    /// Restore the outcome of an earlier tuning, e.g. one read back from disk
+    pub fn set_ef_tuning_report(&self, report: Option<EfTuningReport>) {
+        *self.ef_tuning_report.write() = report;
+    }
+
+    /// Whether the collection has a tuning that was never run, or whose
+    /// collection has grown enough since it last ran
+    pub fn ef_tuning_due(&self) -> bool {
+        let Some(tuning) = self.ef_tuning() else { return false };
+        let points = self.count();
+        self.hnsw.is_some() && points > 0
+            && self.ef_tuning_report().map_or(true, |report| tuning.is_due(report.points, points))
+    }
+
+    /// Find the smallest `ef` whose recall on a sample of stored vectors
+    /// reaches the tuning's target and make it the `ef` search default.
+    /// The HNSW graph is built first if it was not yet. Fails without a
+    /// tuning, an HNSW index or points with dense vectors.
+    pub fn tune_ef(&self) -> Result<EfTuningReport> {
+        let tuning = self.ef_tuning()
+            .ok_or_else(|| Error::InvalidConfig("Collection has no ef tuning set".to_string()))?;
+        let Some(hnsw) = &self.hnsw else {
+            return Err(Error::InvalidConfig("Collection has no HNSW index".to_string()));
+        };
+        self.prewarm_index()?;
+
+        // Stored vectors spread over the collection, as for warming up
+        let (queries, points) = {
+            let points = self.points.read();
+            let step = (points.len() / tuning.sample_size).max(1);
+            let queries: Vec<Vector> = points.values()
+                .filter(|p| !p.vector.as_slice().is_empty())
+                .step_by(step)
+                .take(tuning.sample_size)
+                .map(|p| p.vector.clone())
+                .collect();
+            (queries, points.len())
+        };
+        if queries.is_empty() {
+            return Err(Error::InvalidConfig("Collection has no dense vectors to tune ef on".to_string()));
         }
-        
-        if let Some(hnsw) = &self.hnsw {
-            // Check if we need to build the index first
-            {
-                let mut built = self.hnsw_built.write();
-                if !*built {
-                    let points = self.points.read();
-                    if !points.is_empty() {
-                        let mut index = hnsw.write();
-                        *index = HnswIndex::new(16, 3);
-                        for point in points.values() {
-                            index.insert(point.clone());
-                        }
-                        *built = true;
-                    }
-                }
-            }
-            
-            // Use write lock for search (HNSW search is now mutable for performance)
-            let mut index = hnsw.write();
-            let mut results = index.search(&normalized_query, limit, None);
-            
-            if let Some(f) = filter {
-                results.retain(|(point, _)| f.matches(point));
+
+        let exact: Vec<HashSet<String>> = queries.iter()
+            .map(|query| {
+                self.brute_force_search(&self.prepare_query(query), tuning.limit, None, None)
+                    .into_iter()
+                    .map(|(point, _)| point.id.to_string())
+                    .collect()
+            })
+            .collect();
+        let expected: usize = exact.iter().map(HashSet::len).sum();
+
+        let (ef, recall) = tuning.search(|ef| {
+            if expected == 0 {
+                return 1.0;
             }
-            
-            results
-        } else {
-            let points = self.points.read();
-            let results: Vec<(Point, f32)> = points
-                .values()
-                .filter(|point| {
-                    filter.map(|f| f.matches(point)).unwrap_or(true)
-                })
-                .map(|point| {
-                    let score = match self.config.distance {
-                        Distance::Cosine => point.vector.cosine_similarity(query),
-                        Distance::Euclidean => -point.vector.l2_distance(query),
-                        Distance::Dot => {
-                            point.vector.as_slice()
-                                .iter()
-                                .zip(query.as_slice().iter())
-                                .map(|(a, b)| a * b)
-                                .sum()
-                        }
-                    };
-                    (point.clone(), score)
+            // One query per lock, so searches go on between them
+            let found: usize = queries.iter().zip(&exact)
+                .map(|(query, exact)| {
+                    let results = hnsw.write().search(query, tuning.limit, Some(ef));
+                    results.iter().filter(|(point, _)| exact.contains(&point.id.to_string())).count()
                 })
-                .collect();
+                .sum();
+            found as f32 / expected as f32
+        });
 
-            let mut sorted = results;
-            sorted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-            sorted.truncate(limit);
-            sorted
+        let report = EfTuningReport {
+            ef,
+            recall,
+            target_recall: tuning.target_recall,
+            met_target: recall >= tuning.target_recall,
+            queries: queries.len(),
+            points,
+            tuned_at: crate::journal::now_millis(),
+        };
+        self.search_defaults.write().ef = Some(ef);
+        *self.ef_tuning_report.write() = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Change the HNSW parameters, rebuilding the graph as by
+    /// [`Collection::set_graph_config`]. Returns false if nothing changed.
+    pub fn set_hnsw_params(&self, params: HnswParams) -> Result<bool> {
+        self.set_graph_config(self.distance(), params)
+    }
+
+    pub fn hnsw_params(&self) -> HnswParams {
+        *self.hnsw_params.read()
+    }
+
+    /// Store the detected language of a point's text in its payload
+    fn tag_language(&self, point: &mut Point) {
+        if !self.detect_language() {
+            return;
+        }
+        let Some(payload) = point.payload.as_mut().and_then(|p| p.as_object_mut()) else { return };
+        if payload.contains_key(crate::language::LANGUAGE_FIELD) {
+            return;
+        }
+        let detected = payload.get("text")
+            .and_then(|t| t.as_str())
+            .and_then(crate::language::detect);
+        if let Some(language) = detected {
+            payload.insert(crate::language::LANGUAGE_FIELD.to_string(), language.into());
         }
     }
 
-    /// BM25 text search
-    pub fn search_text(&self, query: &str, limit: usize) -> Vec<(String, f32)> {
-        if let Some(bm25) = &self.bm25 {
-            let index = bm25.read();
-            index.search(query, limit)
-        } else {
-            Vec::new()
+    /// Language a point's text is analyzed in
+    fn text_language<'a>(&self, point: &'a Point) -> Option<&'a str> {
+        if !self.detect_language() {
+            return None;
+        }
+        point.payload.as_ref()?.get(crate::language::LANGUAGE_FIELD)?.as_str()
+    }
+
+    /// Where the terms of a text query occur in a point's `text` payload
+    /// field, with fragments for display. None if the point is not indexed
+    /// or nothing matches.
+    pub fn highlight(&self, id: &str, query: &str, options: &crate::HighlightOptions) -> Option<crate::Highlight> {
+        let point = self.get(id)?;
+        let offsets = {
+            let bm25 = self.bm25.read();
+            let index = bm25.as_ref()?;
+            let terms = self.analyze_query(index, query, self.text_language(&point));
+            index.term_offsets(id, &terms)
+        };
+        if offsets.is_empty() {
+            return None;
         }
+        let text = point.payload.as_ref()?.get("text")?.as_str()?;
+        Some(crate::Highlight::new(text, offsets, options))
+    }
+
+    /// Replace the synonyms and stopwords applied to text queries. Takes
+    /// effect for the next query; nothing is reindexed.
+    pub fn set_text_dictionaries(&self, dictionaries: TextDictionaries) {
+        *self.text_dictionaries.write() = Arc::new(dictionaries);
+    }
+
+    pub fn text_dictionaries(&self) -> Arc<TextDictionaries> {
+        self.text_dictionaries.read().clone()
     }
     
+    /// Dense and BM25 search in one call, with min-max normalized scores
+    /// combined as `alpha * dense + (1 - alpha) * text`. Text matches are
+    /// checked against `filter` too.
+    pub fn search_hybrid(
+        &self,
+        text: &str,
+        vector: &Vector,
+        alpha: f32,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+    ) -> Result<Vec<(Point, f32)>> {
+        if vector.dim() != self.stored_dim() {
+            return Err(Error::InvalidDimension {
+                expected: self.stored_dim(),
+                actual: vector.dim(),
+            });
+        }
+        if !(0.0..=1.0).contains(&alpha) {
+            return Err(Error::InvalidConfig(format!("alpha must be between 0 and 1, got {}", alpha)));
+        }
+        let candidates = limit * crate::hybrid::CANDIDATE_FACTOR;
+        let dense = self.search(vector, candidates, filter);
+        let text = {
+            let points = self.points.read();
+            self.search_text(text, candidates)
+                .into_iter()
+                .filter_map(|(id, score)| points.get(&id).map(|p| (p.clone(), score)))
+                .filter(|(p, _)| filter.map_or(true, |f| f.matches(p)))
+                .collect()
+        };
+        Ok(crate::hybrid::fuse(dense, text, alpha, limit))
+    }
+
     /// Search using multivector MaxSim scoring (ColBERT-style)
     /// 
     /// For each sub-vector in the query, finds the maximum similarity 
     /// with any sub-vector in each document, then sums all maximums.
+    /// Queries longer than the collection's `max_query_tokens` default are
+    /// first pruned to their largest-norm sub-vectors.
     pub fn search_multivector(
         &self,
         query: &MultiVector,
         limit: usize,
         filter: Option<&dyn Filter>,
     ) -> Vec<(Point, f32)> {
+        self.search_multivector_with(query, None, limit, filter, None)
+    }
+
+    /// [`Collection::search_multivector`] that stops scanning once `cancel`
+    /// is cancelled, returning the best results found so far
+    pub fn search_multivector_cancellable(
+        &self,
+        query: &MultiVector,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+        cancel: &CancellationToken,
+    ) -> Vec<(Point, f32)> {
+        self.search_multivector_with(query, None, limit, filter, Some(cancel))
+    }
+
+    /// MaxSim search over the named multivector `name`, scored with its own
+    /// distance. Points without it are skipped.
+    pub fn search_named_multivector(
+        &self,
+        name: &str,
+        query: &MultiVector,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+    ) -> Result<Vec<(Point, f32)>> {
+        self.search_named_multivector_with(name, query, limit, filter, None)
+    }
+
+    /// [`Collection::search_named_multivector`] that stops scanning once
+    /// `cancel` is cancelled, returning the best results found so far
+    pub fn search_named_multivector_cancellable(
+        &self,
+        name: &str,
+        query: &MultiVector,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<(Point, f32)>> {
+        self.search_named_multivector_with(name, query, limit, filter, Some(cancel))
+    }
+
+    fn search_named_multivector_with(
+        &self,
+        name: &str,
+        query: &MultiVector,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<(Point, f32)>> {
+        let params = self.named_multivector(name).ok_or_else(|| {
+            Error::UnknownVector(format!("multivector '{}' is not declared by the collection", name))
+        })?;
+        if query.dim() != params.size {
+            return Err(Error::InvalidDimension { expected: params.size, actual: query.dim() });
+        }
+        Ok(self.search_multivector_with(query, Some((name, params.distance)), limit, filter, cancel))
+    }
+
+    /// MaxSim search over the named multivector given as `(name, distance)`,
+    /// or over the unnamed one with the collection's distance
+    fn search_multivector_with(
+        &self,
+        query: &MultiVector,
+        named: Option<(&str, Distance)>,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+        cancel: Option<&CancellationToken>,
+    ) -> Vec<(Point, f32)> {
+        let pruned;
+        let query = match self.search_defaults.read().max_query_tokens {
+            Some(max_tokens) if query.len() > max_tokens => {
+                pruned = query.prune_tokens(max_tokens);
+                &pruned
+            }
+            _ => query,
+        };
         let points = self.points.read();
         
         let mut results: Vec<(Point, f32)> = Vec::with_capacity(points.len().min(limit * 2));
         
-        for point in points.values() {
+        for (visited, point) in points.values().enumerate() {
+            if cancel::should_stop(cancel, visited) {
+                break;
+            }
             if let Some(f) = filter {
                 if !f.matches(point) {
                     continue;
@@ -606,22 +2855,19 @@ impl Collection {
             }
             
             // Calculate MaxSim score
-            let score = if let Some(doc_mv) = &point.multivector {
-                // Both query and document have multivectors - use MaxSim
-                match self.config.distance {
-                    Distance::Cosine => query.max_sim_cosine(doc_mv),
-                    Distance::Euclidean => query.max_sim_l2(doc_mv),
-                    Distance::Dot => query.max_sim(doc_mv),
+            let score = if let Some((name, distance)) = named {
+                match point.named_multivectors.get(name) {
+                    Some(doc_mv) => query.max_sim_by(doc_mv, distance),
+                    None => continue,
                 }
+            } else if let Some(doc_mv) = &point.multivector {
+                // Both query and document have multivectors - use MaxSim
+                query.max_sim_by(doc_mv, self.distance())
             } else {
                 // Document has single vector - wrap it as multivector
                 let doc_mv = MultiVector::from_single(point.vector.as_slice().to_vec())
                     .unwrap_or_else(|_| MultiVector::new(vec![vec![0.0; query.dim()]]).unwrap());
-                match self.config.distance {
-                    Distance::Cosine => query.max_sim_cosine(&doc_mv),
-                    Distance::Euclidean => query.max_sim_l2(&doc_mv),
-                    Distance::Dot => query.max_sim(&doc_mv),
-                }
+                query.max_sim_by(&doc_mv, self.distance())
             };
             
             results.push((point.clone(), score));
@@ -639,12 +2885,348 @@ impl Collection {
         results
     }
 
+    /// Types, distinct counts, numeric ranges and the `top` most frequent
+    /// values of every payload key
+    pub fn payload_stats(&self, top: usize) -> std::collections::BTreeMap<String, crate::PayloadFieldStats> {
+        crate::payload_stats::payload_stats(self.points.read().values(), top)
+    }
+
+    /// Aggregate the points matching `filter`. The group key must have a
+    /// keyword, integer or bool payload index and every metric field an
+    /// integer or float one.
+    pub fn aggregate(
+        &self,
+        request: &crate::AggregationRequest,
+        filter: Option<&dyn Filter>,
+    ) -> Result<crate::AggregationResult> {
+        {
+            let indexes = self.payload_indexes.read();
+            if let Some(field) = &request.group_by {
+                match indexes.get(field) {
+                    Some(PayloadIndexType::Keyword | PayloadIndexType::Integer | PayloadIndexType::Bool) => {}
+                    _ => return Err(Error::InvalidConfig(format!(
+                        "cannot group by \"{}\": it needs a keyword, integer or bool payload index", field
+                    ))),
+                }
+            }
+            for (name, metric) in &request.metrics {
+                let Some(field) = metric.field() else { continue };
+                if !matches!(indexes.get(field), Some(PayloadIndexType::Integer | PayloadIndexType::Float)) {
+                    return Err(Error::InvalidConfig(format!(
+                        "metric \"{}\" reads \"{}\", which needs an integer or float payload index", name, field
+                    )));
+                }
+                if let crate::Metric::Histogram { interval, .. } = metric {
+                    if !(interval.is_finite() && *interval > 0.0) {
+                        return Err(Error::InvalidConfig(format!(
+                            "metric \"{}\": histogram interval must be positive, got {}", name, interval
+                        )));
+                    }
+                }
+            }
+        }
+        // Only the fields read are copied out, so writes wait for the filter
+        // alone and not for the aggregation
+        let projected: Vec<Point> = self.points.read().values()
+            .filter(|p| filter.map_or(true, |f| f.matches(p)))
+            .map(|p| crate::aggregation::project(p, request))
+            .collect();
+        Ok(crate::aggregation::aggregate(&projected, request))
+    }
+
+    /// Vectors in the built HNSW graph, 0 without one
+    pub fn indexed_vectors_count(&self) -> usize {
+        self.built_hnsw().map_or(0, |hnsw| hnsw.read().len())
+    }
+
+    /// Point and index counts with estimated memory usage
+    pub fn stats(&self) -> crate::CollectionStats {
+        use crate::stats::{point_payload_size, point_vectors_size, MAP_ENTRY_OVERHEAD};
+
+        let mut stats = crate::CollectionStats::default();
+        {
+            let points = self.points.read();
+            stats.points_count = points.len();
+            for (id, point) in points.iter() {
+                if !point.vector.as_slice().is_empty() {
+                    stats.vectors_count += 1;
+                }
+                if !point.sparse_vectors.is_empty() {
+                    stats.sparse_vectors_count += 1;
+                }
+                if point.multivector.is_some() || !point.named_multivectors.is_empty() {
+                    stats.multivectors_count += 1;
+                }
+                stats.memory.vectors_bytes += point_vectors_size(point)
+                    + id.len() + std::mem::size_of::<Point>() + MAP_ENTRY_OVERHEAD;
+                stats.memory.payload_bytes += point_payload_size(point);
+            }
+        }
+
+        if let Some(hnsw) = &self.hnsw {
+            if *self.hnsw_built.read() {
+                let index = hnsw.read();
+                stats.indexed_vectors_count = index.len();
+                stats.memory.hnsw_bytes = index.memory_usage();
+            }
+        }
+        if let Some(index) = self.bm25.read().as_ref() {
+            stats.bm25_documents = index.len();
+            stats.bm25_terms = index.term_count();
+            stats.memory.bm25_bytes = index.memory_usage();
+        }
+
+        stats.pending_points_count = self.pending_points.read().len();
+        stats.hnsw_rebuilding = self.hnsw_rebuilding();
+        stats.rebuild_backlog = self.rebuild_backlog();
+        stats.fresh_points = self.fresh_points.len();
+        stats.fresh_points_overflowed = self.fresh_points.overflowed();
+        stats.payload_indexes = self.payload_indexes.read().len();
+        stats.hnsw_built_at = match self.hnsw_built_at.load(Ordering::Acquire) {
+            0 => None,
+            at => Some(at),
+        };
+        stats.reindex = self.reindex_progress();
+        stats
+    }
+
+    /// Count a finished query and its latency towards the next statistics
+    /// sample
+    pub fn record_query(&self, elapsed: std::time::Duration) {
+        self.query_latencies.record(elapsed);
+    }
+
+    /// p99 latency of the queries since the last statistics sample, in ms
+    pub fn recent_p99_ms(&self) -> Option<f64> {
+        self.query_latencies.p99()
+    }
+
+    /// Queries recorded since the last statistics sample
+    pub fn recent_queries(&self) -> u64 {
+        self.query_latencies.queries()
+    }
+
+    /// Take a statistics sample at `now` (ms since the Unix epoch) and
+    /// append it to the history. Query rate and latency cover the queries
+    /// since the previous sample.
+    pub fn sample_stats(&self, now: u64) -> StatsSample {
+        let stats = self.stats();
+        let (qps, p99_ms) = self.query_latencies.take(now);
+        let sample = StatsSample {
+            timestamp: now,
+            points_count: stats.points_count,
+            memory_bytes: stats.memory.total_bytes(),
+            qps,
+            p99_ms,
+        };
+        self.stats_history.write().push(sample);
+        sample
+    }
+
+    /// Statistics samples taken after `since` (ms since the Unix epoch), or
+    /// all that are kept, oldest first
+    pub fn stats_history(&self, since: Option<u64>) -> Vec<StatsSample> {
+        self.stats_history.read().since(since)
+    }
+
+    /// Replace the statistics history, e.g. with one read back from disk
+    pub fn restore_stats_history(&self, samples: impl IntoIterator<Item = StatsSample>) {
+        let mut history = StatsHistory::default();
+        for sample in samples {
+            history.push(sample);
+        }
+        *self.stats_history.write() = history;
+    }
+
     /// Get all points
     pub fn iter(&self) -> Vec<Point> {
         self.points.read().values().cloned().collect()
     }
+
+    /// Pairwise similarity between a random sample of `sample` points
+    /// matching `filter`, keeping the `limit` nearest neighbours of each
+    pub fn distance_matrix(&self, sample: usize, limit: usize, filter: Option<&dyn Filter>) -> crate::DistanceMatrix {
+        let candidates: Vec<Point> = self.points.read()
+            .values()
+            .filter(|p| filter.map_or(true, |f| f.matches(p)))
+            .cloned()
+            .collect();
+        let sampled = crate::matrix::sample_points(candidates, sample);
+        crate::DistanceMatrix::compute(&sampled, limit, self.distance())
+    }
+
+    /// Find groups of points matching `filter` whose similarity is at least
+    /// `threshold`, comparing each point with its `neighbors` nearest points
+    pub fn find_duplicates(
+        &self,
+        threshold: f32,
+        neighbors: usize,
+        filter: Option<&dyn Filter>,
+    ) -> Vec<crate::DuplicateGroup> {
+        let points: Vec<Point> = self.points.read()
+            .values()
+            .filter(|p| !p.vector.as_slice().is_empty())
+            .filter(|p| filter.map_or(true, |f| f.matches(p)))
+            .cloned()
+            .collect();
+        let positions: HashMap<String, usize> = points.iter()
+            .enumerate()
+            .map(|(i, p)| (p.id.to_string(), i))
+            .collect();
+
+        let mut pairs = Vec::new();
+        for (i, point) in points.iter().enumerate() {
+            for (other, score) in self.search(&point.vector, neighbors + 1, filter) {
+                if score < threshold {
+                    continue;
+                }
+                if let Some(&j) = positions.get(&other.id.to_string()) {
+                    if j != i {
+                        pairs.push((i.min(j), i.max(j), score));
+                    }
+                }
+            }
+        }
+        crate::dedup::group_pairs(&points, &pairs)
+    }
+
+    /// Run k-means over the dense vectors of points matching `filter`. When
+    /// `payload_key` is set, each point's cluster index is stored under it.
+    pub fn kmeans(
+        &self,
+        config: &crate::KMeansConfig,
+        filter: Option<&dyn Filter>,
+        payload_key: Option<&str>,
+    ) -> Result<crate::KMeansResult> {
+        let points: Vec<Point> = self.points.read()
+            .values()
+            .filter(|p| !p.vector.as_slice().is_empty())
+            .filter(|p| filter.map_or(true, |f| f.matches(p)))
+            .cloned()
+            .collect();
+        let vectors: Vec<&[f32]> = points.iter().map(|p| p.vector.as_slice()).collect();
+
+        let result = crate::clustering::kmeans(&vectors, config).ok_or_else(|| {
+            Error::InvalidConfig(format!(
+                "k-means needs k >= 1 and at least k points, got k = {} and {} points",
+                config.k, points.len()
+            ))
+        })?;
+
+        if let Some(key) = payload_key {
+            for (point, cluster) in points.iter().zip(&result.assignments) {
+                self.set_payload(&point.id.to_string(), serde_json::json!({ key: cluster }))?;
+            }
+        }
+        Ok(result)
+    }
     
-    /// Search using sparse vectors (dot product on matching indices)
+    /// Declare the named sparse vector, or replace its configuration. Once
+    /// a collection declares sparse vectors, points holding any other name
+    /// are rejected.
+    pub fn set_sparse_vector(&self, vector_name: &str, config: SparseVectorConfig) {
+        self.sparse_vectors.write().insert(vector_name.to_string(), config);
+        self.sparse_modifiers.write().remove(vector_name);
+    }
+
+    /// Configuration of the named sparse vector, if declared
+    pub fn sparse_vector(&self, vector_name: &str) -> Option<SparseVectorConfig> {
+        self.sparse_vectors.read().get(vector_name).cloned()
+    }
+
+    /// Declared named sparse vectors
+    pub fn sparse_vectors(&self) -> HashMap<String, SparseVectorConfig> {
+        self.sparse_vectors.read().clone()
+    }
+
+    /// Declare the named multivector, or replace its parameters. Points
+    /// may only hold declared named multivectors of the declared size.
+    pub fn set_named_multivector(&self, name: &str, params: MultiVectorParams) {
+        self.named_multivectors.write().insert(name.to_string(), params);
+    }
+
+    /// Parameters of the named multivector, if declared
+    pub fn named_multivector(&self, name: &str) -> Option<MultiVectorParams> {
+        self.named_multivectors.read().get(name).cloned()
+    }
+
+    /// Declared named multivectors
+    pub fn named_multivectors(&self) -> HashMap<String, MultiVectorParams> {
+        self.named_multivectors.read().clone()
+    }
+
+    /// Name queries may select the dense vector by, besides leaving
+    /// `using` out
+    pub fn set_dense_vector_name(&self, name: Option<String>) {
+        *self.dense_vector_name.write() = name.filter(|name| !name.is_empty());
+    }
+
+    pub fn dense_vector_name(&self) -> Option<String> {
+        self.dense_vector_name.read().clone()
+    }
+
+    /// The vector `using` names: the dense vector when left out, empty or
+    /// its declared name, a declared sparse vector or multivector, or, while
+    /// the collection declares no sparse vectors, one its points hold.
+    /// Other names are an [`Error::UnknownVector`] listing the names there
+    /// are, unless the dense vector has no declared name: collections created
+    /// before names were kept take any other name as the dense vector.
+    pub fn resolve_using(&self, using: Option<&str>) -> Result<VectorUsing> {
+        let name = match using {
+            None | Some("") => return Ok(VectorUsing::Dense),
+            Some(name) if self.dense_vector_name.read().as_deref() == Some(name) => return Ok(VectorUsing::Dense),
+            Some(name) => name,
+        };
+        if self.named_multivectors.read().contains_key(name) {
+            return Ok(VectorUsing::Multivector(name.to_string()));
+        }
+        let mut sparse: Vec<String> = self.sparse_vectors.read().keys().cloned().collect();
+        if sparse.is_empty() {
+            sparse = self.sparse_df.read().names().map(str::to_string).collect();
+        }
+        if sparse.iter().any(|sparse| sparse == name) {
+            return Ok(VectorUsing::Sparse(name.to_string()));
+        }
+        let dense = match self.dense_vector_name() {
+            Some(dense) => dense,
+            None => return Ok(VectorUsing::Dense),
+        };
+        sparse.sort_unstable();
+        let mut multivectors: Vec<String> = self.named_multivectors.read().keys().cloned().collect();
+        multivectors.sort_unstable();
+        let list = |names: &[String]| if names.is_empty() { "none".to_string() } else { names.join(", ") };
+        Err(Error::UnknownVector(format!(
+            "'{}' (the collection has dense vector: {}; sparse vectors: {}; multivectors: {})",
+            name, dense, list(&sparse), list(&multivectors)
+        )))
+    }
+
+    /// Set how the named sparse vector is scored. An undeclared vector
+    /// stays undeclared, as in configs written before sparse vectors were
+    /// declared, so points may still hold any name.
+    pub fn set_sparse_modifier(&self, vector_name: &str, modifier: SparseModifier) {
+        if let Some(config) = self.sparse_vectors.write().get_mut(vector_name) {
+            config.modifier = modifier;
+            return;
+        }
+        self.sparse_modifiers.write().insert(vector_name.to_string(), modifier);
+    }
+
+    /// Scoring modifier of the named sparse vector
+    pub fn sparse_modifier(&self, vector_name: &str) -> SparseModifier {
+        if let Some(config) = self.sparse_vectors.read().get(vector_name) {
+            return config.modifier;
+        }
+        self.sparse_modifiers.read().get(vector_name).copied().unwrap_or_default()
+    }
+
+    /// Scoring modifiers of undeclared sparse vectors
+    pub fn sparse_modifiers(&self) -> HashMap<String, SparseModifier> {
+        self.sparse_modifiers.read().clone()
+    }
+
+    /// Search using sparse vectors (dot product on matching indices, scaled
+    /// by IDF when the vector has the `idf` modifier)
     pub fn search_sparse(
         &self,
         query: &crate::point::SparseVector,
@@ -652,11 +3234,46 @@ impl Collection {
         limit: usize,
         filter: Option<&dyn Filter>,
     ) -> Vec<(Point, f32)> {
+        self.search_sparse_with(query, vector_name, limit, filter, None)
+    }
+
+    /// [`Collection::search_sparse`] that stops scanning once `cancel` is
+    /// cancelled, returning the best results found so far
+    pub fn search_sparse_cancellable(
+        &self,
+        query: &crate::point::SparseVector,
+        vector_name: &str,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+        cancel: &CancellationToken,
+    ) -> Vec<(Point, f32)> {
+        self.search_sparse_with(query, vector_name, limit, filter, Some(cancel))
+    }
+
+    fn search_sparse_with(
+        &self,
+        query: &crate::point::SparseVector,
+        vector_name: &str,
+        limit: usize,
+        filter: Option<&dyn Filter>,
+        cancel: Option<&CancellationToken>,
+    ) -> Vec<(Point, f32)> {
+        let weighted;
+        let query = match self.sparse_modifier(vector_name) {
+            SparseModifier::Idf => {
+                weighted = self.sparse_df.read().weight_query(vector_name, query);
+                &weighted
+            }
+            SparseModifier::None => query,
+        };
         let points = self.points.read();
         
         let mut results: Vec<(Point, f32)> = Vec::with_capacity(points.len().min(limit * 2));
         
-        for point in points.values() {
+        for (visited, point) in points.values().enumerate() {
+            if cancel::should_stop(cancel, visited) {
+                break;
+            }
             // Apply filter if provided
             if let Some(f) = filter {
                 if !f.matches(point) {
@@ -684,3 +3301,23 @@ impl Collection {
     }
 }
 
+/// Merge the keys of `payload` into a point's payload
+fn merge_payload(point: &mut Point, payload: serde_json::Value) {
+    if let Some(existing) = &mut point.payload {
+        if let (Some(existing_obj), Some(new_obj)) = (existing.as_object_mut(), payload.as_object()) {
+            for (key, value) in new_obj {
+                existing_obj.insert(key.clone(), value.clone());
+            }
+        }
+    } else {
+        point.payload = Some(payload);
+    }
+}
+
+fn remove_payload_keys(point: &mut Point, keys: &[String]) {
+    if let Some(obj) = point.payload.as_mut().and_then(|p| p.as_object_mut()) {
+        for key in keys {
+            obj.remove(key);
+        }
+    }
+}
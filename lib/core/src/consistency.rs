@@ -0,0 +1,173 @@
+//! Agreement between the search paths of a collection
+//!
+//! A query can be answered by brute force, by the HNSW graph or by the
+//! quantized replica, and all three are meant to score a point the same way;
+//! they only differ in which points they find. [`ConsistencyReport`] runs
+//! one query down every available path and compares each with the exact
+//! brute-force results, so an optimization that changes what a score means
+//! shows up as a score delta rather than as a slightly worse recall.
+//!
+//! Every path also depends on its index agreeing with the stored points;
+//! [`IndexViolation`] names one place where they don't.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// An approximate way of answering a dense vector search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchPath {
+    Hnsw,
+    Quantized,
+}
+
+/// A result of one path: point id and score
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScoredId {
+    pub id: String,
+    pub score: f32,
+}
+
+/// How the results of one path compare with the exact results
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PathComparison {
+    pub path: SearchPath,
+    pub results: Vec<ScoredId>,
+    /// Fraction of the exact results this path also returned
+    pub recall: f32,
+    /// Largest difference between the two scores of a point found by both
+    pub max_score_delta: f32,
+    pub mean_score_delta: f32,
+    /// Spearman correlation of the ranks of the points found by both; None
+    /// when fewer than two are
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank_correlation: Option<f32>,
+}
+
+impl PathComparison {
+    /// Compare `results` of `path` with the `exact` results
+    pub fn new(path: SearchPath, exact: &[ScoredId], results: Vec<ScoredId>) -> Self {
+        let exact_by_id: HashMap<&str, (usize, f32)> = exact.iter()
+            .enumerate()
+            .map(|(rank, r)| (r.id.as_str(), (rank, r.score)))
+            .collect();
+        // (rank among exact results, rank among these results, score delta)
+        let shared: Vec<(usize, usize, f32)> = results.iter()
+            .enumerate()
+            .filter_map(|(rank, r)| {
+                let (exact_rank, exact_score) = exact_by_id.get(r.id.as_str())?;
+                Some((*exact_rank, rank, (r.score - exact_score).abs()))
+            })
+            .collect();
+
+        let recall = if exact.is_empty() { 1.0 } else { shared.len() as f32 / exact.len() as f32 };
+        let max_score_delta = shared.iter().map(|s| s.2).fold(0.0, f32::max);
+        let mean_score_delta = if shared.is_empty() {
+            0.0
+        } else {
+            shared.iter().map(|s| s.2).sum::<f32>() / shared.len() as f32
+        };
+
+        Self {
+            path,
+            results,
+            recall,
+            max_score_delta,
+            mean_score_delta,
+            rank_correlation: spearman(&shared),
+        }
+    }
+}
+
+/// Spearman correlation of two rankings of the same points, given each
+/// point's rank in both. Ranks are renumbered among the shared points first,
+/// so points missing from one side do not count against the order.
+fn spearman(shared: &[(usize, usize, f32)]) -> Option<f32> {
+    let n = shared.len();
+    if n < 2 {
+        return None;
+    }
+    let mut by_exact: Vec<usize> = (0..n).collect();
+    by_exact.sort_by_key(|&i| shared[i].0);
+    let mut exact_rank = vec![0usize; n];
+    for (rank, &i) in by_exact.iter().enumerate() {
+        exact_rank[i] = rank;
+    }
+    // `shared` is already in the order of the compared results
+    let squared: f64 = (0..n)
+        .map(|i| {
+            let d = exact_rank[i] as f64 - i as f64;
+            d * d
+        })
+        .sum();
+    let n = n as f64;
+    Some((1.0 - 6.0 * squared / (n * (n * n - 1.0))) as f32)
+}
+
+/// A disagreement between the stored points and one of their indexes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IndexViolation {
+    /// The HNSW graph links a point that is not stored
+    GraphOrphan { id: String },
+    /// The HNSW graph links a point more than once
+    GraphDuplicate { id: String },
+    /// A stored point is missing from the HNSW graph
+    GraphMissing { id: String },
+    /// BM25 indexes a document for a point that is not stored or has no text
+    Bm25Orphan { id: String },
+    /// The text of a stored point is not indexed by BM25
+    Bm25Missing { id: String },
+    /// The sparse document frequencies differ from those of the stored points
+    SparseFrequencies,
+}
+
+/// One query run down every available search path
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConsistencyReport {
+    pub limit: usize,
+    /// Brute-force results, the reference for every other path
+    pub exact: Vec<ScoredId>,
+    pub paths: Vec<PathComparison>,
+    /// Where the indexes disagree with the stored points
+    pub index_violations: Vec<IndexViolation>,
+}
+
+impl ConsistencyReport {
+    /// Largest score delta over all paths
+    pub fn max_score_delta(&self) -> f32 {
+        self.paths.iter().map(|p| p.max_score_delta).fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scored(ids: &[(&str, f32)]) -> Vec<ScoredId> {
+        ids.iter().map(|(id, score)| ScoredId { id: id.to_string(), score: *score }).collect()
+    }
+
+    #[test]
+    fn test_path_comparison() {
+        let exact = scored(&[("a", 0.9), ("b", 0.8), ("c", 0.7), ("d", 0.6)]);
+
+        let same = PathComparison::new(SearchPath::Hnsw, &exact, exact.clone());
+        assert_eq!(same.recall, 1.0);
+        assert_eq!(same.max_score_delta, 0.0);
+        assert_eq!(same.rank_correlation, Some(1.0));
+
+        // "c" is missed, "b" and "a" swap and "a" is scored differently
+        let results = scored(&[("b", 0.8), ("a", 0.85), ("d", 0.6), ("e", 0.5)]);
+        let cmp = PathComparison::new(SearchPath::Quantized, &exact, results);
+        assert_eq!(cmp.recall, 0.75);
+        assert!((cmp.max_score_delta - 0.05).abs() < 1e-6);
+        assert!((cmp.mean_score_delta - 0.05 / 3.0).abs() < 1e-6);
+        assert_eq!(cmp.rank_correlation, Some(0.5));
+
+        let reversed: Vec<ScoredId> = exact.iter().rev().cloned().collect();
+        let cmp = PathComparison::new(SearchPath::Hnsw, &exact, reversed);
+        assert_eq!(cmp.rank_correlation, Some(-1.0));
+        assert_eq!(PathComparison::new(SearchPath::Hnsw, &exact, Vec::new()).rank_correlation, None);
+    }
+}
@@ -0,0 +1,153 @@
+//! Near-duplicate detection
+//!
+//! [`Collection::find_duplicates`](crate::Collection::find_duplicates) looks
+//! up each point's nearest neighbours (through HNSW on large collections, so
+//! only nearby points are compared) and links pairs scoring at or above a
+//! threshold. Linked points are merged into groups with a union-find; in each
+//! group the point with the highest version is kept and the others are
+//! reported as its duplicates.
+//!
+//! Exact duplicates can also be caught as they are written. With a
+//! [`DuplicatePolicy`] other than `allow`, a collection keys every stored
+//! dense vector by its components rounded to [`QUANTIZATION_STEP`] and
+//! applies the policy to upserts whose vector has the key of another
+//! point's. Repeated vectors add nothing to a search but crowd the HNSW
+//! graph with ties.
+
+use crate::{Point, PointId};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Payload key naming the point a tagged duplicate repeats
+pub const DUPLICATE_OF_FIELD: &str = "duplicate_of";
+
+/// Vector components closer than this count as equal
+pub const QUANTIZATION_STEP: f32 = 1e-6;
+
+/// What an upsert does when its dense vector duplicates that of another
+/// stored point
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// Store it like any other point
+    #[default]
+    Allow,
+    /// Fail the upsert
+    Reject,
+    /// Merge its payload into the existing point instead of storing it
+    Merge,
+    /// Store it with the existing point's id under `duplicate_of`
+    Tag,
+}
+
+impl DuplicatePolicy {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Quantized components of a vector, equal for duplicates
+pub(crate) fn vector_key(vector: &[f32]) -> Vec<i64> {
+    vector.iter().map(|&x| (x as f64 / QUANTIZATION_STEP as f64).round() as i64).collect()
+}
+
+pub(crate) fn key_hash(key: &[i64]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A set of near-duplicate points
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    /// Highest-versioned point of the group (lowest id on ties)
+    pub keep: PointId,
+    /// The other members, which can be deleted
+    pub duplicates: Vec<PointId>,
+    /// Lowest score among the links that formed the group
+    pub min_score: f32,
+}
+
+/// Merge scored pairs (indices into `points`) into duplicate groups, largest
+/// first
+pub(crate) fn group_pairs(points: &[Point], pairs: &[(usize, usize, f32)]) -> Vec<DuplicateGroup> {
+    let mut parent: Vec<usize> = (0..points.len()).collect();
+    for &(a, b, _) in pairs {
+        let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+        if ra != rb {
+            parent[ra.max(rb)] = ra.min(rb);
+        }
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut min_scores: HashMap<usize, f32> = HashMap::new();
+    for &(a, _, score) in pairs {
+        let root = find(&mut parent, a);
+        let min = min_scores.entry(root).or_insert(score);
+        *min = min.min(score);
+    }
+    for i in 0..points.len() {
+        let root = find(&mut parent, i);
+        if min_scores.contains_key(&root) {
+            members.entry(root).or_default().push(i);
+        }
+    }
+
+    let mut groups: Vec<DuplicateGroup> = members
+        .into_iter()
+        .map(|(root, mut indices)| {
+            indices.sort_by(|&a, &b| {
+                points[b].version.cmp(&points[a].version)
+                    .then_with(|| points[a].id.to_string().cmp(&points[b].id.to_string()))
+            });
+            DuplicateGroup {
+                keep: points[indices[0]].id.clone(),
+                duplicates: indices[1..].iter().map(|&i| points[i].id.clone()).collect(),
+                min_score: min_scores[&root],
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.duplicates.len().cmp(&a.duplicates.len())
+            .then_with(|| a.keep.to_string().cmp(&b.keep.to_string()))
+    });
+    groups
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector;
+
+    fn point(id: u64, version: u64) -> Point {
+        let mut point = Point::new(PointId::Integer(id), Vector::new(vec![1.0]), None);
+        point.version = version;
+        point
+    }
+
+    #[test]
+    fn test_groups_keep_highest_version() {
+        let points = vec![point(1, 0), point(2, 3), point(3, 1), point(4, 0), point(5, 0)];
+        // 1-2 and 2-3 chain into one group; 4-5 form another; nothing links them
+        let pairs = vec![(0, 1, 0.99), (1, 2, 0.97), (3, 4, 0.98)];
+        let groups = group_pairs(&points, &pairs);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].keep, PointId::Integer(2));
+        assert_eq!(groups[0].duplicates, vec![PointId::Integer(3), PointId::Integer(1)]);
+        assert_eq!(groups[0].min_score, 0.97);
+        assert_eq!(groups[1].keep, PointId::Integer(4));
+        assert_eq!(groups[1].duplicates, vec![PointId::Integer(5)]);
+    }
+}
@@ -0,0 +1,163 @@
+//! Tuning of the HNSW candidate list size to a recall target
+//!
+//! A larger `ef` finds more of the true nearest neighbours but visits more
+//! of the graph. With [`EfTuning`] set, a collection measures the recall
+//! of its graph against brute force, using a sample of its stored vectors
+//! as queries, and keeps the smallest `ef` that reaches the target as its
+//! default (`search_defaults.ef`). Recall drops as a graph grows, so the
+//! tuning is run again once the collection has grown by `retune_growth`.
+
+use serde::{Deserialize, Serialize};
+
+/// Recall target and sample of an ef tuning
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct EfTuning {
+    /// Fraction of the exact nearest neighbours searches should find
+    pub target_recall: f32,
+    /// Stored points used as queries
+    #[serde(default = "default_sample_size")]
+    pub sample_size: usize,
+    /// Neighbours compared per query
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    /// Largest ef tried; used when even it misses the target
+    #[serde(default = "default_max_ef")]
+    pub max_ef: usize,
+    /// Growth of the point count, as a fraction of the count at the last
+    /// tuning, after which the tuning is run again
+    #[serde(default = "default_retune_growth")]
+    pub retune_growth: f32,
+}
+
+fn default_sample_size() -> usize {
+    100
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+fn default_max_ef() -> usize {
+    1024
+}
+
+fn default_retune_growth() -> f32 {
+    0.2
+}
+
+impl EfTuning {
+    /// Largest accepted `max_ef`
+    pub const MAX_EF: usize = 4096;
+    /// Largest accepted `sample_size`
+    pub const MAX_SAMPLE_SIZE: usize = 10_000;
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.target_recall > 0.0 && self.target_recall <= 1.0) {
+            return Err("target_recall must be in (0, 1]".to_string());
+        }
+        if self.sample_size == 0 || self.limit == 0 {
+            return Err("sample_size and limit must be greater than 0".to_string());
+        }
+        if self.max_ef < self.limit {
+            return Err(format!("max_ef must be at least limit ({})", self.limit));
+        }
+        if self.max_ef > Self::MAX_EF {
+            return Err(format!("max_ef must be at most {}", Self::MAX_EF));
+        }
+        if self.sample_size > Self::MAX_SAMPLE_SIZE {
+            return Err(format!("sample_size must be at most {}", Self::MAX_SAMPLE_SIZE));
+        }
+        if !(self.retune_growth.is_finite() && self.retune_growth >= 0.0) {
+            return Err("retune_growth must be a non-negative number".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether a collection tuned at `tuned_points` points and now holding
+    /// `points` should be tuned again
+    pub fn is_due(&self, tuned_points: usize, points: usize) -> bool {
+        let grown = points.saturating_sub(tuned_points);
+        grown > 0 && grown as f64 >= (tuned_points as f64 * f64::from(self.retune_growth)).round()
+    }
+
+    /// The smallest ef from `limit` to `max_ef` whose recall, as measured
+    /// by `recall_at`, reaches the target, with that recall; `max_ef` and
+    /// its recall if none does. Recall is taken to grow with ef.
+    pub fn search(&self, mut recall_at: impl FnMut(usize) -> f32) -> (usize, f32) {
+        let best = recall_at(self.max_ef);
+        if best < self.target_recall {
+            return (self.max_ef, best);
+        }
+        let (mut low, mut high, mut high_recall) = (self.limit, self.max_ef, best);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let recall = recall_at(mid);
+            if recall >= self.target_recall {
+                high = mid;
+                high_recall = recall;
+            } else {
+                low = mid + 1;
+            }
+        }
+        (high, high_recall)
+    }
+}
+
+/// Outcome of the last ef tuning of a collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct EfTuningReport {
+    /// The ef now set as the collection's default
+    pub ef: usize,
+    /// Mean recall of the sample at that ef
+    pub recall: f32,
+    pub target_recall: f32,
+    /// Whether the recall reaches the target; false if even `max_ef` missed
+    pub met_target: bool,
+    /// Queries in the sample
+    pub queries: usize,
+    /// Points in the collection when it was tuned
+    pub points: usize,
+    /// When it was tuned, in ms since the Unix epoch
+    pub tuned_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuning(target_recall: f32) -> EfTuning {
+        serde_json::from_value(serde_json::json!({"target_recall": target_recall})).unwrap()
+    }
+
+    #[test]
+    fn test_ef_search() {
+        let tuning = tuning(0.95);
+        assert!(tuning.validate().is_ok());
+        assert!(EfTuning { max_ef: 5, ..tuning.clone() }.validate().is_err());
+        assert!(EfTuning { max_ef: EfTuning::MAX_EF, ..tuning.clone() }.validate().is_ok());
+        assert!(EfTuning { max_ef: EfTuning::MAX_EF + 1, ..tuning.clone() }.validate().is_err());
+        assert!(EfTuning { limit: usize::MAX, max_ef: usize::MAX, ..tuning.clone() }.validate().is_err());
+        assert!(EfTuning { sample_size: EfTuning::MAX_SAMPLE_SIZE + 1, ..tuning.clone() }.validate().is_err());
+        assert!(EfTuning { target_recall: 1.5, ..tuning.clone() }.validate().is_err());
+
+        // Recall reaches 0.95 at ef 137
+        let mut calls = 0;
+        let (ef, recall) = tuning.search(|ef| {
+            calls += 1;
+            if ef >= 137 { 0.95 + (ef - 137) as f32 / 1e5 } else { 0.9 }
+        });
+        assert_eq!((ef, recall), (137, 0.95));
+        assert!(calls <= 12);
+
+        assert_eq!(tuning.search(|_| 0.5), (1024, 0.5));
+        assert_eq!(tuning.search(|_| 1.0), (10, 1.0));
+
+        assert!(!tuning.is_due(1000, 1100));
+        assert!(tuning.is_due(1000, 1200));
+        assert!(tuning.is_due(0, 10));
+        assert!(!tuning.is_due(1000, 500));
+    }
+}
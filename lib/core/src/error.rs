@@ -13,12 +13,21 @@ pub enum Error {
     #[error("Invalid vector dimension: expected {expected}, got {actual}")]
     InvalidDimension { expected: usize, actual: usize },
 
+    #[error("Payload of {size} bytes exceeds the limit of {limit} bytes")]
+    PayloadTooLarge { size: usize, limit: usize },
+
+    #[error("Point {id} (index {index} in batch): {reason}")]
+    InvalidPoint { index: usize, id: String, reason: String },
+
     #[error("Point not found: {0}")]
     PointNotFound(String),
 
     #[error("Point already exists: {0}")]
     PointExists(String),
 
+    #[error("Point {id} has the same vector as point {duplicate_of}")]
+    DuplicateVector { id: String, duplicate_of: String },
+
     #[error("Storage error: {0}")]
     Storage(String),
     
@@ -33,5 +42,20 @@ pub enum Error {
 
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
+
+    #[error("Invalid sparse vector: {0}")]
+    InvalidSparseVector(String),
+
+    #[error("Unknown vector name: {0}")]
+    UnknownVector(String),
+
+    #[error("Invalid binary vector: {0}")]
+    InvalidBinaryVector(String),
+
+    #[error("Invalid expression: {0}")]
+    InvalidExpression(String),
+
+    #[error("The HNSW graph is being rebuilt; retry once the rebuild is done")]
+    Rebuilding,
 }
 
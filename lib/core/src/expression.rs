@@ -0,0 +1,486 @@
+//! Arithmetic expressions over point payloads
+//!
+//! Computed fields let a query return values derived from the payload,
+//! such as `price * 1.22` or the distance of a stored location from a
+//! reference point, without a post-processing pass in the client. The
+//! grammar is small:
+//!
+//! ```text
+//! expr    = term (("+" | "-") term)*
+//! term    = unary (("*" | "/" | "%") unary)*
+//! unary   = "-" unary | power
+//! power   = primary ("^" unary)?
+//! primary = number | field | "$score" | call | "(" expr ")"
+//! call    = name "(" expr ("," expr)* ")"
+//! ```
+//!
+//! Fields are payload keys, with dots for nested objects (`address.zip`).
+//! Functions are `abs`, `sqrt`, `ln`, `exp`, `floor`, `ceil`, `round`,
+//! `min`, `max` and `geo_distance(field, lat, lon)`, the great-circle
+//! distance in metres from a `{"lat", "lon"}` field. A missing or
+//! non-numeric field, or a result that is not a finite number, makes the
+//! whole expression evaluate to None.
+//!
+//! Sources longer than [`MAX_EXPRESSION_LEN`] bytes, or nested deeper than
+//! [`MAX_EXPRESSION_DEPTH`] (parentheses, signs, powers, call arguments and
+//! operators chained at one level all count), are rejected, so neither
+//! parsing nor evaluation can recurse far enough to exhaust the stack.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::{Error, GeoPoint, Point, Result};
+
+/// Longest expression source accepted, in bytes
+pub const MAX_EXPRESSION_LEN: usize = 4096;
+
+/// Deepest nesting accepted in an expression
+pub const MAX_EXPRESSION_DEPTH: usize = 64;
+
+/// A parsed expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    /// Payload field, split at dots
+    Field(Vec<String>),
+    /// Score of the point in the query, if it has one
+    Score,
+    Neg(Box<Expression>),
+    Binary(BinaryOp, Box<Expression>, Box<Expression>),
+    Call(Function, Vec<Expression>),
+    GeoDistance { field: Vec<String>, lat: Box<Expression>, lon: Box<Expression> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Abs,
+    Sqrt,
+    Ln,
+    Exp,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "abs" => Function::Abs,
+            "sqrt" => Function::Sqrt,
+            "ln" => Function::Ln,
+            "exp" => Function::Exp,
+            "floor" => Function::Floor,
+            "ceil" => Function::Ceil,
+            "round" => Function::Round,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            _ => return None,
+        })
+    }
+
+    /// Whether the function takes any number of arguments (at least one)
+    /// rather than exactly one
+    fn variadic(self) -> bool {
+        matches!(self, Function::Min | Function::Max)
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self> {
+        if source.len() > MAX_EXPRESSION_LEN {
+            return Err(invalid(format!("longer than {} bytes", MAX_EXPRESSION_LEN)));
+        }
+        let mut parser = Parser { tokens: tokenize(source)?, pos: 0, depth: 0 };
+        let expr = parser.expr()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(invalid(format!("unexpected {}", token))),
+        }
+    }
+
+    /// Value of the expression for `payload`, with `score` for `$score`
+    pub fn eval(&self, payload: Option<&Value>, score: Option<f32>) -> Option<f64> {
+        let value = match self {
+            Expression::Number(n) => *n,
+            Expression::Field(path) => field(payload, path)?.as_f64()?,
+            Expression::Score => f64::from(score?),
+            Expression::Neg(inner) => -inner.eval(payload, score)?,
+            Expression::Binary(op, left, right) => {
+                let (a, b) = (left.eval(payload, score)?, right.eval(payload, score)?);
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Rem => a % b,
+                    BinaryOp::Pow => a.powf(b),
+                }
+            }
+            Expression::Call(function, args) => {
+                let args: Vec<f64> = args.iter().map(|a| a.eval(payload, score)).collect::<Option<_>>()?;
+                match function {
+                    Function::Abs => args[0].abs(),
+                    Function::Sqrt => args[0].sqrt(),
+                    Function::Ln => args[0].ln(),
+                    Function::Exp => args[0].exp(),
+                    Function::Floor => args[0].floor(),
+                    Function::Ceil => args[0].ceil(),
+                    Function::Round => args[0].round(),
+                    Function::Min => args.into_iter().fold(f64::INFINITY, f64::min),
+                    Function::Max => args.into_iter().fold(f64::NEG_INFINITY, f64::max),
+                }
+            }
+            Expression::GeoDistance { field: path, lat, lon } => {
+                let location = GeoPoint::from_value(field(payload, path)?)?;
+                location.distance(&GeoPoint::new(lat.eval(payload, score)?, lon.eval(payload, score)?))
+            }
+        };
+        value.is_finite().then_some(value)
+    }
+}
+
+/// Named expressions whose values are added to result payloads
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComputedFields {
+    fields: BTreeMap<String, Expression>,
+}
+
+impl ComputedFields {
+    /// Parse every expression; the error names the first that fails
+    pub fn parse(fields: &BTreeMap<String, String>) -> Result<Self> {
+        let fields = fields.iter()
+            .map(|(name, source)| {
+                Expression::parse(source)
+                    .map(|expr| (name.clone(), expr))
+                    .map_err(|e| match e {
+                        Error::InvalidExpression(message) => invalid(format!("computed field \"{}\": {}", name, message)),
+                        other => other,
+                    })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { fields })
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Evaluate every field for `point` and set it on `payload`, which
+    /// becomes an object if it is not one; fields that don't evaluate are
+    /// set to null
+    pub fn apply(&self, point: &Point, score: Option<f32>, payload: &mut Value) {
+        if self.fields.is_empty() {
+            return;
+        }
+        if !payload.is_object() {
+            *payload = Value::Object(Default::default());
+        }
+        let Value::Object(object) = payload else { return };
+        for (name, expr) in &self.fields {
+            let value = expr.eval(point.payload.as_ref(), score).map_or(Value::Null, Value::from);
+            object.insert(name.clone(), value);
+        }
+    }
+}
+
+fn field<'a>(payload: Option<&'a Value>, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(payload?, |value, key| value.get(key))
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidExpression(message)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Score,
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::Ident(name) => write!(f, "\"{}\"", name),
+            Token::Score => write!(f, "\"$score\""),
+            Token::Op(op) => write!(f, "\"{}\"", op),
+            Token::LParen => write!(f, "\"(\""),
+            Token::RParen => write!(f, "\")\""),
+            Token::Comma => write!(f, "\",\""),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        i += 1;
+        match c {
+            c if c.is_whitespace() => {}
+            '+' | '-' | '*' | '/' | '%' | '^' => tokens.push(Token::Op(c)),
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            ',' => tokens.push(Token::Comma),
+            c if c.is_ascii_digit() || c == '.' => {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                // Exponent, as in 1e-3
+                if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                    let sign = usize::from(i + 1 < chars.len() && matches!(chars[i + 1], '+' | '-'));
+                    if chars.get(i + 1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                        i += 1 + sign;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                let text: String = chars[start..i].iter().collect();
+                let n = text.parse().map_err(|_| invalid(format!("invalid number \"{}\"", text)))?;
+                tokens.push(Token::Number(n));
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.')) {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                match text.strip_prefix('$') {
+                    Some("score") => tokens.push(Token::Score),
+                    Some(_) => return Err(invalid(format!("unknown variable \"{}\"", text))),
+                    None => tokens.push(Token::Ident(text)),
+                }
+            }
+            c => return Err(invalid(format!("unexpected character '{}'", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Nesting of the expression being parsed, which bounds the depth of
+    /// the tree and so the recursion of `eval`
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self.tokens.get(self.pos).cloned().ok_or_else(|| invalid("unexpected end".to_string()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(invalid(format!("expected {}, found {}", expected, token))),
+        }
+    }
+
+    /// Parse `parse` one level deeper
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_EXPRESSION_DEPTH {
+            return Err(invalid(format!("nested deeper than {}", MAX_EXPRESSION_DEPTH)));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn binary(&mut self, ops: &[(char, BinaryOp)], operand: fn(&mut Self) -> Result<Expression>) -> Result<Expression> {
+        let outer = self.depth;
+        let mut expr = operand(self)?;
+        while let Some(Token::Op(c)) = self.peek() {
+            let Some(&(_, op)) = ops.iter().find(|(symbol, _)| symbol == c) else { break };
+            self.pos += 1;
+            // Each operator puts everything before it one level deeper
+            let right = self.nested(operand)?;
+            self.depth += 1;
+            expr = Expression::Binary(op, Box::new(expr), Box::new(right));
+        }
+        self.depth = outer;
+        Ok(expr)
+    }
+
+    fn expr(&mut self) -> Result<Expression> {
+        self.binary(&[('+', BinaryOp::Add), ('-', BinaryOp::Sub)], Self::term)
+    }
+
+    fn term(&mut self) -> Result<Expression> {
+        self.binary(&[('*', BinaryOp::Mul), ('/', BinaryOp::Div), ('%', BinaryOp::Rem)], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expression> {
+        if self.peek() == Some(&Token::Op('-')) {
+            self.pos += 1;
+            return Ok(Expression::Neg(Box::new(self.nested(Self::unary)?)));
+        }
+        let base = self.primary()?;
+        if self.peek() == Some(&Token::Op('^')) {
+            self.pos += 1;
+            return Ok(Expression::Binary(BinaryOp::Pow, Box::new(base), Box::new(self.nested(Self::unary)?)));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expression> {
+        match self.next()? {
+            Token::Number(n) => Ok(Expression::Number(n)),
+            Token::Score => Ok(Expression::Score),
+            Token::LParen => {
+                let expr = self.nested(Self::expr)?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Token::Ident(name) if self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                self.nested(|parser| parser.call(&name))
+            }
+            Token::Ident(name) => Ok(Expression::Field(split_path(&name)?)),
+            token => Err(invalid(format!("unexpected {}", token))),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Expression> {
+        if name == "geo_distance" {
+            let field = match self.next()? {
+                Token::Ident(field) => split_path(&field)?,
+                token => return Err(invalid(format!("geo_distance expects a field first, found {}", token))),
+            };
+            self.expect(Token::Comma)?;
+            let lat = self.expr()?;
+            self.expect(Token::Comma)?;
+            let lon = self.expr()?;
+            self.expect(Token::RParen)?;
+            return Ok(Expression::GeoDistance { field, lat: Box::new(lat), lon: Box::new(lon) });
+        }
+
+        let function = Function::from_name(name).ok_or_else(|| invalid(format!("unknown function \"{}\"", name)))?;
+        let mut args = vec![self.expr()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            args.push(self.expr()?);
+        }
+        self.expect(Token::RParen)?;
+        if args.len() != 1 && !function.variadic() {
+            return Err(invalid(format!("{} takes one argument, got {}", name, args.len())));
+        }
+        Ok(Expression::Call(function, args))
+    }
+}
+
+fn split_path(name: &str) -> Result<Vec<String>> {
+    let path: Vec<String> = name.split('.').map(str::to_string).collect();
+    if path.iter().any(String::is_empty) {
+        return Err(invalid(format!("invalid field \"{}\"", name)));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PointId, Vector};
+
+    fn eval(source: &str, payload: &Value) -> Option<f64> {
+        Expression::parse(source).unwrap().eval(Some(payload), Some(0.5))
+    }
+
+    #[test]
+    fn test_expressions() {
+        let payload = serde_json::json!({
+            "price": 100, "qty": 3, "name": "x",
+            "shop": {"location": {"lat": 52.52, "lon": 13.405}}
+        });
+        assert_eq!(eval("price * 1.22", &payload), Some(122.0));
+        assert_eq!(eval("2 + 3 * 4 - -1", &payload), Some(15.0));
+        assert_eq!(eval("(2 + 3) * 4 % 7", &payload), Some(6.0));
+        assert_eq!(eval("-2 ^ 2", &payload), Some(-4.0));
+        assert_eq!(eval("max(price / qty, 40, 1e1)", &payload), Some(40.0));
+        assert_eq!(eval("round($score * 10)", &payload), Some(5.0));
+        assert_eq!(eval("missing + 1", &payload), None);
+        assert_eq!(eval("name * 2", &payload), None);
+        assert_eq!(eval("price / 0", &payload), None);
+        assert_eq!(eval("geo_distance(shop.location, 52.52, 13.405)", &payload), Some(0.0));
+        // Berlin to Paris is about 878 km
+        let km = eval("geo_distance(shop.location, 48.8566, 2.3522) / 1000", &payload).unwrap();
+        assert!((km - 878.0).abs() < 5.0, "{}", km);
+
+        for bad in ["price *", "price +* 2", "foo(1)", "abs(1, 2)", "(price", "$other", "price..x", "geo_distance(1, 2, 3)", "2 3"] {
+            assert!(Expression::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_expression_limits() {
+        let payload = serde_json::json!({"price": 2});
+        let nested = |depth: usize| format!("{}price{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(eval(&nested(MAX_EXPRESSION_DEPTH - 1), &payload), Some(2.0));
+        assert_eq!(eval(&format!("{}1", "-".repeat(50)), &payload), Some(1.0));
+        assert_eq!(eval(&vec!["price"; 50].join(" + "), &payload), Some(100.0));
+
+        let too_deep = [
+            nested(1000),
+            format!("{}1", "-".repeat(1000)),
+            vec!["2"; 1000].join("^"),
+            format!("{}1{}", "abs(".repeat(500), ")".repeat(500)),
+            vec!["1"; 1000].join("+"),
+        ];
+        for source in too_deep {
+            let err = Expression::parse(&source).unwrap_err();
+            assert!(matches!(&err, Error::InvalidExpression(m) if m.contains("nested deeper")), "{}", err);
+        }
+
+        for long in [nested(10_000), format!("price{}", " ".repeat(MAX_EXPRESSION_LEN))] {
+            let err = Expression::parse(&long).unwrap_err();
+            assert!(matches!(&err, Error::InvalidExpression(m) if m.contains("longer than")), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_computed_fields() {
+        let fields: BTreeMap<String, String> = [("gross", "price * 1.5"), ("missing", "nope + 1")]
+            .into_iter()
+            .map(|(name, source)| (name.to_string(), source.to_string()))
+            .collect();
+        let computed = ComputedFields::parse(&fields).unwrap();
+        let point = Point::new(PointId::Integer(1), Vector::new(vec![0.0]), Some(serde_json::json!({"price": 10})));
+
+        let mut payload = Value::Null;
+        computed.apply(&point, None, &mut payload);
+        assert_eq!(payload, serde_json::json!({"gross": 15.0, "missing": null}));
+
+        let bad: BTreeMap<String, String> = [("x".to_string(), "1 +".to_string())].into_iter().collect();
+        let err = ComputedFields::parse(&bad).unwrap_err().to_string();
+        assert!(err.contains("computed field \"x\""), "{}", err);
+    }
+}
@@ -1,34 +1,108 @@
 // Simple payload filter implementation
 use serde_json::Value;
-use crate::Point;
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::{geo, trigram, GeoOrigin, GeoPoint, KeywordParams, Point, TextDictionaries};
 
 pub trait Filter {
     fn matches(&self, point: &Point) -> bool;
 }
 
+/// Any predicate on points is a filter
+impl<F: Fn(&Point) -> bool> Filter for F {
+    fn matches(&self, point: &Point) -> bool {
+        self(point)
+    }
+}
+
 pub struct PayloadFilter {
     condition: FilterCondition,
+    dictionaries: Option<Arc<TextDictionaries>>,
+    keyword_params: Option<Arc<HashMap<String, KeywordParams>>>,
 }
 
 #[derive(Debug, Clone)]
 pub enum FilterCondition {
+    /// The field is `value`, or an array holding it
     Equals { field: String, value: Value },
     NotEquals { field: String, value: Value },
+    /// The field is an array holding every one of `values` (or the one
+    /// value given, if it is not an array)
+    All { field: String, values: Vec<Value> },
+    /// The field has a value that is none of `values`; for an array, at
+    /// least one of its elements is none of them
+    Except { field: String, values: Vec<Value> },
     GreaterThan { field: String, value: f64 },
     LessThan { field: String, value: f64 },
     GreaterEqual { field: String, value: f64 },
     LessEqual { field: String, value: f64 },
     Contains { field: String, value: String },
+    /// Any word of `text` (or a synonym) appears in the field, ignoring case
+    Text { field: String, text: String },
+    /// The field is a string within `max_edits` edits of `value`
+    Fuzzy { field: String, value: String, max_edits: usize },
+    /// The field holds a `{"lat", "lon"}` location, or an array with one,
+    /// within `radius` metres of `center`
+    GeoRadius { field: String, center: GeoPoint, radius: f64 },
     And(Vec<FilterCondition>),
     Or(Vec<FilterCondition>),
     Not(Box<FilterCondition>),
 }
 
+impl FilterCondition {
+    /// The field and center of the first `GeoRadius` condition not under a
+    /// `Not`, to measure the distances of matching points from
+    pub fn geo_origin(&self) -> Option<GeoOrigin> {
+        match self {
+            FilterCondition::GeoRadius { field, center, .. } => {
+                Some(GeoOrigin { field: field.clone(), center: *center })
+            }
+            FilterCondition::And(conditions) | FilterCondition::Or(conditions) => {
+                conditions.iter().find_map(FilterCondition::geo_origin)
+            }
+            _ => None,
+        }
+    }
+}
+
 impl PayloadFilter {
     #[inline]
     #[must_use]
     pub fn new(condition: FilterCondition) -> Self {
-        Self { condition }
+        Self { condition, dictionaries: None, keyword_params: None }
+    }
+
+    /// Apply a collection's synonyms and stopwords to `Text` conditions
+    #[must_use]
+    pub fn with_dictionaries(mut self, dictionaries: Arc<TextDictionaries>) -> Self {
+        self.dictionaries = Some(dictionaries);
+        self
+    }
+
+    /// Compare the string values of keyword-indexed fields as their index
+    /// options say in `Equals` and `NotEquals` conditions
+    #[must_use]
+    pub fn with_keyword_params(mut self, keyword_params: Arc<HashMap<String, KeywordParams>>) -> Self {
+        self.keyword_params = Some(keyword_params);
+        self
+    }
+
+    /// Whether a stored value equals a queried one
+    fn values_equal(&self, field: &str, stored: &Value, value: &Value) -> bool {
+        let field = field.strip_prefix('.').unwrap_or(field);
+        match (self.keyword_params.as_ref().and_then(|p| p.get(field)), stored, value) {
+            (Some(params), Value::String(stored), Value::String(value)) => params.equals(stored, value),
+            (_, Value::Number(stored), Value::Number(value)) => stored.as_f64() == value.as_f64(),
+            _ => stored == value,
+        }
+    }
+
+    /// Whether a stored value is a queried one, or an array holding it
+    fn value_matches(&self, field: &str, stored: &Value, value: &Value) -> bool {
+        match stored {
+            Value::Array(items) => items.iter().any(|item| self.values_equal(field, item, value)),
+            stored => self.values_equal(field, stored, value),
+        }
     }
 
     #[inline]
@@ -42,18 +116,31 @@ impl PayloadFilter {
         })
     }
 
-    fn matches_condition(condition: &FilterCondition, point: &Point) -> bool {
+    fn matches_condition(&self, condition: &FilterCondition, point: &Point) -> bool {
         match condition {
             FilterCondition::Equals { field, value } => {
                 Self::get_field_value(point, field)
-                    .map(|v| v == value)
+                    .map(|v| self.value_matches(field, v, value))
                     .unwrap_or(false)
             }
             FilterCondition::NotEquals { field, value } => {
                 Self::get_field_value(point, field)
-                    .map(|v| v != value)
+                    .map(|v| !self.value_matches(field, v, value))
                     .unwrap_or(true)
             }
+            FilterCondition::All { field, values } => {
+                Self::get_field_value(point, field)
+                    .map(|v| values.iter().all(|value| self.value_matches(field, v, value)))
+                    .unwrap_or(false)
+            }
+            FilterCondition::Except { field, values } => {
+                let excluded = |item: &Value| values.iter().any(|value| self.values_equal(field, item, value));
+                match Self::get_field_value(point, field) {
+                    Some(Value::Array(items)) => items.iter().any(|item| !excluded(item)),
+                    Some(Value::Null) | None => false,
+                    Some(v) => !excluded(v),
+                }
+            }
             FilterCondition::GreaterThan { field, value } => {
                 Self::get_field_value(point, field)
                     .and_then(|v: &serde_json::Value| v.as_f64())
@@ -84,14 +171,34 @@ impl PayloadFilter {
                     .map(|v: &str| v.contains(value))
                     .unwrap_or(false)
             }
+            FilterCondition::Text { field, text } => {
+                let Some(value) = Self::get_field_value(point, field).and_then(|v| v.as_str()) else {
+                    return false;
+                };
+                match &self.dictionaries {
+                    Some(dictionaries) => dictionaries.matches_text(value, text),
+                    None => TextDictionaries::default().matches_text(value, text),
+                }
+            }
+            FilterCondition::Fuzzy { field, value, max_edits } => {
+                Self::get_field_value(point, field)
+                    .and_then(|v| v.as_str())
+                    .map(|v| trigram::within_edits(v, value, *max_edits))
+                    .unwrap_or(false)
+            }
+            FilterCondition::GeoRadius { field, center, radius } => {
+                Self::get_field_value(point, field)
+                    .and_then(|v| geo::nearest_distance(v, center))
+                    .is_some_and(|distance| distance <= *radius)
+            }
             FilterCondition::And(conditions) => {
-                conditions.iter().all(|c| Self::matches_condition(c, point))
+                conditions.iter().all(|c| self.matches_condition(c, point))
             }
             FilterCondition::Or(conditions) => {
-                conditions.iter().any(|c| Self::matches_condition(c, point))
+                conditions.iter().any(|c| self.matches_condition(c, point))
             }
             FilterCondition::Not(condition) => {
-                !Self::matches_condition(condition, point)
+                !self.matches_condition(condition, point)
             }
         }
     }
@@ -100,7 +207,7 @@ impl PayloadFilter {
 impl Filter for PayloadFilter {
     #[inline]
     fn matches(&self, point: &Point) -> bool {
-        Self::matches_condition(&self.condition, point)
+        self.matches_condition(&self.condition, point)
     }
 }
 
@@ -0,0 +1,127 @@
+//! Query vectors computed from stored points
+//!
+//! A [`VectorFormula`] is a weighted sum of the dense vectors of existing
+//! points, evaluated inside the server so clients don't have to fetch the
+//! vectors first. It covers analogy style queries (`a - b + c`), centroids
+//! of a set of examples and the recommendation strategy, which moves the
+//! query towards positive examples and away from negative ones:
+//!
+//! ```text
+//! recommend = 2 * avg(positive) - avg(negative)
+//! ```
+
+use crate::{Collection, Error, Result, Vector};
+
+/// Weighted sum of stored point vectors
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VectorFormula {
+    /// (point id, weight)
+    terms: Vec<(String, f32)>,
+}
+
+impl VectorFormula {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `weight` times the vector of `id`
+    pub fn term(mut self, id: impl Into<String>, weight: f32) -> Self {
+        self.terms.push((id.into(), weight));
+        self
+    }
+
+    /// `sum(add) - sum(sub)`
+    pub fn sum(add: &[String], sub: &[String]) -> Self {
+        Self::weighted(add, 1.0, sub, 1.0)
+    }
+
+    /// `avg(add) - avg(sub)`
+    pub fn average(add: &[String], sub: &[String]) -> Self {
+        Self::weighted(add, 1.0 / add.len().max(1) as f32, sub, 1.0 / sub.len().max(1) as f32)
+    }
+
+    /// `2 * avg(positive) - avg(negative)`, or `avg(positive)` without
+    /// negative examples
+    pub fn recommend(positive: &[String], negative: &[String]) -> Self {
+        let scale = if negative.is_empty() { 1.0 } else { 2.0 };
+        Self::weighted(
+            positive,
+            scale / positive.len().max(1) as f32,
+            negative,
+            1.0 / negative.len().max(1) as f32,
+        )
+    }
+
+    fn weighted(add: &[String], add_weight: f32, sub: &[String], sub_weight: f32) -> Self {
+        let terms = add.iter().map(|id| (id.clone(), add_weight))
+            .chain(sub.iter().map(|id| (id.clone(), -sub_weight)))
+            .collect();
+        Self { terms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Ids of the points the formula reads
+    pub fn point_ids(&self) -> impl Iterator<Item = &str> {
+        self.terms.iter().map(|(id, _)| id.as_str())
+    }
+
+    /// Compute the vector from the points of `collection`. Fails if a point
+    /// is missing or the formula has no terms.
+    pub fn evaluate(&self, collection: &Collection) -> Result<Vector> {
+        if self.terms.is_empty() {
+            return Err(Error::InvalidConfig("formula has no terms".to_string()));
+        }
+        let dim = collection.vector_dim();
+        let mut result = vec![0.0f32; dim];
+        for (id, weight) in &self.terms {
+            let point = collection.get(id).ok_or_else(|| Error::PointNotFound(id.clone()))?;
+            let vector = point.vector.as_slice();
+            if vector.len() != dim {
+                return Err(Error::InvalidDimension { expected: dim, actual: vector.len() });
+            }
+            for (r, v) in result.iter_mut().zip(vector) {
+                *r += weight * v;
+            }
+        }
+        Ok(Vector::new(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CollectionConfig, Distance, Point, PointId};
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_formula_evaluation() {
+        let collection = Collection::new(CollectionConfig {
+            name: "formula".to_string(),
+            vector_dim: 2,
+            distance: Distance::Dot,
+            use_hnsw: false,
+            enable_bm25: false,
+        });
+        for (id, v) in [(1, [1.0, 0.0]), (2, [0.0, 1.0]), (3, [3.0, 3.0])] {
+            collection.upsert(Point::new(PointId::Integer(id), Vector::new(v.to_vec()), None)).unwrap();
+        }
+
+        let sum = VectorFormula::sum(&ids(&["1", "2"]), &ids(&["3"])).evaluate(&collection).unwrap();
+        assert_eq!(sum.as_slice(), &[-2.0, -2.0]);
+        let avg = VectorFormula::average(&ids(&["1", "2"]), &ids(&["3"])).evaluate(&collection).unwrap();
+        assert_eq!(avg.as_slice(), &[-2.5, -2.5]);
+        let rec = VectorFormula::recommend(&ids(&["1", "2"]), &[]).evaluate(&collection).unwrap();
+        assert_eq!(rec.as_slice(), &[0.5, 0.5]);
+        let rec = VectorFormula::recommend(&ids(&["1"]), &ids(&["2"])).evaluate(&collection).unwrap();
+        assert_eq!(rec.as_slice(), &[2.0, -1.0]);
+
+        assert!(VectorFormula::new().evaluate(&collection).is_err());
+        assert!(VectorFormula::new().term("9", 1.0).evaluate(&collection).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Stored points that searches through the HNSW graph would miss
+//!
+//! Some writes reach the stored points before the graph: the points of an
+//! open batch are linked when it ends, and a large batch or a vector update
+//! with `relink` leaves the linking to a background rebuild. Until then the
+//! graph lacks those points or links them with their old vectors. Their ids
+//! are kept in [`FreshPoints`], and graph searches score them by brute
+//! force and merge them into their results, so recall does not drop while a
+//! rebuild runs. The buffer empties as the points are linked.
+//!
+//! Past [`MAX_FRESH_POINTS`] the buffer overflows: it forgets its ids and
+//! searches scan every point by brute force instead, until the graph links
+//! all the points again.
+
+use crate::{HnswIndex, Point};
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Fresh points kept before searches fall back to brute force
+pub const MAX_FRESH_POINTS: usize = 10_000;
+
+#[derive(Debug, Default)]
+pub struct FreshPoints {
+    ids: RwLock<HashSet<String>>,
+    /// More than [`MAX_FRESH_POINTS`] were added; `ids` is empty
+    overflowed: AtomicBool,
+}
+
+impl FreshPoints {
+    pub(crate) fn add<'a>(&self, ids: impl IntoIterator<Item = &'a str>) {
+        let mut fresh = self.ids.write();
+        if self.overflowed.load(Ordering::Acquire) {
+            return;
+        }
+        fresh.extend(ids.into_iter().map(str::to_string));
+        if fresh.len() > MAX_FRESH_POINTS {
+            *fresh = HashSet::new();
+            self.overflowed.store(true, Ordering::Release);
+        }
+    }
+
+    /// Forget points just linked into the graph or deleted
+    pub(crate) fn remove<'a>(&self, ids: impl IntoIterator<Item = &'a str>) {
+        let mut fresh = self.ids.write();
+        if fresh.is_empty() {
+            return;
+        }
+        for id in ids {
+            fresh.remove(id);
+        }
+    }
+
+    /// Points noted, 0 once the buffer has overflowed
+    pub fn len(&self) -> usize {
+        self.ids.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.read().is_empty()
+    }
+
+    /// Whether the buffer overflowed, so graph searches would miss points
+    /// it no longer knows and searches should scan every point instead
+    pub fn overflowed(&self) -> bool {
+        self.overflowed.load(Ordering::Acquire)
+    }
+
+    /// Keep only the points `graph` misses, once it is the graph searches
+    /// walk. Points of another dimension are dropped, as it can never link
+    /// them. After an overflow, the caller makes sure `graph` links every
+    /// point with its current vector but those it misses entirely, which
+    /// are noted again.
+    pub(crate) fn retain_unlinked(&self, graph: &HnswIndex, points: &HashMap<String, Point>) {
+        let unlinked = |id: &str, point: &Point| {
+            !graph.contains(id) && (graph.is_empty() || point.vector.dim() == graph.dim())
+        };
+        let mut fresh = self.ids.write();
+        if self.overflowed.load(Ordering::Acquire) {
+            *fresh = points.iter()
+                .filter(|(id, point)| unlinked(id, point))
+                .map(|(id, _)| id.clone())
+                .collect();
+            // Still too many to merge: keep scanning
+            if fresh.len() > MAX_FRESH_POINTS {
+                *fresh = HashSet::new();
+            } else {
+                self.overflowed.store(false, Ordering::Release);
+            }
+        } else {
+            fresh.retain(|id| points.get(id).is_some_and(|point| unlinked(id, point)));
+        }
+    }
+
+    /// Merge the fresh points that `matches` allows into `results`, scored
+    /// by `score`, and keep the best `limit`. `results` come from the graph
+    /// and hold the stored points, so a point in both is kept once.
+    pub(crate) fn merge_into(
+        &self,
+        results: &mut Vec<(Point, f32)>,
+        points: &HashMap<String, Point>,
+        limit: usize,
+        matches: impl Fn(&Point) -> bool,
+        score: impl Fn(&Point) -> f32,
+    ) {
+        let fresh = self.ids.read();
+        if fresh.is_empty() {
+            return;
+        }
+        let found: HashSet<String> = results.iter().map(|(point, _)| point.id.to_string()).collect();
+        results.extend(
+            fresh.iter()
+                .filter(|id| !found.contains(*id))
+                .filter_map(|id| points.get(id))
+                .filter(|point| matches(point))
+                .map(|point| (point.clone(), score(point))),
+        );
+        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(limit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PointId, Vector};
+
+    fn point(id: u64, vector: Vec<f32>) -> Point {
+        Point::new(PointId::Integer(id), Vector::new(vector), None)
+    }
+
+    #[test]
+    fn test_merge_and_retain() {
+        let mut points = HashMap::new();
+        for (id, x) in [(1, 1.0), (2, 0.5), (3, 0.9), (4, 0.1)] {
+            points.insert(id.to_string(), point(id, vec![x, 0.0]));
+        }
+        let fresh = FreshPoints::default();
+        fresh.add(["2", "3", "4", "9"]);
+
+        let score = |point: &Point| point.vector.as_slice()[0];
+        let mut results = vec![(points["1"].clone(), 1.0), (points["2"].clone(), 0.5)];
+        fresh.merge_into(&mut results, &points, 3, |point| point.id != PointId::Integer(4), score);
+        let ids: Vec<String> = results.iter().map(|(point, _)| point.id.to_string()).collect();
+        assert_eq!(ids, ["1", "3", "2"]);
+
+        let mut graph = HnswIndex::new(16, 3);
+        graph.insert(points["2"].clone()).unwrap();
+        fresh.retain_unlinked(&graph, &points);
+        assert_eq!(fresh.len(), 2);
+        fresh.remove(["3", "4"]);
+        assert!(fresh.is_empty());
+
+        // Past the limit the ids are forgotten until a graph links the points
+        fresh.add(["1"]);
+        let many: Vec<String> = (0..MAX_FRESH_POINTS).map(|i| format!("new-{}", i)).collect();
+        fresh.add(many.iter().map(String::as_str));
+        assert!(fresh.overflowed());
+        assert!(fresh.is_empty());
+        fresh.add(["3"]);
+        assert!(fresh.is_empty());
+        fresh.retain_unlinked(&graph, &points);
+        assert!(!fresh.overflowed());
+        let mut unlinked: Vec<String> = fresh.ids.read().iter().cloned().collect();
+        unlinked.sort();
+        assert_eq!(unlinked, ["1", "3", "4"]);
+    }
+}
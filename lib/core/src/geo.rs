@@ -0,0 +1,104 @@
+//! Geographic locations in payloads
+//!
+//! A location is a payload object `{"lat": <degrees>, "lon": <degrees>}`,
+//! and a field may hold an array of them. Distances are great-circle
+//! distances in metres on a sphere of the Earth's mean radius, which is
+//! within 0.5% of the distance on the ellipsoid.
+
+use crate::Point;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Mean radius of the Earth in metres
+pub const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// A point on the Earth, in degrees
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self { lat, lon }
+    }
+
+    /// The location a payload value holds, if it is a `{"lat", "lon"}` object
+    pub fn from_value(value: &Value) -> Option<Self> {
+        Some(Self::new(value.get("lat")?.as_f64()?, value.get("lon")?.as_f64()?))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(-90.0..=90.0).contains(&self.lat) {
+            return Err(format!("latitude {} is out of range [-90, 90]", self.lat));
+        }
+        if !(-180.0..=180.0).contains(&self.lon) {
+            return Err(format!("longitude {} is out of range [-180, 180]", self.lon));
+        }
+        Ok(())
+    }
+
+    /// Great-circle distance to `other` in metres (haversine formula)
+    pub fn distance(&self, other: &GeoPoint) -> f64 {
+        let (phi1, phi2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_phi = (other.lat - self.lat).to_radians();
+        let d_lambda = (other.lon - self.lon).to_radians();
+        let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_M * a.sqrt().min(1.0).asin()
+    }
+}
+
+/// Distance in metres from `origin` to the location in `value`, or to the
+/// nearest one if it is an array; None if it holds no location
+pub fn nearest_distance(value: &Value, origin: &GeoPoint) -> Option<f64> {
+    match value {
+        Value::Array(items) => items.iter()
+            .filter_map(GeoPoint::from_value)
+            .map(|location| origin.distance(&location))
+            .min_by(f64::total_cmp),
+        value => GeoPoint::from_value(value).map(|location| origin.distance(&location)),
+    }
+}
+
+/// A location field and the point its distances are measured from, such
+/// as the center of a geo radius filter
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoOrigin {
+    pub field: String,
+    pub center: GeoPoint,
+}
+
+impl GeoOrigin {
+    /// Distance in metres from the center to the nearest location in the
+    /// field of `point`; a leading dot in the field name is ignored
+    pub fn distance(&self, point: &Point) -> Option<f64> {
+        let field = self.field.strip_prefix('.').unwrap_or(&self.field);
+        nearest_distance(point.payload.as_ref()?.get(field)?, &self.center)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_distance() {
+        let berlin = GeoPoint::new(52.52, 13.405);
+        let paris = GeoPoint::new(48.8566, 2.3522);
+        let km = berlin.distance(&paris) / 1000.0;
+        assert!((875.0..880.0).contains(&km), "{}", km);
+        assert_eq!(berlin.distance(&berlin), 0.0);
+        // Antipodes are half the circumference apart
+        let antipode = GeoPoint::new(-52.52, 13.405 - 180.0);
+        assert!((berlin.distance(&antipode) - std::f64::consts::PI * EARTH_RADIUS_M).abs() < 1.0);
+
+        let locations = json!([{"lat": 48.8566, "lon": 2.3522}, {"lat": 52.5, "lon": 13.4}, "nowhere"]);
+        assert!(nearest_distance(&locations, &berlin).unwrap() < 3000.0);
+        assert_eq!(nearest_distance(&json!({"lat": "52"}), &berlin), None);
+        assert!(GeoPoint::new(91.0, 0.0).validate().is_err());
+        assert!(GeoPoint::new(0.0, -180.0).validate().is_ok());
+    }
+}
@@ -0,0 +1,368 @@
+//! GPU read replica for hot collection vectors
+//!
+//! Keeps an int8 scalar-quantized copy of a collection's vectors and scores
+//! batches of queries against all of them at once (a single matrix product).
+//! With the `cuda` feature the quantized matrix lives in device memory and the
+//! product runs as a CUDA kernel; without it, or when no device/driver is
+//! available at runtime, the same quantized scoring runs on the CPU.
+//!
+//! Quantized scores are approximate, so callers should oversample and rescore
+//! the candidates with full-precision vectors (see [`Collection::search_batch`]).
+//!
+//! [`Collection::search_batch`]: crate::Collection::search_batch
+
+use crate::{Distance, Point};
+use rayon::prelude::*;
+
+/// Quantized, batch-scorable copy of a collection's vectors
+pub struct GpuReplica {
+    dim: usize,
+    distance: Distance,
+    ids: Vec<String>,
+    /// Row-major int8 codes, `ids.len() * dim`
+    codes: Vec<i8>,
+    /// Per-row dequantization scale
+    scales: Vec<f32>,
+    /// Per-row squared L2 norm of the original vector (Euclidean only)
+    norms_sq: Vec<f32>,
+    backend: Backend,
+}
+
+enum Backend {
+    Cpu,
+    #[cfg(feature = "cuda")]
+    Cuda(cuda::CudaScorer),
+}
+
+impl GpuReplica {
+    /// Build a replica from points.
+    ///
+    /// Vectors whose dimension does not match `dim` are skipped. For cosine
+    /// distance vectors are normalized before quantization.
+    pub fn build<'a, I>(points: I, dim: usize, distance: Distance) -> Self
+    where
+        I: IntoIterator<Item = &'a Point>,
+    {
+        let mut ids = Vec::new();
+        let mut codes = Vec::new();
+        let mut scales = Vec::new();
+        let mut norms_sq = Vec::new();
+
+        for point in points {
+            if point.vector.dim() != dim || dim == 0 {
+                continue;
+            }
+            let vector = if distance == Distance::Cosine {
+                point.vector.normalized()
+            } else {
+                point.vector.clone()
+            };
+            let values = vector.as_slice();
+
+            let (row, scale) = quantize(values);
+            ids.push(point.id.to_string());
+            codes.extend_from_slice(&row);
+            scales.push(scale);
+            norms_sq.push(crate::simd::norm_squared_simd(values));
+        }
+
+        let backend = Self::upload(&codes, &scales, dim);
+
+        Self {
+            dim,
+            distance,
+            ids,
+            codes,
+            scales,
+            norms_sq,
+            backend,
+        }
+    }
+
+    #[cfg(feature = "cuda")]
+    fn upload(codes: &[i8], scales: &[f32], dim: usize) -> Backend {
+        if scales.is_empty() {
+            return Backend::Cpu;
+        }
+        match cuda::CudaScorer::new(codes, scales, dim) {
+            Some(scorer) => Backend::Cuda(scorer),
+            None => Backend::Cpu,
+        }
+    }
+
+    #[cfg(not(feature = "cuda"))]
+    fn upload(_codes: &[i8], _scales: &[f32], _dim: usize) -> Backend {
+        Backend::Cpu
+    }
+
+    /// Whether scoring runs on a GPU device (false means CPU fallback)
+    #[inline]
+    #[must_use]
+    pub fn is_gpu(&self) -> bool {
+        !matches!(self.backend, Backend::Cpu)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Approximate size of the quantized data in bytes
+    #[must_use]
+    pub fn memory_bytes(&self) -> usize {
+        self.codes.len() + self.scales.len() * 4 + self.norms_sq.len() * 4
+    }
+
+    /// Score every query against every replicated vector.
+    ///
+    /// Returns one row of `len()` scores per query, higher is better
+    /// (Euclidean scores are negated distances, as in brute-force search).
+    pub fn score_batch(&self, queries: &[&[f32]]) -> Vec<Vec<f32>> {
+        let n = self.ids.len();
+        if n == 0 || queries.is_empty() {
+            return vec![Vec::new(); queries.len()];
+        }
+
+        let prepared: Vec<Vec<f32>> = queries
+            .iter()
+            .map(|q| {
+                let mut q = q.to_vec();
+                q.resize(self.dim, 0.0);
+                if self.distance == Distance::Cosine {
+                    let norm = crate::simd::norm_simd(&q);
+                    if norm > 0.0 {
+                        q.iter_mut().for_each(|x| *x /= norm);
+                    }
+                }
+                q
+            })
+            .collect();
+
+        let dots = match &self.backend {
+            #[cfg(feature = "cuda")]
+            Backend::Cuda(scorer) => scorer
+                .score(&prepared, n)
+                .unwrap_or_else(|| self.score_cpu(&prepared)),
+            Backend::Cpu => self.score_cpu(&prepared),
+        };
+
+        match self.distance {
+            Distance::Cosine | Distance::Dot => dots,
+            // Collection::enable_gpu_replica refuses Hamming collections
+            Distance::Hamming => unreachable!("binary vectors have no quantized replica"),
+            Distance::Euclidean => dots
+                .into_iter()
+                .zip(prepared.iter())
+                .map(|(row, q)| {
+                    let q_norm_sq = crate::simd::norm_squared_simd(q);
+                    row.into_iter()
+                        .zip(self.norms_sq.iter())
+                        .map(|(dot, x_norm_sq)| -(q_norm_sq + x_norm_sq - 2.0 * dot).max(0.0).sqrt())
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Top-`limit` candidates per query as `(point id, approximate score)`
+    pub fn search_batch(&self, queries: &[&[f32]], limit: usize) -> Vec<Vec<(String, f32)>> {
+        self.score_batch(queries)
+            .into_iter()
+            .map(|scores| {
+                let mut scored: Vec<(usize, f32)> = scores.into_iter().enumerate().collect();
+                if scored.len() > limit {
+                    scored.select_nth_unstable_by(limit, |a, b| {
+                        b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal)
+                    });
+                    scored.truncate(limit);
+                }
+                scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+                scored
+                    .into_iter()
+                    .map(|(idx, score)| (self.ids[idx].clone(), score))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn score_cpu(&self, queries: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        let dim = self.dim;
+        queries
+            .iter()
+            .map(|q| {
+                self.codes
+                    .par_chunks(dim)
+                    .zip(self.scales.par_iter())
+                    .map(|(row, scale)| {
+                        let acc: f32 = q.iter().zip(row.iter()).map(|(a, &c)| a * c as f32).sum();
+                        acc * scale
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Symmetric per-vector int8 quantization
+fn quantize(values: &[f32]) -> (Vec<i8>, f32) {
+    let max_abs = values.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+    if max_abs == 0.0 {
+        return (vec![0; values.len()], 0.0);
+    }
+    let scale = max_abs / 127.0;
+    let codes = values
+        .iter()
+        .map(|v| (v / scale).round().clamp(-127.0, 127.0) as i8)
+        .collect();
+    (codes, scale)
+}
+
+#[cfg(feature = "cuda")]
+mod cuda {
+    use cudarc::driver::{CudaDevice, CudaFunction, CudaSlice, LaunchAsync, LaunchConfig};
+    use std::sync::Arc;
+
+    const MODULE: &str = "vectx_replica";
+    const KERNEL: &str = "score_i8";
+    const BLOCK: u32 = 256;
+
+    const KERNEL_SRC: &str = r#"
+extern "C" __global__ void score_i8(
+    const float* queries, const signed char* codes, const float* scales,
+    float* out, int n, int dim, int nq)
+{
+    int row = blockIdx.x * blockDim.x + threadIdx.x;
+    int q = blockIdx.y;
+    if (row >= n || q >= nq) return;
+    const float* qv = queries + (size_t)q * dim;
+    const signed char* cv = codes + (size_t)row * dim;
+    float acc = 0.0f;
+    for (int j = 0; j < dim; ++j) {
+        acc += qv[j] * (float)cv[j];
+    }
+    out[(size_t)q * n + row] = acc * scales[row];
+}
+"#;
+
+    /// Quantized vectors resident in device memory
+    pub(super) struct CudaScorer {
+        device: Arc<CudaDevice>,
+        codes: CudaSlice<i8>,
+        scales: CudaSlice<f32>,
+        dim: usize,
+    }
+
+    impl CudaScorer {
+        /// Upload the replica to device 0. Returns `None` if no CUDA driver or
+        /// device is available, so callers can fall back to CPU.
+        pub(super) fn new(codes: &[i8], scales: &[f32], dim: usize) -> Option<Self> {
+            // cudarc panics when the driver library cannot be loaded
+            let device = std::panic::catch_unwind(|| CudaDevice::new(0)).ok()?.ok()?;
+
+            let ptx = cudarc::nvrtc::compile_ptx(KERNEL_SRC).ok()?;
+            device.load_ptx(ptx, MODULE, &[KERNEL]).ok()?;
+
+            let codes = device.htod_sync_copy(codes).ok()?;
+            let scales = device.htod_sync_copy(scales).ok()?;
+
+            Some(Self { device, codes, scales, dim })
+        }
+
+        fn kernel(&self) -> Option<CudaFunction> {
+            self.device.get_func(MODULE, KERNEL)
+        }
+
+        /// Dot products of each query against all `n` rows
+        pub(super) fn score(&self, queries: &[Vec<f32>], n: usize) -> Option<Vec<Vec<f32>>> {
+            let nq = queries.len();
+            let flat: Vec<f32> = queries.iter().flat_map(|q| q.iter().copied()).collect();
+
+            let queries_dev = self.device.htod_sync_copy(&flat).ok()?;
+            let mut out = self.device.alloc_zeros::<f32>(nq * n).ok()?;
+
+            let cfg = LaunchConfig {
+                grid_dim: ((n as u32).div_ceil(BLOCK), nq as u32, 1),
+                block_dim: (BLOCK, 1, 1),
+                shared_mem_bytes: 0,
+            };
+            unsafe {
+                self.kernel()?
+                    .launch(
+                        cfg,
+                        (
+                            &queries_dev,
+                            &self.codes,
+                            &self.scales,
+                            &mut out,
+                            n as i32,
+                            self.dim as i32,
+                            nq as i32,
+                        ),
+                    )
+                    .ok()?;
+            }
+
+            let host = self.device.dtoh_sync_copy(&out).ok()?;
+            Some(host.chunks(n).map(|row| row.to_vec()).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PointId, Vector};
+
+    fn point(id: &str, v: Vec<f32>) -> Point {
+        Point::new(PointId::String(id.to_string()), Vector::new(v), None)
+    }
+
+    #[test]
+    fn test_replica_cosine_ranking() {
+        let points = [
+            point("a", vec![1.0, 0.0, 0.0]),
+            point("b", vec![0.0, 1.0, 0.0]),
+            point("c", vec![0.7, 0.7, 0.0]),
+        ];
+        let replica = GpuReplica::build(points.iter(), 3, Distance::Cosine);
+        assert_eq!(replica.len(), 3);
+
+        let results = replica.search_batch(&[&[1.0, 0.1, 0.0], &[0.0, 2.0, 0.0]], 2);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0][0].0, "a");
+        assert_eq!(results[0][1].0, "c");
+        assert_eq!(results[1][0].0, "b");
+        assert!((results[1][0].1 - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_replica_euclidean_scores() {
+        let points = [point("a", vec![0.0, 0.0]), point("b", vec![3.0, 4.0])];
+        let replica = GpuReplica::build(points.iter(), 2, Distance::Euclidean);
+
+        let scores = replica.score_batch(&[&[0.0, 0.0]]);
+        assert!(scores[0][0].abs() < 1e-3);
+        assert!((scores[0][1] + 5.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_replica_skips_wrong_dimension() {
+        let points = [point("a", vec![1.0, 0.0]), point("b", vec![1.0, 0.0, 0.0])];
+        let replica = GpuReplica::build(points.iter(), 2, Distance::Dot);
+        assert_eq!(replica.len(), 1);
+        assert_eq!(replica.memory_bytes(), 2 + 4 + 4);
+    }
+}
@@ -0,0 +1,107 @@
+//! Highlighting of matched terms in text search results
+//!
+//! The BM25 index keeps the byte range of every token it indexes, so the
+//! occurrences of the query terms in a document are known without
+//! re-tokenizing it. [`Collection::highlight`](crate::Collection::highlight)
+//! returns those ranges and short fragments of the text around them, with
+//! each match wrapped in `pre_tag`/`post_tag`. Fragments are cut from the
+//! raw payload text and not escaped.
+
+use serde::{Deserialize, Serialize};
+
+/// How highlight fragments are built
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct HighlightOptions {
+    pub pre_tag: String,
+    pub post_tag: String,
+    /// Approximate length of a fragment in bytes
+    pub fragment_size: usize,
+    pub max_fragments: usize,
+}
+
+impl Default for HighlightOptions {
+    fn default() -> Self {
+        Self {
+            pre_tag: "<em>".to_string(),
+            post_tag: "</em>".to_string(),
+            fragment_size: 100,
+            max_fragments: 3,
+        }
+    }
+}
+
+/// Matched terms of one document
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Highlight {
+    /// Byte ranges of the matches in the text, in order
+    pub offsets: Vec<(usize, usize)>,
+    /// Text around the matches with each match tagged
+    pub fragments: Vec<String>,
+}
+
+impl Highlight {
+    /// Build fragments of `text` around `offsets`
+    pub fn new(text: &str, offsets: Vec<(usize, usize)>, options: &HighlightOptions) -> Self {
+        let context = options.fragment_size / 2;
+        let mut fragments = Vec::new();
+        let mut next = 0;
+        while next < offsets.len() && fragments.len() < options.max_fragments {
+            let (first_start, first_end) = offsets[next];
+            let start = char_boundary(text, first_start.saturating_sub(context), false);
+            let end = char_boundary(text, (first_end + context).min(text.len()), true);
+
+            let mut fragment = String::new();
+            let mut cursor = start;
+            while next < offsets.len() && offsets[next].1 <= end {
+                let (match_start, match_end) = offsets[next];
+                fragment.push_str(&text[cursor..match_start]);
+                fragment.push_str(&options.pre_tag);
+                fragment.push_str(&text[match_start..match_end]);
+                fragment.push_str(&options.post_tag);
+                cursor = match_end;
+                next += 1;
+            }
+            fragment.push_str(&text[cursor..end]);
+            fragments.push(fragment.trim().to_string());
+        }
+        Self { offsets, fragments }
+    }
+}
+
+/// Nearest char boundary at or before (or after, with `forward`) `index`
+fn char_boundary(text: &str, mut index: usize, forward: bool) -> usize {
+    while !text.is_char_boundary(index) {
+        if forward { index += 1 } else { index -= 1 }
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BM25Index;
+
+    #[test]
+    fn test_fragments() {
+        let text = "Das große TV-Gerät: the best tv deal, far far away from any other tv.";
+        let terms = vec!["tv".to_string()];
+        let mut index = BM25Index::new();
+        index.insert_doc("1", text);
+        let offsets = index.term_offsets("1", &terms);
+        assert_eq!(offsets.len(), 3);
+        assert!(offsets.iter().all(|&(s, e)| text[s..e].eq_ignore_ascii_case("tv")));
+
+        let options = HighlightOptions { fragment_size: 50, ..Default::default() };
+        let highlight = Highlight::new(text, offsets, &options);
+        // The first two matches share a fragment
+        assert_eq!(highlight.fragments.len(), 2);
+        assert!(highlight.fragments[0].contains("<em>TV</em>-Gerät"));
+        assert!(highlight.fragments[0].contains("best <em>tv</em>"));
+        assert!(highlight.fragments[1].ends_with("other <em>tv</em>."));
+
+        let one = Highlight::new(text, highlight.offsets.clone(), &HighlightOptions { max_fragments: 1, ..options });
+        assert_eq!(one.fragments.len(), 1);
+    }
+}
@@ -0,0 +1,208 @@
+//! Time series of collection statistics
+//!
+//! A collection keeps a bounded ring buffer of [`StatsSample`]s: point
+//! count, memory estimate, query rate and tail latency, taken at a fixed
+//! interval by the storage layer. That is enough to draw trends over the
+//! last day without an external metrics store. Query latencies are
+//! recorded as queries finish and summarized into the next sample.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Samples kept per collection: a day at one sample a minute
+pub const DEFAULT_HISTORY_LEN: usize = 1440;
+
+/// Latencies kept per sampling window; later queries replace earlier ones
+/// round-robin, so the percentile is approximate under heavy load
+const MAX_WINDOW_LATENCIES: usize = 10_000;
+
+/// Statistics of a collection at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatsSample {
+    /// When the sample was taken, ms since the Unix epoch
+    pub timestamp: u64,
+    pub points_count: usize,
+    /// Estimated bytes held in memory
+    pub memory_bytes: usize,
+    /// Queries per second since the previous sample
+    pub qps: f64,
+    /// 99th percentile query latency since the previous sample, in ms;
+    /// None if there were no queries
+    pub p99_ms: Option<f64>,
+}
+
+/// Query count and latencies since the last sample
+#[derive(Debug)]
+struct Window {
+    started_at: u64,
+    queries: u64,
+    /// In the order they were recorded, for round-robin replacement
+    latencies_ms: Vec<f64>,
+    /// The same latencies kept sorted, so percentiles need no sort
+    sorted_ms: Vec<f64>,
+}
+
+impl Window {
+    fn new(started_at: u64) -> Self {
+        Self { started_at, queries: 0, latencies_ms: Vec::new(), sorted_ms: Vec::new() }
+    }
+}
+
+/// Collects query latencies between samples
+#[derive(Debug)]
+pub struct QueryLatencies {
+    window: Mutex<Window>,
+}
+
+impl Default for QueryLatencies {
+    fn default() -> Self {
+        Self { window: Mutex::new(Window::new(crate::journal::now_millis())) }
+    }
+}
+
+impl QueryLatencies {
+    pub fn record(&self, elapsed: Duration) {
+        let mut window = self.window.lock();
+        let latency = elapsed.as_secs_f64() * 1000.0;
+        if window.latencies_ms.len() < MAX_WINDOW_LATENCIES {
+            window.latencies_ms.push(latency);
+        } else {
+            let slot = (window.queries % MAX_WINDOW_LATENCIES as u64) as usize;
+            let replaced = std::mem::replace(&mut window.latencies_ms[slot], latency);
+            let at = window.sorted_ms.partition_point(|&ms| ms.total_cmp(&replaced).is_lt());
+            window.sorted_ms.remove(at);
+        }
+        let at = window.sorted_ms.partition_point(|&ms| ms.total_cmp(&latency).is_lt());
+        window.sorted_ms.insert(at, latency);
+        window.queries += 1;
+    }
+
+    /// p99 latency of the queries in the current window, without closing it
+    pub fn p99(&self) -> Option<f64> {
+        percentile(&self.window.lock().sorted_ms, 0.99)
+    }
+
+    /// Queries recorded in the current window
+    pub fn queries(&self) -> u64 {
+        self.window.lock().queries
+    }
+
+    /// Close the current window at `now` (ms since the Unix epoch) and
+    /// return its query rate and p99 latency
+    pub fn take(&self, now: u64) -> (f64, Option<f64>) {
+        let window = std::mem::replace(&mut *self.window.lock(), Window::new(now));
+        let seconds = now.saturating_sub(window.started_at) as f64 / 1000.0;
+        let qps = if seconds > 0.0 { window.queries as f64 / seconds } else { 0.0 };
+        (qps, percentile(&window.sorted_ms, 0.99))
+    }
+}
+
+/// The value below which a fraction `q` of the sorted `values` fall
+/// (nearest rank)
+fn percentile(values: &[f64], q: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let rank = ((q * values.len() as f64).ceil() as usize).clamp(1, values.len());
+    Some(values[rank - 1])
+}
+
+/// The most recent samples, oldest first
+#[derive(Debug, Clone)]
+pub struct StatsHistory {
+    samples: VecDeque<StatsSample>,
+    capacity: usize,
+}
+
+impl Default for StatsHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_LEN)
+    }
+}
+
+impl StatsHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: VecDeque::with_capacity(capacity.min(DEFAULT_HISTORY_LEN)), capacity: capacity.max(1) }
+    }
+
+    /// Append a sample, dropping the oldest once full
+    pub fn push(&mut self, sample: StatsSample) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Samples taken after `since` (ms since the Unix epoch), or all
+    pub fn since(&self, since: Option<u64>) -> Vec<StatsSample> {
+        self.samples.iter()
+            .filter(|s| since.map_or(true, |since| s.timestamp > since))
+            .copied()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: u64) -> StatsSample {
+        StatsSample { timestamp, points_count: 0, memory_bytes: 0, qps: 0.0, p99_ms: None }
+    }
+
+    #[test]
+    fn test_history_ring_buffer() {
+        let mut history = StatsHistory::new(3);
+        for timestamp in 1..=5 {
+            history.push(sample(timestamp));
+        }
+        let timestamps: Vec<u64> = history.since(None).iter().map(|s| s.timestamp).collect();
+        assert_eq!(timestamps, vec![3, 4, 5]);
+        assert_eq!(history.since(Some(4)).len(), 1);
+    }
+
+    #[test]
+    fn test_query_latencies_window() {
+        let latencies = QueryLatencies { window: Mutex::new(Window::new(1_000)) };
+        for ms in 1..=100 {
+            latencies.record(Duration::from_millis(ms));
+        }
+        assert!((latencies.p99().unwrap() - 99.0).abs() < 1e-6);
+        let (qps, p99) = latencies.take(3_000);
+        assert!((qps - 50.0).abs() < 1e-9);
+        assert!((p99.unwrap() - 99.0).abs() < 1e-6);
+
+        // The next window starts empty
+        let (qps, p99) = latencies.take(4_000);
+        assert_eq!(qps, 0.0);
+        assert_eq!(p99, None);
+    }
+
+    #[test]
+    fn test_query_latencies_replace_oldest() {
+        let latencies = QueryLatencies { window: Mutex::new(Window::new(1_000)) };
+        for _ in 0..MAX_WINDOW_LATENCIES {
+            latencies.record(Duration::from_millis(500));
+        }
+        assert_eq!(latencies.p99(), Some(500.0));
+        for _ in 0..MAX_WINDOW_LATENCIES - 10 {
+            latencies.record(Duration::from_millis(2));
+        }
+        // 10 slow queries are left of the 10,000 kept: exactly the top 0.1%
+        assert_eq!(latencies.p99(), Some(2.0));
+        assert_eq!(latencies.queries(), 2 * MAX_WINDOW_LATENCIES as u64 - 10);
+        let window = latencies.window.lock();
+        assert_eq!(window.sorted_ms.len(), MAX_WINDOW_LATENCIES);
+        assert_eq!(window.sorted_ms.iter().filter(|&&ms| ms == 500.0).count(), 10);
+    }
+}
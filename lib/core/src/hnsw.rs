@@ -1,8 +1,77 @@
-use crate::{Point, Vector};
-use std::collections::HashMap;
+use crate::{Distance, Error, Point, Result, Vector};
+use std::collections::{HashMap, HashSet};
 use std::cmp::Ordering;
 use std::sync::Arc;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+/// Construction and search parameters of a collection's HNSW graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct HnswParams {
+    /// Links per node and layer
+    pub m: usize,
+    /// Size of the candidate list when linking a new node
+    pub ef_construct: usize,
+    /// Collections with fewer points are searched exhaustively
+    pub full_scan_threshold: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self { m: 16, ef_construct: 200, full_scan_threshold: 10_000 }
+    }
+}
+
+impl HnswParams {
+    /// Largest accepted `m`
+    pub const MAX_M: usize = 128;
+    /// Largest accepted `ef_construct`
+    pub const MAX_EF_CONSTRUCT: usize = 4096;
+
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// These parameters with the values `diff` sets replaced
+    #[must_use]
+    pub fn apply(&self, diff: &HnswParamsDiff) -> Self {
+        Self {
+            m: diff.m.unwrap_or(self.m),
+            ef_construct: diff.ef_construct.unwrap_or(self.ef_construct),
+            full_scan_threshold: diff.full_scan_threshold.unwrap_or(self.full_scan_threshold),
+        }
+    }
+
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.m < 2 {
+            return Err("hnsw m must be at least 2".to_string());
+        }
+        if self.ef_construct < 4 {
+            return Err("hnsw ef_construct must be at least 4".to_string());
+        }
+        if self.m > Self::MAX_M {
+            return Err(format!("hnsw m must be at most {}", Self::MAX_M));
+        }
+        if self.ef_construct > Self::MAX_EF_CONSTRUCT {
+            return Err(format!("hnsw ef_construct must be at most {}", Self::MAX_EF_CONSTRUCT));
+        }
+        Ok(())
+    }
+}
+
+/// A change to [`HnswParams`]; unset fields keep their value. Qdrant's
+/// other HNSW settings (`on_disk`, `payload_m`, ...) are accepted and ignored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct HnswParamsDiff {
+    #[serde(default)]
+    pub m: Option<usize>,
+    #[serde(default)]
+    pub ef_construct: Option<usize>,
+    #[serde(default)]
+    pub full_scan_threshold: Option<usize>,
+}
 
 /// Fast bit vector for visited node tracking
 /// Much faster than HashSet for dense integer sets
@@ -16,7 +85,7 @@ struct VisitedSet {
 impl VisitedSet {
     #[inline]
     fn new(capacity: usize) -> Self {
-        let num_words = (capacity + 63) / 64;
+        let num_words = capacity.div_ceil(64);
         Self {
             bits: vec![0; num_words],
             generation: 1,
@@ -37,7 +106,7 @@ impl VisitedSet {
 
     #[inline]
     fn ensure_capacity(&mut self, capacity: usize) {
-        let num_words = (capacity + 63) / 64;
+        let num_words = capacity.div_ceil(64);
         if num_words > self.bits.len() {
             self.bits.resize(num_words, 0);
             self.generations.resize(num_words, 0);
@@ -65,7 +134,7 @@ impl VisitedSet {
         !was_set
     }
 
-    #[inline]
+    #[cfg(test)]
     fn contains(&self, idx: usize) -> bool {
         let word_idx = idx / 64;
         let bit_idx = idx % 64;
@@ -138,6 +207,46 @@ impl PartialOrd for ReverseCandidate {
     }
 }
 
+/// A node of an exported graph and its links on each layer it is on
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphNode {
+    /// Id of the node's point
+    pub id: String,
+    /// Highest layer the node is on
+    pub level: usize,
+    /// Point ids the node links to, layer 0 first
+    pub layers: Vec<Vec<String>>,
+}
+
+/// Shape of one layer of an HNSW graph
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LayerStats {
+    pub layer: usize,
+    /// Nodes on the layer
+    pub nodes: usize,
+    /// Links on the layer; a link is one-way
+    pub edges: usize,
+    /// Outgoing links per node
+    pub avg_degree: f64,
+    pub max_degree: usize,
+    /// Nodes without outgoing or incoming links
+    pub isolated_nodes: usize,
+    /// Groups of nodes connected by links in either direction; a layer
+    /// that is fully reachable has one
+    pub connected_components: usize,
+}
+
+/// Shape of an HNSW graph, layer by layer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphStats {
+    pub nodes: usize,
+    /// Point every search starts from
+    pub entry_point: Option<String>,
+    /// Most links a node keeps per layer before it is pruned
+    pub max_links: usize,
+    pub layers: Vec<LayerStats>,
+}
+
 #[derive(Debug, Clone)]
 struct HnswNode {
     point: Point,
@@ -160,6 +269,9 @@ pub struct HnswIndex {
     max_connections: usize,
     max_layers: usize,
     ef_construction: usize,
+    /// Metric nodes are linked and searched by; cosine graphs keep
+    /// normalized copies of the vectors
+    distance: Distance,
     /// Reusable visited set (avoid allocations)
     visited: VisitedSet,
 }
@@ -174,10 +286,70 @@ impl HnswIndex {
             max_connections,
             max_layers,
             ef_construction: 200,
+            distance: Distance::Cosine,
             visited: VisitedSet::new(1024),
         }
     }
 
+    /// Link and search by `distance` rather than cosine. Only cosine
+    /// normalizes vectors; Hamming compares bit-packed ones (see
+    /// [`crate::binary`]).
+    #[must_use]
+    pub fn with_distance(mut self, distance: Distance) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn distance_metric(&self) -> Distance {
+        self.distance
+    }
+
+    /// Change the links per node and the construction candidate list size.
+    /// Nodes linked from now on use them; nodes already linked keep their
+    /// links until the graph is rebuilt.
+    pub fn set_construction_params(&mut self, max_connections: usize, ef_construction: usize) {
+        self.max_connections = max_connections;
+        self.ef_construction = ef_construction;
+    }
+
+    /// An empty graph with the same parameters and metric
+    #[must_use]
+    pub fn empty_like(&self) -> Self {
+        Self {
+            ef_construction: self.ef_construction,
+            distance: self.distance,
+            ..Self::new(self.max_connections, self.max_layers)
+        }
+    }
+
+    /// Build a graph from `points`. Points whose dimension differs from the
+    /// first one cannot be linked and are left out.
+    pub fn from_points<I: IntoIterator<Item = Point>>(points: I) -> Self {
+        let mut index = Self::new(16, 3);
+        for point in points {
+            let _ = index.insert(point);
+        }
+        index
+    }
+
+    /// Dimension of the indexed vectors (0 while the index is empty)
+    #[inline]
+    #[must_use]
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Fail unless a vector of `dim` components can join the graph. The
+    /// first insert into an empty index fixes the dimension.
+    pub fn check_dim(&self, dim: usize) -> Result<()> {
+        if !self.nodes.is_empty() && dim != self.dim {
+            return Err(Error::InvalidDimension { expected: self.dim, actual: dim });
+        }
+        Ok(())
+    }
+
     /// Get vector slice for a node (from contiguous storage)
     #[inline(always)]
     fn get_vector(&self, node_idx: usize) -> &[f32] {
@@ -188,11 +360,13 @@ impl HnswIndex {
         }
     }
 
-    /// Select layer using exponential decay
+    /// Select layer using exponential decay: each layer holds about one in
+    /// `max_connections` of the nodes of the layer below
     #[inline]
     fn select_layer(&self) -> usize {
+        let promote = 1.0 / self.max_connections.max(2) as f32;
         let mut layer = 0;
-        while layer < self.max_layers - 1 && rand::random::<f32>() < 0.5 {
+        while layer < self.max_layers - 1 && rand::random::<f32>() < promote {
             layer += 1;
         }
         layer
@@ -201,9 +375,18 @@ impl HnswIndex {
     /// Optimized distance calculation using contiguous storage
     #[inline(always)]
     fn distance_to_node(&self, query: &[f32], node_idx: usize) -> f32 {
-        let node_vec = self.get_vector(node_idx);
-        let dot = crate::simd::dot_product_simd(query, node_vec);
-        1.0 - dot
+        self.pair_distance(query, self.get_vector(node_idx))
+    }
+
+    /// Score reported for a distance, as the collection scores points:
+    /// cosine similarity, dot product, negated Euclidean distance or
+    /// negated number of differing bits
+    #[inline]
+    fn similarity(&self, dist: f32) -> f32 {
+        match self.distance {
+            Distance::Cosine => 1.0 - dist,
+            Distance::Euclidean | Distance::Dot | Distance::Hamming => -dist,
+        }
     }
 
     /// Prefetch vector data for a node (reduce cache misses)
@@ -312,22 +495,52 @@ impl HnswIndex {
         result_vec
     }
 
-    /// Distance between query vector and node (for public API)
-    #[inline]
-    fn distance(&self, query: &Vector, node_idx: usize) -> f32 {
-        let node_vec = self.get_vector(node_idx);
-        let dot = crate::simd::dot_product_simd(query.as_slice(), node_vec);
-        1.0 - dot
+    /// Insert a new point into the HNSW graph. Fails without touching the
+    /// graph if the vector's dimension differs from the indexed vectors.
+    pub fn insert(&mut self, point: Point) -> Result<()> {
+        self.check_dim(point.vector.dim())?;
+        self.insert_node(point, None);
+        Ok(())
     }
 
-    /// Insert a new point into the HNSW graph
-    pub fn insert(&mut self, point: Point) {
+    /// Insert many points under one write lock. Neighbour lists that grow past
+    /// their limit are pruned once at the end rather than after every insert.
+    /// Nothing is inserted if any vector has the wrong dimension.
+    pub fn insert_batch(&mut self, points: Vec<Point>) -> Result<()> {
+        let dim = match points.first() {
+            Some(first) if self.nodes.is_empty() => first.vector.dim(),
+            Some(_) => self.dim,
+            None => return Ok(()),
+        };
+        if let Some(point) = points.iter().find(|p| p.vector.dim() != dim) {
+            return Err(Error::InvalidDimension { expected: dim, actual: point.vector.dim() });
+        }
+
+        let mut overfull = HashSet::new();
+        for point in points {
+            self.insert_node(point, Some(&mut overfull));
+        }
+        for (node_idx, layer) in overfull {
+            self.prune_links(node_idx, layer);
+        }
+        Ok(())
+    }
+
+    /// Link a point into the graph. Overfull neighbour lists are pruned right
+    /// away, or collected into `deferred` as (node, layer) pairs.
+    fn insert_node(&mut self, mut point: Point, mut deferred: Option<&mut HashSet<(usize, usize)>>) {
+        // Cosine is measured as a dot product of normalized copies, whatever
+        // the collection stores
+        if self.distance == Distance::Cosine {
+            point.vector.normalize();
+        }
         let id_str = point.id.to_string();
         let layer = self.select_layer();
 
-        // Initialize dimension if first insert
-        if self.dim == 0 {
+        // The first point into an empty index fixes the dimension
+        if self.nodes.is_empty() {
             self.dim = point.vector.dim();
+            self.vectors.clear();
         }
 
         // Add vector to contiguous storage
@@ -345,97 +558,114 @@ impl HnswIndex {
             return;
         }
 
-        let entry_point = 0;
         let query = point.vector.as_slice();
-        let mut current_layer = self.max_layers - 1;
+        let mut entry = self.descend(query, layer);
 
-        while current_layer > layer {
-            let neighbors = self.search_layer(query, entry_point, 1, current_layer);
-            if !neighbors.is_empty() {
-                current_layer -= 1;
-            } else {
-                break;
+        // Link the node on every layer it is on, from the top down, each
+        // layer's closest node being where the next one is searched from
+        for current_layer in (0..=layer).rev() {
+            let candidates = self.search_layer(query, entry, self.ef_construction, current_layer);
+            if let Some(&(closest, _)) = candidates.first() {
+                entry = closest;
             }
+            node.layers[current_layer] = candidates
+                .iter()
+                .map(|&(idx, _)| idx)
+                .filter(|&idx| current_layer < self.nodes[idx].layers.len())
+                .take(self.max_connections)
+                .collect();
         }
 
-        let mut candidates = if !self.nodes.is_empty() {
-            self.search_layer(query, entry_point, self.ef_construction, layer)
-        } else {
-            Vec::new()
-        };
-        
-        candidates.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-        let neighbors: Vec<usize> = candidates
-            .iter()
-            .take(self.max_connections)
-            .map(|(idx, _)| *idx)
-            .collect();
-
-        node.layers[layer] = neighbors.clone();
-
+        let links = node.layers.clone();
         self.nodes.push(node);
         let node_idx = self.nodes.len() - 1;
         self.point_id_to_index.write().insert(id_str, node_idx);
 
-        for &neighbor_idx in &neighbors {
-            if neighbor_idx < self.nodes.len() && layer < self.nodes[neighbor_idx].layers.len() {
-                self.nodes[neighbor_idx].layers[layer].push(node_idx);
-                if self.nodes[neighbor_idx].layers[layer].len() > self.max_connections * 2 {
-                    let neighbor_vec = self.get_vector(neighbor_idx).to_vec();
-                    let mut layer_connections = self.nodes[neighbor_idx].layers[layer].clone();
-                    
-                    layer_connections.sort_by(|&a, &b| {
-                        if a < self.nodes.len() && b < self.nodes.len() {
-                            let dist_a = crate::simd::l2_distance_simd(&neighbor_vec, self.get_vector(a));
-                            let dist_b = crate::simd::l2_distance_simd(&neighbor_vec, self.get_vector(b));
-                            dist_a.partial_cmp(&dist_b).unwrap_or(std::cmp::Ordering::Equal)
-                        } else {
-                            std::cmp::Ordering::Equal
+        for (current_layer, neighbors) in links.iter().enumerate() {
+            for &neighbor_idx in neighbors {
+                self.nodes[neighbor_idx].layers[current_layer].push(node_idx);
+                if self.nodes[neighbor_idx].layers[current_layer].len() > self.max_connections * 2 {
+                    match deferred.as_deref_mut() {
+                        Some(overfull) => {
+                            overfull.insert((neighbor_idx, current_layer));
                         }
-                    });
-                    layer_connections.truncate(self.max_connections * 2);
-                    self.nodes[neighbor_idx].layers[layer] = layer_connections;
+                        None => self.prune_links(neighbor_idx, current_layer),
+                    }
                 }
             }
         }
     }
 
+    /// Walk greedily from the first node through the layers above `layer`,
+    /// returning the node closest to `query` found on the way down
+    fn descend(&mut self, query: &[f32], layer: usize) -> usize {
+        let mut entry = 0;
+        for current_layer in (layer + 1..self.max_layers).rev() {
+            if let Some(&(closest, _)) = self.search_layer(query, entry, 1, current_layer).first() {
+                entry = closest;
+            }
+        }
+        entry
+    }
+
+    /// Distance between two vectors by the graph's metric; smaller is closer
+    #[inline(always)]
+    fn pair_distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self.distance {
+            Distance::Cosine => 1.0 - crate::simd::dot_product_simd(a, b),
+            Distance::Euclidean => crate::simd::l2_distance_simd(a, b),
+            Distance::Dot => -crate::simd::dot_product_simd(a, b),
+            Distance::Hamming => crate::binary::hamming_distance(a, b) as f32,
+        }
+    }
+
+    /// Keep only the closest `2 * max_connections` links of a node on a layer
+    fn prune_links(&mut self, node_idx: usize, layer: usize) {
+        if self.nodes[node_idx].layers[layer].len() <= self.max_connections * 2 {
+            return;
+        }
+        let node_vec = self.get_vector(node_idx).to_vec();
+        let mut layer_connections = self.nodes[node_idx].layers[layer].clone();
+        
+        layer_connections.sort_by(|&a, &b| {
+            if a < self.nodes.len() && b < self.nodes.len() {
+                let dist_a = self.pair_distance(&node_vec, self.get_vector(a));
+                let dist_b = self.pair_distance(&node_vec, self.get_vector(b));
+                dist_a.partial_cmp(&dist_b).unwrap_or(std::cmp::Ordering::Equal)
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        });
+        layer_connections.truncate(self.max_connections * 2);
+        self.nodes[node_idx].layers[layer] = layer_connections;
+    }
+
     /// Search for k nearest neighbors
     /// Optimized for speed with lower ef values
     pub fn search(&mut self, query: &Vector, k: usize, ef: Option<usize>) -> Vec<(Point, f32)> {
-        if self.nodes.is_empty() {
+        if self.nodes.is_empty() || query.dim() != self.dim {
             return Vec::new();
         }
 
         // Use ef = k * 1.5 for speed (Redis-like approach), minimum 16
         let ef = ef.unwrap_or_else(|| (k + k / 2).max(16)).max(k);
-        let entry_point = 0;
-        let query_slice = query.as_slice();
+        let normalized = (self.distance == Distance::Cosine).then(|| query.normalized());
+        let query_slice = normalized.as_ref().unwrap_or(query).as_slice();
         
         // For small datasets, skip upper layer traversal
         if self.nodes.len() < 1000 {
-            let results = self.search_layer(query_slice, entry_point, ef, 0);
+            let results = self.search_layer(query_slice, 0, ef, 0);
             return results
                 .into_iter()
                 .take(k)
                 .map(|(idx, dist)| {
                     let node = &self.nodes[idx];
-                    let similarity = 1.0 - dist;
-                    (node.point.clone(), similarity)
+                    (node.point.clone(), self.similarity(dist))
                 })
                 .collect();
         }
 
-        let mut current_layer = self.max_layers - 1;
-        while current_layer > 0 {
-            let neighbors = self.search_layer(query_slice, entry_point, 1, current_layer);
-            if !neighbors.is_empty() {
-                current_layer -= 1;
-            } else {
-                break;
-            }
-        }
-
+        let entry_point = self.descend(query_slice, 0);
         let results = self.search_layer(query_slice, entry_point, ef, 0);
         
         results
@@ -443,32 +673,61 @@ impl HnswIndex {
             .take(k)
             .map(|(idx, dist)| {
                 let node = &self.nodes[idx];
-                let similarity = 1.0 - dist;
-                (node.point.clone(), similarity)
+                (node.point.clone(), self.similarity(dist))
             })
             .collect()
     }
 
     pub fn remove(&mut self, point_id: &str) -> bool {
-        let mut index_map = self.point_id_to_index.write();
-        if let Some(index) = index_map.remove(point_id) {
-            // Remove from contiguous vector storage
-            let start = index * self.dim;
-            let end = start + self.dim;
-            if end <= self.vectors.len() {
-                self.vectors.drain(start..end);
+        self.remove_batch(&[point_id]) > 0
+    }
+
+    /// Remove several points in one pass: the remaining nodes and their
+    /// vectors are compacted and links are remapped to the new positions
+    /// (links to removed nodes are dropped). Returns how many were removed.
+    pub fn remove_batch(&mut self, point_ids: &[&str]) -> usize {
+        let id_map = self.point_id_to_index.clone();
+        let mut index_map = id_map.write();
+        let removed: HashSet<usize> = point_ids.iter()
+            .filter_map(|id| index_map.get(*id).copied())
+            .collect();
+        if removed.is_empty() {
+            return 0;
+        }
+
+        // Old node position -> new position (None if removed)
+        let mut next = 0;
+        let remap: Vec<Option<usize>> = (0..self.nodes.len())
+            .map(|i| {
+                if removed.contains(&i) {
+                    None
+                } else {
+                    next += 1;
+                    Some(next - 1)
+                }
+            })
+            .collect();
+
+        let old_nodes = std::mem::take(&mut self.nodes);
+        let old_vectors = std::mem::take(&mut self.vectors);
+        for (i, mut node) in old_nodes.into_iter().enumerate() {
+            if remap[i].is_none() {
+                continue;
             }
-            
-            self.nodes.remove(index);
-            
-            index_map.clear();
-            for (i, node) in self.nodes.iter().enumerate() {
-                index_map.insert(node.point.id.to_string(), i);
+            for links in &mut node.layers {
+                *links = links.iter().filter_map(|&l| remap.get(l).copied().flatten()).collect();
             }
-            true
-        } else {
-            false
+            if let Some(vector) = old_vectors.get(i * self.dim..(i + 1) * self.dim) {
+                self.vectors.extend_from_slice(vector);
+            }
+            self.nodes.push(node);
+        }
+
+        index_map.clear();
+        for (i, node) in self.nodes.iter().enumerate() {
+            index_map.insert(node.point.id.to_string(), i);
         }
+        removed.len()
     }
 
     #[inline]
@@ -477,11 +736,116 @@ impl HnswIndex {
         self.nodes.len()
     }
 
+    /// Whether the graph links the point `point_id`
+    pub fn contains(&self, point_id: &str) -> bool {
+        self.point_id_to_index.read().contains_key(point_id)
+    }
+
+    /// Id of the point of every node, in node order; an id linked twice
+    /// appears twice
+    pub fn node_ids(&self) -> Vec<String> {
+        self.nodes.iter().map(|node| node.point.id.to_string()).collect()
+    }
+
     #[inline]
     #[must_use]
     pub fn is_empty(&self) -> bool {
         self.nodes.is_empty()
     }
+
+    /// Every node with its links, in node order
+    pub fn export_graph(&self) -> Vec<GraphNode> {
+        let id = |idx: usize| self.nodes[idx].point.id.to_string();
+        self.nodes.iter()
+            .map(|node| GraphNode {
+                id: node.point.id.to_string(),
+                level: node.layers.len().saturating_sub(1),
+                layers: node.layers.iter()
+                    .map(|links| links.iter().filter(|&&l| l < self.nodes.len()).map(|&l| id(l)).collect())
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Node, link and component counts of every layer
+    pub fn graph_stats(&self) -> GraphStats {
+        let layer_count = self.nodes.iter().map(|node| node.layers.len()).max().unwrap_or(0);
+        let layers = (0..layer_count).map(|layer| self.layer_stats(layer)).collect();
+        GraphStats {
+            nodes: self.nodes.len(),
+            entry_point: self.nodes.first().map(|node| node.point.id.to_string()),
+            max_links: self.max_connections * 2,
+            layers,
+        }
+    }
+
+    fn layer_stats(&self, layer: usize) -> LayerStats {
+        let links_of = |idx: usize| -> &[usize] {
+            self.nodes[idx].layers.get(layer).map_or(&[], |links| links.as_slice())
+        };
+        let members: Vec<usize> = (0..self.nodes.len()).filter(|&idx| self.nodes[idx].layers.len() > layer).collect();
+
+        // Union-find over the links, followed both ways
+        let mut parent: Vec<usize> = (0..self.nodes.len()).collect();
+        fn root(parent: &mut [usize], mut idx: usize) -> usize {
+            while parent[idx] != idx {
+                parent[idx] = parent[parent[idx]];
+                idx = parent[idx];
+            }
+            idx
+        }
+        let mut linked = vec![false; self.nodes.len()];
+        let mut edges = 0;
+        let mut max_degree = 0;
+        for &idx in &members {
+            let links = links_of(idx);
+            edges += links.len();
+            max_degree = max_degree.max(links.len());
+            for &link in links.iter().filter(|&&l| l < self.nodes.len() && self.nodes[l].layers.len() > layer) {
+                linked[idx] = true;
+                linked[link] = true;
+                let (a, b) = (root(&mut parent, idx), root(&mut parent, link));
+                if a != b {
+                    parent[a] = b;
+                }
+            }
+        }
+        let connected_components = members.iter()
+            .filter(|&&idx| root(&mut parent, idx) == idx)
+            .count();
+
+        LayerStats {
+            layer,
+            nodes: members.len(),
+            edges,
+            avg_degree: if members.is_empty() { 0.0 } else { edges as f64 / members.len() as f64 },
+            max_degree,
+            isolated_nodes: members.iter().filter(|&&idx| !linked[idx]).count(),
+            connected_components,
+        }
+    }
+
+    /// Estimated bytes held by the graph, its contiguous vectors and the
+    /// points copied into its nodes
+    pub fn memory_usage(&self) -> usize {
+        use crate::stats::{point_payload_size, point_vectors_size, MAP_ENTRY_OVERHEAD};
+
+        let vectors = self.vectors.len() * std::mem::size_of::<f32>();
+        let nodes: usize = self.nodes.iter()
+            .map(|node| {
+                std::mem::size_of::<HnswNode>()
+                    + point_vectors_size(&node.point)
+                    + point_payload_size(&node.point)
+                    + node.layers.iter()
+                        .map(|links| std::mem::size_of::<Vec<usize>>() + links.len() * std::mem::size_of::<usize>())
+                        .sum::<usize>()
+            })
+            .sum();
+        let ids: usize = self.point_id_to_index.read().keys()
+            .map(|id| id.len() + std::mem::size_of::<usize>() + MAP_ENTRY_OVERHEAD)
+            .sum();
+        vectors + nodes + ids
+    }
 }
 
 #[cfg(test)]
@@ -496,7 +860,7 @@ mod tests {
         for i in 0..10 {
             let vector = Vector::new(vec![i as f32, i as f32, i as f32]);
             let point = Point::new(crate::PointId::Integer(i), vector, None);
-            index.insert(point);
+            index.insert(point).unwrap();
         }
 
         // Search
@@ -505,6 +869,146 @@ mod tests {
         assert!(!results.is_empty());
     }
 
+    #[test]
+    fn test_batch_remove_and_insert() {
+        let mut index = HnswIndex::new(4, 3);
+        let point = |i: u64, v: f32| {
+            let vector = Vector::new(vec![v, 1.0 - v]);
+            Point::new(crate::PointId::Integer(i), vector, None)
+        };
+        index.insert_batch((0..50).map(|i| point(i, i as f32 / 50.0)).collect()).unwrap();
+        assert_eq!(index.len(), 50);
+        // Deferred pruning still bounds every neighbour list
+        assert!(index.nodes.iter().all(|n| n.layers.iter().all(|l| l.len() <= 8)));
+
+        let ids: Vec<String> = (0..50).step_by(2).map(|i: u64| i.to_string()).collect();
+        let id_refs: Vec<&str> = ids.iter().map(String::as_str).collect();
+        assert_eq!(index.remove_batch(&id_refs), 25);
+        assert_eq!(index.len(), 25);
+        assert_eq!(index.vectors.len(), 50);
+        // Links only point at surviving nodes
+        assert!(index.nodes.iter().all(|n| n.layers.iter().flatten().all(|&l| l < 25)));
+
+        let results = index.search(&Vector::new(vec![0.98, 0.02]), 25, None);
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|(p, _)| matches!(p.id, crate::PointId::Integer(i) if i % 2 == 1)));
+    }
+
+    /// Storage, id map and links agree with the node list
+    fn assert_consistent(index: &HnswIndex) {
+        assert_eq!(index.vectors.len(), index.nodes.len() * index.dim);
+        let id_map = index.point_id_to_index.read();
+        assert_eq!(id_map.len(), index.nodes.len());
+        for (i, node) in index.nodes.iter().enumerate() {
+            assert_eq!(id_map.get(&node.point.id.to_string()), Some(&i));
+            assert_eq!(index.get_vector(i), node.point.vector.as_slice());
+            assert!(node.layers.iter().flatten().all(|&l| l < index.nodes.len()));
+        }
+    }
+
+    #[test]
+    fn test_rejects_dimension_mismatch() {
+        let mut index = HnswIndex::new(16, 3);
+        let point = |id: u64, dim: usize| Point::new(crate::PointId::Integer(id), Vector::new(vec![1.0; dim]), None);
+        index.insert(point(1, 3)).unwrap();
+        index.insert(point(2, 3)).unwrap();
+
+        assert!(matches!(
+            index.insert(point(3, 4)),
+            Err(Error::InvalidDimension { expected: 3, actual: 4 })
+        ));
+        assert!(index.insert_batch(vec![point(4, 3), point(5, 2)]).is_err());
+        assert_eq!(index.len(), 2);
+        assert_consistent(&index);
+        assert!(index.search(&Vector::new(vec![1.0; 4]), 1, None).is_empty());
+
+        // An emptied index accepts a new dimension
+        index.remove_batch(&["1", "2"]);
+        index.insert(point(6, 5)).unwrap();
+        assert_eq!(index.dim(), 5);
+        assert_consistent(&index);
+    }
+
+    #[test]
+    fn test_random_operations_stay_consistent() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut index = HnswIndex::new(4, 3);
+            let dim = rng.random_range(1..8);
+            let mut next_id = 0u64;
+
+            for _ in 0..100 {
+                match rng.random_range(0..4) {
+                    0 | 1 => {
+                        let vector: Vec<f32> = (0..dim).map(|_| rng.random_range(-1.0..1.0)).collect();
+                        index.insert(Point::new(crate::PointId::Integer(next_id), Vector::new(vector), None)).unwrap();
+                        next_id += 1;
+                    }
+                    2 => {
+                        let batch: Vec<Point> = (0..rng.random_range(1..10))
+                            .map(|_| {
+                                let vector: Vec<f32> = (0..dim).map(|_| rng.random_range(-1.0..1.0)).collect();
+                                next_id += 1;
+                                Point::new(crate::PointId::Integer(next_id - 1), Vector::new(vector), None)
+                            })
+                            .collect();
+                        index.insert_batch(batch).unwrap();
+                    }
+                    _ => {
+                        let ids: Vec<String> = (0..rng.random_range(1..5))
+                            .map(|_| rng.random_range(0..next_id.max(1)).to_string())
+                            .collect();
+                        let id_refs: Vec<&str> = ids.iter().map(String::as_str).collect();
+                        index.remove_batch(&id_refs);
+                    }
+                }
+                // Wrong-dimension inserts never change the index
+                let bad = Point::new(crate::PointId::Integer(u64::MAX), Vector::new(vec![0.5; dim + 1]), None);
+                if !index.is_empty() {
+                    assert!(index.insert(bad).is_err());
+                }
+                assert_consistent(&index);
+            }
+        }
+    }
+
+    #[test]
+    fn test_graph_stats_and_export() {
+        let mut index = HnswIndex::new(4, 3);
+        assert!(index.graph_stats().layers.is_empty());
+        for i in 0..50 {
+            let angle = i as f32 / 10.0;
+            let point = Point::new(crate::PointId::Integer(i), Vector::new(vec![angle.cos(), angle.sin(), 1.0]), None);
+            index.insert(point).unwrap();
+        }
+
+        let stats = index.graph_stats();
+        assert_eq!(stats.nodes, 50);
+        assert_eq!(stats.entry_point.as_deref(), Some("0"));
+        let nodes = index.export_graph();
+        assert_eq!(nodes.len(), 50);
+        for layer in &stats.layers {
+            let on_layer: Vec<&GraphNode> = nodes.iter().filter(|node| node.level >= layer.layer).collect();
+            let edges: usize = on_layer.iter().map(|node| node.layers[layer.layer].len()).sum();
+            assert_eq!(layer.nodes, on_layer.len());
+            assert_eq!(layer.edges, edges);
+            assert!(layer.max_degree <= stats.max_links);
+            assert!(layer.connected_components >= 1 && layer.connected_components <= layer.nodes);
+            // Every isolated node is a component of its own
+            assert!(layer.isolated_nodes <= layer.connected_components);
+        }
+
+        // Removed nodes leave no links behind
+        let removed = index.remove_batch(&["1", "2", "3"]);
+        assert_eq!(removed, 3);
+        let nodes = index.export_graph();
+        assert!(nodes.iter().all(|node| !["1", "2", "3"].contains(&node.id.as_str())));
+        assert!(nodes.iter().flat_map(|node| node.layers.iter().flatten()).all(|link| !["1", "2", "3"].contains(&link.as_str())));
+    }
+
     #[test]
     fn test_visited_set() {
         let mut vs = VisitedSet::new(100);
@@ -0,0 +1,76 @@
+//! Weighted fusion of dense and BM25 results
+//!
+//! Dense similarities and BM25 scores live on unrelated scales, so each
+//! result list is min-max normalized to `[0, 1]` before they are combined:
+//!
+//! ```text
+//! score = alpha * dense + (1 - alpha) * text
+//! ```
+//!
+//! A point missing from one list contributes 0 for that side. `alpha = 1`
+//! ranks by vector similarity alone, `alpha = 0` by BM25 alone.
+
+use crate::Point;
+use std::collections::HashMap;
+
+/// Candidates fetched from each side per requested result
+pub(crate) const CANDIDATE_FACTOR: usize = 4;
+
+/// Combine two scored result lists, best first, keeping `limit`
+pub(crate) fn fuse(
+    dense: Vec<(Point, f32)>,
+    text: Vec<(Point, f32)>,
+    alpha: f32,
+    limit: usize,
+) -> Vec<(Point, f32)> {
+    let mut fused: HashMap<String, (Point, f32)> = HashMap::new();
+    for (results, weight) in [(dense, alpha), (text, 1.0 - alpha)] {
+        for (point, score) in normalize(results) {
+            fused.entry(point.id.to_string())
+                .or_insert((point, 0.0))
+                .1 += weight * score;
+        }
+    }
+    let mut fused: Vec<(Point, f32)> = fused.into_values().collect();
+    fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    fused.truncate(limit);
+    fused
+}
+
+/// Rescale scores to `[0, 1]`; a list of equal scores maps to 1
+fn normalize(mut results: Vec<(Point, f32)>) -> Vec<(Point, f32)> {
+    let (min, max) = results.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), (_, s)| {
+        (lo.min(*s), hi.max(*s))
+    });
+    let range = max - min;
+    for (_, score) in &mut results {
+        *score = if range > 0.0 { (*score - min) / range } else { 1.0 };
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PointId, Vector};
+
+    fn scored(id: u64, score: f32) -> (Point, f32) {
+        (Point::new(PointId::Integer(id), Vector::new(vec![0.0]), None), score)
+    }
+
+    #[test]
+    fn test_weighted_fusion() {
+        let dense = vec![scored(1, 0.9), scored(2, 0.5), scored(3, 0.1)];
+        let text = vec![scored(3, 12.0), scored(4, 2.0)];
+
+        let ids = |results: Vec<(Point, f32)>| results.iter().map(|(p, _)| p.id.to_string()).collect::<Vec<_>>();
+        assert_eq!(ids(fuse(dense.clone(), text.clone(), 1.0, 2)), vec!["1", "2"]);
+        assert_eq!(ids(fuse(dense.clone(), text.clone(), 0.0, 1)), vec!["3"]);
+
+        // 3 is last on the dense side but first on the text side
+        let both = fuse(dense, text, 0.6, 4);
+        assert_eq!(ids(both.clone()), vec!["1", "3", "2", "4"]);
+        assert_eq!(both[0].1, 0.6);
+        assert_eq!(both[3].1, 0.0);
+    }
+}
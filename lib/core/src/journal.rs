@@ -0,0 +1,263 @@
+//! Per-collection operation journal
+//!
+//! Every mutating collection operation is recorded with a monotonically
+//! increasing operation id, the affected point ids and a timestamp. Recent
+//! entries are kept in memory (bounded by [`JournalRetention`]) so they can be
+//! read back with [`OperationJournal::since`] for auditing, debugging and
+//! change-data-capture. A [`JournalSink`] can be attached to persist entries,
+//! and subscribers registered with [`OperationJournal::subscribe`] receive
+//! every new entry as it is recorded (change streams).
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Kind of write operation recorded in the journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationType {
+    Upsert,
+    Delete,
+    SetPayload,
+    OverwritePayload,
+    DeletePayload,
+    ClearPayload,
+    UpdateVectors,
+    DeleteVectors,
+    CreateIndex,
+    DeleteIndex,
+    /// Several operations applied atomically
+    Transaction,
+}
+
+/// A single journal record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub op_id: u64,
+    pub op_type: OperationType,
+    #[serde(default)]
+    pub point_ids: Vec<String>,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// How many journal entries are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JournalRetention {
+    /// Maximum number of entries kept per collection
+    pub max_entries: usize,
+    /// Drop entries older than this many seconds (None keeps them until `max_entries`)
+    pub max_age_secs: Option<u64>,
+}
+
+impl Default for JournalRetention {
+    fn default() -> Self {
+        Self {
+            max_entries: 10_000,
+            max_age_secs: None,
+        }
+    }
+}
+
+/// Receives every recorded entry, e.g. to append it to a file
+pub trait JournalSink: Send + Sync {
+    fn append(&self, entry: &JournalEntry);
+}
+
+/// Change stream callback; returning `false` unsubscribes it
+pub type JournalSubscriber = Box<dyn Fn(&JournalEntry) -> bool + Send + Sync>;
+
+/// Bounded in-memory log of recent operations
+pub struct OperationJournal {
+    entries: RwLock<VecDeque<JournalEntry>>,
+    retention: RwLock<JournalRetention>,
+    sink: RwLock<Option<Arc<dyn JournalSink>>>,
+    subscribers: RwLock<Vec<(u64, JournalSubscriber)>>,
+    next_subscriber_id: AtomicU64,
+}
+
+impl Default for OperationJournal {
+    fn default() -> Self {
+        Self::new(JournalRetention::default())
+    }
+}
+
+impl OperationJournal {
+    pub fn new(retention: JournalRetention) -> Self {
+        Self {
+            entries: RwLock::new(VecDeque::new()),
+            retention: RwLock::new(retention),
+            sink: RwLock::new(None),
+            subscribers: RwLock::new(Vec::new()),
+            next_subscriber_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Record an operation
+    pub fn record(&self, op_id: u64, op_type: OperationType, point_ids: Vec<String>) {
+        let entry = JournalEntry {
+            op_id,
+            op_type,
+            point_ids,
+            timestamp: now_millis(),
+        };
+
+        if let Some(sink) = self.sink.read().as_ref() {
+            sink.append(&entry);
+        }
+
+        // Retain before notifying, so a subscriber that replays with `since`
+        // right after subscribing cannot miss this entry
+        {
+            let mut entries = self.entries.write();
+            entries.push_back(entry.clone());
+            self.apply_retention(&mut entries);
+        }
+
+        let subscribers = self.subscribers.read();
+        if subscribers.is_empty() {
+            return;
+        }
+        let dropped: Vec<u64> = subscribers
+            .iter()
+            .filter(|(_, subscriber)| !subscriber(&entry))
+            .map(|(id, _)| *id)
+            .collect();
+        drop(subscribers);
+        if !dropped.is_empty() {
+            self.subscribers.write().retain(|(id, _)| !dropped.contains(id));
+        }
+    }
+
+    /// Register a callback invoked for every newly recorded entry.
+    ///
+    /// The callback runs on the writer's thread, so it should only hand the
+    /// entry off (e.g. to a channel). Returns an id for [`Self::unsubscribe`].
+    pub fn subscribe(&self, subscriber: JournalSubscriber) -> u64 {
+        let id = self.next_subscriber_id.fetch_add(1, Ordering::Relaxed);
+        self.subscribers.write().push((id, subscriber));
+        id
+    }
+
+    pub fn unsubscribe(&self, id: u64) {
+        self.subscribers.write().retain(|(sub_id, _)| *sub_id != id);
+    }
+
+    /// Number of active change stream subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.read().len()
+    }
+
+    /// Entries with `op_id >= since` (all retained entries when `since` is None),
+    /// oldest first, at most `limit`
+    pub fn since(&self, since: Option<u64>, limit: usize) -> Vec<JournalEntry> {
+        let mut entries = self.entries.write();
+        self.apply_retention(&mut entries);
+
+        let start = match since {
+            Some(op_id) => entries.partition_point(|e| e.op_id < op_id),
+            None => 0,
+        };
+        entries.iter().skip(start).take(limit).cloned().collect()
+    }
+
+    /// Replace the retained entries, e.g. when loading a persisted journal
+    pub fn restore(&self, restored: Vec<JournalEntry>) {
+        let mut entries = self.entries.write();
+        *entries = restored.into();
+        self.apply_retention(&mut entries);
+    }
+
+    /// Id of the newest retained entry
+    pub fn last_op_id(&self) -> Option<u64> {
+        self.entries.read().back().map(|e| e.op_id)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().is_empty()
+    }
+
+    pub fn retention(&self) -> JournalRetention {
+        *self.retention.read()
+    }
+
+    pub fn set_retention(&self, retention: JournalRetention) {
+        *self.retention.write() = retention;
+        let mut entries = self.entries.write();
+        self.apply_retention(&mut entries);
+    }
+
+    pub fn set_sink(&self, sink: Option<Arc<dyn JournalSink>>) {
+        *self.sink.write() = sink;
+    }
+
+    fn apply_retention(&self, entries: &mut VecDeque<JournalEntry>) {
+        let retention = *self.retention.read();
+
+        while entries.len() > retention.max_entries {
+            entries.pop_front();
+        }
+
+        if let Some(max_age) = retention.max_age_secs {
+            let cutoff = now_millis().saturating_sub(max_age.saturating_mul(1000));
+            while entries.front().is_some_and(|e| e.timestamp < cutoff) {
+                entries.pop_front();
+            }
+        }
+    }
+}
+
+pub(crate) fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_since_and_retention() {
+        let journal = OperationJournal::new(JournalRetention { max_entries: 3, max_age_secs: None });
+        for op_id in 0..5 {
+            journal.record(op_id, OperationType::Upsert, vec![op_id.to_string()]);
+        }
+
+        let all = journal.since(None, usize::MAX);
+        assert_eq!(all.iter().map(|e| e.op_id).collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(journal.since(Some(4), 10).len(), 1);
+        assert_eq!(journal.since(Some(3), 1)[0].op_id, 3);
+    }
+
+    #[test]
+    fn test_subscribers() {
+        let journal = OperationJournal::default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let sink = seen.clone();
+        journal.subscribe(Box::new(move |entry| {
+            sink.lock().unwrap().push(entry.op_id);
+            entry.op_id < 1
+        }));
+        assert_eq!(journal.subscriber_count(), 1);
+
+        journal.record(0, OperationType::Delete, vec!["a".to_string()]);
+        journal.record(1, OperationType::Delete, vec!["b".to_string()]);
+        // Returned false on op 1, so it was dropped
+        journal.record(2, OperationType::Delete, vec!["c".to_string()]);
+
+        assert_eq!(*seen.lock().unwrap(), vec![0, 1]);
+        assert_eq!(journal.subscriber_count(), 0);
+    }
+}
@@ -0,0 +1,71 @@
+//! Keyword comparison options
+//!
+//! A keyword payload index compares values byte for byte unless its
+//! [`KeywordParams`] say otherwise. With `normalize` both sides are brought
+//! to Unicode NFKC, so a precomposed `é` equals `e` followed by a combining
+//! accent and full-width letters equal ASCII ones. With `lowercase` case is
+//! folded, so `Salumi` matches `salumi`. The options apply to the values of
+//! stored points as they are indexed or filtered, and to the values queried.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use unicode_normalization::UnicodeNormalization;
+
+/// How the values of a keyword-indexed field are compared
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct KeywordParams {
+    /// Ignore case
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lowercase: bool,
+    /// Compare values in Unicode normalization form KC
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub normalize: bool,
+}
+
+impl KeywordParams {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `value` in the form it is compared in
+    pub fn apply<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let mut value = Cow::Borrowed(value);
+        if self.normalize {
+            value = Cow::Owned(value.nfkc().collect());
+        }
+        if self.lowercase && value.chars().any(char::is_uppercase) {
+            value = Cow::Owned(value.to_lowercase());
+        }
+        value
+    }
+
+    /// Whether two values are the same keyword
+    pub fn equals(&self, a: &str, b: &str) -> bool {
+        self.apply(a) == self.apply(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let exact = KeywordParams::default();
+        assert!(matches!(exact.apply("Salumi"), Cow::Borrowed("Salumi")));
+        assert!(!exact.equals("Salumi", "salumi"));
+
+        let lowercase = KeywordParams { lowercase: true, normalize: false };
+        assert!(lowercase.equals("Salumi", "SALUMI"));
+        assert!(!lowercase.equals("caf\u{e9}", "cafe\u{301}"));
+
+        let normalize = KeywordParams { lowercase: false, normalize: true };
+        assert!(normalize.equals("caf\u{e9}", "cafe\u{301}"));
+        assert!(normalize.equals("\u{ff33}alumi", "Salumi"));
+        assert!(!normalize.equals("Salumi", "salumi"));
+
+        let both = KeywordParams { lowercase: true, normalize: true };
+        assert!(both.equals("\u{ff33}ALUMI", "salumi"));
+    }
+}
@@ -0,0 +1,78 @@
+//! Language detection and per-language stemming for BM25
+//!
+//! With detection enabled on a collection
+//! ([`Collection::set_detect_language`](crate::Collection::set_detect_language)),
+//! the language of each upserted point's `text` is detected and stored in
+//! its payload under [`LANGUAGE_FIELD`] as an ISO 639-3 code (`"eng"`,
+//! `"deu"`, ...), unless the payload already names one. The text is then
+//! indexed with that language's stemmer, so "running" and "runs" both match
+//! "run". Languages without a stemmer are indexed unstemmed.
+
+use rust_stemmers::{Algorithm, Stemmer};
+use whatlang::Lang;
+
+/// Payload field holding the language of the `text` field
+pub const LANGUAGE_FIELD: &str = "language";
+
+/// ISO 639-3 code of the language of `text`, if it can be told reliably
+pub fn detect(text: &str) -> Option<&'static str> {
+    whatlang::detect(text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang().code())
+}
+
+/// Stemmer for an ISO 639-3 language code
+pub fn stemmer(code: &str) -> Option<Stemmer> {
+    let algorithm = match Lang::from_code(code)? {
+        Lang::Ara => Algorithm::Arabic,
+        Lang::Dan => Algorithm::Danish,
+        Lang::Nld => Algorithm::Dutch,
+        Lang::Eng => Algorithm::English,
+        Lang::Fin => Algorithm::Finnish,
+        Lang::Fra => Algorithm::French,
+        Lang::Deu => Algorithm::German,
+        Lang::Ell => Algorithm::Greek,
+        Lang::Hun => Algorithm::Hungarian,
+        Lang::Ita => Algorithm::Italian,
+        Lang::Nob => Algorithm::Norwegian,
+        Lang::Por => Algorithm::Portuguese,
+        Lang::Ron => Algorithm::Romanian,
+        Lang::Rus => Algorithm::Russian,
+        Lang::Spa => Algorithm::Spanish,
+        Lang::Swe => Algorithm::Swedish,
+        Lang::Tam => Algorithm::Tamil,
+        Lang::Tur => Algorithm::Turkish,
+        _ => return None,
+    };
+    Some(Stemmer::create(algorithm))
+}
+
+/// Stem lowercase tokens in place; tokens of languages without a stemmer
+/// are left as they are
+pub fn stem_all<'a>(code: &str, tokens: impl IntoIterator<Item = &'a mut String>) {
+    if let Some(stemmer) = stemmer(code) {
+        for token in tokens {
+            let stemmed = stemmer.stem(token).into_owned();
+            *token = stemmed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_stem() {
+        assert_eq!(detect("Comfortable running shoes for long distance runners on the road"), Some("eng"));
+        assert_eq!(detect("Bequeme Laufschuhe für lange Strecken auf der Straße und im Wald"), Some("deu"));
+        assert_eq!(detect("ok"), None);
+
+        let mut tokens = vec!["running".to_string(), "dogs".to_string()];
+        stem_all("eng", tokens.iter_mut());
+        assert_eq!(tokens, vec!["run", "dog"]);
+        // No stemmer for Esperanto: unchanged
+        stem_all("epo", tokens.iter_mut());
+        assert_eq!(tokens, vec!["run", "dog"]);
+    }
+}
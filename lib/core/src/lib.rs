@@ -45,6 +45,39 @@ pub mod bm25;
 pub mod filter;
 pub mod background;
 pub mod multivector;
+pub mod journal;
+pub mod matrix;
+pub mod clustering;
+pub mod dedup;
+pub mod stats;
+pub mod sparse;
+pub mod store;
+pub mod transaction;
+pub mod formula;
+pub mod expression;
+pub mod hybrid;
+pub mod analyzer;
+pub mod highlight;
+pub mod language;
+pub mod trigram;
+pub mod keyword;
+pub mod cancel;
+pub mod payload_stats;
+pub mod aggregation;
+pub mod search_defaults;
+pub mod recency;
+pub mod templates;
+pub mod ef_tuning;
+pub mod consistency;
+pub mod history;
+pub mod points_iter;
+pub mod binary;
+pub mod rebuild;
+pub mod fresh;
+pub mod geo;
+
+/// Quantized read replica for batched scoring, on GPU with the `cuda` feature
+pub mod gpu;
 
 /// SIMD-optimized vector operations
 ///
@@ -54,13 +87,42 @@ pub mod multivector;
 /// - NEON on ARM64/Apple Silicon
 pub mod simd;
 
-pub use collection::{Collection, CollectionConfig, Distance, PayloadIndexType};
+pub use background::{CpuBudget, JobContext, JobPriority, JobProgress, JobSnapshot, JobState};
+pub use collection::{Collection, CollectionConfig, Distance, NormalizationPolicy, PayloadIndexType, UpsertStatus, VectorUsing};
 pub use vector::Vector;
 pub use error::{Error, Result};
 pub use point::{Point, PointId, VectorData, SparseVector};
-pub use hnsw::HnswIndex;
+pub use hnsw::{GraphNode, GraphStats, HnswIndex, HnswParams, HnswParamsDiff, LayerStats};
 pub use graph::{Node, Edge, NodeId, EdgeId};
 pub use bm25::BM25Index;
 pub use filter::{Filter, PayloadFilter, FilterCondition};
-pub use multivector::{MultiVector, MultiVectorConfig, MultiVectorComparator};
+pub use multivector::{MultiVector, MultiVectorConfig, MultiVectorComparator, MultiVectorParams};
+pub use gpu::GpuReplica;
+pub use stats::{CollectionStats, MemoryStats, ReindexProgress};
+pub use dedup::{DuplicateGroup, DuplicatePolicy};
+pub use clustering::{KMeansConfig, KMeansResult};
+pub use matrix::{DistanceMatrix, MatrixPair};
+pub use sparse::{SparseDocFrequencies, SparseIndexParams, SparseModifier, SparseVectorConfig};
+pub use store::PointStore;
+pub use transaction::BatchOperation;
+pub use formula::VectorFormula;
+pub use expression::{ComputedFields, Expression};
+pub use analyzer::TextDictionaries;
+pub use highlight::{Highlight, HighlightOptions};
+pub use trigram::TrigramIndex;
+pub use keyword::KeywordParams;
+pub use cancel::CancellationToken;
+pub use payload_stats::{PayloadFieldStats, ValueCount};
+pub use aggregation::{AggregationGroup, AggregationRequest, AggregationResult, HistogramBucket, Metric, MetricValue};
+pub use search_defaults::SearchDefaults;
+pub use recency::{RecencyBoost, RecencyOverride};
+pub use templates::QueryTemplate;
+pub use ef_tuning::{EfTuning, EfTuningReport};
+pub use consistency::{ConsistencyReport, IndexViolation, PathComparison, ScoredId, SearchPath};
+pub use history::{QueryLatencies, StatsHistory, StatsSample, DEFAULT_HISTORY_LEN};
+pub use points_iter::{PointsIter, Projection};
+pub use rebuild::RebuildBacklog;
+pub use fresh::FreshPoints;
+pub use geo::{GeoOrigin, GeoPoint};
+pub use journal::{OperationJournal, JournalEntry, JournalRetention, JournalSink, JournalSubscriber, OperationType};
 
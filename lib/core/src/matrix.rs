@@ -0,0 +1,149 @@
+//! Sparse pairwise similarity matrix over a sample of points
+//!
+//! [`Collection::distance_matrix`](crate::Collection::distance_matrix) samples
+//! points and keeps, for each one, its `limit` most similar neighbours within
+//! the sample. The result can be read as a list of scored pairs or in a
+//! compressed (COO) offsets form, matching Qdrant's
+//! `/points/search/matrix/{pairs,offsets}` responses.
+
+use crate::{Distance, Point, PointId};
+use rand::seq::SliceRandom;
+
+/// Sparse similarity matrix: row `i` holds the nearest neighbours of `ids[i]`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DistanceMatrix {
+    pub ids: Vec<PointId>,
+    /// Per row: (column index into `ids`, score), best first
+    pub rows: Vec<Vec<(usize, f32)>>,
+}
+
+/// One scored pair of the matrix
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatrixPair {
+    pub a: PointId,
+    pub b: PointId,
+    pub score: f32,
+}
+
+impl DistanceMatrix {
+    /// Build the matrix for `points` (already sampled), keeping `limit`
+    /// neighbours per point
+    pub fn compute(points: &[Point], limit: usize, distance: Distance) -> Self {
+        let rows = points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let mut scored: Vec<(usize, f32)> = points
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(j, other)| (j, score(point, other, distance)))
+                    .collect();
+                scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+                scored.truncate(limit);
+                scored
+            })
+            .collect();
+
+        Self {
+            ids: points.iter().map(|p| p.id.clone()).collect(),
+            rows,
+        }
+    }
+
+    /// Every (row, neighbour) entry as a scored pair
+    pub fn pairs(&self) -> Vec<MatrixPair> {
+        self.rows
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| {
+                row.iter().map(move |&(j, score)| MatrixPair {
+                    a: self.ids[i].clone(),
+                    b: self.ids[j].clone(),
+                    score,
+                })
+            })
+            .collect()
+    }
+
+    /// Coordinate form: parallel `(offsets_row, offsets_col, scores)` arrays
+    /// indexing into `ids`
+    pub fn offsets(&self) -> (Vec<u64>, Vec<u64>, Vec<f32>) {
+        let mut offsets_row = Vec::new();
+        let mut offsets_col = Vec::new();
+        let mut scores = Vec::new();
+        for (i, row) in self.rows.iter().enumerate() {
+            for &(j, score) in row {
+                offsets_row.push(i as u64);
+                offsets_col.push(j as u64);
+                scores.push(score);
+            }
+        }
+        (offsets_row, offsets_col, scores)
+    }
+}
+
+/// Pick up to `sample` points uniformly at random
+pub(crate) fn sample_points(mut points: Vec<Point>, sample: usize) -> Vec<Point> {
+    if points.len() > sample {
+        let (chosen, _) = points.partial_shuffle(&mut rand::rng(), sample);
+        chosen.to_vec()
+    } else {
+        points
+    }
+}
+
+/// Same scoring as search: stored cosine vectors are already normalized
+fn score(a: &Point, b: &Point, distance: Distance) -> f32 {
+    match distance {
+        Distance::Cosine | Distance::Dot => {
+            crate::simd::dot_product_simd(a.vector.as_slice(), b.vector.as_slice())
+        }
+        Distance::Euclidean => -crate::simd::l2_distance_simd(a.vector.as_slice(), b.vector.as_slice()),
+        Distance::Hamming => crate::binary::hamming_score(a.vector.as_slice(), b.vector.as_slice()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector;
+
+    fn point(id: u64, v: Vec<f32>) -> Point {
+        let mut vector = Vector::new(v);
+        vector.normalize();
+        Point::new(PointId::Integer(id), vector, None)
+    }
+
+    #[test]
+    fn test_nearest_neighbours_per_row() {
+        let points = vec![
+            point(1, vec![1.0, 0.0]),
+            point(2, vec![0.9, 0.1]),
+            point(3, vec![0.0, 1.0]),
+        ];
+        let matrix = DistanceMatrix::compute(&points, 1, Distance::Cosine);
+
+        assert_eq!(matrix.ids.len(), 3);
+        assert_eq!(matrix.rows[0][0].0, 1);
+        assert_eq!(matrix.rows[1][0].0, 0);
+        assert_eq!(matrix.rows[2][0].0, 1);
+
+        let pairs = matrix.pairs();
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(pairs[0].a, PointId::Integer(1));
+        assert_eq!(pairs[0].b, PointId::Integer(2));
+
+        let (rows, cols, scores) = matrix.offsets();
+        assert_eq!(rows, vec![0, 1, 2]);
+        assert_eq!(cols, vec![1, 0, 1]);
+        assert_eq!(scores.len(), 3);
+    }
+
+    #[test]
+    fn test_sample_size() {
+        let points: Vec<Point> = (0..20).map(|i| point(i, vec![i as f32, 1.0])).collect();
+        assert_eq!(sample_points(points.clone(), 5).len(), 5);
+        assert_eq!(sample_points(points, 50).len(), 20);
+    }
+}
@@ -4,7 +4,8 @@
 //! <https://arxiv.org/pdf/2112.01488.pdf>
 
 use serde::{Deserialize, Serialize};
-use crate::Vector;
+use crate::simd::{dot_products_simd, l2_distance_simd, norm_simd, norm_squared_simd};
+use crate::{Distance, Vector};
 
 /// Configuration for multivector comparison
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -12,15 +13,27 @@ pub enum MultiVectorComparator {
     /// MaxSim: For each query vector, find max similarity with any document vector, then sum
     /// This is the ColBERT algorithm for late interaction retrieval
     #[default]
+    #[serde(rename = "max_sim", alias = "MaxSim")]
     MaxSim,
 }
 
 /// Configuration for multivector storage and search
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MultiVectorConfig {
     pub comparator: MultiVectorComparator,
 }
 
+/// A named multivector declared by a collection, with its own sub-vector
+/// size and distance independent of the collection's dense vectors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultiVectorParams {
+    /// Dimension of each sub-vector
+    pub size: usize,
+    pub distance: Distance,
+    #[serde(default)]
+    pub multivector_config: MultiVectorConfig,
+}
+
 /// A multivector - multiple dense vectors per point (ColBERT-style)
 /// Each sub-vector typically represents a token embedding
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -97,38 +110,43 @@ impl MultiVector {
         Vector::new(self.vectors[0].clone())
     }
     
+    /// Keep the `max_tokens` sub-vectors with the largest norms, in their
+    /// original order. Low-norm query tokens add little to MaxSim scores,
+    /// so pruning them trades a little accuracy for proportionally less
+    /// work per candidate.
+    pub fn prune_tokens(&self, max_tokens: usize) -> MultiVector {
+        if max_tokens == 0 || self.vectors.len() <= max_tokens {
+            return self.clone();
+        }
+        let norms: Vec<f32> = self.vectors.iter().map(|v| norm_squared_simd(v)).collect();
+        let mut order: Vec<usize> = (0..self.vectors.len()).collect();
+        order.sort_by(|&a, &b| norms[b].total_cmp(&norms[a]).then(a.cmp(&b)));
+        order.truncate(max_tokens);
+        order.sort_unstable();
+        let vectors = order.into_iter().map(|i| self.vectors[i].clone()).collect();
+        Self { vectors, dim: self.dim }
+    }
+
     /// Compute MaxSim score between two multivectors
     /// 
     /// For each sub-vector in `self` (query), find the maximum similarity 
     /// with any sub-vector in `other` (document), then sum all maximums.
     /// 
-    /// This is the ColBERT scoring algorithm.
+    /// This is the ColBERT scoring algorithm. Each query sub-vector is
+    /// scored against all document sub-vectors in one batched SIMD pass.
     pub fn max_sim(&self, other: &MultiVector) -> f32 {
         if self.dim != other.dim {
             return 0.0;
         }
         
-        let mut total_score = 0.0;
-        
-        // For each query sub-vector
-        for query_vec in &self.vectors {
-            let mut max_sim = f32::NEG_INFINITY;
-            
-            // Find max similarity with any document sub-vector
-            for doc_vec in &other.vectors {
-                let sim = dot_product(query_vec, doc_vec);
-                if sim > max_sim {
-                    max_sim = sim;
-                }
-            }
-            
-            // Only add if we found a valid similarity
-            if max_sim > f32::NEG_INFINITY {
-                total_score += max_sim;
-            }
-        }
-        
-        total_score
+        let mut dots = vec![0.0; other.vectors.len()];
+        self.vectors
+            .iter()
+            .filter_map(|query_vec| {
+                dot_products_simd(query_vec, &other.vectors, &mut dots);
+                dots.iter().copied().reduce(f32::max)
+            })
+            .sum()
     }
     
     /// Compute MaxSim with cosine similarity (normalized dot product)
@@ -137,29 +155,25 @@ impl MultiVector {
             return 0.0;
         }
         
+        // Document norms are the same for every query sub-vector
+        let doc_norms: Vec<f32> = other.vectors.iter().map(|v| norm_simd(v)).collect();
+        let mut dots = vec![0.0; other.vectors.len()];
         let mut total_score = 0.0;
         
         for query_vec in &self.vectors {
-            let query_norm = norm(query_vec);
+            let query_norm = norm_simd(query_vec);
             if query_norm < f32::EPSILON {
                 continue;
             }
             
-            let mut max_sim = f32::NEG_INFINITY;
-            
-            for doc_vec in &other.vectors {
-                let doc_norm = norm(doc_vec);
-                if doc_norm < f32::EPSILON {
-                    continue;
-                }
-                
-                let sim = dot_product(query_vec, doc_vec) / (query_norm * doc_norm);
-                if sim > max_sim {
-                    max_sim = sim;
-                }
-            }
+            dot_products_simd(query_vec, &other.vectors, &mut dots);
+            let max_sim = dots.iter()
+                .zip(&doc_norms)
+                .filter(|(_, &doc_norm)| doc_norm >= f32::EPSILON)
+                .map(|(&dot, &doc_norm)| dot / (query_norm * doc_norm))
+                .reduce(f32::max);
             
-            if max_sim > f32::NEG_INFINITY {
+            if let Some(max_sim) = max_sim {
                 total_score += max_sim;
             }
         }
@@ -176,16 +190,11 @@ impl MultiVector {
         let mut total_score = 0.0;
         
         for query_vec in &self.vectors {
-            let mut min_dist = f32::INFINITY;
-            
-            for doc_vec in &other.vectors {
-                let dist = l2_distance(query_vec, doc_vec);
-                if dist < min_dist {
-                    min_dist = dist;
-                }
-            }
+            let min_dist = other.vectors.iter()
+                .map(|doc_vec| l2_distance_simd(query_vec, doc_vec))
+                .reduce(f32::min);
             
-            if min_dist < f32::INFINITY {
+            if let Some(min_dist) = min_dist {
                 // Negative because we want higher scores for closer vectors
                 total_score -= min_dist;
             }
@@ -193,28 +202,30 @@ impl MultiVector {
         
         total_score
     }
-}
 
-/// Simple dot product (can be replaced with SIMD version)
-#[inline]
-fn dot_product(a: &[f32], b: &[f32]) -> f32 {
-    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
-}
+    /// MaxSim under `distance`
+    pub fn max_sim_by(&self, other: &MultiVector, distance: Distance) -> f32 {
+        match distance {
+            Distance::Cosine => self.max_sim_cosine(other),
+            Distance::Euclidean => self.max_sim_l2(other),
+            Distance::Dot => self.max_sim(other),
+            Distance::Hamming => self.max_sim_hamming(other),
+        }
+    }
 
-/// Vector norm
-#[inline]
-fn norm(v: &[f32]) -> f32 {
-    v.iter().map(|x| x * x).sum::<f32>().sqrt()
-}
+    /// Compute MaxSim with the negated Hamming distance of bit-packed vectors
+    pub fn max_sim_hamming(&self, other: &MultiVector) -> f32 {
+        if self.dim != other.dim {
+            return f32::NEG_INFINITY;
+        }
 
-/// L2 distance
-#[inline]
-fn l2_distance(a: &[f32], b: &[f32]) -> f32 {
-    a.iter()
-        .zip(b.iter())
-        .map(|(x, y)| (x - y) * (x - y))
-        .sum::<f32>()
-        .sqrt()
+        self.vectors
+            .iter()
+            .filter_map(|query_vec| {
+                other.vectors.iter().map(|doc_vec| crate::binary::hamming_score(query_vec, doc_vec)).reduce(f32::max)
+            })
+            .fold(0.0, |total, best| total + best)
+    }
 }
 
 #[cfg(test)]
@@ -280,4 +291,41 @@ mod tests {
         let score = query.max_sim_cosine(&doc);
         assert!((score - 1.0).abs() < 1e-6);
     }
+    
+    #[test]
+    fn test_max_sim_matches_scalar() {
+        // Odd sizes exercise the row remainder and the per-row tail
+        let rows = |n: usize, seed: usize| -> Vec<Vec<f32>> {
+            (0..n).map(|r| (0..37).map(|c| (((r * 31 + c * 17 + seed) % 23) as f32 - 11.0) / 7.0).collect()).collect()
+        };
+        let query = MultiVector::new(rows(5, 3)).unwrap();
+        let doc = MultiVector::new(rows(11, 8)).unwrap();
+        let dot = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+        let norm = |a: &[f32]| dot(a, a).sqrt();
+
+        let expected: f32 = query.vectors().iter()
+            .map(|q| doc.vectors().iter().map(|d| dot(q, d)).fold(f32::NEG_INFINITY, f32::max))
+            .sum();
+        assert!((query.max_sim(&doc) - expected).abs() < 1e-3);
+
+        let expected: f32 = query.vectors().iter()
+            .map(|q| doc.vectors().iter().map(|d| dot(q, d) / (norm(q) * norm(d))).fold(f32::NEG_INFINITY, f32::max))
+            .sum();
+        assert!((query.max_sim_cosine(&doc) - expected).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn test_prune_tokens() {
+        let query = MultiVector::new(vec![
+            vec![0.1, 0.0],
+            vec![3.0, 0.0],
+            vec![0.0, 0.2],
+            vec![0.0, 2.0],
+        ]).unwrap();
+        
+        // The two largest tokens survive, in their original order
+        let pruned = query.prune_tokens(2);
+        assert_eq!(pruned.vectors(), &[vec![3.0, 0.0], vec![0.0, 2.0]]);
+        assert_eq!(query.prune_tokens(10), query);
+    }
 }
@@ -0,0 +1,236 @@
+//! Statistics of the payload values of a collection, for schema discovery
+//!
+//! One pass over the points summarises every payload key: the JSON types of
+//! its values, how many distinct values it holds, the range of its numbers
+//! and its most frequent values. Nested objects are reported under dotted
+//! paths (`address.city`) and objects inside arrays under `[]` paths
+//! (`diet[].food`), matching filter keys; the elements of an array of
+//! scalars count as values of the array's key, as they do for indexes.
+//!
+//! Values are counted exactly up to [`MAX_TRACKED_VALUES`] distinct values
+//! per key. Beyond that the distinct count comes from a HyperLogLog sketch
+//! (about 1.6% error) and the top values from the values seen first.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::Point;
+
+/// Distinct values counted exactly per key
+pub const MAX_TRACKED_VALUES: usize = 10_000;
+
+/// log2 of the number of HyperLogLog registers
+const SKETCH_BITS: u32 = 12;
+const SKETCH_REGISTERS: usize = 1 << SKETCH_BITS;
+
+/// A payload value and the number of times it occurs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueCount {
+    pub value: Value,
+    pub count: usize,
+}
+
+/// Summary of the values stored under one payload key
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PayloadFieldStats {
+    /// Points with at least one value under the key
+    pub points: usize,
+    /// Number of values per JSON type: `string`, `integer`, `float`,
+    /// `bool` or `null`
+    pub types: BTreeMap<String, usize>,
+    /// Number of distinct values (estimated past [`MAX_TRACKED_VALUES`])
+    pub distinct_values: usize,
+    /// Whether `distinct_values` is an estimate
+    pub approximate: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Most frequent values, most frequent first
+    pub top_values: Vec<ValueCount>,
+}
+
+/// Statistics of every payload key, by key
+pub fn payload_stats<'a>(points: impl IntoIterator<Item = &'a Point>, top: usize) -> BTreeMap<String, PayloadFieldStats> {
+    let mut fields: HashMap<String, FieldAccumulator> = HashMap::new();
+    for point in points {
+        let Some(payload) = &point.payload else { continue };
+        let mut seen = HashSet::new();
+        visit(&mut fields, &mut seen, String::new(), payload);
+    }
+    fields.into_iter().map(|(key, field)| (key, field.finish(top))).collect()
+}
+
+/// Record the leaves of `value` under `path`
+fn visit(fields: &mut HashMap<String, FieldAccumulator>, seen: &mut HashSet<String>, path: String, value: &Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let child_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                visit(fields, seen, child_path, child);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                match item {
+                    Value::Object(_) => visit(fields, seen, format!("{}[]", path), item),
+                    _ => visit(fields, seen, path.clone(), item),
+                }
+            }
+        }
+        leaf => {
+            let field = fields.entry(path.clone()).or_insert_with(FieldAccumulator::new);
+            if seen.insert(path) {
+                field.points += 1;
+            }
+            field.add(leaf);
+        }
+    }
+}
+
+struct FieldAccumulator {
+    points: usize,
+    types: BTreeMap<String, usize>,
+    /// Counts by serialized value, up to MAX_TRACKED_VALUES entries
+    counts: HashMap<String, (Value, usize)>,
+    /// A value was not tracked because `counts` was full
+    overflowed: bool,
+    sketch: Vec<u8>,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl FieldAccumulator {
+    fn new() -> Self {
+        Self {
+            points: 0,
+            types: BTreeMap::new(),
+            counts: HashMap::new(),
+            overflowed: false,
+            sketch: vec![0; SKETCH_REGISTERS],
+            min: None,
+            max: None,
+        }
+    }
+
+    fn add(&mut self, value: &Value) {
+        *self.types.entry(type_name(value).to_string()).or_insert(0) += 1;
+        if let Some(n) = value.as_f64() {
+            self.min = Some(self.min.map_or(n, |m| m.min(n)));
+            self.max = Some(self.max.map_or(n, |m| m.max(n)));
+        }
+
+        let key = value.to_string();
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+        // The low bits pick a register, the rest give the rank
+        let register = (hash as usize) & (SKETCH_REGISTERS - 1);
+        let rank = ((hash >> SKETCH_BITS).trailing_zeros() + 1).min(64 - SKETCH_BITS + 1) as u8;
+        self.sketch[register] = self.sketch[register].max(rank);
+
+        let tracked = self.counts.len();
+        match self.counts.get_mut(&key) {
+            Some((_, count)) => *count += 1,
+            None if tracked < MAX_TRACKED_VALUES => {
+                self.counts.insert(key, (value.clone(), 1));
+            }
+            None => self.overflowed = true,
+        }
+    }
+
+    fn finish(self, top: usize) -> PayloadFieldStats {
+        let distinct_values = if self.overflowed { estimate(&self.sketch) } else { self.counts.len() };
+        let mut values: Vec<(String, Value, usize)> = self.counts.into_iter()
+            .map(|(key, (value, count))| (key, value, count))
+            .collect();
+        values.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        values.truncate(top);
+
+        PayloadFieldStats {
+            points: self.points,
+            types: self.types,
+            distinct_values,
+            approximate: self.overflowed,
+            min: self.min,
+            max: self.max,
+            top_values: values.into_iter().map(|(_, value, count)| ValueCount { value, count }).collect(),
+        }
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// HyperLogLog cardinality estimate, with linear counting for small sets
+fn estimate(sketch: &[u8]) -> usize {
+    let m = sketch.len() as f64;
+    let alpha = 0.7213 / (1.0 + 1.079 / m);
+    let sum: f64 = sketch.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+    let raw = alpha * m * m / sum;
+    let zeros = sketch.iter().filter(|&&r| r == 0).count();
+    let estimate = if raw <= 2.5 * m && zeros > 0 { m * (m / zeros as f64).ln() } else { raw };
+    estimate.round() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PointId, Vector};
+
+    fn point(id: u64, payload: Value) -> Point {
+        Point::new(PointId::Integer(id), Vector::new(vec![0.0]), Some(payload))
+    }
+
+    #[test]
+    fn test_payload_stats() {
+        let points = vec![
+            point(1, serde_json::json!({"city": "Rome", "price": 3, "tags": ["a", "b"], "diet": [{"food": "pasta"}]})),
+            point(2, serde_json::json!({"city": "Rome", "price": 4.5, "address": {"zip": "00100"}})),
+            point(3, serde_json::json!({"city": "Milan", "price": null, "tags": ["a"]})),
+        ];
+        let stats = payload_stats(&points, 1);
+
+        let city = &stats["city"];
+        assert_eq!(city.points, 3);
+        assert_eq!(city.distinct_values, 2);
+        assert_eq!(city.top_values, vec![ValueCount { value: serde_json::json!("Rome"), count: 2 }]);
+        assert!(city.min.is_none());
+
+        let price = &stats["price"];
+        assert_eq!(price.types.get("integer"), Some(&1));
+        assert_eq!(price.types.get("float"), Some(&1));
+        assert_eq!(price.types.get("null"), Some(&1));
+        assert_eq!((price.min, price.max), (Some(3.0), Some(4.5)));
+
+        // Array elements count as values of the key; the point once
+        assert_eq!(stats["tags"].points, 2);
+        assert_eq!(stats["tags"].types.get("string"), Some(&3));
+        assert_eq!(stats["diet[].food"].points, 1);
+        assert_eq!(stats["address.zip"].distinct_values, 1);
+    }
+
+    #[test]
+    fn test_distinct_estimate() {
+        let points: Vec<Point> = (0..(MAX_TRACKED_VALUES as u64 * 3))
+            .map(|i| point(i, serde_json::json!({"n": i})))
+            .collect();
+        let n = &payload_stats(&points, 3)["n"];
+        assert!(n.approximate);
+        let expected = points.len() as f64;
+        assert!((n.distinct_values as f64 - expected).abs() / expected < 0.05, "{}", n.distinct_values);
+        assert_eq!(n.top_values.len(), 3);
+    }
+}
@@ -18,6 +18,26 @@ impl SparseVector {
     pub fn new(indices: Vec<u32>, values: Vec<f32>) -> Self {
         Self { indices, values }
     }
+
+    /// Check that indices and values pair up, indices are unique and values
+    /// are finite
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.indices.len() != self.values.len() {
+            return Err(crate::Error::InvalidSparseVector(format!(
+                "{} indices but {} values",
+                self.indices.len(),
+                self.values.len()
+            )));
+        }
+        let mut seen = std::collections::HashSet::with_capacity(self.indices.len());
+        if let Some(idx) = self.indices.iter().find(|&&idx| !seen.insert(idx)) {
+            return Err(crate::Error::InvalidSparseVector(format!("duplicate index {}", idx)));
+        }
+        if self.values.iter().any(|v| !v.is_finite()) {
+            return Err(crate::Error::InvalidSparseVector("values must be finite".to_string()));
+        }
+        Ok(())
+    }
     
     /// Compute dot product with another sparse vector
     pub fn dot(&self, other: &SparseVector) -> f32 {
@@ -112,6 +132,9 @@ pub struct Point {
     /// Optional multivector data for ColBERT-style search
     #[serde(skip_serializing_if = "Option::is_none")]
     pub multivector: Option<MultiVector>,
+    /// Named multivectors declared by the collection (e.g., {"colbert": MultiVector})
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub named_multivectors: HashMap<String, MultiVector>,
     /// Named sparse vectors (e.g., {"keywords": SparseVector})
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub sparse_vectors: HashMap<String, SparseVector>,
@@ -164,6 +187,7 @@ impl Point {
             version: 0,
             vector,
             multivector: None,
+            named_multivectors: HashMap::new(),
             sparse_vectors: HashMap::new(),
             payload,
         }
@@ -180,6 +204,7 @@ impl Point {
             version: 0,
             vector,
             multivector: Some(multivector),
+            named_multivectors: HashMap::new(),
             sparse_vectors: HashMap::new(),
             payload,
         }
@@ -192,8 +217,9 @@ impl Point {
         Self {
             id,
             version: 0,
-            vector: Vector::new(vec![0.0]), // Placeholder for sparse-only points
+            vector: Vector::new(vec![]),
             multivector: None,
+            named_multivectors: HashMap::new(),
             sparse_vectors,
             payload,
         }
@@ -221,6 +247,39 @@ impl Point {
         self.multivector.as_ref()
     }
 
+    /// Add a named multivector to this point
+    pub fn add_named_multivector(&mut self, name: String, multivector: MultiVector) {
+        self.named_multivectors.insert(name, multivector);
+    }
+
+    /// Get a named multivector by name
+    pub fn get_named_multivector(&self, name: &str) -> Option<&MultiVector> {
+        self.named_multivectors.get(name)
+    }
+
+    /// Size of the payload serialized as JSON, in bytes
+    pub fn payload_size(&self) -> usize {
+        struct ByteCount(usize);
+
+        impl std::io::Write for ByteCount {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0 += buf.len();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut count = ByteCount(0);
+        if let Some(payload) = &self.payload {
+            // Writing to a counter cannot fail
+            let _ = serde_json::to_writer(&mut count, payload);
+        }
+        count.0
+    }
+
     #[inline]
     #[must_use]
     pub fn with_payload(mut self, payload: serde_json::Value) -> Self {
@@ -0,0 +1,101 @@
+//! Iteration over the points of a collection
+//!
+//! [`Collection::iter_filtered`](crate::Collection::iter_filtered) evaluates
+//! a filter once, under a single read lock, and keeps only the ids of the
+//! points that match. [`PointsIter`] then fetches them one at a time and
+//! clones only the parts its [`Projection`] asks for, so scanning a large
+//! collection neither copies every vector nor holds the lock while the
+//! caller works, which may well be writing to the same collection. As such
+//! writes can change a point after it matched, the filter runs again on each
+//! point as it is fetched.
+
+use crate::{Filter, Point, Vector};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Parts of a point an iterator yields besides its id and version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Projection {
+    pub payload: bool,
+    /// Dense, sparse and multivectors
+    pub vectors: bool,
+}
+
+impl Projection {
+    /// Ids and versions only
+    pub const IDS: Self = Self { payload: false, vectors: false };
+    /// Ids, versions and payloads
+    pub const PAYLOAD: Self = Self { payload: true, vectors: false };
+    /// Whole points
+    pub const ALL: Self = Self { payload: true, vectors: true };
+
+    #[inline]
+    #[must_use]
+    pub fn new(payload: bool, vectors: bool) -> Self {
+        Self { payload, vectors }
+    }
+
+    /// Copy of `point` with only the projected parts; the others are empty
+    pub fn apply(&self, point: &Point) -> Point {
+        if self.payload && self.vectors {
+            return point.clone();
+        }
+        Point {
+            id: point.id.clone(),
+            version: point.version,
+            vector: if self.vectors { point.vector.clone() } else { Vector::new(Vec::new()) },
+            multivector: if self.vectors { point.multivector.clone() } else { None },
+            named_multivectors: if self.vectors { point.named_multivectors.clone() } else { HashMap::new() },
+            sparse_vectors: if self.vectors { point.sparse_vectors.clone() } else { HashMap::new() },
+            payload: if self.payload { point.payload.clone() } else { None },
+        }
+    }
+}
+
+/// Points that matched a filter, fetched as they are iterated. A point
+/// deleted after the filter ran is skipped, as is one updated since that no
+/// longer matches; one that still does is yielded as it is now.
+pub struct PointsIter<'f> {
+    points: Arc<RwLock<HashMap<String, Point>>>,
+    ids: std::vec::IntoIter<String>,
+    projection: Projection,
+    filter: Option<Box<dyn Filter + 'f>>,
+}
+
+impl<'f> PointsIter<'f> {
+    pub(crate) fn new(
+        points: Arc<RwLock<HashMap<String, Point>>>,
+        ids: Vec<String>,
+        projection: Projection,
+        filter: Option<Box<dyn Filter + 'f>>,
+    ) -> Self {
+        Self { points, ids: ids.into_iter(), projection, filter }
+    }
+
+    /// Number of matching points not iterated yet, deleted ones included
+    #[inline]
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl Iterator for PointsIter<'_> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        for id in self.ids.by_ref() {
+            let points = self.points.read();
+            let Some(point) = points.get(&id) else { continue };
+            if self.filter.as_ref().map_or(true, |f| f.matches(point)) {
+                return Some(self.projection.apply(point));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.ids.len()))
+    }
+}
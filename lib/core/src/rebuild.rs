@@ -0,0 +1,218 @@
+//! Writes made while the HNSW graph is rebuilt
+//!
+//! A rebuild links a copy of the points into a new graph on a background
+//! thread while writes go on against the old graph. Each point written
+//! meanwhile is noted in the collection's [`RebuildBacklog`]. When the new
+//! graph is complete, the noted points are relinked into it as they stand
+//! then, in chunks under the collection's write lock, and it replaces the
+//! old graph before the next write. The backlog is thus the work a rebuild still owes
+//! writes, and what admission control of writes looks at.
+
+use crate::{FreshPoints, HnswIndex, Point};
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Noted points relinked under one hold of the write lock before the swap
+pub const RELINK_CHUNK: usize = 256;
+
+/// Ids of the points written since a running rebuild copied the points
+pub struct RebuildBacklog {
+    ids: Mutex<HashSet<String>>,
+    /// The collection's write lock, points and fresh points
+    write_lock: Arc<Mutex<()>>,
+    points: Arc<RwLock<HashMap<String, Point>>>,
+    fresh: Arc<FreshPoints>,
+}
+
+impl RebuildBacklog {
+    pub(crate) fn new(
+        write_lock: Arc<Mutex<()>>,
+        points: Arc<RwLock<HashMap<String, Point>>>,
+        fresh: Arc<FreshPoints>,
+    ) -> Self {
+        Self {
+            ids: Mutex::new(HashSet::new()),
+            write_lock,
+            points,
+            fresh,
+        }
+    }
+
+    /// Note points just written; the caller holds the write lock
+    pub(crate) fn note(&self, ids: &[String]) {
+        self.ids.lock().extend(ids.iter().cloned());
+    }
+
+    /// Number of distinct points noted
+    pub fn len(&self) -> usize {
+        self.ids.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.lock().is_empty()
+    }
+
+    /// Relink the noted points into `graph` and swap it into `hnsw`, then
+    /// run `finish`, e.g. to mark the rebuild done, before a write can see
+    /// the new graph. Fresh points the new graph links are forgotten.
+    ///
+    /// A large backlog is first relinked [`RELINK_CHUNK`] points at a time,
+    /// each chunk under the write lock, so writes wait for one chunk rather
+    /// than the whole backlog. Writes between chunks are noted again. Once
+    /// the backlog stops shrinking, the rest is relinked along with the swap.
+    pub(crate) fn swap_in(&self, mut graph: HnswIndex, hnsw: &RwLock<HnswIndex>, finish: impl FnOnce()) {
+        let mut left = self.len();
+        while left > RELINK_CHUNK {
+            let _writer = self.write_lock.lock();
+            let ids: Vec<String> = {
+                let mut noted = self.ids.lock();
+                let ids: Vec<String> = noted.iter().take(RELINK_CHUNK).cloned().collect();
+                for id in &ids {
+                    noted.remove(id);
+                }
+                ids
+            };
+            self.relink(&mut graph, &ids);
+            let now = self.len();
+            if now >= left {
+                break;
+            }
+            left = now;
+        }
+
+        let _writer = self.write_lock.lock();
+        let ids: Vec<String> = self.ids.lock().drain().collect();
+        self.relink(&mut graph, &ids);
+        self.fresh.retain_unlinked(&graph, &self.points.read());
+        *hnsw.write() = graph;
+        finish();
+    }
+
+    /// Link the points `ids` into `graph` as they are stored now. The caller
+    /// holds the write lock.
+    fn relink(&self, graph: &mut HnswIndex, ids: &[String]) {
+        if ids.is_empty() {
+            return;
+        }
+        let points = self.points.read();
+        let id_refs: Vec<&str> = ids.iter().map(String::as_str).collect();
+        graph.remove_batch(&id_refs);
+        for id in ids {
+            // Deleted points stay out, as do points of another dimension
+            if let Some(point) = points.get(id) {
+                if graph.is_empty() || point.vector.dim() == graph.dim() {
+                    let _ = graph.insert(point.clone());
+                }
+            }
+        }
+    }
+
+    /// Forget the noted points of a rebuild that ends without a new graph,
+    /// running `finish` before the next write
+    pub(crate) fn discard(&self, finish: impl FnOnce()) {
+        let _writer = self.write_lock.lock();
+        self.ids.lock().clear();
+        finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::BackgroundJob;
+    use crate::{Collection, CollectionConfig, Distance, Error, HnswParams, JobContext, PointId, Vector};
+
+    fn point(id: u64, vector: Vec<f32>) -> Point {
+        Point::new(PointId::Integer(id), Vector::new(vector), None)
+    }
+
+    #[test]
+    fn test_swap_in_relinks_noted_points() {
+        let points = Arc::new(RwLock::new(HashMap::new()));
+        let fresh = Arc::new(FreshPoints::default());
+        let backlog = RebuildBacklog::new(Arc::new(Mutex::new(())), points.clone(), fresh.clone());
+        let hnsw = RwLock::new(HnswIndex::new(16, 3));
+
+        // The rebuild copied points 1 and 2; then 2 was deleted and 3 written
+        let mut graph = HnswIndex::new(16, 3);
+        graph.insert(point(1, vec![1.0, 0.0])).unwrap();
+        graph.insert(point(2, vec![0.0, 1.0])).unwrap();
+        points.write().insert("1".to_string(), point(1, vec![1.0, 0.0]));
+        points.write().insert("3".to_string(), point(3, vec![1.0, 1.0]));
+        backlog.note(&["2".to_string(), "3".to_string(), "3".to_string()]);
+        fresh.add(["3"]);
+        assert_eq!(backlog.len(), 2);
+
+        let mut finished = false;
+        backlog.swap_in(graph, &hnsw, || finished = true);
+        assert!(finished);
+        assert!(backlog.is_empty());
+        assert!(fresh.is_empty());
+        let mut linked = hnsw.read().node_ids();
+        linked.sort();
+        assert_eq!(linked, vec!["1".to_string(), "3".to_string()]);
+    }
+
+    #[test]
+    fn test_writes_during_rebuild() {
+        let collection = Collection::new(CollectionConfig {
+            name: "busy".to_string(),
+            vector_dim: 8,
+            distance: Distance::Euclidean,
+            use_hnsw: true,
+            enable_bm25: false,
+        });
+        let vector = |i: u64| Vector::new((0..8).map(|d| ((i * 7 + d) % 13) as f32).collect());
+        collection.batch_upsert((0..3000).map(|i| point(i, vector(i).as_slice().to_vec())).collect()).unwrap();
+        collection.prewarm_index().unwrap();
+        assert_eq!(collection.stats().indexed_vectors_count, 3000);
+
+        // Upserts, deletes and vector updates made after the rebuild copied
+        // the points, more than one relinked chunk of them
+        let job = collection.reindex_job().unwrap();
+        for i in 0..200 {
+            collection.upsert(point(10_000 + i, vector(i + 5).as_slice().to_vec())).unwrap();
+            collection.delete(&PointId::Integer(i).to_string()).unwrap();
+            collection.update_vectors(vec![((1000 + i).to_string(), vector(i + 1))], false).unwrap();
+        }
+        assert_eq!(collection.rebuild_backlog(), 600);
+        assert!(collection.rebuild_backlog() > RELINK_CHUNK);
+        Box::new(job).execute(&JobContext::default());
+
+        // The new graph has every write made meanwhile
+        assert!(!collection.hnsw_rebuilding());
+        assert_eq!(collection.rebuild_backlog(), 0);
+        assert_eq!(collection.pending_points(), 0);
+        assert!(collection.check_index_consistency().is_empty());
+        assert_eq!(collection.stats().indexed_vectors_count, 3000);
+        let results = collection.search(&vector(1), 1, None);
+        assert_eq!(results[0].1, 0.0);
+    }
+
+    #[test]
+    fn test_settings_changes_during_rebuild() {
+        let collection = Collection::new(CollectionConfig {
+            name: "relinking".to_string(),
+            vector_dim: 2,
+            distance: Distance::Euclidean,
+            use_hnsw: true,
+            enable_bm25: false,
+        });
+        collection.batch_upsert((0..10).map(|i| point(i, vec![i as f32, 1.0])).collect()).unwrap();
+        collection.prewarm_index().unwrap();
+
+        // The running rebuild links by the old distance and parameters, so
+        // changing either is refused until it is done
+        let job = collection.reindex_job().unwrap();
+        let params = HnswParams { m: 8, ..collection.hnsw_params() };
+        assert!(matches!(collection.set_distance(Distance::Cosine), Err(Error::Rebuilding)));
+        assert!(matches!(collection.set_hnsw_params(params), Err(Error::Rebuilding)));
+        assert_eq!(collection.distance(), Distance::Euclidean);
+        assert_ne!(collection.hnsw_params(), params);
+        Box::new(job).execute(&JobContext::default());
+
+        assert!(!collection.hnsw_rebuilding());
+        assert!(collection.set_hnsw_params(params).unwrap());
+    }
+}
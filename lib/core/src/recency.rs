@@ -0,0 +1,156 @@
+//! Recency boost of search scores
+//!
+//! A [`RecencyBoost`] adds `weight * 0.5^(age / half_life_secs)` to the
+//! score of each result, where `age` is how long ago the timestamp in a
+//! payload field was. Points timestamped now get the full `weight`, points
+//! one half-life old get half of it, and points without a readable
+//! timestamp get nothing. Timestamps in the future count as now.
+//!
+//! The boost reorders results after the vector search, so searches fetch
+//! [`RECENCY_OVERSAMPLE`] times the candidates they return, letting recent
+//! points from a little further down the raw ranking move up.
+
+use crate::Point;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Candidates fetched per returned result when a boost applies
+pub const RECENCY_OVERSAMPLE: usize = 4;
+
+/// Exponential decay of scores with the age of a payload timestamp
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct RecencyBoost {
+    /// Top-level payload field with the timestamp: Unix seconds, or an
+    /// RFC 3339 date-time or date string
+    pub field: String,
+    /// Age in seconds at which the boost is halved
+    pub half_life_secs: f64,
+    /// Boost of a point timestamped now
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+/// A request's say on the recency boost: `false` turns the collection's
+/// off, `true` keeps it, and a boost replaces it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum RecencyOverride {
+    Enabled(bool),
+    Boost(RecencyBoost),
+}
+
+impl RecencyOverride {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Enabled(_) => Ok(()),
+            Self::Boost(boost) => boost.validate(),
+        }
+    }
+}
+
+impl RecencyBoost {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.field.is_empty() {
+            return Err("recency field must not be empty".to_string());
+        }
+        if !(self.half_life_secs.is_finite() && self.half_life_secs > 0.0) {
+            return Err("recency half_life_secs must be greater than 0".to_string());
+        }
+        if !(self.weight.is_finite() && self.weight >= 0.0) {
+            return Err("recency weight must be a non-negative number".to_string());
+        }
+        Ok(())
+    }
+
+    /// Boost of `point` at `now`, in Unix seconds
+    pub fn boost(&self, point: &Point, now: f64) -> f32 {
+        let Some(timestamp) = point.payload.as_ref()
+            .and_then(|payload| payload.get(&self.field))
+            .and_then(timestamp_secs)
+        else {
+            return 0.0;
+        };
+        let age = (now - timestamp).max(0.0);
+        self.weight * 0.5f64.powf(age / self.half_life_secs) as f32
+    }
+
+    /// `results` with boosted scores, best first
+    pub fn apply(&self, results: Vec<(Point, f32)>) -> Vec<(Point, f32)> {
+        let now = now_secs();
+        let mut boosted: Vec<(Point, f32)> = results
+            .into_iter()
+            .map(|(point, score)| {
+                let boost = self.boost(&point, now);
+                (point, score + boost)
+            })
+            .collect();
+        boosted.sort_by(|a, b| b.1.total_cmp(&a.1));
+        boosted
+    }
+}
+
+/// The current time in Unix seconds, as [`RecencyBoost::boost`] takes it
+pub fn now_secs() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// Unix seconds of a timestamp payload value
+pub fn timestamp_secs(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => {
+            let s = s.trim();
+            if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+                return Some(dt.timestamp_millis() as f64 / 1000.0);
+            }
+            if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+                return Some(dt.and_utc().timestamp_millis() as f64 / 1000.0);
+            }
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|dt| dt.and_utc().timestamp() as f64)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector;
+
+    fn point(id: u64, payload: Value) -> Point {
+        Point::new(crate::PointId::Integer(id), Vector::new(vec![1.0]), Some(payload))
+    }
+
+    #[test]
+    fn test_recency_boost() {
+        let boost = RecencyBoost { field: "ts".to_string(), half_life_secs: 100.0, weight: 1.0 };
+        assert!(boost.validate().is_ok());
+        assert!(RecencyBoost { half_life_secs: 0.0, ..boost.clone() }.validate().is_err());
+        assert!(RecencyBoost { weight: -1.0, ..boost.clone() }.validate().is_err());
+
+        let now = 1_000_000.0;
+        assert_eq!(boost.boost(&point(1, serde_json::json!({"ts": now})), now), 1.0);
+        assert_eq!(boost.boost(&point(1, serde_json::json!({"ts": now + 50.0})), now), 1.0);
+        assert!((boost.boost(&point(1, serde_json::json!({"ts": now - 100.0})), now) - 0.5).abs() < 1e-6);
+        assert_eq!(boost.boost(&point(1, serde_json::json!({"other": now})), now), 0.0);
+
+        assert_eq!(timestamp_secs(&serde_json::json!("1970-01-02")), Some(86400.0));
+        assert_eq!(timestamp_secs(&serde_json::json!("1970-01-01T00:01:00Z")), Some(60.0));
+        assert_eq!(timestamp_secs(&serde_json::json!("1970-01-01T01:00:00+01:00")), Some(0.0));
+        assert_eq!(timestamp_secs(&serde_json::json!("yesterday")), None);
+    }
+}
@@ -0,0 +1,153 @@
+//! Per-collection defaults for search requests
+//!
+//! A collection can carry [`SearchDefaults`] that fill in the parameters a
+//! search request leaves out, so operators can tune a collection without
+//! touching its clients. Parameters given in a request always win, except
+//! that `max_limit` caps every request's limit.
+
+use crate::recency::{RecencyBoost, RecencyOverride};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Search parameters applied when a request omits them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct SearchDefaults {
+    /// Number of results when the request has no `limit`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Upper bound on the number of results of any request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_limit: Option<usize>,
+    /// Size of the HNSW candidate list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ef: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_threshold: Option<f32>,
+    /// Whether results carry payloads when the request does not say
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub with_payload: Option<bool>,
+    /// Top-level payload keys kept in results when the request does not set
+    /// `with_payload`; all keys when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_fields: Option<Vec<String>>,
+    /// Multivector queries keep only this many of their sub-vectors, those
+    /// with the largest norms
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_query_tokens: Option<usize>,
+    /// Boost of recent points, applied unless the request turns it off or
+    /// brings its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recency: Option<RecencyBoost>,
+}
+
+impl SearchDefaults {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Reject zero sizes and a default limit above the cap
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("limit", self.limit),
+            ("max_limit", self.max_limit),
+            ("ef", self.ef),
+            ("max_query_tokens", self.max_query_tokens),
+        ] {
+            if value == Some(0) {
+                return Err(format!("search default '{}' must be greater than 0", name));
+            }
+        }
+        if let (Some(limit), Some(max_limit)) = (self.limit, self.max_limit) {
+            if limit > max_limit {
+                return Err(format!("search default limit {} exceeds max_limit {}", limit, max_limit));
+            }
+        }
+        self.recency.as_ref().map_or(Ok(()), RecencyBoost::validate)
+    }
+
+    /// The limit of a request: its own, else the default, else `fallback`,
+    /// capped by `max_limit`
+    pub fn limit(&self, requested: Option<usize>, fallback: usize) -> usize {
+        let limit = requested.or(self.limit).unwrap_or(fallback);
+        self.max_limit.map_or(limit, |max| limit.min(max))
+    }
+
+    pub fn score_threshold(&self, requested: Option<f32>) -> Option<f32> {
+        requested.or(self.score_threshold)
+    }
+
+    /// Whether results carry payloads; they do unless the request or the
+    /// defaults say otherwise
+    pub fn with_payload(&self, requested: Option<bool>) -> bool {
+        requested.or(self.with_payload).unwrap_or(true)
+    }
+
+    /// Fields the returned payloads are cut down to, if any. A request that
+    /// sets `with_payload` gets whole payloads.
+    pub fn payload_fields(&self, requested: Option<bool>) -> Option<&[String]> {
+        match requested {
+            Some(_) => None,
+            None => self.payload_fields.as_deref(),
+        }
+    }
+
+    /// The recency boost of a request, if any
+    pub fn recency<'a>(&'a self, requested: Option<&'a RecencyOverride>) -> Option<&'a RecencyBoost> {
+        match requested {
+            None | Some(RecencyOverride::Enabled(true)) => self.recency.as_ref(),
+            Some(RecencyOverride::Enabled(false)) => None,
+            Some(RecencyOverride::Boost(boost)) => Some(boost),
+        }
+    }
+}
+
+/// `payload` with only the top-level `fields` kept
+pub fn project_payload(payload: &Value, fields: &[String]) -> Value {
+    match payload {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(key, _)| fields.contains(key))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_defaults() {
+        let defaults = SearchDefaults {
+            limit: Some(5),
+            max_limit: Some(20),
+            with_payload: Some(false),
+            payload_fields: Some(vec!["title".to_string()]),
+            ..Default::default()
+        };
+        assert!(defaults.validate().is_ok());
+        assert_eq!(defaults.limit(None, 10), 5);
+        assert_eq!(defaults.limit(Some(100), 10), 20);
+        assert!(!defaults.with_payload(None));
+        assert!(defaults.with_payload(Some(true)));
+        assert!(defaults.payload_fields(Some(true)).is_none());
+
+        let payload = serde_json::json!({"title": "a", "body": "b"});
+        assert_eq!(project_payload(&payload, defaults.payload_fields(None).unwrap()), serde_json::json!({"title": "a"}));
+
+        assert!(SearchDefaults { limit: Some(30), ..defaults.clone() }.validate().is_err());
+        assert!(SearchDefaults { ef: Some(0), ..Default::default() }.validate().is_err());
+        assert!(SearchDefaults { max_query_tokens: Some(0), ..Default::default() }.validate().is_err());
+        assert!(SearchDefaults::default().is_empty());
+
+        let recency = RecencyBoost { field: "ts".to_string(), half_life_secs: 60.0, weight: 1.0 };
+        let with_recency = SearchDefaults { recency: Some(recency.clone()), ..Default::default() };
+        assert_eq!(with_recency.recency(None), Some(&recency));
+        assert_eq!(with_recency.recency(Some(&RecencyOverride::Enabled(false))), None);
+        assert!(SearchDefaults { recency: Some(RecencyBoost { half_life_secs: -1.0, ..recency }), ..Default::default() }.validate().is_err());
+    }
+}
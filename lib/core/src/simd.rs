@@ -218,6 +218,105 @@ fn dot_product_scalar(a: &[f32], b: &[f32]) -> f32 {
 }
 
 
+/// Dot products of `query` with each of `rows`, written to `out`. Rows are
+/// scored four at a time so each query chunk is loaded once per block; a
+/// row whose length differs from the query scores 0.0, as in
+/// [`dot_product_simd`].
+pub fn dot_products_simd<R: AsRef<[f32]>>(query: &[f32], rows: &[R], out: &mut [f32]) {
+    assert_eq!(rows.len(), out.len(), "one output per row");
+    if rows.iter().any(|row| row.as_ref().len() != query.len()) {
+        for (row, out) in rows.iter().zip(out.iter_mut()) {
+            *out = dot_product_simd(query, row.as_ref());
+        }
+        return;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2")
+            && is_x86_feature_detected!("fma")
+            && query.len() >= MIN_DIM_SIZE_SIMD
+        {
+            return unsafe { dot_products_avx2(query, rows, out) };
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") && query.len() >= MIN_DIM_SIZE_SIMD {
+            return unsafe { dot_products_neon(query, rows, out) };
+        }
+    }
+
+    for (row, out) in rows.iter().zip(out.iter_mut()) {
+        *out = dot_product_simd(query, row.as_ref());
+    }
+}
+
+/// Four rows per pass, 8 floats at a time, with one accumulator per row
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn dot_products_avx2<R: AsRef<[f32]>>(query: &[f32], rows: &[R], out: &mut [f32]) {
+    let dim = query.len();
+    let blocks = rows.chunks_exact(4);
+    let rest = blocks.remainder();
+    let mut out_blocks = out.chunks_exact_mut(4);
+
+    for (block, out) in blocks.zip(out_blocks.by_ref()) {
+        let r = [block[0].as_ref(), block[1].as_ref(), block[2].as_ref(), block[3].as_ref()];
+        let mut sums = [_mm256_setzero_ps(); 4];
+        let mut i = 0;
+        while i + 7 < dim {
+            let q = _mm256_loadu_ps(query.as_ptr().add(i));
+            for (sum, row) in sums.iter_mut().zip(r) {
+                *sum = _mm256_fmadd_ps(q, _mm256_loadu_ps(row.as_ptr().add(i)), *sum);
+            }
+            i += 8;
+        }
+        for ((out, sum), row) in out.iter_mut().zip(sums).zip(r) {
+            let high = _mm256_extractf128_ps(sum, 1);
+            let mut sum_128 = _mm_add_ps(high, _mm256_castps256_ps128(sum));
+            sum_128 = _mm_hadd_ps(sum_128, sum_128);
+            sum_128 = _mm_hadd_ps(sum_128, sum_128);
+            *out = _mm_cvtss_f32(sum_128) + query[i..].iter().zip(&row[i..]).map(|(a, b)| a * b).sum::<f32>();
+        }
+    }
+
+    for (row, out) in rest.iter().zip(out_blocks.into_remainder()) {
+        *out = dot_product_avx2(query, row.as_ref());
+    }
+}
+
+/// Four rows per pass, 4 floats at a time, with one accumulator per row
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn dot_products_neon<R: AsRef<[f32]>>(query: &[f32], rows: &[R], out: &mut [f32]) {
+    let dim = query.len();
+    let blocks = rows.chunks_exact(4);
+    let rest = blocks.remainder();
+    let mut out_blocks = out.chunks_exact_mut(4);
+
+    for (block, out) in blocks.zip(out_blocks.by_ref()) {
+        let r = [block[0].as_ref(), block[1].as_ref(), block[2].as_ref(), block[3].as_ref()];
+        let mut sums = [vdupq_n_f32(0.0); 4];
+        let mut i = 0;
+        while i + 3 < dim {
+            let q = vld1q_f32(query.as_ptr().add(i));
+            for (sum, row) in sums.iter_mut().zip(r) {
+                *sum = vfmaq_f32(*sum, q, vld1q_f32(row.as_ptr().add(i)));
+            }
+            i += 4;
+        }
+        for ((out, sum), row) in out.iter_mut().zip(sums).zip(r) {
+            *out = vaddvq_f32(sum) + query[i..].iter().zip(&row[i..]).map(|(a, b)| a * b).sum::<f32>();
+        }
+    }
+
+    for (row, out) in rest.iter().zip(out_blocks.into_remainder()) {
+        *out = dot_product_neon(query, row.as_ref());
+    }
+}
+
 /// SIMD-optimized L2 distance (Euclidean)
 #[inline]
 pub fn l2_distance_simd(a: &[f32], b: &[f32]) -> f32 {
@@ -438,3 +537,118 @@ pub fn norm_simd(v: &[f32]) -> f32 {
     norm_squared_simd(v).sqrt()
 }
 
+
+/// Words from which the AVX2 Hamming kernel pays off (256 bits)
+#[cfg(target_arch = "x86_64")]
+const MIN_WORDS_AVX: usize = 8;
+
+/// Number of differing bits between two bit-packed vectors (popcount of
+/// their XOR). Returns `u32::MAX` if the lengths differ.
+#[inline]
+pub fn hamming_distance_simd(a: &[u32], b: &[u32]) -> u32 {
+    if a.len() != b.len() {
+        return u32::MAX;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") && a.len() >= MIN_WORDS_AVX {
+            return unsafe { hamming_distance_avx2(a, b) };
+        }
+        if is_x86_feature_detected!("popcnt") {
+            return unsafe { hamming_distance_popcnt(a, b) };
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { hamming_distance_neon(a, b) };
+        }
+    }
+
+    hamming_distance_scalar(a, b)
+}
+
+/// AVX2 Hamming distance, 256 bits at a time: the XOR's nibbles are
+/// counted with a shuffle lookup and the byte counts summed with SAD
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline]
+unsafe fn hamming_distance_avx2(a: &[u32], b: &[u32]) -> u32 {
+    let len = a.len();
+    let mut i = 0;
+
+    let lookup = _mm256_setr_epi8(
+        0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4,
+        0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4,
+    );
+    let low_mask = _mm256_set1_epi8(0x0f);
+    let zero = _mm256_setzero_si256();
+    let mut sum = _mm256_setzero_si256();
+
+    while i + 7 < len {
+        let va = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+        let vb = _mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i);
+        let x = _mm256_xor_si256(va, vb);
+        let lo = _mm256_and_si256(x, low_mask);
+        let hi = _mm256_and_si256(_mm256_srli_epi16(x, 4), low_mask);
+        let counts = _mm256_add_epi8(_mm256_shuffle_epi8(lookup, lo), _mm256_shuffle_epi8(lookup, hi));
+        // Sum each group of 8 byte counts into a 64-bit lane
+        sum = _mm256_add_epi64(sum, _mm256_sad_epu8(counts, zero));
+        i += 8;
+    }
+
+    let mut lanes = [0u64; 4];
+    _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+    let distance = lanes.iter().sum::<u64>() as u32;
+
+    distance + hamming_distance_scalar(&a[i..], &b[i..])
+}
+
+/// Hamming distance with the POPCNT instruction, 64 bits at a time
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "popcnt")]
+#[inline]
+unsafe fn hamming_distance_popcnt(a: &[u32], b: &[u32]) -> u32 {
+    // count_ones compiles to POPCNT with the feature enabled
+    let chunks = a.chunks_exact(2);
+    let remainder = chunks.remainder();
+    let mut distance = 0u32;
+    for (a_pair, b_pair) in chunks.zip(b.chunks_exact(2)) {
+        let x = (u64::from(a_pair[0]) | u64::from(a_pair[1]) << 32)
+            ^ (u64::from(b_pair[0]) | u64::from(b_pair[1]) << 32);
+        distance += x.count_ones();
+    }
+    for i in (a.len() - remainder.len())..a.len() {
+        distance += (a[i] ^ b[i]).count_ones();
+    }
+    distance
+}
+
+/// NEON Hamming distance, 128 bits at a time with per-byte popcounts
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+#[inline]
+unsafe fn hamming_distance_neon(a: &[u32], b: &[u32]) -> u32 {
+    let len = a.len();
+    let mut i = 0;
+    let mut distance = 0u32;
+
+    while i + 3 < len {
+        let va = vld1q_u32(a.as_ptr().add(i));
+        let vb = vld1q_u32(b.as_ptr().add(i));
+        let counts = vcntq_u8(vreinterpretq_u8_u32(veorq_u32(va, vb)));
+        // At most 128 set bits, so the horizontal sum fits a byte
+        distance += u32::from(vaddvq_u8(counts));
+        i += 4;
+    }
+
+    distance + hamming_distance_scalar(&a[i..], &b[i..])
+}
+
+/// Scalar Hamming distance
+#[inline]
+fn hamming_distance_scalar(a: &[u32], b: &[u32]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
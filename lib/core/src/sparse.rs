@@ -0,0 +1,184 @@
+//! Sparse vector scoring modifiers
+//!
+//! With [`SparseModifier::Idf`] a sparse vector is scored like BM25: each
+//! matching dimension contributes `query * document * idf(dimension)`, where
+//!
+//! ```text
+//! idf = ln((N - n + 0.5) / (n + 0.5) + 1)
+//! ```
+//!
+//! with `N` the number of points holding that named sparse vector and `n` the
+//! number of them with a non-zero value in the dimension. Document
+//! frequencies are updated on every upsert and delete, so scores reflect the
+//! current contents of the collection.
+
+use crate::point::SparseVector;
+use crate::Point;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How sparse vector scores are weighted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SparseModifier {
+    /// Plain dot product
+    #[default]
+    None,
+    /// Scale each dimension by its inverse document frequency
+    Idf,
+}
+
+/// Index parameters of a named sparse vector, as Qdrant takes them. Sparse
+/// search scans the points holding the vector, so they are kept and
+/// reported but don't change how it runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SparseIndexParams {
+    /// Points below which Qdrant scans instead of using the index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_scan_threshold: Option<usize>,
+    /// Whether Qdrant keeps the index on disk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_disk: Option<bool>,
+}
+
+/// Configuration of a named sparse vector declared by a collection
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SparseVectorConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<SparseIndexParams>,
+    #[serde(default, deserialize_with = "modifier_or_none")]
+    pub modifier: SparseModifier,
+}
+
+/// A `null` modifier means none
+fn modifier_or_none<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<SparseModifier, D::Error> {
+    Option::<SparseModifier>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+/// Per-dimension document frequencies of every named sparse vector
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SparseDocFrequencies {
+    /// vector name -> points holding it
+    docs: HashMap<String, usize>,
+    /// vector name -> dimension -> points with a non-zero value there
+    frequencies: HashMap<String, HashMap<u32, usize>>,
+}
+
+impl SparseDocFrequencies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the sparse vectors of a newly stored point
+    pub fn add(&mut self, point: &Point) {
+        for (name, sparse) in &point.sparse_vectors {
+            *self.docs.entry(name.clone()).or_default() += 1;
+            let frequencies = self.frequencies.entry(name.clone()).or_default();
+            for (&index, &value) in sparse.indices.iter().zip(&sparse.values) {
+                if value != 0.0 {
+                    *frequencies.entry(index).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    /// Forget the sparse vectors of a replaced or deleted point
+    pub fn remove(&mut self, point: &Point) {
+        for (name, sparse) in &point.sparse_vectors {
+            if let Some(docs) = self.docs.get_mut(name) {
+                *docs = docs.saturating_sub(1);
+                if *docs == 0 {
+                    self.docs.remove(name);
+                    self.frequencies.remove(name);
+                    continue;
+                }
+            }
+            if let Some(frequencies) = self.frequencies.get_mut(name) {
+                for (&index, &value) in sparse.indices.iter().zip(&sparse.values) {
+                    if value == 0.0 {
+                        continue;
+                    }
+                    if let Some(count) = frequencies.get_mut(&index) {
+                        *count -= 1;
+                        if *count == 0 {
+                            frequencies.remove(&index);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Names of the sparse vectors stored points hold
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.docs.keys().map(String::as_str)
+    }
+
+    /// Points holding the named sparse vector
+    pub fn doc_count(&self, name: &str) -> usize {
+        self.docs.get(name).copied().unwrap_or(0)
+    }
+
+    /// Points with a non-zero value in `index` of the named sparse vector
+    pub fn doc_frequency(&self, name: &str, index: u32) -> usize {
+        self.frequencies.get(name)
+            .and_then(|f| f.get(&index))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Inverse document frequency of a dimension
+    pub fn idf(&self, name: &str, index: u32) -> f32 {
+        let total = self.doc_count(name) as f32;
+        let df = self.doc_frequency(name, index) as f32;
+        ((total - df + 0.5) / (df + 0.5) + 1.0).ln()
+    }
+
+    /// The query with each value scaled by the IDF of its dimension, so a
+    /// plain dot product against documents gives the IDF-weighted score
+    pub fn weight_query(&self, name: &str, query: &SparseVector) -> SparseVector {
+        let values = query.indices.iter()
+            .zip(&query.values)
+            .map(|(&index, &value)| value * self.idf(name, index))
+            .collect();
+        SparseVector::new(query.indices.clone(), values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PointId;
+
+    fn point(id: u64, indices: Vec<u32>) -> Point {
+        let values = vec![1.0; indices.len()];
+        let mut sparse = HashMap::new();
+        sparse.insert("text".to_string(), SparseVector::new(indices, values));
+        Point::new_sparse(PointId::Integer(id), sparse, None)
+    }
+
+    #[test]
+    fn test_rare_dimensions_weigh_more() {
+        let mut df = SparseDocFrequencies::new();
+        let points = [point(1, vec![1, 2]), point(2, vec![1]), point(3, vec![1, 3])];
+        for p in &points {
+            df.add(p);
+        }
+        assert_eq!(df.doc_count("text"), 3);
+        assert_eq!(df.doc_frequency("text", 1), 3);
+        assert!(df.idf("text", 2) > df.idf("text", 1));
+        // Unseen dimensions get the highest weight
+        assert!(df.idf("text", 9) > df.idf("text", 2));
+
+        df.remove(&points[0]);
+        assert_eq!(df.doc_count("text"), 2);
+        assert_eq!(df.doc_frequency("text", 1), 2);
+        assert_eq!(df.doc_frequency("text", 2), 0);
+
+        let query = SparseVector::new(vec![1, 3], vec![2.0, 1.0]);
+        let weighted = df.weight_query("text", &query);
+        assert_eq!(weighted.values[0], 2.0 * df.idf("text", 1));
+    }
+}
@@ -0,0 +1,129 @@
+//! Resource statistics for a collection
+//!
+//! Memory figures are estimates: they add up the heap data of each structure
+//! (vector components, payload JSON, graph links, posting lists) plus a fixed
+//! per-entry overhead, without asking the allocator. They are cheap enough
+//! to compute on every info request and accurate enough to compare
+//! collections and spot growth.
+
+use crate::Point;
+use serde::Serialize;
+
+/// Approximate bookkeeping cost of one hash map entry (hash, control byte,
+/// padding)
+pub(crate) const MAP_ENTRY_OVERHEAD: usize = 16;
+
+/// Estimated bytes held in memory, by structure
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryStats {
+    /// Dense, sparse and multivector data of stored points
+    pub vectors_bytes: usize,
+    pub payload_bytes: usize,
+    /// Graph links plus the index's own copies of vectors and payloads
+    pub hnsw_bytes: usize,
+    /// Posting lists and document lengths
+    pub bm25_bytes: usize,
+}
+
+impl MemoryStats {
+    pub fn total_bytes(&self) -> usize {
+        self.vectors_bytes + self.payload_bytes + self.hnsw_bytes + self.bm25_bytes
+    }
+}
+
+/// Points linked into the new HNSW graph by a running reindex, out of all
+/// points
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ReindexProgress {
+    pub indexed: usize,
+    pub total: usize,
+}
+
+/// Point and index counts plus memory estimates of a collection
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CollectionStats {
+    pub points_count: usize,
+    /// Points with a dense vector
+    pub vectors_count: usize,
+    /// Points with at least one sparse vector
+    pub sparse_vectors_count: usize,
+    /// Points with an unnamed or at least one named multivector
+    pub multivectors_count: usize,
+    /// Points reachable through the HNSW graph (0 until the graph is built)
+    pub indexed_vectors_count: usize,
+    /// Points upserted in the current batch, not yet journaled
+    pub pending_points_count: usize,
+    /// The HNSW graph is being built or rebuilt in the background
+    pub hnsw_rebuilding: bool,
+    /// Points written during the rebuild, to link into the new graph
+    pub rebuild_backlog: usize,
+    /// Points the HNSW graph misses or links with an old vector, which
+    /// searches score by brute force
+    pub fresh_points: usize,
+    /// Too many points are fresh to track, so searches scan every point
+    pub fresh_points_overflowed: bool,
+    pub bm25_documents: usize,
+    pub bm25_terms: usize,
+    pub payload_indexes: usize,
+    /// When the HNSW graph was last (re)built, ms since the Unix epoch
+    pub hnsw_built_at: Option<u64>,
+    /// Progress of a running reindex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reindex: Option<ReindexProgress>,
+    pub memory: MemoryStats,
+}
+
+/// Bytes of vector data held by a point
+pub(crate) fn point_vectors_size(point: &Point) -> usize {
+    let dense = std::mem::size_of_val(point.vector.as_slice());
+    let multi: usize = point.multivector.iter()
+        .chain(point.named_multivectors.values())
+        .flat_map(|mv| mv.vectors())
+        .map(|v| std::mem::size_of_val(v.as_slice()))
+        .sum();
+    let sparse: usize = point.sparse_vectors.iter()
+        .map(|(name, sv)| name.len() + (sv.indices.len() + sv.values.len()) * 4 + MAP_ENTRY_OVERHEAD)
+        .sum();
+    dense + multi + sparse
+}
+
+/// Bytes of payload data held by a point
+pub(crate) fn point_payload_size(point: &Point) -> usize {
+    point.payload.as_ref().map_or(0, json_size)
+}
+
+/// Approximate heap size of a JSON value
+pub(crate) fn json_size(value: &serde_json::Value) -> usize {
+    use serde_json::Value;
+    let own = std::mem::size_of::<Value>();
+    match value {
+        Value::Null | Value::Bool(_) | Value::Number(_) => own,
+        Value::String(s) => own + s.len(),
+        Value::Array(items) => own + items.iter().map(json_size).sum::<usize>(),
+        Value::Object(map) => own + map.iter()
+            .map(|(k, v)| k.len() + MAP_ENTRY_OVERHEAD + json_size(v))
+            .sum::<usize>(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PointId, SparseVector, Vector};
+
+    #[test]
+    fn test_point_sizes() {
+        let mut point = Point::new(
+            PointId::Integer(1),
+            Vector::new(vec![0.0; 8]),
+            Some(serde_json::json!({"title": "abcd"})),
+        );
+        assert_eq!(point_vectors_size(&point), 32);
+
+        point.sparse_vectors.insert("kw".to_string(), SparseVector::new(vec![1, 2], vec![0.5, 0.5]));
+        assert_eq!(point_vectors_size(&point), 32 + 2 + 16 + MAP_ENTRY_OVERHEAD);
+
+        let value_size = std::mem::size_of::<serde_json::Value>();
+        assert_eq!(point_payload_size(&point), 2 * value_size + "title".len() + MAP_ENTRY_OVERHEAD + 4);
+    }
+}
@@ -0,0 +1,145 @@
+//! Durable point storage hook
+//!
+//! A [`PointStore`] attached with
+//! [`Collection::set_point_store`](crate::Collection::set_point_store) sees
+//! the full record of every point a write touches, after the change is
+//! applied, so it can keep a persistent copy of the collection (the storage
+//! crate writes them to LMDB). Calls are made on the writer's thread while
+//! the collection's point map may still be locked: implementations must not
+//! call back into the collection. A write the store refuses fails, so no
+//! client is told a change succeeded that is not on disk.
+
+use crate::{Point, Result};
+
+/// Receives every point written to or removed from a collection
+pub trait PointStore: Send + Sync {
+    /// Store the current state of a point
+    fn put(&self, point: &Point) -> Result<()>;
+
+    /// Forget a deleted point
+    fn remove(&self, id: &str) -> Result<()>;
+
+    /// Store and forget several points together, e.g. for an atomic batch.
+    /// Stores that can should apply them as one write.
+    fn apply(&self, puts: &[Point], removes: &[String]) -> Result<()> {
+        for point in puts {
+            self.put(point)?;
+        }
+        for id in removes {
+            self.remove(id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Collection, CollectionConfig, Distance, Error, PointId, Vector};
+    use parking_lot::Mutex;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    /// Counts the store writes it is asked for, refusing them while `fail` is set
+    #[derive(Default)]
+    struct Recording {
+        writes: Mutex<Vec<(usize, usize)>>,
+        fail: AtomicBool,
+    }
+
+    impl PointStore for Recording {
+        fn put(&self, point: &Point) -> Result<()> {
+            self.apply(std::slice::from_ref(point), &[])
+        }
+
+        fn remove(&self, id: &str) -> Result<()> {
+            self.apply(&[], &[id.to_string()])
+        }
+
+        fn apply(&self, puts: &[Point], removes: &[String]) -> Result<()> {
+            if self.fail.load(Ordering::Relaxed) {
+                return Err(Error::Storage("disk full".to_string()));
+            }
+            self.writes.lock().push((puts.len(), removes.len()));
+            Ok(())
+        }
+    }
+
+    fn point(id: u64, x: f32) -> Point {
+        Point::new(PointId::Integer(id), Vector::new(vec![x, 1.0]), Some(serde_json::json!({"n": id})))
+    }
+
+    #[test]
+    fn test_store_errors_fail_the_write() {
+        let collection = Collection::new(CollectionConfig {
+            name: "stored".to_string(),
+            vector_dim: 2,
+            distance: Distance::Euclidean,
+            use_hnsw: true,
+            enable_bm25: false,
+        });
+        let store = Arc::new(Recording::default());
+        collection.set_point_store(Some(store.clone()));
+
+        // A batch is one store write
+        collection.batch_upsert((0..50).map(|i| point(i, i as f32)).collect()).unwrap();
+        assert_eq!(*store.writes.lock(), [(50, 0)]);
+        collection.delete_batch(&["1".to_string(), "2".to_string(), "1".to_string(), "99".to_string()]).unwrap();
+        assert_eq!(store.writes.lock().last(), Some(&(0, 2)));
+
+        // A refused write fails and changes nothing in memory
+        store.fail.store(true, Ordering::Relaxed);
+        assert!(collection.upsert(point(100, 1.0)).is_err());
+        assert!(collection.get("100").is_none());
+        assert!(collection.set_payload("3", serde_json::json!({"n": 0})).is_err());
+        assert_eq!(collection.get("3").unwrap().payload, Some(serde_json::json!({"n": 3})));
+        assert!(collection.delete("3").is_err());
+        assert!(collection.delete_batch(&["4".to_string()]).is_err());
+        assert!(collection.update_vectors(vec![("5".to_string(), Vector::new(vec![9.0, 9.0]))], false).is_err());
+        assert_eq!(collection.get("5").unwrap().vector.as_slice(), &[5.0, 1.0]);
+        assert!(collection.get("4").is_some() && collection.get("3").is_some());
+        assert_eq!(collection.count(), 48);
+
+        store.fail.store(false, Ordering::Relaxed);
+        assert!(collection.upsert(point(100, 1.0)).is_ok());
+        assert_eq!(collection.get("5").unwrap().version, 0);
+    }
+
+    #[test]
+    fn test_refused_batch_is_rolled_back() {
+        let collection = Collection::new(CollectionConfig {
+            name: "stored".to_string(),
+            vector_dim: 2,
+            distance: Distance::Euclidean,
+            use_hnsw: true,
+            enable_bm25: true,
+        });
+        let store = Arc::new(Recording::default());
+        collection.set_point_store(Some(store.clone()));
+        let text = |id: u64, text: &str| {
+            Point::new(PointId::Integer(id), Vector::new(vec![id as f32, 1.0]), Some(serde_json::json!({"text": text})))
+        };
+        collection.batch_upsert(vec![text(0, "apple"), text(1, "pear")]).unwrap();
+
+        // Neither the new points nor the changes to stored ones outlive a
+        // batch the store refuses
+        store.fail.store(true, Ordering::Relaxed);
+        let batch = vec![text(1, "plum"), text(7, "plum"), point(8, 8.0)];
+        assert!(collection.batch_upsert(batch.clone()).is_err());
+        assert!(collection.batch_upsert_lenient(batch, None).is_err());
+        assert!(collection.get("7").is_none() && collection.get("8").is_none());
+        let kept = collection.get("1").unwrap();
+        assert_eq!((kept.version, kept.payload), (0, Some(serde_json::json!({"text": "pear"}))));
+        assert_eq!(collection.count(), 2);
+        assert!(collection.search_text("plum", 10).is_empty());
+        assert_eq!(collection.search_text("pear", 10)[0].0, "1");
+        let found = collection.search(&Vector::new(vec![8.0, 1.0]), 10, None);
+        assert_eq!(found.iter().map(|(p, _)| p.id.to_string()).collect::<Vec<_>>(), ["1", "0"]);
+        assert!(collection.check_index_consistency().is_empty());
+        assert_eq!(collection.journal().len(), 1);
+
+        store.fail.store(false, Ordering::Relaxed);
+        collection.batch_upsert(vec![text(7, "plum")]).unwrap();
+        assert_eq!(collection.search_text("plum", 10)[0].0, "7");
+    }
+}
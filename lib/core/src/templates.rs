@@ -0,0 +1,148 @@
+//! Named query templates
+//!
+//! A collection can keep [`QueryTemplate`]s: query request bodies in which a
+//! string such as `"$vector"` stands for a value given when the template is
+//! run. Applications then send only those values, and the filters, fusion
+//! and other parameters around them can be changed on the server without
+//! touching the clients.
+//!
+//! A placeholder is a whole JSON string made of `$` and a name of letters,
+//! digits and underscores. Strings that merely start with `$` are left
+//! alone, and `$$` at the start of a string is a literal `$`, so
+//! `"$$score"` renders as `"$score"`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+/// A query body with placeholders, and defaults for some of them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct QueryTemplate {
+    /// Body of the query, with `"$name"` strings as placeholders
+    pub query: Value,
+    /// Values of variables that callers may leave out
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub defaults: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Whether `name` can name a template: letters, digits, `_` and `-`
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// The variable `s` stands for, if it is a placeholder
+fn placeholder(s: &str) -> Option<&str> {
+    let name = s.strip_prefix('$')?;
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(name)
+}
+
+impl QueryTemplate {
+    /// Reject bodies that are not objects and defaults for variables the
+    /// body does not use
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.query.is_object() {
+            return Err("template query must be a JSON object".to_string());
+        }
+        let variables = self.variables();
+        if let Some(name) = self.defaults.keys().find(|name| !variables.contains(*name)) {
+            return Err(format!("default for unknown template variable '{}'", name));
+        }
+        Ok(())
+    }
+
+    /// Names of the variables the body uses
+    pub fn variables(&self) -> BTreeSet<String> {
+        fn collect(value: &Value, names: &mut BTreeSet<String>) {
+            match value {
+                Value::String(s) => names.extend(placeholder(s).map(str::to_string)),
+                Value::Array(items) => items.iter().for_each(|item| collect(item, names)),
+                Value::Object(map) => map.values().for_each(|item| collect(item, names)),
+                _ => {}
+            }
+        }
+        let mut names = BTreeSet::new();
+        collect(&self.query, &mut names);
+        names
+    }
+
+    /// The body with every placeholder replaced by its value in
+    /// `variables`, else its default. Unknown and missing variables are
+    /// errors.
+    pub fn render(&self, variables: &Map<String, Value>) -> Result<Value, String> {
+        let used = self.variables();
+        if let Some(name) = variables.keys().find(|name| !used.contains(*name)) {
+            return Err(format!("Unknown template variable '{}'", name));
+        }
+        if let Some(name) = used.iter().find(|name| !variables.contains_key(*name) && !self.defaults.contains_key(*name)) {
+            return Err(format!("Missing template variable '{}'", name));
+        }
+        Ok(self.substitute(&self.query, &|name| variables.get(name).or_else(|| self.defaults.get(name))))
+    }
+
+    /// The body with defaults filled in and other placeholders null, to
+    /// check its shape before any caller supplies values
+    pub fn preview(&self) -> Value {
+        self.substitute(&self.query, &|name| self.defaults.get(name))
+    }
+
+    /// `value` with every placeholder replaced by `lookup(name)`, or null
+    fn substitute<'a>(&self, value: &Value, lookup: &dyn Fn(&str) -> Option<&'a Value>) -> Value {
+        match value {
+            Value::String(s) => match placeholder(s) {
+                Some(name) => lookup(name).cloned().unwrap_or(Value::Null),
+                None => match s.strip_prefix("$$") {
+                    Some(rest) => Value::String(format!("${}", rest)),
+                    None => value.clone(),
+                },
+            },
+            Value::Array(items) => Value::Array(items.iter().map(|item| self.substitute(item, lookup)).collect()),
+            Value::Object(map) => Value::Object(
+                map.iter().map(|(key, item)| (key.clone(), self.substitute(item, lookup))).collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_template() {
+        let template: QueryTemplate = serde_json::from_value(json!({
+            "query": {
+                "query": "$vector",
+                "limit": "$limit",
+                "filter": {"must": [{"key": "lang", "match": {"value": "$lang"}}]},
+                "computed": {"boosted": "$$score * 2", "price": "$ price"}
+            },
+            "defaults": {"limit": 10, "lang": "en"}
+        })).unwrap();
+        template.validate().unwrap();
+        assert_eq!(template.variables().into_iter().collect::<Vec<_>>(), vec!["lang", "limit", "vector"]);
+
+        let variables = json!({"vector": [0.1, 0.2], "lang": "de"});
+        let rendered = template.render(variables.as_object().unwrap()).unwrap();
+        assert_eq!(rendered["query"], json!([0.1, 0.2]));
+        assert_eq!(rendered["limit"], json!(10));
+        assert_eq!(rendered["filter"]["must"][0]["match"]["value"], json!("de"));
+        assert_eq!(rendered["computed"], json!({"boosted": "$score * 2", "price": "$ price"}));
+
+        assert!(template.render(&Map::new()).unwrap_err().contains("vector"));
+        assert_eq!(template.preview()["query"], Value::Null);
+        let unknown = json!({"vector": [0.1], "colour": "red"});
+        assert!(template.render(unknown.as_object().unwrap()).unwrap_err().contains("colour"));
+
+        let bad_default = QueryTemplate { defaults: json!({"x": 1}).as_object().unwrap().clone(), ..template.clone() };
+        assert!(bad_default.validate().is_err());
+        assert!(QueryTemplate { query: json!([1]), defaults: Map::new(), description: None }.validate().is_err());
+        assert!(is_valid_name("news-v2") && !is_valid_name("a/b") && !is_valid_name(""));
+    }
+}
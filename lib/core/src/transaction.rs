@@ -0,0 +1,22 @@
+//! All-or-nothing batches of point operations
+//!
+//! [`Collection::apply_atomic`](crate::Collection::apply_atomic) takes a list
+//! of [`BatchOperation`]s, e.g. a parent record upserted together with its
+//! chunk points and the deletion of stale chunks. The operations are applied
+//! in order, each seeing the effects of the previous ones. Readers observe
+//! either none or all of them, the point store receives them as one write,
+//! and the journal records them as one `transaction` entry.
+
+use crate::Point;
+
+/// One operation of an atomic batch
+#[derive(Debug, Clone)]
+pub enum BatchOperation {
+    Upsert(Vec<Point>),
+    Delete(Vec<String>),
+    /// Merge `payload` into the payload of each point
+    SetPayload { ids: Vec<String>, payload: serde_json::Value },
+    OverwritePayload { ids: Vec<String>, payload: serde_json::Value },
+    DeletePayload { ids: Vec<String>, keys: Vec<String> },
+    ClearPayload(Vec<String>),
+}
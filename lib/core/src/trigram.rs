@@ -0,0 +1,130 @@
+//! Trigram index for typo-tolerant keyword matching
+//!
+//! A `fuzzy` match accepts payload values within `max_edits` Levenshtein
+//! edits of the query. Comparing the query against every distinct value is
+//! slow on large fields, so keyword-indexed fields keep a [`TrigramIndex`]
+//! of their values. An edit changes at most three of a string's padded
+//! trigrams, so a value within `k` edits shares all but `3k` of the query's
+//! trigrams; only values passing that count are compared in full. Queries
+//! too short to leave any trigram after `3k` (e.g. 2 chars with 1 edit)
+//! fall back to comparing every value.
+
+use std::collections::{HashMap, HashSet};
+
+/// Pads both ends of a string so its first and last chars start trigrams
+const PAD: char = '\0';
+
+/// Distinct values of one payload field, by trigram
+#[derive(Debug, Clone, Default)]
+pub struct TrigramIndex {
+    postings: HashMap<[char; 3], HashSet<String>>,
+    values: HashSet<String>,
+}
+
+impl TrigramIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, value: &str) {
+        if !self.values.insert(value.to_string()) {
+            return;
+        }
+        for trigram in trigrams(value) {
+            self.postings.entry(trigram).or_default().insert(value.to_string());
+        }
+    }
+
+    /// Number of distinct values
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Values within `max_edits` edits of `query`, sorted
+    pub fn matches(&self, query: &str, max_edits: usize) -> Vec<String> {
+        let query_trigrams = trigrams(query);
+        let required = query_trigrams.len().saturating_sub(max_edits.saturating_mul(3));
+
+        let mut found: Vec<String> = if required == 0 {
+            self.values.iter()
+                .filter(|value| within_edits(query, value, max_edits))
+                .cloned()
+                .collect()
+        } else {
+            let mut shared: HashMap<&str, usize> = HashMap::new();
+            for trigram in &query_trigrams {
+                for value in self.postings.get(trigram).into_iter().flatten() {
+                    *shared.entry(value.as_str()).or_insert(0) += 1;
+                }
+            }
+            shared.into_iter()
+                .filter(|&(value, count)| count >= required && within_edits(query, value, max_edits))
+                .map(|(value, _)| value.to_string())
+                .collect()
+        };
+        found.sort();
+        found
+    }
+}
+
+/// Distinct padded trigrams of `value`
+fn trigrams(value: &str) -> HashSet<[char; 3]> {
+    let chars: Vec<char> = [PAD, PAD].into_iter()
+        .chain(value.chars())
+        .chain([PAD, PAD])
+        .collect();
+    chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// Whether the Levenshtein distance between `a` and `b` (in chars) is at
+/// most `max_edits`
+pub fn within_edits(a: &str, b: &str, max_edits: usize) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max_edits {
+        return false;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        // Distances never shrink from one row to the next
+        if current.iter().min().is_some_and(|&d| d > max_edits) {
+            return false;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()] <= max_edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_matches() {
+        assert!(within_edits("prosciuto", "prosciutto", 1));
+        assert!(within_edits("kitten", "sitting", 3));
+        assert!(!within_edits("kitten", "sitting", 2));
+        assert!(within_edits("", "ab", 2));
+
+        let mut index = TrigramIndex::new();
+        for value in ["prosciutto", "prosecco", "pancetta", "ox", "prosciutto"] {
+            index.insert(value);
+        }
+        assert_eq!(index.len(), 4);
+        assert_eq!(index.matches("prosciuto", 1), vec!["prosciutto"]);
+        assert_eq!(index.matches("prosciuto", 0), Vec::<String>::new());
+        assert_eq!(index.matches("pancetta", 0), vec!["pancetta"]);
+        // Too short for the trigram bound: every value is compared
+        assert_eq!(index.matches("ax", 1), vec!["ox"]);
+    }
+}
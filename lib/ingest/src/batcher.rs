@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use vectx_core::{Error, Point, UpsertStatus};
+use vectx_storage::{AuditEntry, StorageManager};
+
+/// Wait before retrying a failed flush, doubled on each failure up to
+/// [`RETRY_MAX_DELAY`]
+const RETRY_INITIAL_DELAY: Duration = Duration::from_millis(100);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Operation ingest upserts are recorded under in the audit log
+const AUDIT_OPERATION: &str = "Ingest/Upsert";
+
+/// Buffers mapped points and applies them as one batched upsert.
+///
+/// Offsets are only checkpointed after the batch has been upserted, which
+/// stores its points in the collection's point store, so a crash can replay
+/// messages but never skip them (at-least-once). Upserts go through the
+/// checks of the APIs' writes: they wait while `/locks` forbids writes or
+/// the collection has as many pending points as the storage admits, and
+/// are recorded in the audit log. A flush failing for a reason that passes,
+/// such as these or a failing store, is retried until it succeeds, so the
+/// source stalls instead of skipping the batch. A batch that would fail the
+/// same way every time, e.g. because its collection is gone, is dropped
+/// with an error and its offsets are checkpointed.
+pub struct Batcher {
+    storage: Arc<StorageManager>,
+    /// Name of the target collection, looked up on every flush
+    collection: String,
+    batch_size: usize,
+    points: Vec<Point>,
+    /// Highest offset seen per checkpoint key since the last flush
+    offsets: HashMap<String, u64>,
+}
+
+impl Batcher {
+    pub fn new(storage: Arc<StorageManager>, collection: impl Into<String>, batch_size: usize) -> Self {
+        Self {
+            storage,
+            collection: collection.into(),
+            batch_size: batch_size.max(1),
+            points: Vec::new(),
+            offsets: HashMap::new(),
+        }
+    }
+
+    /// Add a message's point (None if it could not be mapped) and record its offset
+    pub fn push(&mut self, point: Option<Point>, checkpoint_key: &str, offset: u64) {
+        if let Some(point) = point {
+            self.points.push(point);
+        }
+        let current = self.offsets.entry(checkpoint_key.to_string()).or_insert(offset);
+        *current = (*current).max(offset);
+    }
+
+    /// Whether enough points are buffered to flush
+    pub fn is_full(&self) -> bool {
+        self.points.len() >= self.batch_size
+    }
+
+    /// Number of buffered points
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty() && self.offsets.is_empty()
+    }
+
+    /// Upsert the buffered points and checkpoint the offsets, retrying with
+    /// backoff until both succeed, or dropping the points if they can never
+    /// be upserted. Each attempt looks the collection up again, so one
+    /// unloaded while a write waited is loaded back. Returns the number of
+    /// points upserted.
+    pub async fn flush(&mut self) -> usize {
+        let mut upserted = 0;
+        let mut attempt = 1;
+        let mut delay = RETRY_INITIAL_DELAY;
+        loop {
+            match self.try_flush(&mut upserted).await {
+                Ok(()) => return upserted,
+                Err(FlushError::Permanent(e)) => {
+                    tracing::error!(
+                        "Ingest dropped {} points for {}, which cannot take them: {}",
+                        self.points.len(), self.collection, e
+                    );
+                    self.points.clear();
+                }
+                Err(FlushError::Transient(e)) => {
+                    tracing::warn!(
+                        "Ingest flush into {} failed (attempt {}), retrying in {:?}: {}",
+                        self.collection, attempt, delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(RETRY_MAX_DELAY);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Upsert the buffered points, then checkpoint the offsets. Each step
+    /// drops what it wrote once it succeeds, so a retry after a failed
+    /// checkpoint does not upsert the points again.
+    async fn try_flush(&mut self, upserted: &mut usize) -> Result<(), FlushError> {
+        if !self.points.is_empty() {
+            let storage = self.storage.clone();
+            let name = self.collection.clone();
+            let points = self.points.clone();
+            *upserted = tokio::task::spawn_blocking(move || upsert(&storage, &name, points)).await??;
+            self.points.clear();
+        }
+
+        if !self.offsets.is_empty() {
+            let storage = self.storage.clone();
+            let offsets = self.offsets.clone();
+            tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+                for (key, offset) in offsets {
+                    storage.save_ingest_checkpoint(&key, offset)?;
+                }
+                Ok(())
+            })
+            .await??;
+            self.offsets.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Why a flush failed, which decides whether it is retried
+enum FlushError {
+    /// Retrying may succeed: writes are locked, the collection has too many
+    /// pending points or is being rebuilt, or storage failed
+    Transient(anyhow::Error),
+    /// Retrying would fail the same way, e.g. the collection is gone
+    Permanent(anyhow::Error),
+}
+
+/// Checkpoints failing to save, and upserts that panicked, are retried
+impl From<anyhow::Error> for FlushError {
+    fn from(error: anyhow::Error) -> Self {
+        Self::Transient(error)
+    }
+}
+
+impl From<tokio::task::JoinError> for FlushError {
+    fn from(error: tokio::task::JoinError) -> Self {
+        Self::Transient(error.into())
+    }
+}
+
+impl From<Error> for FlushError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Storage(_) | Error::Persistence(_) | Error::Io(_) | Error::Rebuilding => Self::Transient(error.into()),
+            error => Self::Permanent(error.into()),
+        }
+    }
+}
+
+/// Upsert `points` into the collection `name`, skipping the points it
+/// refuses, once writes are allowed and the collection admits them.
+/// Returns the number of points upserted.
+fn upsert(storage: &StorageManager, name: &str, points: Vec<Point>) -> Result<usize, FlushError> {
+    if let Some(message) = storage.write_lock_error() {
+        return Err(FlushError::Transient(anyhow::anyhow!(message)));
+    }
+    let Some(collection) = storage.get_collection(name) else {
+        // A collection being unloaded, or one that failed to load, may come
+        // back; only a deleted one never will
+        let error = anyhow::anyhow!("collection '{}' not found", name);
+        return Err(if storage.collection_exists(name) {
+            FlushError::Transient(error)
+        } else {
+            FlushError::Permanent(error)
+        });
+    };
+    if let Some(message) = storage.admission_error(&collection) {
+        return Err(FlushError::Transient(anyhow::anyhow!(message)));
+    }
+    let ids: Vec<String> = points.iter().map(|point| point.id.to_string()).collect();
+    let audited: Vec<serde_json::Value> = points.iter()
+        .filter_map(|point| serde_json::to_value(&point.id).ok())
+        .collect();
+    let statuses = collection.batch_upsert_lenient(points, None);
+    audit(storage, name, audited, statuses.is_ok());
+    let mut upserted = 0;
+    for (id, status) in ids.iter().zip(statuses?) {
+        match status {
+            UpsertStatus::Failed(e) => tracing::warn!("Ingest skipped point {} in {}: {}", id, name, e),
+            _ => upserted += 1,
+        }
+    }
+    Ok(upserted)
+}
+
+/// Record an upsert of the points `ids` in the audit log, if there is one
+fn audit(storage: &StorageManager, collection: &str, ids: Vec<serde_json::Value>, success: bool) {
+    let Some(log) = storage.audit_log() else { return };
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as i64);
+    log.record(&AuditEntry {
+        timestamp,
+        key_id: None,
+        api: "ingest".to_string(),
+        operation: AUDIT_OPERATION.to_string(),
+        collection: Some(collection.to_string()),
+        points: ids,
+        success,
+        status: None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vectx_core::{CollectionConfig, Distance, PointId, Vector};
+    use vectx_storage::{AuditLog, AuditQuery, Locks};
+
+    fn config(name: &str) -> CollectionConfig {
+        CollectionConfig {
+            name: name.to_string(),
+            vector_dim: 2,
+            distance: Distance::Cosine,
+            use_hnsw: false,
+            enable_bm25: false,
+        }
+    }
+
+    fn point(id: u64) -> Option<Point> {
+        Some(Point::new(PointId::Integer(id), Vector::new(vec![1.0, 0.0]), None))
+    }
+
+    #[tokio::test]
+    async fn test_flush_retries_until_it_succeeds() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(StorageManager::new(dir.path()).unwrap());
+        storage.create_collection(config("feed")).unwrap();
+        storage.set_audit_log(Some(AuditLog::new(dir.path().join("audit"), 1 << 20, 1).unwrap()));
+
+        // Writes are locked on the first attempts; nothing is checkpointed
+        // until the batch lands
+        storage.set_locks(Locks { write: true, error_message: None });
+        let mut batcher = Batcher::new(storage.clone(), "feed", 10);
+        batcher.push(point(1), "kafka:src:feed:0", 7);
+        let flush = tokio::spawn(async move { batcher.flush().await });
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(storage.ingest_checkpoint("kafka:src:feed:0").unwrap(), None);
+        assert!(storage.get_collection("feed").unwrap().get("1").is_none());
+
+        storage.set_locks(Locks::default());
+        assert_eq!(flush.await.unwrap(), 1);
+        assert!(storage.get_collection("feed").unwrap().get("1").is_some());
+        assert_eq!(storage.ingest_checkpoint("kafka:src:feed:0").unwrap(), Some(7));
+
+        let entries = storage.audit_log().unwrap().recent(&AuditQuery::default(), 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].api.as_str(), entries[0].operation.as_str()), ("ingest", AUDIT_OPERATION));
+        assert_eq!(entries[0].points, vec![serde_json::json!(1)]);
+        assert!(entries[0].success);
+    }
+
+    #[tokio::test]
+    async fn test_flush_drops_what_cannot_be_upserted() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(StorageManager::new(dir.path()).unwrap());
+
+        // A missing collection never takes the batch, so it is dropped and
+        // the source moves past it
+        let mut batcher = Batcher::new(storage.clone(), "gone", 10);
+        batcher.push(point(1), "nats:src:gone", 3);
+        let flushed = tokio::time::timeout(Duration::from_secs(5), batcher.flush()).await;
+        assert_eq!(flushed.unwrap(), 0);
+        assert!(batcher.is_empty());
+        assert_eq!(storage.ingest_checkpoint("nats:src:gone").unwrap(), Some(3));
+    }
+}
@@ -0,0 +1,75 @@
+use serde::Deserialize;
+use std::path::Path;
+use crate::mapping::FieldMapping;
+
+/// Top-level ingestion configuration (loaded from a JSON file)
+#[derive(Debug, Clone, Deserialize)]
+pub struct IngestConfig {
+    pub sources: Vec<SourceConfig>,
+}
+
+impl IngestConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+}
+
+/// One message source feeding one collection
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceConfig {
+    /// Unique source name, used as the checkpoint key prefix
+    pub name: String,
+    /// Target collection (must exist)
+    pub collection: String,
+    #[serde(flatten)]
+    pub kind: SourceKind,
+    #[serde(default)]
+    pub mapping: FieldMapping,
+    /// Upsert once this many messages are buffered
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Upsert buffered messages at least this often
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceKind {
+    Kafka {
+        brokers: String,
+        topic: String,
+        #[serde(default = "default_group_id")]
+        group_id: String,
+    },
+    /// NATS JetStream stream, optionally filtered to a subject
+    Nats {
+        url: String,
+        stream: String,
+        #[serde(default)]
+        subject: Option<String>,
+    },
+}
+
+impl SourceKind {
+    /// Connector (and cargo feature) name
+    pub fn connector(&self) -> &'static str {
+        match self {
+            SourceKind::Kafka { .. } => "kafka",
+            SourceKind::Nats { .. } => "nats",
+        }
+    }
+}
+
+fn default_batch_size() -> usize {
+    256
+}
+
+fn default_flush_interval_ms() -> u64 {
+    1000
+}
+
+fn default_group_id() -> String {
+    "vectx".to_string()
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::{Message, Offset, TopicPartitionList};
+use vectx_storage::StorageManager;
+use crate::batcher::Batcher;
+use crate::config::SourceConfig;
+
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Consume a Kafka topic into a collection.
+///
+/// Partitions are assigned manually and resumed from the offsets checkpointed
+/// in LMDB; Kafka's own consumer group offsets are not used.
+pub async fn run(
+    source: SourceConfig,
+    brokers: String,
+    topic: String,
+    group_id: String,
+    storage: Arc<StorageManager>,
+) -> anyhow::Result<()> {
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("bootstrap.servers", &brokers)
+        .set("group.id", &group_id)
+        .set("enable.auto.commit", "false")
+        .set("enable.partition.eof", "false")
+        .create()?;
+
+    let metadata = consumer.fetch_metadata(Some(&topic), METADATA_TIMEOUT)?;
+    let partitions: Vec<i32> = metadata
+        .topics()
+        .iter()
+        .filter(|t| t.name() == topic)
+        .flat_map(|t| t.partitions().iter().map(|p| p.id()))
+        .collect();
+    if partitions.is_empty() {
+        anyhow::bail!("Kafka topic '{}' has no partitions", topic);
+    }
+
+    let mut assignment = TopicPartitionList::new();
+    for &partition in &partitions {
+        let offset = match storage.ingest_checkpoint(&checkpoint_key(&source.name, &topic, partition))? {
+            Some(last) => Offset::Offset(last as i64 + 1),
+            None => Offset::Beginning,
+        };
+        assignment.add_partition_offset(&topic, partition, offset)?;
+    }
+    consumer.assign(&assignment)?;
+
+    let mut batcher = Batcher::new(storage, source.collection.clone(), source.batch_size);
+    let mut ticker = tokio::time::interval(Duration::from_millis(source.flush_interval_ms.max(1)));
+
+    loop {
+        tokio::select! {
+            message = consumer.recv() => {
+                let message = message?;
+                let point = match message.payload() {
+                    Some(bytes) => match source.mapping.to_point(bytes) {
+                        Ok(point) => Some(point),
+                        Err(e) => {
+                            tracing::warn!("Ingest source {} skipped message at {}/{}: {}",
+                                source.name, message.partition(), message.offset(), e);
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                let key = checkpoint_key(&source.name, &topic, message.partition());
+                batcher.push(point, &key, message.offset() as u64);
+                if batcher.is_full() {
+                    batcher.flush().await;
+                }
+            }
+            _ = ticker.tick() => {
+                batcher.flush().await;
+            }
+        }
+    }
+}
+
+fn checkpoint_key(source: &str, topic: &str, partition: i32) -> String {
+    format!("kafka:{}:{}:{}", source, topic, partition)
+}
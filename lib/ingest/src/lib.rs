@@ -0,0 +1,85 @@
+//! # vectX Ingest
+//!
+//! Streaming ingestion for vectX: consumes JSON messages from Kafka topics or
+//! NATS JetStream streams, maps them to points and applies batched upserts.
+//!
+//! Delivery is at-least-once: source offsets are checkpointed in LMDB only
+//! after a batch has been upserted into the collection's point store, and
+//! consumption resumes from the last checkpoint on restart.
+//!
+//! Connectors are behind the `kafka` and `nats` cargo features.
+
+pub mod batcher;
+pub mod config;
+pub mod mapping;
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "nats")]
+pub mod nats;
+
+pub use batcher::Batcher;
+pub use config::{IngestConfig, SourceConfig, SourceKind};
+pub use mapping::FieldMapping;
+
+use std::sync::Arc;
+use vectx_storage::StorageManager;
+
+/// Spawn one task per configured source.
+///
+/// Fails without spawning anything if a target collection does not exist or a
+/// source needs a connector this build was compiled without.
+pub fn start(config: IngestConfig, storage: Arc<StorageManager>) -> anyhow::Result<Vec<tokio::task::JoinHandle<()>>> {
+    let mut sources = Vec::with_capacity(config.sources.len());
+    for source in config.sources {
+        if storage.get_collection(&source.collection).is_none() {
+            anyhow::bail!("Ingest source '{}': collection '{}' not found", source.name, source.collection);
+        }
+        if !connector_enabled(&source.kind) {
+            anyhow::bail!(
+                "Ingest source '{}': this build has no {} connector (enable the '{}' feature)",
+                source.name, source.kind.connector(), source.kind.connector()
+            );
+        }
+        sources.push(source);
+    }
+
+    Ok(sources
+        .into_iter()
+        .map(|source| {
+            let storage = storage.clone();
+            tokio::spawn(async move {
+                let name = source.name.clone();
+                if let Err(e) = run_source(source, storage).await {
+                    tracing::error!("Ingest source {} stopped: {}", name, e);
+                }
+            })
+        })
+        .collect())
+}
+
+fn connector_enabled(kind: &SourceKind) -> bool {
+    match kind {
+        SourceKind::Kafka { .. } => cfg!(feature = "kafka"),
+        SourceKind::Nats { .. } => cfg!(feature = "nats"),
+    }
+}
+
+#[allow(unused_variables)]
+async fn run_source(
+    source: SourceConfig,
+    storage: Arc<StorageManager>,
+) -> anyhow::Result<()> {
+    match source.kind.clone() {
+        #[cfg(feature = "kafka")]
+        SourceKind::Kafka { brokers, topic, group_id } => {
+            kafka::run(source, brokers, topic, group_id, storage).await
+        }
+        #[cfg(feature = "nats")]
+        SourceKind::Nats { url, stream, subject } => {
+            nats::run(source, url, stream, subject, storage).await
+        }
+        #[allow(unreachable_patterns)]
+        other => anyhow::bail!("no {} connector in this build", other.connector()),
+    }
+}
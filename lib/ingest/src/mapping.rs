@@ -0,0 +1,147 @@
+use serde::Deserialize;
+use vectx_core::{Point, PointId, Vector};
+
+/// Where to find the point id, vector and payload in a JSON message
+///
+/// Field names may be dotted paths into nested objects (e.g. `"meta.id"`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldMapping {
+    #[serde(default = "default_id_field")]
+    pub id: String,
+    #[serde(default = "default_vector_field")]
+    pub vector: String,
+    /// Field holding the payload object. When unset, the message without its
+    /// id and vector fields becomes the payload.
+    #[serde(default)]
+    pub payload: Option<String>,
+}
+
+impl Default for FieldMapping {
+    fn default() -> Self {
+        Self {
+            id: default_id_field(),
+            vector: default_vector_field(),
+            payload: None,
+        }
+    }
+}
+
+fn default_id_field() -> String {
+    "id".to_string()
+}
+
+fn default_vector_field() -> String {
+    "vector".to_string()
+}
+
+impl FieldMapping {
+    /// Build a point from a raw JSON message
+    pub fn to_point(&self, message: &[u8]) -> Result<Point, String> {
+        let value: serde_json::Value = serde_json::from_slice(message)
+            .map_err(|e| format!("invalid JSON: {}", e))?;
+
+        let id = match get_path(&value, &self.id) {
+            Some(serde_json::Value::Number(n)) => n.as_u64()
+                .map(PointId::Integer)
+                .ok_or_else(|| format!("id '{}' must be an unsigned integer or string", n))?,
+            Some(serde_json::Value::String(s)) => match uuid::Uuid::parse_str(s) {
+                Ok(u) => PointId::Uuid(u),
+                Err(_) => PointId::String(s.clone()),
+            },
+            _ => return Err(format!("missing id field '{}'", self.id)),
+        };
+
+        let vector: Vec<f32> = match get_path(&value, &self.vector) {
+            Some(serde_json::Value::Array(arr)) => arr.iter()
+                .map(|v| v.as_f64().map(|f| f as f32))
+                .collect::<Option<Vec<f32>>>()
+                .ok_or_else(|| format!("vector field '{}' must contain only numbers", self.vector))?,
+            _ => return Err(format!("missing vector field '{}'", self.vector)),
+        };
+
+        let payload = match &self.payload {
+            Some(field) => get_path(&value, field).cloned(),
+            None => {
+                let mut value = value;
+                remove_path(&mut value, &self.id);
+                remove_path(&mut value, &self.vector);
+                match value {
+                    serde_json::Value::Object(obj) if !obj.is_empty() => Some(serde_json::Value::Object(obj)),
+                    _ => None,
+                }
+            }
+        };
+
+        Ok(Point::new(id, Vector::new(vector), payload))
+    }
+}
+
+fn get_path<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    path.split('.').try_fold(value, |current, key| current.get(key))
+}
+
+/// Remove the field at a dotted `path`, leaving its parent objects in place
+fn remove_path(value: &mut serde_json::Value, path: &str) {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (parent.split('.').try_fold(value, |current, key| current.get_mut(key)), key),
+        None => (Some(value), path),
+    };
+    if let Some(serde_json::Value::Object(obj)) = parent {
+        obj.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_mapping() {
+        let mapping = FieldMapping::default();
+        let point = mapping
+            .to_point(br#"{"id": 7, "vector": [0.1, 0.2], "title": "hello"}"#)
+            .unwrap();
+
+        assert_eq!(point.id, PointId::Integer(7));
+        assert_eq!(point.vector.as_slice(), &[0.1, 0.2]);
+        assert_eq!(point.payload, Some(serde_json::json!({"title": "hello"})));
+    }
+
+    #[test]
+    fn test_nested_mapping() {
+        let mapping = FieldMapping {
+            id: "meta.key".to_string(),
+            vector: "embedding".to_string(),
+            payload: Some("doc".to_string()),
+        };
+        let point = mapping
+            .to_point(br#"{"meta": {"key": "doc-1"}, "embedding": [1, 0], "doc": {"lang": "en"}}"#)
+            .unwrap();
+
+        assert_eq!(point.id, PointId::String("doc-1".to_string()));
+        assert_eq!(point.payload, Some(serde_json::json!({"lang": "en"})));
+    }
+
+    #[test]
+    fn test_nested_fields_left_out_of_payload() {
+        let mapping = FieldMapping {
+            id: "meta.id".to_string(),
+            vector: "data.embedding".to_string(),
+            payload: None,
+        };
+        let point = mapping
+            .to_point(br#"{"meta": {"id": 3, "lang": "en"}, "data": {"embedding": [1, 0]}, "title": "hello"}"#)
+            .unwrap();
+
+        assert_eq!(point.id, PointId::Integer(3));
+        assert_eq!(point.payload, Some(serde_json::json!({"meta": {"lang": "en"}, "data": {}, "title": "hello"})));
+    }
+
+    #[test]
+    fn test_invalid_messages() {
+        let mapping = FieldMapping::default();
+        assert!(mapping.to_point(b"not json").is_err());
+        assert!(mapping.to_point(br#"{"vector": [1.0]}"#).is_err());
+        assert!(mapping.to_point(br#"{"id": 1, "vector": ["a"]}"#).is_err());
+    }
+}
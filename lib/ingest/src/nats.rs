@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use std::time::Duration;
+use async_nats::jetstream::{self, consumer::DeliverPolicy};
+use futures_util::StreamExt;
+use vectx_storage::StorageManager;
+use crate::batcher::Batcher;
+use crate::config::SourceConfig;
+
+/// Consume a NATS JetStream stream into a collection.
+///
+/// Uses an ordered (ephemeral) consumer that starts after the stream sequence
+/// checkpointed in LMDB, so no durable consumer state lives on the server.
+pub async fn run(
+    source: SourceConfig,
+    url: String,
+    stream_name: String,
+    subject: Option<String>,
+    storage: Arc<StorageManager>,
+) -> anyhow::Result<()> {
+    let client = async_nats::connect(&url).await?;
+    let context = jetstream::new(client);
+    let stream = context.get_stream(&stream_name).await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let key = format!("nats:{}:{}", source.name, stream_name);
+    let deliver_policy = match storage.ingest_checkpoint(&key)? {
+        Some(last) => DeliverPolicy::ByStartSequence { start_sequence: last + 1 },
+        None => DeliverPolicy::All,
+    };
+
+    let consumer = stream
+        .create_consumer(jetstream::consumer::pull::OrderedConfig {
+            filter_subject: subject.unwrap_or_default(),
+            deliver_policy,
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let mut messages = consumer.messages().await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let mut batcher = Batcher::new(storage, source.collection.clone(), source.batch_size);
+    let mut ticker = tokio::time::interval(Duration::from_millis(source.flush_interval_ms.max(1)));
+
+    loop {
+        tokio::select! {
+            message = messages.next() => {
+                let Some(message) = message else {
+                    batcher.flush().await;
+                    anyhow::bail!("NATS stream '{}' closed", stream_name);
+                };
+                let message = message.map_err(|e| anyhow::anyhow!("{}", e))?;
+                let sequence = message.info()
+                    .map_err(|e| anyhow::anyhow!("{}", e))?
+                    .stream_sequence;
+                let point = match source.mapping.to_point(&message.payload) {
+                    Ok(point) => Some(point),
+                    Err(e) => {
+                        tracing::warn!("Ingest source {} skipped message {}: {}", source.name, sequence, e);
+                        None
+                    }
+                };
+                batcher.push(point, &key, sequence);
+                if batcher.is_full() {
+                    batcher.flush().await;
+                }
+            }
+            _ = ticker.tick() => {
+                batcher.flush().await;
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! API keys and per-collection access control
+//!
+//! A single master key (set at startup) grants full access. Additional keys
+//! are created at runtime, each scoped to a set of collections and a
+//! [`Permission`] level, and persisted to `api_keys.json` in the data
+//! directory. Only SHA-256 hashes of the secrets are stored.
+//!
+//! Clients may also present a JWT (HS256, signed with the master key) in place
+//! of a key. Its claims follow Qdrant's format: `access` is either `"r"`
+//! (read everything), `"m"` (manage everything) or a list of
+//! `{"collection", "access": "r" | "rw", "payload"}` entries. A `payload`
+//! object is a forced filter: the server restricts every operation on that
+//! collection to points whose payload matches all of its key/value pairs.
+//!
+//! Access control is enforced once a master key is configured or at least one
+//! key exists; otherwise every request is allowed.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use crate::audit::{JWT_KEY_ID, MASTER_KEY_ID};
+
+/// Operation level granted by a key. Each level includes the ones below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Search, scroll, retrieve and inspect
+    Read,
+    /// Read plus point and payload mutations and field indexes
+    Write,
+    /// Write plus creating, updating and deleting collections and snapshots
+    Admin,
+}
+
+/// A stored API key (the secret itself is never kept)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    /// Collections the key applies to. Empty means every collection; an entry
+    /// ending in `*` matches by prefix.
+    #[serde(default)]
+    pub collections: Vec<String>,
+    pub permission: Permission,
+    /// Seconds since the Unix epoch
+    pub created_at: i64,
+    key_hash: String,
+}
+
+impl ApiKey {
+    /// Whether the key may access `collection` (all collections when None)
+    pub fn covers(&self, collection: Option<&str>) -> bool {
+        if self.collections.is_empty() {
+            return true;
+        }
+        let Some(collection) = collection else { return false };
+        self.collections.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => collection.starts_with(prefix),
+            None => pattern == collection,
+        })
+    }
+
+    /// Whether the key allows `required` on `collection`
+    pub fn allows(&self, collection: Option<&str>, required: Permission) -> bool {
+        self.permission >= required && self.covers(collection)
+    }
+}
+
+/// Claims of a JWT accepted in place of an API key
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtClaims {
+    /// Expiry (seconds since the Unix epoch); tokens without it do not expire
+    #[serde(default)]
+    pub exp: Option<u64>,
+    pub access: JwtAccess,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum JwtAccess {
+    Global(GlobalAccess),
+    Collections(Vec<CollectionAccess>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum GlobalAccess {
+    #[serde(rename = "r")]
+    Read,
+    #[serde(rename = "m")]
+    Manage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum CollectionAccessLevel {
+    #[serde(rename = "r")]
+    Read,
+    #[serde(rename = "rw")]
+    ReadWrite,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CollectionAccess {
+    pub collection: String,
+    pub access: CollectionAccessLevel,
+    /// Payload values every accessed point must match
+    #[serde(default)]
+    pub payload: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl JwtClaims {
+    /// Whether the claims allow `required` on `collection`; returns the forced
+    /// payload filter, if any
+    fn allows(
+        &self,
+        collection: Option<&str>,
+        required: Permission,
+    ) -> Option<Option<serde_json::Map<String, serde_json::Value>>> {
+        match &self.access {
+            JwtAccess::Global(GlobalAccess::Manage) => Some(None),
+            JwtAccess::Global(GlobalAccess::Read) => (required == Permission::Read).then_some(None),
+            JwtAccess::Collections(entries) => {
+                let collection = collection?;
+                let entry = entries.iter().find(|e| e.collection == collection)?;
+                let granted = match entry.access {
+                    CollectionAccessLevel::Read => Permission::Read,
+                    CollectionAccessLevel::ReadWrite => Permission::Write,
+                };
+                (granted >= required).then(|| entry.payload.clone().filter(|p| !p.is_empty()))
+            }
+        }
+    }
+}
+
+/// Result of a successful access check
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccessGrant {
+    /// Forced payload filter from a JWT claim; operations must be restricted
+    /// to points whose payload matches every key/value pair
+    pub payload: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AccessError {
+    #[error("Missing or invalid API key")]
+    Unauthenticated,
+
+    #[error("API key is not allowed to perform this operation")]
+    Forbidden,
+}
+
+/// Master key plus the persisted set of scoped keys
+pub struct AccessControl {
+    path: PathBuf,
+    master_key_hash: RwLock<Option<String>>,
+    /// The master key itself, used to verify JWT signatures
+    jwt_secret: RwLock<Option<String>>,
+    /// key_hash -> key
+    keys: RwLock<HashMap<String, ApiKey>>,
+}
+
+impl AccessControl {
+    /// Load keys from `path` (starts empty if the file does not exist)
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let keys: Vec<ApiKey> = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path,
+            master_key_hash: RwLock::new(None),
+            jwt_secret: RwLock::new(None),
+            keys: RwLock::new(keys.into_iter().map(|k| (k.key_hash.clone(), k)).collect()),
+        })
+    }
+
+    /// Set (or clear) the key that grants full access and signs JWTs
+    pub fn set_master_key(&self, key: Option<&str>) {
+        let key = key.filter(|k| !k.is_empty());
+        *self.master_key_hash.write() = key.map(hash_key);
+        *self.jwt_secret.write() = key.map(str::to_string);
+    }
+
+    /// Whether requests have to present a key
+    pub fn is_enabled(&self) -> bool {
+        self.master_key_hash.read().is_some() || !self.keys.read().is_empty()
+    }
+
+    /// Create a key. Returns the stored key and its secret, which is not
+    /// retrievable afterwards.
+    pub fn create_key(
+        &self,
+        name: &str,
+        collections: Vec<String>,
+        permission: Permission,
+    ) -> anyhow::Result<(ApiKey, String)> {
+        let secret = format!("vx_{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        let key = ApiKey {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            collections,
+            permission,
+            created_at: chrono::Utc::now().timestamp(),
+            key_hash: hash_key(&secret),
+        };
+
+        let mut keys = self.keys.write();
+        keys.insert(key.key_hash.clone(), key.clone());
+        self.persist(&keys)?;
+        Ok((key, secret))
+    }
+
+    /// All keys, oldest first
+    pub fn list_keys(&self) -> Vec<ApiKey> {
+        let mut keys: Vec<ApiKey> = self.keys.read().values().cloned().collect();
+        keys.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        keys
+    }
+
+    pub fn get_key(&self, id: &str) -> Option<ApiKey> {
+        self.keys.read().values().find(|k| k.id == id).cloned()
+    }
+
+    /// Revoke a key by id
+    pub fn delete_key(&self, id: &str) -> anyhow::Result<bool> {
+        let mut keys = self.keys.write();
+        let before = keys.len();
+        keys.retain(|_, k| k.id != id);
+        if keys.len() == before {
+            return Ok(false);
+        }
+        self.persist(&keys)?;
+        Ok(true)
+    }
+
+    /// Id of the key `secret` is, for the audit log: the id of a stored key,
+    /// [`MASTER_KEY_ID`] or [`JWT_KEY_ID`]. None for anything else, and
+    /// while access control is off.
+    pub fn identify(&self, secret: Option<&str>) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let secret = secret?;
+        let hash = hash_key(secret);
+        if self.master_key_hash.read().as_deref() == Some(hash.as_str()) {
+            return Some(MASTER_KEY_ID.to_string());
+        }
+        if let Some(key) = self.keys.read().get(&hash) {
+            return Some(key.id.clone());
+        }
+        self.decode_jwt(secret).map(|_| JWT_KEY_ID.to_string())
+    }
+
+    /// Check that `secret` (an API key or JWT) may perform an operation
+    /// needing `required` on `collection` (None for operations that are not
+    /// collection-scoped)
+    pub fn check(
+        &self,
+        secret: Option<&str>,
+        collection: Option<&str>,
+        required: Permission,
+    ) -> Result<AccessGrant, AccessError> {
+        if !self.is_enabled() {
+            return Ok(AccessGrant::default());
+        }
+        let secret = secret.ok_or(AccessError::Unauthenticated)?;
+        let hash = hash_key(secret);
+
+        if self.master_key_hash.read().as_deref() == Some(hash.as_str()) {
+            return Ok(AccessGrant::default());
+        }
+        if let Some(key) = self.keys.read().get(&hash) {
+            return if key.allows(collection, required) {
+                Ok(AccessGrant::default())
+            } else {
+                Err(AccessError::Forbidden)
+            };
+        }
+
+        let claims = self.decode_jwt(secret).ok_or(AccessError::Unauthenticated)?;
+        match claims.allows(collection, required) {
+            Some(payload) => Ok(AccessGrant { payload }),
+            None => Err(AccessError::Forbidden),
+        }
+    }
+
+    /// Verify a JWT against the master key
+    fn decode_jwt(&self, token: &str) -> Option<JwtClaims> {
+        if token.split('.').count() != 3 {
+            return None;
+        }
+        let secret = self.jwt_secret.read().clone()?;
+
+        let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::HS256);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = true;
+        jsonwebtoken::decode::<JwtClaims>(
+            token,
+            &jsonwebtoken::DecodingKey::from_secret(secret.as_bytes()),
+            &validation,
+        )
+        .ok()
+        .map(|data| data.claims)
+    }
+
+    fn persist(&self, keys: &HashMap<String, ApiKey>) -> anyhow::Result<()> {
+        let mut list: Vec<&ApiKey> = keys.values().collect();
+        list.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        let data = serde_json::to_vec_pretty(&list)?;
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = atomicwrites::AtomicFile::new(&self.path, atomicwrites::AllowOverwrite);
+        file.write(|f| std::io::Write::write_all(f, &data))
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", self.path.display(), e))?;
+        Ok(())
+    }
+}
+
+fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
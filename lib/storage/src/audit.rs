@@ -0,0 +1,258 @@
+//! Audit log of mutating requests
+//!
+//! Once an [`AuditLog`] is set on the storage manager, the APIs record each
+//! request that changes points, collections or the server's settings: who
+//! made it (the id of its API key), when, the operation, the collection and
+//! the points it names, and whether it succeeded. Entries are appended as
+//! JSON lines to `audit.log` in the log's directory. A file that grows past
+//! `max_file_bytes` is renamed `audit.log.1`, older files move up by one,
+//! and the oldest beyond `max_files` is deleted.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the current audit file
+const FILE_NAME: &str = "audit.log";
+
+/// Largest audit file before it is rotated
+pub const DEFAULT_AUDIT_FILE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Rotated audit files kept by default
+pub const DEFAULT_AUDIT_FILES: usize = 10;
+
+/// Id recorded for requests made with the master key
+pub const MASTER_KEY_ID: &str = "master";
+
+/// Id recorded for requests made with a JWT
+pub const JWT_KEY_ID: &str = "jwt";
+
+/// One recorded request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AuditEntry {
+    /// Milliseconds since the Unix epoch
+    pub timestamp: i64,
+    /// Id of the API key, [`MASTER_KEY_ID`] or [`JWT_KEY_ID`]; None when
+    /// access control is off or the request carried no valid key
+    pub key_id: Option<String>,
+    /// `rest`, `grpc` or `ingest`
+    pub api: String,
+    /// Method and path of a REST request, e.g. `PUT /collections/docs/points`,
+    /// or the gRPC method, e.g. `Points/Upsert`
+    pub operation: String,
+    pub collection: Option<String>,
+    /// Ids of the points the request names; empty when it selects points
+    /// by filter or names none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub points: Vec<serde_json::Value>,
+    pub success: bool,
+    /// HTTP status of a REST request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+}
+
+/// Which entries [`AuditLog::recent`] returns
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditQuery {
+    pub collection: Option<String>,
+    pub key_id: Option<String>,
+    /// Part of the operation, e.g. `points` or `DELETE`
+    pub operation: Option<String>,
+    /// Only entries at or after this many milliseconds since the epoch
+    pub since: Option<i64>,
+    /// Only entries of successful requests, or only of failed ones
+    pub success: Option<bool>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.collection.as_ref().map_or(true, |c| entry.collection.as_ref() == Some(c))
+            && self.key_id.as_ref().map_or(true, |k| entry.key_id.as_ref() == Some(k))
+            && self.operation.as_ref().map_or(true, |o| entry.operation.contains(o.as_str()))
+            && self.since.map_or(true, |since| entry.timestamp >= since)
+            && self.success.map_or(true, |success| entry.success == success)
+    }
+}
+
+struct Writer {
+    file: Option<File>,
+    size: u64,
+}
+
+/// Rotated JSON lines files of [`AuditEntry`]
+pub struct AuditLog {
+    dir: PathBuf,
+    max_file_bytes: u64,
+    max_files: usize,
+    writer: Mutex<Writer>,
+}
+
+impl AuditLog {
+    /// An audit log in `dir`, appending to the entries already there
+    pub fn new<P: AsRef<Path>>(dir: P, max_file_bytes: u64, max_files: usize) -> anyhow::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let size = fs::metadata(dir.join(FILE_NAME)).map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            dir,
+            max_file_bytes: max_file_bytes.max(1),
+            max_files,
+            writer: Mutex::new(Writer { file: None, size }),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The current file, then its rotations from newest to oldest
+    fn path(&self, rotation: usize) -> PathBuf {
+        match rotation {
+            0 => self.dir.join(FILE_NAME),
+            n => self.dir.join(format!("{}.{}", FILE_NAME, n)),
+        }
+    }
+
+    /// Append `entry`. A failing write is logged, not returned: the request
+    /// it records has already been carried out.
+    pub fn record(&self, entry: &AuditEntry) {
+        if let Err(e) = self.append(entry) {
+            tracing::warn!("Failed to write audit entry to {:?}: {}", self.dir, e);
+        }
+    }
+
+    fn append(&self, entry: &AuditEntry) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut writer = self.writer.lock();
+        if writer.size > 0 && writer.size + line.len() as u64 > self.max_file_bytes {
+            writer.file = None;
+            self.rotate()?;
+            writer.size = 0;
+        }
+        if writer.file.is_none() {
+            writer.file = Some(OpenOptions::new().create(true).append(true).open(self.path(0))?);
+        }
+        if let Some(file) = writer.file.as_mut() {
+            file.write_all(&line)?;
+        }
+        writer.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift every file up by one rotation, dropping the oldest
+    fn rotate(&self) -> std::io::Result<()> {
+        if self.max_files == 0 {
+            return fs::remove_file(self.path(0));
+        }
+        let oldest = self.path(self.max_files);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for rotation in (0..self.max_files).rev() {
+            let path = self.path(rotation);
+            if path.exists() {
+                fs::rename(path, self.path(rotation + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` entries matching `query`, newest first. Lines that do
+    /// not parse, e.g. a torn last write, are skipped. The files are opened
+    /// under the writer's lock, so that none is rotated halfway, and read
+    /// backwards without it, up to their length when opened, until `limit`
+    /// entries are found.
+    pub fn recent(&self, query: &AuditQuery, limit: usize) -> anyhow::Result<Vec<AuditEntry>> {
+        let mut files = Vec::new();
+        {
+            let _writer = self.writer.lock();
+            for rotation in 0..=self.max_files {
+                let file = match File::open(self.path(rotation)) {
+                    Ok(file) => file,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+                    Err(e) => return Err(e.into()),
+                };
+                let len = file.metadata()?.len();
+                files.push((file, len));
+            }
+        }
+
+        let mut found = Vec::new();
+        for (file, len) in files {
+            for line in RevLines::new(file, len) {
+                if found.len() >= limit {
+                    return Ok(found);
+                }
+                if let Ok(entry) = serde_json::from_slice::<AuditEntry>(&line?) {
+                    if query.matches(&entry) {
+                        found.push(entry);
+                    }
+                }
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Bytes read at a time by [`RevLines`]
+const REV_CHUNK_BYTES: u64 = 64 * 1024;
+
+/// The lines of the first `len` bytes of a file, last first
+struct RevLines {
+    file: File,
+    /// Bytes before `pending` still to be read
+    pos: u64,
+    /// Read bytes not yet returned as lines
+    pending: Vec<u8>,
+}
+
+impl RevLines {
+    fn new(file: File, len: u64) -> Self {
+        Self { file, pos: len, pending: Vec::new() }
+    }
+}
+
+impl Iterator for RevLines {
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // A complete line follows the last newline, barring the one
+            // that ends the file
+            let end = self.pending.len().saturating_sub(1);
+            if let Some(newline) = self.pending[..end].iter().rposition(|&b| b == b'\n') {
+                let mut line = self.pending.split_off(newline + 1);
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                }
+                return Some(Ok(line));
+            }
+            if self.pos == 0 {
+                if self.pending.is_empty() {
+                    return None;
+                }
+                let mut line = std::mem::take(&mut self.pending);
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                }
+                return Some(Ok(line));
+            }
+            let read = REV_CHUNK_BYTES.min(self.pos);
+            self.pos -= read;
+            let mut chunk = vec![0; read as usize];
+            let result = self.file.seek(SeekFrom::Start(self.pos))
+                .and_then(|_| self.file.read_exact(&mut chunk));
+            if let Err(e) = result {
+                self.pos = 0;
+                self.pending.clear();
+                return Some(Err(e));
+            }
+            chunk.append(&mut self.pending);
+            self.pending = chunk;
+        }
+    }
+}
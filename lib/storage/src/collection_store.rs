@@ -0,0 +1,408 @@
+// Per-collection storage directories
+//
+// Each collection lives in `data/collections/<name>/`:
+//
+//   manifest.json   name, configuration and format version
+//   points/         LMDB environment with one record per point
+//
+// Manifests are small and read for every collection on startup; the points
+// environment is only opened when the collection is loaded, so idle
+// collections cost neither memory nor file handles.
+use anyhow::{anyhow, bail, Result};
+use heed::{CompactionOption, Database, Env, EnvOpenOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use vectx_core::{MultiVector, Point, PointId, PointStore, SparseVector, Vector};
+use crate::snapshot::CollectionConfigData;
+
+/// Version of the directory layout written by this build
+pub const FORMAT_VERSION: u32 = 1;
+
+pub(crate) const MANIFEST_FILE: &str = "manifest.json";
+pub(crate) const POINTS_DIR: &str = "points";
+/// Compacted copy of the points environment, while it is being written
+pub(crate) const COMPACTING_DIR: &str = "points.compact";
+/// The points environment a compacted copy replaces, until it is removed
+pub(crate) const REPLACED_DIR: &str = "points.old";
+const DB_POINTS: &str = "points";
+
+/// Address space reserved for one collection's points. LMDB only maps what
+/// is written, but the reservation bounds the collection's size.
+const MAP_SIZE: usize = 16 * 1024 * 1024 * 1024;
+
+/// Longest collection name accepted, in bytes
+pub const MAX_COLLECTION_NAME_BYTES: usize = 255;
+
+/// Refuse a collection name that is not a plain directory name. Names
+/// become paths under the data directory, so anything that could leave
+/// it, e.g. `..` or a path separator, is an error. Other names, such as
+/// `docs.v2`, are accepted as Qdrant does.
+pub fn check_collection_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > MAX_COLLECTION_NAME_BYTES {
+        bail!("invalid collection name {:?}: use 1 to {} bytes", name, MAX_COLLECTION_NAME_BYTES);
+    }
+    if name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+        bail!("invalid collection name {:?}: it cannot be '.' or '..' or contain '/', '\\' or NUL", name);
+    }
+    Ok(())
+}
+
+/// Description of a collection, stored next to its points
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionManifest {
+    pub format_version: u32,
+    pub name: String,
+    pub config: CollectionConfigData,
+}
+
+impl CollectionManifest {
+    pub fn new(name: &str, config: CollectionConfigData) -> Self {
+        Self { format_version: FORMAT_VERSION, name: name.to_string(), config }
+    }
+
+    /// Read `dir/manifest.json`
+    pub fn read(dir: &Path) -> Result<Self> {
+        let data = std::fs::read(dir.join(MANIFEST_FILE))?;
+        let manifest: Self = serde_json::from_slice(&data)?;
+        if manifest.format_version > FORMAT_VERSION {
+            bail!(
+                "collection format version {} is newer than this build supports ({})",
+                manifest.format_version, FORMAT_VERSION
+            );
+        }
+        Ok(manifest)
+    }
+
+    /// Write `dir/manifest.json`, replacing any previous manifest atomically
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let data = serde_json::to_vec_pretty(self)?;
+        atomicwrites::AtomicFile::new(dir.join(MANIFEST_FILE), atomicwrites::AllowOverwrite)
+            .write(|f| f.write_all(&data))
+            .map_err(|e| anyhow!("failed to write manifest: {}", e))
+    }
+
+    /// Manifests of every collection directory under `root`. Directories
+    /// without a readable manifest are reported and skipped.
+    pub fn read_all(root: &Path) -> Result<Vec<Self>> {
+        let mut manifests = Vec::new();
+        if !root.exists() {
+            return Ok(manifests);
+        }
+        for entry in std::fs::read_dir(root)? {
+            let path = entry?.path();
+            if !path.join(MANIFEST_FILE).is_file() {
+                continue;
+            }
+            match Self::read(&path) {
+                Ok(manifest) if path.file_name() != Some(std::ffi::OsStr::new(&manifest.name)) => tracing::warn!(
+                    "Skipping collection directory {:?}: its manifest names collection {:?}",
+                    path, manifest.name
+                ),
+                Ok(manifest) => manifests.push(manifest),
+                Err(e) => tracing::warn!("Skipping collection directory {:?}: {}", path, e),
+            }
+        }
+        Ok(manifests)
+    }
+}
+
+/// The open points environment of one collection
+pub struct CollectionStore {
+    dir: PathBuf,
+    env: Env,
+    points_db: Database<heed::types::Str, heed::types::Bytes>,
+}
+
+impl CollectionStore {
+    /// Open (or create) the points environment in a collection directory,
+    /// first recovering from a compaction cut short. A directory that has
+    /// a manifest but no points environment is an error, rather than
+    /// opened as an empty collection.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        Self::recover_compaction(&dir)?;
+        let points_path = dir.join(POINTS_DIR);
+        if !points_path.is_dir() && dir.join(MANIFEST_FILE).is_file() {
+            bail!("points environment missing in {:?}", dir);
+        }
+        std::fs::create_dir_all(&points_path)?;
+
+        let env = Self::open_env(&points_path)?;
+        let mut wtxn = env.write_txn()?;
+        let points_db = env.create_database(&mut wtxn, Some(DB_POINTS))?;
+        wtxn.commit()?;
+
+        Ok(Self { dir, env, points_db })
+    }
+
+    /// Open the points environment of a collection directory as it is,
+    /// without recovering or creating anything, e.g. to check it
+    pub(crate) fn open_existing(dir: &Path) -> Result<Self> {
+        let points_path = dir.join(POINTS_DIR);
+        if !points_path.is_dir() {
+            bail!("points environment missing in {:?}", dir);
+        }
+        let env = Self::open_env(&points_path)?;
+        let rtxn = env.read_txn()?;
+        let points_db = env.open_database(&rtxn, Some(DB_POINTS))?
+            .ok_or_else(|| anyhow!("points database missing in {:?}", dir))?;
+        // Committed, so the handle outlives the transaction
+        rtxn.commit()?;
+        Ok(Self { dir: dir.to_path_buf(), env, points_db })
+    }
+
+    fn open_env(points_path: &Path) -> Result<Env> {
+        let env = unsafe {
+            EnvOpenOptions::new()
+                .map_size(MAP_SIZE)
+                .max_dbs(1)
+                .open(points_path)?
+        };
+        Ok(env)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn save_point(&self, point: &Point) -> Result<()> {
+        let data = PointRecord::encode(point)?;
+        let mut wtxn = self.env.write_txn()?;
+        self.points_db.put(&mut wtxn, &point.id.to_string(), &data)?;
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    pub fn delete_point(&self, point_id: &str) -> Result<bool> {
+        let mut wtxn = self.env.write_txn()?;
+        let existed = self.points_db.delete(&mut wtxn, point_id)?;
+        wtxn.commit()?;
+        Ok(existed)
+    }
+
+    /// Write and delete several point records in one transaction
+    pub fn apply(&self, puts: &[Point], removes: &[String]) -> Result<()> {
+        let mut wtxn = self.env.write_txn()?;
+        for point in puts {
+            self.points_db.put(&mut wtxn, &point.id.to_string(), &PointRecord::encode(point)?)?;
+        }
+        for id in removes {
+            self.points_db.delete(&mut wtxn, id)?;
+        }
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    /// Every point record, read in one transaction
+    pub fn load_points(&self) -> Result<Vec<Point>> {
+        let rtxn = self.env.read_txn()?;
+        let mut points = Vec::new();
+        for result in self.points_db.iter(&rtxn)? {
+            let (_, data) = result?;
+            points.push(PointRecord::decode(data)?);
+        }
+        Ok(points)
+    }
+
+    /// Decode every point record in one read transaction, passing each key
+    /// with its point or the decoding error
+    pub fn scan(&self, mut visit: impl FnMut(&str, Result<Point>)) -> Result<()> {
+        let rtxn = self.env.read_txn()?;
+        for result in self.points_db.iter(&rtxn)? {
+            let (key, data) = result?;
+            visit(key, PointRecord::decode(data));
+        }
+        Ok(())
+    }
+
+    /// Replace all point records in one transaction
+    pub fn replace_points<'a>(&self, points: impl IntoIterator<Item = &'a Point>) -> Result<()> {
+        let mut wtxn = self.env.write_txn()?;
+        self.points_db.clear(&mut wtxn)?;
+        for point in points {
+            self.points_db.put(&mut wtxn, &point.id.to_string(), &PointRecord::encode(point)?)?;
+        }
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    /// Flush written pages to disk
+    pub fn sync(&self) -> Result<()> {
+        self.env.force_sync()?;
+        Ok(())
+    }
+
+    /// Write a compacted, consistent copy of the points environment to
+    /// `path`. LMDB copies from a read transaction, so writers are not blocked.
+    pub fn copy_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.env.copy_to_path(path, CompactionOption::Enabled)?;
+        Ok(())
+    }
+
+    /// Replace the points environment in a collection directory with a
+    /// compacted copy, returning its size before and after in bytes. The
+    /// environment must not be open anywhere else. A crash leaves the copy
+    /// in `points.compact`, or the old environment in `points.old`;
+    /// [`CollectionStore::open`] recovers from either.
+    pub fn compact_dir<P: AsRef<Path>>(dir: P) -> Result<(u64, u64)> {
+        let dir = dir.as_ref();
+        let points = dir.join(POINTS_DIR);
+        if !points.is_dir() && !dir.join(REPLACED_DIR).is_dir() {
+            bail!("points environment missing in {:?}", dir);
+        }
+        let store = Self::open(dir)?;
+        let before = dir_size(&points)?;
+
+        let compacting = dir.join(COMPACTING_DIR);
+        std::fs::create_dir_all(&compacting)?;
+        store.copy_to(compacting.join("data.mdb"))?;
+        drop(store);
+        std::fs::File::open(compacting.join("data.mdb"))?.sync_all()?;
+
+        let replaced = dir.join(REPLACED_DIR);
+        std::fs::rename(&points, &replaced)?;
+        std::fs::rename(&compacting, &points)?;
+        std::fs::remove_dir_all(&replaced)?;
+        Ok((before, dir_size(&points)?))
+    }
+
+    /// Finish with a compaction of `dir` that a crash cut short. Without
+    /// `points`, the environment it replaced is moved back from
+    /// `points.old`. Once `points` exists, the copy or the replaced
+    /// environment left beside it is removed; `points.old` never is while
+    /// `points` is missing.
+    pub fn recover_compaction(dir: &Path) -> Result<()> {
+        let points = dir.join(POINTS_DIR);
+        let replaced = dir.join(REPLACED_DIR);
+        if !points.exists() && replaced.is_dir() {
+            tracing::warn!("Restoring the points environment of {:?} left by an interrupted compaction", dir);
+            std::fs::rename(&replaced, &points)?;
+        }
+        if points.is_dir() {
+            for leftover in [dir.join(COMPACTING_DIR), replaced] {
+                if leftover.exists() {
+                    std::fs::remove_dir_all(&leftover)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Total size of the files directly in `dir`
+fn dir_size(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let metadata = entry?.metadata()?;
+        if metadata.is_file() {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Stored form of a point: bincode, with the payload kept as JSON text.
+/// Named multivectors follow the record as a second bincode value, written
+/// only when the point has any, so records of earlier builds still decode.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PointRecord {
+    id: String,
+    version: u64,
+    vector: Vec<f32>,
+    multivector: Option<Vec<Vec<f32>>>,
+    sparse_vectors: HashMap<String, SparseVector>,
+    #[serde(with = "crate::persistence::json_text")]
+    payload: Option<serde_json::Value>,
+}
+
+impl PointRecord {
+    pub(crate) fn encode(point: &Point) -> Result<Vec<u8>> {
+        let record = PointRecord {
+            id: point.id.to_string(),
+            version: point.version,
+            vector: point.vector.as_slice().to_vec(),
+            multivector: point.multivector.as_ref().map(|mv| mv.vectors().to_vec()),
+            sparse_vectors: point.sparse_vectors.clone(),
+            payload: point.payload.clone(),
+        };
+        let mut data = bincode::serialize(&record)?;
+        if !point.named_multivectors.is_empty() {
+            let named: HashMap<&String, &[Vec<f32>]> = point.named_multivectors.iter()
+                .map(|(name, mv)| (name, mv.vectors()))
+                .collect();
+            bincode::serialize_into(&mut data, &named)?;
+        }
+        Ok(data)
+    }
+
+    pub(crate) fn decode(mut data: &[u8]) -> Result<Point> {
+        let record: PointRecord = bincode::deserialize_from(&mut data)?;
+        let named_multivectors = if data.is_empty() {
+            HashMap::new()
+        } else {
+            bincode::deserialize::<HashMap<String, Vec<Vec<f32>>>>(data)?
+                .into_iter()
+                .map(|(name, vectors)| MultiVector::new(vectors).map(|mv| (name, mv)))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(anyhow::Error::msg)?
+        };
+        let id = record.id.parse::<u64>()
+            .map(PointId::Integer)
+            .unwrap_or(PointId::String(record.id));
+        let multivector = record.multivector
+            .map(MultiVector::new)
+            .transpose()
+            .map_err(anyhow::Error::msg)?;
+        Ok(Point {
+            id,
+            version: record.version,
+            vector: Vector::new(record.vector),
+            multivector,
+            named_multivectors,
+            sparse_vectors: record.sparse_vectors,
+            payload: record.payload,
+        })
+    }
+}
+
+/// Mirrors the writes of one collection into its points environment. Holds
+/// the store weakly, so unloading the collection or dropping the storage
+/// manager still closes it; writes after that fail.
+pub struct LmdbPointStore {
+    store: Weak<CollectionStore>,
+    collection: String,
+}
+
+impl LmdbPointStore {
+    pub fn new(store: &Arc<CollectionStore>, collection: &str) -> Self {
+        Self { store: Arc::downgrade(store), collection: collection.to_string() }
+    }
+
+    fn store(&self) -> vectx_core::Result<Arc<CollectionStore>> {
+        self.store.upgrade().ok_or_else(|| vectx_core::Error::Storage(format!(
+            "collection {} is no longer loaded; write not persisted", self.collection
+        )))
+    }
+
+    fn failed(&self, e: anyhow::Error) -> vectx_core::Error {
+        vectx_core::Error::Storage(format!("failed to persist a write to {}: {}", self.collection, e))
+    }
+}
+
+impl PointStore for LmdbPointStore {
+    fn put(&self, point: &Point) -> vectx_core::Result<()> {
+        self.store()?.save_point(point).map_err(|e| self.failed(e))
+    }
+
+    fn remove(&self, id: &str) -> vectx_core::Result<()> {
+        self.store()?.delete_point(id).map(|_| ()).map_err(|e| self.failed(e))
+    }
+
+    fn apply(&self, puts: &[Point], removes: &[String]) -> vectx_core::Result<()> {
+        self.store()?.apply(puts, removes).map_err(|e| self.failed(e))
+    }
+}
@@ -0,0 +1,54 @@
+//! Exclusive use of a data directory
+//!
+//! A server, `vectx fsck` and `vectx compact` each hold an advisory lock
+//! on `data_dir/.lock` while they use the directory, so none of them reads
+//! files another is rewriting, e.g. a points environment being compacted
+//! or a migration in progress. The lock goes with the process; a crashed
+//! holder leaves nothing to clean up. The file records who holds it, for
+//! the error the next one gets.
+
+use anyhow::{anyhow, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+pub(crate) const LOCK_FILE: &str = ".lock";
+
+/// Held while a data directory is in use; released on drop
+pub struct DataDirLock {
+    #[cfg(unix)]
+    _file: nix::fcntl::Flock<File>,
+    #[cfg(not(unix))]
+    _file: File,
+}
+
+impl DataDirLock {
+    /// Lock `data_dir` for `holder` (e.g. "server"), failing at once if
+    /// another process or instance holds it
+    pub fn acquire(data_dir: &Path, holder: &str) -> Result<Self> {
+        let path = data_dir.join(LOCK_FILE);
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+
+        #[cfg(unix)]
+        let mut file = match nix::fcntl::Flock::lock(file, nix::fcntl::FlockArg::LockExclusiveNonblock) {
+            Ok(locked) => locked,
+            Err((mut file, _)) => {
+                let mut owner = String::new();
+                let _ = file.read_to_string(&mut owner);
+                return Err(anyhow!(
+                    "data directory {:?} is in use by {}; stop it first",
+                    data_dir,
+                    Some(owner.trim()).filter(|owner| !owner.is_empty()).unwrap_or("another process")
+                ));
+            }
+        };
+        #[cfg(not(unix))]
+        let mut file = file;
+
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        write!(file, "{} (pid {})", holder, std::process::id())?;
+        file.flush()?;
+        Ok(Self { _file: file })
+    }
+}
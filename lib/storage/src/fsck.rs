@@ -0,0 +1,456 @@
+//! Offline integrity check and compaction of a data directory
+//!
+//! `vectx fsck` and `vectx compact` run these while no server uses the
+//! directory. The check reads everything a server would load: collection
+//! manifests and point records, operation journals, collection and full
+//! snapshots, and the background save dump. It changes no data; it only
+//! takes the directory lock and opens LMDB environments. Compaction
+//! rewrites each collection's points environment without free pages, and
+//! on request drops what the check found corrupt and removes orphaned
+//! files.
+
+use crate::collection_store::{CollectionManifest, CollectionStore, COMPACTING_DIR, MANIFEST_FILE, POINTS_DIR, REPLACED_DIR};
+use crate::dir_lock::DataDirLock;
+use crate::manager::StorageManager;
+use crate::migration;
+use crate::persistence::SnapshotData;
+use crate::snapshot::SnapshotManager;
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use vectx_core::JournalEntry;
+
+/// What is wrong with a file or record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// A collection manifest is missing or unreadable
+    CorruptManifest,
+    /// A collection's points environment is missing or fails to open
+    CorruptStore,
+    /// A point record does not decode or does not fit its collection, so
+    /// the collection fails to load
+    CorruptRecord,
+    /// A journal line does not parse
+    CorruptJournalEntry,
+    /// A snapshot fails to parse or its checksums do not match
+    CorruptSnapshot,
+    /// The background save dump fails to parse
+    CorruptDump,
+    /// A snapshot or journal of a collection that does not exist, or a
+    /// differential snapshot whose base is gone
+    Orphaned,
+    /// A temporary file or directory left by an interrupted write
+    Leftover,
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::CorruptManifest => "corrupt manifest",
+            Self::CorruptStore => "corrupt points environment",
+            Self::CorruptRecord => "corrupt point record",
+            Self::CorruptJournalEntry => "corrupt journal entry",
+            Self::CorruptSnapshot => "corrupt snapshot",
+            Self::CorruptDump => "corrupt dump",
+            Self::Orphaned => "orphaned",
+            Self::Leftover => "leftover",
+        })
+    }
+}
+
+/// One problem found by [`check`]
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub kind: IssueKind,
+    /// The file or directory concerned; a collection's directory for its
+    /// point records
+    pub path: PathBuf,
+    /// Key of the point record, or line of the journal entry
+    pub record: Option<String>,
+    pub detail: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.path.display())?;
+        if let Some(record) = &self.record {
+            write!(f, " [{}]", record)?;
+        }
+        write!(f, ": {}", self.detail)
+    }
+}
+
+/// What [`check`] read and found
+#[derive(Debug, Clone, Default)]
+pub struct FsckReport {
+    pub collections: usize,
+    pub points: usize,
+    pub journals: usize,
+    pub snapshots: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn push(&mut self, kind: IssueKind, path: &Path, record: Option<String>, detail: impl ToString) {
+        self.issues.push(Issue { kind, path: path.to_path_buf(), record, detail: detail.to_string() });
+    }
+}
+
+/// What [`compact`] may change besides compacting
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactOptions {
+    /// Delete corrupt point records and journal entries
+    pub drop_corrupt: bool,
+    /// Delete orphaned snapshots and journals and leftover temporary files
+    pub remove_orphans: bool,
+}
+
+/// What [`compact`] did
+#[derive(Debug, Clone, Default)]
+pub struct CompactReport {
+    /// The check run before compacting
+    pub check: FsckReport,
+    /// Collections whose points environment was rewritten
+    pub collections: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// Point records and journal entries deleted
+    pub dropped: usize,
+    /// Files and directories deleted
+    pub removed: Vec<PathBuf>,
+}
+
+/// Check a data directory without changing its data
+pub fn check(data_dir: &Path) -> Result<FsckReport> {
+    let _lock = lock(data_dir, "vectx fsck")?;
+    check_locked(data_dir)
+}
+
+fn lock(data_dir: &Path, holder: &str) -> Result<DataDirLock> {
+    if !data_dir.is_dir() {
+        bail!("data directory {:?} does not exist", data_dir);
+    }
+    DataDirLock::acquire(data_dir, holder)
+}
+
+fn check_locked(data_dir: &Path) -> Result<FsckReport> {
+    migration::check_supported(data_dir)?;
+    let mut report = FsckReport::default();
+    let collections = check_collections(&data_dir.join("collections"), &mut report)?;
+    check_journals(&data_dir.join("journal"), &collections, &mut report)?;
+    check_snapshots(&data_dir.join("snapshots"), &collections, &mut report)?;
+    check_dump(data_dir, &mut report);
+    Ok(report)
+}
+
+/// Check a data directory, then rewrite every readable collection's points
+/// environment compactly. Corrupt snapshots, manifests and dumps are only
+/// reported; nothing here can repair them.
+pub fn compact(data_dir: &Path, options: CompactOptions) -> Result<CompactReport> {
+    let _lock = lock(data_dir, "vectx compact")?;
+    for dir in collection_dirs(&data_dir.join("collections"))? {
+        CollectionStore::recover_compaction(&dir)?;
+    }
+    let check = check_locked(data_dir)?;
+    let mut report = CompactReport::default();
+
+    if options.drop_corrupt {
+        let mut records: HashMap<&Path, Vec<String>> = HashMap::new();
+        let mut journals: HashSet<&Path> = HashSet::new();
+        for issue in &check.issues {
+            match (issue.kind, &issue.record) {
+                (IssueKind::CorruptRecord, Some(key)) => records.entry(&issue.path).or_default().push(key.clone()),
+                (IssueKind::CorruptJournalEntry, _) => {
+                    journals.insert(&issue.path);
+                }
+                _ => {}
+            }
+        }
+        for (dir, keys) in records {
+            CollectionStore::open(dir)?.apply(&[], &keys)?;
+            report.dropped += keys.len();
+        }
+        for path in journals {
+            report.dropped += rewrite_journal(path)?;
+        }
+    }
+
+    let unreadable: HashSet<&Path> = check.issues.iter()
+        .filter(|i| matches!(i.kind, IssueKind::CorruptManifest | IssueKind::CorruptStore))
+        .map(|i| i.path.as_path())
+        .collect();
+    for dir in collection_dirs(&data_dir.join("collections"))? {
+        if unreadable.contains(dir.as_path()) || !dir.join(POINTS_DIR).is_dir() {
+            continue;
+        }
+        let (before, after) = CollectionStore::compact_dir(&dir)?;
+        report.collections += 1;
+        report.bytes_before += before;
+        report.bytes_after += after;
+    }
+
+    if options.remove_orphans {
+        for issue in &check.issues {
+            if !matches!(issue.kind, IssueKind::Orphaned | IssueKind::Leftover) || !issue.path.exists() {
+                continue;
+            }
+            // Never the replaced points environment while it is the only one
+            let replaced = issue.path.file_name() == Some(std::ffi::OsStr::new(REPLACED_DIR));
+            if replaced && issue.path.parent().map_or(true, |dir| !dir.join(POINTS_DIR).is_dir()) {
+                continue;
+            }
+            if issue.path.is_dir() {
+                fs::remove_dir_all(&issue.path)?;
+            } else {
+                fs::remove_file(&issue.path)?;
+            }
+            report.removed.push(issue.path.clone());
+        }
+    }
+
+    report.check = check;
+    Ok(report)
+}
+
+fn collection_dirs(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    if root.is_dir() {
+        for entry in fs::read_dir(root)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            }
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Check every collection directory, returning the names of the
+/// collections found. A directory counts as a collection even if its
+/// manifest is damaged, so its snapshots are not reported as orphaned.
+fn check_collections(root: &Path, report: &mut FsckReport) -> Result<HashSet<String>> {
+    let mut names = HashSet::new();
+    for dir in collection_dirs(root)? {
+        let Some(name) = dir.file_name().and_then(|n| n.to_str()).map(str::to_string) else { continue };
+        names.insert(name.clone());
+        report.collections += 1;
+
+        // Without `points`, `points.old` is the only copy of the points
+        // and no leftover; opening the collection moves it back
+        let interrupted = !dir.join(POINTS_DIR).exists() && dir.join(REPLACED_DIR).is_dir();
+        for leftover in [COMPACTING_DIR, REPLACED_DIR] {
+            if dir.join(leftover).exists() && !(interrupted && leftover == REPLACED_DIR) {
+                report.push(IssueKind::Leftover, &dir.join(leftover), None, "left by an interrupted compaction");
+            }
+        }
+
+        if !dir.join(MANIFEST_FILE).is_file() {
+            report.push(IssueKind::CorruptManifest, &dir, None, "manifest.json is missing; the collection is not loaded");
+            continue;
+        }
+        let manifest = match CollectionManifest::read(&dir) {
+            Ok(manifest) if manifest.name == name => manifest,
+            Ok(manifest) => {
+                report.push(IssueKind::CorruptManifest, &dir, None, format!("manifest names collection '{}'", manifest.name));
+                continue;
+            }
+            Err(e) => {
+                report.push(IssueKind::CorruptManifest, &dir, None, e);
+                continue;
+            }
+        };
+
+        if interrupted {
+            report.push(IssueKind::CorruptStore, &dir, None,
+                "points environment is in points.old after an interrupted compaction; the server or compact moves it back");
+            continue;
+        }
+        if !dir.join(POINTS_DIR).is_dir() {
+            report.push(IssueKind::CorruptStore, &dir, None, "points environment is missing");
+            continue;
+        }
+        let collection = StorageManager::collection_from_config(&name, &manifest.config);
+        let mut corrupt = Vec::new();
+        let scanned = CollectionStore::open_existing(&dir).and_then(|store| {
+            store.scan(|key, decoded| {
+                report.points += 1;
+                let problem = match decoded {
+                    Err(e) => Some(e.to_string()),
+                    Ok(point) if point.id.to_string() != key => Some(format!("record holds point {}", point.id)),
+                    Ok(point) => collection.validate_point(&point).err().map(|e| e.to_string()),
+                };
+                if let Some(problem) = problem {
+                    corrupt.push((key.to_string(), problem));
+                }
+            })
+        });
+        if let Err(e) = scanned {
+            report.push(IssueKind::CorruptStore, &dir, None, e);
+        }
+        for (key, problem) in corrupt {
+            report.push(IssueKind::CorruptRecord, &dir, Some(key), problem);
+        }
+    }
+    Ok(names)
+}
+
+/// Each line of a journal that fails to parse, by line number
+fn corrupt_journal_lines(path: &Path) -> Result<Vec<(usize, String)>> {
+    let mut corrupt = Vec::new();
+    for (index, line) in BufReader::new(fs::File::open(path)?).split(b'\n').enumerate() {
+        let line = line?;
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        if let Err(e) = serde_json::from_slice::<JournalEntry>(&line) {
+            corrupt.push((index + 1, e.to_string()));
+        }
+    }
+    Ok(corrupt)
+}
+
+fn check_journals(root: &Path, collections: &HashSet<String>, report: &mut FsckReport) -> Result<()> {
+    if !root.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if name.ends_with(".jsonl.tmp") {
+            report.push(IssueKind::Leftover, &path, None, "left by an interrupted journal compaction");
+            continue;
+        }
+        let Some(collection) = name.strip_suffix(".jsonl") else { continue };
+        report.journals += 1;
+        if !collections.contains(collection) {
+            report.push(IssueKind::Orphaned, &path, None, format!("collection '{}' does not exist", collection));
+            continue;
+        }
+        for (line, problem) in corrupt_journal_lines(&path)? {
+            report.push(IssueKind::CorruptJournalEntry, &path, Some(format!("line {}", line)), problem);
+        }
+    }
+    Ok(())
+}
+
+/// Rewrite a journal without the lines that fail to parse, returning how
+/// many were dropped
+fn rewrite_journal(path: &Path) -> Result<usize> {
+    let mut kept = Vec::new();
+    let mut dropped = 0;
+    for line in BufReader::new(fs::File::open(path)?).split(b'\n') {
+        let line = line?;
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        if serde_json::from_slice::<JournalEntry>(&line).is_ok() {
+            kept.extend_from_slice(&line);
+            kept.push(b'\n');
+        } else {
+            dropped += 1;
+        }
+    }
+    atomicwrites::AtomicFile::new(path, atomicwrites::AllowOverwrite)
+        .write(|f| f.write_all(&kept))
+        .map_err(|e| anyhow::anyhow!("failed to rewrite journal {:?}: {}", path, e))?;
+    Ok(dropped)
+}
+
+fn check_snapshots(root: &Path, collections: &HashSet<String>, report: &mut FsckReport) -> Result<()> {
+    if !root.is_dir() {
+        return Ok(());
+    }
+    let snapshots = SnapshotManager::new(root)?;
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        if matches!(extension, "partial" | "staging") {
+            report.push(IssueKind::Leftover, &path, None, "left by an interrupted full snapshot");
+        } else if path.is_file() && extension == "snapshot" {
+            report.snapshots += 1;
+            if let Err(e) = check_full_snapshot(&path) {
+                report.push(IssueKind::CorruptSnapshot, &path, None, e);
+            }
+        } else if path.is_dir() {
+            let collection = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            check_collection_snapshots(&snapshots, &path, collections.contains(collection), report)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_collection_snapshots(snapshots: &SnapshotManager, dir: &Path, collection_exists: bool, report: &mut FsckReport) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("snapshot") {
+            continue;
+        }
+        report.snapshots += 1;
+        if !collection_exists {
+            report.push(IssueKind::Orphaned, &path, None, "its collection does not exist");
+            continue;
+        }
+        match snapshots.load_snapshot_from_path(&path) {
+            Ok(data) => {
+                if let Some(delta) = data.delta.filter(|delta| !dir.join(&delta.base).is_file()) {
+                    report.push(IssueKind::Orphaned, &path, None, format!("base snapshot {} is missing", delta.base));
+                }
+            }
+            Err(e) => report.push(IssueKind::CorruptSnapshot, &path, None, e),
+        }
+    }
+    Ok(())
+}
+
+/// Read every entry of a full snapshot archive to its end
+fn check_full_snapshot(path: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(BufReader::new(fs::File::open(path)?));
+    let mut manifests = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.file_name().and_then(|n| n.to_str()) == Some(MANIFEST_FILE) {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            serde_json::from_slice::<CollectionManifest>(&data)?;
+            manifests += 1;
+        } else {
+            std::io::copy(&mut entry, &mut std::io::sink())?;
+        }
+    }
+    if manifests == 0 {
+        bail!("archive holds no collection");
+    }
+    Ok(())
+}
+
+/// Check `dump.rdb`, written by background saves, the way startup reads it
+fn check_dump(data_dir: &Path, report: &mut FsckReport) {
+    for leftover in ["dump.tmp", "dump.version.tmp"] {
+        let path = data_dir.join(leftover);
+        if path.exists() {
+            report.push(IssueKind::Leftover, &path, None, "left by an interrupted background save");
+        }
+    }
+    let path = data_dir.join("dump.rdb");
+    if !path.exists() {
+        return;
+    }
+    if !path.with_extension("version").exists() {
+        report.push(IssueKind::CorruptDump, &path, None, "version marker missing; the save did not complete");
+        return;
+    }
+    let result = fs::read(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| Ok(bincode::deserialize::<SnapshotData>(&data).map(|_| ())?));
+    if let Err(e) = result {
+        report.push(IssueKind::CorruptDump, &path, None, e);
+    }
+}
@@ -0,0 +1,143 @@
+//! Long-running admin jobs (clustering, deduplication, ...)
+//!
+//! Jobs run at low priority on the background job system, within its CPU
+//! budget; their status and result are kept in memory so clients can poll
+//! them. Jobs do not survive a restart. Cancelling a job stops it at its
+//! next check and discards its result.
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use vectx_core::background::{get_background_system, BackgroundJobType};
+use vectx_core::{JobContext, JobPriority, JobProgress};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// State of a submitted job
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: String,
+    /// Job type, e.g. `kmeans`
+    pub kind: String,
+    pub collection: Option<String>,
+    pub status: JobStatus,
+    /// Id of the job on the background job system
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_id: Option<u64>,
+    /// Seconds since the Unix epoch
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    /// Work done so far, for running jobs that report it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<JobProgress>,
+    /// Job-specific output once completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Registry of submitted jobs
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: RwLock<HashMap<String, JobInfo>>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `work` on the background job system and track it. Returns the
+    /// job as submitted.
+    pub fn spawn<F>(self: &Arc<Self>, kind: &str, collection: Option<&str>, work: F) -> anyhow::Result<JobInfo>
+    where
+        F: FnOnce(&JobContext) -> Result<serde_json::Value, String> + Send + 'static,
+    {
+        let mut job = JobInfo {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            collection: collection.map(str::to_string),
+            status: JobStatus::Running,
+            background_id: None,
+            started_at: chrono::Utc::now().timestamp(),
+            finished_at: None,
+            progress: None,
+            result: None,
+            error: None,
+        };
+        // Held until the background id is recorded, so a cancel in between
+        // reaches the background job
+        let mut jobs = self.jobs.write();
+
+        let registry = self.clone();
+        let id = job.id.clone();
+        let background_id = get_background_system().spawn_job(BackgroundJobType::Admin, JobPriority::Low, collection, move |ctx| {
+            let outcome = work(ctx);
+            registry.finish(&id, outcome);
+        });
+        job.background_id = Some(background_id);
+        jobs.insert(job.id.clone(), job.clone());
+        Ok(job)
+    }
+
+    /// Cancel a running job: it never starts if still queued, and is asked
+    /// to stop otherwise. Returns None if there is no such job.
+    pub fn cancel(&self, id: &str) -> Option<JobInfo> {
+        let mut jobs = self.jobs.write();
+        let job = jobs.get_mut(id)?;
+        if job.status == JobStatus::Running {
+            if let Some(background_id) = job.background_id {
+                get_background_system().cancel(background_id);
+            }
+            job.status = JobStatus::Cancelled;
+            job.finished_at = Some(chrono::Utc::now().timestamp());
+        }
+        Some(job.clone())
+    }
+
+    fn finish(&self, id: &str, outcome: Result<serde_json::Value, String>) {
+        if let Some(job) = self.jobs.write().get_mut(id).filter(|job| job.status == JobStatus::Running) {
+            job.finished_at = Some(chrono::Utc::now().timestamp());
+            match outcome {
+                Ok(result) => {
+                    job.status = JobStatus::Completed;
+                    job.result = Some(result);
+                }
+                Err(error) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(error);
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<JobInfo> {
+        self.jobs.read().get(id).cloned().map(with_progress)
+    }
+
+    /// All jobs, most recent first
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self.jobs.read().values().cloned().map(with_progress).collect();
+        jobs.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| a.id.cmp(&b.id)));
+        jobs
+    }
+}
+
+/// `job` with the progress its background job last reported
+fn with_progress(mut job: JobInfo) -> JobInfo {
+    if job.status == JobStatus::Running {
+        job.progress = job.background_id
+            .and_then(|id| get_background_system().job(id))
+            .and_then(|background| background.progress);
+    }
+    job
+}
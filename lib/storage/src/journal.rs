@@ -0,0 +1,97 @@
+use anyhow::Result;
+use parking_lot::RwLock;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use vectx_core::{JournalEntry, JournalSink};
+use crate::wal::WriteAheadLog;
+
+/// Persists a collection's operation journal as JSON lines
+/// Appends go through the WAL; the file is periodically compacted down to the
+/// entries still retained in memory (like Redis AOF rewrite)
+pub struct FileJournalSink {
+    wal: RwLock<WriteAheadLog>,
+    path: PathBuf,
+    appended: AtomicUsize,
+}
+
+impl FileJournalSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            wal: RwLock::new(WriteAheadLog::new(&path)?),
+            path,
+            appended: AtomicUsize::new(0),
+        })
+    }
+
+    /// Read persisted entries, skipping lines that fail to parse (e.g. a torn last write)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<JournalEntry>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let reader = BufReader::new(fs::File::open(path)?);
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<JournalEntry>(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => tracing::warn!("Skipping corrupt journal entry in {:?}: {}", path, e),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Number of entries appended since the last compaction
+    #[inline]
+    pub fn appended(&self) -> usize {
+        self.appended.load(Ordering::Relaxed)
+    }
+
+    /// Rewrite the file with only `entries`
+    pub fn compact(&self, entries: &[JournalEntry]) -> Result<()> {
+        let mut wal = self.wal.write();
+
+        let temp_path = self.path.with_extension("jsonl.tmp");
+        {
+            let mut file = std::io::BufWriter::new(fs::File::create(&temp_path)?);
+            for entry in entries {
+                serde_json::to_writer(&mut file, entry)?;
+                file.write_all(b"\n")?;
+            }
+            file.flush()?;
+            file.get_ref().sync_all()?;
+        }
+        fs::rename(&temp_path, &self.path)?;
+
+        *wal = WriteAheadLog::new(&self.path)?;
+        self.appended.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl JournalSink for FileJournalSink {
+    fn append(&self, entry: &JournalEntry) {
+        let result = serde_json::to_vec(entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| self.wal.read().append(&data));
+        match result {
+            Ok(()) => {
+                self.appended.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => tracing::warn!("Failed to append journal entry to {:?}: {}", self.path, e),
+        }
+    }
+}
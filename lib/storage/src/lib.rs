@@ -1,12 +1,30 @@
 pub mod manager;
 pub mod wal;
 pub mod lmdb_storage;
+pub mod collection_store;
 pub mod snapshot;
+mod snapshot_format;
 pub mod persistence;
+pub mod journal;
+pub mod acl;
+pub mod audit;
+pub mod jobs;
+pub mod fsck;
+pub mod migration;
+pub mod dir_lock;
 
-pub use manager::StorageManager;
+pub use manager::{LoadLimits, Locks, StorageManager, WarmUpSummary, DEFAULT_MAX_PAYLOAD_BYTES, DEFAULT_WRITE_LOCK_MESSAGE, LOAD_RECOVERY, MIN_P99_QUERIES};
 pub use wal::WriteAheadLog;
 pub use lmdb_storage::LmdbStorage;
-pub use snapshot::{SnapshotManager, SnapshotDescription, CollectionSnapshotData, PointData, CollectionConfigData};
-pub use persistence::ForkBasedPersistence;
+pub use collection_store::{check_collection_name, CollectionManifest, CollectionStore, MAX_COLLECTION_NAME_BYTES};
+pub use snapshot::{SnapshotManager, SnapshotDescription, CollectionSnapshotData, PointData, CollectionConfigData, DeltaInfo, RecoverOptions, RestoreScope, SnapshotPriority};
+pub use snapshot_format::VectorEncoding;
+pub use persistence::{ForkBasedPersistence, SaveStrategy};
+pub use journal::FileJournalSink;
+pub use jobs::{JobInfo, JobRegistry, JobStatus};
+pub use fsck::{CompactOptions, CompactReport, FsckReport, Issue, IssueKind};
+pub use acl::{AccessControl, AccessError, AccessGrant, ApiKey, JwtClaims, Permission};
+pub use audit::{AuditEntry, AuditLog, AuditQuery, DEFAULT_AUDIT_FILES, DEFAULT_AUDIT_FILE_BYTES};
 
+pub use migration::DATA_FORMAT_VERSION;
+pub use dir_lock::DataDirLock;
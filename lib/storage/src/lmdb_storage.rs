@@ -1,13 +1,22 @@
 // LMDB-based storage for fast persistence (like helix-db)
+//
+// Collection contents now live in per-collection environments (see
+// `collection_store`). The shared environment keeps ingestion checkpoints
+// and graph data; collections still stored here by an older version are
+// moved into their own directories by the first migration (see
+// `migration`).
 use anyhow::Result;
 use heed::{Database, Env, EnvOpenOptions};
 use std::path::Path;
 use std::sync::Arc;
+use vectx_core::Point;
+use crate::collection_store::PointRecord;
 
 const DB_COLLECTIONS: &str = "collections";
 const DB_POINTS: &str = "points";
 const DB_GRAPH_NODES: &str = "graph_nodes";
 const DB_GRAPH_EDGES: &str = "graph_edges";
+const DB_CHECKPOINTS: &str = "checkpoints";
 
 pub struct LmdbStorage {
     env: Arc<Env>,
@@ -15,6 +24,7 @@ pub struct LmdbStorage {
     points_db: Database<heed::types::Str, heed::types::Bytes>,
     nodes_db: Database<heed::types::U64<heed::byteorder::BE>, heed::types::Bytes>,
     edges_db: Database<heed::types::U64<heed::byteorder::BE>, heed::types::Bytes>,
+    checkpoints_db: Database<heed::types::Str, heed::types::U64<heed::byteorder::BE>>,
 }
 
 impl LmdbStorage {
@@ -44,6 +54,9 @@ impl LmdbStorage {
         let edges_db = env
             .create_database(&mut wtxn, Some(DB_GRAPH_EDGES))?;
 
+        let checkpoints_db = env
+            .create_database(&mut wtxn, Some(DB_CHECKPOINTS))?;
+
         wtxn.commit()?;
 
         Ok(Self {
@@ -52,21 +65,20 @@ impl LmdbStorage {
             points_db,
             nodes_db,
             edges_db,
+            checkpoints_db,
         })
     }
 
     pub fn save_point(&self, collection: &str, point_id: &str, data: &[u8]) -> Result<()> {
         let mut wtxn = self.env.write_txn()?;
-        let key = format!("{}:{}", collection, point_id);
-        self.points_db.put(&mut wtxn, &key, data)?;
+        self.points_db.put(&mut wtxn, &point_key(collection, point_id), data)?;
         wtxn.commit()?;
         Ok(())
     }
 
     pub fn get_point(&self, collection: &str, point_id: &str) -> Result<Option<Vec<u8>>> {
         let rtxn = self.env.read_txn()?;
-        let key = format!("{}:{}", collection, point_id);
-        match self.points_db.get(&rtxn, &key)? {
+        match self.points_db.get(&rtxn, &point_key(collection, point_id))? {
             Some(data) => Ok(Some(data.to_vec())),
             None => Ok(None),
         }
@@ -74,12 +86,43 @@ impl LmdbStorage {
 
     pub fn delete_point(&self, collection: &str, point_id: &str) -> Result<bool> {
         let mut wtxn = self.env.write_txn()?;
-        let key = format!("{}:{}", collection, point_id);
-        let existed = self.points_db.delete(&mut wtxn, &key)?;
+        let existed = self.points_db.delete(&mut wtxn, &point_key(collection, point_id))?;
         wtxn.commit()?;
         Ok(existed)
     }
 
+    /// Every point record of a collection, read in one transaction
+    pub fn load_points(&self, collection: &str) -> Result<Vec<Point>> {
+        let rtxn = self.env.read_txn()?;
+        let prefix = point_key(collection, "");
+        let mut points = Vec::new();
+        for result in self.points_db.prefix_iter(&rtxn, &prefix)? {
+            let (_, data) = result?;
+            points.push(PointRecord::decode(data)?);
+        }
+        Ok(points)
+    }
+
+    /// Remove a collection's configuration and all of its point records
+    pub fn delete_collection(&self, name: &str) -> Result<()> {
+        let mut wtxn = self.env.write_txn()?;
+        self.collections_db.delete(&mut wtxn, name)?;
+        self.clear_points(&mut wtxn, name)?;
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    fn clear_points(&self, wtxn: &mut heed::RwTxn, collection: &str) -> Result<()> {
+        let prefix = point_key(collection, "");
+        let keys: Vec<String> = self.points_db.prefix_iter(wtxn, &prefix)?
+            .map(|result| result.map(|(key, _)| key.to_string()))
+            .collect::<heed::Result<_>>()?;
+        for key in keys {
+            self.points_db.delete(wtxn, &key)?;
+        }
+        Ok(())
+    }
+
     pub fn save_collection(&self, name: &str, data: &[u8]) -> Result<()> {
         let mut wtxn = self.env.write_txn()?;
         self.collections_db.put(&mut wtxn, name, data)?;
@@ -134,5 +177,23 @@ impl LmdbStorage {
             None => Ok(None),
         }
     }
+
+    /// Store a consumer offset (e.g. Kafka partition offset, NATS stream sequence)
+    pub fn save_checkpoint(&self, key: &str, offset: u64) -> Result<()> {
+        let mut wtxn = self.env.write_txn()?;
+        self.checkpoints_db.put(&mut wtxn, key, &offset)?;
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    pub fn get_checkpoint(&self, key: &str) -> Result<Option<u64>> {
+        let rtxn = self.env.read_txn()?;
+        Ok(self.checkpoints_db.get(&rtxn, key)?)
+    }
 }
 
+/// Point records are keyed `collection\0point_id`, so a prefix scan over one
+/// collection never reaches into another whose name extends it
+fn point_key(collection: &str, point_id: &str) -> String {
+    format!("{}\0{}", collection, point_id)
+}
@@ -1,21 +1,63 @@
-use vectx_core::{Collection, CollectionConfig, Distance, Error, Result, Point, PointId, Vector, MultiVector};
-use parking_lot::RwLock;
-use std::collections::HashMap;
+use vectx_core::{Collection, CollectionConfig, Distance, Error, Result, Point, PointId, Vector, MultiVector, JournalEntry, JournalRetention, Projection, JournalSink, StatsSample};
+use vectx_core::background::{get_background_system, BackgroundJobType};
+use parking_lot::{Mutex, MutexGuard, RwLock};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use crate::collection_store::{check_collection_name, CollectionManifest, CollectionStore, LmdbPointStore};
 use crate::lmdb_storage::LmdbStorage;
 use crate::wal::WriteAheadLog;
-use crate::snapshot::{SnapshotManager, SnapshotDescription, CollectionSnapshotData, CollectionConfigData, PointData};
-use crate::persistence::ForkBasedPersistence;
+use crate::snapshot::{SnapshotManager, SnapshotDescription, CollectionSnapshotData, CollectionConfigData, DeltaInfo, PointData, RecoverOptions, RestoreScope, SnapshotPriority};
+use crate::snapshot_format::VectorEncoding;
+use crate::persistence::{ForkBasedPersistence, SaveStrategy};
+use crate::journal::FileJournalSink;
+use crate::acl::AccessControl;
+use crate::audit::AuditLog;
+use crate::jobs::JobRegistry;
+use crate::dir_lock::DataDirLock;
+use crate::migration;
+
+/// Payload size limit of point writes unless configured otherwise (1 MiB)
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 1 << 20;
+
+/// Error of writes refused by a write lock that sets no message
+pub const DEFAULT_WRITE_LOCK_MESSAGE: &str = "Write operations are forbidden";
+
+/// Statistics samples of a collection, in its directory
+const STATS_HISTORY_FILE: &str = "stats_history.json";
+
+/// Instance-wide locks, as Qdrant's `/locks` sets them
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Locks {
+    /// Refuse every write while reads go on
+    pub write: bool,
+    /// Error returned to refused writes
+    #[serde(default)]
+    pub error_message: Option<String>,
+}
 
 /// Manages collections and persistence
 pub struct StorageManager {
+    /// Collections whose points are loaded in memory
     collections: Arc<RwLock<HashMap<String, Arc<Collection>>>>,
+    /// Manifests of every collection, loaded or not
+    manifests: Arc<RwLock<HashMap<String, CollectionManifest>>>,
+    /// Open points environments of loaded collections
+    stores: Arc<RwLock<HashMap<String, Arc<CollectionStore>>>>,
+    /// Held while loading, installing or unloading a collection
+    loading: Mutex<()>,
+    /// Collections in the middle of being unloaded
+    unloading: RwLock<HashSet<String>>,
+    /// Collections being restored from a snapshot
+    restoring: RwLock<HashSet<String>>,
     /// Aliases: alias_name -> collection_name
     aliases: Arc<RwLock<HashMap<String, String>>>,
     data_dir: PathBuf,
-    #[allow(dead_code)]
+    /// Keeps `fsck`, `compact` and other servers out of the data directory
+    _dir_lock: DataDirLock,
     lmdb: Option<Arc<LmdbStorage>>,
     #[allow(dead_code)]
     wal: Option<Arc<WriteAheadLog>>,
@@ -23,12 +65,76 @@ pub struct StorageManager {
     persistence: Arc<ForkBasedPersistence>,
     #[allow(dead_code)]
     save_interval: Option<Duration>,
+    /// Persisted operation journals: collection_name -> sink
+    journals: Arc<RwLock<HashMap<String, Arc<FileJournalSink>>>>,
+    journal_retention: Arc<RwLock<JournalRetention>>,
+    /// Largest payload accepted by point writes, in bytes
+    max_payload_bytes: RwLock<Option<usize>>,
+    /// Pending points past which point writes to a collection are refused
+    max_pending_points: RwLock<Option<usize>>,
+    /// Point writes refused for that so far
+    rejected_writes: AtomicU64,
+    locks: RwLock<Locks>,
+    access: Arc<AccessControl>,
+    /// Where mutating requests are recorded, if anywhere
+    audit_log: RwLock<Option<Arc<AuditLog>>>,
+    jobs: Arc<JobRegistry>,
+    /// A warm-up started by `start_warm_up` has not finished
+    warming_up: AtomicBool,
+    /// The node has been ready once; later restores and graph builds are
+    /// reported per collection rather than holding readiness
+    started_up: AtomicBool,
+    /// Check every collection's indexes after each write
+    check_invariants: AtomicBool,
+    /// How often statistics are sampled, once `start_stats_history` ran
+    stats_interval: RwLock<Option<Duration>>,
+    /// Load past which the node reports itself not ready
+    load_limits: RwLock<LoadLimits>,
+    /// Too many background jobs were queued, and the queue has not yet
+    /// drained below the recovery point
+    jobs_overloaded: AtomicBool,
+    /// Collections whose p99 latency passed the limit and has not yet
+    /// dropped below the recovery point
+    slow_collections: Mutex<HashSet<String>>,
+}
+
+/// Queries a collection must have run since its last statistics sample
+/// before its p99 latency counts against the load limit
+pub const MIN_P99_QUERIES: u64 = 20;
+
+/// Fraction of a load limit the load must drop below before a node past
+/// it reports itself ready again, so it does not flap around the limit
+pub const LOAD_RECOVERY: f64 = 0.8;
+
+/// Load past which the node reports itself not ready (see
+/// [`StorageManager::readiness_issues`]), so load balancers send traffic to
+/// other replicas while it catches up, e.g. on heavy HNSW rebuilds. Unset
+/// limits are not checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LoadLimits {
+    /// Background jobs (HNSW rebuilds, clustering, ...) waiting to start
+    pub max_queued_jobs: Option<usize>,
+    /// p99 latency of a loaded collection's queries since its last
+    /// statistics sample, in ms
+    pub max_p99_ms: Option<f64>,
+}
+
+/// What a startup warm-up did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmUpSummary {
+    pub collections: usize,
+    pub points: usize,
+    pub queries: usize,
+    pub elapsed: Duration,
 }
 
 impl StorageManager {
     pub fn new<P: AsRef<Path>>(data_dir: P) -> Result<Self> {
         let data_dir = data_dir.as_ref().to_path_buf();
         std::fs::create_dir_all(&data_dir)?;
+        let dir_lock = DataDirLock::acquire(&data_dir, "a vectX server")
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        migration::migrate(&data_dir).map_err(|e| Error::Storage(e.to_string()))?;
 
         let lmdb_path = data_dir.join("lmdb");
         let lmdb = Arc::new(LmdbStorage::new(&lmdb_path)
@@ -44,68 +150,296 @@ impl StorageManager {
 
         let persistence = Arc::new(ForkBasedPersistence::new(&data_dir));
 
+        let access = Arc::new(AccessControl::load(data_dir.join("api_keys.json"))
+            .map_err(|e| Error::Storage(e.to_string()))?);
+
         let collections = Arc::new(RwLock::new(HashMap::new()));
         let aliases = Arc::new(RwLock::new(HashMap::new()));
-        
-        if let Some(snapshot) = persistence.load_snapshot()
-            .map_err(|e| Error::Persistence(e.to_string()))? {
-            eprintln!("Loading snapshot from disk...");
-            let mut collections_map = HashMap::new();
-            
-            for col_snapshot in snapshot.collections {
-                let config = CollectionConfig {
-                    name: col_snapshot.name.clone(),
-                    vector_dim: col_snapshot.config.vector_dim,
-                    distance: match col_snapshot.config.distance.as_str() {
-                        "Cosine" => Distance::Cosine,
-                        "Euclidean" => Distance::Euclidean,
-                        "Dot" => Distance::Dot,
-                        _ => Distance::Cosine,
-                    },
-                    use_hnsw: col_snapshot.config.use_hnsw,
-                    enable_bm25: col_snapshot.config.enable_bm25,
-                };
-                
-                let collection = Arc::new(Collection::new(config));
-                
-                for point_snapshot in col_snapshot.points {
-                    let point = Point::new(
-                        PointId::String(point_snapshot.id.clone()),
-                        Vector::new(point_snapshot.vector),
-                        point_snapshot.payload,
-                    );
-                    if let Err(e) = collection.upsert(point) {
-                        eprintln!("Warning: Failed to restore point {}: {}", point_snapshot.id, e);
+
+        // Only manifests are read here; points are loaded on first access
+        let manifests: HashMap<String, CollectionManifest> = CollectionManifest::read_all(&data_dir.join("collections"))
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .into_iter()
+            .map(|manifest| (manifest.name.clone(), manifest))
+            .collect();
+
+        // Collections of versions that only kept the background save dump,
+        // or whose directories were lost, are restored from dump.rdb
+        let mut restored = Vec::new();
+        if manifests.is_empty() {
+            if let Some(snapshot) = persistence.load_snapshot()
+                .map_err(|e| Error::Persistence(e.to_string()))? {
+                tracing::info!("Loading snapshot from disk...");
+
+                for col_snapshot in snapshot.collections {
+                    let config = CollectionConfig {
+                        name: col_snapshot.name.clone(),
+                        vector_dim: col_snapshot.config.vector_dim,
+                        distance: match col_snapshot.config.distance.as_str() {
+                            "Cosine" => Distance::Cosine,
+                            "Euclidean" => Distance::Euclidean,
+                            "Dot" => Distance::Dot,
+                            "Hamming" => Distance::Hamming,
+                            _ => Distance::Cosine,
+                        },
+                        use_hnsw: col_snapshot.config.use_hnsw,
+                        enable_bm25: col_snapshot.config.enable_bm25,
+                    };
+
+                    let collection = Arc::new(Collection::new(config));
+
+                    for point_snapshot in col_snapshot.points {
+                        let point = Point::new(
+                            PointId::String(point_snapshot.id.clone()),
+                            Vector::new(point_snapshot.vector),
+                            point_snapshot.payload,
+                        );
+                        if let Err(e) = collection.upsert(point) {
+                            tracing::warn!("Failed to restore point {}: {}", point_snapshot.id, e);
+                        }
                     }
+
+                    restored.push(collection);
                 }
-                
-                collections_map.insert(col_snapshot.name, collection);
+
+                tracing::info!("Snapshot loaded: {} collections", restored.len());
             }
-            
-            *collections.write() = collections_map;
-            eprintln!("Snapshot loaded: {} collections", collections.read().len());
+        } else {
+            tracing::info!("Found {} collections", manifests.len());
         }
 
         let manager = Self {
             collections,
+            manifests: Arc::new(RwLock::new(manifests)),
+            stores: Arc::new(RwLock::new(HashMap::new())),
+            loading: Mutex::new(()),
+            unloading: RwLock::new(HashSet::new()),
+            restoring: RwLock::new(HashSet::new()),
             aliases,
             data_dir,
+            _dir_lock: dir_lock,
             lmdb: Some(lmdb),
             wal: Some(wal),
             snapshots,
             persistence,
             save_interval: Some(Duration::from_secs(300)),
+            journals: Arc::new(RwLock::new(HashMap::new())),
+            journal_retention: Arc::new(RwLock::new(JournalRetention::default())),
+            max_payload_bytes: RwLock::new(Some(DEFAULT_MAX_PAYLOAD_BYTES)),
+            max_pending_points: RwLock::new(None),
+            rejected_writes: AtomicU64::new(0),
+            locks: RwLock::new(Locks::default()),
+            access,
+            audit_log: RwLock::new(None),
+            jobs: Arc::new(JobRegistry::new()),
+            warming_up: AtomicBool::new(false),
+            started_up: AtomicBool::new(false),
+            jobs_overloaded: AtomicBool::new(false),
+            slow_collections: Mutex::new(HashSet::new()),
+            check_invariants: AtomicBool::new(false),
+            stats_interval: RwLock::new(None),
+            load_limits: RwLock::new(LoadLimits::default()),
         };
 
+        for collection in restored {
+            // It stays in dump.rdb, which is only read while no collection
+            // has a directory
+            if let Err(e) = Self::validate_collection_name(collection.name()) {
+                tracing::warn!("Not restoring collection {:?} from the dump: {}", collection.name(), e);
+                continue;
+            }
+            let loading = manager.loading.lock();
+            manager.install_collection(&loading, collection, false)?;
+        }
+
         manager.start_background_save();
 
         Ok(manager)
     }
 
+    /// An empty collection with the given configuration
+    pub(crate) fn collection_from_config(name: &str, config_data: &CollectionConfigData) -> Collection {
+        let config = CollectionConfig {
+            name: name.to_string(),
+            vector_dim: config_data.vector_dim,
+            distance: match config_data.distance.as_str() {
+                "Cosine" => Distance::Cosine,
+                "Euclidean" => Distance::Euclidean,
+                "Dot" => Distance::Dot,
+                "Hamming" => Distance::Hamming,
+                _ => Distance::Cosine,
+            },
+            use_hnsw: config_data.use_hnsw,
+            enable_bm25: config_data.enable_bm25,
+        };
+        let collection = Collection::new(config);
+        for (vector_name, modifier) in &config_data.sparse_modifiers {
+            collection.set_sparse_modifier(vector_name, *modifier);
+        }
+        for (vector_name, sparse_config) in &config_data.sparse_vectors {
+            collection.set_sparse_vector(vector_name, sparse_config.clone());
+        }
+        for (vector_name, params) in &config_data.named_multivectors {
+            collection.set_named_multivector(vector_name, params.clone());
+        }
+        collection.set_payload_indexes(config_data.payload_indexes.clone(), config_data.keyword_params.clone());
+        collection.set_dense_vector_name(config_data.dense_vector_name.clone());
+        collection.set_text_dictionaries(config_data.text_dictionaries.clone());
+        collection.set_detect_language(config_data.detect_language);
+        collection.set_search_defaults(config_data.search_defaults.clone());
+        // Nothing is stored yet, so there is nothing to normalize or persist
+        let _ = collection.set_normalization(config_data.normalization);
+        collection.set_duplicate_policy(config_data.duplicate_policy);
+        // Nor anything to relink, so the new parameters are never refused
+        let _ = collection.set_hnsw_params(config_data.hnsw_config);
+        collection.set_infer_vector_dim(config_data.infer_vector_dim);
+        collection.set_query_templates(config_data.query_templates.clone());
+        collection.set_ef_tuning(config_data.ef_tuning.clone());
+        collection.set_ef_tuning_report(config_data.ef_tuning_report.clone());
+        collection
+    }
+
+    /// Refuse a collection name that could not be used as its directory
+    /// name (see [`check_collection_name`])
+    pub fn validate_collection_name(name: &str) -> Result<()> {
+        check_collection_name(name).map_err(|e| Error::InvalidConfig(e.to_string()))
+    }
+
+    fn collection_dir(&self, collection_name: &str) -> PathBuf {
+        self.data_dir.join("collections").join(collection_name)
+    }
+
+    /// The points environment of a collection, opened on first use.
+    /// Callers hold `loading`, so an environment is never opened twice.
+    fn open_store(&self, _loading: &MutexGuard<'_, ()>, collection_name: &str) -> Result<Arc<CollectionStore>> {
+        if let Some(store) = self.stores.read().get(collection_name) {
+            return Ok(store.clone());
+        }
+        let store = Arc::new(CollectionStore::open(self.collection_dir(collection_name))
+            .map_err(|e| Error::Storage(e.to_string()))?);
+        self.stores.write().insert(collection_name.to_string(), store.clone());
+        Ok(store)
+    }
+
+    /// Mirror every future write of a collection into its points environment
+    fn attach_point_store(&self, collection: &Collection, store: &Arc<CollectionStore>) {
+        let point_store = LmdbPointStore::new(store, collection.name());
+        collection.set_point_store(Some(Arc::new(point_store)));
+    }
+
+    /// Write a collection's manifest and points to its directory and make it
+    /// the loaded instance under its name, replacing any previous one
+    fn install_collection(&self, loading: &MutexGuard<'_, ()>, collection: Arc<Collection>, fresh_journal: bool) -> Result<()> {
+        let name = collection.name().to_string();
+        Self::validate_collection_name(&name)?;
+        // Finish the writes to an instance this one replaces before its
+        // points are overwritten
+        if let Some(replaced) = self.collections.read().get(&name) {
+            replaced.close();
+        }
+        let store = self.open_store(loading, &name)?;
+        store.replace_points(&collection.get_all_points())
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        self.save_collection_config(&collection)?;
+        collection.set_check_invariants(self.check_invariants());
+        self.restore_stats_history(&collection);
+        self.collections.write().insert(name, collection.clone());
+        self.attach_journal(&collection, fresh_journal);
+        self.attach_point_store(&collection, &store);
+        Ok(())
+    }
+
+    /// Persist a collection's configuration to its manifest, e.g. after its
+    /// sparse vector modifiers or text dictionaries changed
+    pub fn save_collection_config(&self, collection: &Collection) -> Result<()> {
+        let manifest = CollectionManifest::new(collection.name(), Self::snapshot_config(collection));
+        manifest.write(&self.collection_dir(collection.name()))
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        self.manifests.write().insert(collection.name().to_string(), manifest);
+        Ok(())
+    }
+
+    /// Load a collection's points into memory, unless they already are
+    pub fn load_collection(&self, name: &str) -> Result<Arc<Collection>> {
+        let name = self.resolve_collection_name(name);
+        let loading = self.loading.lock();
+        if let Some(collection) = self.collections.read().get(&name) {
+            return Ok(collection.clone());
+        }
+        let manifest = self.manifests.read().get(&name).cloned()
+            .ok_or_else(|| Error::CollectionNotFound(name.clone()))?;
+
+        let store = self.open_store(&loading, &name)?;
+        let collection = Arc::new(Self::collection_from_config(&name, &manifest.config));
+        collection.set_check_invariants(self.check_invariants());
+        let points = store.load_points().map_err(|e| Error::Storage(e.to_string()))?;
+        collection.load_points(points)?;
+        self.restore_stats_history(&collection);
+
+        self.collections.write().insert(name, collection.clone());
+        self.attach_journal(&collection, false);
+        self.attach_point_store(&collection, &store);
+        Ok(collection)
+    }
+
+    /// Drop a loaded collection from memory and close its points
+    /// environment. Its manifest stays, and the next access loads it again.
+    /// Every write is already on disk; the environment is synced before it
+    /// is closed. Until this returns, [`Self::get_collection`] does not
+    /// reload the collection and [`Self::is_unloading`] reports it. Returns
+    /// false if the collection was not loaded.
+    pub fn unload_collection(&self, name: &str) -> Result<bool> {
+        let name = self.resolve_collection_name(name);
+        self.unloading.write().insert(name.clone());
+        let result = self.unload_marked(&name);
+        self.unloading.write().remove(&name);
+        result
+    }
+
+    fn unload_marked(&self, name: &str) -> Result<bool> {
+        let _loading = self.loading.lock();
+        let Some(collection) = self.collections.write().remove(name) else {
+            return Ok(false);
+        };
+        // Writes already holding the collection finish and reach the store;
+        // later ones fail rather than change the copy being dropped
+        collection.close();
+        self.journals.write().remove(name);
+        if let Some(store) = self.stores.write().remove(name) {
+            store.sync().map_err(|e| Error::Storage(e.to_string()))?;
+        }
+        Ok(true)
+    }
+
+    /// Whether a collection's points are in memory
+    pub fn is_loaded(&self, name: &str) -> bool {
+        let name = self.resolve_collection_name(name);
+        self.collections.read().contains_key(&name)
+    }
+
+    /// Whether a collection is being unloaded right now
+    pub fn is_unloading(&self, name: &str) -> bool {
+        let name = self.resolve_collection_name(name);
+        self.unloading.read().contains(&name)
+    }
+
+    /// A collection by name or alias if its points are in memory; never
+    /// loads it
+    pub fn loaded_collection(&self, name: &str) -> Option<Arc<Collection>> {
+        let name = self.resolve_collection_name(name);
+        self.collections.read().get(&name).cloned()
+    }
+
+    /// Collections whose points are in memory
+    pub fn loaded_collections(&self) -> Vec<Arc<Collection>> {
+        self.collections.read().values().cloned().collect()
+    }
+
     /// Start background save thread
     fn start_background_save(&self) {
         let collections = self.collections.clone();
         let persistence = self.persistence.clone();
+        let journals = self.journals.clone();
         let interval = self.save_interval.unwrap_or(Duration::from_secs(300));
 
         std::thread::spawn(move || {
@@ -115,72 +449,495 @@ impl StorageManager {
                 if !ForkBasedPersistence::is_bgsave_in_progress() {
                     let collections_map = collections.read();
                     if let Err(e) = persistence.bgsave(&collections_map) {
-                        eprintln!("Background save error: {}", e);
+                        tracing::error!("Background save error: {}", e);
+                    }
+                }
+
+                Self::compact_journals(&collections, &journals);
+            }
+        });
+    }
+
+    /// Rewrite journal files that have grown past their retained size
+    fn compact_journals(
+        collections: &RwLock<HashMap<String, Arc<Collection>>>,
+        journals: &RwLock<HashMap<String, Arc<FileJournalSink>>>,
+    ) {
+        let collections = collections.read();
+        for (name, sink) in journals.read().iter() {
+            let Some(collection) = collections.get(name) else { continue };
+            let journal = collection.journal();
+            if sink.appended() <= journal.retention().max_entries {
+                continue;
+            }
+            let entries = journal.since(None, usize::MAX);
+            if let Err(e) = sink.compact(&entries) {
+                tracing::error!("Journal compaction error for {}: {}", name, e);
+            }
+        }
+    }
+
+    fn journal_path(&self, collection_name: &str) -> Result<PathBuf> {
+        Self::validate_collection_name(collection_name)?;
+        Ok(self.data_dir.join("journal").join(format!("{}.jsonl", collection_name)))
+    }
+
+    /// Load the persisted journal for a collection and start persisting new entries.
+    /// With `fresh` any journal left over from a previous collection of the same name is discarded.
+    fn attach_journal(&self, collection: &Arc<Collection>, fresh: bool) {
+        let name = collection.name().to_string();
+        let path = match self.journal_path(&name) {
+            Ok(path) => path,
+            Err(e) => {
+                tracing::warn!("Not persisting the journal of {:?}: {}", name, e);
+                return;
+            }
+        };
+
+        if fresh {
+            let _ = std::fs::remove_file(&path);
+        }
+
+        let entries = match FileJournalSink::load(&path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("Failed to load journal for {}: {}", name, e);
+                Vec::new()
+            }
+        };
+
+        let journal = collection.journal();
+        journal.set_retention(*self.journal_retention.read());
+        collection.restore_journal(entries);
+
+        match FileJournalSink::new(&path) {
+            Ok(sink) => {
+                let sink = Arc::new(sink);
+                journal.set_sink(Some(sink.clone() as Arc<dyn JournalSink>));
+                self.journals.write().insert(name, sink);
+            }
+            Err(e) => tracing::warn!("Failed to open journal for {}: {}", name, e),
+        }
+    }
+
+    fn detach_journal(&self, collection_name: &str) {
+        if let Some(sink) = self.journals.write().remove(collection_name) {
+            let _ = std::fs::remove_file(sink.path());
+        }
+    }
+
+    /// Set how many journal entries are kept, for all current and future collections
+    pub fn set_journal_retention(&self, retention: JournalRetention) {
+        *self.journal_retention.write() = retention;
+        for collection in self.collections.read().values() {
+            collection.journal().set_retention(retention);
+        }
+    }
+
+    pub fn journal_retention(&self) -> JournalRetention {
+        *self.journal_retention.read()
+    }
+
+    /// Limit the JSON size of payloads written through the APIs; None lifts
+    /// the limit
+    pub fn set_max_payload_bytes(&self, limit: Option<usize>) {
+        *self.max_payload_bytes.write() = limit;
+    }
+
+    pub fn max_payload_bytes(&self) -> Option<usize> {
+        *self.max_payload_bytes.read()
+    }
+
+    /// Refuse point writes to a collection while it has `limit` or more
+    /// pending points (see [`Collection::pending_points`]), which pile up
+    /// while its HNSW graph is rebuilt; None lifts the limit
+    pub fn set_max_pending_points(&self, limit: Option<usize>) {
+        *self.max_pending_points.write() = limit;
+    }
+
+    pub fn max_pending_points(&self) -> Option<usize> {
+        *self.max_pending_points.read()
+    }
+
+    /// Why a point write to `collection` is refused for now, if it is;
+    /// each refusal is counted in [`StorageManager::rejected_writes`]
+    pub fn admission_error(&self, collection: &Collection) -> Option<String> {
+        let limit = self.max_pending_points()?;
+        let pending = collection.pending_points();
+        if pending < limit {
+            return None;
+        }
+        self.rejected_writes.fetch_add(1, Ordering::Relaxed);
+        Some(format!(
+            "Collection {} has {} points pending indexing (limit {}), retry later",
+            collection.name(),
+            pending,
+            limit
+        ))
+    }
+
+    /// Point writes refused by [`StorageManager::admission_error`] so far
+    pub fn rejected_writes(&self) -> u64 {
+        self.rejected_writes.load(Ordering::Relaxed)
+    }
+
+    /// Check the indexes of all current and future collections against
+    /// their points after every write (see [`Collection::set_check_invariants`])
+    pub fn set_check_invariants(&self, enabled: bool) {
+        self.check_invariants.store(enabled, Ordering::Relaxed);
+        for collection in self.collections.read().values() {
+            collection.set_check_invariants(enabled);
+        }
+    }
+
+    pub fn check_invariants(&self) -> bool {
+        self.check_invariants.load(Ordering::Relaxed)
+    }
+
+    /// Replace the locks, returning the previous ones. Locks are kept in
+    /// memory only and lifted by a restart.
+    pub fn set_locks(&self, locks: Locks) -> Locks {
+        std::mem::replace(&mut *self.locks.write(), locks)
+    }
+
+    pub fn locks(&self) -> Locks {
+        self.locks.read().clone()
+    }
+
+    /// The error for a write while writes are locked, or None if they are not
+    pub fn write_lock_error(&self) -> Option<String> {
+        let locks = self.locks.read();
+        locks.write.then(|| {
+            locks.error_message.clone().unwrap_or_else(|| DEFAULT_WRITE_LOCK_MESSAGE.to_string())
+        })
+    }
+
+    /// Load every collection and warm it up (see `Collection::warm_up`) on
+    /// a background thread, running up to `queries` searches on each.
+    /// `is_warming_up` is true from this call until the thread finishes.
+    pub fn start_warm_up(self: &Arc<Self>, queries: usize) -> std::thread::JoinHandle<WarmUpSummary> {
+        self.warming_up.store(true, Ordering::Release);
+        let manager = self.clone();
+        std::thread::spawn(move || {
+            // Cleared however the thread ends, so a panicking warm-up does
+            // not keep the node unready
+            struct WarmingUp<'a>(&'a AtomicBool);
+            impl Drop for WarmingUp<'_> {
+                fn drop(&mut self) {
+                    self.0.store(false, Ordering::Release);
+                }
+            }
+            let _warming_up = WarmingUp(&manager.warming_up);
+            let started = Instant::now();
+            let mut summary = WarmUpSummary { collections: 0, points: 0, queries: 0, elapsed: Duration::ZERO };
+            for name in manager.list_collections() {
+                let Some(collection) = manager.get_collection(&name) else { continue };
+                match collection.warm_up(queries) {
+                    Ok(run) => {
+                        summary.collections += 1;
+                        summary.points += collection.count();
+                        summary.queries += run;
                     }
+                    Err(e) => tracing::warn!("Failed to warm up collection {}: {}", name, e),
                 }
             }
+            summary.elapsed = started.elapsed();
+            summary
+        })
+    }
+
+    pub fn is_warming_up(&self) -> bool {
+        self.warming_up.load(Ordering::Acquire)
+    }
+
+    /// Sample the statistics of every loaded collection each `interval` on
+    /// a background thread, saving each collection's history to its
+    /// directory. Unloaded collections are not sampled. The thread stops
+    /// once the manager is dropped.
+    pub fn start_stats_history(self: &Arc<Self>, interval: Duration) {
+        *self.stats_interval.write() = Some(interval);
+        let manager = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            let Some(manager) = manager.upgrade() else { return };
+            manager.sample_stats();
         });
     }
 
+    /// Take a statistics sample of every loaded collection and save the
+    /// histories
+    pub fn sample_stats(&self) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        for collection in self.loaded_collections() {
+            collection.sample_stats(now);
+            if let Err(e) = self.save_stats_history(&collection) {
+                tracing::warn!("Failed to save statistics history of {}: {}", collection.name(), e);
+            }
+        }
+    }
+
+    /// Re-run the ef tuning of every loaded collection that is due (see
+    /// `Collection::ef_tuning_due`) each `interval` on a background thread.
+    /// The thread stops once the manager is dropped.
+    pub fn start_ef_tuner(self: &Arc<Self>, interval: Duration) {
+        let manager = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            let Some(manager) = manager.upgrade() else { return };
+            manager.tune_due_collections();
+        });
+    }
+
+    /// Run the ef tuning of every loaded collection that is due and save
+    /// the tuned `ef` with the collection's config
+    pub fn tune_due_collections(&self) {
+        for collection in self.loaded_collections() {
+            if !collection.ef_tuning_due() {
+                continue;
+            }
+            let tuned = collection.tune_ef()
+                .and_then(|_| self.save_collection_config(&collection));
+            if let Err(e) = tuned {
+                tracing::warn!("Failed to tune ef of collection {}: {}", collection.name(), e);
+            }
+        }
+    }
+
+    /// Interval of the statistics sampler, None if it isn't running
+    pub fn stats_interval(&self) -> Option<Duration> {
+        *self.stats_interval.read()
+    }
+
+    fn save_stats_history(&self, collection: &Collection) -> std::io::Result<()> {
+        let dir = self.collection_dir(collection.name());
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        let data = serde_json::to_vec(&collection.stats_history(None))?;
+        atomicwrites::AtomicFile::new(dir.join(STATS_HISTORY_FILE), atomicwrites::AllowOverwrite)
+            .write(|f| std::io::Write::write_all(f, &data))
+            .map_err(std::io::Error::from)
+    }
+
+    /// Read back the statistics history saved for a collection, if any
+    fn restore_stats_history(&self, collection: &Collection) {
+        let path = self.collection_dir(collection.name()).join(STATS_HISTORY_FILE);
+        let Ok(data) = std::fs::read(&path) else { return };
+        match serde_json::from_slice::<Vec<StatsSample>>(&data) {
+            Ok(samples) => collection.restore_stats_history(samples),
+            Err(e) => tracing::warn!("Failed to read statistics history of {}: {}", collection.name(), e),
+        }
+    }
+
+    /// Why the node should not take traffic yet: a startup warm-up, or load
+    /// past the [`LoadLimits`]. Until the node has been ready once, snapshot
+    /// restores and HNSW graphs being built for collections with no graph to
+    /// search meanwhile count too; after that they only concern their
+    /// collection (see [`StorageManager::is_restoring`]). Empty when it is
+    /// ready.
+    pub fn readiness_issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if self.is_warming_up() {
+            issues.push("warming up".to_string());
+        }
+        if !self.started_up.load(Ordering::Acquire) {
+            let mut restoring: Vec<String> = self.restoring.read().iter().cloned().collect();
+            restoring.sort();
+            issues.extend(restoring.into_iter().map(|name| format!("restoring collection {} from a snapshot", name)));
+            let mut indexing: Vec<String> = self.loaded_collections()
+                .into_iter()
+                .filter(|c| c.awaiting_index())
+                .map(|c| c.name().to_string())
+                .collect();
+            indexing.sort();
+            issues.extend(indexing.into_iter().map(|name| format!("building the HNSW graph of collection {}", name)));
+            if issues.is_empty() {
+                self.started_up.store(true, Ordering::Release);
+            }
+        }
+        issues.extend(self.overload_issues());
+        issues
+    }
+
+    /// Whether the collection is being restored from a snapshot
+    pub fn is_restoring(&self, collection_name: &str) -> bool {
+        self.restoring.read().contains(collection_name)
+    }
+
+    pub fn set_load_limits(&self, limits: LoadLimits) {
+        *self.load_limits.write() = limits;
+    }
+
+    pub fn load_limits(&self) -> LoadLimits {
+        *self.load_limits.read()
+    }
+
+    /// Load limits the node is currently past. Once past a limit, the node
+    /// stays past it until the load drops below [`LOAD_RECOVERY`] of the
+    /// limit. A collection's p99 latency only counts once it ran
+    /// [`MIN_P99_QUERIES`] queries since its last statistics sample.
+    fn overload_issues(&self) -> Vec<String> {
+        let limits = self.load_limits();
+        let mut issues = Vec::new();
+        match limits.max_queued_jobs {
+            Some(max) => {
+                let background = get_background_system();
+                let queued: usize = [BackgroundJobType::HnswRebuild, BackgroundJobType::LazyFree, BackgroundJobType::Admin]
+                    .into_iter()
+                    .map(|job_type| background.pending_jobs(job_type))
+                    .sum();
+                let overloaded = if self.jobs_overloaded.load(Ordering::Acquire) {
+                    queued as f64 > max as f64 * LOAD_RECOVERY
+                } else {
+                    queued > max
+                };
+                self.jobs_overloaded.store(overloaded, Ordering::Release);
+                if overloaded {
+                    issues.push(format!("{} background jobs queued (limit {})", queued, max));
+                }
+            }
+            None => self.jobs_overloaded.store(false, Ordering::Release),
+        }
+        let mut slow_collections = self.slow_collections.lock();
+        match limits.max_p99_ms {
+            Some(max) => {
+                let mut slow: Vec<(String, f64)> = Vec::new();
+                for collection in self.loaded_collections() {
+                    let name = collection.name();
+                    let p99 = collection.recent_p99_ms()
+                        .filter(|_| collection.recent_queries() >= MIN_P99_QUERIES);
+                    let limit = if slow_collections.contains(name) { max * LOAD_RECOVERY } else { max };
+                    match p99.filter(|&p99| p99 > limit) {
+                        Some(p99) => {
+                            slow_collections.insert(name.to_string());
+                            slow.push((name.to_string(), p99));
+                        }
+                        None => {
+                            slow_collections.remove(name);
+                        }
+                    }
+                }
+                slow.sort_by(|a, b| a.0.cmp(&b.0));
+                issues.extend(slow.into_iter().map(|(name, p99)| {
+                    format!("p99 query latency of collection {} is {:.1} ms (limit {} ms)", name, p99, max)
+                }));
+            }
+            None => slow_collections.clear(),
+        }
+        issues
+    }
+
+    /// Recent write operations for a collection, starting at operation id `since`
+    pub fn get_operations(&self, collection_name: &str, since: Option<u64>, limit: usize) -> Result<Vec<JournalEntry>> {
+        let collection = self.get_collection(collection_name)
+            .ok_or_else(|| Error::CollectionNotFound(collection_name.to_string()))?;
+        Ok(collection.journal().since(since, limit))
+    }
+
     pub fn create_collection(&self, config: CollectionConfig) -> Result<Arc<Collection>> {
         let name = config.name.clone();
-        let mut collections = self.collections.write();
+        Self::validate_collection_name(&name)?;
+        let loading = self.loading.lock();
 
-        if collections.contains_key(&name) {
+        if self.manifests.read().contains_key(&name) {
             return Err(Error::CollectionExists(name));
         }
 
         let collection = Arc::new(Collection::new(config));
-        collections.insert(name.clone(), collection.clone());
+        self.install_collection(&loading, collection.clone(), true)?;
         Ok(collection)
     }
 
+    /// A collection by name or alias, loading its points on first access
     #[inline]
     pub fn get_collection(&self, name: &str) -> Option<Arc<Collection>> {
-        let collections = self.collections.read();
-        // First try direct collection lookup
-        if let Some(col) = collections.get(name) {
+        if let Some(col) = self.collections.read().get(name) {
             return Some(col.clone());
         }
-        // Then try alias lookup
-        let aliases = self.aliases.read();
-        if let Some(collection_name) = aliases.get(name) {
-            return collections.get(collection_name).cloned();
+        let name = self.resolve_collection_name(name);
+        if let Some(col) = self.collections.read().get(&name) {
+            return Some(col.clone());
+        }
+        if !self.manifests.read().contains_key(&name) || self.unloading.read().contains(&name) {
+            return None;
+        }
+        match self.load_collection(&name) {
+            Ok(collection) => Some(collection),
+            Err(e) => {
+                tracing::warn!("Failed to load collection {}: {}", name, e);
+                None
+            }
         }
-        None
     }
 
+    /// Delete a collection by its own name. An alias is refused rather
+    /// than followed, so deleting what looks like a collection never drops
+    /// the data behind an alias by accident. The collection's aliases are
+    /// removed with it.
     pub fn delete_collection(&self, name: &str) -> Result<bool> {
-        let mut collections = self.collections.write();
-        let removed = collections.remove(name).is_some();
-        
-        // Also clean up similarity schema if it exists
-        if removed {
+        let _loading = self.loading.lock();
+        if !self.manifests.read().contains_key(name) {
+            if let Some(target) = self.aliases.read().get(name) {
+                return Err(Error::InvalidConfig(format!(
+                    "'{}' is an alias of collection '{}'; delete the alias, or the collection by its name",
+                    name, target
+                )));
+            }
+        }
+        Self::validate_collection_name(name)?;
+        if self.manifests.write().remove(name).is_none() {
+            return Ok(false);
+        }
+        self.aliases.write().retain(|_, collection| collection != name);
+        if let Some(collection) = self.collections.write().remove(name) {
+            collection.close();
+        }
+        self.stores.write().remove(name);
+        self.detach_journal(name);
+
+        let dir = self.collection_dir(name);
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
         }
-        
-        Ok(removed)
+        Ok(true)
     }
 
+    /// Every collection, loaded or not
     #[inline]
     #[must_use]
     pub fn list_collections(&self) -> Vec<String> {
-        self.collections.read().keys().cloned().collect()
+        self.manifests.read().keys().cloned().collect()
+    }
+
+    /// The manifest of a collection by its own name, loaded or not; never
+    /// loads its points
+    pub fn collection_manifest(&self, name: &str) -> Option<CollectionManifest> {
+        self.manifests.read().get(name).cloned()
     }
 
+    /// Whether `name` is a collection or an alias of one
     #[inline]
     #[must_use]
     pub fn collection_exists(&self, name: &str) -> bool {
-        self.collections.read().contains_key(name)
+        let name = self.resolve_collection_name(name);
+        self.manifests.read().contains_key(&name)
     }
 
-    /// Create an alias for a collection
+    /// Create an alias for a collection. An alias of an alias points at the
+    /// collection itself; a name already used by a collection is refused.
     pub fn create_alias(&self, alias_name: &str, collection_name: &str) -> Result<bool> {
-        // Check that collection exists
-        if !self.collection_exists(collection_name) {
-            return Err(Error::CollectionNotFound(collection_name.to_string()));
+        let collection_name = self.resolve_collection_name(collection_name);
+        if !self.manifests.read().contains_key(&collection_name) {
+            return Err(Error::CollectionNotFound(collection_name));
+        }
+        if self.manifests.read().contains_key(alias_name) {
+            return Err(Error::CollectionExists(alias_name.to_string()));
         }
         let mut aliases = self.aliases.write();
-        aliases.insert(alias_name.to_string(), collection_name.to_string());
+        aliases.insert(alias_name.to_string(), collection_name);
         Ok(true)
     }
 
@@ -192,6 +949,9 @@ impl StorageManager {
 
     /// Rename an alias
     pub fn rename_alias(&self, old_alias: &str, new_alias: &str) -> Result<bool> {
+        if self.manifests.read().contains_key(new_alias) {
+            return Err(Error::CollectionExists(new_alias.to_string()));
+        }
         let mut aliases = self.aliases.write();
         if let Some(collection_name) = aliases.remove(old_alias) {
             aliases.insert(new_alias.to_string(), collection_name);
@@ -209,11 +969,22 @@ impl StorageManager {
             .collect()
     }
 
+    /// Name of the collection `name` refers to, following aliases. Every
+    /// method taking a collection name resolves it with this, except
+    /// [`Self::delete_collection`].
+    pub fn resolve_collection_name(&self, name: &str) -> String {
+        if self.manifests.read().contains_key(name) {
+            return name.to_string();
+        }
+        self.aliases.read().get(name).cloned().unwrap_or_else(|| name.to_string())
+    }
+
     /// List aliases for a specific collection
     pub fn list_collection_aliases(&self, collection_name: &str) -> Vec<String> {
+        let collection_name = self.resolve_collection_name(collection_name);
         self.aliases.read()
             .iter()
-            .filter(|(_, col)| *col == collection_name)
+            .filter(|(_, col)| **col == collection_name)
             .map(|(alias, _)| alias.clone())
             .collect()
     }
@@ -224,6 +995,43 @@ impl StorageManager {
         &self.data_dir
     }
 
+    /// API keys and collection access rules
+    #[inline]
+    pub fn access_control(&self) -> &Arc<AccessControl> {
+        &self.access
+    }
+
+    /// Admin jobs submitted to this instance
+    #[inline]
+    pub fn jobs(&self) -> &Arc<JobRegistry> {
+        &self.jobs
+    }
+
+    /// Record mutating requests in `log`; None stops recording
+    pub fn set_audit_log(&self, log: Option<AuditLog>) {
+        *self.audit_log.write() = log.map(Arc::new);
+    }
+
+    pub fn audit_log(&self) -> Option<Arc<AuditLog>> {
+        self.audit_log.read().clone()
+    }
+
+
+    /// Persist an ingestion offset checkpoint (stored in LMDB)
+    pub fn save_ingest_checkpoint(&self, key: &str, offset: u64) -> Result<()> {
+        let lmdb = self.lmdb.as_ref()
+            .ok_or_else(|| Error::Storage("LMDB storage not available".to_string()))?;
+        lmdb.save_checkpoint(key, offset)
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Last persisted ingestion offset for `key`
+    pub fn ingest_checkpoint(&self, key: &str) -> Result<Option<u64>> {
+        let lmdb = self.lmdb.as_ref()
+            .ok_or_else(|| Error::Storage("LMDB storage not available".to_string()))?;
+        lmdb.get_checkpoint(key)
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
 
     /// Trigger background save
     pub fn bgsave(&self) -> Result<bool> {
@@ -239,6 +1047,15 @@ impl StorageManager {
             .map_err(|e| Error::Storage(e.to_string()))
     }
 
+    /// Choose how background saves capture the collections
+    pub fn set_save_strategy(&self, strategy: SaveStrategy) {
+        self.persistence.set_strategy(strategy);
+    }
+
+    pub fn save_strategy(&self) -> SaveStrategy {
+        self.persistence.strategy()
+    }
+
     /// Get last save time
     pub fn last_save_time(&self) -> u64 {
         ForkBasedPersistence::last_save_time()
@@ -253,71 +1070,197 @@ impl StorageManager {
 
     /// Create a snapshot for a collection
     pub fn create_collection_snapshot(&self, collection_name: &str) -> Result<SnapshotDescription> {
-        let collections = self.collections.read();
-        let collection = collections.get(collection_name)
+        self.create_collection_snapshot_encoded(collection_name, VectorEncoding::F32)
+    }
+
+    /// Create a snapshot for a collection, writing vectors with `encoding`
+    pub fn create_collection_snapshot_encoded(&self, collection_name: &str, encoding: VectorEncoding) -> Result<SnapshotDescription> {
+        let collection_name = self.resolve_collection_name(collection_name);
+        let collection = self.get_collection(&collection_name)
+            .ok_or_else(|| Error::CollectionNotFound(collection_name.clone()))?;
+        let encoding = Self::snapshot_encoding(&collection, encoding);
+
+        // Read the op count first: writes racing with the copy below are
+        // then also picked up by the next differential snapshot. Points are
+        // copied one at a time, each under a brief read lock, so writers
+        // never wait for the whole collection to be copied.
+        let op_count = collection.operation_count();
+        let points = collection.iter_filtered(None, Projection::ALL);
+
+        let snapshot_data = CollectionSnapshotData {
+            name: collection_name.to_string(),
+            config: Self::snapshot_config(&collection),
+            points: points.map(|point| Self::snapshot_point(&point)).collect(),
+            created_at: Self::unix_now(),
+            op_count,
+            delta: None,
+        };
+
+        self.snapshots.create_collection_snapshot(snapshot_data, encoding)
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Create a differential snapshot holding only the points changed since
+    /// `base_snapshot` (a full or differential snapshot of the same
+    /// collection). Changes are taken from the operation journal, so the
+    /// journal must still retain every operation since the base was taken.
+    pub fn create_differential_snapshot(
+        &self,
+        collection_name: &str,
+        base_snapshot: &str,
+        encoding: VectorEncoding,
+    ) -> Result<SnapshotDescription> {
+        let collection_name = self.resolve_collection_name(collection_name);
+        let collection_name = collection_name.as_str();
+        let collection = self.get_collection(collection_name)
             .ok_or_else(|| Error::CollectionNotFound(collection_name.to_string()))?;
+        let encoding = Self::snapshot_encoding(&collection, encoding);
+        let (base_op_count, _) = self.snapshots.load_snapshot_info(collection_name, base_snapshot)
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        let op_count = collection.operation_count();
+        if base_op_count > op_count {
+            return Err(Error::InvalidConfig(format!(
+                "snapshot '{}' is ahead of collection '{}'; take a full snapshot",
+                base_snapshot, collection_name
+            )));
+        }
+        let entries = collection.journal().since(Some(base_op_count), usize::MAX);
+        if base_op_count < op_count && entries.first().map(|e| e.op_id) != Some(base_op_count) {
+            return Err(Error::InvalidConfig(format!(
+                "the operation journal no longer covers everything since snapshot '{}'; take a full snapshot",
+                base_snapshot
+            )));
+        }
 
-        let points = collection.get_all_points();
+        let changed: std::collections::BTreeSet<String> = entries.into_iter()
+            .filter(|e| e.op_id < op_count)
+            .flat_map(|e| e.point_ids)
+            .collect();
+        let mut points = Vec::new();
+        let mut deleted = Vec::new();
+        for id in changed {
+            match collection.get(&id) {
+                Some(point) => points.push(Self::snapshot_point(&point)),
+                None => deleted.push(id),
+            }
+        }
 
         let snapshot_data = CollectionSnapshotData {
             name: collection_name.to_string(),
-            config: CollectionConfigData {
-                vector_dim: collection.vector_dim(),
-                distance: match collection.distance() {
-                    Distance::Cosine => "Cosine".to_string(),
-                    Distance::Euclidean => "Euclidean".to_string(),
-                    Distance::Dot => "Dot".to_string(),
-                },
-                use_hnsw: collection.use_hnsw(),
-                enable_bm25: collection.enable_bm25(),
-            },
-            points: points.iter().map(|p| PointData {
-                id: match &p.id {
-                    PointId::Integer(i) => i.to_string(),
-                    PointId::String(s) => s.clone(),
-                    PointId::Uuid(u) => u.to_string(),
-                },
-                vector: p.vector.as_slice().to_vec(),
-                multivector: p.multivector.as_ref().map(|mv: &MultiVector| mv.vectors().to_vec()),
-                payload: p.payload.clone(),
-            }).collect(),
-            created_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0),
+            config: Self::snapshot_config(&collection),
+            points,
+            created_at: Self::unix_now(),
+            op_count,
+            delta: Some(DeltaInfo {
+                base: base_snapshot.to_string(),
+                deleted,
+            }),
         };
 
-        self.snapshots.create_collection_snapshot(snapshot_data)
+        self.snapshots.create_collection_snapshot(snapshot_data, encoding)
             .map_err(|e| Error::Storage(e.to_string()))
     }
 
+    /// Packed binary vectors would not survive rounding to half precision,
+    /// so they are always written as they are stored
+    fn snapshot_encoding(collection: &Collection, encoding: VectorEncoding) -> VectorEncoding {
+        match collection.distance() {
+            Distance::Hamming => VectorEncoding::F32,
+            _ => encoding,
+        }
+    }
+
+    pub(crate) fn snapshot_config(collection: &Collection) -> CollectionConfigData {
+        CollectionConfigData {
+            vector_dim: collection.vector_dim(),
+            distance: match collection.distance() {
+                Distance::Cosine => "Cosine".to_string(),
+                Distance::Euclidean => "Euclidean".to_string(),
+                Distance::Dot => "Dot".to_string(),
+                Distance::Hamming => "Hamming".to_string(),
+            },
+            use_hnsw: collection.use_hnsw(),
+            enable_bm25: collection.enable_bm25(),
+            sparse_vectors: collection.sparse_vectors(),
+            sparse_modifiers: collection.sparse_modifiers(),
+            named_multivectors: collection.named_multivectors(),
+            payload_indexes: collection.get_payload_indexes(),
+            keyword_params: (*collection.keyword_params()).clone(),
+            dense_vector_name: collection.dense_vector_name(),
+            text_dictionaries: (*collection.text_dictionaries()).clone(),
+            detect_language: collection.detect_language(),
+            search_defaults: collection.search_defaults(),
+            normalization: collection.normalization(),
+            duplicate_policy: collection.duplicate_policy(),
+            hnsw_config: collection.hnsw_params(),
+            infer_vector_dim: collection.infer_vector_dim(),
+            query_templates: collection.query_templates(),
+            ef_tuning: collection.ef_tuning(),
+            ef_tuning_report: collection.ef_tuning_report(),
+        }
+    }
+
+    fn snapshot_point(p: &Point) -> PointData {
+        PointData {
+            id: match &p.id {
+                PointId::Integer(i) => i.to_string(),
+                PointId::String(s) => s.clone(),
+                PointId::Uuid(u) => u.to_string(),
+            },
+            vector: p.vector.as_slice().to_vec(),
+            multivector: p.multivector.as_ref().map(|mv: &MultiVector| mv.vectors().to_vec()),
+            named_multivectors: p.named_multivectors.iter()
+                .map(|(name, mv)| (name.clone(), mv.vectors().to_vec()))
+                .collect(),
+            sparse_vectors: p.sparse_vectors.clone(),
+            payload: p.payload.clone(),
+        }
+    }
+
+    fn unix_now() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
     /// List snapshots for a collection
     pub fn list_collection_snapshots(&self, collection_name: &str) -> Result<Vec<SnapshotDescription>> {
-        self.snapshots.list_collection_snapshots(collection_name)
+        self.snapshots.list_collection_snapshots(&self.resolve_collection_name(collection_name))
             .map_err(|e| Error::Storage(e.to_string()))
     }
 
     /// Delete a snapshot
     pub fn delete_collection_snapshot(&self, collection_name: &str, snapshot_name: &str) -> Result<bool> {
-        self.snapshots.delete_collection_snapshot(collection_name, snapshot_name)
+        self.snapshots.delete_collection_snapshot(&self.resolve_collection_name(collection_name), snapshot_name)
             .map_err(|e| Error::Storage(e.to_string()))
     }
 
     /// Get snapshot file path for download
     pub fn get_snapshot_path(&self, collection_name: &str, snapshot_name: &str) -> Option<PathBuf> {
-        self.snapshots.get_snapshot_path(collection_name, snapshot_name)
+        self.snapshots.get_snapshot_path(&self.resolve_collection_name(collection_name), snapshot_name)
     }
 
     /// Recover collection from a snapshot file
-    pub fn recover_from_snapshot(&self, collection_name: &str, snapshot_name: &str) -> Result<Arc<Collection>> {
-        let snapshot_data = self.snapshots.load_collection_snapshot(collection_name, snapshot_name)
+    pub fn recover_from_snapshot(&self, collection_name: &str, snapshot_name: &str, options: &RecoverOptions) -> Result<Arc<Collection>> {
+        let collection_name = self.resolve_collection_name(collection_name);
+        let snapshot_data = self.snapshots.load_collection_snapshot(&collection_name, snapshot_name)
             .map_err(|e| Error::Storage(e.to_string()))?;
 
-        self.restore_collection_from_data_with_name(snapshot_data, Some(collection_name))
+        self.restore_snapshot_chain(snapshot_data, &collection_name, options)
     }
 
     /// Recover collection from a URL
-    pub async fn recover_from_url(&self, collection_name: &str, url: &str, checksum: Option<&str>) -> Result<Arc<Collection>> {
+    pub async fn recover_from_url(
+        &self,
+        collection_name: &str,
+        url: &str,
+        checksum: Option<&str>,
+        options: &RecoverOptions,
+    ) -> Result<Arc<Collection>> {
+        let collection_name = self.resolve_collection_name(collection_name);
+        let collection_name = collection_name.as_str();
         let snapshot_path = self.snapshots.download_snapshot_from_url(collection_name, url, checksum)
             .await
             .map_err(|e| Error::Storage(e.to_string()))?;
@@ -325,66 +1268,214 @@ impl StorageManager {
         let snapshot_data = self.snapshots.load_snapshot_from_path(&snapshot_path)
             .map_err(|e| Error::Storage(e.to_string()))?;
 
-        self.restore_collection_from_data_with_name(snapshot_data, Some(collection_name))
+        self.restore_snapshot_chain(snapshot_data, collection_name, options)
     }
 
-    fn restore_collection_from_data_with_name(&self, data: CollectionSnapshotData, target_name: Option<&str>) -> Result<Arc<Collection>> {
-        let collection_name = target_name.unwrap_or(&data.name).to_string();
-        
-        let config = CollectionConfig {
-            name: collection_name.clone(),
-            vector_dim: data.config.vector_dim,
-            distance: match data.config.distance.as_str() {
-                "Cosine" => Distance::Cosine,
-                "Euclidean" => Distance::Euclidean,
-                "Dot" => Distance::Dot,
-                _ => Distance::Cosine,
-            },
-            use_hnsw: data.config.use_hnsw,
-            enable_bm25: data.config.enable_bm25,
-        };
+    /// Restore a snapshot. A differential snapshot is applied on top of its
+    /// base chain, which is read from the collection's snapshot directory:
+    /// the full snapshot is loaded first, then each delta in order. The
+    /// resulting points are restored according to `options`.
+    fn restore_snapshot_chain(&self, data: CollectionSnapshotData, collection_name: &str, options: &RecoverOptions) -> Result<Arc<Collection>> {
+        self.restoring.write().insert(collection_name.to_string());
+        let result = self.restore_marked(data, collection_name, options);
+        self.restoring.write().remove(collection_name);
+        result
+    }
 
-        {
-            let mut collections = self.collections.write();
-            collections.remove(&collection_name);
+    fn restore_marked(&self, data: CollectionSnapshotData, collection_name: &str, options: &RecoverOptions) -> Result<Arc<Collection>> {
+        let mut chain = vec![data];
+        let mut seen = std::collections::HashSet::new();
+        while let Some(base) = chain.last().and_then(|d| d.delta.as_ref()).map(|d| d.base.clone()) {
+            if !seen.insert(base.clone()) {
+                return Err(Error::Storage(format!("snapshot chain loops back to '{}'", base)));
+            }
+            let base_data = self.snapshots.load_collection_snapshot(collection_name, &base)
+                .map_err(|e| Error::Storage(format!("base snapshot '{}': {}", base, e)))?;
+            chain.push(base_data);
         }
 
-        let collection = Arc::new(Collection::new(config));
+        // Fold the chain into the final point set, keeping snapshot order
+        let full = chain.pop().expect("chain holds at least the requested snapshot");
+        let mut config = full.config;
+        let mut order: Vec<String> = Vec::with_capacity(full.points.len());
+        let mut points: HashMap<String, PointData> = HashMap::with_capacity(full.points.len());
+        for point_data in full.points {
+            order.push(point_data.id.clone());
+            points.insert(point_data.id.clone(), point_data);
+        }
+        while let Some(delta) = chain.pop() {
+            config.sparse_modifiers.extend(delta.config.sparse_modifiers);
+            config.sparse_vectors.extend(delta.config.sparse_vectors);
+            config.named_multivectors.extend(delta.config.named_multivectors);
+            config.payload_indexes = delta.config.payload_indexes;
+            config.keyword_params = delta.config.keyword_params;
+            for id in delta.delta.iter().flat_map(|d| &d.deleted) {
+                points.remove(id);
+            }
+            for point_data in delta.points {
+                if !points.contains_key(&point_data.id) {
+                    order.push(point_data.id.clone());
+                }
+                points.insert(point_data.id.clone(), point_data);
+            }
+        }
+        let points: Vec<Point> = order.into_iter()
+            .filter_map(|id| points.remove(&id))
+            .map(Self::point_from_data)
+            .filter(|point| options.filter.as_ref().map_or(true, |f| f.matches(point)))
+            .collect();
 
-        for point_data in data.points {
-            let point_id = point_data.id.parse::<u64>()
-                .map(PointId::Integer)
-                .unwrap_or_else(|_| PointId::String(point_data.id.clone()));
-
-            let point = if let Some(mv_data) = point_data.multivector {
-                match MultiVector::new(mv_data) {
-                    Ok(mv) => Point::new_multi(point_id, mv, point_data.payload),
-                    Err(e) => {
-                        eprintln!("Warning: Failed to create multivector: {}", e);
-                        Point::new(point_id, Vector::new(point_data.vector), point_data.payload)
+        let existing = self.get_collection(collection_name);
+        match (options.scope, existing) {
+            (RestoreScope::PayloadOnly, None) => Err(Error::CollectionNotFound(collection_name.to_string())),
+            (RestoreScope::PayloadOnly, Some(collection)) => {
+                for point in points {
+                    let id = point.id.to_string();
+                    let Some(current) = collection.get(&id) else { continue };
+                    if options.priority == SnapshotPriority::Replica && current.payload.is_some() {
+                        continue;
                     }
+                    match point.payload {
+                        Some(payload) => collection.overwrite_payload(&id, payload)?,
+                        None => collection.clear_payload(&id)?,
+                    };
                 }
-            } else {
-                Point::new(
-                    point_id,
-                    Vector::new(point_data.vector),
-                    point_data.payload,
-                )
-            };
+                Ok(collection)
+            }
+            (scope, Some(collection)) if options.priority == SnapshotPriority::Replica => {
+                if scope == RestoreScope::Full {
+                    for point in points {
+                        if collection.get(&point.id.to_string()).is_none() {
+                            if let Err(e) = collection.upsert(point) {
+                                tracing::warn!("Failed to restore point: {}", e);
+                            }
+                        }
+                    }
+                }
+                Ok(collection)
+            }
+            (scope, _) => {
+                let points = if scope == RestoreScope::ConfigOnly { Vec::new() } else { points };
+                self.replace_collection(collection_name, &config, points)
+            }
+        }
+    }
 
-            if let Err(e) = collection.upsert(point) {
-                eprintln!("Warning: Failed to restore point: {}", e);
+    fn point_from_data(point_data: PointData) -> Point {
+        let point_id = point_data.id.parse::<u64>()
+            .map(PointId::Integer)
+            .unwrap_or_else(|_| PointId::String(point_data.id.clone()));
+
+        let mut point = if let Some(mv_data) = point_data.multivector {
+            match MultiVector::new(mv_data) {
+                Ok(mv) => Point::new_multi(point_id, mv, point_data.payload),
+                Err(e) => {
+                    tracing::warn!("Failed to create multivector: {}", e);
+                    Point::new(point_id, Vector::new(point_data.vector), point_data.payload)
+                }
+            }
+        } else {
+            Point::new(
+                point_id,
+                Vector::new(point_data.vector),
+                point_data.payload,
+            )
+        };
+        point.sparse_vectors = point_data.sparse_vectors;
+        for (name, mv_data) in point_data.named_multivectors {
+            match MultiVector::new(mv_data) {
+                Ok(mv) => point.add_named_multivector(name, mv),
+                Err(e) => tracing::warn!("Failed to create multivector '{}': {}", name, e),
             }
         }
+        point
+    }
 
-        {
-            let mut collections = self.collections.write();
-            collections.insert(collection_name, collection.clone());
+    /// Replace a collection with a fresh one holding `points`
+    fn replace_collection(&self, collection_name: &str, config_data: &CollectionConfigData, points: Vec<Point>) -> Result<Arc<Collection>> {
+        let collection = Arc::new(Self::collection_from_config(collection_name, config_data));
+        for point in points {
+            if let Err(e) = collection.upsert(point) {
+                tracing::warn!("Failed to restore point: {}", e);
+            }
         }
 
+        let loading = self.loading.lock();
+        self.install_collection(&loading, collection.clone(), true)?;
         Ok(collection)
     }
 
+    /// Snapshot the whole storage as a tar archive laid out like the data
+    /// directory: every collection's manifest plus a compacted copy of its
+    /// points environment. Each collection is copied from one read
+    /// transaction, so writes continue; unloaded collections are copied
+    /// without loading their points.
+    pub fn create_full_snapshot(&self) -> Result<SnapshotDescription> {
+        let path = self.snapshots.new_full_snapshot_path();
+        let partial = path.with_extension("partial");
+        self.write_full_snapshot(&partial)
+            .and_then(|()| Ok(std::fs::rename(&partial, &path)?))
+            .map_err(|e| {
+                let _ = std::fs::remove_file(&partial);
+                let _ = std::fs::remove_dir_all(partial.with_extension("staging"));
+                Error::Storage(e.to_string())
+            })?;
+        self.snapshots.describe_full_snapshot(&path)
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    fn write_full_snapshot(&self, path: &Path) -> anyhow::Result<()> {
+        let staging = path.with_extension("staging");
+        std::fs::create_dir_all(&staging)?;
+        let mut archive = tar::Builder::new(std::fs::File::create(path)?);
+
+        let loading = self.loading.lock();
+        let manifests: Vec<CollectionManifest> = self.manifests.read().values().cloned().collect();
+        for manifest in manifests {
+            let dir = Path::new("collections").join(&manifest.name);
+            let manifest_data = serde_json::to_vec_pretty(&manifest)?;
+            let mut header = tar::Header::new_gnu();
+            header.set_size(manifest_data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(Self::unix_now());
+            archive.append_data(&mut header, dir.join("manifest.json"), manifest_data.as_slice())?;
+
+            // Loaded collections already have their environment open; others
+            // are opened just for the copy
+            let loaded = self.stores.read().get(&manifest.name).cloned();
+            let store = match loaded {
+                Some(store) => store,
+                None => Arc::new(CollectionStore::open(self.collection_dir(&manifest.name))?),
+            };
+            let copy = staging.join(format!("{}.mdb", manifest.name));
+            store.copy_to(&copy)?;
+            archive.append_path_with_name(&copy, dir.join("points").join("data.mdb"))?;
+            std::fs::remove_file(&copy)?;
+        }
+        drop(loading);
+
+        archive.into_inner()?.sync_all()?;
+        std::fs::remove_dir(&staging)?;
+        Ok(())
+    }
+
+    /// List full storage snapshots
+    pub fn list_full_snapshots(&self) -> Result<Vec<SnapshotDescription>> {
+        self.snapshots.list_full_snapshots()
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Get full storage snapshot file path for download
+    pub fn get_full_snapshot_path(&self, snapshot_name: &str) -> Option<PathBuf> {
+        self.snapshots.get_full_snapshot_path(snapshot_name)
+    }
+
+    /// Delete a full storage snapshot
+    pub fn delete_full_snapshot(&self, snapshot_name: &str) -> Result<bool> {
+        self.snapshots.delete_full_snapshot(snapshot_name)
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
     /// List all snapshots
     pub fn list_all_snapshots(&self) -> Result<Vec<SnapshotDescription>> {
         self.snapshots.list_all_snapshots()
@@ -397,13 +1488,16 @@ impl StorageManager {
         collection_name: &str, 
         data: &[u8],
         filename: Option<&str>,
+        options: &RecoverOptions,
     ) -> Result<Arc<Collection>> {
+        let collection_name = self.resolve_collection_name(collection_name);
+        let collection_name = collection_name.as_str();
         let snapshot_path = self.snapshots.save_uploaded_snapshot(collection_name, data, filename)
             .map_err(|e| Error::Storage(e.to_string()))?;
 
         let snapshot_data = self.snapshots.load_snapshot_from_path(&snapshot_path)
             .map_err(|e| Error::Storage(e.to_string()))?;
 
-        self.restore_collection_from_data_with_name(snapshot_data, Some(collection_name))
+        self.restore_snapshot_chain(snapshot_data, collection_name, options)
     }
 }
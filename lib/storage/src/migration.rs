@@ -0,0 +1,197 @@
+//! Versioned migrations of the data directory layout
+//!
+//! The layout version of a data directory is kept in `format.json` at its
+//! root. [`StorageManager::new`](crate::StorageManager::new) calls
+//! [`migrate`] before it opens anything, which runs the migrations between
+//! the directory's version and [`DATA_FORMAT_VERSION`] in order and records
+//! the version after each, so an interrupted upgrade resumes at the step
+//! that did not finish. Before a step runs, the paths it changes are copied
+//! to `migration_backups/v<from>-to-v<to>-<time>/`; copying them back and
+//! restoring the old version in `format.json` undoes it.
+//!
+//! A directory without `format.json` that holds anything was written before
+//! versions were kept and counts as version 0; an empty one is new and gets
+//! the current version. A directory of a newer version than this build
+//! knows is refused rather than guessed at.
+
+use crate::collection_store::{check_collection_name, CollectionManifest, CollectionStore, MANIFEST_FILE};
+use crate::dir_lock::LOCK_FILE;
+use crate::lmdb_storage::LmdbStorage;
+use crate::manager::StorageManager;
+use crate::snapshot::CollectionConfigData;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Layout version written by this build
+pub const DATA_FORMAT_VERSION: u32 = 1;
+
+const FORMAT_FILE: &str = "format.json";
+const BACKUP_DIR: &str = "migration_backups";
+
+#[derive(Serialize, Deserialize)]
+struct FormatFile {
+    format_version: u32,
+}
+
+/// One step between layout versions
+struct Migration {
+    /// Version the directory has once the step ran; it runs on directories
+    /// of the version before
+    version: u32,
+    description: &'static str,
+    /// Paths under the data directory the step changes or removes
+    backup: &'static [&'static str],
+    run: fn(&Path) -> Result<()>,
+}
+
+/// Every step, oldest first; the last one's version is
+/// [`DATA_FORMAT_VERSION`]
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "move collections from the shared LMDB environment into their own directories",
+        backup: &["lmdb"],
+        run: split_lmdb_collections,
+    },
+];
+
+/// Layout version recorded in `data_dir`, if any
+pub fn format_version(data_dir: &Path) -> Result<Option<u32>> {
+    let path = data_dir.join(FORMAT_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file: FormatFile = serde_json::from_slice(&fs::read(&path)?)
+        .map_err(|e| anyhow!("{:?} is not a valid format file: {}", path, e))?;
+    Ok(Some(file.format_version))
+}
+
+/// Refuse a data directory written by a newer build
+pub fn check_supported(data_dir: &Path) -> Result<()> {
+    match format_version(data_dir)? {
+        Some(version) if version > DATA_FORMAT_VERSION => bail!(
+            "data directory {:?} has format version {}, but this build of vectX ({}) only reads versions up to {}; \
+             run the vectX version that wrote it or a newer one",
+            data_dir, version, env!("CARGO_PKG_VERSION"), DATA_FORMAT_VERSION
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Bring `data_dir` to [`DATA_FORMAT_VERSION`]. Returns the version it had.
+/// The caller holds the directory's [`DataDirLock`](crate::DataDirLock).
+pub fn migrate(data_dir: &Path) -> Result<u32> {
+    check_supported(data_dir)?;
+    let found = match format_version(data_dir)? {
+        Some(version) => version,
+        None if is_new(data_dir)? => {
+            write_version(data_dir, DATA_FORMAT_VERSION)?;
+            return Ok(DATA_FORMAT_VERSION);
+        }
+        None => 0,
+    };
+
+    let mut version = found;
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > found) {
+        let backup = back_up(data_dir, version, migration)?;
+        tracing::info!(
+            "Migrating data directory {:?} from format version {} to {}: {} (backup in {:?})",
+            data_dir, version, migration.version, migration.description, backup
+        );
+        (migration.run)(data_dir).map_err(|e| anyhow!(
+            "migration of data directory {:?} to format version {} failed: {}; the data it changes is backed up in {:?}",
+            data_dir, migration.version, e, backup
+        ))?;
+        version = migration.version;
+        write_version(data_dir, version)?;
+    }
+    Ok(found)
+}
+
+/// Whether `data_dir` holds nothing but its lock file
+fn is_new(data_dir: &Path) -> Result<bool> {
+    for entry in fs::read_dir(data_dir)? {
+        if entry?.file_name() != LOCK_FILE {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn write_version(data_dir: &Path, version: u32) -> Result<()> {
+    let data = serde_json::to_vec_pretty(&FormatFile { format_version: version })?;
+    atomicwrites::AtomicFile::new(data_dir.join(FORMAT_FILE), atomicwrites::AllowOverwrite)
+        .write(|f| f.write_all(&data))
+        .map_err(|e| anyhow!("failed to write format version: {}", e))
+}
+
+/// Copy the paths `migration` changes into a new backup directory
+fn back_up(data_dir: &Path, from: u32, migration: &Migration) -> Result<PathBuf> {
+    let name = format!("v{}-to-v{}-{}", from, migration.version, chrono::Utc::now().format("%Y%m%dT%H%M%S"));
+    let backup = data_dir.join(BACKUP_DIR).join(name);
+    fs::create_dir_all(&backup)?;
+    for path in migration.backup {
+        let source = data_dir.join(path);
+        if source.exists() {
+            copy_all(&source, &backup.join(path))?;
+        }
+    }
+    Ok(backup)
+}
+
+fn copy_all(source: &Path, target: &Path) -> Result<()> {
+    if source.is_dir() {
+        fs::create_dir_all(target)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &target.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(source, target)?;
+    }
+    Ok(())
+}
+
+/// Version 1: collections that older versions kept in the shared LMDB
+/// environment get their own directories (see `collection_store`). A
+/// collection that already has a directory keeps it, and its LMDB copy is
+/// dropped: either a run that stopped before deleting it moved it already,
+/// or nothing would load it again (the backup still holds it).
+fn split_lmdb_collections(data_dir: &Path) -> Result<()> {
+    let lmdb_dir = data_dir.join("lmdb");
+    if !lmdb_dir.is_dir() {
+        return Ok(());
+    }
+    let lmdb = LmdbStorage::new(&lmdb_dir)?;
+    for name in lmdb.list_collections()? {
+        if let Err(e) = check_collection_name(&name) {
+            tracing::warn!("Keeping collection {:?} in {:?}: {}", name, lmdb_dir, e);
+            continue;
+        }
+        let dir = data_dir.join("collections").join(&name);
+        if dir.join(MANIFEST_FILE).is_file() {
+            tracing::warn!("Collection {} also has a directory {:?}; keeping the directory and dropping the LMDB copy", name, dir);
+            lmdb.delete_collection(&name)?;
+            continue;
+        }
+        let config = lmdb.get_collection(&name)?
+            .ok_or_else(|| anyhow!("configuration of collection {} missing", name))?;
+        let config: CollectionConfigData = serde_json::from_slice(&config)
+            .map_err(|e| anyhow!("configuration of collection {} does not parse: {}", name, e))?;
+        // As loading would, e.g. reading the sparse modifiers of older
+        // versions into their sparse vector configurations
+        let config = StorageManager::snapshot_config(&StorageManager::collection_from_config(&name, &config));
+        let points = lmdb.load_points(&name)?;
+
+        // The manifest goes last: a directory without one is skipped on
+        // startup, and rewritten if the migration runs again
+        CollectionStore::open(&dir)?.replace_points(&points)?;
+        CollectionManifest::new(&name, config).write(&dir)?;
+        lmdb.delete_collection(&name)?;
+        tracing::info!("Moved collection {} ({} points) into {:?}", name, points.len(), dir);
+    }
+    Ok(())
+}
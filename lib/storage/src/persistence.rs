@@ -1,8 +1,14 @@
 use anyhow::Result;
+#[cfg(unix)]
 use nix::unistd::{fork, ForkResult};
+#[cfg(unix)]
 use nix::sys::wait::waitpid;
+#[cfg(unix)]
 use nix::sys::wait::WaitStatus;
+use parking_lot::RwLock;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+#[cfg(unix)]
 use std::process;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
@@ -36,14 +42,66 @@ pub struct CollectionConfigSnapshot {
 pub struct PointSnapshot {
     pub id: String,
     pub vector: Vec<f32>,
+    #[serde(with = "json_text")]
     pub payload: Option<serde_json::Value>,
 }
 
+/// Store payloads as JSON text: bincode is not self-describing and cannot
+/// deserialize a `serde_json::Value` directly
+pub(crate) mod json_text {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<serde_json::Value>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.as_ref().map(|v| v.to_string()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<serde_json::Value>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| serde_json::from_str(&text).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// How a background save captures the collections
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveStrategy {
+    /// fork() a child that serializes its copy-on-write image of the parent's
+    /// memory. Unix only; elsewhere it falls back to `Thread`.
+    Fork,
+    /// Copy each collection's points under its read lock, then serialize and
+    /// write them on a background thread. Works everywhere, at the cost of
+    /// holding a copy of the data until the write finishes.
+    Thread,
+}
+
+impl Default for SaveStrategy {
+    fn default() -> Self {
+        if cfg!(unix) {
+            Self::Fork
+        } else {
+            Self::Thread
+        }
+    }
+}
+
+impl std::str::FromStr for SaveStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fork" => Ok(Self::Fork),
+            "thread" => Ok(Self::Thread),
+            other => Err(format!("invalid save strategy '{}': expected 'fork' or 'thread'", other)),
+        }
+    }
+}
+
 /// Fork-based background save
 pub struct ForkBasedPersistence {
     #[allow(dead_code)]
     data_dir: PathBuf,  // Stored for potential future use
     rdb_filename: PathBuf,
+    strategy: RwLock<SaveStrategy>,
 }
 
 impl ForkBasedPersistence {
@@ -54,45 +112,83 @@ impl ForkBasedPersistence {
         Self {
             data_dir,
             rdb_filename,
+            strategy: RwLock::new(SaveStrategy::default()),
         }
     }
 
-    /// Start background save (bgsave) - forks a child process
+    pub fn strategy(&self) -> SaveStrategy {
+        *self.strategy.read()
+    }
+
+    pub fn set_strategy(&self, strategy: SaveStrategy) {
+        *self.strategy.write() = strategy;
+    }
+
+    /// Start background save (bgsave). Returns false if one is already running.
     pub fn bgsave(&self, collections: &std::collections::HashMap<String, Arc<vectx_core::Collection>>) -> Result<bool> {
         // Check if already in progress
         if BGSAVE_IN_PROGRESS.swap(true, Ordering::Acquire) {
             return Ok(false); // Already in progress
         }
 
+        match self.strategy() {
+            #[cfg(unix)]
+            SaveStrategy::Fork => self.bgsave_fork(collections),
+            _ => self.bgsave_thread(collections),
+        }
+    }
+
+    /// Serialize a copy of the collections on a background thread. Each
+    /// collection is copied under its read lock, so it is saved as of one
+    /// instant even while writes continue.
+    fn bgsave_thread(&self, collections: &std::collections::HashMap<String, Arc<vectx_core::Collection>>) -> Result<bool> {
+        let snapshot = match self.create_snapshot(collections) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                BGSAVE_IN_PROGRESS.store(false, Ordering::Release);
+                return Err(e);
+            }
+        };
+        let rdb_filename = self.rdb_filename.clone();
+        std::thread::spawn(move || {
+            match Self::write_rdb(&rdb_filename, &snapshot) {
+                Ok(size) => {
+                    tracing::info!("Background save completed successfully ({} bytes)", size);
+                    LAST_SAVE_TIME.store(unix_now(), Ordering::Release);
+                }
+                Err(e) => tracing::error!("Background save failed: {}", e),
+            }
+            BGSAVE_IN_PROGRESS.store(false, Ordering::Release);
+        });
+        Ok(true)
+    }
+
+    /// Fork a child process that writes the snapshot
+    #[cfg(unix)]
+    fn bgsave_fork(&self, collections: &std::collections::HashMap<String, Arc<vectx_core::Collection>>) -> Result<bool> {
         // Fork the process
         match unsafe { fork() } {
             Ok(ForkResult::Parent { child, .. }) => {
                 // Parent process - continue serving requests
                 // The child will handle the snapshot
-                eprintln!("Background save started by pid {}", child);
+                tracing::info!("Background save started by pid {}", child);
                 
                 // Spawn a thread to wait for child and reset flag
                 std::thread::spawn(move || {
                     match waitpid(child, None) {
                         Ok(WaitStatus::Exited(_, code)) => {
                             if code == 0 {
-                                eprintln!("Background save completed successfully");
-                                LAST_SAVE_TIME.store(
-                                    std::time::SystemTime::now()
-                                        .duration_since(std::time::UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_secs(),
-                                    Ordering::Release,
-                                );
+                                tracing::info!("Background save completed successfully");
+                                LAST_SAVE_TIME.store(unix_now(), Ordering::Release);
                             } else {
-                                eprintln!("Background save failed with exit code {}", code);
+                                tracing::error!("Background save failed with exit code {}", code);
                             }
                         }
                         Ok(status) => {
-                            eprintln!("Background save child process: {:?}", status);
+                            tracing::info!("Background save child process: {:?}", status);
                         }
                         Err(e) => {
-                            eprintln!("Error waiting for background save: {}", e);
+                            tracing::error!("Error waiting for background save: {}", e);
                         }
                     }
                     BGSAVE_IN_PROGRESS.store(false, Ordering::Release);
@@ -105,22 +201,21 @@ impl ForkBasedPersistence {
                 // Set process title (if possible)
                 eprintln!("Child process: Starting snapshot...");
                 
-                // Create snapshot data
-                let snapshot = self.create_snapshot(collections)?;
-                
-                // Write to temporary file first (atomic write)
-                let temp_file = self.rdb_filename.with_extension("tmp");
-                let data = bincode::serialize(&snapshot)
-                    .map_err(|e| anyhow::anyhow!("Serialization error: {}", e))?;
-                std::fs::write(&temp_file, &data)?;
-                
-                // Atomic rename
-                std::fs::rename(&temp_file, &self.rdb_filename)?;
-                
-                eprintln!("Child process: Snapshot saved to {:?}", self.rdb_filename);
+                // Create snapshot data and write it atomically
+                let result = self.create_snapshot(collections)
+                    .and_then(|snapshot| Self::write_rdb(&self.rdb_filename, &snapshot));
                 
                 // Exit child process
-                process::exit(0);
+                match result {
+                    Ok(_) => {
+                        eprintln!("Child process: Snapshot saved to {:?}", self.rdb_filename);
+                        process::exit(0);
+                    }
+                    Err(e) => {
+                        eprintln!("Child process: Snapshot failed: {}", e);
+                        process::exit(1);
+                    }
+                }
             }
             Err(e) => {
                 BGSAVE_IN_PROGRESS.store(false, Ordering::Release);
@@ -176,7 +271,7 @@ impl ForkBasedPersistence {
     /// - Logs detailed warnings
     pub fn load_snapshot(&self) -> Result<Option<SnapshotData>> {
         if !self.rdb_filename.exists() {
-            eprintln!("[vectX] No snapshot file found, starting with empty database");
+            tracing::info!("No snapshot file found, starting with an empty database");
             return Ok(None);
         }
         
@@ -184,8 +279,7 @@ impl ForkBasedPersistence {
         let version_file = self.rdb_filename.with_extension("version");
         if self.rdb_filename.exists() && !version_file.exists() {
             // Snapshot exists but no version file - incomplete save (crash recovery)
-            eprintln!("[vectX] Warning: Snapshot file exists but version marker missing.");
-            eprintln!("[vectX] This indicates an incomplete save. Starting fresh.");
+            tracing::warn!("Snapshot file exists but its version marker is missing, so a save was incomplete; starting fresh");
             self.backup_and_remove_corrupt_file("incomplete");
             return Ok(None);
         }
@@ -194,15 +288,14 @@ impl ForkBasedPersistence {
         let data = match std::fs::read(&self.rdb_filename) {
             Ok(d) => d,
             Err(e) => {
-                eprintln!("[vectX] Warning: Could not read snapshot file: {}", e);
-                eprintln!("[vectX] Starting with empty database.");
+                tracing::warn!("Could not read snapshot file: {}; starting with an empty database", e);
                 return Ok(None);
             }
         };
         
         // Check minimum size (basic integrity check like Redis)
         if data.len() < 16 {
-            eprintln!("[vectX] Warning: Snapshot file too small ({} bytes), likely corrupt", data.len());
+            tracing::warn!("Snapshot file too small ({} bytes), likely corrupt", data.len());
             self.backup_and_remove_corrupt_file("too_small");
             return Ok(None);
         }
@@ -210,13 +303,12 @@ impl ForkBasedPersistence {
         // Deserialize with error handling (Redis: skip corrupt entries where possible)
         match bincode::deserialize(&data) {
             Ok(snapshot) => {
-                eprintln!("[vectX] Successfully loaded snapshot ({} bytes)", data.len());
+                tracing::info!("Successfully loaded snapshot ({} bytes)", data.len());
                 Ok(Some(snapshot))
             }
             Err(e) => {
                 // Data is corrupted - backup and start fresh
-                eprintln!("[vectX] Warning: Snapshot data is corrupted: {}", e);
-                eprintln!("[vectX] Starting with empty database.");
+                tracing::warn!("Snapshot data is corrupted: {}; starting with an empty database", e);
                 self.backup_and_remove_corrupt_file("corrupt");
                 Ok(None)
             }
@@ -234,13 +326,13 @@ impl ForkBasedPersistence {
         let backup_path = self.rdb_filename.with_file_name(backup_name);
         
         if let Err(e) = std::fs::rename(&self.rdb_filename, &backup_path) {
-            eprintln!("[vectX] Could not backup corrupt file: {}", e);
+            tracing::error!("Could not backup corrupt file: {}", e);
             // Try to delete it instead
             if let Err(del_err) = std::fs::remove_file(&self.rdb_filename) {
-                eprintln!("[vectX] Could not delete corrupt file: {}", del_err);
+                tracing::error!("Could not delete corrupt file: {}", del_err);
             }
         } else {
-            eprintln!("[vectX] Corrupt snapshot backed up to: {:?}", backup_path);
+            tracing::info!("Corrupt snapshot backed up to: {:?}", backup_path);
         }
         
         // Also remove version file if it exists
@@ -262,25 +354,51 @@ impl ForkBasedPersistence {
     /// Uses atomic rename pattern and version markers for data integrity
     pub fn save(&self, collections: &std::collections::HashMap<String, Arc<vectx_core::Collection>>) -> Result<()> {
         let snapshot = self.create_snapshot(collections)?;
-        let temp_file = self.rdb_filename.with_extension("tmp");
-        let version_file = self.rdb_filename.with_extension("version");
-        
-        // Serialize data
-        let data = bincode::serialize(&snapshot)
+        let size = Self::write_rdb(&self.rdb_filename, &snapshot)?;
+        tracing::info!("Snapshot saved ({} bytes)", size);
+        Ok(())
+    }
+
+    /// Write a snapshot so that a crash at any point leaves a loadable dump:
+    /// the data goes to an fsynced temp file that is renamed over dump.rdb,
+    /// then the version marker is replaced the same way. Returns the number
+    /// of bytes written.
+    fn write_rdb(rdb_filename: &Path, snapshot: &SnapshotData) -> Result<usize> {
+        let data = bincode::serialize(snapshot)
             .map_err(|e| anyhow::anyhow!("Serialization error: {}", e))?;
-        
+
         // Write to temp file first (atomic write pattern from Redis)
-        std::fs::write(&temp_file, &data)?;
-        
+        let temp_file = rdb_filename.with_extension("tmp");
+        write_synced(&temp_file, &data)?;
         // Atomic rename (Redis pattern - prevents partial writes)
-        std::fs::rename(&temp_file, &self.rdb_filename)?;
-        
+        std::fs::rename(&temp_file, rdb_filename)?;
+
         // Write version marker (indicates complete save)
-        let version_data = format!("vectx:0.1.0:{}", data.len());
-        std::fs::write(&version_file, version_data)?;
-        
-        eprintln!("[vectX] Snapshot saved ({} bytes)", data.len());
-        Ok(())
+        let version_file = rdb_filename.with_extension("version");
+        let version_temp = rdb_filename.with_extension("version.tmp");
+        write_synced(&version_temp, format!("vectx:0.1.0:{}", data.len()).as_bytes())?;
+        std::fs::rename(&version_temp, &version_file)?;
+
+        // Persist the renames themselves (directories cannot be opened on Windows)
+        #[cfg(unix)]
+        if let Some(dir) = rdb_filename.parent() {
+            std::fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(data.len())
     }
 }
 
+fn write_synced(path: &Path, data: &[u8]) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
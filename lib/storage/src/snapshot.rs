@@ -1,12 +1,13 @@
 // Snapshot support for persistence with LMDB
+use crate::collection_store::check_collection_name;
+use crate::snapshot_format::{self, VectorEncoding};
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, File};
 use std::io::{Read, Write, BufReader, BufWriter, Cursor};
 use std::path::{Path, PathBuf};
 use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
-use flate2::Compression;
 use chrono::{DateTime, Utc};
 use sha2::{Sha256, Digest};
 use tar::Archive;
@@ -26,16 +27,81 @@ pub struct SnapshotDescription {
 pub struct CollectionSnapshotData {
     pub name: String,
     pub config: CollectionConfigData,
+    /// All points, or only the changed ones in a differential snapshot
     pub points: Vec<PointData>,
     pub created_at: u64,
+    /// Write operations the collection had applied when the snapshot was taken
+    #[serde(default)]
+    pub op_count: u64,
+    /// Set on differential snapshots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<DeltaInfo>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// What a differential snapshot is relative to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeltaInfo {
+    /// Snapshot this one applies on top of (full or differential)
+    pub base: String,
+    /// Points deleted since the base
+    pub deleted: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollectionConfigData {
     pub vector_dim: usize,
     pub distance: String,
     pub use_hnsw: bool,
     pub enable_bm25: bool,
+    /// Declared named sparse vectors
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub sparse_vectors: HashMap<String, vectx_core::SparseVectorConfig>,
+    /// Scoring modifiers of undeclared named sparse vectors, as earlier
+    /// versions wrote them; they declare nothing
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub sparse_modifiers: HashMap<String, vectx_core::SparseModifier>,
+    /// Declared named multivectors
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub named_multivectors: HashMap<String, vectx_core::MultiVectorParams>,
+    /// Indexed payload fields and their index types
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub payload_indexes: HashMap<String, vectx_core::PayloadIndexType>,
+    /// Comparison options of keyword payload indexes
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub keyword_params: HashMap<String, vectx_core::KeywordParams>,
+    /// Name the dense vector was declared under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dense_vector_name: Option<String>,
+    /// Synonyms and stopwords applied to text queries
+    #[serde(default, skip_serializing_if = "vectx_core::TextDictionaries::is_empty")]
+    pub text_dictionaries: vectx_core::TextDictionaries,
+    /// Detect the language of upserted text and stem it accordingly
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub detect_language: bool,
+    /// Parameters applied to searches that leave them out
+    #[serde(default, skip_serializing_if = "vectx_core::SearchDefaults::is_empty")]
+    pub search_defaults: vectx_core::SearchDefaults,
+    /// Whether cosine vectors are stored normalized
+    #[serde(default, skip_serializing_if = "vectx_core::NormalizationPolicy::is_default")]
+    pub normalization: vectx_core::NormalizationPolicy,
+    /// What upserts do with a vector another point already has
+    #[serde(default, skip_serializing_if = "vectx_core::DuplicatePolicy::is_default")]
+    pub duplicate_policy: vectx_core::DuplicatePolicy,
+    /// Parameters of the HNSW graph
+    #[serde(default, skip_serializing_if = "vectx_core::HnswParams::is_default")]
+    pub hnsw_config: vectx_core::HnswParams,
+    /// Take the dense dimension from the first point, if `vector_dim` is 0
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub infer_vector_dim: bool,
+    /// Named query templates
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub query_templates: BTreeMap<String, vectx_core::QueryTemplate>,
+    /// Recall target the `ef` search default is tuned to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ef_tuning: Option<vectx_core::EfTuning>,
+    /// Outcome of the last ef tuning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ef_tuning_report: Option<vectx_core::EfTuningReport>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,14 +110,65 @@ pub struct PointData {
     pub vector: Vec<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub multivector: Option<Vec<Vec<f32>>>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub named_multivectors: HashMap<String, Vec<Vec<f32>>>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub sparse_vectors: HashMap<String, vectx_core::SparseVector>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub payload: Option<serde_json::Value>,
 }
 
+/// Which data wins when a snapshot is recovered into an existing collection.
+/// vectX runs one replica per collection, so there is nothing to sync with
+/// and `NoSync` behaves like `Snapshot`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotPriority {
+    /// Replace the collection with the snapshot
+    #[default]
+    Snapshot,
+    /// Keep existing points; only add what the collection lacks
+    Replica,
+    /// Load the snapshot locally without syncing replicas
+    NoSync,
+}
+
+/// Which parts of a snapshot are restored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreScope {
+    /// Configuration and points
+    #[default]
+    Full,
+    /// Configuration only; the collection is recreated empty
+    ConfigOnly,
+    /// Payloads of points that already exist in the collection
+    PayloadOnly,
+}
+
+/// How a snapshot is recovered
+#[derive(Default)]
+pub struct RecoverOptions {
+    pub priority: SnapshotPriority,
+    pub scope: RestoreScope,
+    /// Restore only the snapshot points matching this filter
+    pub filter: Option<Box<dyn vectx_core::Filter>>,
+}
+
 pub struct SnapshotManager {
     snapshot_dir: PathBuf,
 }
 
+/// Refuse a snapshot name that is not a plain file name
+fn check_snapshot_name(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(anyhow!("invalid snapshot name {:?}", name));
+    }
+    Ok(())
+}
+
 impl SnapshotManager {
     pub fn new<P: AsRef<Path>>(snapshot_dir: P) -> Result<Self> {
         let snapshot_dir = snapshot_dir.as_ref().to_path_buf();
@@ -60,61 +177,125 @@ impl SnapshotManager {
     }
 
     /// Get the snapshot directory for a specific collection
-    fn collection_snapshot_dir(&self, collection_name: &str) -> PathBuf {
-        self.snapshot_dir.join(collection_name)
+    fn collection_snapshot_dir(&self, collection_name: &str) -> Result<PathBuf> {
+        check_collection_name(collection_name)?;
+        Ok(self.snapshot_dir.join(collection_name))
+    }
+
+    /// Path of a collection's snapshot
+    fn collection_snapshot_path(&self, collection_name: &str, snapshot_name: &str) -> Result<PathBuf> {
+        check_snapshot_name(snapshot_name)?;
+        Ok(self.collection_snapshot_dir(collection_name)?.join(snapshot_name))
     }
 
-    /// Generate snapshot filename with timestamp
+    /// Generate snapshot filename with timestamp (millisecond resolution, so
+    /// a base and its deltas taken in the same second do not collide)
     fn generate_snapshot_name(collection_name: &str) -> String {
         let now: DateTime<Utc> = Utc::now();
         format!(
             "{}-{}.snapshot",
             collection_name,
-            now.format("%Y-%m-%d-%H-%M-%S")
+            now.format("%Y-%m-%d-%H-%M-%S-%3f")
         )
     }
 
-    /// Create a snapshot for a collection
-    pub fn create_collection_snapshot(&self, data: CollectionSnapshotData) -> Result<SnapshotDescription> {
-        let collection_dir = self.collection_snapshot_dir(&data.name);
+    /// Create a snapshot for a collection in the binary v2 format
+    pub fn create_collection_snapshot(&self, data: CollectionSnapshotData, encoding: VectorEncoding) -> Result<SnapshotDescription> {
+        let collection_dir = self.collection_snapshot_dir(&data.name)?;
         fs::create_dir_all(&collection_dir)?;
 
-        let snapshot_name = Self::generate_snapshot_name(&data.name);
+        // Never overwrite: a delta's base may have been taken the same millisecond
+        let mut snapshot_name = Self::generate_snapshot_name(&data.name);
+        let stem = snapshot_name.trim_end_matches(".snapshot").to_string();
+        let mut attempt = 1;
+        while collection_dir.join(&snapshot_name).exists() {
+            snapshot_name = format!("{}-{}.snapshot", stem, attempt);
+            attempt += 1;
+        }
         let snapshot_path = collection_dir.join(&snapshot_name);
 
-        // Serialize to JSON and compress with gzip
-        let json_data = serde_json::to_vec(&data)?;
-        
-        let file = File::create(&snapshot_path)?;
-        let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
-        encoder.write_all(&json_data)?;
-        encoder.finish()?;
+        let mut writer = BufWriter::new(File::create(&snapshot_path)?);
+        snapshot_format::write_snapshot(&mut writer, &data, encoding)?;
+        writer.flush()?;
+
+        Self::describe_snapshot(&snapshot_path, &snapshot_name)
+    }
 
-        // Calculate checksum
-        let file_data = fs::read(&snapshot_path)?;
+    /// Size, creation time and SHA-256 checksum of a snapshot file
+    fn describe_snapshot(path: &Path, name: &str) -> Result<SnapshotDescription> {
+        let file_data = fs::read(path)?;
         let checksum = format!("{:x}", Sha256::digest(&file_data));
 
-        let metadata = fs::metadata(&snapshot_path)?;
+        let metadata = fs::metadata(path)?;
         let creation_time = metadata.created()
             .ok()
             .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .map(|d| {
+            .and_then(|d| {
                 DateTime::from_timestamp(d.as_secs() as i64, 0)
                     .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
-            })
-            .flatten();
+            });
 
         Ok(SnapshotDescription {
-            name: snapshot_name,
+            name: name.to_string(),
             creation_time,
             size: metadata.len(),
             checksum: Some(checksum),
         })
     }
 
+    /// Path for a new full storage snapshot
+    pub fn new_full_snapshot_path(&self) -> PathBuf {
+        let now: DateTime<Utc> = Utc::now();
+        let name = format!("full-snapshot-{}.snapshot", now.format("%Y-%m-%d-%H-%M-%S-%3f"));
+        self.snapshot_dir.join(name)
+    }
+
+    /// Describe a full storage snapshot written to `path`
+    pub fn describe_full_snapshot(&self, path: &Path) -> Result<SnapshotDescription> {
+        let name = path.file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("invalid snapshot path {:?}", path))?;
+        Self::describe_snapshot(path, name)
+    }
+
+    /// Full storage snapshots, newest first
+    pub fn list_full_snapshots(&self) -> Result<Vec<SnapshotDescription>> {
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(&self.snapshot_dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("snapshot") {
+                snapshots.push(self.describe_full_snapshot(&path)?);
+            }
+        }
+        snapshots.sort_by(|a, b| b.name.cmp(&a.name));
+        Ok(snapshots)
+    }
+
+    /// Get full storage snapshot file path for download
+    pub fn get_full_snapshot_path(&self, snapshot_name: &str) -> Option<PathBuf> {
+        check_snapshot_name(snapshot_name).ok()?;
+        let path = self.snapshot_dir.join(snapshot_name);
+        if path.is_file() {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    /// Delete a full storage snapshot
+    pub fn delete_full_snapshot(&self, snapshot_name: &str) -> Result<bool> {
+        match self.get_full_snapshot_path(snapshot_name) {
+            Some(path) => {
+                fs::remove_file(path)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     /// List all snapshots for a collection
     pub fn list_collection_snapshots(&self, collection_name: &str) -> Result<Vec<SnapshotDescription>> {
-        let collection_dir = self.collection_snapshot_dir(collection_name);
+        let collection_dir = self.collection_snapshot_dir(collection_name)?;
         
         if !collection_dir.exists() {
             return Ok(Vec::new());
@@ -127,27 +308,7 @@ impl SnapshotManager {
             
             if path.extension().and_then(|s| s.to_str()) == Some("snapshot") {
                 if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                    let metadata = fs::metadata(&path)?;
-                    
-                    // Calculate checksum
-                    let file_data = fs::read(&path)?;
-                    let checksum = format!("{:x}", Sha256::digest(&file_data));
-                    
-                    let creation_time = metadata.created()
-                        .ok()
-                        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                        .map(|d| {
-                            DateTime::from_timestamp(d.as_secs() as i64, 0)
-                                .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
-                        })
-                        .flatten();
-
-                    snapshots.push(SnapshotDescription {
-                        name: name.to_string(),
-                        creation_time,
-                        size: metadata.len(),
-                        checksum: Some(checksum),
-                    });
+                    snapshots.push(Self::describe_snapshot(&path, name)?);
                 }
             }
         }
@@ -159,24 +320,32 @@ impl SnapshotManager {
 
     /// Load a snapshot from file
     pub fn load_collection_snapshot(&self, collection_name: &str, snapshot_name: &str) -> Result<CollectionSnapshotData> {
-        let snapshot_path = self.collection_snapshot_dir(collection_name).join(snapshot_name);
+        let snapshot_path = self.collection_snapshot_path(collection_name, snapshot_name)?;
         
         if !snapshot_path.exists() {
             return Err(anyhow!("Snapshot '{}' not found for collection '{}'", snapshot_name, collection_name));
         }
 
-        let file = File::open(&snapshot_path)?;
-        let mut decoder = GzDecoder::new(BufReader::new(file));
-        let mut json_data = Vec::new();
-        decoder.read_to_end(&mut json_data)?;
+        self.load_snapshot_from_path(&snapshot_path)
+    }
 
-        let data: CollectionSnapshotData = serde_json::from_slice(&json_data)?;
-        Ok(data)
+    /// Operation count and delta info of a snapshot, without loading its
+    /// points when the format allows
+    pub fn load_snapshot_info(&self, collection_name: &str, snapshot_name: &str) -> Result<(u64, Option<DeltaInfo>)> {
+        let snapshot_path = self.collection_snapshot_path(collection_name, snapshot_name)?;
+        if !snapshot_path.exists() {
+            return Err(anyhow!("Snapshot '{}' not found for collection '{}'", snapshot_name, collection_name));
+        }
+        if let Some(info) = snapshot_format::read_info(BufReader::new(File::open(&snapshot_path)?))? {
+            return Ok(info);
+        }
+        let data = self.load_snapshot_from_path(&snapshot_path)?;
+        Ok((data.op_count, data.delta))
     }
 
     /// Delete a snapshot
     pub fn delete_collection_snapshot(&self, collection_name: &str, snapshot_name: &str) -> Result<bool> {
-        let snapshot_path = self.collection_snapshot_dir(collection_name).join(snapshot_name);
+        let snapshot_path = self.collection_snapshot_path(collection_name, snapshot_name)?;
         
         if snapshot_path.exists() {
             fs::remove_file(&snapshot_path)?;
@@ -188,7 +357,7 @@ impl SnapshotManager {
 
     /// Get snapshot file path for download
     pub fn get_snapshot_path(&self, collection_name: &str, snapshot_name: &str) -> Option<PathBuf> {
-        let path = self.collection_snapshot_dir(collection_name).join(snapshot_name);
+        let path = self.collection_snapshot_path(collection_name, snapshot_name).ok()?;
         if path.exists() {
             Some(path)
         } else {
@@ -204,14 +373,14 @@ impl SnapshotManager {
         url: &str,
         expected_checksum: Option<&str>,
     ) -> Result<PathBuf> {
-        let collection_dir = self.collection_snapshot_dir(collection_name);
+        let collection_dir = self.collection_snapshot_dir(collection_name)?;
         fs::create_dir_all(&collection_dir)?;
 
         // Extract filename from URL or generate one
         let filename = url
             .rsplit('/')
             .next()
-            .filter(|s| s.ends_with(".snapshot"))
+            .filter(|s| s.ends_with(".snapshot") && check_snapshot_name(s).is_ok())
             .map(|s| s.to_string())
             .unwrap_or_else(|| Self::generate_snapshot_name(collection_name));
 
@@ -247,9 +416,13 @@ impl SnapshotManager {
     }
 
     /// Load snapshot from a file path (for recovery)
-    /// Supports both vectX format (gzipped JSON) and Qdrant format (tar or tar.gz archive)
+    /// Supports vectX v2 (binary), vectX v1 (gzipped JSON) and Qdrant format (tar or tar.gz archive)
     pub fn load_snapshot_from_path(&self, path: &Path) -> Result<CollectionSnapshotData> {
         let file_data = fs::read(path)?;
+
+        if snapshot_format::is_v2(&file_data) {
+            return snapshot_format::read_snapshot(&file_data);
+        }
         
         // Check if it's gzipped (magic bytes 1f 8b)
         let data = if file_data.len() > 2 && file_data[0] == 0x1f && file_data[1] == 0x8b {
@@ -282,7 +455,7 @@ impl SnapshotManager {
         let mut archive = Archive::new(cursor);
         
         let mut collection_config: Option<serde_json::Value> = None;
-        let mut collection_name = String::from("imported_collection");
+        let collection_name = String::from("imported_collection");
         
         // Read through the archive looking for config.json
         for entry in archive.entries()? {
@@ -348,8 +521,8 @@ impl SnapshotManager {
 
         // We found the config but cannot extract points from RocksDB
         // Return an empty collection with the right config and a helpful message
-        eprintln!(
-            "Note: Imported Qdrant collection config ({}D vectors, {} distance). \
+        tracing::warn!(
+            "Imported Qdrant collection config ({}D vectors, {} distance). \
             Points cannot be automatically extracted from Qdrant's RocksDB storage. \
             Please use the Qdrant scroll API to migrate points.",
             vector_dim, distance
@@ -362,12 +535,30 @@ impl SnapshotManager {
                 distance,
                 use_hnsw: true,
                 enable_bm25: false,
+                sparse_vectors: HashMap::new(),
+                sparse_modifiers: HashMap::new(),
+                named_multivectors: HashMap::new(),
+                payload_indexes: HashMap::new(),
+                keyword_params: HashMap::new(),
+                dense_vector_name: None,
+                text_dictionaries: Default::default(),
+                detect_language: false,
+                search_defaults: Default::default(),
+                normalization: Default::default(),
+                duplicate_policy: Default::default(),
+                hnsw_config: Default::default(),
+                infer_vector_dim: false,
+                query_templates: Default::default(),
+                ef_tuning: None,
+                ef_tuning_report: None,
             },
             points: Vec::new(), // Empty - points can't be extracted from RocksDB
             created_at: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .map(|d| d.as_secs())
                 .unwrap_or(0),
+            op_count: 0,
+            delta: None,
         })
     }
 
@@ -378,12 +569,12 @@ impl SnapshotManager {
         data: &[u8],
         filename: Option<&str>,
     ) -> Result<PathBuf> {
-        let collection_dir = self.collection_snapshot_dir(collection_name);
+        let collection_dir = self.collection_snapshot_dir(collection_name)?;
         fs::create_dir_all(&collection_dir)?;
 
         // Use provided filename or generate one
         let snapshot_name = filename
-            .filter(|f| f.ends_with(".snapshot"))
+            .filter(|f| f.ends_with(".snapshot") && check_snapshot_name(f).is_ok())
             .map(|f| f.to_string())
             .unwrap_or_else(|| Self::generate_snapshot_name(collection_name));
 
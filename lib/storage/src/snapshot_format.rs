@@ -0,0 +1,322 @@
+//! Binary collection snapshot format (version 2)
+//!
+//! Version 1 snapshots are gzipped JSON, which spends most of its time and
+//! size printing floats. Version 2 stores vectors as raw little-endian
+//! numbers and keeps JSON only for payloads:
+//!
+//! ```text
+//! magic     "VXSNAP"                  6 bytes
+//! version   u16                       2
+//! sections  tag u8 | len u32 | body | crc32(body) u32
+//!   1 header    JSON: name, config, created_at, op count, delta info, point count,
+//!               chunk size, encoding
+//!   2 vectors   one block per chunk of points (ids, dense and multivectors)
+//!   3 payloads  one block per chunk, JSON array aligned with the vector block
+//!   0 end       empty body
+//! ```
+//!
+//! Every section carries its own CRC, so a damaged file is rejected with the
+//! offending section named instead of restoring partial data. All integers
+//! are little-endian.
+
+use crate::snapshot::{CollectionConfigData, CollectionSnapshotData, DeltaInfo, PointData};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use vectx_core::SparseVector;
+
+pub(crate) const MAGIC: &[u8; 6] = b"VXSNAP";
+pub(crate) const VERSION: u16 = 2;
+
+/// Points per vector and payload block
+const CHUNK_SIZE: usize = 1024;
+/// Fewest bytes a point takes in a vector block: its id, vector and
+/// multivector lengths
+const MIN_POINT_BYTES: usize = 12;
+
+const TAG_END: u8 = 0;
+const TAG_HEADER: u8 = 1;
+const TAG_VECTORS: u8 = 2;
+const TAG_PAYLOADS: u8 = 3;
+
+/// How vector components are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum VectorEncoding {
+    /// Exact 32-bit floats
+    #[default]
+    F32,
+    /// Half-precision floats: half the size, ~3 significant digits
+    F16,
+}
+
+impl VectorEncoding {
+    fn width(self) -> usize {
+        match self {
+            Self::F32 => 4,
+            Self::F16 => 2,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    name: String,
+    config: CollectionConfigData,
+    created_at: u64,
+    #[serde(default)]
+    op_count: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delta: Option<DeltaInfo>,
+    points: usize,
+    chunk_size: usize,
+    encoding: VectorEncoding,
+}
+
+/// Per-point data kept as JSON in the payload blocks
+#[derive(Serialize, Deserialize)]
+struct PointExtras {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    sparse_vectors: HashMap<String, SparseVector>,
+    /// Named multivectors, kept as f32 whatever the vector encoding
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    named_multivectors: HashMap<String, Vec<Vec<f32>>>,
+}
+
+/// Whether `data` starts like a version 2 snapshot
+pub(crate) fn is_v2(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Write `data` as a version 2 snapshot
+pub(crate) fn write_snapshot<W: Write>(out: &mut W, data: &CollectionSnapshotData, encoding: VectorEncoding) -> Result<()> {
+    out.write_all(MAGIC)?;
+    out.write_all(&VERSION.to_le_bytes())?;
+
+    let header = Header {
+        name: data.name.clone(),
+        config: data.config.clone(),
+        created_at: data.created_at,
+        op_count: data.op_count,
+        delta: data.delta.clone(),
+        points: data.points.len(),
+        chunk_size: CHUNK_SIZE,
+        encoding,
+    };
+    write_section(out, TAG_HEADER, &serde_json::to_vec(&header)?)?;
+
+    for chunk in data.points.chunks(CHUNK_SIZE) {
+        let mut body = Vec::new();
+        put_u32(&mut body, chunk.len())?;
+        for point in chunk {
+            put_u32(&mut body, point.id.len())?;
+            body.extend_from_slice(point.id.as_bytes());
+            put_vector(&mut body, &point.vector, encoding)?;
+            let multivector = point.multivector.as_deref().unwrap_or_default();
+            put_u32(&mut body, multivector.len())?;
+            for vector in multivector {
+                put_vector(&mut body, vector, encoding)?;
+            }
+        }
+        write_section(out, TAG_VECTORS, &body)?;
+    }
+
+    for chunk in data.points.chunks(CHUNK_SIZE) {
+        let extras: Vec<PointExtras> = chunk.iter()
+            .map(|p| PointExtras {
+                payload: p.payload.clone(),
+                sparse_vectors: p.sparse_vectors.clone(),
+                named_multivectors: p.named_multivectors.clone(),
+            })
+            .collect();
+        write_section(out, TAG_PAYLOADS, &serde_json::to_vec(&extras)?)?;
+    }
+
+    write_section(out, TAG_END, &[])?;
+    Ok(())
+}
+
+/// Parse a version 2 snapshot, verifying every section checksum
+pub(crate) fn read_snapshot(data: &[u8]) -> Result<CollectionSnapshotData> {
+    let mut reader = Reader { data, pos: 0 };
+    if reader.take(MAGIC.len())? != MAGIC {
+        bail!("not a vectX v2 snapshot");
+    }
+    let version = u16::from_le_bytes(reader.take(2)?.try_into()?);
+    if version != VERSION {
+        bail!("unsupported snapshot version {}", version);
+    }
+
+    let (tag, body) = reader.section()?;
+    if tag != TAG_HEADER {
+        bail!("snapshot header missing");
+    }
+    let header: Header = serde_json::from_slice(body)?;
+
+    // The header's count is only a hint: reserve no more than the rest of
+    // the file could hold
+    let remaining = data.len() - reader.pos;
+    let mut points: Vec<PointData> = Vec::with_capacity(header.points.min(remaining / MIN_POINT_BYTES));
+    let mut extras_read = 0;
+    loop {
+        let (tag, body) = reader.section()?;
+        match tag {
+            TAG_END => break,
+            TAG_VECTORS => {
+                let mut block = Reader { data: body, pos: 0 };
+                let count = block.u32()?;
+                for _ in 0..count {
+                    let id_len = block.u32()?;
+                    let id = std::str::from_utf8(block.take(id_len)?)?.to_string();
+                    let vector = block.vector(header.encoding)?;
+                    let multivector_len = block.u32()?;
+                    let multivector = if multivector_len > 0 {
+                        Some((0..multivector_len).map(|_| block.vector(header.encoding)).collect::<Result<_>>()?)
+                    } else {
+                        None
+                    };
+                    points.push(PointData {
+                        id,
+                        vector,
+                        multivector,
+                        named_multivectors: HashMap::new(),
+                        sparse_vectors: HashMap::new(),
+                        payload: None,
+                    });
+                }
+            }
+            TAG_PAYLOADS => {
+                let extras: Vec<PointExtras> = serde_json::from_slice(body)?;
+                let targets = points.get_mut(extras_read..extras_read + extras.len())
+                    .ok_or_else(|| anyhow!("payload block without matching vectors"))?;
+                for (point, extra) in targets.iter_mut().zip(extras) {
+                    point.payload = extra.payload;
+                    point.sparse_vectors = extra.sparse_vectors;
+                    point.named_multivectors = extra.named_multivectors;
+                }
+                extras_read += targets.len();
+            }
+            other => bail!("unknown snapshot section {}", other),
+        }
+    }
+
+    if points.len() != header.points || extras_read != header.points {
+        bail!(
+            "snapshot truncated: header lists {} points, found {} vectors and {} payloads",
+            header.points, points.len(), extras_read
+        );
+    }
+
+    Ok(CollectionSnapshotData {
+        name: header.name,
+        config: header.config,
+        points,
+        created_at: header.created_at,
+        op_count: header.op_count,
+        delta: header.delta,
+    })
+}
+
+/// Operation count and delta info from the header alone. Returns None if the
+/// input is not a version 2 snapshot.
+pub(crate) fn read_info<R: Read>(mut input: R) -> Result<Option<(u64, Option<DeltaInfo>)>> {
+    let mut prefix = [0u8; 13];
+    if input.read_exact(&mut prefix).is_err() || !is_v2(&prefix) {
+        return Ok(None);
+    }
+    let version = u16::from_le_bytes([prefix[6], prefix[7]]);
+    if version != VERSION || prefix[8] != TAG_HEADER {
+        bail!("unsupported snapshot version {}", version);
+    }
+    let len = u32::from_le_bytes([prefix[9], prefix[10], prefix[11], prefix[12]]) as usize;
+    let mut section = vec![0u8; len + 4];
+    input.read_exact(&mut section)?;
+    let (body, crc) = section.split_at(len);
+    if crc32fast::hash(body).to_le_bytes() != crc {
+        bail!("checksum mismatch in snapshot header");
+    }
+    let header: Header = serde_json::from_slice(body)?;
+    Ok(Some((header.op_count, header.delta)))
+}
+
+fn write_section<W: Write>(out: &mut W, tag: u8, body: &[u8]) -> Result<()> {
+    let len = u32::try_from(body.len()).map_err(|_| anyhow!("snapshot section too large"))?;
+    out.write_all(&[tag])?;
+    out.write_all(&len.to_le_bytes())?;
+    out.write_all(body)?;
+    out.write_all(&crc32fast::hash(body).to_le_bytes())?;
+    Ok(())
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: usize) -> Result<()> {
+    let value = u32::try_from(value).map_err(|_| anyhow!("length {} does not fit a snapshot", value))?;
+    buf.extend_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+fn put_vector(buf: &mut Vec<u8>, vector: &[f32], encoding: VectorEncoding) -> Result<()> {
+    put_u32(buf, vector.len())?;
+    buf.reserve(vector.len() * encoding.width());
+    match encoding {
+        VectorEncoding::F32 => {
+            for v in vector {
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        VectorEncoding::F16 => {
+            for v in vector {
+                buf.extend_from_slice(&half::f16::from_f32(*v).to_le_bytes());
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self.pos.checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| anyhow!("snapshot truncated at byte {}", self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<usize> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?) as usize)
+    }
+
+    /// Next section as (tag, body), with its checksum verified
+    fn section(&mut self) -> Result<(u8, &'a [u8])> {
+        let offset = self.pos;
+        let tag = self.take(1)?[0];
+        let len = self.u32()?;
+        let body = self.take(len)?;
+        let crc = u32::from_le_bytes(self.take(4)?.try_into()?);
+        if crc32fast::hash(body) != crc {
+            bail!("checksum mismatch in snapshot section at byte {}", offset);
+        }
+        Ok((tag, body))
+    }
+
+    fn vector(&mut self, encoding: VectorEncoding) -> Result<Vec<f32>> {
+        let dim = self.u32()?;
+        let bytes = self.take(dim * encoding.width())?;
+        Ok(match encoding {
+            VectorEncoding::F32 => bytes.chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            VectorEncoding::F16 => bytes.chunks_exact(2)
+                .map(|b| half::f16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect(),
+        })
+    }
+}
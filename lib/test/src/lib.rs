@@ -0,0 +1,315 @@
+//! In-process vectX servers for integration tests
+//!
+//! [`TestServer::start`] opens a [`StorageManager`] in a temporary
+//! directory and serves it over REST and gRPC on random local ports, as the
+//! `vectx` binary would. The servers stop and the directory is removed when
+//! the `TestServer` is dropped.
+//!
+//! ```no_run
+//! use vectx_test::TestServer;
+//!
+//! let server = TestServer::start();
+//! server.create_collection("docs", 3, "Cosine");
+//! server.upsert_points("docs", serde_json::json!([
+//!     {"id": 1, "vector": [1.0, 0.0, 0.0], "payload": {"lang": "en"}},
+//! ]));
+//! let hits = server.search("docs", &[1.0, 0.0, 0.0], 5);
+//! assert_eq!(hits[0].id, serde_json::json!(1));
+//! ```
+//!
+//! The HTTP helpers block, so use them from synchronous tests rather than
+//! from within an async runtime. gRPC clients are async; run their calls
+//! with [`TestServer::block_on`].
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::HeaderMap;
+pub use reqwest::Method;
+use serde_json::Value;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tonic::transport::{Channel, Endpoint};
+use vectx_api::grpc::health::health_client::HealthClient;
+use vectx_api::grpc::vectx::collections_client::CollectionsClient;
+use vectx_api::grpc::vectx::points_client::PointsClient;
+use vectx_api::parsing::ParseMode;
+use vectx_api::{GrpcApi, RestApi};
+use vectx_storage::StorageManager;
+
+/// A REST and a gRPC server over one temporary storage
+pub struct TestServer {
+    storage: Arc<StorageManager>,
+    http_addr: SocketAddr,
+    grpc_addr: SocketAddr,
+    http: Option<(actix_web::dev::ServerHandle, std::thread::JoinHandle<()>)>,
+    grpc_shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+    runtime: tokio::runtime::Runtime,
+    client: Client,
+    // Removed after the servers stop
+    data_dir: TempDir,
+}
+
+impl TestServer {
+    /// Start both servers on an empty storage
+    pub fn start() -> Self {
+        Self::start_with(|_| {})
+    }
+
+    /// Start both servers after `configure` has set up the storage, e.g.
+    /// with API keys or collections
+    pub fn start_with(configure: impl FnOnce(&StorageManager)) -> Self {
+        Self::start_with_parse_mode(ParseMode::default(), configure)
+    }
+
+    /// [`TestServer::start_with`], with the REST server parsing requests
+    /// in `parse_mode`
+    pub fn start_with_parse_mode(parse_mode: ParseMode, configure: impl FnOnce(&StorageManager)) -> Self {
+        let data_dir = tempfile::tempdir().expect("create a temporary data directory");
+        let storage = Arc::new(StorageManager::new(data_dir.path()).expect("open the storage"));
+        configure(&storage);
+
+        let http_listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind the REST port");
+        let http_addr = http_listener.local_addr().expect("REST address");
+        let (started, handle) = std::sync::mpsc::channel();
+        let http_storage = storage.clone();
+        let http_thread = std::thread::spawn(move || {
+            actix_web::rt::System::new().block_on(async move {
+                match RestApi::serve_with_parse_mode(http_storage, http_listener, parse_mode) {
+                    Ok(server) => {
+                        let _ = started.send(Ok(server.handle()));
+                        let _ = server.await;
+                    }
+                    Err(e) => {
+                        let _ = started.send(Err(e));
+                    }
+                }
+            })
+        });
+        let http_handle = handle.recv()
+            .expect("REST server thread exited")
+            .expect("start the REST server");
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .expect("start the gRPC runtime");
+        let grpc_listener = runtime.block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .expect("bind the gRPC port");
+        let grpc_addr = grpc_listener.local_addr().expect("gRPC address");
+        let (grpc_shutdown, shutdown) = tokio::sync::oneshot::channel::<()>();
+        runtime.spawn(GrpcApi::serve(storage.clone(), grpc_listener, async {
+            let _ = shutdown.await;
+        }));
+
+        Self {
+            storage,
+            http_addr,
+            grpc_addr,
+            http: Some((http_handle, http_thread)),
+            grpc_shutdown: Some(grpc_shutdown),
+            runtime,
+            client: Client::new(),
+            data_dir,
+        }
+    }
+
+    /// The storage both servers serve
+    pub fn storage(&self) -> &Arc<StorageManager> {
+        &self.storage
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        self.data_dir.path()
+    }
+
+    pub fn http_addr(&self) -> SocketAddr {
+        self.http_addr
+    }
+
+    pub fn grpc_addr(&self) -> SocketAddr {
+        self.grpc_addr
+    }
+
+    /// URL of a REST path, e.g. `/collections`
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.http_addr, path)
+    }
+
+    /// A REST request to complete, e.g. with headers, and pass to [`send`](Self::send)
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client.request(method, self.url(path))
+    }
+
+    /// Send a REST request and read its response
+    #[track_caller]
+    pub fn send(&self, request: RequestBuilder) -> TestResponse {
+        let response = request.send().expect("send the request");
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let text = response.text().expect("read the response body");
+        let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+        TestResponse { status, headers, body }
+    }
+
+    #[track_caller]
+    pub fn get(&self, path: &str) -> TestResponse {
+        self.send(self.request(Method::GET, path))
+    }
+
+    #[track_caller]
+    pub fn post(&self, path: &str, body: Value) -> TestResponse {
+        self.send(self.request(Method::POST, path).json(&body))
+    }
+
+    #[track_caller]
+    pub fn put(&self, path: &str, body: Value) -> TestResponse {
+        self.send(self.request(Method::PUT, path).json(&body))
+    }
+
+    #[track_caller]
+    pub fn patch(&self, path: &str, body: Value) -> TestResponse {
+        self.send(self.request(Method::PATCH, path).json(&body))
+    }
+
+    #[track_caller]
+    pub fn delete(&self, path: &str) -> TestResponse {
+        self.send(self.request(Method::DELETE, path))
+    }
+
+    /// Create a collection of `size`-dimensional vectors, e.g. with
+    /// distance `"Cosine"`, and fail the test if that is refused
+    #[track_caller]
+    pub fn create_collection(&self, name: &str, size: usize, distance: &str) {
+        self.put(
+            &format!("/collections/{}", name),
+            serde_json::json!({"vectors": {"size": size, "distance": distance}}),
+        ).assert_ok();
+    }
+
+    /// Upsert points given as in a `PUT /points` request and fail the test
+    /// if that is refused
+    #[track_caller]
+    pub fn upsert_points(&self, collection: &str, points: Value) {
+        self.put(
+            &format!("/collections/{}/points?wait=true", collection),
+            serde_json::json!({"points": points}),
+        ).assert_ok();
+    }
+
+    /// The `limit` nearest points to `vector`, with their payloads
+    #[track_caller]
+    pub fn search(&self, collection: &str, vector: &[f32], limit: usize) -> Vec<Hit> {
+        let response = self.post(
+            &format!("/collections/{}/points/search", collection),
+            serde_json::json!({"vector": vector, "limit": limit, "with_payload": true}),
+        );
+        response.assert_ok();
+        response.result().as_array()
+            .expect("search result is an array")
+            .iter()
+            .map(|hit| Hit {
+                id: hit["id"].clone(),
+                score: hit["score"].as_f64().expect("hit score") as f32,
+                payload: hit.get("payload").filter(|p| !p.is_null()).cloned(),
+            })
+            .collect()
+    }
+
+    /// Number of points in a collection
+    #[track_caller]
+    pub fn count(&self, collection: &str) -> usize {
+        let response = self.post(&format!("/collections/{}/points/count", collection), serde_json::json!({}));
+        response.assert_ok();
+        response.result()["count"].as_u64().expect("count in the result") as usize
+    }
+
+    /// A gRPC channel to the server, connected on first use
+    pub fn grpc_channel(&self) -> Channel {
+        let _runtime = self.runtime.enter();
+        Endpoint::from_shared(format!("http://{}", self.grpc_addr))
+            .expect("gRPC endpoint")
+            .connect_lazy()
+    }
+
+    pub fn collections_client(&self) -> CollectionsClient<Channel> {
+        CollectionsClient::new(self.grpc_channel())
+    }
+
+    pub fn points_client(&self) -> PointsClient<Channel> {
+        PointsClient::new(self.grpc_channel())
+    }
+
+    pub fn health_client(&self) -> HealthClient<Channel> {
+        HealthClient::new(self.grpc_channel())
+    }
+
+    /// Run a future, e.g. a gRPC call, on the runtime of the gRPC server
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.grpc_shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some((handle, thread)) = self.http.take() {
+            // The stop command is sent right away; the thread ends with the server
+            drop(handle.stop(false));
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Status, headers and body of a REST response
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    /// The body as JSON, or as a JSON string if it is not JSON
+    pub body: Value,
+}
+
+impl TestResponse {
+    /// Fail the test unless the response has `status`
+    #[track_caller]
+    pub fn assert_status(&self, status: u16) -> &Self {
+        assert_eq!(self.status, status, "unexpected status, body: {}", self.body);
+        self
+    }
+
+    /// Fail the test unless the response is a success
+    #[track_caller]
+    pub fn assert_ok(&self) -> &Self {
+        assert!((200..300).contains(&self.status), "request failed with {}: {}", self.status, self.body);
+        self
+    }
+
+    /// A header's value, if it is present and text
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// The `result` a Qdrant-style response wraps its data in; null if
+    /// there is none
+    pub fn result(&self) -> &Value {
+        &self.body["result"]
+    }
+
+    /// The error message of a failed Qdrant-style response
+    pub fn error(&self) -> Option<&str> {
+        self.body["status"]["error"].as_str()
+    }
+}
+
+/// A search hit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub id: Value,
+    pub score: f32,
+    pub payload: Option<Value>,
+}
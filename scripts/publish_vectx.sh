@@ -1,5 +1,6 @@
 #!/bin/bash
-# Script to yank old distx packages and publish new vectx packages
+# Script to yank old distx packages and publish new vectx packages, plus
+# deprecated distx-* releases that re-export them (see lib/compat)
 # 
 # Usage:
 #   ./scripts/publish_vectx.sh [--yank-only] [--publish-only]
@@ -90,7 +91,7 @@ if [ "$YANK_ONLY" = false ]; then
     
     # Verify we're in the right directory
     if [ ! -f "Cargo.toml" ]; then
-        echo -e "${RED}Error: Cargo.toml not found. Please run this script from the distx root directory.${NC}"
+        echo -e "${RED}Error: Cargo.toml not found. Please run this script from the vectx root directory.${NC}"
         exit 1
     fi
     
@@ -174,6 +175,21 @@ if [ "$YANK_ONLY" = false ]; then
         echo -e "${YELLOW}Skipped vectx${NC}"
     fi
     echo ""
+
+    # 5. Deprecated distx-* shims (re-export the vectx-* crates above)
+    echo -e "${YELLOW}Publishing deprecated distx-* shims...${NC}"
+    for package in distx-core distx-storage distx-api distx; do
+        cargo publish -p "$package" --dry-run
+        read -p "Publish ${package}? (y/n) " -n 1 -r
+        echo
+        if [[ $REPLY =~ ^[Yy]$ ]]; then
+            cargo publish -p "$package"
+            echo -e "${GREEN}✓ ${package} published${NC}"
+        else
+            echo -e "${YELLOW}Skipped ${package}${NC}"
+        fi
+    done
+    echo ""
     
     echo "========================================"
     echo -e "${GREEN}All packages published successfully!${NC}"
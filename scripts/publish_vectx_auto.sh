@@ -29,7 +29,7 @@ echo ""
 
 # Verify we're in the right directory
 if [ ! -f "Cargo.toml" ]; then
-    echo -e "${RED}Error: Cargo.toml not found. Please run this script from the distx root directory.${NC}"
+    echo -e "${RED}Error: Cargo.toml not found. Please run this script from the vectx root directory.${NC}"
     exit 1
 fi
 
@@ -1,18 +1,27 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tracing::{info, Level};
-use tracing_subscriber::FmtSubscriber;
+use tracing::{error, info};
+use vectx_api::compression::{CompressionConfig, DEFAULT_MAX_BODY_BYTES};
+use vectx_api::logging::{LogFormat, LogOptions, LogRotation};
+use vectx_api::parsing::ParseMode;
+use vectx_api::rerank::ModelSpec;
 use vectx_api::{GrpcApi, RestApi};
-use vectx_storage::StorageManager;
+use vectx_core::background::configure_background_system;
+use vectx_core::{CpuBudget, JournalRetention};
+use vectx_storage::{AuditLog, CompactOptions, LoadLimits, SaveStrategy, StorageManager, DEFAULT_AUDIT_FILES, DEFAULT_AUDIT_FILE_BYTES, DEFAULT_MAX_PAYLOAD_BYTES};
 
 /// A simple, fast, in-memory vector database
 #[derive(Parser, Debug)]
 #[command(name = "vectx")]
 #[command(about = "A simple, fast vector database", long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the data directory
-    #[arg(short, long, default_value = "./data")]
+    #[arg(short, long, default_value = "./data", global = true)]
     data_dir: PathBuf,
 
     /// HTTP API port
@@ -23,45 +32,330 @@ struct Args {
     #[arg(long, default_value_t = 6334)]
     grpc_port: u16,
 
-    /// Log level
+    /// Log level, or filter directives such as "info,vectx_core=debug";
+    /// changeable at run time through POST /admin/log_level
     #[arg(long, default_value = "info")]
     log_level: String,
+
+    /// Log format: "text" or "json" (one object per line)
+    #[arg(long, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Write logs to this file instead of stdout, rotated as --log-rotation
+    /// says
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// When to start a new log file: "minutely", "hourly", "daily" or
+    /// "never"
+    #[arg(long, default_value_t = LogRotation::Daily)]
+    log_rotation: LogRotation,
+
+    /// Rotated log files kept besides the current one (0 keeps all)
+    #[arg(long, default_value_t = 7)]
+    log_max_files: usize,
+
+    /// Record who made each mutating request, when, and the collection and
+    /// points it named, in <data-dir>/audit; see GET /admin/audit
+    #[arg(long)]
+    audit_log: bool,
+
+    /// Size in bytes at which the audit log file is rotated
+    #[arg(long, default_value_t = DEFAULT_AUDIT_FILE_BYTES)]
+    audit_max_file_bytes: u64,
+
+    /// Rotated audit log files kept besides the current one
+    #[arg(long, default_value_t = DEFAULT_AUDIT_FILES)]
+    audit_max_files: usize,
+
+    /// Number of operation journal entries kept per collection
+    #[arg(long, default_value_t = 10_000)]
+    journal_max_entries: usize,
+
+    /// Drop operation journal entries older than this many seconds
+    #[arg(long)]
+    journal_max_age_secs: Option<u64>,
+
+    /// Master API key granting full access (falls back to VECTX_API_KEY).
+    /// Scoped keys are managed through /admin/keys.
+    #[arg(long)]
+    api_key: Option<String>,
+
+    /// How background saves capture data: "fork" (Unix default) or "thread"
+    /// (copies each collection and writes on a background thread; the
+    /// default elsewhere)
+    #[arg(long)]
+    bgsave_strategy: Option<SaveStrategy>,
+
+    /// JSON file describing Kafka/NATS ingestion sources
+    #[arg(long)]
+    ingest_config: Option<PathBuf>,
+
+    /// Background jobs (HNSW rebuilds, clustering, ...) running at once
+    /// (default: half of the cores)
+    #[arg(long)]
+    background_threads: Option<usize>,
+
+    /// Largest payload accepted by point writes, in bytes as JSON (0 for no
+    /// limit)
+    #[arg(long, default_value_t = DEFAULT_MAX_PAYLOAD_BYTES)]
+    max_payload_bytes: usize,
+
+    /// Refuse point writes to a collection with this many points not yet
+    /// linked into its HNSW graph, e.g. during a rebuild, with 429 (gRPC:
+    /// UNAVAILABLE) until it catches up (0 for no limit)
+    #[arg(long, default_value_t = 0)]
+    max_pending_points: usize,
+
+    /// Nice value of background job threads on Linux, so queries get the
+    /// CPU first (0-19)
+    #[arg(long, default_value_t = 10)]
+    background_nice: i32,
+
+    /// Load every collection, read its vectors into memory, build pending
+    /// HNSW graphs and run a few searches before /readyz reports ready
+    #[arg(long)]
+    warm_on_start: bool,
+
+    /// Searches run per collection by --warm-on-start
+    #[arg(long, default_value_t = 16)]
+    warm_up_queries: usize,
+
+    /// Seconds between statistics samples of each loaded collection, kept
+    /// for GET /collections/{name}/stats/history (0 to disable)
+    #[arg(long, default_value_t = 60)]
+    stats_interval_secs: u64,
+
+    /// Report not ready on /readyz and NOT_SERVING on gRPC health checks
+    /// while more background jobs than this wait to start
+    #[arg(long)]
+    max_queued_jobs: Option<usize>,
+
+    /// Report not ready on /readyz and NOT_SERVING on gRPC health checks
+    /// while a collection's p99 query latency since its last statistics
+    /// sample exceeds this many ms, until it drops below 80% of it
+    #[arg(long)]
+    max_p99_latency_ms: Option<f64>,
+
+    /// Seconds between checks for collections whose ef tuning is due,
+    /// either never run or outgrown (0 to disable)
+    #[arg(long, default_value_t = 300)]
+    ef_tuning_interval_secs: u64,
+
+    /// Check each collection's indexes against its points after every
+    /// write and panic on a mismatch; slow, for debugging
+    #[arg(long)]
+    check_invariants: bool,
+
+    /// Post an anonymized telemetry report to this URL at startup and then
+    /// every --telemetry-interval-secs. Off unless set; setting
+    /// VECTX_TELEMETRY_DISABLED turns it off regardless.
+    #[arg(long)]
+    telemetry_url: Option<String>,
+
+    /// Seconds between telemetry reports
+    #[arg(long, default_value_t = 86_400)]
+    telemetry_interval_secs: u64,
+
+    /// Encodings REST responses and gRPC replies may be compressed with,
+    /// for clients that accept them: "zstd,gzip", "gzip" or "none".
+    /// Compressed requests are accepted either way.
+    #[arg(long, default_value = "zstd,gzip")]
+    compression: CompressionConfig,
+
+    /// Largest REST JSON body or gRPC message accepted, in bytes once
+    /// decompressed
+    #[arg(long, default_value_t = DEFAULT_MAX_BODY_BYTES)]
+    max_request_bytes: usize,
+
+    /// What to do with parts of REST filters and point vectors that cannot
+    /// be parsed: "lenient" logs and skips them as earlier versions did,
+    /// "strict" rejects the request
+    #[arg(long, default_value_t = ParseMode::Lenient)]
+    parse_mode: ParseMode,
+
+    /// Load a cross-encoder for query reranking, as NAME=DIRECTORY with
+    /// model.onnx and tokenizer.json in the directory; repeatable. Needs
+    /// the `inference` feature and the ONNX Runtime library, found
+    /// through ORT_DYLIB_PATH.
+    #[arg(long = "rerank-model", value_name = "NAME=DIRECTORY")]
+    rerank_models: Vec<ModelSpec>,
+}
+
+/// Offline maintenance of a data directory; stop the server first
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check manifests, point records, journals, snapshots and the
+    /// background save dump, and report corrupted records and orphaned
+    /// files without changing anything. Exits with status 1 on any issue.
+    Fsck,
+    /// Rewrite every collection's points compactly
+    Compact {
+        /// Delete point records and journal entries that fsck reports corrupt
+        #[arg(long)]
+        drop_corrupt: bool,
+        /// Delete orphaned snapshots and journals, and temporary files left
+        /// by interrupted writes
+        #[arg(long)]
+        remove_orphans: bool,
+    },
+}
+
+/// Run an offline command, returning whether the directory is clean
+/// afterwards
+fn run_command(command: &Command, data_dir: &Path) -> anyhow::Result<bool> {
+    let check = match command {
+        Command::Fsck => vectx_storage::fsck::check(data_dir)?,
+        Command::Compact { drop_corrupt, remove_orphans } => {
+            let options = CompactOptions { drop_corrupt: *drop_corrupt, remove_orphans: *remove_orphans };
+            let report = vectx_storage::fsck::compact(data_dir, options)?;
+            println!(
+                "Compacted {} collection(s): {} -> {} bytes",
+                report.collections, report.bytes_before, report.bytes_after
+            );
+            if report.dropped > 0 {
+                println!("Dropped {} corrupt record(s) and journal entries", report.dropped);
+            }
+            for path in &report.removed {
+                println!("Removed {}", path.display());
+            }
+            vectx_storage::fsck::check(data_dir)?
+        }
+    };
+    for issue in &check.issues {
+        println!("{}", issue);
+    }
+    println!(
+        "Checked {} collection(s), {} point(s), {} journal(s), {} snapshot(s): {} issue(s)",
+        check.collections, check.points, check.journals, check.snapshots, check.issues.len()
+    );
+    Ok(check.is_clean())
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    let log_level = match args.log_level.as_str() {
-        "trace" => Level::TRACE,
-        "debug" => Level::DEBUG,
-        "info" => Level::INFO,
-        "warn" => Level::WARN,
-        "error" => Level::ERROR,
-        _ => Level::INFO,
-    };
+    if let Some(command) = &args.command {
+        if !run_command(command, &args.data_dir)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(log_level)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber)?;
+    // Flushes the log file when main returns
+    let _log_guard = vectx_api::logging::init(&LogOptions {
+        level: args.log_level.clone(),
+        format: args.log_format,
+        file: args.log_file.clone(),
+        rotation: args.log_rotation,
+        max_files: args.log_max_files,
+    })?;
 
     info!("Starting vectX v{}", env!("CARGO_PKG_VERSION"));
     info!("Data directory: {:?}", args.data_dir);
     info!("HTTP API port: {}", args.http_port);
     info!("gRPC API port: {}", args.grpc_port);
 
+    // Before the storage is loaded, which may already queue index rebuilds
+    let budget = CpuBudget {
+        max_threads: args.background_threads.unwrap_or(CpuBudget::default().max_threads),
+        nice: args.background_nice.clamp(0, 19),
+    };
+    configure_background_system(budget);
+    info!("Background jobs: {} thread(s), nice {}", budget.max_threads, budget.nice);
+
     let storage = Arc::new(StorageManager::new(&args.data_dir)?);
+    storage.set_journal_retention(JournalRetention {
+        max_entries: args.journal_max_entries,
+        max_age_secs: args.journal_max_age_secs,
+    });
+    storage.set_max_payload_bytes(Some(args.max_payload_bytes).filter(|&limit| limit > 0));
+    storage.set_max_pending_points(Some(args.max_pending_points).filter(|&limit| limit > 0));
+    storage.set_load_limits(LoadLimits {
+        max_queued_jobs: args.max_queued_jobs,
+        max_p99_ms: args.max_p99_latency_ms,
+    });
+    if let Some(strategy) = args.bgsave_strategy {
+        storage.set_save_strategy(strategy);
+    }
+    if args.stats_interval_secs > 0 {
+        storage.start_stats_history(std::time::Duration::from_secs(args.stats_interval_secs));
+    }
+    if args.ef_tuning_interval_secs > 0 {
+        storage.start_ef_tuner(std::time::Duration::from_secs(args.ef_tuning_interval_secs));
+    }
+    if args.audit_log {
+        let dir = args.data_dir.join("audit");
+        storage.set_audit_log(Some(AuditLog::new(&dir, args.audit_max_file_bytes, args.audit_max_files)?));
+        info!("Audit log: {:?}", dir);
+    }
+    if args.check_invariants {
+        storage.set_check_invariants(true);
+        info!("Index invariant checks enabled");
+    }
     info!("Storage initialized");
 
+    let telemetry_url = args.telemetry_url.clone().filter(|_| !vectx_api::telemetry::disabled_by_env());
+    vectx_api::telemetry::init(BTreeMap::from([
+        ("kafka".to_string(), cfg!(feature = "kafka")),
+        ("nats".to_string(), cfg!(feature = "nats")),
+        ("inference".to_string(), cfg!(feature = "inference")),
+        ("ingestion".to_string(), args.ingest_config.is_some()),
+        ("warm_on_start".to_string(), args.warm_on_start),
+        ("telemetry_reporting".to_string(), telemetry_url.is_some()),
+    ]));
+    if let Some(url) = telemetry_url {
+        let interval = std::time::Duration::from_secs(args.telemetry_interval_secs.max(60));
+        tokio::spawn(vectx_api::telemetry::report_periodically(storage.clone(), url, interval));
+        info!("Telemetry reports enabled");
+    }
+
+    let compression = CompressionConfig { max_body_bytes: args.max_request_bytes, ..args.compression.clone() };
+    info!("Response compression: {}", compression);
+    vectx_api::compression::configure(compression);
+    if args.parse_mode == ParseMode::Strict {
+        info!("Strict request parsing: unparseable filter conditions and vectors are rejected");
+    }
+
+    for spec in &args.rerank_models {
+        vectx_api::rerank::load_onnx(&spec.name, &spec.dir).map_err(anyhow::Error::msg)?;
+        info!("Rerank model '{}' loaded from {:?}", spec.name, spec.dir);
+    }
+
+    let api_key = args.api_key.clone().or_else(|| std::env::var("VECTX_API_KEY").ok());
+    storage.access_control().set_master_key(api_key.as_deref());
+    if storage.access_control().is_enabled() {
+        info!("API key authentication enabled");
+    }
+
+    if let Some(path) = &args.ingest_config {
+        let config = vectx_ingest::IngestConfig::from_file(path)?;
+        let sources = vectx_ingest::start(config, storage.clone())?;
+        info!("Ingestion started: {} source(s)", sources.len());
+    }
+
+    if args.warm_on_start {
+        let warm_up = storage.start_warm_up(args.warm_up_queries);
+        info!("Warming up collections");
+        tokio::task::spawn_blocking(move || match warm_up.join() {
+            Ok(summary) => info!(
+                "Warm-up done: {} collection(s), {} point(s), {} search(es) in {:.1}s",
+                summary.collections, summary.points, summary.queries, summary.elapsed.as_secs_f64()
+            ),
+            Err(_) => error!("Warm-up failed"),
+        });
+    }
+
     let storage_http = storage.clone();
     let http_port = args.http_port;
+    let parse_mode = args.parse_mode;
     let http_handle = std::thread::spawn(move || {
         info!("Starting HTTP server on port {}", http_port);
         let sys = actix_web::rt::System::new();
         sys.block_on(async {
-            if let Err(e) = RestApi::start(storage_http, http_port).await {
-                eprintln!("HTTP server error: {}", e);
+            if let Err(e) = RestApi::start_with_parse_mode(storage_http, http_port, parse_mode).await {
+                error!("HTTP server error: {}", e);
             }
         })
     });
@@ -71,7 +365,7 @@ async fn main() -> anyhow::Result<()> {
     let grpc_handle = tokio::spawn(async move {
         info!("Starting gRPC server on port {}", grpc_port);
         if let Err(e) = GrpcApi::start(storage_grpc, grpc_port).await {
-            eprintln!("gRPC server error: {}", e);
+            error!("gRPC server error: {}", e);
         } else {
             info!("gRPC server stopped");
         }
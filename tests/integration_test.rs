@@ -1,7 +1,8 @@
 // Integration tests for vectX
-use vectx_core::{Collection, CollectionConfig, Distance, Point, PointId, Vector};
-use vectx_storage::StorageManager;
 use std::collections::HashMap;
+use vectx_core::{AggregationRequest, BatchOperation, Collection, CollectionConfig, Distance, DuplicatePolicy, KMeansConfig, MetricValue, NormalizationPolicy, OperationType, PayloadIndexType, Point, PointId, Projection, SearchPath, TextDictionaries, UpsertStatus, Vector, VectorUsing};
+use vectx_core::background::get_background_system;
+use vectx_storage::{AccessError, AuditEntry, AuditLog, AuditQuery, JobStatus, LoadLimits, Locks, Permission, RecoverOptions, RestoreScope, SnapshotPriority, StorageManager, VectorEncoding, DEFAULT_WRITE_LOCK_MESSAGE, MIN_P99_QUERIES};
 
 #[test]
 fn test_collection_creation() {
@@ -45,6 +46,263 @@ fn test_point_insertion() {
     assert_eq!(retrieved.unwrap().vector.dim(), 3);
 }
 
+#[test]
+fn test_batch_validation() {
+    let collection = Collection::new(CollectionConfig {
+        name: "validated".to_string(),
+        vector_dim: 3,
+        distance: Distance::Cosine,
+        use_hnsw: true,
+        enable_bm25: false,
+    });
+
+    let points = vec![
+        Point::new(PointId::Integer(1), Vector::new(vec![1.0, 0.0, 0.0]), None),
+        Point::new(PointId::Integer(2), Vector::new(vec![1.0, 0.0]), None),
+    ];
+    let err = collection.batch_upsert(points).unwrap_err().to_string();
+    assert!(err.contains("Point 2 (index 1 in batch)"), "{}", err);
+    assert!(err.contains("expected 3, got 2"), "{}", err);
+    // Nothing of a rejected batch is applied
+    assert_eq!(collection.count(), 0);
+
+    let large = Point::new(
+        PointId::Integer(3),
+        Vector::new(vec![0.0, 1.0, 0.0]),
+        Some(serde_json::json!({"text": "x".repeat(100)})),
+    );
+    assert!(large.payload_size() > 100);
+    let err = collection.validate_points(std::slice::from_ref(&large), Some(64)).unwrap_err().to_string();
+    assert!(err.contains("exceeds the limit of 64 bytes"), "{}", err);
+    assert!(collection.validate_points(&[large], None).is_ok());
+}
+
+#[test]
+fn test_batch_upsert_statuses() {
+    let collection = Collection::new(CollectionConfig {
+        name: "statuses".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    collection.upsert(Point::new(PointId::Integer(1), Vector::new(vec![1.0, 0.0]), None)).unwrap();
+
+    let points = vec![
+        Point::new(PointId::Integer(1), Vector::new(vec![0.0, 1.0]), None),
+        Point::new(PointId::Integer(2), Vector::new(vec![1.0]), None),
+        Point::new(PointId::Integer(3), Vector::new(vec![1.0, 1.0]), Some(serde_json::json!({"text": "x".repeat(64)}))),
+        Point::new(PointId::Integer(4), Vector::new(vec![1.0, 1.0]), None),
+    ];
+    let statuses = collection.batch_upsert_lenient(points, Some(32)).unwrap();
+    assert_eq!(statuses[0], UpsertStatus::Updated);
+    assert!(matches!(&statuses[1], UpsertStatus::Failed(e) if e.contains("expected 2, got 1")));
+    assert!(matches!(&statuses[2], UpsertStatus::Failed(e) if e.contains("limit of 32 bytes")));
+    assert_eq!(statuses[3], UpsertStatus::Inserted);
+    assert_eq!(collection.count(), 2);
+}
+
+#[test]
+fn test_search_paths_agree() {
+    // Brute force below 10k points, HNSW from 10k and the quantized replica
+    // must give a point the same score
+    let mut seed = 7u64;
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+    };
+    let vectors: Vec<Vec<f32>> = (0..10_000)
+        .map(|i| {
+            let scale = 0.5 + (i % 5) as f32;
+            (0..8).map(|_| next() * scale).collect()
+        })
+        .collect();
+    let query = Vector::new(vectors[42].iter().map(|x| x * 3.0).collect());
+
+    for (distance, policy) in [
+        (Distance::Cosine, NormalizationPolicy::OnWrite),
+        (Distance::Cosine, NormalizationPolicy::Never),
+        (Distance::Euclidean, NormalizationPolicy::OnWrite),
+        (Distance::Dot, NormalizationPolicy::OnWrite),
+    ] {
+        let collection = Collection::new(CollectionConfig {
+            name: "paths".to_string(),
+            vector_dim: 8,
+            distance,
+            use_hnsw: true,
+            enable_bm25: false,
+        });
+        collection.set_normalization(policy).unwrap();
+        let (last, rest) = vectors.split_last().unwrap();
+        collection.batch_upsert(rest.iter().enumerate()
+            .map(|(i, v)| Point::new(PointId::Integer(i as u64), Vector::new(v.clone()), None))
+            .collect()).unwrap();
+
+        let brute: HashMap<String, f32> = collection.search(&query, rest.len(), None)
+            .into_iter()
+            .map(|(p, score)| (p.id.to_string(), score))
+            .collect();
+        collection.upsert(Point::new(PointId::Integer(9_999), Vector::new(last.clone()), None)).unwrap();
+        let indexed = collection.search(&query, 10, None);
+        collection.enable_gpu_replica();
+        let replica = collection.search(&query, 10, None);
+
+        for results in [&indexed, &replica] {
+            assert!(!results.is_empty());
+            for (point, score) in results.iter().filter(|(p, _)| p.id != PointId::Integer(9_999)) {
+                let expected = brute[&point.id.to_string()];
+                assert!((score - expected).abs() < 1e-4, "{:?} {:?}: {} vs {}", distance, policy, score, expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_verify_consistency() {
+    let collection = Collection::new(CollectionConfig {
+        name: "verify".to_string(),
+        vector_dim: 4,
+        distance: Distance::Euclidean,
+        use_hnsw: true,
+        enable_bm25: false,
+    });
+    for i in 0..200u64 {
+        let x = i as f32;
+        let vector = Vector::new(vec![x.sin(), x.cos(), (x * 0.3).sin(), 1.0 + x / 200.0]);
+        collection.upsert(Point::new(PointId::Integer(i), vector, None)).unwrap();
+    }
+    collection.enable_gpu_replica();
+
+    let query = Vector::new(vec![0.5, 0.5, 0.0, 1.2]);
+    let report = collection.verify_consistency(&query, 10).unwrap();
+    assert_eq!(report.exact.len(), 10);
+    let paths: Vec<SearchPath> = report.paths.iter().map(|p| p.path).collect();
+    assert_eq!(paths, vec![SearchPath::Hnsw, SearchPath::Quantized]);
+    assert!(report.max_score_delta() < 1e-4);
+    assert!(report.paths.iter().all(|p| p.recall > 0.0));
+
+    assert!(collection.verify_consistency(&Vector::new(vec![1.0]), 10).is_err());
+}
+
+#[test]
+fn test_delete_batch() {
+    let collection = Collection::new(CollectionConfig {
+        name: "bulk_delete".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: true,
+        enable_bm25: true,
+    });
+    for i in 0..10u64 {
+        let vector = Vector::new(vec![1.0, i as f32]);
+        let payload = serde_json::json!({"text": format!("doc number {}", i)});
+        collection.upsert(Point::new(PointId::Integer(i), vector, Some(payload))).unwrap();
+    }
+    collection.prewarm_index().unwrap();
+    let ops_before = collection.journal().since(None, 100).len();
+
+    // Missing and repeated ids are not counted
+    let ids: Vec<String> = ["1", "2", "2", "99"].iter().map(|id| id.to_string()).collect();
+    assert_eq!(collection.delete_batch(&ids).unwrap(), 2);
+    assert_eq!(collection.count(), 8);
+    assert!(collection.get("1").is_none() && collection.get("2").is_none());
+
+    let entries = collection.journal().since(None, 100);
+    assert_eq!(entries.len(), ops_before + 1);
+    assert_eq!(entries.last().unwrap().op_type, OperationType::Delete);
+    assert_eq!(entries.last().unwrap().point_ids, vec!["1".to_string(), "2".to_string()]);
+
+    let found: Vec<String> = collection.search(&Vector::new(vec![1.0, 1.5]), 10, None)
+        .into_iter()
+        .map(|(p, _)| p.id.to_string())
+        .collect();
+    assert_eq!(found.len(), 8);
+    assert!(!found.contains(&"1".to_string()) && !found.contains(&"2".to_string()));
+    assert!(collection.search_text("number", 10).iter().all(|(id, _)| id != "1" && id != "2"));
+    assert_eq!(collection.delete_batch(&ids).unwrap(), 0);
+}
+
+#[test]
+fn test_iter_filtered() {
+    let collection = Collection::new(CollectionConfig {
+        name: "iter_filtered".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    for i in 0..10u64 {
+        let payload = serde_json::json!({"even": i % 2 == 0});
+        collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![1.0, i as f32]), Some(payload))).unwrap();
+    }
+
+    let even = |p: &Point| p.payload.as_ref().and_then(|p| p.get("even")) == Some(&serde_json::json!(true));
+    let mut iter = collection.iter_filtered(Some(&even), Projection::IDS);
+    assert_eq!(iter.remaining(), 5);
+
+    // Writes while iterating: a deleted point is skipped, as is one that
+    // no longer matches, and an updated one is seen as it is now
+    let first = iter.next().unwrap();
+    assert!(first.payload.is_none() && first.vector.as_slice().is_empty());
+    let mut rest: Vec<String> = collection.iter_filtered(Some(&even), Projection::IDS)
+        .map(|p| p.id.to_string())
+        .filter(|id| *id != first.id.to_string())
+        .collect();
+    rest.sort();
+    let mut after = collection.iter_filtered(Some(&even), Projection::IDS);
+    collection.delete(&rest[0]).unwrap();
+    collection.set_payload(&rest[1], serde_json::json!({"seen": true})).unwrap();
+    collection.set_payload(&rest[2], serde_json::json!({"even": false})).unwrap();
+    let seen: Vec<Point> = collection.iter_filtered(Some(&even), Projection::PAYLOAD).collect();
+    assert_eq!(seen.len(), 3);
+    assert_eq!(iter.count(), 2);
+    assert_eq!(after.remaining(), 5);
+    assert!(after.all(|p| p.id.to_string() != rest[0] && p.id.to_string() != rest[2]));
+    let updated = collection.iter_filtered(None, Projection::ALL)
+        .find(|p| p.id.to_string() == rest[1])
+        .unwrap();
+    assert_eq!(updated.payload.unwrap()["seen"], true);
+    assert_eq!(updated.vector.as_slice().len(), 2);
+    assert_eq!(collection.iter_filtered(None, Projection::IDS).count(), 9);
+}
+
+#[test]
+fn test_aggregate() {
+    let collection = Collection::new(CollectionConfig {
+        name: "aggregate".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    for i in 0..10u64 {
+        let payload = serde_json::json!({"shop": if i < 6 { "north" } else { "south" }, "price": i, "note": "x"});
+        collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![1.0, i as f32]), Some(payload))).unwrap();
+    }
+    let request: AggregationRequest = serde_json::from_value(serde_json::json!({
+        "group_by": "shop",
+        "metrics": {"revenue": {"type": "sum", "field": "price"}, "top": {"type": "max", "field": "price"}}
+    })).unwrap();
+
+    // Fields must be indexed as the metric reads them
+    assert!(collection.aggregate(&request, None).is_err());
+    collection.create_payload_index("shop", PayloadIndexType::Keyword).unwrap();
+    collection.create_payload_index("price", PayloadIndexType::Text).unwrap();
+    assert!(collection.aggregate(&request, None).is_err());
+    collection.create_payload_index("price", PayloadIndexType::Integer).unwrap();
+
+    let result = collection.aggregate(&request, None).unwrap();
+    assert_eq!(result.total_groups, 2);
+    assert_eq!(result.groups[0].key, Some(serde_json::json!("north")));
+    assert_eq!(result.groups[0].metrics["revenue"], MetricValue::Number(Some(15.0)));
+    assert_eq!(result.groups[1].metrics["top"], MetricValue::Number(Some(9.0)));
+
+    let cheap = |p: &Point| p.payload.as_ref().and_then(|p| p["price"].as_u64()).is_some_and(|price| price < 7);
+    let result = collection.aggregate(&request, Some(&cheap)).unwrap();
+    assert_eq!(result.groups[1].count, 1);
+    assert_eq!(result.groups[1].metrics["revenue"], MetricValue::Number(Some(6.0)));
+}
+
 #[test]
 fn test_vector_search() {
     let config = CollectionConfig {
@@ -107,6 +365,119 @@ fn test_bm25_search() {
     assert!(results[0].1 >= 0.0); // Should have a score (can be 0.0)
 }
 
+#[test]
+fn test_text_index_rebuild() {
+    let config = CollectionConfig {
+        name: "texts".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: true,
+    };
+    let collection = Collection::new(config);
+    for i in 0..2500u64 {
+        let text = if i % 2 == 0 { format!("even number {}", i) } else { format!("odd number {}", i) };
+        let point = Point::new(PointId::Integer(i), Vector::new(vec![1.0, i as f32]), Some(serde_json::json!({"text": text})));
+        collection.upsert(point).unwrap();
+    }
+    collection.upsert(Point::new(PointId::Integer(9999), Vector::new(vec![1.0, 0.0]), None)).unwrap();
+
+    let ctx = vectx_core::JobContext::default();
+    assert_eq!(collection.rebuild_text_index(&ctx).unwrap(), Some(2500));
+    assert!(collection.check_index_consistency().is_empty());
+    assert_eq!(collection.search_text("odd", 5000).len(), 1250);
+    assert_eq!(collection.search_text("1234", 10)[0].0, "1234");
+
+    let plain = Collection::new(CollectionConfig {
+        name: "plain".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    assert!(plain.rebuild_text_index(&ctx).is_err());
+
+    // BM25 can be turned on once points exist, and off again
+    plain.upsert(Point::new(PointId::Integer(1), Vector::new(vec![1.0, 0.0]), Some(serde_json::json!({"text": "late text"})))).unwrap();
+    assert!(plain.search_text("late", 10).is_empty());
+    assert_eq!(plain.enable_text_index(&ctx).unwrap(), Some(1));
+    assert!(plain.enable_bm25());
+    assert!(plain.enable_text_index(&ctx).is_err());
+    plain.upsert(Point::new(PointId::Integer(2), Vector::new(vec![1.0, 0.0]), Some(serde_json::json!({"text": "later text"})))).unwrap();
+    assert_eq!(plain.search_text("text", 10).len(), 2);
+    assert!(plain.check_index_consistency().is_empty());
+    plain.disable_text_index().unwrap();
+    assert!(!plain.enable_bm25());
+    assert!(plain.search_text("text", 10).is_empty());
+    assert!(plain.check_index_consistency().is_empty());
+}
+
+#[test]
+fn test_update_collection_checks_every_section() {
+    use vectx_test::TestServer;
+
+    let server = TestServer::start();
+    server.create_collection("notes", 2, "Dot");
+    let collection = server.storage().get_collection("notes").unwrap();
+
+    // An invalid section refuses the whole update, including the valid
+    // sections before it
+    let response = server.patch("/collections/notes", serde_json::json!({
+        "sparse_vectors": {"text": {}},
+        "vectors": {"distance": "Euclid"},
+        "hnsw_config": {"m": 32},
+        "search_defaults": {"limit": 0},
+    }));
+    response.assert_status(400);
+    assert!(collection.sparse_vectors().is_empty());
+    assert_eq!(collection.distance(), Distance::Dot);
+    assert_ne!(collection.hnsw_params().m, 32);
+
+    server.patch("/collections/notes", serde_json::json!({
+        "sparse_vectors": {"text": {}},
+        "vectors": {"distance": "Euclid"},
+        "hnsw_config": {"m": 32},
+    })).assert_ok();
+    assert!(collection.sparse_vector("text").is_some());
+    assert_eq!(collection.distance(), Distance::Euclidean);
+    assert_eq!(collection.hnsw_params().m, 32);
+}
+
+#[test]
+fn test_enable_bm25_later() {
+    use vectx_test::TestServer;
+
+    let server = TestServer::start();
+    server.create_collection("notes", 2, "Dot");
+    server.upsert_points("notes", serde_json::json!([
+        {"id": 1, "vector": [1.0, 0.0], "payload": {"text": "red wool coat"}},
+        {"id": 2, "vector": [0.0, 1.0], "payload": {"text": "blue hat"}},
+    ]));
+    server.patch("/collections/notes", serde_json::json!({"enable_bm25": true})).assert_ok();
+
+    let storage = server.storage();
+    let job = (0..500)
+        .find_map(|_| {
+            let job = storage.jobs().list().into_iter().find(|job| job.kind == "text_index_build")?;
+            if job.status == JobStatus::Running {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                None
+            } else {
+                Some(job)
+            }
+        })
+        .expect("text index build did not finish");
+    assert_eq!(job.status, JobStatus::Completed);
+    assert_eq!(job.result.unwrap()["documents"], 2);
+    let collection = storage.get_collection("notes").unwrap();
+    assert_eq!(collection.search_text("wool", 10)[0].0, "1");
+    assert!(storage.collection_manifest("notes").unwrap().config.enable_bm25);
+
+    server.patch("/collections/notes", serde_json::json!({"enable_bm25": false})).assert_ok();
+    assert!(collection.search_text("wool", 10).is_empty());
+    assert!(!storage.collection_manifest("notes").unwrap().config.enable_bm25);
+}
+
 #[test]
 fn test_storage_manager() {
     let temp_dir = tempfile::tempdir().unwrap();
@@ -172,94 +543,4087 @@ fn test_persistence_snapshot() {
 }
 
 #[test]
-fn test_payload_filtering() {
-    let config = CollectionConfig {
-        name: "test".to_string(),
+fn test_persisted_payloads_survive_restart() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "payloads".to_string(),
         vector_dim: 3,
         distance: Distance::Cosine,
         use_hnsw: false,
         enable_bm25: false,
+    }).unwrap();
+    for i in 0..10 {
+        let payload = serde_json::json!({"n": i, "tags": ["a", "b"], "nested": {"x": 1.5}});
+        collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 1.0, 1.0]), Some(payload))).unwrap();
+    }
+    storage.save().unwrap();
+    drop(collection);
+    drop(storage);
+
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let restored = storage.get_collection("payloads").unwrap();
+    assert_eq!(restored.get("5").unwrap().payload, Some(serde_json::json!({"n": 5, "tags": ["a", "b"], "nested": {"x": 1.5}})));
+}
+
+#[test]
+fn test_payload_index_persistence() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let config = CollectionConfig {
+        name: "shops".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
     };
-    
-    let collection = Collection::new(config);
-    
-    // Insert points with different categories
-    for i in 0..20 {
-        let point = Point::new(
-            PointId::Integer(i),
-            Vector::new(vec![i as f32, i as f32, i as f32]),
-            Some(serde_json::json!({
-                "category": if i % 2 == 0 { "A" } else { "B" },
-                "score": i
-            })),
-        );
-        collection.upsert(point).unwrap();
+    let collection = storage.create_collection(config).unwrap();
+    for (i, city) in ["Berlin", "Bern", "Paris"].iter().enumerate() {
+        collection.upsert(Point::new(
+            PointId::Integer(i as u64),
+            Vector::new(vec![1.0, i as f32]),
+            Some(serde_json::json!({"city": city})),
+        )).unwrap();
     }
-    
-    // Search with filter
-    use vectx_core::{PayloadFilter, FilterCondition};
-    let query = Vector::new(vec![10.0, 10.0, 10.0]);
-    let filter = PayloadFilter::new(FilterCondition::Equals {
-        field: "category".to_string(),
-        value: serde_json::json!("A"),
-    });
-    
-    let results = collection.search(&query, 10, Some(&filter));
-    
-    // All results should have category "A"
-    for (point, _) in results {
-        let category = point.payload
-            .as_ref()
-            .and_then(|p| p.get("category"))
-            .and_then(|v| v.as_str());
-        assert_eq!(category, Some("A"));
+    collection.create_payload_index("city", PayloadIndexType::Keyword).unwrap();
+    collection.create_payload_index("rating", PayloadIndexType::Float).unwrap();
+    storage.save_collection_config(&collection).unwrap();
+    assert_eq!(collection.payload_index_points()["city"], 3);
+    assert_eq!(collection.payload_index_points()["rating"], 0);
+    drop(collection);
+    drop(storage);
+
+    // Definitions survive a restart and the trigram index is rebuilt from them
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("shops").unwrap();
+    assert_eq!(collection.get_payload_indexes().get("city"), Some(&PayloadIndexType::Keyword));
+    assert_eq!(collection.get_payload_indexes().get("rating"), Some(&PayloadIndexType::Float));
+    let mut matches = collection.fuzzy_matches("city", "Berln", 1).unwrap();
+    matches.sort();
+    assert_eq!(matches, vec!["Berlin".to_string(), "Bern".to_string()]);
+
+    // Snapshots carry the definitions as of when they were taken
+    let snapshot = storage.create_collection_snapshot("shops").unwrap();
+    collection.delete_payload_index("rating").unwrap();
+    storage.save_collection_config(&collection).unwrap();
+    let restored = storage.recover_from_snapshot("shops", &snapshot.name, &RecoverOptions::default()).unwrap();
+    assert_eq!(restored.get_payload_indexes().len(), 2);
+    restored.delete_payload_index("rating").unwrap();
+    storage.save_collection_config(&restored).unwrap();
+    drop(collection);
+    drop(restored);
+    drop(storage);
+
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("shops").unwrap();
+    assert_eq!(collection.get_payload_indexes().len(), 1);
+    assert!(collection.is_field_indexed("city"));
+}
+
+#[test]
+fn test_warm_up() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "warm".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: true,
+        enable_bm25: false,
+    }).unwrap();
+    for i in 0..100u64 {
+        collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![1.0, i as f32]), None)).unwrap();
     }
+    storage.save().unwrap();
+    drop(collection);
+    drop(storage);
+
+    // Collections are loaded lazily; the warm-up loads them and builds the graph
+    let storage = std::sync::Arc::new(StorageManager::new(temp_dir.path()).unwrap());
+    assert!(!storage.is_loaded("warm"));
+    let warm_up = storage.start_warm_up(8);
+    let summary = warm_up.join().unwrap();
+    assert!(!storage.is_warming_up());
+    assert_eq!((summary.collections, summary.points, summary.queries), (1, 100, 8));
+    assert!(storage.is_loaded("warm"));
+    let collection = storage.get_collection("warm").unwrap();
+    assert_eq!(collection.stats().indexed_vectors_count, 100);
+    assert!(!collection.awaiting_index());
+    assert!(storage.readiness_issues().is_empty());
 }
 
-// ==================== Similarity Engine Tests ====================
+#[test]
+fn test_load_limits() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let config = CollectionConfig {
+        name: "busy".to_string(),
+        vector_dim: 2,
+        distance: Distance::Euclidean,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = storage.create_collection(config).unwrap();
+    let record = |ms: u64, queries: u64| {
+        for _ in 0..queries {
+            collection.record_query(std::time::Duration::from_millis(ms));
+        }
+    };
+    record(250, 3);
+    assert!(storage.readiness_issues().is_empty());
+
+    // A few slow queries are not enough to tell
+    storage.set_load_limits(LoadLimits { max_queued_jobs: Some(usize::MAX), max_p99_ms: Some(100.0) });
+    assert!(storage.readiness_issues().is_empty());
+    record(250, MIN_P99_QUERIES);
+    let issues = storage.readiness_issues();
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].contains("collection busy"), "{:?}", issues);
+
+    // Once past the limit, the latency must drop well below it
+    collection.sample_stats(0);
+    record(90, MIN_P99_QUERIES);
+    assert_eq!(storage.readiness_issues().len(), 1);
+    collection.sample_stats(0);
+    record(70, MIN_P99_QUERIES);
+    assert!(storage.readiness_issues().is_empty());
+    record(90, MIN_P99_QUERIES);
+    assert!(storage.readiness_issues().is_empty());
+
+    // The latency window closes with each statistics sample
+    record(250, MIN_P99_QUERIES);
+    assert!(!storage.readiness_issues().is_empty());
+    collection.sample_stats(0);
+    assert!(storage.readiness_issues().is_empty());
+}
 
 #[test]
-fn test_vector_search_with_collection() {
-    // Create collection
+fn test_runtime_rebuilds_keep_the_node_ready() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    assert!(storage.readiness_issues().is_empty());
+
+    // Once the node has been ready, a graph built with nothing to search
+    // meanwhile only concerns its collection
+    let collection = storage.create_collection(CollectionConfig {
+        name: "late".to_string(),
+        vector_dim: 2,
+        distance: Distance::Euclidean,
+        use_hnsw: true,
+        enable_bm25: false,
+    }).unwrap();
+    // A small graph, so the rebuild does not hold up other tests' jobs
+    collection.set_hnsw_params(vectx_core::HnswParams { m: 4, ef_construct: 4, ..Default::default() }).unwrap();
+    let points: Vec<Point> = (0..10_001u64)
+        .map(|i| Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 1.0]), None))
+        .collect();
+    collection.batch_upsert(points).unwrap();
+    assert!(collection.awaiting_index());
+    assert!(storage.readiness_issues().is_empty());
+    assert!(!storage.is_restoring("late"));
+}
+
+#[test]
+fn test_hamming_collection() {
+    use vectx_core::binary::{pack_bytes, unpack_bytes};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "hashes".to_string(),
+        vector_dim: 64,
+        distance: Distance::Hamming,
+        use_hnsw: true,
+        enable_bm25: false,
+    }).unwrap();
+    assert_eq!(collection.stored_dim(), 2);
+    // Runs of set bits: neighbouring ids differ in one bit
+    let code = |i: u64| ((1u64 << (i % 64)) - 1).rotate_left(13 * (i / 64) as u32).to_le_bytes();
+    for i in 0..300u64 {
+        collection.upsert(Point::new(PointId::Integer(i), pack_bytes(&code(i)), None)).unwrap();
+    }
+    assert!(collection.upsert(Point::new(PointId::Integer(999), Vector::new(vec![0.0; 64]), None)).is_err());
+
+    // One flipped bit away from point 7
+    let mut query = code(7);
+    query[3] ^= 0b100;
+    let results = collection.search(&pack_bytes(&query), 3, None);
+    assert_eq!(results[0].0.id.to_string(), "7");
+    assert_eq!(results[0].1, -1.0);
+    assert!(results[1].1 < -1.0);
+
+    // Results found through the graph are scored by Hamming distance too
+    let report = collection.verify_consistency(&pack_bytes(&query), 10).unwrap();
+    let hnsw = report.paths.iter().find(|p| p.path == SearchPath::Hnsw).unwrap();
+    assert!(!hnsw.results.is_empty());
+    assert!(hnsw.results.iter().all(|r| r.score <= 0.0 && r.score.fract() == 0.0));
+
+    // Packed bits survive a restart and cannot be switched to a float metric
+    assert!(!collection.set_distance(Distance::Cosine).unwrap());
+    storage.save().unwrap();
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("hashes").unwrap();
+    assert_eq!(collection.distance(), Distance::Hamming);
+    assert_eq!(unpack_bytes(&collection.get("42").unwrap().vector, 64), code(42));
+}
+
+#[test]
+fn test_normalization_policy() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
     let config = CollectionConfig {
-        name: "products".to_string(),
-        vector_dim: 3,  // Simple mock vectors
+        name: "raw".to_string(),
+        vector_dim: 2,
         distance: Distance::Cosine,
         use_hnsw: false,
         enable_bm25: false,
     };
-    
-    let collection = Collection::new(config);
-    
-    // Insert products with mock vectors
-    let products = vec![
-        (vec![1.0, 0.0, 0.0], serde_json::json!({"name": "Prosciutto cotto", "price": 1.99, "category": "salumi"})),
-        (vec![0.9, 0.1, 0.0], serde_json::json!({"name": "Prosciutto crudo", "price": 2.49, "category": "salumi"})),
-        (vec![0.8, 0.2, 0.0], serde_json::json!({"name": "Mortadella", "price": 1.79, "category": "salumi"})),
-        (vec![0.0, 0.0, 1.0], serde_json::json!({"name": "iPhone 15", "price": 999.0, "category": "electronics"})),
-        (vec![0.1, 0.0, 0.9], serde_json::json!({"name": "Samsung Galaxy", "price": 899.0, "category": "electronics"})),
-    ];
-    
-    for (i, (vec, payload)) in products.iter().enumerate() {
-        let point = Point::new(
-            PointId::Integer(i as u64),
-            Vector::new(vec.clone()),
-            Some(payload.clone()),
-        );
-        collection.upsert(point).unwrap();
-    }
-    
-    // Query with a vector similar to salumi products
-    let query_vector = Vector::new(vec![0.95, 0.05, 0.0]);
-    
-    let results = collection.search(&query_vector, 3, None);
-    assert_eq!(results.len(), 3);
-    
-    // Top results should be salumi products (similar vectors)
-    let top_category = results[0].0.payload
-        .as_ref()
-        .and_then(|p| p.get("category"))
-        .and_then(|v| v.as_str());
-    assert_eq!(top_category, Some("salumi"));
+    let collection = storage.create_collection(config).unwrap();
+    assert!(collection.set_normalization(NormalizationPolicy::Never).unwrap());
+    storage.save_collection_config(&collection).unwrap();
+    collection.upsert(Point::new(PointId::Integer(1), Vector::new(vec![3.0, 4.0]), None)).unwrap();
+    assert_eq!(collection.get("1").unwrap().vector.as_slice(), &[3.0, 4.0]);
+    let results = collection.search(&Vector::new(vec![6.0, 8.0]), 1, None);
+    assert!((results[0].1 - 1.0).abs() < 1e-6);
+
+    storage.save().unwrap();
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("raw").unwrap();
+    assert_eq!(collection.normalization(), NormalizationPolicy::Never);
+    assert_eq!(collection.get("1").unwrap().vector.as_slice(), &[3.0, 4.0]);
+
+    // Switching back normalizes what is stored
+    assert!(collection.set_normalization(NormalizationPolicy::OnWrite).unwrap());
+    let stored = collection.get("1").unwrap().vector;
+    assert!((stored.as_slice()[0] - 0.6).abs() < 1e-6);
+    assert!((stored.as_slice()[1] - 0.8).abs() < 1e-6);
 }
 
+#[test]
+fn test_duplicate_policy() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let config = CollectionConfig {
+        name: "dedup".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = storage.create_collection(config).unwrap();
+    collection.upsert(Point::new(PointId::Integer(1), Vector::new(vec![3.0, 4.0]), Some(serde_json::json!({"a": 1})))).unwrap();
+
+    // Vectors compare as stored, so a scaled copy is a duplicate under cosine
+    collection.set_duplicate_policy(DuplicatePolicy::Reject);
+    storage.save_collection_config(&collection).unwrap();
+    let copy = |id: u64, payload: serde_json::Value| Point::new(PointId::Integer(id), Vector::new(vec![6.0, 8.0]), Some(payload));
+    assert!(matches!(collection.upsert(copy(2, serde_json::json!({}))), Err(vectx_core::Error::DuplicateVector { .. })));
+    assert!(collection.batch_upsert(vec![
+        Point::new(PointId::Integer(3), Vector::new(vec![1.0, 0.0]), None),
+        Point::new(PointId::Integer(4), Vector::new(vec![2.0, 0.0]), None),
+    ]).is_err());
+    assert_eq!(collection.count(), 1);
+    // A point may keep its own vector
+    assert_eq!(collection.upsert_with_status(copy(1, serde_json::json!({"a": 2}))).unwrap(), UpsertStatus::Updated);
+
+    collection.set_duplicate_policy(DuplicatePolicy::Merge);
+    let status = collection.upsert_with_status(copy(2, serde_json::json!({"b": 3}))).unwrap();
+    assert_eq!(status, UpsertStatus::Merged("1".to_string()));
+    assert!(collection.get("2").is_none());
+    assert_eq!(collection.get("1").unwrap().payload, Some(serde_json::json!({"a": 2, "b": 3})));
+
+    collection.set_duplicate_policy(DuplicatePolicy::Tag);
+    storage.save_collection_config(&collection).unwrap();
+    collection.upsert(copy(2, serde_json::json!({"b": 4}))).unwrap();
+    assert_eq!(collection.get("2").unwrap().payload, Some(serde_json::json!({"b": 4, "duplicate_of": 1})));
+
+    // Once the first point is gone, its key no longer counts
+    collection.delete("1").unwrap();
+    collection.delete("2").unwrap();
+    collection.upsert(copy(5, serde_json::json!({}))).unwrap();
+    assert_eq!(collection.get("5").unwrap().payload, Some(serde_json::json!({})));
+
+    storage.save().unwrap();
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("dedup").unwrap();
+    assert_eq!(collection.duplicate_policy(), DuplicatePolicy::Tag);
+    collection.upsert(copy(6, serde_json::json!({}))).unwrap();
+    assert_eq!(collection.get("6").unwrap().payload, Some(serde_json::json!({"duplicate_of": 5})));
+}
+
+#[test]
+fn test_duplicate_keys_outlive_the_first_point() {
+    let collection = Collection::new(CollectionConfig {
+        name: "dedup_keys".to_string(),
+        vector_dim: 2,
+        distance: Distance::Euclidean,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    let copy = |id: u64| Point::new(PointId::Integer(id), Vector::new(vec![1.0, 2.0]), Some(serde_json::json!({})));
+    collection.set_duplicate_policy(DuplicatePolicy::Tag);
+    collection.upsert(copy(1)).unwrap();
+    collection.upsert(copy(2)).unwrap();
+    assert_eq!(collection.get("2").unwrap().payload, Some(serde_json::json!({"duplicate_of": 1})));
+
+    // Point 2 still has the vector once point 1 is gone or moves away
+    collection.delete("1").unwrap();
+    collection.upsert(copy(3)).unwrap();
+    assert_eq!(collection.get("3").unwrap().payload, Some(serde_json::json!({"duplicate_of": 2})));
+    collection.upsert(Point::new(PointId::Integer(2), Vector::new(vec![5.0, 5.0]), None)).unwrap();
+    collection.upsert(copy(4)).unwrap();
+    assert_eq!(collection.get("4").unwrap().payload, Some(serde_json::json!({"duplicate_of": 3})));
+
+    // A rejected batch leaves nothing behind and batch mode off
+    collection.set_duplicate_policy(DuplicatePolicy::Reject);
+    assert!(collection.batch_upsert(vec![
+        Point::new(PointId::Integer(5), Vector::new(vec![9.0, 9.0]), None),
+        copy(6),
+    ]).is_err());
+    assert!(collection.get("5").is_none());
+    collection.upsert(Point::new(PointId::Integer(7), Vector::new(vec![7.0, 7.0]), None)).unwrap();
+    assert_eq!(collection.count(), 4);
+}
+
+#[test]
+fn test_write_lock() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    assert!(storage.write_lock_error().is_none());
+
+    let previous = storage.set_locks(Locks { write: true, error_message: None });
+    assert_eq!(previous, Locks::default());
+    assert_eq!(storage.write_lock_error().as_deref(), Some(DEFAULT_WRITE_LOCK_MESSAGE));
+
+    let locks = Locks { write: true, error_message: Some("migrating".to_string()) };
+    storage.set_locks(locks.clone());
+    assert_eq!(storage.write_lock_error().as_deref(), Some("migrating"));
+    assert_eq!(storage.set_locks(Locks::default()), locks);
+    assert!(storage.write_lock_error().is_none());
+}
+
+#[test]
+fn test_write_lock_enforced() {
+    use vectx_api::grpc::vectx::{CountPoints, DeleteCollection, GetCollectionInfoRequest, SearchPoints, UpsertPoints};
+    use vectx_test::TestServer;
+
+    let server = TestServer::start();
+    server.create_collection("locked", 2, "Cosine");
+    server.upsert_points("locked", serde_json::json!([{"id": 1, "vector": [1.0, 0.0]}]));
+    server.post("/locks", serde_json::json!({"write": true, "error_message": "migrating"})).assert_ok();
+    assert_eq!(server.get("/locks").result()["write"], true);
+
+    // Writes over REST are refused with the lock's message
+    let refused = [
+        server.put("/collections/locked/points", serde_json::json!({"points": [{"id": 2, "vector": [0.0, 1.0]}]})),
+        server.post("/collections/locked/points/payload", serde_json::json!({"payload": {"a": 1}, "points": [1]})),
+        server.post("/collections/locked/points/delete", serde_json::json!({"points": [1]})),
+        server.put("/collections/locked/index", serde_json::json!({"field_name": "a", "field_schema": "keyword"})),
+        server.put("/collections/other", serde_json::json!({"vectors": {"size": 2, "distance": "Cosine"}})),
+        server.delete("/collections/locked"),
+        server.post("/collections/locked/reindex", serde_json::json!({})),
+        server.post("/collections/locked/index/text/rebuild", serde_json::json!({})),
+        server.put("/collections/locked/ef_tuning", serde_json::json!({"target_recall": 0.9})),
+        server.delete("/collections/locked/ef_tuning"),
+    ];
+    for response in refused {
+        response.assert_status(403);
+        assert_eq!(response.error(), Some("migrating"));
+    }
+
+    // Reads go on, including those sent with POST
+    assert_eq!(server.count("locked"), 1);
+    server.get("/collections/locked").assert_ok();
+    server.post("/collections/locked/points/search", serde_json::json!({"vector": [1.0, 0.0], "limit": 1})).assert_ok();
+    server.post("/collections/locked/points/scroll", serde_json::json!({"limit": 1})).assert_ok();
+    server.post("/collections/locked/unload", serde_json::json!({})).assert_ok();
+    server.post("/collections/locked/load", serde_json::json!({})).assert_ok();
+
+    // The same over gRPC
+    let upsert = server.block_on(server.points_client().upsert(UpsertPoints {
+        collection_name: "locked".to_string(),
+        ..Default::default()
+    })).unwrap_err();
+    assert_eq!(upsert.code(), tonic::Code::PermissionDenied);
+    assert_eq!(upsert.message(), "migrating");
+    let delete = server.block_on(server.collections_client().delete(DeleteCollection {
+        collection_name: "locked".to_string(),
+    })).unwrap_err();
+    assert_eq!(delete.code(), tonic::Code::PermissionDenied);
+    let count = server.block_on(server.points_client().count(CountPoints {
+        collection_name: "locked".to_string(),
+        ..Default::default()
+    })).unwrap().into_inner();
+    assert_eq!(count.result.unwrap().count, 1);
+    server.block_on(server.points_client().search(SearchPoints {
+        collection_name: "locked".to_string(),
+        vector: vec![1.0, 0.0],
+        limit: 1,
+        ..Default::default()
+    })).unwrap();
+    server.block_on(server.collections_client().get(GetCollectionInfoRequest {
+        collection_name: "locked".to_string(),
+    })).unwrap();
+
+    // Unlocked, writes go through again
+    server.post("/locks", serde_json::json!({"write": false})).assert_ok();
+    server.upsert_points("locked", serde_json::json!([{"id": 2, "vector": [0.0, 1.0]}]));
+    assert_eq!(server.count("locked"), 2);
+}
+
+#[test]
+fn test_operation_journal_persistence() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    
+    let config = CollectionConfig {
+        name: "journaled".to_string(),
+        vector_dim: 3,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    
+    let collection = storage.create_collection(config).unwrap();
+    collection.batch_upsert(vec![
+        Point::new(PointId::Integer(1), Vector::new(vec![1.0, 0.0, 0.0]), None),
+        Point::new(PointId::Integer(2), Vector::new(vec![0.0, 1.0, 0.0]), None),
+    ]).unwrap();
+    collection.update_vector("1", Vector::new(vec![0.5, 0.5, 0.0])).unwrap();
+    collection.delete("2").unwrap();
+    
+    let ops = storage.get_operations("journaled", None, 100).unwrap();
+    assert_eq!(ops.len(), 3);
+    assert_eq!(ops[0].op_type, OperationType::Upsert);
+    assert_eq!(ops[0].point_ids, vec!["1".to_string(), "2".to_string()]);
+    assert_eq!(ops[2].op_type, OperationType::Delete);
+    
+    let since = storage.get_operations("journaled", Some(ops[1].op_id), 100).unwrap();
+    assert_eq!(since.len(), 2);
+    
+    storage.save().unwrap();
+    drop(storage);
+    
+    // Journal survives a restart and op ids keep increasing
+    let storage2 = StorageManager::new(temp_dir.path()).unwrap();
+    let restored = storage2.get_operations("journaled", None, 100).unwrap();
+    assert_eq!(restored, ops);
+    
+    let collection = storage2.get_collection("journaled").unwrap();
+    collection.clear_payload("1").unwrap();
+    let latest = storage2.get_operations("journaled", Some(ops[2].op_id + 1), 100).unwrap();
+    assert_eq!(latest.len(), 1);
+    assert_eq!(latest[0].op_type, OperationType::ClearPayload);
+}
+
+#[test]
+fn test_payload_filtering() {
+    let config = CollectionConfig {
+        name: "test".to_string(),
+        vector_dim: 3,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    
+    let collection = Collection::new(config);
+    
+    // Insert points with different categories
+    for i in 0..20 {
+        let point = Point::new(
+            PointId::Integer(i),
+            Vector::new(vec![i as f32, i as f32, i as f32]),
+            Some(serde_json::json!({
+                "category": if i % 2 == 0 { "A" } else { "B" },
+                "score": i
+            })),
+        );
+        collection.upsert(point).unwrap();
+    }
+    
+    // Search with filter
+    use vectx_core::{PayloadFilter, FilterCondition};
+    let query = Vector::new(vec![10.0, 10.0, 10.0]);
+    let filter = PayloadFilter::new(FilterCondition::Equals {
+        field: "category".to_string(),
+        value: serde_json::json!("A"),
+    });
+    
+    let results = collection.search(&query, 10, Some(&filter));
+    
+    // All results should have category "A"
+    for (point, _) in results {
+        let category = point.payload
+            .as_ref()
+            .and_then(|p| p.get("category"))
+            .and_then(|v| v.as_str());
+        assert_eq!(category, Some("A"));
+    }
+}
+
+#[test]
+fn test_array_match_modes() {
+    use vectx_api::core_requests;
+
+    let collection = Collection::new(CollectionConfig {
+        name: "deli".to_string(),
+        vector_dim: 1,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    let tags = [
+        serde_json::json!(["cured", "pork"]),
+        serde_json::json!(["cured", "beef", "smoked"]),
+        serde_json::json!("cured"),
+        serde_json::json!([]),
+        serde_json::json!(["pork"]),
+    ];
+    for (i, tags) in tags.into_iter().enumerate() {
+        let point = Point::new(PointId::Integer(i as u64), Vector::new(vec![1.0]), Some(serde_json::json!({"tags": tags})));
+        collection.upsert(point).unwrap();
+    }
+    collection.upsert(Point::new(PointId::Integer(5), Vector::new(vec![1.0]), None)).unwrap();
+    let matching_ids = |filter: serde_json::Value| -> Vec<String> {
+        let condition = core_requests::parse_filter(&filter);
+        let filter = core_requests::build_filter(condition, None, Some(&collection)).unwrap();
+        let mut ids: Vec<String> = collection.get_all_points().into_iter()
+            .filter(|p| filter.matches(p))
+            .map(|p| p.id.to_string())
+            .collect();
+        ids.sort();
+        ids
+    };
+    let with_match = |m: serde_json::Value| serde_json::json!({"must": [{"key": "tags", "match": m}]});
+
+    // A value matches arrays holding it
+    assert_eq!(matching_ids(with_match(serde_json::json!({"value": "cured"}))), vec!["0", "1", "2"]);
+    assert_eq!(matching_ids(with_match(serde_json::json!({"any": ["beef", "pork"]}))), vec!["0", "1", "4"]);
+    assert_eq!(matching_ids(with_match(serde_json::json!({"all": ["cured", "pork"]}))), vec!["0"]);
+    assert_eq!(matching_ids(with_match(serde_json::json!({"all": ["cured"]}))), vec!["0", "1", "2"]);
+    // Except needs an element outside the list; empty arrays and missing fields have none
+    assert_eq!(matching_ids(with_match(serde_json::json!({"except": ["cured", "pork"]}))), vec!["1"]);
+    assert_eq!(matching_ids(with_match(serde_json::json!({"except": ["beef"]}))), vec!["0", "1", "2", "4"]);
+    // An array value is not matched against the elements of a stored array
+    assert_eq!(matching_ids(with_match(serde_json::json!({"value": ["cured", "pork"]}))), Vec::<String>::new());
+}
+
+// ==================== Similarity Engine Tests ====================
+
+#[test]
+fn test_vector_search_with_collection() {
+    // Create collection
+    let config = CollectionConfig {
+        name: "products".to_string(),
+        vector_dim: 3,  // Simple mock vectors
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    
+    let collection = Collection::new(config);
+    
+    // Insert products with mock vectors
+    let products = [
+        (vec![1.0, 0.0, 0.0], serde_json::json!({"name": "Prosciutto cotto", "price": 1.99, "category": "salumi"})),
+        (vec![0.9, 0.1, 0.0], serde_json::json!({"name": "Prosciutto crudo", "price": 2.49, "category": "salumi"})),
+        (vec![0.8, 0.2, 0.0], serde_json::json!({"name": "Mortadella", "price": 1.79, "category": "salumi"})),
+        (vec![0.0, 0.0, 1.0], serde_json::json!({"name": "iPhone 15", "price": 999.0, "category": "electronics"})),
+        (vec![0.1, 0.0, 0.9], serde_json::json!({"name": "Samsung Galaxy", "price": 899.0, "category": "electronics"})),
+    ];
+    
+    for (i, (vec, payload)) in products.iter().enumerate() {
+        let point = Point::new(
+            PointId::Integer(i as u64),
+            Vector::new(vec.clone()),
+            Some(payload.clone()),
+        );
+        collection.upsert(point).unwrap();
+    }
+    
+    // Query with a vector similar to salumi products
+    let query_vector = Vector::new(vec![0.95, 0.05, 0.0]);
+    
+    let results = collection.search(&query_vector, 3, None);
+    assert_eq!(results.len(), 3);
+    
+    // Top results should be salumi products (similar vectors)
+    let top_category = results[0].0.payload
+        .as_ref()
+        .and_then(|p| p.get("category"))
+        .and_then(|v| v.as_str());
+    assert_eq!(top_category, Some("salumi"));
+}
+
+#[test]
+fn test_scoped_api_keys() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let access = storage.access_control();
+
+    // No keys configured: everything is allowed
+    assert!(access.check(None, Some("docs"), Permission::Admin).is_ok());
+
+    access.set_master_key(Some("master"));
+    let (_, reader) = access
+        .create_key("team-a", vec!["team_a_*".to_string()], Permission::Read)
+        .unwrap();
+    let (writer_key, writer) = access
+        .create_key("ingest", vec!["docs".to_string()], Permission::Write)
+        .unwrap();
+
+    assert!(access.check(Some("master"), None, Permission::Admin).is_ok());
+    assert!(access.check(Some(&reader), Some("team_a_docs"), Permission::Read).is_ok());
+    assert_eq!(access.check(Some(&reader), Some("team_a_docs"), Permission::Write), Err(AccessError::Forbidden));
+    assert_eq!(access.check(Some(&reader), Some("docs"), Permission::Read), Err(AccessError::Forbidden));
+    assert_eq!(access.check(Some(&reader), None, Permission::Read), Err(AccessError::Forbidden));
+    assert!(access.check(Some(&writer), Some("docs"), Permission::Write).is_ok());
+    assert_eq!(access.check(Some(&writer), Some("docs"), Permission::Admin), Err(AccessError::Forbidden));
+    assert_eq!(access.check(Some("wrong"), Some("docs"), Permission::Read), Err(AccessError::Unauthenticated));
+    assert_eq!(access.check(None, Some("docs"), Permission::Read), Err(AccessError::Unauthenticated));
+
+    // Keys survive a restart; only hashes are stored
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let access = storage.access_control();
+    assert_eq!(access.list_keys().len(), 2);
+    assert!(access.check(Some(&writer), Some("docs"), Permission::Write).is_ok());
+    let stored = std::fs::read_to_string(temp_dir.path().join("api_keys.json")).unwrap();
+    assert!(!stored.contains(&writer));
+
+    assert!(access.delete_key(&writer_key.id).unwrap());
+    assert_eq!(access.check(Some(&writer), Some("docs"), Permission::Write), Err(AccessError::Unauthenticated));
+}
+
+#[test]
+fn test_rest_route_access() {
+    use actix_web::http::Method;
+    use vectx_api::auth::required_access;
+
+    assert_eq!(required_access(&Method::GET, "/healthz"), None);
+
+    let search = required_access(&Method::POST, "/collections/docs/points/search").unwrap();
+    assert_eq!(search.collection.as_deref(), Some("docs"));
+    assert_eq!(search.permission, Permission::Read);
+
+    let hybrid = required_access(&Method::POST, "/collections/docs/points/hybrid").unwrap();
+    assert_eq!(hybrid.permission, Permission::Read);
+
+    let upsert = required_access(&Method::PUT, "/collections/docs/points").unwrap();
+    assert_eq!(upsert.permission, Permission::Write);
+
+    let delete = required_access(&Method::POST, "/collections/docs/points/delete").unwrap();
+    assert_eq!(delete.permission, Permission::Write);
+
+    let drop_collection = required_access(&Method::DELETE, "/collections/docs").unwrap();
+    assert_eq!(drop_collection.permission, Permission::Admin);
+
+    let shard_snapshot = required_access(&Method::POST, "/collections/docs/shards/0/snapshots").unwrap();
+    assert_eq!(shard_snapshot.collection.as_deref(), Some("docs"));
+    assert_eq!(shard_snapshot.permission, Permission::Admin);
+    let shard_download = required_access(&Method::GET, "/collections/docs/shards/0/snapshots/s.snapshot").unwrap();
+    assert_eq!(shard_download.permission, Permission::Read);
+
+    let unload = required_access(&Method::POST, "/collections/docs/unload").unwrap();
+    assert_eq!(unload.collection.as_deref(), Some("docs"));
+    assert_eq!(unload.permission, Permission::Admin);
+
+    let graph = required_access(&Method::GET, "/collections/docs/hnsw/graph").unwrap();
+    assert_eq!(graph.collection.as_deref(), Some("docs"));
+    assert_eq!(graph.permission, Permission::Admin);
+
+    let rebuild = required_access(&Method::POST, "/collections/docs/index/text/rebuild").unwrap();
+    assert_eq!(rebuild.permission, Permission::Admin);
+
+    let tuning = required_access(&Method::PUT, "/collections/docs/ef_tuning").unwrap();
+    assert_eq!(tuning.permission, Permission::Admin);
+    let tuning = required_access(&Method::GET, "/collections/docs/ef_tuning").unwrap();
+    assert_eq!(tuning.permission, Permission::Read);
+
+    let admin = required_access(&Method::GET, "/admin/keys").unwrap();
+    assert_eq!(admin.collection, None);
+    assert_eq!(admin.permission, Permission::Admin);
+}
+
+#[test]
+fn test_jwt_claims() {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let access = storage.access_control();
+    access.set_master_key(Some("secret"));
+
+    let sign = |claims: serde_json::Value, key: &str| {
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(key.as_bytes())).unwrap()
+    };
+
+    let tenant = sign(serde_json::json!({
+        "access": [{"collection": "docs", "access": "r", "payload": {"tenant_id": "acme"}}]
+    }), "secret");
+    let grant = access.check(Some(&tenant), Some("docs"), Permission::Read).unwrap();
+    assert_eq!(grant.payload.unwrap()["tenant_id"], "acme");
+    assert_eq!(access.check(Some(&tenant), Some("docs"), Permission::Write), Err(AccessError::Forbidden));
+    assert_eq!(access.check(Some(&tenant), Some("other"), Permission::Read), Err(AccessError::Forbidden));
+
+    let reader = sign(serde_json::json!({"access": "r"}), "secret");
+    assert_eq!(access.check(Some(&reader), None, Permission::Read).unwrap().payload, None);
+    assert_eq!(access.check(Some(&reader), Some("docs"), Permission::Write), Err(AccessError::Forbidden));
+
+    let manager = sign(serde_json::json!({"access": "m"}), "secret");
+    assert!(access.check(Some(&manager), None, Permission::Admin).is_ok());
+
+    let forged = sign(serde_json::json!({"access": "m"}), "not-the-secret");
+    assert_eq!(access.check(Some(&forged), None, Permission::Read), Err(AccessError::Unauthenticated));
+
+    let expired = sign(serde_json::json!({"access": "m", "exp": 1_000_000}), "secret");
+    assert_eq!(access.check(Some(&expired), None, Permission::Read), Err(AccessError::Unauthenticated));
+}
+
+#[test]
+fn test_kmeans_job() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+
+    let config = CollectionConfig {
+        name: "clusters".to_string(),
+        vector_dim: 2,
+        distance: Distance::Euclidean,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = storage.create_collection(config).unwrap();
+    for i in 0..40u64 {
+        let base = if i % 2 == 0 { 0.0 } else { 50.0 };
+        let vector = Vector::new(vec![base + (i % 3) as f32 * 0.1, base]);
+        collection.upsert(Point::new(PointId::Integer(i), vector, None)).unwrap();
+    }
+
+    let kmeans = KMeansConfig { seed: Some(1), ..KMeansConfig::new(2) };
+    let job = storage.jobs().spawn("kmeans", Some("clusters"), move |_| {
+        let result = collection.kmeans(&kmeans, None, Some("cluster_id")).map_err(|e| e.to_string())?;
+        Ok(serde_json::json!({ "sizes": result.sizes }))
+    }).unwrap();
+
+    let finished = (0..200)
+        .find_map(|_| {
+            let job = storage.jobs().get(&job.id).unwrap();
+            if job.status == JobStatus::Running {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                None
+            } else {
+                Some(job)
+            }
+        })
+        .expect("job did not finish");
+    assert_eq!(finished.status, JobStatus::Completed);
+    assert_eq!(finished.result.unwrap()["sizes"], serde_json::json!([20, 20]));
+    assert_eq!(storage.jobs().list().len(), 1);
+
+    let collection = storage.get_collection("clusters").unwrap();
+    let a = collection.get("0").unwrap().payload.unwrap()["cluster_id"].clone();
+    let b = collection.get("1").unwrap().payload.unwrap()["cluster_id"].clone();
+    assert_ne!(a, b);
+    assert_eq!(collection.get("2").unwrap().payload.unwrap()["cluster_id"], a);
+
+    // A cancelled job stops at its next check and its result is dropped
+    assert_eq!(storage.jobs().cancel(&job.id).unwrap().status, JobStatus::Completed);
+    let job = storage.jobs().spawn("wait", Some("clusters"), |ctx| {
+        while !ctx.is_cancelled() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        Ok(serde_json::json!("done"))
+    }).unwrap();
+    let cancelled = storage.jobs().cancel(&job.id).unwrap();
+    assert_eq!(cancelled.status, JobStatus::Cancelled);
+    let background = get_background_system();
+    let background_id = job.background_id.unwrap();
+    assert!((0..200).any(|_| {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        background.job(background_id).is_none()
+    }));
+    assert!(storage.jobs().get(&job.id).unwrap().result.is_none());
+    assert!(storage.jobs().cancel("missing").is_none());
+}
+
+#[test]
+fn test_find_duplicates() {
+    let config = CollectionConfig {
+        name: "dedup".to_string(),
+        vector_dim: 3,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = Collection::new(config);
+
+    collection.upsert(Point::new(PointId::Integer(1), Vector::new(vec![1.0, 0.0, 0.0]), None)).unwrap();
+    collection.upsert(Point::new(PointId::Integer(2), Vector::new(vec![0.99, 0.01, 0.0]), None)).unwrap();
+    collection.upsert(Point::new(PointId::Integer(3), Vector::new(vec![0.0, 1.0, 0.0]), None)).unwrap();
+    // Re-upsert bumps the version, so point 2 is the one to keep
+    collection.upsert(Point::new(PointId::Integer(2), Vector::new(vec![0.99, 0.01, 0.0]), None)).unwrap();
+
+    let groups = collection.find_duplicates(0.95, 5, None);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].keep, PointId::Integer(2));
+    assert_eq!(groups[0].duplicates, vec![PointId::Integer(1)]);
+    assert!(groups[0].min_score >= 0.95);
+
+    assert!(collection.find_duplicates(0.99999, 5, None).is_empty());
+}
+
+#[test]
+fn test_collection_stats() {
+    let config = CollectionConfig {
+        name: "stats".to_string(),
+        vector_dim: 4,
+        distance: Distance::Cosine,
+        use_hnsw: true,
+        enable_bm25: true,
+    };
+    let collection = Collection::new(config);
+    for i in 0..10u64 {
+        let payload = serde_json::json!({"text": format!("document number {}", i)});
+        collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![1.0, i as f32, 0.0, 0.5]), Some(payload))).unwrap();
+    }
+
+    let stats = collection.stats();
+    assert_eq!(stats.points_count, 10);
+    assert_eq!(stats.vectors_count, 10);
+    assert!(stats.memory.vectors_bytes >= 10 * 4 * 4);
+    assert!(stats.memory.payload_bytes > 0);
+
+    collection.prewarm_index().unwrap();
+    let stats = collection.stats();
+    assert_eq!(stats.indexed_vectors_count, 10);
+    assert!(stats.memory.hnsw_bytes > 0);
+    assert!(stats.hnsw_built_at.is_some());
+    assert_eq!(stats.memory.total_bytes(),
+        stats.memory.vectors_bytes + stats.memory.payload_bytes + stats.memory.hnsw_bytes + stats.memory.bm25_bytes);
+}
+
+#[test]
+fn test_consistency_params() {
+    use actix_web::web::Query;
+    use vectx_api::consistency::{ReadConsistency, ReadParams, ReplicationParams, UpdateParams, WriteOrdering};
+
+    let update = Query::<UpdateParams>::from_query("wait=true&ordering=strong").unwrap();
+    assert_eq!(update.ordering, Some(WriteOrdering::Strong));
+    assert_eq!(update.status(), "completed");
+    assert_eq!(Query::<UpdateParams>::from_query("").unwrap().status(), "acknowledged");
+    assert!(Query::<UpdateParams>::from_query("ordering=eventual").is_err());
+
+    let read = Query::<ReadParams>::from_query("consistency=majority").unwrap();
+    assert_eq!(read.consistency, Some(ReadConsistency::Majority));
+    assert_eq!(Query::<ReadParams>::from_query("consistency=2").unwrap().consistency, Some(ReadConsistency::Factor(2)));
+    assert!(Query::<ReadParams>::from_query("consistency=0").is_err());
+    assert!(Query::<ReadParams>::from_query("consistency=most").is_err());
+
+    assert_eq!(ReadConsistency::Factor(3).required_replicas(1), 1);
+    assert_eq!(ReadConsistency::Majority.required_replicas(4), 3);
+    assert_eq!(ReadConsistency::All.required_replicas(3), 3);
+
+    let ok = ReplicationParams { shard_number: Some(1), replication_factor: Some(2), write_consistency_factor: Some(2) };
+    assert!(ok.validate().is_ok());
+    let too_strict = ReplicationParams { write_consistency_factor: Some(2), ..Default::default() };
+    assert!(too_strict.validate().is_err());
+    let zero = ReplicationParams { shard_number: Some(0), ..Default::default() };
+    assert!(zero.validate().is_err());
+}
+
+#[test]
+fn test_sparse_vectors() {
+    use std::collections::HashMap;
+    use vectx_core::SparseVector;
+
+    let config = CollectionConfig {
+        name: "sparse".to_string(),
+        vector_dim: 0,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = Collection::new(config);
+
+    let point = |id: u64, indices: Vec<u32>, values: Vec<f32>| {
+        let mut sparse = HashMap::new();
+        sparse.insert("text".to_string(), SparseVector::new(indices, values));
+        Point::new_sparse(PointId::Integer(id), sparse, None)
+    };
+    collection.upsert(point(1, vec![1, 5], vec![1.0, 2.0])).unwrap();
+    collection.upsert(point(2, vec![5, 9], vec![0.5, 3.0])).unwrap();
+
+    // Mismatched lengths and duplicate indices are rejected
+    assert!(collection.upsert(point(3, vec![1, 2], vec![1.0])).is_err());
+    assert!(collection.upsert(point(3, vec![4, 4], vec![1.0, 1.0])).is_err());
+    assert_eq!(collection.count(), 2);
+
+    let stored = collection.get("1").unwrap();
+    assert_eq!(stored.sparse_vectors["text"].indices, vec![1, 5]);
+
+    let query = SparseVector::new(vec![5], vec![1.0]);
+    let results = collection.search_sparse(&query, "text", 10, None);
+    assert_eq!(results[0].0.id, PointId::Integer(1));
+    assert_eq!(results[0].1, 2.0);
+    assert_eq!(results[1].1, 0.5);
+}
+
+#[test]
+fn test_resolve_using() {
+    use vectx_core::{SparseVector, SparseVectorConfig};
+
+    let config = CollectionConfig {
+        name: "using".to_string(),
+        vector_dim: 0,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = Collection::new(config);
+    let mut sparse = HashMap::new();
+    sparse.insert("text".to_string(), SparseVector::new(vec![1, 5], vec![1.0, 2.0]));
+    collection.upsert(Point::new_sparse(PointId::Integer(1), sparse, None)).unwrap();
+
+    // Undeclared sparse vectors are selected by the names points hold
+    assert_eq!(collection.resolve_using(None).unwrap(), VectorUsing::Dense);
+    assert_eq!(collection.resolve_using(Some("")).unwrap(), VectorUsing::Dense);
+    assert_eq!(collection.resolve_using(Some("text")).unwrap(), VectorUsing::Sparse("text".to_string()));
+
+    // Without a dense vector name, as for collections created before names
+    // were kept, other names select the dense vector
+    assert_eq!(collection.resolve_using(Some("dense")).unwrap(), VectorUsing::Dense);
+
+    // Once the dense vector is named, unknown names are errors
+    collection.set_dense_vector_name(Some("dense".to_string()));
+    assert_eq!(collection.resolve_using(Some("dense")).unwrap(), VectorUsing::Dense);
+    let err = collection.resolve_using(Some("txet")).unwrap_err().to_string();
+    assert!(err.contains("'txet'") && err.contains("dense vector: dense") && err.contains("sparse vectors: text"), "{}", err);
+
+    // Once declared, only declared sparse names can be queried
+    collection.set_sparse_vector("title", SparseVectorConfig::default());
+    assert_eq!(collection.resolve_using(Some("title")).unwrap(), VectorUsing::Sparse("title".to_string()));
+    let err = collection.resolve_using(Some("text")).unwrap_err().to_string();
+    assert!(err.contains("sparse vectors: title"), "{}", err);
+}
+
+#[test]
+fn test_sparse_only_dense_vectors() {
+    use std::collections::HashMap;
+    use vectx_core::SparseVector;
+
+    let sparse_point = |id: u64| {
+        let mut sparse = HashMap::new();
+        sparse.insert("text".to_string(), SparseVector::new(vec![id as u32], vec![1.0]));
+        Point::new_sparse(PointId::Integer(id), sparse, None)
+    };
+    let dense_point = |id: u64, vector: Vec<f32>| Point::new(PointId::Integer(id), Vector::new(vector), None);
+    let config = |name: &str| CollectionConfig {
+        name: name.to_string(),
+        vector_dim: 0,
+        distance: Distance::Dot,
+        use_hnsw: true,
+        enable_bm25: false,
+    };
+
+    // Without a dimension, dense vectors are rejected
+    let sparse = Collection::new(config("sparse"));
+    sparse.upsert(sparse_point(1)).unwrap();
+    let err = sparse.upsert(dense_point(2, vec![1.0, 2.0])).unwrap_err();
+    assert!(err.to_string().contains("infer_vector_dim"), "{}", err);
+    assert!(sparse.batch_upsert(vec![sparse_point(3), dense_point(4, vec![1.0])]).is_err());
+    assert!(sparse.update_vector("1", Vector::new(vec![1.0, 2.0])).is_err());
+    assert_eq!(sparse.count(), 1);
+    assert_eq!(sparse.vector_dim(), 0);
+
+    // Inferring takes the dimension from the first point of an empty collection
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let inferred = storage.create_collection(config("inferred")).unwrap();
+    inferred.set_infer_vector_dim(true);
+    storage.save_collection_config(&inferred).unwrap();
+    inferred.upsert(dense_point(1, vec![1.0, 2.0, 3.0])).unwrap();
+    assert_eq!(inferred.vector_dim(), 3);
+    assert!(matches!(
+        inferred.upsert(dense_point(2, vec![1.0, 2.0])),
+        Err(vectx_core::Error::InvalidDimension { expected: 3, actual: 2 })
+    ));
+    inferred.upsert(dense_point(2, vec![0.0, 1.0, 0.0])).unwrap();
+
+    // Points written first without a dense vector leave nothing to infer from
+    let late = Collection::new(config("late"));
+    late.set_infer_vector_dim(true);
+    late.upsert(sparse_point(1)).unwrap();
+    assert!(late.upsert(dense_point(2, vec![1.0])).is_err());
+
+    // The flag is kept, and the dimension inferred again from the stored points
+    storage.save().unwrap();
+    drop(inferred);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let restored = storage.get_collection("inferred").unwrap();
+    assert!(restored.infer_vector_dim());
+    assert_eq!(restored.vector_dim(), 3);
+    assert!(restored.upsert(dense_point(3, vec![1.0])).is_err());
+}
+
+#[test]
+fn test_sparse_idf_modifier() {
+    use std::collections::HashMap;
+    use vectx_core::{SparseModifier, SparseVector};
+
+    let config = CollectionConfig {
+        name: "sparse_idf".to_string(),
+        vector_dim: 0,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = Collection::new(config);
+    collection.set_sparse_modifier("text", SparseModifier::Idf);
+    assert_eq!(collection.sparse_modifier("text"), SparseModifier::Idf);
+    assert_eq!(collection.sparse_modifier("other"), SparseModifier::None);
+
+    let point = |id: u64, indices: Vec<u32>| {
+        let values = vec![1.0; indices.len()];
+        let mut sparse = HashMap::new();
+        sparse.insert("text".to_string(), SparseVector::new(indices, values));
+        Point::new_sparse(PointId::Integer(id), sparse, None)
+    };
+    // Dimension 1 is in every point, dimension 2 only in point 1
+    collection.upsert(point(1, vec![2])).unwrap();
+    collection.upsert(point(2, vec![1])).unwrap();
+    collection.upsert(point(3, vec![1])).unwrap();
+    collection.upsert(point(4, vec![1])).unwrap();
+
+    // A plain dot product ties; IDF favours the rare dimension
+    let query = SparseVector::new(vec![1, 2], vec![1.0, 1.0]);
+    let results = collection.search_sparse(&query, "text", 4, None);
+    assert_eq!(results[0].0.id, PointId::Integer(1));
+    assert!(results[0].1 > results[1].1);
+
+    // Frequencies follow deletes: once dimension 1 is rare too, scores tie
+    collection.delete("3").unwrap();
+    collection.delete("4").unwrap();
+    let results = collection.search_sparse(&query, "text", 4, None);
+    assert_eq!(results.len(), 2);
+    assert!((results[0].1 - results[1].1).abs() < 1e-6);
+
+    collection.set_sparse_modifier("text", SparseModifier::None);
+    let results = collection.search_sparse(&query, "text", 4, None);
+    assert_eq!(results[0].1, 1.0);
+}
+
+#[test]
+fn test_sparse_vector_declarations() {
+    use vectx_core::{SparseModifier, SparseVector, SparseVectorConfig};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let config = CollectionConfig {
+        name: "declared".to_string(),
+        vector_dim: 0,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = storage.create_collection(config).unwrap();
+    let sparse_point = |id: u64, name: &str| {
+        let mut sparse = HashMap::new();
+        sparse.insert(name.to_string(), SparseVector::new(vec![1, 4], vec![0.5, 1.0]));
+        Point::new_sparse(PointId::Integer(id), sparse, None)
+    };
+
+    // Without declarations any name is accepted
+    collection.upsert(sparse_point(1, "anything")).unwrap();
+
+    let text: SparseVectorConfig = serde_json::from_value(serde_json::json!({
+        "index": {"full_scan_threshold": 5000, "on_disk": false},
+        "modifier": "idf"
+    })).unwrap();
+    assert_eq!(text.index.as_ref().unwrap().full_scan_threshold, Some(5000));
+    let title: SparseVectorConfig = serde_json::from_value(serde_json::json!({"modifier": null})).unwrap();
+    assert_eq!(title.modifier, SparseModifier::None);
+    assert!(serde_json::from_value::<SparseVectorConfig>(serde_json::json!({"modifer": "idf"})).is_err());
+    collection.set_sparse_vector("text", text.clone());
+    collection.set_sparse_vector("title", title.clone());
+    storage.save_collection_config(&collection).unwrap();
+
+    collection.upsert(sparse_point(2, "text")).unwrap();
+    collection.upsert(sparse_point(3, "title")).unwrap();
+    let err = collection.upsert(sparse_point(4, "body")).unwrap_err().to_string();
+    assert!(err.contains("'body' is not declared"), "{}", err);
+    assert!(err.contains("text, title"), "{}", err);
+    let err = collection.validate_points(&[sparse_point(5, "text"), sparse_point(6, "body")], None).unwrap_err();
+    assert!(err.to_string().contains("index 1"));
+    assert_eq!(collection.sparse_modifier("text"), SparseModifier::Idf);
+
+    // Declarations survive a restart
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("declared").unwrap();
+    assert_eq!(collection.sparse_vector("text"), Some(text));
+    assert_eq!(collection.sparse_vector("title"), Some(title));
+    assert_eq!(collection.sparse_vector("body"), None);
+    assert!(collection.upsert(sparse_point(4, "body")).is_err());
+}
+
+#[test]
+fn test_legacy_sparse_modifiers() {
+    use vectx_core::{SparseModifier, SparseVector, SparseVectorConfig};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "legacy".to_string(),
+        vector_dim: 0,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    }).unwrap();
+    let sparse_point = |id: u64, name: &str| {
+        let mut sparse = HashMap::new();
+        sparse.insert(name.to_string(), SparseVector::new(vec![1, 4], vec![0.5, 1.0]));
+        Point::new_sparse(PointId::Integer(id), sparse, None)
+    };
+
+    // A modifier from an older config scores the vector but declares nothing
+    collection.set_sparse_modifier("text", SparseModifier::Idf);
+    assert_eq!(collection.sparse_modifier("text"), SparseModifier::Idf);
+    assert!(collection.sparse_vectors().is_empty());
+    collection.upsert(sparse_point(1, "body")).unwrap();
+    storage.save_collection_config(&collection).unwrap();
+
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("legacy").unwrap();
+    assert_eq!(collection.sparse_modifier("text"), SparseModifier::Idf);
+    assert!(collection.sparse_vectors().is_empty());
+    collection.upsert(sparse_point(2, "title")).unwrap();
+
+    // Declaring the vector takes its modifier over
+    collection.set_sparse_vector("text", SparseVectorConfig::default());
+    assert_eq!(collection.sparse_modifier("text"), SparseModifier::None);
+    assert!(collection.sparse_modifiers().is_empty());
+}
+
+#[test]
+fn test_batch_update_vectors() {
+    let config = CollectionConfig {
+        name: "refresh".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: true,
+        enable_bm25: false,
+    };
+    let collection = Collection::new(config);
+    for i in 0..20u64 {
+        let angle = i as f32 * 0.05;
+        let point = Point::new(PointId::Integer(i), Vector::new(vec![angle.cos(), angle.sin()]), None);
+        collection.upsert(point).unwrap();
+    }
+    collection.prewarm_index().unwrap();
+
+    // Point 19 moves onto the x axis; point 0 moves away from it
+    let updates = vec![
+        ("19".to_string(), Vector::new(vec![1.0, 0.0])),
+        ("0".to_string(), Vector::new(vec![0.0, 1.0])),
+        ("missing".to_string(), Vector::new(vec![1.0, 1.0])),
+    ];
+    let updated = collection.update_vectors(updates, false).unwrap();
+    assert_eq!(updated, [("19".to_string(), 1), ("0".to_string(), 1)]);
+
+    let results = collection.search(&Vector::new(vec![1.0, 0.0]), 1, None);
+    assert_eq!(results[0].0.id, PointId::Integer(19));
+    assert_eq!(collection.get("0").unwrap().vector.as_slice(), &[0.0, 1.0]);
+
+    // Wrong dimensions reject the whole batch
+    let updates = vec![
+        ("1".to_string(), Vector::new(vec![1.0, 0.0])),
+        ("2".to_string(), Vector::new(vec![1.0, 0.0, 0.0])),
+    ];
+    assert!(collection.update_vectors(updates, false).is_err());
+    assert_ne!(collection.get("1").unwrap().vector.as_slice(), &[1.0, 0.0]);
+}
+
+#[test]
+fn test_thread_bgsave() {
+    use vectx_storage::{ForkBasedPersistence, SaveStrategy};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    storage.set_save_strategy(SaveStrategy::Thread);
+    let config = CollectionConfig {
+        name: "saved".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = storage.create_collection(config).unwrap();
+    for i in 0..20 {
+        let point = Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 1.0]), Some(serde_json::json!({"n": i})));
+        collection.upsert(point).unwrap();
+    }
+
+    assert!(storage.bgsave().unwrap());
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while storage.is_bgsave_in_progress() {
+        assert!(std::time::Instant::now() < deadline, "background save did not finish");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    // The dump is complete, version marker included, and loads back
+    let snapshot = ForkBasedPersistence::new(temp_dir.path()).load_snapshot().unwrap().unwrap();
+    assert_eq!(snapshot.collections.len(), 1);
+    assert_eq!(snapshot.collections[0].points.len(), 20);
+    assert!(!temp_dir.path().join("dump.tmp").exists());
+    assert!("fork".parse::<SaveStrategy>().is_ok());
+    assert!("spawn".parse::<SaveStrategy>().is_err());
+}
+
+#[test]
+fn test_mutations_bump_version() {
+    let collection = Collection::new(CollectionConfig {
+        name: "versions".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    collection.upsert(Point::new(PointId::Integer(1), Vector::new(vec![1.0, 0.0]), None)).unwrap();
+    assert_eq!(collection.get("1").unwrap().version, 0);
+
+    collection.set_payload("1", serde_json::json!({"a": 1})).unwrap();
+    collection.overwrite_payload("1", serde_json::json!({"b": 2, "c": 3})).unwrap();
+    collection.delete_payload_keys("1", &["b".to_string()]).unwrap();
+    assert_eq!(collection.get("1").unwrap().version, 3);
+    assert_eq!(collection.clear_payload("1").unwrap(), Some(4));
+    assert_eq!(collection.update_vector("1", Vector::new(vec![2.0, 0.0])).unwrap(), Some(5));
+    assert_eq!(collection.update_multivector("1", None).unwrap(), Some(6));
+    assert_eq!(collection.set_payload("missing", serde_json::json!({})).unwrap(), None);
+
+    let hits = collection.search(&Vector::new(vec![1.0, 0.0]), 1, None);
+    assert_eq!(hits[0].0.version, 6);
+}
+
+#[test]
+fn test_index_consistency() {
+    // No dimension is configured, so the first point sets it
+    let collection = Collection::new(CollectionConfig {
+        name: "drift".to_string(),
+        vector_dim: 0,
+        distance: Distance::Dot,
+        use_hnsw: true,
+        enable_bm25: true,
+    });
+    collection.set_infer_vector_dim(true);
+    collection.set_check_invariants(true);
+    let doc = |id: u64, x: f32, text: &str| {
+        Point::new(PointId::Integer(id), Vector::new(vec![x, 1.0]), Some(serde_json::json!({"text": text})))
+    };
+    collection.upsert(doc(1, 1.0, "apple pie")).unwrap();
+    collection.upsert(doc(2, 2.0, "banana bread")).unwrap();
+    collection.prewarm_index().unwrap();
+
+    // A point that can't join the graph fails the whole batch before
+    // anything is stored or indexed
+    let err = collection.apply_atomic(vec![
+        BatchOperation::Upsert(vec![doc(3, 3.0, "cherry tart")]),
+        BatchOperation::Upsert(vec![Point::new(PointId::Integer(4), Vector::new(vec![1.0, 2.0, 3.0]), None)]),
+    ]);
+    assert!(err.is_err());
+    assert!(collection.get("3").is_none());
+    assert!(collection.search_text("cherry", 10).is_empty());
+    assert!(collection.upsert(Point::new(PointId::Integer(4), Vector::new(vec![1.0]), None)).is_err());
+    assert_eq!(collection.count(), 2);
+
+    // Text removed by an upsert or changed by a payload update leaves BM25
+    collection.upsert(Point::new(PointId::Integer(1), Vector::new(vec![1.0, 1.0]), None)).unwrap();
+    assert!(collection.search_text("apple", 10).is_empty());
+    collection.set_payload("2", serde_json::json!({"text": "cherry cake"})).unwrap();
+    assert!(collection.search_text("banana", 10).is_empty());
+    assert_eq!(collection.search_text("cherry", 10)[0].0, "2");
+    collection.apply_atomic(vec![BatchOperation::ClearPayload(vec!["2".to_string()])]).unwrap();
+    assert!(collection.search_text("cherry", 10).is_empty());
+
+    // Points written in a batch reach BM25 at once and the built graph when it ends
+    collection.batch_upsert(vec![doc(5, 5.0, "date loaf"), doc(5, 6.0, "fig roll"), doc(6, 7.0, "grape jam")]).unwrap();
+    assert_eq!(collection.search_text("fig", 10)[0].0, "5");
+    assert!(collection.search_text("date", 10).is_empty());
+    assert!(collection.check_index_consistency().is_empty());
+}
+
+#[test]
+fn test_stats_history() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "trends".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    }).unwrap();
+    storage.sample_stats();
+    for i in 0..10 {
+        collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 1.0]), None)).unwrap();
+    }
+    for ms in [2, 4, 40] {
+        collection.record_query(std::time::Duration::from_millis(ms));
+    }
+    storage.sample_stats();
+
+    let samples = collection.stats_history(None);
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[0].points_count, 0);
+    assert_eq!(samples[0].p99_ms, None);
+    assert_eq!(samples[1].points_count, 10);
+    assert!(samples[1].memory_bytes > samples[0].memory_bytes);
+    assert!((samples[1].p99_ms.unwrap() - 40.0).abs() < 1.0);
+    assert!(samples[1].qps > 0.0);
+
+    // The history is saved with each sample and read back on load
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("trends").unwrap();
+    assert_eq!(collection.stats_history(None), samples);
+}
+
+#[test]
+fn test_atomic_batch() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "docs".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: true,
+        enable_bm25: false,
+    }).unwrap();
+    let chunk = |id: u64, x: f32| Point::new(PointId::Integer(id), Vector::new(vec![x, 1.0]), Some(serde_json::json!({"parent": 1})));
+    collection.batch_upsert(vec![chunk(10, 1.0), chunk(11, 2.0)]).unwrap();
+    let ops_before = collection.journal().since(None, 100).len();
+
+    // A bad point anywhere in the batch leaves the collection untouched
+    let bad = collection.apply_atomic(vec![
+        BatchOperation::Delete(vec!["10".to_string()]),
+        BatchOperation::Upsert(vec![Point::new(PointId::Integer(12), Vector::new(vec![1.0]), None)]),
+    ]);
+    assert!(bad.is_err());
+    assert!(collection.get("10").is_some());
+    assert_eq!(collection.journal().since(None, 100).len(), ops_before);
+
+    // Replace the chunks of a document together with its parent record
+    let changed = collection.apply_atomic(vec![
+        BatchOperation::Upsert(vec![
+            Point::new(PointId::Integer(1), Vector::new(vec![0.0, 1.0]), Some(serde_json::json!({"kind": "parent"}))),
+            chunk(12, 3.0),
+        ]),
+        BatchOperation::Delete(vec!["10".to_string(), "missing".to_string()]),
+        BatchOperation::SetPayload { ids: vec!["11".to_string(), "12".to_string()], payload: serde_json::json!({"rev": 2}) },
+    ]).unwrap();
+    assert_eq!(changed, vec!["1", "12", "10", "11"]);
+    assert!(collection.get("10").is_none());
+    assert_eq!(collection.get("11").unwrap().version, 1);
+    // Later operations see earlier ones: 12 was upserted then updated
+    assert_eq!(collection.get("12").unwrap().payload, Some(serde_json::json!({"parent": 1, "rev": 2})));
+    assert_eq!(collection.count(), 3);
+    assert_eq!(collection.search(&Vector::new(vec![1.0, 0.0]), 1, None)[0].0.id, PointId::Integer(12));
+
+    let entries = collection.journal().since(None, 100);
+    assert_eq!(entries.len(), ops_before + 1);
+    let last = entries.last().unwrap();
+    assert_eq!(last.op_type, OperationType::Transaction);
+    assert_eq!(last.point_ids, changed);
+
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let restored = storage.get_collection("docs").unwrap();
+    assert_eq!(restored.count(), 3);
+    assert!(restored.get("10").is_none());
+    assert_eq!(restored.get("11").unwrap().payload, Some(serde_json::json!({"parent": 1, "rev": 2})));
+}
+
+#[test]
+fn test_hybrid_search() {
+    use vectx_core::{PayloadFilter, FilterCondition};
+    let collection = Collection::new(CollectionConfig {
+        name: "hybrid".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: true,
+    });
+    let docs = [
+        (1, [1.0, 0.0], "red apple"),
+        (2, [0.9, 0.1], "green pear"),
+        (3, [0.0, 1.0], "apple apple pie"),
+        (4, [0.0, 0.5], "banana bread"),
+        (5, [0.1, 0.0], "orange juice"),
+    ];
+    for (id, v, text) in docs {
+        let point = Point::new(PointId::Integer(id), Vector::new(v.to_vec()), Some(serde_json::json!({"text": text})));
+        collection.upsert(point).unwrap();
+    }
+    let query = Vector::new(vec![1.0, 0.0]);
+    let top = |alpha| collection.search_hybrid("apple", &query, alpha, 1, None).unwrap()[0].0.id.clone();
+
+    assert_eq!(top(1.0), PointId::Integer(1));
+    assert_eq!(top(0.0), PointId::Integer(3));
+    // Both sides contribute; points found by only one side are kept
+    let both = collection.search_hybrid("apple", &query, 0.6, 5, None).unwrap();
+    let ids: Vec<PointId> = both.iter().map(|(p, _)| p.id.clone()).collect();
+    assert_eq!(ids[..3], [PointId::Integer(1), PointId::Integer(2), PointId::Integer(3)]);
+    assert_eq!(both.len(), 5);
+
+    let filter = PayloadFilter::new(FilterCondition::Equals {
+        field: "text".to_string(),
+        value: serde_json::json!("apple apple pie"),
+    });
+    let filtered = collection.search_hybrid("apple", &query, 0.5, 3, Some(&filter)).unwrap();
+    assert_eq!(filtered.len(), 1);
+    assert!(collection.search_hybrid("apple", &Vector::new(vec![1.0]), 0.5, 3, None).is_err());
+    assert!(collection.search_hybrid("apple", &query, 1.5, 3, None).is_err());
+}
+
+#[test]
+fn test_text_dictionaries() {
+    use vectx_core::{Filter, FilterCondition, PayloadFilter};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "shop".to_string(),
+        vector_dim: 1,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: true,
+    }).unwrap();
+    let texts = ["the best television deals", "the cheapest laptop", "the radio show"];
+    for (i, text) in texts.iter().enumerate() {
+        let point = Point::new(PointId::Integer(i as u64), Vector::new(vec![1.0]), Some(serde_json::json!({"text": text})));
+        collection.upsert(point).unwrap();
+    }
+    assert!(collection.search_text("tv", 10).is_empty());
+
+    // New dictionaries apply to the next query, without reindexing
+    collection.set_text_dictionaries(TextDictionaries::new(
+        vec![vec!["tv".to_string(), "television".to_string()]],
+        vec!["the".to_string()],
+    ));
+    storage.save_collection_config(&collection).unwrap();
+    let hits = collection.search_text("the tv", 10);
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0, "0");
+
+    let filter = PayloadFilter::new(FilterCondition::Text { field: "text".to_string(), text: "the tv".to_string() })
+        .with_dictionaries(collection.text_dictionaries());
+    let matching: Vec<Point> = collection.get_all_points().into_iter().filter(|p| filter.matches(p)).collect();
+    assert_eq!(matching.len(), 1);
+
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let restored = storage.get_collection("shop").unwrap();
+    assert!(restored.text_dictionaries().stopwords.contains("the"));
+    assert_eq!(restored.search_text("tv", 10).len(), 1);
+
+    // Highlights come from the token offsets kept in the BM25 index
+    let options = vectx_core::HighlightOptions::default();
+    let highlight = restored.highlight("0", "the tv", &options).unwrap();
+    assert_eq!(highlight.offsets, vec![(9, 19)]);
+    assert_eq!(highlight.fragments, vec!["the best <em>television</em> deals"]);
+    assert!(restored.highlight("2", "tv", &options).is_none());
+}
+
+#[test]
+fn test_language_detection() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "catalog".to_string(),
+        vector_dim: 1,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: true,
+    }).unwrap();
+    collection.set_detect_language(true);
+    storage.save_collection_config(&collection).unwrap();
+
+    let texts = [
+        serde_json::json!({"text": "Comfortable running shoes for long distance runners on the road"}),
+        serde_json::json!({"text": "Bequeme Laufschuhe für lange Strecken auf der Straße und im Wald"}),
+        serde_json::json!({"text": "running", "language": "eng"}),
+    ];
+    for (i, payload) in texts.into_iter().enumerate() {
+        collection.upsert(Point::new(PointId::Integer(i as u64), Vector::new(vec![1.0]), Some(payload))).unwrap();
+    }
+    let language = |id: &str| collection.get(id).unwrap().payload.unwrap()["language"].clone();
+    assert_eq!(language("0"), "eng");
+    assert_eq!(language("1"), "deu");
+
+    // Stemming: "runs" matches "running"
+    let hits = collection.search_text("runs", 10);
+    let mut ids: Vec<&str> = hits.iter().map(|(id, _)| id.as_str()).collect();
+    ids.sort();
+    assert_eq!(ids, vec!["0", "2"]);
+    let highlight = collection.highlight("0", "runs", &vectx_core::HighlightOptions::default()).unwrap();
+    assert_eq!(highlight.fragments, vec!["Comfortable <em>running</em> shoes for long distance runners on the road"]);
+
+    // Language-filtered queries only see documents in that language
+    assert!(collection.search_text_in("Straße", Some("eng"), 10).is_empty());
+    assert_eq!(collection.search_text_in("Straße", Some("deu"), 10)[0].0, "1");
+
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let restored = storage.get_collection("catalog").unwrap();
+    assert!(restored.detect_language());
+    assert_eq!(restored.search_text_in("runs", Some("eng"), 10).len(), 2);
+}
+
+#[test]
+fn test_cancellable_search() {
+    use vectx_core::{CancellationToken, MultiVector};
+
+    let collection = Collection::new(CollectionConfig {
+        name: "scan".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    for i in 0..3000u64 {
+        collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 1.0]), None)).unwrap();
+    }
+    let query = Vector::new(vec![1.0, 0.0]);
+
+    let token = CancellationToken::new();
+    let results = collection.search_cancellable(&query, 5, None, &token);
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[0].0.id.to_string(), "2999");
+    assert!(!token.interrupted());
+
+    // A cancelled token stops the scan before the first point
+    token.cancel();
+    assert!(collection.search_cancellable(&query, 5, None, &token).is_empty());
+    assert!(token.interrupted());
+    assert!(!token.timed_out());
+
+    let expired = CancellationToken::with_timeout(std::time::Duration::ZERO);
+    let multivector = MultiVector::new(vec![vec![1.0, 0.0], vec![0.0, 1.0]]).unwrap();
+    assert!(collection.search_multivector_cancellable(&multivector, 5, None, &expired).is_empty());
+    assert!(expired.interrupted() && expired.timed_out());
+    assert_eq!(collection.search_multivector(&multivector, 5, None).len(), 5);
+}
+
+#[test]
+fn test_fuzzy_filter() {
+    use vectx_core::{Filter, FilterCondition, PayloadFilter, PayloadIndexType};
+
+    let collection = Collection::new(CollectionConfig {
+        name: "deli".to_string(),
+        vector_dim: 1,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    for (i, name) in ["prosciutto", "prosecco", "pancetta"].iter().enumerate() {
+        let point = Point::new(PointId::Integer(i as u64), Vector::new(vec![1.0]), Some(serde_json::json!({"name": name})));
+        collection.upsert(point).unwrap();
+    }
+    let fuzzy = FilterCondition::Fuzzy { field: "name".to_string(), value: "prosciuto".to_string(), max_edits: 1 };
+    let matching_ids = |condition: FilterCondition| -> Vec<String> {
+        let filter = PayloadFilter::new(collection.resolve_fuzzy(condition));
+        let mut ids: Vec<String> = collection.get_all_points().into_iter()
+            .filter(|p| filter.matches(p))
+            .map(|p| p.id.to_string())
+            .collect();
+        ids.sort();
+        ids
+    };
+
+    // Without an index the edit distance is checked point by point
+    assert!(collection.fuzzy_matches("name", "prosciuto", 1).is_none());
+    assert_eq!(matching_ids(fuzzy.clone()), vec!["0"]);
+
+    collection.create_payload_index("name", PayloadIndexType::Keyword).unwrap();
+    assert_eq!(collection.fuzzy_matches("name", "prosciuto", 1).unwrap(), vec!["prosciutto"]);
+    assert_eq!(matching_ids(fuzzy.clone()), vec!["0"]);
+
+    // Writes are picked up by the next fuzzy match
+    collection.upsert(Point::new(PointId::Integer(3), Vector::new(vec![1.0]), Some(serde_json::json!({"name": "prosciutti"})))).unwrap();
+    collection.delete("0").unwrap();
+    assert_eq!(collection.fuzzy_matches("name", "prosciuto", 1).unwrap(), Vec::<String>::new());
+    assert_eq!(matching_ids(fuzzy.clone()), Vec::<String>::new());
+    assert_eq!(collection.fuzzy_matches("name", "prosciuto", 2).unwrap(), vec!["prosciutti"]);
+    assert_eq!(matching_ids(FilterCondition::Not(Box::new(fuzzy))), vec!["1", "2", "3"]);
+}
+
+#[test]
+fn test_keyword_index_params() {
+    use vectx_core::{Filter, FilterCondition, KeywordParams, PayloadFilter};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "deli".to_string(),
+        vector_dim: 1,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    }).unwrap();
+    for (i, name) in ["Salumi", "caf\u{e9}", "SALAMI", "pancetta"].iter().enumerate() {
+        let point = Point::new(PointId::Integer(i as u64), Vector::new(vec![1.0]), Some(serde_json::json!({"name": name})));
+        collection.upsert(point).unwrap();
+    }
+    let matching_ids = |collection: &Collection, condition: FilterCondition| -> Vec<String> {
+        let filter = PayloadFilter::new(collection.resolve_fuzzy(condition))
+            .with_keyword_params(collection.keyword_params());
+        let mut ids: Vec<String> = collection.get_all_points().into_iter()
+            .filter(|p| filter.matches(p))
+            .map(|p| p.id.to_string())
+            .collect();
+        ids.sort();
+        ids
+    };
+    let equals = |value: &str| FilterCondition::Equals { field: "name".to_string(), value: serde_json::json!(value) };
+
+    // Plain keyword indexes compare bytes
+    collection.create_payload_index("name", PayloadIndexType::Keyword).unwrap();
+    assert_eq!(matching_ids(&collection, equals("salumi")), Vec::<String>::new());
+    assert_eq!(matching_ids(&collection, equals("cafe\u{301}")), Vec::<String>::new());
+
+    let params = KeywordParams { lowercase: true, normalize: true };
+    collection.create_keyword_index("name", params).unwrap();
+    storage.save_collection_config(&collection).unwrap();
+    assert_eq!(matching_ids(&collection, equals("salumi")), vec!["0"]);
+    assert_eq!(matching_ids(&collection, equals("CAFE\u{301}")), vec!["1"]);
+    assert_eq!(matching_ids(&collection, FilterCondition::NotEquals { field: "name".to_string(), value: serde_json::json!("salumi") }), vec!["1", "2", "3"]);
+
+    // Fuzzy matches go through the folded trigram index
+    let fuzzy = FilterCondition::Fuzzy { field: "name".to_string(), value: "Salam".to_string(), max_edits: 1 };
+    assert_eq!(collection.fuzzy_matches("name", "Salam", 1).unwrap(), vec!["salami"]);
+    assert_eq!(matching_ids(&collection, fuzzy), vec!["2"]);
+
+    // The options are kept across restarts
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("deli").unwrap();
+    assert_eq!(collection.keyword_params().get("name"), Some(&params));
+    assert_eq!(matching_ids(&collection, equals("SALUMI")), vec!["0"]);
+
+    // Recreating the index without options makes it exact again
+    collection.create_payload_index("name", PayloadIndexType::Keyword).unwrap();
+    assert!(collection.keyword_params().is_empty());
+    assert_eq!(matching_ids(&collection, equals("SALUMI")), Vec::<String>::new());
+}
+
+#[test]
+fn test_search_defaults_persist() {
+    use vectx_core::SearchDefaults;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "tuned".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: true,
+        enable_bm25: false,
+    }).unwrap();
+    let defaults = SearchDefaults {
+        limit: Some(3),
+        max_limit: Some(50),
+        ef: Some(128),
+        score_threshold: Some(0.5),
+        with_payload: None,
+        payload_fields: Some(vec!["title".to_string()]),
+        max_query_tokens: Some(16),
+        recency: Some(vectx_core::RecencyBoost { field: "published".to_string(), half_life_secs: 86400.0, weight: 0.2 }),
+    };
+    defaults.validate().unwrap();
+    collection.set_search_defaults(defaults.clone());
+    storage.save_collection_config(&collection).unwrap();
+
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let restored = storage.get_collection("tuned").unwrap();
+    assert_eq!(restored.search_defaults(), defaults);
+    assert_eq!(restored.search_defaults().limit(None, 10), 3);
+    assert_eq!(restored.search_defaults().limit(Some(500), 10), 50);
+}
+
+#[test]
+fn test_query_templates_persist() {
+    use vectx_core::QueryTemplate;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "docs".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: true,
+        enable_bm25: false,
+    }).unwrap();
+    let template: QueryTemplate = serde_json::from_value(serde_json::json!({
+        "query": {"query": "$vector", "limit": "$limit", "filter": {"must": [{"key": "lang", "match": {"value": "$lang"}}]}},
+        "defaults": {"limit": 5},
+        "description": "Documents in one language"
+    })).unwrap();
+    template.validate().unwrap();
+    collection.set_query_template("by-lang", template.clone());
+    collection.set_query_template("dropped", template.clone());
+    assert!(collection.remove_query_template("dropped"));
+    assert!(!collection.remove_query_template("dropped"));
+    storage.save_collection_config(&collection).unwrap();
+
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let restored = storage.get_collection("docs").unwrap();
+    assert_eq!(restored.query_templates().keys().collect::<Vec<_>>(), vec!["by-lang"]);
+    let template = restored.query_template("by-lang").unwrap();
+    let variables = serde_json::json!({"vector": [1.0, 0.0], "lang": "it"});
+    let rendered = template.render(variables.as_object().unwrap()).unwrap();
+    assert_eq!(rendered, serde_json::json!({
+        "query": [1.0, 0.0], "limit": 5, "filter": {"must": [{"key": "lang", "match": {"value": "it"}}]}
+    }));
+}
+
+#[test]
+fn test_ef_tuning() {
+    use vectx_core::EfTuning;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "tuned".to_string(),
+        vector_dim: 8,
+        distance: Distance::Cosine,
+        use_hnsw: true,
+        enable_bm25: false,
+    }).unwrap();
+    let point = |i: u64| {
+        let vector = (0..8).map(|d| ((i * 31 + d * 17) as f32 * 0.37).sin()).collect();
+        Point::new(PointId::Integer(i), Vector::new(vector), None)
+    };
+    collection.batch_upsert((0..1000).map(point).collect()).unwrap();
+    assert!(!collection.ef_tuning_due());
+    assert!(collection.tune_ef().is_err());
+
+    let tuning: EfTuning = serde_json::from_value(serde_json::json!({"target_recall": 0.9, "sample_size": 50})).unwrap();
+    collection.set_ef_tuning(Some(tuning.clone()));
+    assert!(collection.ef_tuning_due());
+    let report = collection.tune_ef().unwrap();
+    // The smallest ef reaching the target, or the largest tried
+    assert!(report.ef >= 10 && report.ef <= 1024);
+    assert_eq!(report.met_target, report.recall >= 0.9);
+    assert!(report.met_target || report.ef == 1024);
+    assert_eq!((report.queries, report.points), (50, 1000));
+    assert_eq!(collection.search_defaults().ef, Some(report.ef));
+    storage.save_collection_config(&collection).unwrap();
+
+    drop(collection);
+    drop(storage);
+    let storage = std::sync::Arc::new(StorageManager::new(temp_dir.path()).unwrap());
+    let restored = storage.get_collection("tuned").unwrap();
+    assert_eq!(restored.ef_tuning(), Some(tuning));
+    assert_eq!(restored.ef_tuning_report(), Some(report.clone()));
+    assert_eq!(restored.search_defaults().ef, Some(report.ef));
+    assert!(!restored.ef_tuning_due());
+
+    // Growing by a fifth makes it due again
+    restored.batch_upsert((1000..1200).map(point).collect()).unwrap();
+    assert!(restored.ef_tuning_due());
+    storage.tune_due_collections();
+    assert_eq!(restored.ef_tuning_report().unwrap().points, 1200);
+    assert!(!restored.ef_tuning_due());
+
+    // Stopping keeps the tuned ef
+    restored.set_ef_tuning(None);
+    assert!(restored.ef_tuning_report().is_none());
+    assert!(restored.search_defaults().ef.is_some());
+}
+
+#[test]
+fn test_recency_boost() {
+    use vectx_api::core_requests::{self, RecommendRequest};
+    use vectx_core::{RecencyBoost, RecencyOverride, SearchDefaults};
+
+    let collection = Collection::new(CollectionConfig {
+        name: "news".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    let now = vectx_core::recency::now_secs();
+    let points = [
+        ("0", [1.0, 0.0], serde_json::json!({})),
+        ("1", [1.0, 0.1], serde_json::json!({"published": "2001-09-09T01:46:40Z"})),
+        ("2", [1.0, 0.3], serde_json::json!({"published": now})),
+        ("3", [1.0, 0.2], serde_json::json!({"published": "not a date"})),
+    ];
+    for (id, vector, payload) in points {
+        collection.upsert(Point::new(PointId::String(id.to_string()), Vector::new(vector.to_vec()), Some(payload))).unwrap();
+    }
+    let ids = |request: &RecommendRequest| -> Vec<String> {
+        core_requests::recommend(&collection, request, None).unwrap()
+            .iter()
+            .map(|(p, _)| p.id.to_string())
+            .collect()
+    };
+    let request = RecommendRequest { positive: vec!["0".to_string()], limit: Some(2), ..Default::default() };
+    assert_eq!(ids(&request), vec!["1", "3"]);
+
+    // The collection's boost lifts the fresh point, which a request can turn off
+    collection.set_search_defaults(SearchDefaults {
+        recency: Some(RecencyBoost { field: "published".to_string(), half_life_secs: 86400.0, weight: 0.5 }),
+        ..Default::default()
+    });
+    assert_eq!(ids(&request), vec!["2", "1"]);
+    let disabled = RecommendRequest { recency: Some(RecencyOverride::Enabled(false)), ..request.clone() };
+    assert_eq!(ids(&disabled), vec!["1", "3"]);
+
+    // A request's own boost replaces the collection's
+    let stale = RecencyBoost { field: "missing".to_string(), half_life_secs: 60.0, weight: 1.0 };
+    let replaced = RecommendRequest { recency: Some(RecencyOverride::Boost(stale.clone())), ..request.clone() };
+    assert_eq!(ids(&replaced), vec!["1", "3"]);
+    let invalid = RecommendRequest { recency: Some(RecencyOverride::Boost(RecencyBoost { half_life_secs: 0.0, ..stale })), ..request };
+    assert!(core_requests::recommend(&collection, &invalid, None).is_err());
+}
+
+#[test]
+fn test_hnsw_params_persist() {
+    use vectx_core::{HnswParams, HnswParamsDiff};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "graph".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: true,
+        enable_bm25: false,
+    }).unwrap();
+    assert_eq!(collection.hnsw_params(), HnswParams::default());
+
+    let diff = HnswParamsDiff { m: Some(8), ef_construct: None, full_scan_threshold: Some(0) };
+    let params = collection.hnsw_params().apply(&diff);
+    assert_eq!(params.ef_construct, HnswParams::default().ef_construct);
+    assert!(collection.set_hnsw_params(params).unwrap());
+    assert!(!collection.set_hnsw_params(params).unwrap());
+    storage.save_collection_config(&collection).unwrap();
+    assert!(HnswParams { m: 1, ..params }.validate().is_err());
+    assert!(HnswParams { m: HnswParams::MAX_M, ef_construct: HnswParams::MAX_EF_CONSTRUCT, ..params }.validate().is_ok());
+    assert!(HnswParams { m: HnswParams::MAX_M + 1, ..params }.validate().is_err());
+    assert!(HnswParams { ef_construct: HnswParams::MAX_EF_CONSTRUCT + 1, ..params }.validate().is_err());
+    assert!(HnswParams { m: usize::MAX, ..params }.validate().is_err());
+
+    // With no full scan threshold the graph answers every search
+    for i in 0..20 {
+        let angle = i as f32 * 0.1;
+        collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![angle.cos(), angle.sin()]), None)).unwrap();
+    }
+    let results = collection.search(&Vector::new(vec![1.0, 0.0]), 1, None);
+    assert_eq!(results[0].0.id, PointId::Integer(0));
+
+    storage.save().unwrap();
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let restored = storage.get_collection("graph").unwrap();
+    assert_eq!(restored.hnsw_params(), params);
+}
+
+#[test]
+fn test_multivector_query_pruning() {
+    use vectx_core::{MultiVector, SearchDefaults};
+
+    let collection = Collection::new(CollectionConfig {
+        name: "colbert".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    for (id, token) in [(1, vec![1.0, 0.0]), (2, vec![0.0, 1.0])] {
+        let mv = MultiVector::new(vec![token]).unwrap();
+        collection.upsert(Point::new_multi(PointId::Integer(id), mv, None)).unwrap();
+    }
+    let query = MultiVector::new(vec![vec![10.0, 0.0], vec![0.0, 0.1]]).unwrap();
+    let score_of = |results: &[(Point, f32)], id: u64| results.iter().find(|(p, _)| p.id == PointId::Integer(id)).unwrap().1;
+
+    let results = collection.search_multivector(&query, 2, None);
+    assert!((score_of(&results, 1) - 10.0).abs() < 1e-6);
+    assert!((score_of(&results, 2) - 0.1).abs() < 1e-6);
+
+    // Only the larger query token is kept
+    collection.set_search_defaults(SearchDefaults { max_query_tokens: Some(1), ..Default::default() });
+    let results = collection.search_multivector(&query, 2, None);
+    assert!((score_of(&results, 1) - 10.0).abs() < 1e-6);
+    assert_eq!(score_of(&results, 2), 0.0);
+}
+
+#[test]
+fn test_named_multivectors() {
+    use vectx_core::{MultiVector, MultiVectorConfig, MultiVectorParams};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "pages".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    }).unwrap();
+    let params = |size, distance| MultiVectorParams { size, distance, multivector_config: MultiVectorConfig::default() };
+    collection.set_named_multivector("colbert", params(2, Distance::Dot));
+    collection.set_named_multivector("colpali", params(3, Distance::Cosine));
+    storage.save_collection_config(&collection).unwrap();
+
+    let mut point = Point::new(PointId::Integer(1), Vector::new(vec![1.0, 0.0]), None);
+    point.add_named_multivector("colbert".to_string(), MultiVector::new(vec![vec![2.0, 0.0], vec![0.0, 1.0]]).unwrap());
+    point.add_named_multivector("colpali".to_string(), MultiVector::new(vec![vec![0.0, 0.0, 3.0]]).unwrap());
+    collection.upsert(point).unwrap();
+    collection.upsert(Point::new(PointId::Integer(2), Vector::new(vec![0.0, 1.0]), None)).unwrap();
+
+    // Undeclared names and wrong dimensions are rejected
+    let mut bad = Point::new(PointId::Integer(3), Vector::new(vec![1.0, 1.0]), None);
+    bad.add_named_multivector("other".to_string(), MultiVector::new(vec![vec![1.0, 0.0]]).unwrap());
+    assert!(collection.upsert(bad).is_err());
+    let mut bad = Point::new(PointId::Integer(3), Vector::new(vec![1.0, 1.0]), None);
+    bad.add_named_multivector("colpali".to_string(), MultiVector::new(vec![vec![1.0, 0.0]]).unwrap());
+    assert!(collection.upsert(bad).is_err());
+    // Also as requests give them, rather than falling back to the unnamed multivector
+    let mut input = vectx_api::core_requests::PointInput::new(PointId::Integer(3), vec![1.0, 1.0], None);
+    input.named_multivectors.push(("other".to_string(), vec![vec![1.0, 0.0]]));
+    assert!(input.into_point(&collection).unwrap_err().contains("'other' is not declared"));
+    let mut input = vectx_api::core_requests::PointInput::new(PointId::Integer(3), vec![1.0, 1.0], None);
+    input.multivector = Some(vec![vec![1.0, 0.0], vec![1.0]]);
+    assert!(input.into_point(&collection).is_err());
+
+    // Each name is scored with its own distance; points without it are skipped
+    let query = MultiVector::new(vec![vec![1.0, 0.0]]).unwrap();
+    let results = collection.search_named_multivector("colbert", &query, 10, None).unwrap();
+    assert_eq!(results.len(), 1);
+    assert!((results[0].1 - 2.0).abs() < 1e-6);
+    let query = MultiVector::new(vec![vec![0.0, 0.0, 5.0]]).unwrap();
+    let results = collection.search_named_multivector("colpali", &query, 10, None).unwrap();
+    assert!((results[0].1 - 1.0).abs() < 1e-6);
+    assert!(collection.search_named_multivector("other", &query, 10, None).is_err());
+    assert!(collection.search_named_multivector("colbert", &query, 10, None).is_err());
+
+    let updated = MultiVector::new(vec![vec![0.0, 4.0]]).unwrap();
+    assert!(collection.update_named_multivector("2", "colbert", Some(updated)).unwrap().is_some());
+    let query = MultiVector::new(vec![vec![0.0, 1.0]]).unwrap();
+    let results = collection.search_named_multivector("colbert", &query, 10, None).unwrap();
+    assert_eq!(results[0].0.id, PointId::Integer(2));
+
+    // Declarations and data survive a restart
+    drop(collection);
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("pages").unwrap();
+    assert_eq!(collection.named_multivector("colpali").unwrap().size, 3);
+    let point = collection.get("1").unwrap();
+    assert_eq!(point.get_named_multivector("colbert").unwrap().vectors()[0], vec![2.0, 0.0]);
+    assert_eq!(point.get_named_multivector("colpali").unwrap().vectors()[0], vec![0.0, 0.0, 3.0]);
+
+    // And a snapshot round trip
+    let snapshot = storage.create_collection_snapshot("pages").unwrap();
+    storage.delete_collection("pages").unwrap();
+    let restored = storage.recover_from_snapshot("pages", &snapshot.name, &RecoverOptions::default()).unwrap();
+    assert_eq!(restored.named_multivector("colbert").unwrap().distance, Distance::Dot);
+    assert_eq!(restored.get("2").unwrap().get_named_multivector("colbert").unwrap().vectors()[0], vec![0.0, 4.0]);
+}
+
+#[test]
+fn test_distance_change_reindexes() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    {
+        let storage = StorageManager::new(temp_dir.path()).unwrap();
+        let collection = storage.create_collection(CollectionConfig {
+            name: "metric".to_string(),
+            vector_dim: 2,
+            distance: Distance::Cosine,
+            use_hnsw: true,
+            enable_bm25: false,
+        }).unwrap();
+        for i in 0..50 {
+            collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 1.0]), None)).unwrap();
+        }
+
+        assert!(!collection.set_distance(Distance::Cosine).unwrap());
+        assert!(collection.set_distance(Distance::Euclidean).unwrap());
+        storage.save_collection_config(&collection).unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while collection.reindex_progress().is_some() {
+            assert!(std::time::Instant::now() < deadline, "reindex did not finish");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let stats = collection.stats();
+        assert_eq!(stats.indexed_vectors_count, 50);
+        assert!(stats.hnsw_built_at.is_some());
+
+        // Euclidean scores are negated distances
+        let results = collection.search(&Vector::new(vec![3.0, 1.0]), 1, None);
+        assert!(results[0].1 <= 0.0);
+    }
+
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    assert_eq!(storage.get_collection("metric").unwrap().distance(), Distance::Euclidean);
+}
+
+#[test]
+fn test_graph_follows_distance() {
+    use vectx_core::HnswParams;
+
+    // Points along one direction: cosine cannot tell them apart
+    let points = || (1..=50u64).map(|k| Point::new(PointId::Integer(k), Vector::new(vec![k as f32, 0.0]), None));
+    let wait_for_reindex = |collection: &Collection| {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while collection.reindex_progress().is_some() {
+            assert!(std::time::Instant::now() < deadline, "reindex did not finish");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    };
+
+    for (distance, query, nearest) in [(Distance::Euclidean, [3.0, 0.0], 3), (Distance::Dot, [1.0, 0.0], 50)] {
+        let collection = Collection::new(CollectionConfig {
+            name: "graph_metric".to_string(),
+            vector_dim: 2,
+            distance,
+            use_hnsw: true,
+            enable_bm25: false,
+        });
+        assert!(collection.set_hnsw_params(HnswParams { full_scan_threshold: 0, ..HnswParams::default() }).unwrap());
+        for point in points() {
+            collection.upsert(point).unwrap();
+        }
+        let results = collection.search(&Vector::new(query.to_vec()), 1, None);
+        assert_eq!(results[0].0.id, PointId::Integer(nearest), "{:?}", distance);
+
+        // A graph reindexed from cosine links by the new distance
+        let collection = Collection::new(CollectionConfig {
+            name: "graph_metric".to_string(),
+            vector_dim: 2,
+            distance: Distance::Cosine,
+            use_hnsw: true,
+            enable_bm25: false,
+        });
+        assert!(collection.set_hnsw_params(HnswParams { full_scan_threshold: 0, ..HnswParams::default() }).unwrap());
+        collection.set_normalization(NormalizationPolicy::Never).unwrap();
+        for point in points() {
+            collection.upsert(point).unwrap();
+        }
+        collection.prewarm_index().unwrap();
+        assert!(collection.set_distance(distance).unwrap());
+        wait_for_reindex(&collection);
+        let results = collection.search(&Vector::new(query.to_vec()), 1, None);
+        assert_eq!(results[0].0.id, PointId::Integer(nearest), "{:?} after reindex", distance);
+    }
+}
+
+#[test]
+fn test_hnsw_recall_per_distance() {
+    use vectx_core::binary::pack_bytes;
+    use vectx_core::SearchDefaults;
+
+    let mut seed = 11u64;
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        seed >> 33
+    };
+    let limit = 10;
+    for distance in [Distance::Cosine, Distance::Euclidean, Distance::Dot, Distance::Hamming] {
+        let (dim, vector): (usize, Box<dyn FnMut() -> Vector>) = match distance {
+            Distance::Hamming => (128, Box::new(|| pack_bytes(&(0..16).map(|_| next() as u8).collect::<Vec<u8>>()))),
+            _ => (16, Box::new(|| Vector::new((0..16).map(|_| (next() % 2000) as f32 / 1000.0 - 1.0).collect()))),
+        };
+        let collection = Collection::new(CollectionConfig {
+            name: "recall".to_string(),
+            vector_dim: dim,
+            distance,
+            use_hnsw: true,
+            enable_bm25: false,
+        });
+        let mut vector = vector;
+        // Uniformly random codes are the hard case for a graph; the default
+        // ef of 16 is tuned for embeddings with structure
+        collection.set_search_defaults(SearchDefaults { ef: Some(64), ..SearchDefaults::default() });
+        collection.batch_upsert((0..2000u64).map(|i| Point::new(PointId::Integer(i), vector(), None)).collect()).unwrap();
+
+        // Points tied with the last exact result count as found
+        let (mut found, mut expected) = (0, 0);
+        for _ in 0..30 {
+            let report = collection.verify_consistency(&vector(), limit).unwrap();
+            let cutoff = report.exact.last().unwrap().score;
+            let hnsw = report.paths.iter().find(|p| p.path == SearchPath::Hnsw).unwrap();
+            found += hnsw.results.iter().filter(|r| r.score >= cutoff - 1e-5).count().min(limit);
+            expected += report.exact.len();
+        }
+        let recall = found as f32 / expected as f32;
+        assert!(recall >= 0.9, "{:?}: recall {}", distance, recall);
+    }
+}
+
+#[test]
+fn test_alias_resolution() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "products_v2".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    }).unwrap();
+    collection.upsert(Point::new(PointId::Integer(1), Vector::new(vec![1.0, 0.0]), None)).unwrap();
+
+    storage.create_alias("products", "products_v2").unwrap();
+    // An alias of an alias points at the collection
+    storage.create_alias("catalog", "products").unwrap();
+    assert_eq!(storage.resolve_collection_name("catalog"), "products_v2");
+    assert!(storage.create_alias("products_v2", "products").is_err());
+
+    assert!(storage.collection_exists("products"));
+    assert!(!storage.collection_exists("missing"));
+    let mut aliases = storage.list_collection_aliases("products");
+    aliases.sort();
+    assert_eq!(aliases, vec!["catalog", "products"]);
+
+    // Snapshots taken through an alias belong to the collection
+    let snapshot = storage.create_collection_snapshot("products").unwrap();
+    assert_eq!(storage.list_collection_snapshots("products_v2").unwrap().len(), 1);
+    assert!(storage.get_snapshot_path("catalog", &snapshot.name).is_some());
+
+    // Deleting through an alias is refused; deleting by name drops the aliases
+    assert!(storage.delete_collection("products").is_err());
+    assert!(storage.get_collection("products_v2").is_some());
+    assert!(storage.delete_collection("products_v2").unwrap());
+    assert!(storage.list_aliases().is_empty());
+    assert!(!storage.collection_exists("products"));
+}
+
+#[test]
+fn test_lmdb_point_store() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let config = CollectionConfig {
+        name: "durable".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: true,
+        enable_bm25: false,
+    };
+    let collection = storage.create_collection(config).unwrap();
+    for i in 0..5 {
+        let point = Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 1.0]), Some(serde_json::json!({"n": i})));
+        collection.upsert(point).unwrap();
+    }
+    collection.upsert(Point::new(PointId::Integer(1), Vector::new(vec![1.0, 2.0]), None)).unwrap();
+    collection.set_payload("2", serde_json::json!({"tag": "x"})).unwrap();
+    collection.update_vector("3", Vector::new(vec![9.0, 9.0])).unwrap();
+    collection.delete("4").unwrap();
+    storage.create_collection(CollectionConfig {
+        name: "dropped".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    }).unwrap();
+    storage.delete_collection("dropped").unwrap();
+
+    // A full snapshot is a copy of the LMDB environment
+    let full = storage.create_full_snapshot().unwrap();
+    assert!(full.size > 0);
+    assert_eq!(storage.list_full_snapshots().unwrap()[0].name, full.name);
+
+    // No explicit save: every write is already in LMDB
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    assert!(!storage.collection_exists("dropped"));
+    let restored = storage.get_collection("durable").unwrap();
+    assert_eq!(restored.count(), 4);
+    let one = restored.get("1").unwrap();
+    assert_eq!(one.version, 1);
+    assert_eq!(one.payload, None);
+    assert_eq!(restored.get("2").unwrap().payload, Some(serde_json::json!({"n": 2, "tag": "x"})));
+    assert_eq!(restored.get("3").unwrap().vector.as_slice(), &[9.0, 9.0]);
+    assert!(restored.get("4").is_none());
+    // Restored points get new writes persisted too, with versions continuing
+    restored.upsert(Point::new(PointId::Integer(1), Vector::new(vec![0.0, 1.0]), None)).unwrap();
+    assert_eq!(restored.get("1").unwrap().version, 2);
+    assert_eq!(restored.search(&Vector::new(vec![1.0, 1.0]), 1, None)[0].0.id, PointId::Integer(3));
+
+    assert!(storage.delete_full_snapshot(&full.name).unwrap());
+    assert!(storage.get_full_snapshot_path(&full.name).is_none());
+}
+
+#[test]
+fn test_lazy_collection_loading() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    for name in ["hot", "idle"] {
+        let collection = storage.create_collection(CollectionConfig {
+            name: name.to_string(),
+            vector_dim: 2,
+            distance: Distance::Euclidean,
+            use_hnsw: false,
+            enable_bm25: false,
+        }).unwrap();
+        for i in 0..3 {
+            collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 0.0]), None)).unwrap();
+        }
+    }
+    let dir = temp_dir.path().join("collections").join("idle");
+    assert!(dir.join("manifest.json").is_file());
+    assert!(dir.join("points").join("data.mdb").is_file());
+    storage.create_alias("cold", "idle").unwrap();
+
+    // Unloading drops the points from memory; the next access reads them back
+    assert!(storage.unload_collection("cold").unwrap());
+    assert!(!storage.is_loaded("idle"));
+    assert!(!storage.is_unloading("idle"));
+    assert_eq!(storage.loaded_collections().len(), 1);
+    assert!(!storage.unload_collection("idle").unwrap());
+    assert!(storage.collection_exists("idle"));
+    let idle = storage.get_collection("cold").unwrap();
+    assert!(storage.is_loaded("idle"));
+    assert_eq!(idle.count(), 3);
+    idle.delete("0").unwrap();
+    drop(idle);
+
+    // The full snapshot includes collections that are not loaded
+    storage.unload_collection("idle").unwrap();
+    let full = storage.create_full_snapshot().unwrap();
+    let file = std::fs::File::open(storage.get_full_snapshot_path(&full.name).unwrap()).unwrap();
+    let mut entries: Vec<String> = tar::Archive::new(file).entries().unwrap()
+        .map(|e| e.unwrap().path().unwrap().to_string_lossy().into_owned())
+        .collect();
+    entries.sort();
+    assert_eq!(entries, [
+        "collections/hot/manifest.json",
+        "collections/hot/points/data.mdb",
+        "collections/idle/manifest.json",
+        "collections/idle/points/data.mdb",
+    ]);
+    assert!(!storage.is_loaded("idle"));
+
+    // Startup reads manifests only
+    drop(storage);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let mut names = storage.list_collections();
+    names.sort();
+    assert_eq!(names, ["hot", "idle"]);
+    assert!(!storage.is_loaded("hot") && !storage.is_loaded("idle"));
+    let idle = storage.load_collection("idle").unwrap();
+    assert_eq!(idle.count(), 2);
+    assert!(!storage.is_loaded("hot"));
+
+    assert!(storage.delete_collection("idle").unwrap());
+    assert!(!dir.exists());
+}
+
+#[test]
+fn test_snapshot_v2_roundtrip() {
+    use std::collections::HashMap;
+    use vectx_core::{MultiVector, SparseModifier, SparseVector};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let config = CollectionConfig {
+        name: "snap".to_string(),
+        vector_dim: 3,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = storage.create_collection(config).unwrap();
+    collection.set_sparse_modifier("text", SparseModifier::Idf);
+    for i in 0..2500u64 {
+        let mut point = Point::new(
+            PointId::Integer(i),
+            Vector::new(vec![i as f32 * 0.5, 1.0 / (i + 1) as f32, -1.25]),
+            Some(serde_json::json!({"n": i, "tag": format!("t{}", i % 3)})),
+        );
+        let mut sparse = HashMap::new();
+        sparse.insert("text".to_string(), SparseVector::new(vec![i as u32, 100_000], vec![1.0, 0.5]));
+        point.sparse_vectors = sparse;
+        collection.upsert(point).unwrap();
+    }
+    let mv = MultiVector::new(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]).unwrap();
+    collection.upsert(Point::new_multi(PointId::Integer(9999), mv, None)).unwrap();
+
+    // f32: exact round trip, including payloads, sparse and multivectors
+    let f32_snapshot = storage.create_collection_snapshot("snap").unwrap();
+    storage.delete_collection("snap").unwrap();
+    let restored = storage.recover_from_snapshot("snap", &f32_snapshot.name, &RecoverOptions::default()).unwrap();
+    assert_eq!(restored.count(), 2501);
+    let point = restored.get("1234").unwrap();
+    assert_eq!(point.vector.as_slice(), &[617.0, 1.0 / 1235.0, -1.25]);
+    assert_eq!(point.payload.unwrap()["tag"], "t1");
+    assert_eq!(point.sparse_vectors["text"].indices, vec![1234, 100_000]);
+    assert_eq!(restored.get("9999").unwrap().multivector.unwrap().vectors()[1], vec![4.0, 5.0, 6.0]);
+    assert_eq!(restored.sparse_modifier("text"), SparseModifier::Idf);
+
+    // f16: about half the size, values within half-precision error
+    let f16_snapshot = storage.create_collection_snapshot_encoded("snap", VectorEncoding::F16).unwrap();
+    assert!(f16_snapshot.size < f32_snapshot.size);
+    let restored = storage.recover_from_snapshot("snap", &f16_snapshot.name, &RecoverOptions::default()).unwrap();
+    let point = restored.get("1234").unwrap();
+    assert!((point.vector.as_slice()[0] - 617.0).abs() < 0.5);
+
+    // A flipped byte fails the section checksum instead of restoring bad data
+    let path = storage.get_snapshot_path("snap", &f32_snapshot.name).unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    let middle = bytes.len() / 2;
+    bytes[middle] ^= 0xff;
+    std::fs::write(&path, bytes).unwrap();
+    let err = storage.recover_from_snapshot("snap", &f32_snapshot.name, &RecoverOptions::default()).err().unwrap();
+    assert!(err.to_string().contains("checksum"));
+
+    // Version 1 (JSON) snapshots still load
+    let v1 = serde_json::json!({
+        "name": "old",
+        "config": {"vector_dim": 2, "distance": "Cosine", "use_hnsw": false, "enable_bm25": false},
+        "points": [{"id": "a", "vector": [1.0, 0.0], "payload": {"k": "v"}}],
+        "created_at": 0
+    });
+    let old = storage.upload_and_restore_snapshot("old", v1.to_string().as_bytes(), None, &RecoverOptions::default()).unwrap();
+    assert_eq!(old.get("a").unwrap().payload, Some(serde_json::json!({"k": "v"})));
+}
+
+#[test]
+fn test_snapshot_header_count_is_not_trusted() {
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &byte in data {
+            crc ^= u32::from(byte);
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "counted".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    }).unwrap();
+    collection.upsert(Point::new(PointId::Integer(1), Vector::new(vec![1.0, 0.0]), None)).unwrap();
+    let snapshot = storage.create_collection_snapshot("counted").unwrap();
+    let bytes = std::fs::read(storage.get_snapshot_path("counted", &snapshot.name).unwrap()).unwrap();
+
+    // Magic and version, then the header section: tag, length, JSON, checksum
+    let len = u32::from_le_bytes(bytes[9..13].try_into().unwrap()) as usize;
+    let mut header: serde_json::Value = serde_json::from_slice(&bytes[13..13 + len]).unwrap();
+    header["points"] = serde_json::json!(u64::MAX / 2);
+    let body = serde_json::to_vec(&header).unwrap();
+    let mut forged = bytes[..9].to_vec();
+    forged.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    forged.extend_from_slice(&body);
+    forged.extend_from_slice(&crc32(&body).to_le_bytes());
+    forged.extend_from_slice(&bytes[13 + len + 4..]);
+
+    // Rejected for the points missing, without reserving room for them first
+    let err = storage.upload_and_restore_snapshot("forged", &forged, None, &RecoverOptions::default()).err().unwrap();
+    assert!(err.to_string().contains("truncated"), "{}", err);
+    assert!(storage.get_collection("forged").is_none());
+}
+
+#[test]
+fn test_differential_snapshots() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let config = CollectionConfig {
+        name: "delta".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = storage.create_collection(config).unwrap();
+    let point = |i: u64, v: f32| Point::new(PointId::Integer(i), Vector::new(vec![v, v]), Some(serde_json::json!({"v": v})));
+    for i in 0..100 {
+        collection.upsert(point(i, 1.0)).unwrap();
+    }
+    let base = storage.create_collection_snapshot("delta").unwrap();
+
+    // First delta: one update, one delete, one insert
+    collection.upsert(point(5, 2.0)).unwrap();
+    collection.delete("6").unwrap();
+    collection.upsert(point(100, 3.0)).unwrap();
+    let delta1 = storage.create_differential_snapshot("delta", &base.name, VectorEncoding::F32).unwrap();
+    assert!(delta1.size < base.size);
+
+    // Second delta on top of the first
+    collection.set_payload("7", serde_json::json!({"tag": "x"})).unwrap();
+    collection.delete("100").unwrap();
+    let delta2 = storage.create_differential_snapshot("delta", &delta1.name, VectorEncoding::F32).unwrap();
+
+    storage.delete_collection("delta").unwrap();
+    let restored = storage.recover_from_snapshot("delta", &delta2.name, &RecoverOptions::default()).unwrap();
+    assert_eq!(restored.count(), 99);
+    assert_eq!(restored.get("5").unwrap().vector.as_slice(), &[2.0, 2.0]);
+    assert!(restored.get("6").is_none());
+    assert!(restored.get("100").is_none());
+    assert_eq!(restored.get("7").unwrap().payload.unwrap()["tag"], "x");
+
+    // Restoring the first delta stops there
+    let restored = storage.recover_from_snapshot("delta", &delta1.name, &RecoverOptions::default()).unwrap();
+    assert_eq!(restored.count(), 100);
+    assert!(restored.get("100").is_some());
+
+    // A base the journal no longer covers is refused
+    let collection = storage.get_collection("delta").unwrap();
+    collection.journal().set_retention(vectx_core::JournalRetention { max_entries: 1, max_age_secs: None });
+    collection.upsert(point(1, 5.0)).unwrap();
+    collection.upsert(point(2, 5.0)).unwrap();
+    let snapshot = storage.create_collection_snapshot("delta").unwrap();
+    collection.upsert(point(3, 5.0)).unwrap();
+    collection.upsert(point(4, 5.0)).unwrap();
+    assert!(storage.create_differential_snapshot("delta", &snapshot.name, VectorEncoding::F32).is_err());
+}
+
+#[test]
+fn test_snapshot_during_writes() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let config = CollectionConfig {
+        name: "busy".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = storage.create_collection(config).unwrap();
+    let point = |i: u64| Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 1.0]), Some(serde_json::json!({"i": i})));
+    for i in 0..5_000 {
+        collection.upsert(point(i)).unwrap();
+    }
+
+    // Writers keep going while the snapshot copies the points
+    let writer = {
+        let collection = collection.clone();
+        std::thread::spawn(move || {
+            for i in 5_000..10_000 {
+                collection.upsert(point(i)).unwrap();
+                if i % 2 == 0 {
+                    collection.delete(&(i - 5_000).to_string()).unwrap();
+                }
+            }
+        })
+    };
+    let snapshot = storage.create_collection_snapshot("busy").unwrap();
+    writer.join().unwrap();
+    assert_eq!(collection.count(), 7_500);
+
+    // Every point in the snapshot is whole, whichever writes it saw
+    let restored = storage.recover_from_snapshot("busy", &snapshot.name, &RecoverOptions::default()).unwrap();
+    assert!(restored.count() >= 2_500);
+    for restored_point in restored.get_all_points() {
+        let i = restored_point.payload.as_ref().unwrap()["i"].as_u64().unwrap();
+        assert_eq!(restored_point.id.to_string(), i.to_string());
+        assert_eq!(restored_point.vector.as_slice(), &[i as f32, 1.0]);
+    }
+    // Odd ids below 5000 were never deleted
+    assert!((1..5_000).step_by(2).all(|i| restored.get(&i.to_string()).is_some()));
+}
+
+#[test]
+fn test_recover_priority_and_scope() {
+    use vectx_core::{FilterCondition, PayloadFilter};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let config = CollectionConfig {
+        name: "partial".to_string(),
+        vector_dim: 2,
+        distance: Distance::Dot,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    let collection = storage.create_collection(config).unwrap();
+    let point = |i: u64, v: f32, tag: &str| {
+        Point::new(PointId::Integer(i), Vector::new(vec![v, v]), Some(serde_json::json!({"tag": tag})))
+    };
+    for i in 0..10 {
+        collection.upsert(point(i, 1.0, if i % 2 == 0 { "even" } else { "odd" })).unwrap();
+    }
+    let snapshot = storage.create_collection_snapshot("partial").unwrap();
+
+    // Local changes after the snapshot
+    collection.upsert(point(0, 9.0, "changed")).unwrap();
+    collection.delete("1").unwrap();
+    collection.upsert(point(50, 9.0, "new")).unwrap();
+
+    // replica: existing points win, missing ones come back
+    let options = RecoverOptions { priority: SnapshotPriority::Replica, ..Default::default() };
+    let restored = storage.recover_from_snapshot("partial", &snapshot.name, &options).unwrap();
+    assert_eq!(restored.count(), 11);
+    assert_eq!(restored.get("0").unwrap().vector.as_slice(), &[9.0, 9.0]);
+    assert!(restored.get("1").is_some());
+    assert!(restored.get("50").is_some());
+
+    // payload only: vectors stay, payloads come from the snapshot
+    let options = RecoverOptions { scope: RestoreScope::PayloadOnly, ..Default::default() };
+    let restored = storage.recover_from_snapshot("partial", &snapshot.name, &options).unwrap();
+    let zero = restored.get("0").unwrap();
+    assert_eq!(zero.vector.as_slice(), &[9.0, 9.0]);
+    assert_eq!(zero.payload.unwrap()["tag"], "even");
+    assert_eq!(restored.get("50").unwrap().payload.unwrap()["tag"], "new");
+
+    // filter: the collection is replaced by the matching snapshot points
+    let filter = PayloadFilter::new(FilterCondition::Equals {
+        field: "tag".to_string(),
+        value: serde_json::json!("odd"),
+    });
+    let options = RecoverOptions { filter: Some(Box::new(filter)), ..Default::default() };
+    let restored = storage.recover_from_snapshot("partial", &snapshot.name, &options).unwrap();
+    assert_eq!(restored.count(), 5);
+    assert!(restored.get("0").is_none());
+    assert_eq!(restored.get("1").unwrap().vector.as_slice(), &[1.0, 1.0]);
+
+    // config only: an empty collection with the snapshot configuration
+    let options = RecoverOptions { scope: RestoreScope::ConfigOnly, ..Default::default() };
+    let restored = storage.recover_from_snapshot("partial", &snapshot.name, &options).unwrap();
+    assert_eq!(restored.count(), 0);
+    assert_eq!(restored.vector_dim(), 2);
+
+    storage.delete_collection("partial").unwrap();
+    let options = RecoverOptions { scope: RestoreScope::PayloadOnly, ..Default::default() };
+    assert!(storage.recover_from_snapshot("partial", &snapshot.name, &options).is_err());
+}
+
+#[test]
+fn test_core_requests() {
+    use vectx_api::core_requests::{self, PointInput, RecommendRequest, RequestError};
+
+    let collection = Collection::new(CollectionConfig {
+        name: "shared".to_string(),
+        vector_dim: 2,
+        distance: Distance::Euclidean,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    let points: Vec<PointInput> = (0..6u64)
+        .map(|i| PointInput::new(
+            PointId::Integer(i),
+            vec![i as f32, 0.0],
+            Some(serde_json::json!({"rank": i, "tag": if i % 2 == 0 { "even" } else { "odd" }})),
+        ))
+        .collect();
+    let statuses = core_requests::upsert_points(&collection, points, None).unwrap();
+    assert_eq!(statuses.len(), 6);
+    assert_eq!(collection.count(), 6);
+
+    // A bad point rejects the whole batch and names its index
+    let bad = vec![
+        PointInput::new(PointId::Integer(10), vec![1.0, 1.0], None),
+        PointInput::new(PointId::Integer(11), vec![1.0], None),
+    ];
+    match core_requests::upsert_points(&collection, bad, None) {
+        Err(RequestError::Invalid(message)) => assert!(message.contains("index 1"), "{}", message),
+        other => panic!("expected an invalid request, got {:?}", other),
+    }
+    assert!(collection.get("10").is_none());
+
+    // Every bound of a range applies, and `any` matches each of its values
+    let filter = core_requests::parse_filter(&serde_json::json!({
+        "must": [
+            {"key": "rank", "range": {"gte": 1, "lt": 5}},
+            {"key": "tag", "match": {"any": ["even", "none"]}}
+        ]
+    }));
+    let filter = core_requests::build_filter(filter, None, Some(&collection)).unwrap();
+    let mut ranks: Vec<String> = collection.iter_filtered(Some(filter.as_ref()), Projection::IDS)
+        .map(|p| p.id.to_string())
+        .collect();
+    ranks.sort();
+    assert_eq!(ranks, vec!["2", "4"]);
+
+    let request = RecommendRequest {
+        positive: vec!["0".to_string()],
+        limit: Some(2),
+        offset: 1,
+        filter: core_requests::parse_filter(&serde_json::json!({"key": "tag", "match": {"value": "odd"}})),
+        ..Default::default()
+    };
+    let found = core_requests::recommend(&collection, &request, None).unwrap();
+    let ids: Vec<String> = found.iter().map(|(p, _)| p.id.to_string()).collect();
+    assert_eq!(ids, vec!["3", "5"]);
+
+    let missing = RecommendRequest { positive: vec!["99".to_string()], ..Default::default() };
+    assert!(matches!(core_requests::recommend(&collection, &missing, None), Err(RequestError::Invalid(_))));
+}
+
+#[test]
+fn test_fsck_and_compact() {
+    use vectx_storage::fsck::{check, compact};
+    use vectx_storage::{CollectionStore, CompactOptions, IssueKind};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    for name in ["kept", "dropped"] {
+        let collection = storage.create_collection(CollectionConfig {
+            name: name.to_string(),
+            vector_dim: 2,
+            distance: Distance::Euclidean,
+            use_hnsw: false,
+            enable_bm25: false,
+        }).unwrap();
+        for i in 0..50 {
+            collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 1.0]), None)).unwrap();
+        }
+    }
+    for i in 0..40 {
+        storage.get_collection("kept").unwrap().delete(&i.to_string()).unwrap();
+    }
+    storage.create_collection_snapshot("kept").unwrap();
+    storage.create_collection_snapshot("dropped").unwrap();
+    storage.delete_collection("dropped").unwrap();
+    drop(storage);
+
+    // The deleted collection's snapshot stays behind
+    let report = check(temp_dir.path()).unwrap();
+    assert_eq!(report.issues.len(), 1, "{:?}", report.issues);
+    assert_eq!(report.issues[0].kind, IssueKind::Orphaned);
+
+    // A record that no longer fits the collection and a torn journal line
+    let dir = temp_dir.path().join("collections").join("kept");
+    let store = CollectionStore::open(&dir).unwrap();
+    store.save_point(&Point::new(PointId::Integer(99), Vector::new(vec![1.0, 2.0, 3.0]), None)).unwrap();
+    drop(store);
+    let journal = temp_dir.path().join("journal").join("kept.jsonl");
+    let mut text = std::fs::read_to_string(&journal).unwrap();
+    text.push_str("{\"op_id\": 7, \"op_ty");
+    std::fs::write(&journal, text).unwrap();
+
+    let report = check(temp_dir.path()).unwrap();
+    assert_eq!(report.collections, 1);
+    assert_eq!(report.points, 11);
+    let mut kinds: Vec<String> = report.issues.iter().map(|i| i.kind.to_string()).collect();
+    kinds.sort();
+    assert_eq!(kinds, vec!["corrupt journal entry", "corrupt point record", "orphaned"]);
+    let record = report.issues.iter().find(|i| i.kind == IssueKind::CorruptRecord).unwrap();
+    assert_eq!(record.record.as_deref(), Some("99"));
+
+    // Without options compaction keeps every record and file
+    let kept = compact(temp_dir.path(), CompactOptions::default()).unwrap();
+    assert_eq!(kept.collections, 1);
+    assert_eq!(kept.dropped, 0);
+    assert_eq!(check(temp_dir.path()).unwrap().issues.len(), 3);
+
+    let fixed = compact(temp_dir.path(), CompactOptions { drop_corrupt: true, remove_orphans: true }).unwrap();
+    assert_eq!(fixed.dropped, 2);
+    assert_eq!(fixed.removed.len(), 1);
+    assert!(fixed.bytes_after <= fixed.bytes_before);
+    assert!(check(temp_dir.path()).unwrap().is_clean());
+
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.get_collection("kept").unwrap();
+    assert_eq!(collection.count(), 10);
+    assert_eq!(collection.journal().since(None, usize::MAX).len(), 90);
+}
+
+#[test]
+fn test_qdrant_point_export() {
+    use vectx_api::export::qdrant_point;
+    use vectx_core::{MultiVector, SparseVector};
+
+    let collection = Collection::new(CollectionConfig {
+        name: "export".to_string(),
+        vector_dim: 2,
+        distance: Distance::Cosine,
+        use_hnsw: false,
+        enable_bm25: false,
+    });
+    let dense = Point::new(PointId::Integer(1), Vector::new(vec![0.6, 0.8]), Some(serde_json::json!({"a": 1})));
+    assert_eq!(
+        qdrant_point(&collection, &dense),
+        serde_json::json!({"id": 1, "vector": [0.6f32, 0.8f32], "payload": {"a": 1}})
+    );
+
+    let multi = Point::new_multi(PointId::String("m".to_string()), MultiVector::new(vec![vec![1.0, 0.0], vec![0.0, 1.0]]).unwrap(), None);
+    assert_eq!(
+        qdrant_point(&collection, &multi),
+        serde_json::json!({"id": "m", "vector": [[1.0, 0.0], [0.0, 1.0]], "payload": {}})
+    );
+
+    // With sparse vectors the dense one goes under the default name
+    let mut hybrid = Point::new(PointId::Integer(2), Vector::new(vec![1.0, 0.0]), None);
+    hybrid.sparse_vectors.insert("text".to_string(), SparseVector::new(vec![3], vec![0.5]));
+    assert_eq!(
+        qdrant_point(&collection, &hybrid)["vector"],
+        serde_json::json!({"": [1.0, 0.0], "text": {"indices": [3], "values": [0.5]}})
+    );
+}
+
+#[test]
+fn test_telemetry_report() {
+    use vectx_api::telemetry::{telemetry_report, TelemetryOptions};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    for name in ["loaded", "idle"] {
+        let collection = storage.create_collection(CollectionConfig {
+            name: name.to_string(),
+            vector_dim: 2,
+            distance: Distance::Dot,
+            use_hnsw: false,
+            enable_bm25: false,
+        }).unwrap();
+        for i in 0..25 {
+            collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![1.0, i as f32]), None)).unwrap();
+        }
+    }
+    assert!(storage.unload_collection("idle").unwrap());
+
+    let report = telemetry_report(&storage, TelemetryOptions::default());
+    let id = report["id"].as_str().unwrap().to_string();
+    assert_eq!(report["collections"]["number_of_collections"], 2);
+    assert_eq!(report["collections"]["loaded_collections"], 1);
+    let collections = report["collections"]["collections"].as_array().unwrap();
+    assert_eq!(collections[0]["id"], "idle");
+    assert_eq!(collections[0]["loaded"], false);
+    assert_eq!(collections[0]["config"]["params"]["vectors"]["distance"], "Dot");
+    assert_eq!(collections[1]["points_count"], 25);
+    assert_eq!(collections[1]["config"]["hnsw_config"]["m"], 16);
+    assert!(!storage.is_loaded("idle"));
+
+    let anonymous = telemetry_report(&storage, TelemetryOptions { anonymize: true, details_level: 1 });
+    assert_eq!(anonymous["id"], id.as_str());
+    let collections = anonymous["collections"]["collections"].as_array().unwrap();
+    assert!(collections.iter().all(|c| c["id"] != "idle" && c["id"] != "loaded"));
+    assert!(collections.iter().all(|c| c["id"].as_str().unwrap().len() == 64));
+    assert_eq!(anonymous["collections"]["points_count"], 10);
+    // Memory sizes are bucketed like counts
+    let bytes = anonymous["memory_total_bytes"].as_u64().unwrap();
+    assert_eq!(bytes, 10u64.pow(bytes.ilog10()));
+    assert_eq!(collections[1]["memory_total_bytes"], anonymous["memory_total_bytes"]);
+    // A name keeps its hash across reports
+    let again = telemetry_report(&storage, TelemetryOptions { anonymize: true, details_level: 1 });
+    assert_eq!(again["collections"]["collections"][1]["id"], collections[1]["id"]);
+
+    let totals = telemetry_report(&storage, TelemetryOptions { anonymize: false, details_level: 0 });
+    assert!(totals["collections"].get("collections").is_none());
+}
+
+#[test]
+fn test_compression_negotiation() {
+    use vectx_api::compression::{CompressionConfig, Encoding};
+
+    let both: CompressionConfig = "zstd, gzip".parse().unwrap();
+    assert_eq!(both, CompressionConfig::default());
+    assert_eq!(both.to_string(), "zstd,gzip");
+    let none: CompressionConfig = "none".parse().unwrap();
+    assert!(!none.is_enabled());
+    assert!("gzip,lz4".parse::<CompressionConfig>().is_err());
+
+    // Brotli and deflate are never picked
+    assert_eq!(both.restrict_accept_encoding("br, gzip;q=0.8, deflate").as_deref(), Some("gzip;q=0.8"));
+    assert_eq!(both.restrict_accept_encoding("*;q=0.5").as_deref(), Some("zstd;q=0.5, gzip;q=0.5"));
+    assert_eq!(both.restrict_accept_encoding("br"), None);
+    assert_eq!(both.restrict_accept_encoding("identity, br"), None);
+
+    let gzip = CompressionConfig { encodings: vec![Encoding::Gzip], ..CompressionConfig::default() };
+    assert_eq!(gzip.restrict_accept_encoding("zstd, x-gzip").as_deref(), Some("x-gzip"));
+    assert_eq!(none.restrict_accept_encoding("gzip, zstd"), None);
+}
+
+#[test]
+fn test_rerank_request() {
+    use std::sync::Arc;
+    use vectx_api::rerank::{self, CrossEncoder, ModelSpec, RerankRequest};
+
+    /// Scores a text by how many of the query's words it contains
+    struct WordOverlap;
+    impl CrossEncoder for WordOverlap {
+        fn score(&self, query: &str, texts: &[&str]) -> Result<Vec<f32>, String> {
+            Ok(texts.iter()
+                .map(|text| query.split_whitespace().filter(|word| text.contains(word)).count() as f32)
+                .collect())
+        }
+    }
+    rerank::register("overlap", Arc::new(WordOverlap));
+
+    let request: RerankRequest = serde_json::from_value(serde_json::json!({
+        "model": "overlap", "query": "red wool coat"
+    })).unwrap();
+    assert_eq!(request.top_k, rerank::DEFAULT_TOP_K);
+    assert_eq!(request.field, "text");
+    request.validate().unwrap();
+
+    let points: Vec<Point> = [
+        serde_json::json!({"text": "a red coat"}),
+        serde_json::json!({"title": "no text"}),
+        serde_json::json!({"text": "red wool coat"}),
+    ].into_iter()
+        .enumerate()
+        .map(|(i, payload)| Point::new(PointId::Integer(i as u64), Vector::new(vec![1.0]), Some(payload)))
+        .collect();
+    let refs: Vec<&Point> = points.iter().collect();
+    assert_eq!(request.score(&refs).unwrap(), vec![2.0, 0.0, 3.0]);
+
+    let unknown = RerankRequest { model: "missing".to_string(), ..request.clone() };
+    assert!(unknown.validate().unwrap_err().contains("missing"));
+    assert!(RerankRequest { top_k: 0, ..request.clone() }.validate().is_err());
+    assert!(serde_json::from_value::<RerankRequest>(serde_json::json!({"model": "overlap"})).is_err());
+
+    let spec: ModelSpec = "ms-marco=/models/ms-marco".parse().unwrap();
+    assert_eq!(spec.name, "ms-marco");
+    assert_eq!(spec.dir, std::path::PathBuf::from("/models/ms-marco"));
+    assert!("no-directory".parse::<ModelSpec>().is_err());
+}
+
+#[test]
+fn test_rerank_query() {
+    use std::sync::Arc;
+    use vectx_api::rerank::{self, CrossEncoder, MAX_TOP_K};
+    use vectx_test::TestServer;
+
+    /// Scores a text by how many of the query's words it contains
+    struct WordOverlap;
+    impl CrossEncoder for WordOverlap {
+        fn score(&self, query: &str, texts: &[&str]) -> Result<Vec<f32>, String> {
+            Ok(texts.iter()
+                .map(|text| query.split_whitespace().filter(|word| text.contains(word)).count() as f32)
+                .collect())
+        }
+    }
+    rerank::register("overlap-query", Arc::new(WordOverlap));
+
+    let server = TestServer::start();
+    server.create_collection("coats", 2, "Dot");
+    // Vector order 1, 2, 3, 4; text order 3, 4, 2, 1
+    server.upsert_points("coats", serde_json::json!([
+        {"id": 1, "vector": [1.0, 0.0], "payload": {"text": "a hat"}},
+        {"id": 2, "vector": [0.9, 0.1], "payload": {"text": "a coat"}},
+        {"id": 3, "vector": [0.8, 0.2], "payload": {"text": "red wool coat"}},
+        {"id": 4, "vector": [0.7, 0.3], "payload": {"text": "a wool coat"}},
+    ]));
+    let query = |extra: serde_json::Value| {
+        let mut body = serde_json::json!({
+            "query": [1.0, 0.0],
+            "limit": 10,
+            "rerank": {"model": "overlap-query", "query": "red wool coat"},
+        });
+        for (key, value) in extra.as_object().unwrap() {
+            body[key] = value.clone();
+        }
+        server.post("/collections/coats/points/query", body)
+    };
+    let ids = |response: &vectx_test::TestResponse| -> Vec<u64> {
+        response.result()["points"].as_array().unwrap().iter().map(|p| p["id"].as_u64().unwrap()).collect()
+    };
+
+    // Results come back in the model's order, scored by it
+    let response = query(serde_json::json!({}));
+    response.assert_ok();
+    assert_eq!(ids(&response), vec![3, 4, 2, 1]);
+    assert_eq!(response.result()["points"][0]["score"], serde_json::json!(3.0));
+
+    // The threshold applies to the model's score
+    let response = query(serde_json::json!({"score_threshold": 2.0}));
+    assert_eq!(ids(&response), vec![3, 4]);
+
+    // Explanations keep the vector score next to the model's
+    let response = query(serde_json::json!({"explain": true}));
+    let explanation = &response.result()["points"][0]["explanation"];
+    assert_eq!(explanation["rerank"], serde_json::json!({"model": "overlap-query", "score": 3.0}));
+    assert_eq!(explanation["contributions"][0]["rank"], serde_json::json!(3));
+
+    // top_k beyond the hard maximum is refused; max_limit caps it
+    query(serde_json::json!({"rerank": {"model": "overlap-query", "query": "red", "top_k": MAX_TOP_K + 1}})).assert_status(400);
+    server.patch("/collections/coats", serde_json::json!({"search_defaults": {"max_limit": 2}})).assert_ok();
+    let response = query(serde_json::json!({"rerank": {"model": "overlap-query", "query": "red wool coat", "top_k": 4}}));
+    assert_eq!(ids(&response), vec![2, 1]);
+}
+
+#[test]
+fn test_msgpack_negotiation() {
+    use actix_web::http::header::{Accept, Header};
+    use vectx_api::content::{is_msgpack, prefers_msgpack};
+
+    assert!(is_msgpack("application/msgpack"));
+    assert!(is_msgpack("application/x-msgpack; charset=binary"));
+    assert!(!is_msgpack("application/json"));
+
+    let prefers = |accept: &str| {
+        let req = actix_web::test::TestRequest::default().insert_header(("Accept", accept)).to_http_request();
+        prefers_msgpack(&Accept::parse(&req).unwrap())
+    };
+    assert!(prefers("application/msgpack"));
+    assert!(prefers("application/msgpack, application/json;q=0.9"));
+    assert!(!prefers("application/json, application/msgpack;q=0.5"));
+    assert!(!prefers("*/*"));
+    assert!(!prefers("text/html"));
+}
+
+#[test]
+fn test_page_cache() {
+    use std::time::Duration;
+    use vectx_api::pagination::{Hit, PageCache, PageOwner};
+
+    let cache = PageCache::new(Duration::from_secs(60));
+    let owner = PageOwner::new(Some("key"), None);
+    let hits: Vec<Hit> = (0..7)
+        .map(|i| Hit { id: i.to_string(), score: 1.0 - i as f32 / 10.0, explanation: serde_json::Value::Null })
+        .collect();
+    assert!(cache.store("docs", &owner, hits[..3].to_vec(), 3).is_none());
+
+    let token = cache.store("docs", &owner, hits.clone(), 3).unwrap();
+    let (page, next) = cache.page("docs", &owner, &token, None).unwrap();
+    assert_eq!(page.iter().map(|hit| hit.id.as_str()).collect::<Vec<_>>(), vec!["3", "4", "5"]);
+    let (page, next) = cache.page("docs", &owner, &next.unwrap(), None).unwrap();
+    assert_eq!(page.iter().map(|hit| hit.id.as_str()).collect::<Vec<_>>(), vec!["6"]);
+    assert!(next.is_none());
+
+    // Pages can be read again, with another size
+    let (page, next) = cache.page("docs", &owner, &token, Some(1)).unwrap();
+    assert_eq!(page[0].id, "3");
+    assert!(next.is_some());
+
+    assert!(cache.page("other", &owner, &token, None).unwrap_err().contains("another collection"));
+    // Nor with another key or filter
+    for stranger in [
+        PageOwner::new(Some("other"), None),
+        PageOwner::new(None, None),
+        PageOwner::new(Some("key"), Some(&serde_json::json!({"must": []}))),
+    ] {
+        assert!(cache.page("docs", &stranger, &token, None).unwrap_err().contains("another API key"));
+    }
+    assert!(cache.page("docs", &owner, "garbage", None).is_err());
+    assert!(cache.page("docs", &owner, "0123.3", None).is_err());
+    // Offsets past the list and huge page sizes are handled
+    let (id, _) = token.rsplit_once('.').unwrap();
+    assert!(cache.page("docs", &owner, &format!("{}.8", id), None).is_err());
+    assert!(cache.page("docs", &owner, &format!("{}.{}", id, usize::MAX), None).is_err());
+    let (page, next) = cache.page("docs", &owner, &format!("{}.6", id), Some(usize::MAX)).unwrap();
+    assert_eq!(page.len(), 1);
+    assert!(next.is_none());
+
+    let expiring = PageCache::new(Duration::ZERO);
+    let token = expiring.store("docs", &owner, hits, 3).unwrap();
+    assert!(expiring.page("docs", &owner, &token, None).unwrap_err().contains("expired"));
+}
+
+#[test]
+fn test_page_tokens_per_caller() {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use vectx_test::{Method, TestServer};
+
+    let server = TestServer::start_with(|storage| storage.access_control().set_master_key(Some("master")));
+    let tenant = |name: &str| {
+        let claims = serde_json::json!({
+            "access": [{"collection": "docs", "access": "r", "payload": {"tenant_id": name}}]
+        });
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(b"master")).unwrap()
+    };
+    let (acme, globex) = (tenant("acme"), tenant("globex"));
+    let search = |server: &TestServer, key: &str, body: serde_json::Value| {
+        server.send(server.request(Method::POST, "/collections/docs/points/search").header("api-key", key).json(&body))
+    };
+
+    server.send(server.request(Method::PUT, "/collections/docs").header("api-key", "master")
+        .json(&serde_json::json!({"vectors": {"size": 2, "distance": "Dot"}}))).assert_ok();
+    let points: Vec<serde_json::Value> = (0..6)
+        .map(|i| serde_json::json!({"id": i, "vector": [i as f32, 1.0], "payload": {"tenant_id": if i % 2 == 0 { "acme" } else { "globex" }}}))
+        .collect();
+    server.send(server.request(Method::PUT, "/collections/docs/points?wait=true").header("api-key", "master")
+        .json(&serde_json::json!({"points": points}))).assert_ok();
+
+    let first = search(&server, &acme, serde_json::json!({"vector": [1.0, 0.0], "limit": 1, "paginate": true}));
+    let token = first.assert_ok().body["next_page_token"].as_str().unwrap().to_string();
+    let next = serde_json::json!({"vector": [1.0, 0.0], "page_token": token});
+    search(&server, &acme, next.clone()).assert_ok();
+
+    // Another tenant, another filter or another server can't follow it
+    let refused = search(&server, &globex, next.clone());
+    refused.assert_status(400);
+    assert!(refused.error().unwrap().contains("another API key"), "{:?}", refused.error());
+    let filtered = serde_json::json!({"page_token": token, "filter": {"must": [{"key": "tenant_id", "match": {"value": "acme"}}]}});
+    search(&server, &acme, filtered).assert_status(400);
+    let other = TestServer::start_with(|storage| storage.access_control().set_master_key(Some("master")));
+    other.send(other.request(Method::PUT, "/collections/docs").header("api-key", "master")
+        .json(&serde_json::json!({"vectors": {"size": 2, "distance": "Dot"}}))).assert_ok();
+    search(&other, &acme, next).assert_status(400);
+}
+
+#[test]
+fn test_claim_restricted_aggregate() {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use vectx_test::{Method, TestServer};
+
+    let server = TestServer::start_with(|storage| storage.access_control().set_master_key(Some("master")));
+    let claims = serde_json::json!({
+        "access": [{"collection": "docs", "access": "r", "payload": {"tenant_id": "acme"}}]
+    });
+    let acme = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"master")).unwrap();
+    let post = |path: &str, body: serde_json::Value| {
+        server.send(server.request(Method::POST, path).header("api-key", &acme).json(&body))
+    };
+
+    server.send(server.request(Method::PUT, "/collections/docs").header("api-key", "master")
+        .json(&serde_json::json!({"vectors": {"size": 2, "distance": "Dot"}}))).assert_ok();
+    let points: Vec<serde_json::Value> = (0..6)
+        .map(|i| serde_json::json!({"id": i, "vector": [i as f32, 1.0], "payload": {
+            "tenant_id": if i % 2 == 0 { "acme" } else { "globex" }, "price": i
+        }}))
+        .collect();
+    server.send(server.request(Method::PUT, "/collections/docs/points?wait=true").header("api-key", "master")
+        .json(&serde_json::json!({"points": points}))).assert_ok();
+    for (field, schema) in [("tenant_id", "keyword"), ("price", "integer")] {
+        server.send(server.request(Method::PUT, "/collections/docs/index").header("api-key", "master")
+            .json(&serde_json::json!({"field_name": field, "field_schema": schema}))).assert_ok();
+    }
+
+    // The tenant only aggregates its own points
+    let aggregate = post("/collections/docs/aggregate", serde_json::json!({
+        "group_by": "tenant_id",
+        "metrics": {"revenue": {"type": "sum", "field": "price"}}
+    }));
+    let result = aggregate.assert_ok().result();
+    assert_eq!(result["total_groups"], 1);
+    assert_eq!(result["groups"][0]["key"], "acme");
+    assert_eq!(result["groups"][0]["count"], 3);
+    assert_eq!(result["groups"][0]["metrics"]["revenue"], 6.0);
+
+    // Batch and grouped searches are restricted the same way
+    let batch = post("/collections/docs/points/search/batch", serde_json::json!({
+        "searches": [{"vector": [1.0, 0.0], "limit": 10, "with_payload": true}]
+    }));
+    let hits = batch.assert_ok().result()[0].as_array().unwrap().clone();
+    assert_eq!(hits.len(), 3);
+    assert!(hits.iter().all(|hit| hit["payload"]["tenant_id"] == "acme"));
+    let groups = post("/collections/docs/points/search/groups", serde_json::json!({
+        "vector": [1.0, 0.0], "group_by": "tenant_id", "limit": 10, "group_size": 10
+    }));
+    let groups = groups.assert_ok().result()["groups"].as_array().unwrap().clone();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["id"], "acme");
+}
+
+/// Point store refusing every write, like a full disk
+struct RefusingStore;
+
+impl vectx_core::PointStore for RefusingStore {
+    fn put(&self, _point: &Point) -> vectx_core::Result<()> {
+        Err(vectx_core::Error::Storage("disk full".to_string()))
+    }
+
+    fn remove(&self, _id: &str) -> vectx_core::Result<()> {
+        Err(vectx_core::Error::Storage("disk full".to_string()))
+    }
+}
+
+/// Holds each write until it is released, telling when one starts
+struct SlowStore {
+    started: std::sync::Mutex<std::sync::mpsc::Sender<()>>,
+    release: std::sync::Mutex<std::sync::mpsc::Receiver<()>>,
+}
+
+impl vectx_core::PointStore for SlowStore {
+    fn put(&self, _point: &Point) -> vectx_core::Result<()> {
+        self.started.lock().unwrap().send(()).unwrap();
+        self.release.lock().unwrap().recv_timeout(std::time::Duration::from_secs(10)).unwrap();
+        Ok(())
+    }
+
+    fn remove(&self, _id: &str) -> vectx_core::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_searches_during_store_writes() {
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    let collection = std::sync::Arc::new(Collection::new(CollectionConfig {
+        name: "slow".to_string(),
+        vector_dim: 2,
+        distance: Distance::Euclidean,
+        use_hnsw: true,
+        enable_bm25: false,
+    }));
+    collection.set_hnsw_params(vectx_core::HnswParams { full_scan_threshold: 0, ..Default::default() }).unwrap();
+    for i in 0..10 {
+        collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 0.0]), None)).unwrap();
+    }
+    collection.prewarm_index().unwrap();
+    let (started, writing) = channel();
+    let (release, released) = channel();
+    collection.set_point_store(Some(std::sync::Arc::new(SlowStore {
+        started: std::sync::Mutex::new(started),
+        release: std::sync::Mutex::new(released),
+    })));
+
+    let writer = {
+        let collection = collection.clone();
+        std::thread::spawn(move || collection.upsert(Point::new(PointId::Integer(10), Vector::new(vec![10.0, 0.0]), None)))
+    };
+    writing.recv_timeout(Duration::from_secs(10)).unwrap();
+
+    // The graph isn't locked while the point is stored
+    let (found, results) = channel();
+    let searcher = collection.clone();
+    std::thread::spawn(move || found.send(searcher.search(&Vector::new(vec![3.0, 0.0]), 1, None)).unwrap());
+    let hits = results.recv_timeout(Duration::from_secs(5)).expect("search waited for the store");
+    assert_eq!(hits[0].0.id, PointId::Integer(3));
+
+    release.send(()).unwrap();
+    writer.join().unwrap().unwrap();
+    assert_eq!(collection.search(&Vector::new(vec![10.0, 0.0]), 1, None)[0].0.id, PointId::Integer(10));
+}
+
+#[test]
+fn test_refused_payload_writes_fail() {
+    use vectx_test::TestServer;
+
+    let server = TestServer::start();
+    server.create_collection("deli", 2, "Cosine");
+    server.upsert_points("deli", serde_json::json!([
+        {"id": 1, "vector": [1.0, 0.0], "payload": {"kind": "cheese"}},
+    ]));
+    let collection = server.storage().get_collection("deli").unwrap();
+    collection.set_point_store(Some(std::sync::Arc::new(RefusingStore)));
+
+    let by_filter = serde_json::json!({"must": [{"key": "kind", "match": {"value": "cheese"}}]});
+    for (path, body) in [
+        ("payload", serde_json::json!({"payload": {"aged": true}, "points": [1]})),
+        ("payload", serde_json::json!({"payload": {"aged": true}, "filter": by_filter})),
+        ("payload/delete", serde_json::json!({"keys": ["kind"], "points": [1]})),
+        ("payload/clear", serde_json::json!({"points": [1]})),
+    ] {
+        let refused = server.post(&format!("/collections/deli/points/{}", path), body);
+        refused.assert_status(400);
+        assert!(refused.error().unwrap().contains("disk full"), "{:?}", refused.error());
+    }
+    let refused = server.put("/collections/deli/points/payload", serde_json::json!({"payload": {}, "points": [1]}));
+    assert!(refused.error().unwrap().contains("disk full"), "{:?}", refused.error());
+    assert_eq!(collection.get("1").unwrap().payload, Some(serde_json::json!({"kind": "cheese"})));
+
+    // Each operation of a batch reports its own failure
+    let batch = server.post("/collections/deli/points/batch", serde_json::json!({"operations": [
+        {"upsert": {"points": [{"id": 2, "vector": [0.0, 1.0]}]}},
+        {"set_payload": {"payload": {"aged": true}, "points": [1]}},
+        {"delete": {"points": [1]}},
+        {"upsert": {"points": [{"id": 3, "vector": [1.0]}]}},
+    ]}));
+    let results = batch.assert_ok().result().as_array().unwrap().clone();
+    assert_eq!(results.len(), 4);
+    for result in &results {
+        assert_eq!(result["status"], "failed", "{}", result);
+    }
+    assert!(results[0]["error"].as_str().unwrap().contains("disk full"));
+    assert!(results[3]["error"].as_str().unwrap().contains("dimension"));
+    assert!(collection.get("2").is_none() && collection.get("1").is_some());
+}
+
+#[test]
+fn test_delete_points_by_filter() {
+    use vectx_test::TestServer;
+
+    let server = TestServer::start();
+    server.create_collection("deli", 2, "Cosine");
+    server.upsert_points("deli", serde_json::json!([
+        {"id": 1, "vector": [1.0, 0.0], "payload": {"kind": "cheese", "aged": true}},
+        {"id": 2, "vector": [0.0, 1.0], "payload": {"kind": "cheese", "aged": false}},
+        {"id": 3, "vector": [0.7, 0.7], "payload": {"kind": "salumi", "aged": true}},
+        {"id": 4, "vector": [0.5, 0.5], "payload": {"kind": "salumi", "aged": false}},
+    ]));
+
+    // Every condition counts, not only the first
+    server.post("/collections/deli/points/delete", serde_json::json!({"filter": {"must": [
+        {"key": "kind", "match": {"value": "cheese"}},
+        {"key": "aged", "match": {"value": true}},
+    ]}})).assert_ok();
+    assert!(server.get("/collections/deli/points/1").assert_status(404).error().is_some());
+    server.get("/collections/deli/points/2").assert_ok();
+    server.post("/collections/deli/points/delete", serde_json::json!({"filter": {
+        "must": [{"key": "kind", "match": {"value": "salumi"}}],
+        "must_not": [{"key": "aged", "match": {"value": true}}],
+    }})).assert_ok();
+    server.get("/collections/deli/points/3").assert_ok();
+    assert_eq!(server.count("deli"), 2);
+
+    // Filters selecting everything or that cannot be parsed delete nothing
+    for filter in [
+        serde_json::json!({}),
+        serde_json::json!({"must": []}),
+        serde_json::json!({"must": [{"key": "kind", "match": {"all": []}}]}),
+        serde_json::json!({"must": [{"key": "kind", "match": {"value": "cheese"}}, {"key": "aged", "shape": "round"}]}),
+    ] {
+        server.post("/collections/deli/points/delete", serde_json::json!({"filter": filter})).assert_status(400);
+    }
+    assert_eq!(server.count("deli"), 2);
+}
+
+#[test]
+fn test_batch_and_grouped_searches() {
+    use vectx_test::TestServer;
+
+    let server = TestServer::start();
+    server.create_collection("deli", 2, "Cosine");
+    server.upsert_points("deli", serde_json::json!([
+        {"id": 1, "vector": [1.0, 0.0], "payload": {"kind": "salumi"}},
+        {"id": 2, "vector": [0.0, 1.0], "payload": {"kind": "cheese"}},
+        {"id": 3, "vector": [0.9, 0.1], "payload": {"kind": "cheese"}},
+    ]));
+    let cheese = serde_json::json!({"must": [{"key": "kind", "match": {"value": "cheese"}}]});
+
+    // Each search of a batch gets its own results, in order
+    let batch = server.post("/collections/deli/points/search/batch", serde_json::json!({"searches": [
+        {"vector": [1.0, 0.0], "limit": 1},
+        {"vector": [1.0, 0.0], "limit": 1, "filter": cheese},
+    ]}));
+    let batch = batch.assert_ok().result();
+    assert_eq!(batch[0][0]["id"], 1);
+    assert_eq!(batch[1][0]["id"], 3);
+    let batch = server.post("/collections/deli/points/query/batch", serde_json::json!({"searches": [
+        {"query": [0.0, 1.0], "limit": 1},
+    ]}));
+    assert_eq!(batch.assert_ok().result()[0]["points"][0]["id"], 2);
+    server.post("/collections/deli/points/search/batch", serde_json::json!({"searches": [{"limit": 1}]}))
+        .assert_status(400);
+
+    // Groups and discovery apply their filter
+    let groups = server.post("/collections/deli/points/search/groups", serde_json::json!({
+        "vector": [1.0, 0.0], "group_by": "kind", "filter": cheese,
+    }));
+    let groups = groups.assert_ok().result()["groups"].as_array().unwrap().clone();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["id"], "cheese");
+    let found = server.post("/collections/deli/points/discover", serde_json::json!({
+        "target": [1.0, 0.0], "limit": 3, "filter": cheese, "with_vector": true,
+    }));
+    let found = found.assert_ok().result().as_array().unwrap().clone();
+    assert_eq!(found.len(), 2);
+    assert!(found[0]["vector"].is_array());
+
+    // Vectors can be deleted from the points a filter selects
+    server.post("/collections/deli/points/vectors/delete", serde_json::json!({
+        "vectors": ["multivector"], "filter": {"must": [{"key": "kind", "match": {"value": "nothing"}}]},
+    })).assert_ok();
+}
+
+#[test]
+fn test_http_surface() {
+    use vectx_test::TestServer;
+
+    let server = TestServer::start();
+    server.get("/livez").assert_ok();
+    server.get("/collections/deli").assert_status(404);
+
+    server.create_collection("deli", 2, "Cosine");
+    server.upsert_points("deli", serde_json::json!([
+        {"id": 1, "vector": [1.0, 0.0], "payload": {"name": "Salumi", "tags": ["cured", "pork"]}},
+        {"id": 2, "vector": [0.0, 1.0], "payload": {"name": "pecorino", "tags": ["cheese"]}},
+        {"id": 3, "vector": [0.7, 0.7], "payload": {"name": "coppa", "tags": ["cured", "pork", "smoked"]}},
+    ]));
+    assert_eq!(server.count("deli"), 3);
+    let hits = server.search("deli", &[1.0, 0.1], 2);
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].id, serde_json::json!(1));
+    assert_eq!(hits[0].payload.as_ref().unwrap()["name"], "Salumi");
+
+    // Detailed statistics are only computed on request
+    let info = server.get("/collections/deli");
+    let info = info.assert_ok().result();
+    assert_eq!(info["points_count"], 3);
+    assert_eq!(info["vectors_count"], 3);
+    assert!(info.get("stats").is_none());
+    assert!(info.get("hnsw_graph").is_none());
+    let info = server.get("/collections/deli?with_stats=true");
+    let info = info.assert_ok().result();
+    assert_eq!(info["stats"]["points_count"], 3);
+    assert!(info["memory_total_bytes"].as_u64().unwrap() > 0);
+    assert!(info.get("hnsw_graph").is_some());
+
+    // A bad request is refused with a Qdrant-style error
+    let refused = server.put("/collections/deli/points", serde_json::json!({"points": [{"id": 4, "vector": [1.0]}]}));
+    assert_eq!(refused.status, 400);
+    assert!(refused.error().unwrap().contains("dimension"), "{:?}", refused.error());
+
+    // Field indexes are listed in the collection info
+    server.put("/collections/deli/index", serde_json::json!({
+        "field_name": "name",
+        "field_schema": {"type": "keyword", "lowercase": true},
+    })).assert_ok();
+    let info = server.get("/collections/deli");
+    let schema = &info.assert_ok().result()["payload_schema"]["name"];
+    assert_eq!(schema["data_type"], "keyword");
+    assert!(schema.get("points").is_none());
+    assert_eq!(schema["params"]["lowercase"], true);
+    let info = server.get("/collections/deli?with_stats=true");
+    assert_eq!(info.assert_ok().result()["payload_schema"]["name"]["points"], 3);
+
+    let scroll = |filter: serde_json::Value| -> Vec<u64> {
+        let response = server.post("/collections/deli/points/scroll", serde_json::json!({"filter": filter}));
+        let mut ids: Vec<u64> = response.assert_ok().result()["points"].as_array().unwrap()
+            .iter()
+            .map(|p| p["id"].as_u64().unwrap())
+            .collect();
+        ids.sort();
+        ids
+    };
+    assert_eq!(scroll(serde_json::json!({"must": [{"key": "tags", "match": {"all": ["pork", "smoked"]}}]})), vec![3]);
+    assert_eq!(scroll(serde_json::json!({"must": [{"key": "tags", "match": {"except": ["cured", "pork"]}}]})), vec![2, 3]);
+
+    // The gRPC server shares the storage
+    let info = server.block_on(server.collections_client().get(vectx_api::grpc::vectx::GetCollectionInfoRequest {
+        collection_name: "deli".to_string(),
+    })).unwrap().into_inner().result.unwrap();
+    assert_eq!(info.points_count, 3);
+    assert!(info.payload_schema.contains_key("name"));
+    let health = server.block_on(server.health_client().check(vectx_api::grpc::health::HealthCheckRequest {
+        service: String::new(),
+    })).unwrap().into_inner();
+    assert_eq!(health.status, vectx_api::grpc::health::health_check_response::ServingStatus::Serving as i32);
+    assert!(server.storage().get_collection("deli").is_some());
+}
+
+#[test]
+fn test_fuzz_corpus() {
+    // Every saved fuzz input runs through its target without panicking
+    let corpus = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus");
+    assert!(vectx_api::fuzz::replay_corpus(&corpus).unwrap() > 0);
+}
+
+#[test]
+fn test_structured_parse_errors() {
+    use vectx_api::parsing::ParseMode;
+    use vectx_test::TestServer;
+
+    let server = TestServer::start_with_parse_mode(ParseMode::Strict, |_| {});
+    // A lenient server in the same process keeps its own mode
+    let lenient = TestServer::start();
+    server.create_collection("shop", 2, "Dot");
+    server.upsert_points("shop", serde_json::json!([
+        {"id": 1, "vector": [1.0, 0.0], "payload": {"city": "Rome"}},
+        {"id": 2, "vector": [0.0, 1.0], "payload": {"city": "Oslo"}},
+    ]));
+
+    // A filter that cannot be parsed fails the search instead of matching everything
+    let refused = server.post("/collections/shop/points/search", serde_json::json!({
+        "vector": [1.0, 0.0],
+        "filter": {"must": [{"key": "city", "geo_polygon": {"exterior": {"points": []}}}]},
+    }));
+    refused.assert_status(400);
+    assert_eq!(refused.body["status"]["path"], "filter.must[0].geo_polygon");
+    assert!(refused.error().unwrap().contains("unsupported condition"), "{:?}", refused.error());
+    let refused = server.post("/collections/shop/points/query", serde_json::json!({
+        "prefetch": [{"query": [1.0, 0.0], "filter": {"must": [{"key": "city", "match": {"text": 3}}]}}],
+        "query": {"fusion": "rrf"},
+    }));
+    refused.assert_status(400);
+    assert_eq!(refused.body["status"]["path"], "prefetch[0].filter.must[0].match.text");
+    let found = server.post("/collections/shop/points/search", serde_json::json!({
+        "vector": [1.0, 0.0],
+        "filter": {"must": {"key": "city", "match": {"value": "Oslo"}}},
+    }));
+    assert_eq!(found.assert_ok().result().as_array().unwrap().len(), 1);
+
+    // Named vectors that cannot be parsed are refused with their name
+    let refused = server.put("/collections/shop/points", serde_json::json!({
+        "points": [{"id": 3, "vector": {"dense": [1.0, 0.0], "extra": "oops"}}],
+    }));
+    refused.assert_status(400);
+    assert_eq!(refused.body["status"]["path"], "points[0].vector");
+    let message = refused.error().unwrap();
+    assert!(message.contains("vector 'extra': expected a vector, a multivector or a sparse vector"), "{}", message);
+    let refused = server.put("/collections/shop/points", serde_json::json!({
+        "points": [{"id": 3, "vector": [1e39, 0.0]}],
+    }));
+    refused.assert_status(400);
+    assert_eq!(refused.body["status"]["path"], "points[0].vector");
+    assert!(refused.error().unwrap().contains("out of range"), "{}", refused.body);
+    let refused = server.post("/collections/shop/points/search", serde_json::json!({"vector": [1.0, 0.0], "limit": "ten"}));
+    refused.assert_status(400);
+    assert_eq!(refused.body["status"]["path"], "limit");
+    assert_eq!(server.count("shop"), 2);
+
+    // The lenient server skips what the strict one refuses
+    lenient.create_collection("shop", 2, "Dot");
+    lenient.upsert_points("shop", serde_json::json!([{"id": 1, "vector": [1.0, 0.0]}]));
+    lenient.post("/collections/shop/points/search", serde_json::json!({
+        "vector": [1.0, 0.0],
+        "filter": {"must": [{"key": "city", "geo_polygon": {"exterior": {"points": []}}}]},
+    })).assert_ok();
+    lenient.put("/collections/shop/points", serde_json::json!({
+        "points": [{"id": 3, "vector": {"dense": [1.0, 0.0], "extra": "oops"}}],
+    })).assert_ok();
+}
+
+#[test]
+fn test_write_admission() {
+    use vectx_test::TestServer;
+
+    let server = TestServer::start_with(|storage| storage.set_max_pending_points(Some(2)));
+    server.create_collection("feed", 2, "Cosine");
+    server.upsert_points("feed", serde_json::json!([{"id": 1, "vector": [1.0, 0.0]}]));
+
+    // Points of an open batch are pending until it ends
+    let collection = server.storage().get_collection("feed").unwrap();
+    collection.start_batch();
+    collection.upsert(Point::new(PointId::Integer(2), Vector::new(vec![0.0, 1.0]), None)).unwrap();
+    server.upsert_points("feed", serde_json::json!([{"id": 3, "vector": [0.5, 0.5]}]));
+    assert_eq!(collection.pending_points(), 2);
+
+    let refused = server.put("/collections/feed/points", serde_json::json!({"points": [{"id": 4, "vector": [1.0, 1.0]}]}));
+    refused.assert_status(429);
+    assert_eq!(refused.header("retry-after"), Some("1"));
+    let refused = server.post("/collections/feed/points/batch", serde_json::json!({
+        "operations": [{"upsert": {"points": [{"id": 4, "vector": [1.0, 1.0]}]}}],
+    }));
+    refused.assert_status(429);
+    assert!(refused.error().unwrap().contains("2 points pending indexing (limit 2)"), "{:?}", refused.error());
+    let refused = server.block_on(server.points_client().upsert(vectx_api::grpc::vectx::UpsertPoints {
+        collection_name: "feed".to_string(),
+        ..Default::default()
+    })).unwrap_err();
+    assert_eq!(refused.code(), tonic::Code::Unavailable);
+
+    // Reads and deletes go on
+    assert_eq!(server.count("feed"), 3);
+    server.post("/collections/feed/points/delete", serde_json::json!({"points": [3]})).assert_ok();
+
+    let metrics = server.get("/metrics");
+    let metrics = metrics.body.as_str().unwrap();
+    assert!(metrics.contains("rejected_writes_total 3\n"), "{}", metrics);
+    assert!(metrics.contains("pending_points{collection=\"feed\"} 2\n"), "{}", metrics);
+    assert!(metrics.contains("hnsw_rebuilds_running 0\n"), "{}", metrics);
+
+    collection.end_batch().unwrap();
+    server.upsert_points("feed", serde_json::json!([{"id": 4, "vector": [1.0, 1.0]}]));
+    assert_eq!(server.count("feed"), 3);
+}
+
+#[test]
+fn test_search_merges_fresh_points() {
+    use vectx_core::{FilterCondition, HnswParams, PayloadFilter};
+
+    let collection = Collection::new(CollectionConfig {
+        name: "fresh".to_string(),
+        vector_dim: 4,
+        distance: Distance::Cosine,
+        use_hnsw: true,
+        enable_bm25: false,
+    });
+    collection.set_hnsw_params(HnswParams { full_scan_threshold: 0, ..Default::default() }).unwrap();
+    let vector = |i: u64| {
+        let x = i as f32;
+        Vector::new(vec![x.sin(), x.cos(), (2.0 * x).sin(), (3.0 * x).cos()])
+    };
+    collection.batch_upsert((0..500).map(|i| Point::new(PointId::Integer(i), vector(i), Some(serde_json::json!({"n": i})))).collect()).unwrap();
+    collection.prewarm_index().unwrap();
+    assert_eq!(collection.stats().fresh_points, 0);
+
+    // Points of an open batch are found before the batch links them
+    let batched = Vector::new(vec![1.0, 2.0, 3.0, 4.0]);
+    collection.start_batch();
+    collection.upsert(Point::new(PointId::Integer(900), batched.clone(), Some(serde_json::json!({"n": 900})))).unwrap();
+    assert_eq!(collection.stats().fresh_points, 1);
+    let results = collection.search(&batched, 1, None);
+    assert_eq!(results[0].0.id, PointId::Integer(900));
+    let filter = PayloadFilter::new(FilterCondition::Equals { field: "n".to_string(), value: serde_json::json!(900) });
+    let results = collection.search(&vector(1), 3, Some(&filter));
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0.id, PointId::Integer(900));
+    collection.end_batch().unwrap();
+    assert_eq!(collection.stats().fresh_points, 0);
+
+    // A vector updated with relink stays fresh until the rebuild links it,
+    // and searches find the point by its new vector meanwhile
+    let moved = Vector::new(vec![-4.0, 3.0, -2.0, 1.0]);
+    collection.update_vectors(vec![("7".to_string(), moved.clone())], true).unwrap();
+    let rebuilding = collection.hnsw_rebuilding();
+    let results = collection.search(&moved, 1, None);
+    assert_eq!(results[0].0.id, PointId::Integer(7), "rebuilding: {}", rebuilding);
+    assert!(collection.stats().fresh_points <= 1);
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(30);
+    while collection.hnsw_rebuilding() {
+        assert!(std::time::Instant::now() < deadline, "rebuild did not finish");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(collection.stats().fresh_points, 0);
+    assert!(collection.check_index_consistency().is_empty());
+}
+
+#[test]
+fn test_geo_radius_distances() {
+    use vectx_test::TestServer;
+
+    let server = TestServer::start();
+    server.create_collection("places", 2, "Dot");
+    server.upsert_points("places", serde_json::json!([
+        {"id": 1, "vector": [1.0, 0.0], "payload": {"city": "Berlin", "location": {"lat": 52.52, "lon": 13.405}}},
+        {"id": 2, "vector": [0.9, 0.1], "payload": {"city": "Potsdam", "location": {"lat": 52.3906, "lon": 13.0645}}},
+        {"id": 3, "vector": [0.8, 0.2], "payload": {"city": "Paris", "location": [{"lat": 48.8566, "lon": 2.3522}]}},
+        {"id": 4, "vector": [0.7, 0.3], "payload": {"city": "Nowhere"}},
+    ]));
+    let near_berlin = serde_json::json!({
+        "must": [{"key": "location", "geo_radius": {"center": {"lat": 52.52, "lon": 13.405}, "radius": 50_000.0}}]
+    });
+
+    // Hits within the radius carry their distance from its center
+    let found = server.post("/collections/places/points/search", serde_json::json!({
+        "vector": [1.0, 0.0], "filter": near_berlin, "limit": 10,
+    }));
+    let hits = found.assert_ok().result().as_array().unwrap().clone();
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0]["id"], 1);
+    assert_eq!(hits[0]["geo_distance"].as_f64(), Some(0.0));
+    let potsdam = hits[1]["geo_distance"].as_f64().unwrap();
+    assert!((25_000.0..30_000.0).contains(&potsdam), "{}", potsdam);
+
+    // Under a should, the distance is reported as well; arrays of locations
+    // are measured to their nearest one
+    let found = server.post("/collections/places/points/query", serde_json::json!({
+        "query": [1.0, 0.0],
+        "filter": {"should": [{"key": "location", "geo_radius": {"center": {"lat": 48.85, "lon": 2.35}, "radius": 1_000.0}}]},
+    }));
+    let points = found.assert_ok().result()["points"].as_array().unwrap().clone();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0]["id"], 3);
+    assert!(points[0]["geo_distance"].as_f64().unwrap() < 1_000.0);
+
+    // Without a geo filter hits have no distance
+    let found = server.post("/collections/places/points/search", serde_json::json!({"vector": [1.0, 0.0]}));
+    assert!(found.assert_ok().result()[0].get("geo_distance").is_none());
+
+    // Bad centers and radii are refused where they are
+    let refused = server.post("/collections/places/points/search", serde_json::json!({
+        "vector": [1.0, 0.0],
+        "filter": {"must": [{"key": "location", "geo_radius": {"center": {"lat": 95.0, "lon": 0.0}, "radius": 1.0}}]},
+    }));
+    refused.assert_status(400);
+    assert_eq!(refused.body["status"]["path"], "filter.must[0].geo_radius.center");
+    let refused = server.post("/collections/places/points/search", serde_json::json!({
+        "vector": [1.0, 0.0],
+        "filter": {"must": [{"key": "location", "geo_radius": {"center": {"lat": 0.0, "lon": 0.0}, "radius": -1}}]},
+    }));
+    refused.assert_status(400);
+    assert_eq!(refused.body["status"]["path"], "filter.must[0].geo_radius.radius");
+}
+
+#[test]
+fn test_log_level_endpoint() {
+    use vectx_api::logging::{LogOptions, LogRotation};
+    use vectx_api::parsing::{ParseError, ParseMode};
+    use vectx_test::TestServer;
+
+    let server = TestServer::start();
+    // Typos and malformed directives are refused before anything changes
+    for level in ["verbose", "vectx_core=loud", ""] {
+        let refused = server.post("/admin/log_level", serde_json::json!({"level": level}));
+        refused.assert_status(400);
+        assert!(refused.error().unwrap().contains("log level"), "{:?}", refused.error());
+    }
+    // Until logging is set up there is no level to change
+    let refused = server.post("/admin/log_level", serde_json::json!({"level": "debug"}));
+    refused.assert_status(400);
+    assert!(refused.error().unwrap().contains("not set up"), "{:?}", refused.error());
+    let current = server.get("/admin/log_level");
+    assert!(current.assert_ok().result()["level"].is_null());
+
+    // Once it is, the level changes at run time, and the libraries' warnings
+    // follow it. No other test sets up logging.
+    let log_dir = tempfile::tempdir().unwrap();
+    let guard = vectx_api::logging::init(&LogOptions {
+        level: "error".to_string(),
+        file: Some(log_dir.path().join("vectx.log")),
+        rotation: LogRotation::Never,
+        ..Default::default()
+    }).unwrap();
+    let warn = |marker: &str| ParseMode::Lenient.tolerate(ParseError::new("filter", marker)).unwrap();
+    warn("hidden-marker");
+    server.post("/admin/log_level", serde_json::json!({"level": "warn,vectx_core=debug"})).assert_ok();
+    let current = server.get("/admin/log_level");
+    assert_eq!(current.assert_ok().result()["level"], "warn,vectx_core=debug");
+    warn("shown-marker");
+    drop(guard);
+    let log = std::fs::read_to_string(log_dir.path().join("vectx.log")).unwrap();
+    assert!(log.contains("Ignoring filter: shown-marker"), "{}", log);
+    assert!(!log.contains("hidden-marker"), "{}", log);
+}
+
+#[test]
+fn test_audit_log_rotation() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let entry = |n: i64, collection: &str| AuditEntry {
+        timestamp: n,
+        key_id: Some("ingest".to_string()),
+        api: "rest".to_string(),
+        operation: "PUT /collections/docs/points".to_string(),
+        collection: Some(collection.to_string()),
+        points: vec![serde_json::json!(n)],
+        success: n % 2 == 0,
+        status: Some(200),
+    };
+    // Failed entries are the longer ones
+    let line_bytes = serde_json::to_vec(&entry(11, "docs")).unwrap().len() as u64 + 1;
+
+    // Two entries to a file, and two rotated files besides the current one
+    let log = AuditLog::new(temp_dir.path(), 2 * line_bytes, 2).unwrap();
+    for n in 10..17 {
+        log.record(&entry(n, if n % 3 == 0 { "logs" } else { "docs" }));
+    }
+    let mut files: Vec<String> = std::fs::read_dir(temp_dir.path()).unwrap()
+        .map(|file| file.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["audit.log", "audit.log.1", "audit.log.2"]);
+
+    // Entries 10 and 11 were rotated out; the newest come first
+    let timestamps = |entries: Vec<AuditEntry>| entries.iter().map(|e| e.timestamp).collect::<Vec<_>>();
+    assert_eq!(timestamps(log.recent(&AuditQuery::default(), 100).unwrap()), [16, 15, 14, 13, 12]);
+    assert_eq!(timestamps(log.recent(&AuditQuery::default(), 3).unwrap()), [16, 15, 14]);
+    let docs = AuditQuery { collection: Some("docs".to_string()), success: Some(true), ..Default::default() };
+    assert_eq!(timestamps(log.recent(&docs, 100).unwrap()), [16, 14]);
+    let since = AuditQuery { since: Some(15), operation: Some("points".to_string()), ..Default::default() };
+    assert_eq!(timestamps(log.recent(&since, 100).unwrap()), [16, 15]);
+
+    // A reopened log appends to the current file
+    drop(log);
+    let log = AuditLog::new(temp_dir.path(), 2 * line_bytes, 2).unwrap();
+    log.record(&entry(17, "docs"));
+    assert_eq!(timestamps(log.recent(&AuditQuery::default(), 2).unwrap()), [17, 16]);
+
+    // Files are read from the end, across read chunks, past a torn write
+    let temp_dir = tempfile::tempdir().unwrap();
+    let log = AuditLog::new(temp_dir.path(), u64::MAX, 0).unwrap();
+    for n in 0..2000 {
+        log.record(&entry(n, "docs"));
+    }
+    let mut file = std::fs::OpenOptions::new().append(true).open(temp_dir.path().join("audit.log")).unwrap();
+    std::io::Write::write_all(&mut file, b"{\"timestamp\": 20").unwrap();
+    assert_eq!(timestamps(log.recent(&AuditQuery::default(), 2).unwrap()), [1999, 1998]);
+    let all = timestamps(log.recent(&AuditQuery::default(), usize::MAX).unwrap());
+    assert_eq!(all, (0..2000).rev().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_audit_log_endpoint() {
+    use vectx_api::grpc::vectx::{point_id::PointIdOptions, points_selector::PointsSelectorOneOf, DeletePoints, PointsIdsList, PointsSelector};
+    use vectx_test::{Method, TestServer};
+
+    let audit_dir = tempfile::tempdir().unwrap();
+    let (mut ingest_id, mut ingest_key) = (String::new(), String::new());
+    let server = TestServer::start_with(|storage| {
+        storage.set_audit_log(Some(AuditLog::new(audit_dir.path(), 1 << 20, 1).unwrap()));
+        let access = storage.access_control();
+        access.set_master_key(Some("master"));
+        let (key, secret) = access.create_key("ingest", vec!["docs".to_string()], Permission::Write).unwrap();
+        (ingest_id, ingest_key) = (key.id, secret);
+    });
+    let send = |method: Method, path: &str, key: &str, body: serde_json::Value| {
+        server.send(server.request(method, path).header("api-key", key).json(&body))
+    };
+
+    send(Method::PUT, "/collections/docs", "master", serde_json::json!({"vectors": {"size": 2, "distance": "Dot"}})).assert_ok();
+    send(Method::PUT, "/collections/docs/points?wait=true", &ingest_key, serde_json::json!({"points": [
+        {"id": 1, "vector": [1.0, 0.0]},
+        {"id": 2, "vector": [0.0, 1.0]},
+        {"id": 3, "vector": [1.0, 1.0]},
+    ]})).assert_ok();
+    // Reads and refused requests are not recorded
+    send(Method::POST, "/collections/docs/points/search", &ingest_key, serde_json::json!({"vector": [1.0, 0.0], "limit": 1})).assert_ok();
+    send(Method::PUT, "/collections/other", &ingest_key, serde_json::json!({"vectors": {"size": 2, "distance": "Dot"}})).assert_status(403);
+    send(Method::DELETE, "/collections/docs/points/2", &ingest_key, serde_json::json!(null)).assert_ok();
+    send(Method::POST, "/collections/docs/points/batch", &ingest_key, serde_json::json!({"operations": [
+        {"set_payload": {"payload": {"lang": "en"}, "points": [1]}},
+        {"delete": {"points": [3, 4]}},
+    ]})).assert_ok();
+
+    let mut request = tonic::Request::new(DeletePoints {
+        collection_name: "docs".to_string(),
+        points: Some(PointsSelector {
+            points_selector_one_of: Some(PointsSelectorOneOf::Points(PointsIdsList {
+                ids: vec![vectx_api::grpc::vectx::PointId { point_id_options: Some(PointIdOptions::Num(1)) }],
+            })),
+        }),
+        ..Default::default()
+    });
+    request.metadata_mut().insert("api-key", ingest_key.parse().unwrap());
+    server.block_on(server.points_client().delete(request)).unwrap();
+
+    let audit = send(Method::GET, "/admin/audit", "master", serde_json::json!(null));
+    let entries: Vec<AuditEntry> = serde_json::from_value(audit.assert_ok().result()["entries"].clone()).unwrap();
+    let summary: Vec<(&str, &str, Option<&str>)> = entries.iter()
+        .map(|e| (e.api.as_str(), e.operation.as_str(), e.key_id.as_deref()))
+        .collect();
+    let ingest = Some(ingest_id.as_str());
+    assert_eq!(summary, [
+        ("grpc", "Points/Delete", ingest),
+        ("rest", "POST /collections/docs/points/batch", ingest),
+        ("rest", "DELETE /collections/docs/points/2", ingest),
+        ("rest", "PUT /collections/docs/points", ingest),
+        ("rest", "PUT /collections/docs", Some("master")),
+    ]);
+    assert!(entries.iter().all(|e| e.success && e.collection.as_deref() == Some("docs")));
+    assert_eq!(entries[0].points, [serde_json::json!(1)]);
+    assert_eq!(entries[1].points, [serde_json::json!(1), serde_json::json!(3), serde_json::json!(4)]);
+    assert_eq!(entries[2].points, [serde_json::json!(2)]);
+    assert_eq!(entries[3].points, [serde_json::json!(1), serde_json::json!(2), serde_json::json!(3)]);
+    assert_eq!(entries[3].status, Some(200));
+    assert!(entries.windows(2).all(|pair| pair[0].timestamp >= pair[1].timestamp));
+
+    let path = format!("/admin/audit?key_id={}&operation=DELETE&limit=5", ingest_id);
+    let filtered = send(Method::GET, &path, "master", serde_json::json!(null));
+    let filtered = filtered.assert_ok().result()["entries"].as_array().unwrap().clone();
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0]["operation"], "DELETE /collections/docs/points/2");
+    let limited = send(Method::GET, "/admin/audit?limit=2", "master", serde_json::json!(null));
+    assert_eq!(limited.assert_ok().result()["entries"].as_array().unwrap().len(), 2);
+
+    // Only admins read the log
+    send(Method::GET, "/admin/audit", &ingest_key, serde_json::json!(null)).assert_status(403);
+}
+
+#[test]
+fn test_data_dir_migrations() {
+    use vectx_storage::{fsck, migration, CollectionManifest, LmdbStorage, DATA_FORMAT_VERSION};
+
+    // A new directory starts at the current version
+    let temp_dir = tempfile::tempdir().unwrap();
+    drop(StorageManager::new(temp_dir.path()).unwrap());
+    assert_eq!(migration::format_version(temp_dir.path()).unwrap(), Some(DATA_FORMAT_VERSION));
+    assert!(!temp_dir.path().join("migration_backups").exists());
+
+    // A directory of an older version, with a collection in the shared LMDB
+    // environment as versions before per-collection directories kept it
+    let legacy = tempfile::tempdir().unwrap();
+    {
+        let lmdb = LmdbStorage::new(legacy.path().join("lmdb")).unwrap();
+        let config = serde_json::json!({
+            "vector_dim": 3, "distance": "Dot", "use_hnsw": true, "enable_bm25": false,
+            "sparse_modifiers": {"keywords": "idf"},
+        });
+        lmdb.save_collection("old", config.to_string().as_bytes()).unwrap();
+        lmdb.save_checkpoint("orders", 42).unwrap();
+    }
+    let storage = StorageManager::new(legacy.path()).unwrap();
+    assert_eq!(storage.get_collection("old").unwrap().vector_dim(), 3);
+    assert_eq!(storage.ingest_checkpoint("orders").unwrap(), Some(42));
+    drop(storage);
+    assert_eq!(migration::format_version(legacy.path()).unwrap(), Some(DATA_FORMAT_VERSION));
+    let manifest = CollectionManifest::read(&legacy.path().join("collections").join("old")).unwrap();
+    // Legacy modifiers stay modifiers; they do not declare the vector
+    assert_eq!(manifest.config.sparse_modifiers.get("keywords"), Some(&vectx_core::SparseModifier::Idf));
+    assert!(manifest.config.sparse_vectors.is_empty());
+
+    // The LMDB environment was backed up before the collection left it
+    let backups = || -> Vec<std::path::PathBuf> {
+        std::fs::read_dir(legacy.path().join("migration_backups")).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect()
+    };
+    assert_eq!(backups().len(), 1);
+    let backup = &backups()[0];
+    assert!(backup.file_name().unwrap().to_str().unwrap().starts_with("v0-to-v1-"), "{:?}", backup);
+    let lmdb = LmdbStorage::new(backup.join("lmdb")).unwrap();
+    assert_eq!(lmdb.list_collections().unwrap(), ["old"]);
+    drop(lmdb);
+
+    // Nothing runs again on the next start
+    drop(StorageManager::new(legacy.path()).unwrap());
+    assert_eq!(backups().len(), 1);
+
+    // A run that stopped after writing a collection's manifest but before
+    // deleting its LMDB copy drops the copy when it runs again
+    let interrupted = tempfile::tempdir().unwrap();
+    {
+        let config = serde_json::json!({"vector_dim": 2, "distance": "Cosine", "use_hnsw": false, "enable_bm25": false});
+        let lmdb = LmdbStorage::new(interrupted.path().join("lmdb")).unwrap();
+        lmdb.save_collection("moved", config.to_string().as_bytes()).unwrap();
+        let dir = interrupted.path().join("collections").join("moved");
+        vectx_storage::CollectionStore::open(&dir).unwrap().replace_points(&[]).unwrap();
+        CollectionManifest::new("moved", serde_json::from_value(config).unwrap()).write(&dir).unwrap();
+    }
+    let storage = StorageManager::new(interrupted.path()).unwrap();
+    assert_eq!(storage.get_collection("moved").unwrap().vector_dim(), 2);
+    drop(storage);
+    let lmdb = LmdbStorage::new(interrupted.path().join("lmdb")).unwrap();
+    assert!(lmdb.list_collections().unwrap().is_empty());
+    drop(lmdb);
+
+    // A failing migration leaves the version alone and names the backup,
+    // which restores the directory as it was
+    let failing = tempfile::tempdir().unwrap();
+    let good = serde_json::json!({"vector_dim": 2, "distance": "Cosine", "use_hnsw": false, "enable_bm25": false});
+    {
+        let lmdb = LmdbStorage::new(failing.path().join("lmdb")).unwrap();
+        lmdb.save_collection("a_good", good.to_string().as_bytes()).unwrap();
+        lmdb.save_collection("z_bad", b"{not json").unwrap();
+    }
+    let failed = StorageManager::new(failing.path()).err().unwrap().to_string();
+    assert!(failed.contains("z_bad") && failed.contains("backed up in"), "{}", failed);
+    assert_eq!(migration::format_version(failing.path()).unwrap(), None);
+    let backup = std::fs::read_dir(failing.path().join("migration_backups")).unwrap()
+        .next().unwrap().unwrap().path();
+    std::fs::remove_dir_all(failing.path().join("collections")).unwrap();
+    std::fs::remove_dir_all(failing.path().join("lmdb")).unwrap();
+    std::fs::create_dir(failing.path().join("lmdb")).unwrap();
+    for file in std::fs::read_dir(backup.join("lmdb")).unwrap() {
+        let file = file.unwrap();
+        std::fs::copy(file.path(), failing.path().join("lmdb").join(file.file_name())).unwrap();
+    }
+    {
+        let lmdb = LmdbStorage::new(failing.path().join("lmdb")).unwrap();
+        assert_eq!(lmdb.list_collections().unwrap(), ["a_good", "z_bad"]);
+        lmdb.save_collection("z_bad", good.to_string().as_bytes()).unwrap();
+    }
+    let storage = StorageManager::new(failing.path()).unwrap();
+    assert!(storage.get_collection("a_good").is_some() && storage.get_collection("z_bad").is_some());
+    drop(storage);
+    assert_eq!(migration::format_version(failing.path()).unwrap(), Some(DATA_FORMAT_VERSION));
+
+    // A directory written by a newer version is refused
+    std::fs::write(legacy.path().join("format.json"), r#"{"format_version": 99}"#).unwrap();
+    let refused = StorageManager::new(legacy.path()).err().unwrap().to_string();
+    assert!(refused.contains("format version 99") && refused.contains("only reads versions up to"), "{}", refused);
+    assert!(fsck::check(legacy.path()).is_err());
+    assert_eq!(migration::format_version(legacy.path()).unwrap(), Some(99));
+}
+
+#[test]
+fn test_collection_names_stay_in_data_dir() {
+    use vectx_test::TestServer;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let storage = StorageManager::new(&data_dir).unwrap();
+    let config = |name: &str| CollectionConfig {
+        name: name.to_string(),
+        vector_dim: 2,
+        distance: Distance::Euclidean,
+        use_hnsw: false,
+        enable_bm25: false,
+    };
+    for name in ["", ".", "..", "../outside", "a/b", "a\\b", "nul\0", &"x".repeat(256)] {
+        assert!(storage.create_collection(config(name)).is_err(), "{:?} accepted", name);
+    }
+    // Names Qdrant accepts work, and can be deleted again
+    for name in ["docs.v2", "..hidden", "with space", "ünïcode"] {
+        storage.create_collection(config(name)).unwrap();
+        assert!(data_dir.join("collections").join(name).is_dir(), "{:?}", name);
+        assert!(storage.delete_collection(name).unwrap(), "{:?}", name);
+    }
+    assert!(storage.delete_collection("..").is_err());
+    assert!(storage.list_collection_snapshots("..").is_err());
+    assert!(!temp_dir.path().join("outside").exists());
+
+    // A write that reaches an instance after it was unloaded fails instead
+    // of changing a copy nothing persists
+    let kept = storage.create_collection(config("kept-1_A")).unwrap();
+    kept.upsert(Point::new(PointId::Integer(1), Vector::new(vec![1.0, 0.0]), None)).unwrap();
+    assert!(storage.unload_collection("kept-1_A").unwrap());
+    assert!(kept.upsert(Point::new(PointId::Integer(2), Vector::new(vec![0.0, 1.0]), None)).is_err());
+    assert!(kept.set_payload("1", serde_json::json!({"a": 1})).is_err());
+    assert_eq!(storage.get_collection("kept-1_A").unwrap().count(), 1);
+
+    let server = TestServer::start();
+    server.create_collection("victim", 2, "Cosine");
+    for path in ["/collections/%2E%2E", "/collections/..%2Fvictim", "/collections/a%2Fb"] {
+        let created = server.put(path, serde_json::json!({"vectors": {"size": 2, "distance": "Cosine"}}));
+        assert!(created.status >= 400, "{} accepted", path);
+        server.delete(path);
+    }
+    server.get("/collections/victim").assert_ok();
+}
+
+#[test]
+fn test_grpc_delete_filter_never_widens() {
+    use vectx_api::grpc::vectx::{
+        condition::ConditionOneOf, points_selector::PointsSelectorOneOf, r#match::MatchValue, Condition, DeletePoints,
+        FieldCondition, Filter, Match, PointsSelector, RepeatedStrings,
+    };
+    use vectx_test::TestServer;
+
+    let server = TestServer::start();
+    server.create_collection("docs", 2, "Cosine");
+    server.upsert_points("docs", serde_json::json!([
+        {"id": 1, "vector": [1.0, 0.0], "payload": {"tag": "a"}},
+        {"id": 2, "vector": [0.0, 1.0], "payload": {"tag": "b"}},
+    ]));
+    let field = |key: &str, r#match: Option<Match>| Condition {
+        condition_one_of: Some(ConditionOneOf::Field(FieldCondition { key: key.to_string(), r#match, range: None })),
+    };
+    let delete = |filter: Filter| {
+        server.block_on(server.points_client().delete(DeletePoints {
+            collection_name: "docs".to_string(),
+            points: Some(PointsSelector { points_selector_one_of: Some(PointsSelectorOneOf::Filter(filter)) }),
+            ..Default::default()
+        })).map(|_| ()).map_err(|status| status.code())
+    };
+
+    let refused = [
+        // No condition at all
+        Filter::default(),
+        // A field condition with neither a match nor a range
+        Filter { must: vec![field("tag", None)], ..Default::default() },
+        // A match without a value, and an empty `any` list
+        Filter { must: vec![field("tag", Some(Match { match_value: None }))], ..Default::default() },
+        Filter {
+            must: vec![field("tag", Some(Match { match_value: Some(MatchValue::Keywords(RepeatedStrings::default())) }))],
+            ..Default::default()
+        },
+        // Dropping a condition under must_not would select more points
+        Filter { must_not: vec![Condition { condition_one_of: None }], ..Default::default() },
+        Filter {
+            must_not: vec![Condition { condition_one_of: Some(ConditionOneOf::Filter(Filter::default())) }],
+            ..Default::default()
+        },
+    ];
+    for filter in refused {
+        assert_eq!(delete(filter.clone()), Err(tonic::Code::InvalidArgument), "{:?}", filter);
+    }
+    assert_eq!(server.count("docs"), 2);
+
+    let tag_a = Some(Match { match_value: Some(MatchValue::Keyword("a".to_string())) });
+    delete(Filter { must: vec![field("tag", tag_a)], ..Default::default() }).unwrap();
+    assert_eq!(server.count("docs"), 1);
+}
+
+#[test]
+fn test_empty_match_all_is_refused_in_selectors() {
+    use vectx_api::core_requests;
+    use vectx_api::parsing::ParseMode;
+    use vectx_test::TestServer;
+
+    let empty_all = serde_json::json!({"must_not": [{"key": "tags", "match": {"all": []}}]});
+    let error = core_requests::try_parse_filter(&empty_all, ParseMode::Strict).unwrap_err();
+    assert_eq!(error.path, "filter.must_not[0].match.all");
+    assert!(core_requests::check_write_selector(&empty_all).is_err());
+    assert!(core_requests::check_write_selector(&serde_json::json!({"must": [{"key": "tags", "match": {"all": ["a"]}}]})).is_ok());
+
+    let server = TestServer::start();
+    server.create_collection("docs", 2, "Cosine");
+    server.upsert_points("docs", serde_json::json!([
+        {"id": 1, "vector": [1.0, 0.0], "payload": {"tags": ["a"]}},
+        {"id": 2, "vector": [0.0, 1.0], "payload": {"tags": ["b"]}},
+    ]));
+    for (path, body) in [
+        ("/collections/docs/points/payload", serde_json::json!({"payload": {"x": 1}, "filter": empty_all})),
+        ("/collections/docs/points/payload/clear", serde_json::json!({"filter": empty_all})),
+        ("/collections/docs/points/payload/delete", serde_json::json!({"keys": ["tags"], "filter": empty_all})),
+    ] {
+        let refused = server.post(path, body);
+        assert_eq!(refused.status, 400, "{}", path);
+        assert!(refused.error().unwrap().contains("match.all"), "{:?}", refused.error());
+    }
+    let points = server.post("/collections/docs/points", serde_json::json!({"ids": [1, 2], "with_payload": true}));
+    for point in points.assert_ok().result().as_array().unwrap() {
+        assert!(point["payload"]["tags"].is_array() && point["payload"].get("x").is_none(), "{}", point);
+    }
+}
+
+#[test]
+fn test_interrupted_compaction_and_dir_lock() {
+    use vectx_storage::fsck::{check, compact};
+    use vectx_storage::{CompactOptions, IssueKind};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    let collection = storage.create_collection(CollectionConfig {
+        name: "docs".to_string(),
+        vector_dim: 2,
+        distance: Distance::Euclidean,
+        use_hnsw: false,
+        enable_bm25: false,
+    }).unwrap();
+    for i in 0..20 {
+        collection.upsert(Point::new(PointId::Integer(i), Vector::new(vec![i as f32, 1.0]), None)).unwrap();
+    }
+    drop(collection);
+
+    // Only one user of the directory at a time
+    let in_use = check(temp_dir.path()).unwrap_err().to_string();
+    assert!(in_use.contains("in use by a vectX server"), "{}", in_use);
+    assert!(StorageManager::new(temp_dir.path()).is_err());
+    drop(storage);
+
+    // A crash between the renames of a compaction: the points are only in
+    // points.old, which is reported but not removed as a leftover
+    let dir = temp_dir.path().join("collections").join("docs");
+    std::fs::rename(dir.join("points"), dir.join("points.old")).unwrap();
+    std::fs::create_dir(dir.join("points.compact")).unwrap();
+    let report = check(temp_dir.path()).unwrap();
+    let kinds: Vec<(IssueKind, &str)> = report.issues.iter()
+        .map(|i| (i.kind, i.path.file_name().unwrap().to_str().unwrap()))
+        .collect();
+    assert_eq!(kinds, [(IssueKind::Leftover, "points.compact"), (IssueKind::CorruptStore, "docs")]);
+    assert!(dir.join("points.old").is_dir());
+
+    // The server moves the environment back when it opens the collection
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    assert_eq!(storage.get_collection("docs").unwrap().count(), 20);
+    assert!(dir.join("points").is_dir() && !dir.join("points.old").exists() && !dir.join("points.compact").exists());
+    drop(storage);
+
+    // ... and so does compact, before anything is removed
+    std::fs::rename(dir.join("points"), dir.join("points.old")).unwrap();
+    let report = compact(temp_dir.path(), CompactOptions { drop_corrupt: true, remove_orphans: true }).unwrap();
+    assert!(report.check.is_clean(), "{:?}", report.check.issues);
+    assert_eq!(report.collections, 1);
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    assert_eq!(storage.get_collection("docs").unwrap().count(), 20);
+    drop(storage);
+
+    // A collection whose points are gone fails to load instead of loading empty
+    std::fs::remove_dir_all(dir.join("points")).unwrap();
+    let storage = StorageManager::new(temp_dir.path()).unwrap();
+    assert!(storage.get_collection("docs").is_none());
+    assert!(!dir.join("points").exists());
+}
+
+#[test]
+fn test_openapi_covers_every_route() {
+    use vectx_test::TestServer;
+
+    // Routes are read from the table `RestApi` registers them with
+    let source = include_str!("../lib/api/src/rest.rs");
+    let routes: Vec<(String, String)> = source.lines()
+        .filter_map(|line| line.trim().strip_prefix(".route(\""))
+        .map(|route| {
+            let (path, rest) = route.split_once('"').unwrap();
+            let method = rest.split_once("web::").unwrap().1.split_once('(').unwrap().0;
+            (method.to_string(), path.to_string())
+        })
+        .collect();
+    assert!(routes.len() > 100, "{} routes found", routes.len());
+
+    let server = TestServer::start();
+    let spec = server.get("/openapi.json");
+    let paths = spec.assert_ok().body["paths"].as_object().unwrap();
+    let missing: Vec<String> = routes.iter()
+        .filter(|(_, path)| path != "/openapi.json")
+        .filter(|(method, path)| paths.get(path).and_then(|ops| ops.get(method)).is_none())
+        .map(|(method, path)| format!("{} {}", method, path))
+        .collect();
+    assert!(missing.is_empty(), "routes missing from /openapi.json: {:?}", missing);
+}